@@ -1,3 +1,4 @@
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::env;
@@ -5,16 +6,18 @@ use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStdin, Command};
-use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex, OwnedSemaphorePermit};
 use tokio::time::timeout;
 
 use crate::backend::events::{AppServerEvent, EventSink};
 use crate::shared::process_core::{kill_child_process_tree, tokio_command};
-use crate::codex::args::parse_codex_args;
+use crate::shared::process_limits_core::{acquire_process_slot, ProcessKind};
+use crate::shared::session_log_core::SessionLog;
+use crate::codex::args::{parse_codex_args, CodexArgs, CodexCommand};
 use crate::types::WorkspaceEntry;
 
 #[cfg(target_os = "windows")]
@@ -52,17 +55,45 @@ fn build_initialize_params(client_version: &str) -> Value {
     })
 }
 
-pub(crate) struct WorkspaceSession {
-    pub(crate) entry: WorkspaceEntry,
-    pub(crate) child: Mutex<Child>,
-    pub(crate) stdin: Mutex<ChildStdin>,
-    pub(crate) pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
-    pub(crate) next_id: AtomicU64,
+pub struct WorkspaceSession {
+    /// The workspace this session serves; updated through `replace_entry`.
+    entry: std::sync::Mutex<WorkspaceEntry>,
+    pub child: Mutex<Child>,
+    pub stdin: Mutex<ChildStdin>,
+    pub pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    pub next_id: AtomicU64,
     /// Callbacks for background threads - events for these threadIds are sent through the channel
-    pub(crate) background_thread_callbacks: Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>,
+    pub background_thread_callbacks: Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>,
+    /// Counts the app-server against the session limit until the session is dropped.
+    _process_slot: OwnedSemaphorePermit,
 }
 
 impl WorkspaceSession {
+    pub fn entry(&self) -> WorkspaceEntry {
+        self.entry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn workspace_path(&self) -> String {
+        self.entry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .path
+            .clone()
+    }
+
+    /// Swaps in an updated workspace entry, e.g. after a rename or a settings change.
+    /// Later threads and turns use its path as their `cwd` and its turn defaults; the
+    /// app-server process itself keeps running.
+    pub fn replace_entry(&self, entry: WorkspaceEntry) {
+        *self
+            .entry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = entry;
+    }
+
     async fn write_message(&self, value: Value) -> Result<(), String> {
         let mut stdin = self.stdin.lock().await;
         let mut line = serde_json::to_string(&value).map_err(|e| e.to_string())?;
@@ -73,7 +104,7 @@ impl WorkspaceSession {
             .map_err(|e| e.to_string())
     }
 
-    pub(crate) async fn send_request(&self, method: &str, params: Value) -> Result<Value, String> {
+    pub async fn send_request(&self, method: &str, params: Value) -> Result<Value, String> {
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let (tx, rx) = oneshot::channel();
         self.pending.lock().await.insert(id, tx);
@@ -82,7 +113,7 @@ impl WorkspaceSession {
         rx.await.map_err(|_| "request canceled".to_string())
     }
 
-    pub(crate) async fn send_notification(
+    pub async fn send_notification(
         &self,
         method: &str,
         params: Option<Value>,
@@ -95,13 +126,38 @@ impl WorkspaceSession {
         self.write_message(value).await
     }
 
-    pub(crate) async fn send_response(&self, id: Value, result: Value) -> Result<(), String> {
+    pub async fn send_response(&self, id: Value, result: Value) -> Result<(), String> {
         self.write_message(json!({ "id": id, "result": result }))
             .await
     }
 }
 
-pub(crate) fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
+/// `bin` directories of nvm-installed Node versions, newest first, so `node` resolves to
+/// the latest one rather than whichever `read_dir` happens to list first.
+fn nvm_node_bins(nvm_root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(nvm_root) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<(Vec<u64>, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("bin").is_dir())
+        .map(|path| {
+            let version = path
+                .file_name()
+                .map(|name| name.to_string_lossy().trim_start_matches('v').to_string())
+                .unwrap_or_default()
+                .split('.')
+                .map(|part| part.parse::<u64>().unwrap_or(0))
+                .collect();
+            (version, path.join("bin"))
+        })
+        .collect();
+    versions.sort_by(|a, b| b.cmp(a));
+    versions.into_iter().map(|(_, bin)| bin).collect()
+}
+
+pub fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
     let mut paths: Vec<PathBuf> = env::var_os("PATH")
         .map(|value| env::split_paths(&value).collect())
         .unwrap_or_default();
@@ -125,15 +181,7 @@ pub(crate) fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
             extras.push(home_path.join(".local/share/mise/shims"));
             extras.push(home_path.join(".cargo/bin"));
             extras.push(home_path.join(".bun/bin"));
-            let nvm_root = home_path.join(".nvm/versions/node");
-            if let Ok(entries) = std::fs::read_dir(nvm_root) {
-                for entry in entries.flatten() {
-                    let bin_path = entry.path().join("bin");
-                    if bin_path.is_dir() {
-                        extras.push(bin_path);
-                    }
-                }
-            }
+            extras.extend(nvm_node_bins(&home_path.join(".nvm/versions/node")));
         }
     }
 
@@ -180,7 +228,7 @@ pub(crate) fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
         .map(|joined| joined.to_string_lossy().to_string())
 }
 
-pub(crate) fn build_codex_command_with_bin(
+pub fn build_codex_command_with_bin(
     codex_bin: Option<String>,
     codex_args: Option<&str>,
     args: Vec<String>,
@@ -234,7 +282,7 @@ pub(crate) fn build_codex_command_with_bin(
     Ok(command)
 }
 
-pub(crate) async fn check_codex_installation(
+pub async fn check_codex_installation(
     codex_bin: Option<String>,
 ) -> Result<Option<String>, String> {
     let mut command =
@@ -242,6 +290,7 @@ pub(crate) async fn check_codex_installation(
     command.stdout(std::process::Stdio::piped());
     command.stderr(std::process::Stdio::piped());
 
+    let _slot = acquire_process_slot(ProcessKind::Tool).await?;
     let output = match timeout(Duration::from_secs(5), command.output()).await {
         Ok(result) => result.map_err(|e| {
             if e.kind() == ErrorKind::NotFound {
@@ -282,46 +331,157 @@ pub(crate) async fn check_codex_installation(
     Ok(if version.is_empty() { None } else { Some(version) })
 }
 
-pub(crate) async fn spawn_workspace_session<E: EventSink>(
+/// The steps of `spawn_workspace_session`, each announced as a `codex/spawnProgress`
+/// event when it starts. A failure is reported against the step it happened in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpawnStage {
+    ResolvingBinary,
+    StartingProcess,
+    Initializing,
+    Ready,
+}
+
+struct SpawnProgress<'a, E: EventSink> {
+    workspace_id: &'a str,
+    event_sink: &'a E,
+    started: Instant,
+    stage: SpawnStage,
+}
+
+impl<'a, E: EventSink> SpawnProgress<'a, E> {
+    fn new(workspace_id: &'a str, event_sink: &'a E) -> Self {
+        Self {
+            workspace_id,
+            event_sink,
+            started: Instant::now(),
+            stage: SpawnStage::ResolvingBinary,
+        }
+    }
+
+    fn enter(&mut self, stage: SpawnStage) {
+        self.stage = stage;
+        self.emit(None);
+    }
+
+    /// Reports `error` against the current stage and hands it back for `?`.
+    fn fail(&self, error: String) -> String {
+        self.emit(Some(&error));
+        error
+    }
+
+    fn emit(&self, error: Option<&str>) {
+        self.event_sink.emit_app_server_event(AppServerEvent {
+            workspace_id: self.workspace_id.to_string(),
+            message: spawn_progress_message(
+                self.workspace_id,
+                self.stage,
+                self.started.elapsed(),
+                error,
+            ),
+        });
+    }
+}
+
+fn spawn_progress_message(
+    workspace_id: &str,
+    stage: SpawnStage,
+    elapsed: Duration,
+    error: Option<&str>,
+) -> Value {
+    json!({
+        "method": "codex/spawnProgress",
+        "params": {
+            "workspaceId": workspace_id,
+            "stage": stage,
+            "elapsedMs": elapsed.as_millis() as u64,
+            "error": error,
+        },
+    })
+}
+
+/// Waits briefly for the child to be reaped and describes how it ended.
+async fn describe_exit(session: &WorkspaceSession) -> String {
+    for _ in 0..20 {
+        if let Ok(Some(status)) = session.child.lock().await.try_wait() {
+            return format!("exited with {status}");
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    "process still running".to_string()
+}
+
+pub async fn spawn_workspace_session<E: EventSink>(
     entry: WorkspaceEntry,
     default_codex_bin: Option<String>,
-    codex_args: Option<String>,
+    codex_args: Option<CodexArgs>,
     codex_home: Option<PathBuf>,
+    session_log_dir: Option<PathBuf>,
     client_version: String,
     event_sink: E,
 ) -> Result<Arc<WorkspaceSession>, String> {
+    let mut progress = SpawnProgress::new(&entry.id, &event_sink);
+    progress.enter(SpawnStage::ResolvingBinary);
     let codex_bin = entry
         .codex_bin
         .clone()
         .filter(|value| !value.trim().is_empty())
         .or(default_codex_bin);
-    let _ = check_codex_installation(codex_bin.clone()).await?;
-
-    let mut command = build_codex_command_with_bin(
-        codex_bin,
-        codex_args.as_deref(),
-        vec!["app-server".to_string()],
-    )?;
+    let _ = check_codex_installation(codex_bin.clone())
+        .await
+        .map_err(|err| progress.fail(err))?;
+
+    let launch = CodexCommand::for_session(
+        &entry,
+        codex_bin.as_deref(),
+        codex_args.as_ref(),
+        codex_home.as_deref(),
+    )
+    .map_err(|err| progress.fail(err))?;
+    let mut command = build_codex_command_with_bin(codex_bin, None, launch.args)
+        .map_err(|err| progress.fail(err))?;
     command.current_dir(&entry.path);
-    if let Some(codex_home) = codex_home {
-        command.env("CODEX_HOME", codex_home);
-    }
+    command.envs(&launch.env);
     command.stdin(std::process::Stdio::piped());
     command.stdout(std::process::Stdio::piped());
     command.stderr(std::process::Stdio::piped());
 
-    let mut child = command.spawn().map_err(|e| e.to_string())?;
+    let session_log = session_log_dir.map(|dir| Arc::new(SessionLog::new(&dir, &entry.id)));
+    progress.enter(SpawnStage::StartingProcess);
+    let process_slot = acquire_process_slot(ProcessKind::Session)
+        .await
+        .map_err(|err| progress.fail(err))?;
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            if let Some(log) = &session_log {
+                log.append(&format!("failed to spawn codex app-server: {err}"));
+            }
+            return Err(progress.fail(err.to_string()));
+        }
+    };
+    if let Some(log) = &session_log {
+        log.append(&format!(
+            "started codex app-server (pid {}) in {}",
+            child
+                .id()
+                .map(|pid| pid.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            entry.path
+        ));
+    }
     let stdin = child.stdin.take().ok_or("missing stdin")?;
     let stdout = child.stdout.take().ok_or("missing stdout")?;
     let stderr = child.stderr.take().ok_or("missing stderr")?;
 
     let session = Arc::new(WorkspaceSession {
-        entry: entry.clone(),
+        entry: std::sync::Mutex::new(entry.clone()),
         child: Mutex::new(child),
         stdin: Mutex::new(stdin),
         pending: Mutex::new(HashMap::new()),
         next_id: AtomicU64::new(1),
         background_thread_callbacks: Mutex::new(HashMap::new()),
+        _process_slot: process_slot,
     });
 
     let session_clone = Arc::clone(&session);
@@ -405,12 +565,17 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
 
     let workspace_id = entry.id.clone();
     let event_sink_clone = event_sink.clone();
+    let stderr_log = session_log.clone();
+    let stderr_session = Arc::clone(&session);
     tokio::spawn(async move {
         let mut lines = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = lines.next_line().await {
             if line.trim().is_empty() {
                 continue;
             }
+            if let Some(log) = &stderr_log {
+                log.append(&line);
+            }
             let payload = AppServerEvent {
                 workspace_id: workspace_id.clone(),
                 message: json!({
@@ -420,8 +585,16 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
             };
             event_sink_clone.emit_app_server_event(payload);
         }
+        // stderr closes when the process dies, so this is where exits get recorded.
+        if let Some(log) = &stderr_log {
+            log.append(&format!(
+                "codex app-server stderr closed; {}",
+                describe_exit(&stderr_session).await
+            ));
+        }
     });
 
+    progress.enter(SpawnStage::Initializing);
     let init_params = build_initialize_params(&client_version);
     let init_result = timeout(
         Duration::from_secs(15),
@@ -433,14 +606,24 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
         Err(_) => {
             let mut child = session.child.lock().await;
             kill_child_process_tree(&mut child).await;
-            return Err(
+            if let Some(log) = &session_log {
+                log.append("codex app-server did not respond to initialize; killed");
+            }
+            return Err(progress.fail(
                 "Codex app-server did not respond to initialize. Check that `codex app-server` works in Terminal."
                     .to_string(),
-            );
+            ));
         }
     };
-    init_response?;
-    session.send_notification("initialized", None).await?;
+    if let (Err(err), Some(log)) = (&init_response, &session_log) {
+        log.append(&format!("initialize failed: {err}"));
+    }
+    init_response.map_err(|err| progress.fail(err))?;
+    session
+        .send_notification("initialized", None)
+        .await
+        .map_err(|err| progress.fail(err))?;
+    progress.enter(SpawnStage::Ready);
 
     let payload = AppServerEvent {
         workspace_id: entry.id.clone(),
@@ -456,8 +639,12 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
 
 #[cfg(test)]
 mod tests {
-    use super::{build_initialize_params, extract_thread_id};
+    use super::{
+        build_initialize_params, extract_thread_id, nvm_node_bins, spawn_progress_message,
+        SpawnStage,
+    };
     use serde_json::json;
+    use std::time::Duration;
 
     #[test]
     fn extract_thread_id_reads_camel_case() {
@@ -477,6 +664,31 @@ mod tests {
         assert_eq!(extract_thread_id(&value), None);
     }
 
+    #[test]
+    fn spawn_progress_names_the_stage_and_any_failure() {
+        let message = spawn_progress_message(
+            "ws-1",
+            SpawnStage::StartingProcess,
+            Duration::from_millis(42),
+            Some("No such file or directory"),
+        );
+        assert_eq!(
+            message,
+            json!({
+                "method": "codex/spawnProgress",
+                "params": {
+                    "workspaceId": "ws-1",
+                    "stage": "startingProcess",
+                    "elapsedMs": 42,
+                    "error": "No such file or directory",
+                },
+            })
+        );
+        let ready = spawn_progress_message("ws-1", SpawnStage::Ready, Duration::ZERO, None);
+        assert_eq!(ready["params"]["stage"], "ready");
+        assert!(ready["params"]["error"].is_null());
+    }
+
     #[test]
     fn build_initialize_params_enables_experimental_api() {
         let params = build_initialize_params("1.2.3");
@@ -488,4 +700,21 @@ mod tests {
             Some(true)
         );
     }
+
+    #[test]
+    fn nvm_node_bins_prefers_the_newest_version() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-nvm-{}", uuid::Uuid::new_v4()));
+        for version in ["v18.19.0", "v20.11.1", "v9.11.2", "v20.9.0"] {
+            std::fs::create_dir_all(root.join(version).join("bin")).expect("create bin");
+        }
+        std::fs::create_dir_all(root.join("v22.0.0")).expect("create dir without bin");
+        let bins = nvm_node_bins(&root);
+        let versions: Vec<_> = bins
+            .iter()
+            .map(|bin| bin.parent().and_then(|dir| dir.file_name()).unwrap_or_default())
+            .collect();
+        assert_eq!(versions, ["v20.11.1", "v20.9.0", "v18.19.0", "v9.11.2"]);
+        assert!(nvm_node_bins(&root.join("missing")).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,156 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Latest app-server event schema. Bump it whenever `normalize_message` changes what
+/// clients see, and keep a translation for every older version in `for_schema`.
+pub const EVENT_SCHEMA_VERSION: u32 = 2;
+/// Events exactly as the codex app-server sent them; what clients that don't ask for a
+/// schema get.
+pub const LEGACY_EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Field spellings that differ between codex versions, mapped to the schema's name.
+const FIELD_ALIASES: &[(&str, &str)] = &[
+    ("thread_id", "threadId"),
+    ("turn_id", "turnId"),
+    ("item_id", "itemId"),
+    ("call_id", "callId"),
+    ("token_usage", "tokenUsage"),
+    ("rate_limits", "rateLimits"),
+    ("auth_mode", "authMode"),
+];
+/// Nested objects whose fields follow the same aliases as `params`.
+const NESTED_OBJECTS: &[&str] = &["thread", "turn", "item"];
+
+#[derive(Serialize, Clone)]
+pub struct AppServerEvent {
+    pub workspace_id: String,
+    pub message: Value,
+}
+
+impl AppServerEvent {
+    /// The `app-server-event` params for a client on `schema`; versions newer than
+    /// this build get the latest schema.
+    pub fn for_schema(&self, schema: u32) -> Value {
+        if schema <= LEGACY_EVENT_SCHEMA_VERSION {
+            return json!({ "workspace_id": self.workspace_id, "message": self.message });
+        }
+        json!({
+            "workspace_id": self.workspace_id,
+            "schemaVersion": EVENT_SCHEMA_VERSION,
+            "message": normalize_message(&self.message),
+        })
+    }
+}
+
+/// The schema a client gets for the version it asked for. Clients that don't ask get
+/// the legacy schema, so older builds keep working unchanged.
+pub fn negotiate_event_schema(requested: Option<u64>) -> u32 {
+    match requested {
+        Some(requested) => requested.clamp(
+            u64::from(LEGACY_EVENT_SCHEMA_VERSION),
+            u64::from(EVENT_SCHEMA_VERSION),
+        ) as u32,
+        None => LEGACY_EVENT_SCHEMA_VERSION,
+    }
+}
+
+/// Schema 2: `params` and its `thread`/`turn`/`item` objects use camelCase ids and
+/// field names whatever codex version sent them. Other fields pass through untouched.
+pub fn normalize_message(message: &Value) -> Value {
+    let mut message = message.clone();
+    if let Some(params) = message.get_mut("params").and_then(Value::as_object_mut) {
+        normalize_fields(params);
+        for key in NESTED_OBJECTS {
+            if let Some(nested) = params.get_mut(*key).and_then(Value::as_object_mut) {
+                normalize_fields(nested);
+            }
+        }
+    }
+    message
+}
+
+fn normalize_fields(object: &mut Map<String, Value>) {
+    for (alias, name) in FIELD_ALIASES {
+        if let Some(value) = object.remove(*alias) {
+            object.entry(name.to_string()).or_insert(value);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TerminalOutput {
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: String,
+    #[serde(rename = "terminalId")]
+    pub terminal_id: String,
+    pub data: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TerminalExit {
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: String,
+    #[serde(rename = "terminalId")]
+    pub terminal_id: String,
+}
+
+pub trait EventSink: Clone + Send + Sync + 'static {
+    fn emit_app_server_event(&self, event: AppServerEvent);
+    fn emit_terminal_output(&self, event: TerminalOutput);
+    fn emit_terminal_exit(&self, event: TerminalExit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(message: Value) -> AppServerEvent {
+        AppServerEvent {
+            workspace_id: "ws-1".to_string(),
+            message,
+        }
+    }
+
+    #[test]
+    fn normalizes_field_spellings_for_the_latest_schema() {
+        let event = event(json!({
+            "method": "turn/started",
+            "params": {
+                "thread_id": "thread-1",
+                "turn": { "id": "turn-1", "thread_id": "thread-1" },
+                "changes": { "src/token_usage.rs": { "item_id": "kept" } }
+            }
+        }));
+        let latest = event.for_schema(EVENT_SCHEMA_VERSION);
+        assert_eq!(latest["schemaVersion"], json!(2));
+        assert_eq!(
+            latest["message"]["params"],
+            json!({
+                "threadId": "thread-1",
+                "turn": { "id": "turn-1", "threadId": "thread-1" },
+                "changes": { "src/token_usage.rs": { "item_id": "kept" } }
+            })
+        );
+        assert_eq!(event.for_schema(99), latest);
+
+        let legacy = event.for_schema(LEGACY_EVENT_SCHEMA_VERSION);
+        assert_eq!(
+            legacy,
+            json!({ "workspace_id": "ws-1", "message": event.message })
+        );
+    }
+
+    #[test]
+    fn negotiates_within_the_supported_range() {
+        assert_eq!(negotiate_event_schema(None), LEGACY_EVENT_SCHEMA_VERSION);
+        assert_eq!(negotiate_event_schema(Some(0)), LEGACY_EVENT_SCHEMA_VERSION);
+        assert_eq!(negotiate_event_schema(Some(2)), 2);
+        assert_eq!(negotiate_event_schema(Some(7)), EVENT_SCHEMA_VERSION);
+
+        let both = normalize_message(&json!({
+            "method": "item/agentMessage/delta",
+            "params": { "threadId": "new", "thread_id": "old", "delta": "hi" }
+        }));
+        assert_eq!(both["params"], json!({ "threadId": "new", "delta": "hi" }));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod app_server;
+pub mod events;
@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::shared::thread_branches_core::workspace_branch;
+use crate::types::{AppSettings, CodexLaunch, WorkspaceEntry};
+
+/// The Codex args a workspace ends up with: its own, its parent's for a worktree, or the
+/// app's, whichever level sets any first. Within a level `codexLaunch` wins over `codexArgs`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodexArgs {
+    /// The shell-style `codexArgs` string.
+    Shell(String),
+    Structured(CodexLaunch),
+}
+
+impl CodexArgs {
+    /// Args and extra environment with `{workspacePath}` and `{branch}` filled in.
+    pub fn expand(
+        &self,
+        entry: &WorkspaceEntry,
+    ) -> Result<(Vec<String>, BTreeMap<String, String>), String> {
+        let context = PlaceholderContext {
+            workspace_path: &entry.path,
+            branch: workspace_branch(entry),
+        };
+        let fill = |value: &str| fill_placeholders(value, Some(&context));
+        match self {
+            Self::Shell(raw) => {
+                let args = parse_codex_args(Some(raw))?
+                    .iter()
+                    .map(|arg| fill(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((args, BTreeMap::new()))
+            }
+            Self::Structured(launch) => {
+                let args = launch
+                    .args
+                    .iter()
+                    .map(|arg| fill(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let env = launch
+                    .env
+                    .iter()
+                    .map(|(name, value)| Ok((name.clone(), fill(value)?)))
+                    .collect::<Result<BTreeMap<_, _>, String>>()?;
+                Ok((args, env))
+            }
+        }
+    }
+}
+
+struct PlaceholderContext<'a> {
+    workspace_path: &'a str,
+    branch: Option<String>,
+}
+
+/// Replaces `{workspacePath}` and `{branch}`. Braces around anything but a bare word are
+/// left alone, so TOML passed with `-c` survives; an unknown bare word is an error. With
+/// no context the placeholders are only checked.
+fn fill_placeholders(value: &str, context: Option<&PlaceholderContext>) -> Result<String, String> {
+    let mut filled = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after
+            .find(|ch: char| !ch.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        if name_len == 0 || !after[name_len..].starts_with('}') {
+            filled.push('{');
+            rest = after;
+            continue;
+        }
+        let name = &after[..name_len];
+        match (name, context) {
+            ("workspacePath" | "branch", None) => {
+                filled.push_str(&rest[start..start + name_len + 2])
+            }
+            ("workspacePath", Some(context)) => filled.push_str(context.workspace_path),
+            ("branch", Some(context)) => filled.push_str(context.branch.as_deref().ok_or(
+                "Invalid Codex args: `{branch}` needs a workspace checked out on a branch",
+            )?),
+            _ => {
+                return Err(format!(
+                    "Invalid Codex args: unknown placeholder `{{{name}}}`; \
+                     use `{{workspacePath}}` or `{{branch}}`"
+                ))
+            }
+        }
+        rest = &after[name_len + 1..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// Checks one settings level's `codexArgs` and `codexLaunch` before they are saved.
+pub fn validate_codex_args(
+    codex_args: Option<&str>,
+    codex_launch: Option<&CodexLaunch>,
+) -> Result<(), String> {
+    for arg in parse_codex_args(codex_args)? {
+        fill_placeholders(&arg, None)?;
+    }
+    let Some(launch) = codex_launch else {
+        return Ok(());
+    };
+    for arg in &launch.args {
+        if arg.contains('\0') {
+            return Err("Invalid Codex args: an argument contains a NUL byte".to_string());
+        }
+        fill_placeholders(arg, None)?;
+    }
+    for (name, value) in &launch.env {
+        if name.is_empty() || name.contains(['=', '\0']) {
+            return Err(format!("Invalid Codex environment variable name: `{name}`"));
+        }
+        if value.contains('\0') {
+            return Err(format!(
+                "Invalid Codex environment variable `{name}`: contains a NUL byte"
+            ));
+        }
+        fill_placeholders(value, None)?;
+    }
+    Ok(())
+}
+
+/// The program, argv and extra environment a workspace's app-server is spawned with.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CodexCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Only what is set on top of the inherited environment; `PATH` is also extended.
+    pub env: BTreeMap<String, String>,
+    pub cwd: String,
+    /// `program` and `args` quoted for a POSIX shell.
+    pub command_line: String,
+}
+
+impl CodexCommand {
+    pub fn for_session(
+        entry: &WorkspaceEntry,
+        default_bin: Option<&str>,
+        codex_args: Option<&CodexArgs>,
+        codex_home: Option<&Path>,
+    ) -> Result<Self, String> {
+        let program = entry
+            .codex_bin
+            .as_deref()
+            .filter(|value| !value.trim().is_empty())
+            .or(default_bin.filter(|value| !value.trim().is_empty()))
+            .unwrap_or("codex")
+            .trim()
+            .to_string();
+        let (mut args, mut env) = match codex_args {
+            Some(codex_args) => codex_args.expand(entry)?,
+            None => (Vec::new(), BTreeMap::new()),
+        };
+        args.push("app-server".to_string());
+        if let Some(codex_home) = codex_home {
+            env.insert(
+                "CODEX_HOME".to_string(),
+                codex_home.to_string_lossy().to_string(),
+            );
+        }
+        let command_line = shell_words::join(std::iter::once(&program).chain(&args));
+        Ok(Self {
+            program,
+            args,
+            env,
+            cwd: entry.path.clone(),
+            command_line,
+        })
+    }
+
+    pub fn argv(&self) -> Vec<String> {
+        std::iter::once(self.program.clone())
+            .chain(self.args.iter().cloned())
+            .collect()
+    }
+}
+
+pub fn parse_codex_args(value: Option<&str>) -> Result<Vec<String>, String> {
+    let raw = match value {
+        Some(raw) if !raw.trim().is_empty() => raw.trim(),
+        _ => return Ok(Vec::new()),
+    };
+    shell_words::split(raw)
+        .map_err(|err| format!("Invalid Codex args: {err}"))
+        .map(|args| args.into_iter().filter(|arg| !arg.is_empty()).collect())
+}
+
+pub fn resolve_workspace_codex_args(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+    app_settings: Option<&AppSettings>,
+) -> Option<CodexArgs> {
+    if let Some(args) = level_codex_args(
+        entry.settings.codex_args.as_deref(),
+        entry.settings.codex_launch.as_ref(),
+    ) {
+        return Some(args);
+    }
+    if entry.kind.is_worktree() {
+        if let Some(parent) = parent_entry {
+            if let Some(args) = level_codex_args(
+                parent.settings.codex_args.as_deref(),
+                parent.settings.codex_launch.as_ref(),
+            ) {
+                return Some(args);
+            }
+        }
+    }
+    app_settings.and_then(|settings| {
+        level_codex_args(settings.codex_args.as_deref(), settings.codex_launch.as_ref())
+    })
+}
+
+fn level_codex_args(
+    codex_args: Option<&str>,
+    codex_launch: Option<&CodexLaunch>,
+) -> Option<CodexArgs> {
+    if let Some(launch) = codex_launch {
+        if !launch.args.is_empty() || !launch.env.is_empty() {
+            return Some(CodexArgs::Structured(launch.clone()));
+        }
+    }
+    codex_args.and_then(normalize_codex_args).map(CodexArgs::Shell)
+}
+
+fn normalize_codex_args(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_codex_args, resolve_workspace_codex_args, validate_codex_args, CodexArgs,
+        CodexCommand,
+    };
+    use crate::types::{
+        AppSettings, CodexLaunch, WorkspaceEntry, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn parses_empty_args() {
+        assert!(parse_codex_args(None).expect("parse none").is_empty());
+        assert!(parse_codex_args(Some("   ")).expect("parse blanks").is_empty());
+    }
+
+    #[test]
+    fn parses_simple_args() {
+        let args =
+            parse_codex_args(Some("--profile personal --flag")).expect("parse args");
+        assert_eq!(args, vec!["--profile", "personal", "--flag"]);
+    }
+
+    #[test]
+    fn parses_quoted_args() {
+        let args =
+            parse_codex_args(Some("--path \"a b\" --name='c d'")).expect("parse args");
+        assert_eq!(args, vec!["--path", "a b", "--name=c d"]);
+    }
+
+    #[test]
+    fn resolves_workspace_codex_args_precedence() {
+        let mut app_settings = AppSettings::default();
+        app_settings.codex_args = Some("--profile app".to_string());
+
+        let parent = WorkspaceEntry {
+            id: "parent".to_string(),
+            name: "Parent".to_string(),
+            path: "/tmp/parent".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                codex_args: Some("--profile parent".to_string()),
+                ..WorkspaceSettings::default()
+            },
+        };
+
+        let child = WorkspaceEntry {
+            id: "child".to_string(),
+            name: "Child".to_string(),
+            path: "/tmp/child".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some(parent.id.clone()),
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+        };
+
+        let resolved = resolve_workspace_codex_args(&child, Some(&parent), Some(&app_settings));
+        assert_eq!(resolved, Some(CodexArgs::Shell("--profile parent".to_string())));
+
+        let mut override_child = child.clone();
+        override_child.settings.codex_args = Some("  --profile child  ".to_string());
+        let resolved_child =
+            resolve_workspace_codex_args(&override_child, Some(&parent), Some(&app_settings));
+        assert_eq!(
+            resolved_child,
+            Some(CodexArgs::Shell("--profile child".to_string()))
+        );
+
+        let launch = CodexLaunch {
+            args: vec!["--profile".to_string(), "launch".to_string()],
+            ..CodexLaunch::default()
+        };
+        override_child.settings.codex_launch = Some(launch.clone());
+        let resolved_launch =
+            resolve_workspace_codex_args(&override_child, Some(&parent), Some(&app_settings));
+        assert_eq!(resolved_launch, Some(CodexArgs::Structured(launch)));
+
+        let main = WorkspaceEntry {
+            id: "main".to_string(),
+            name: "Main".to_string(),
+            path: "/tmp/main".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+        };
+        let resolved_main = resolve_workspace_codex_args(&main, None, Some(&app_settings));
+        assert_eq!(resolved_main, Some(CodexArgs::Shell("--profile app".to_string())));
+    }
+
+    #[test]
+    fn expands_placeholders_into_the_spawn_command() {
+        let entry = WorkspaceEntry {
+            id: "wt".to_string(),
+            name: "Worktree".to_string(),
+            path: "/tmp/wt dir".to_string(),
+            codex_bin: Some(" /opt/codex ".to_string()),
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some("parent".to_string()),
+            worktree: Some(WorktreeInfo {
+                branch: "feature/x".to_string(),
+            }),
+            settings: WorkspaceSettings::default(),
+        };
+        let launch = CodexLaunch {
+            args: vec![
+                "-c".to_string(),
+                "notes={workspacePath}/notes-{branch}".to_string(),
+                "-c".to_string(),
+                "tools={ web = true }".to_string(),
+            ],
+            env: [("RUST_LOG".to_string(), "debug-{branch}".to_string())].into(),
+        };
+        assert_eq!(validate_codex_args(None, Some(&launch)), Ok(()));
+        let command = CodexCommand::for_session(
+            &entry,
+            Some("codex-default"),
+            Some(&CodexArgs::Structured(launch)),
+            Some(Path::new("/tmp/home")),
+        )
+        .expect("command");
+        assert_eq!(command.program, "/opt/codex");
+        assert_eq!(
+            command.args,
+            vec![
+                "-c",
+                "notes=/tmp/wt dir/notes-feature/x",
+                "-c",
+                "tools={ web = true }",
+                "app-server"
+            ]
+        );
+        assert_eq!(command.env["RUST_LOG"], "debug-feature/x");
+        assert_eq!(command.env["CODEX_HOME"], "/tmp/home");
+        assert_eq!(
+            command.command_line,
+            "/opt/codex -c 'notes=/tmp/wt dir/notes-feature/x' -c 'tools={ web = true }' app-server"
+        );
+
+        let shell = CodexArgs::Shell("--cd {workspacePath}".to_string());
+        let command = CodexCommand::for_session(&entry, None, Some(&shell), None).expect("shell");
+        assert_eq!(command.argv()[1..], ["--cd", "/tmp/wt dir", "app-server"]);
+    }
+
+    #[test]
+    fn rejects_invalid_args_at_save_time() {
+        let error = validate_codex_args(Some("--profile {profile}"), None).expect_err("unknown");
+        assert!(error.contains("unknown placeholder `{profile}`"), "{error}");
+        assert!(validate_codex_args(Some("--name \"open"), None).is_err());
+        let bad_env = CodexLaunch {
+            env: [("A=B".to_string(), "x".to_string())].into(),
+            ..CodexLaunch::default()
+        };
+        assert!(validate_codex_args(None, Some(&bad_env)).is_err());
+        assert_eq!(validate_codex_args(Some("--x {}"), None), Ok(()));
+    }
+}
@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
 use toml::Value as TomlValue;
 
 use crate::files::io::read_text_file_within;
@@ -7,28 +8,39 @@ use crate::files::ops::write_with_policy;
 use crate::files::policy::{policy_for, FileKind, FileScope};
 
 const FEATURES_TABLE: &str = "[features]";
+const SKILLS_CONFIG_TABLE: &str = "[[skills.config]]";
+/// Codex's default for `project_doc_max_bytes`.
+const DEFAULT_PROJECT_DOC_MAX_BYTES: u64 = 32 * 1024;
 
-pub(crate) fn read_steer_enabled() -> Result<Option<bool>, String> {
+/// One `[[skills.config]]` entry; a skill without an entry is enabled.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillConfigEntry {
+    pub path: String,
+    pub enabled: bool,
+}
+
+pub fn read_steer_enabled() -> Result<Option<bool>, String> {
     read_feature_flag("steer")
 }
 
-pub(crate) fn read_collab_enabled() -> Result<Option<bool>, String> {
+pub fn read_collab_enabled() -> Result<Option<bool>, String> {
     read_feature_flag("collab")
 }
 
-pub(crate) fn read_collaboration_modes_enabled() -> Result<Option<bool>, String> {
+pub fn read_collaboration_modes_enabled() -> Result<Option<bool>, String> {
     read_feature_flag("collaboration_modes")
 }
 
-pub(crate) fn read_unified_exec_enabled() -> Result<Option<bool>, String> {
+pub fn read_unified_exec_enabled() -> Result<Option<bool>, String> {
     read_feature_flag("unified_exec")
 }
 
-pub(crate) fn read_apps_enabled() -> Result<Option<bool>, String> {
+pub fn read_apps_enabled() -> Result<Option<bool>, String> {
     read_feature_flag("apps")
 }
 
-pub(crate) fn read_personality() -> Result<Option<String>, String> {
+pub fn read_personality() -> Result<Option<String>, String> {
     let Some(root) = resolve_default_codex_home() else {
         return Ok(None);
     };
@@ -39,27 +51,27 @@ pub(crate) fn read_personality() -> Result<Option<String>, String> {
         .map(|value| value.to_string()))
 }
 
-pub(crate) fn write_steer_enabled(enabled: bool) -> Result<(), String> {
+pub fn write_steer_enabled(enabled: bool) -> Result<(), String> {
     write_feature_flag("steer", enabled)
 }
 
-pub(crate) fn write_collab_enabled(enabled: bool) -> Result<(), String> {
+pub fn write_collab_enabled(enabled: bool) -> Result<(), String> {
     write_feature_flag("collab", enabled)
 }
 
-pub(crate) fn write_collaboration_modes_enabled(enabled: bool) -> Result<(), String> {
+pub fn write_collaboration_modes_enabled(enabled: bool) -> Result<(), String> {
     write_feature_flag("collaboration_modes", enabled)
 }
 
-pub(crate) fn write_unified_exec_enabled(enabled: bool) -> Result<(), String> {
+pub fn write_unified_exec_enabled(enabled: bool) -> Result<(), String> {
     write_feature_flag("unified_exec", enabled)
 }
 
-pub(crate) fn write_apps_enabled(enabled: bool) -> Result<(), String> {
+pub fn write_apps_enabled(enabled: bool) -> Result<(), String> {
     write_feature_flag("apps", enabled)
 }
 
-pub(crate) fn write_personality(personality: &str) -> Result<(), String> {
+pub fn write_personality(personality: &str) -> Result<(), String> {
     let Some(root) = resolve_default_codex_home() else {
         return Ok(());
     };
@@ -115,11 +127,11 @@ fn write_feature_flag(key: &str, enabled: bool) -> Result<(), String> {
     write_with_policy(&root, policy, &updated)
 }
 
-pub(crate) fn config_toml_path() -> Option<PathBuf> {
+pub fn config_toml_path() -> Option<PathBuf> {
     resolve_default_codex_home().map(|home| home.join("config.toml"))
 }
 
-pub(crate) fn read_config_model(codex_home: Option<PathBuf>) -> Result<Option<String>, String> {
+pub fn read_config_model(codex_home: Option<PathBuf>) -> Result<Option<String>, String> {
     let root = codex_home.or_else(resolve_default_codex_home);
     let Some(root) = root else {
         return Err("Unable to resolve CODEX_HOME".to_string());
@@ -127,6 +139,84 @@ pub(crate) fn read_config_model(codex_home: Option<PathBuf>) -> Result<Option<St
     read_config_model_from_root(&root)
 }
 
+/// How Codex finds project `AGENTS.md` files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectDocConfig {
+    /// Budget for all project instruction files together.
+    pub max_bytes: u64,
+    /// Tried after `AGENTS.override.md` and `AGENTS.md` in each directory.
+    pub fallback_filenames: Vec<String>,
+}
+
+impl Default for ProjectDocConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_PROJECT_DOC_MAX_BYTES,
+            fallback_filenames: Vec::new(),
+        }
+    }
+}
+
+/// `project_doc_max_bytes` and `project_doc_fallback_filenames` from
+/// `<root>/config.toml`; Codex's defaults when unset or unreadable.
+pub fn read_project_doc_config(root: &Path) -> ProjectDocConfig {
+    read_config_contents_from_root(root)
+        .ok()
+        .flatten()
+        .map(|contents| parse_project_doc_config(&contents))
+        .unwrap_or_default()
+}
+
+fn parse_project_doc_config(contents: &str) -> ProjectDocConfig {
+    let Ok(parsed) = toml::from_str::<TomlValue>(contents) else {
+        return ProjectDocConfig::default();
+    };
+    let max_bytes = parsed
+        .get("project_doc_max_bytes")
+        .and_then(TomlValue::as_integer)
+        .and_then(|value| u64::try_from(value).ok())
+        .unwrap_or(DEFAULT_PROJECT_DOC_MAX_BYTES);
+    let fallback_filenames = parsed
+        .get("project_doc_fallback_filenames")
+        .and_then(TomlValue::as_array)
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(TomlValue::as_str)
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    ProjectDocConfig {
+        max_bytes,
+        fallback_filenames,
+    }
+}
+
+/// Skill entries from `<root>/config.toml`.
+pub fn read_skill_configs(root: &Path) -> Result<Vec<SkillConfigEntry>, String> {
+    let contents = read_config_contents_from_root(root)?;
+    Ok(contents
+        .as_deref()
+        .map(parse_skill_configs)
+        .unwrap_or_default())
+}
+
+/// Sets `enabled` on the `[[skills.config]]` entry for `skill_path` in
+/// `<root>/config.toml`, adding the entry when there is none.
+pub fn write_skill_enabled(
+    root: &Path,
+    skill_path: &str,
+    enabled: bool,
+) -> Result<(), String> {
+    let policy = config_policy()?;
+    let contents = read_config_contents_from_root(root)?.unwrap_or_default();
+    let updated = upsert_skill_config(&contents, skill_path, enabled);
+    write_with_policy(&root.to_path_buf(), policy, &updated)
+}
+
 fn resolve_default_codex_home() -> Option<PathBuf> {
     crate::codex::home::resolve_default_codex_home()
 }
@@ -305,6 +395,90 @@ fn upsert_top_level_string_key(contents: &str, key: &str, value: &str) -> String
     updated
 }
 
+fn parse_skill_configs(contents: &str) -> Vec<SkillConfigEntry> {
+    let Ok(parsed) = toml::from_str::<TomlValue>(contents) else {
+        return Vec::new();
+    };
+    parsed
+        .get("skills")
+        .and_then(|skills| skills.get("config"))
+        .and_then(TomlValue::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(SkillConfigEntry {
+                        path: entry.get("path")?.as_str()?.to_string(),
+                        enabled: entry
+                            .get("enabled")
+                            .and_then(TomlValue::as_bool)
+                            .unwrap_or(true),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_string_value(line: &str) -> Option<String> {
+    let (_, value) = line.split_once('=')?;
+    let parsed = toml::from_str::<TomlValue>(&format!("value = {}", value.trim())).ok()?;
+    parsed.get("value")?.as_str().map(str::to_string)
+}
+
+/// Only edits the array-of-tables form; entries written as inline tables are left
+/// alone and a new block is appended instead.
+fn upsert_skill_config(contents: &str, skill_path: &str, enabled: bool) -> String {
+    let mut lines: Vec<String> = contents.lines().map(|line| line.to_string()).collect();
+    let enabled_line = format!("enabled = {enabled}");
+    let mut index = 0;
+    let mut replaced = false;
+
+    while index < lines.len() {
+        if lines[index].trim() != SKILLS_CONFIG_TABLE {
+            index += 1;
+            continue;
+        }
+        let start = index + 1;
+        let end = lines[start..]
+            .iter()
+            .position(|line| line.trim_start().starts_with('['))
+            .map(|offset| start + offset)
+            .unwrap_or(lines.len());
+        let path_index = (start..end).find(|idx| {
+            is_key_value_for(&lines[*idx], "path")
+                && parse_string_value(&lines[*idx]).as_deref() == Some(skill_path)
+        });
+        if let Some(path_index) = path_index {
+            match (start..end).find(|idx| is_key_value_for(&lines[*idx], "enabled")) {
+                Some(enabled_index) => lines[enabled_index] = enabled_line.clone(),
+                None => lines.insert(path_index + 1, enabled_line.clone()),
+            }
+            replaced = true;
+            break;
+        }
+        index = end;
+    }
+
+    if !replaced {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(SKILLS_CONFIG_TABLE.to_string());
+        lines.push(format!(
+            "path = {}",
+            TomlValue::String(skill_path.to_string())
+        ));
+        lines.push(enabled_line);
+    }
+
+    let mut updated = lines.join("\n");
+    if contents.ends_with('\n') || updated.is_empty() || !replaced {
+        updated.push('\n');
+    }
+    updated
+}
+
 fn is_key_value_for(line: &str, key: &str) -> bool {
     let trimmed = line.trim();
     if trimmed.is_empty() || trimmed.starts_with('#') {
@@ -341,7 +515,9 @@ impl<T> RetainWithIndex<T> for Vec<T> {
 #[cfg(test)]
 mod tests {
     use super::{
-        parse_personality_from_toml, remove_top_level_key, upsert_top_level_string_key,
+        parse_personality_from_toml, parse_project_doc_config, parse_skill_configs,
+        remove_top_level_key, upsert_skill_config, upsert_top_level_string_key, ProjectDocConfig,
+        SkillConfigEntry,
     };
 
     #[test]
@@ -377,4 +553,52 @@ mod tests {
         let updated = remove_top_level_key(input, "personality");
         assert_eq!(updated, "model = \"gpt-5\"\n[features]\nsteer = true\n");
     }
+
+    #[test]
+    fn upsert_skill_config_updates_or_appends_entries() {
+        let input = "model = \"m\"\n[[skills.config]]\npath = \"/sk/a/SKILL.md\"\n";
+        let disabled = upsert_skill_config(input, "/sk/a/SKILL.md", false);
+        assert_eq!(
+            disabled,
+            "model = \"m\"\n[[skills.config]]\npath = \"/sk/a/SKILL.md\"\nenabled = false\n"
+        );
+
+        let appended = upsert_skill_config(&disabled, "C:\\skills\\b\\SKILL.md", false);
+        assert_eq!(
+            parse_skill_configs(&appended),
+            vec![
+                SkillConfigEntry {
+                    path: "/sk/a/SKILL.md".to_string(),
+                    enabled: false,
+                },
+                SkillConfigEntry {
+                    path: "C:\\skills\\b\\SKILL.md".to_string(),
+                    enabled: false,
+                },
+            ]
+        );
+        assert_eq!(
+            upsert_skill_config(&appended, "C:\\skills\\b\\SKILL.md", false),
+            appended
+        );
+    }
+
+    #[test]
+    fn parses_project_doc_config() {
+        let config = parse_project_doc_config(
+            "project_doc_max_bytes = 4096\n\
+             project_doc_fallback_filenames = [\"CONTRIBUTING.md\", \" \"]\n",
+        );
+        assert_eq!(
+            config,
+            ProjectDocConfig {
+                max_bytes: 4096,
+                fallback_filenames: vec!["CONTRIBUTING.md".to_string()],
+            }
+        );
+        assert_eq!(
+            parse_project_doc_config("project_doc_max_bytes = -1\n"),
+            ProjectDocConfig::default()
+        );
+    }
 }
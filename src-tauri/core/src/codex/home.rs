@@ -1,25 +1,60 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::path::PathBuf;
+use std::sync::RwLock;
 
 use crate::types::WorkspaceEntry;
 
-pub(crate) fn resolve_workspace_codex_home(
-    entry: &WorkspaceEntry,
-    parent_entry: Option<&WorkspaceEntry>,
-) -> Option<PathBuf> {
+/// Codex home of each account profile by name; kept current by `AccountProfiles`.
+static ACCOUNT_PROFILE_HOMES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+pub fn set_account_profile_homes(homes: BTreeMap<String, String>) {
+    *ACCOUNT_PROFILE_HOMES
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = homes;
+}
+
+/// The profile's Codex home, or `None` when no profile has that name.
+pub fn resolve_account_profile_home(name: &str) -> Option<PathBuf> {
+    let homes = ACCOUNT_PROFILE_HOMES
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    homes
+        .get(name.trim())
+        .and_then(|value| normalize_account_profile_home(value))
+}
+
+/// Profile homes may use `~` and environment variables but must end up absolute.
+pub fn normalize_account_profile_home(value: &str) -> Option<PathBuf> {
+    normalize_codex_home(value).filter(|path| path.is_absolute())
+}
+
+/// The workspace's own `codexHome` override, else the home of its account profile.
+fn configured_codex_home(entry: &WorkspaceEntry) -> Option<PathBuf> {
     if let Some(value) = entry.settings.codex_home.as_ref() {
         let base = PathBuf::from(&entry.path);
         if let Some(path) = normalize_codex_home_with_base(value, &base) {
             return Some(path);
         }
     }
+    entry
+        .settings
+        .account_profile
+        .as_deref()
+        .and_then(resolve_account_profile_home)
+}
+
+pub fn resolve_workspace_codex_home(
+    entry: &WorkspaceEntry,
+    parent_entry: Option<&WorkspaceEntry>,
+) -> Option<PathBuf> {
+    if let Some(path) = configured_codex_home(entry) {
+        return Some(path);
+    }
     if entry.kind.is_worktree() {
         if let Some(parent) = parent_entry {
-            if let Some(value) = parent.settings.codex_home.as_ref() {
-                let base = PathBuf::from(&parent.path);
-                if let Some(path) = normalize_codex_home_with_base(value, &base) {
-                    return Some(path);
-                }
+            if let Some(path) = configured_codex_home(parent) {
+                return Some(path);
             }
             let legacy_home = PathBuf::from(&parent.path).join(".codexmonitor");
             if legacy_home.is_dir() {
@@ -34,7 +69,7 @@ pub(crate) fn resolve_workspace_codex_home(
     resolve_default_codex_home()
 }
 
-pub(crate) fn resolve_default_codex_home() -> Option<PathBuf> {
+pub fn resolve_default_codex_home() -> Option<PathBuf> {
     if let Ok(value) = env::var("CODEX_HOME") {
         if let Some(path) = normalize_codex_home(&value) {
             return Some(path);
@@ -170,7 +205,7 @@ fn join_env_path(prefix: &str, remainder: &str) -> PathBuf {
     }
 }
 
-fn resolve_home_dir() -> Option<PathBuf> {
+pub fn resolve_home_dir() -> Option<PathBuf> {
     if let Ok(value) = env::var("HOME") {
         if !value.trim().is_empty() {
             return Some(PathBuf::from(value));
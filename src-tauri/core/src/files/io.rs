@@ -5,7 +5,7 @@ use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-pub(crate) struct TextFileResponse {
+pub struct TextFileResponse {
     pub exists: bool,
     pub content: String,
     pub truncated: bool,
@@ -48,7 +48,7 @@ fn resolve_or_create_root(root: &Path, root_context: &str) -> Result<PathBuf, St
     Ok(canonical_root)
 }
 
-pub(crate) fn read_text_file_within(
+pub fn read_text_file_within(
     root: &Path,
     filename: &str,
     root_may_be_missing: bool,
@@ -93,7 +93,7 @@ pub(crate) fn read_text_file_within(
     })
 }
 
-pub(crate) fn write_text_file_within(
+pub fn write_text_file_within(
     root: &Path,
     filename: &str,
     content: &str,
@@ -136,6 +136,31 @@ pub(crate) fn write_text_file_within(
         .map_err(|err| format!("Failed to write {file_context}: {err}"))
 }
 
+/// Removes `root/filename`. A symlink is removed itself, never its target. Returns
+/// whether there was a file to remove.
+pub fn delete_text_file_within(
+    root: &Path,
+    filename: &str,
+    root_context: &str,
+    file_context: &str,
+) -> Result<bool, String> {
+    let Some(canonical_root) = resolve_root(root, root_context, true)? else {
+        return Ok(false);
+    };
+    let candidate = canonical_root.join(filename);
+    let metadata = match std::fs::symlink_metadata(&candidate) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(format!("Failed to resolve {file_context}: {err}")),
+    };
+    if metadata.is_dir() {
+        return Err(format!("{file_context} is a directory"));
+    }
+    std::fs::remove_file(&candidate)
+        .map_err(|err| format!("Failed to delete {file_context}: {err}"))?;
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,6 +199,30 @@ mod tests {
         assert_eq!(response.content, "hello");
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn delete_removes_symlink_but_not_target() {
+        use std::os::unix::fs::symlink;
+
+        let root = temp_dir();
+        let outside = temp_dir();
+        std::fs::create_dir_all(&root).expect("create root");
+        std::fs::create_dir_all(&outside).expect("create outside");
+
+        let outside_file = outside.join("AGENTS.md");
+        std::fs::write(&outside_file, "outside").expect("seed outside file");
+        symlink(&outside_file, root.join("AGENTS.md")).expect("create symlink");
+
+        let deleted = delete_text_file_within(&root, "AGENTS.md", "CODEX_HOME", "AGENTS.md")
+            .expect("delete should succeed");
+        assert!(deleted);
+        assert!(!root.join("AGENTS.md").exists());
+        assert!(outside_file.exists());
+        let again = delete_text_file_within(&root, "AGENTS.md", "CODEX_HOME", "AGENTS.md")
+            .expect("second delete should succeed");
+        assert!(!again);
+    }
+
     #[cfg(unix)]
     #[test]
     fn write_rejects_symlink_escape() {
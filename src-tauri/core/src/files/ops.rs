@@ -1,9 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::files::io::{read_text_file_within, write_text_file_within, TextFileResponse};
+use crate::files::io::{
+    delete_text_file_within, read_text_file_within, write_text_file_within, TextFileResponse,
+};
 use crate::files::policy::FilePolicy;
 
-pub(crate) fn read_with_policy(root: &PathBuf, policy: FilePolicy) -> Result<TextFileResponse, String> {
+pub fn read_with_policy(root: &PathBuf, policy: FilePolicy) -> Result<TextFileResponse, String> {
     read_text_file_within(
         root,
         policy.filename,
@@ -14,7 +16,7 @@ pub(crate) fn read_with_policy(root: &PathBuf, policy: FilePolicy) -> Result<Tex
     )
 }
 
-pub(crate) fn write_with_policy(
+pub fn write_with_policy(
     root: &PathBuf,
     policy: FilePolicy,
     content: &str,
@@ -30,6 +32,10 @@ pub(crate) fn write_with_policy(
     )
 }
 
+pub fn delete_with_policy(root: &Path, policy: FilePolicy) -> Result<bool, String> {
+    delete_text_file_within(root, policy.filename, policy.root_context, policy.filename)
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
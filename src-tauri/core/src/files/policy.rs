@@ -0,0 +1,213 @@
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::SkipDirSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileScope {
+    Workspace,
+    Global,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    Agents,
+    /// `AGENTS.override.md`, which Codex reads instead of `AGENTS.md` next to it.
+    AgentsOverride,
+    Config,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilePolicy {
+    pub filename: &'static str,
+    pub root_context: &'static str,
+    pub root_may_be_missing: bool,
+    pub create_root: bool,
+    pub allow_external_symlink_target: bool,
+}
+
+const AGENTS_FILENAME: &str = "AGENTS.md";
+const AGENTS_OVERRIDE_FILENAME: &str = "AGENTS.override.md";
+const CONFIG_FILENAME: &str = "config.toml";
+
+pub fn policy_for(scope: FileScope, kind: FileKind) -> Result<FilePolicy, String> {
+    match (scope, kind) {
+        (FileScope::Workspace, FileKind::Agents) => Ok(FilePolicy {
+            filename: AGENTS_FILENAME,
+            root_context: "workspace root",
+            root_may_be_missing: false,
+            create_root: false,
+            allow_external_symlink_target: false,
+        }),
+        (FileScope::Global, FileKind::Agents) => Ok(FilePolicy {
+            filename: AGENTS_FILENAME,
+            root_context: "CODEX_HOME",
+            root_may_be_missing: true,
+            create_root: true,
+            allow_external_symlink_target: true,
+        }),
+        (FileScope::Workspace, FileKind::AgentsOverride) => Ok(FilePolicy {
+            filename: AGENTS_OVERRIDE_FILENAME,
+            ..policy_for(FileScope::Workspace, FileKind::Agents)?
+        }),
+        (FileScope::Global, FileKind::AgentsOverride) => Ok(FilePolicy {
+            filename: AGENTS_OVERRIDE_FILENAME,
+            ..policy_for(FileScope::Global, FileKind::Agents)?
+        }),
+        (FileScope::Global, FileKind::Config) => Ok(FilePolicy {
+            filename: CONFIG_FILENAME,
+            root_context: "CODEX_HOME",
+            root_may_be_missing: true,
+            create_root: true,
+            allow_external_symlink_target: false,
+        }),
+        (FileScope::Workspace, FileKind::Config) => {
+            Err("config.toml is only supported for global scope".to_string())
+        }
+    }
+}
+
+const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn", ".jj"];
+const BUILD_ARTIFACT_DIRS: &[&str] = &["target", "dist", "release-artifacts"];
+const PACKAGE_DIRS: &[&str] = &["node_modules"];
+
+/// The directory names workspace walks leave out, built from `skipDirs` in the app settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipDirPolicy {
+    names: Vec<String>,
+}
+
+impl SkipDirPolicy {
+    pub fn from_settings(settings: &SkipDirSettings) -> Self {
+        let categories = [
+            (settings.vcs, VCS_DIRS),
+            (settings.build_artifacts, BUILD_ARTIFACT_DIRS),
+            (settings.package_dirs, PACKAGE_DIRS),
+        ];
+        let mut names = categories
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .flat_map(|(_, names)| names.iter().map(|name| name.to_string()))
+            .chain(
+                settings
+                    .custom
+                    .iter()
+                    .map(|name| name.trim().trim_matches('/').to_string())
+                    .filter(|name| !name.is_empty()),
+            )
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        Self { names }
+    }
+
+    /// The policy in effect. Walkers take it once up front so one walk sees one policy.
+    pub fn current() -> Self {
+        SKIP_DIR_POLICY
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+            .unwrap_or_else(|| Self::from_settings(&SkipDirSettings::default()))
+    }
+
+    pub fn skips(&self, name: &str) -> bool {
+        self.names.iter().any(|skipped| skipped == name)
+    }
+}
+
+static SKIP_DIR_POLICY: RwLock<Option<SkipDirPolicy>> = RwLock::new(None);
+
+/// Switches the process-wide skip-dir policy; called when settings load or change.
+pub fn set_skip_dirs(settings: &SkipDirSettings) {
+    *SKIP_DIR_POLICY
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = Some(SkipDirPolicy::from_settings(settings));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{policy_for, FileKind, FilePolicy, FileScope, SkipDirPolicy};
+    use crate::types::SkipDirSettings;
+
+    #[test]
+    fn workspace_agents_policy_is_strict() {
+        let policy = policy_for(FileScope::Workspace, FileKind::Agents).expect("policy");
+        assert_eq!(policy.filename, "AGENTS.md");
+        assert_eq!(policy.root_context, "workspace root");
+        assert!(!policy.root_may_be_missing);
+        assert!(!policy.create_root);
+        assert!(!policy.allow_external_symlink_target);
+    }
+
+    #[test]
+    fn global_agents_policy_creates_root() {
+        let policy = policy_for(FileScope::Global, FileKind::Agents).expect("policy");
+        assert_eq!(policy.filename, "AGENTS.md");
+        assert_eq!(policy.root_context, "CODEX_HOME");
+        assert!(policy.root_may_be_missing);
+        assert!(policy.create_root);
+        assert!(policy.allow_external_symlink_target);
+    }
+
+    #[test]
+    fn agents_override_policy_matches_agents() {
+        for scope in [FileScope::Workspace, FileScope::Global] {
+            let agents = policy_for(scope, FileKind::Agents).expect("agents policy");
+            let policy = policy_for(scope, FileKind::AgentsOverride).expect("override policy");
+            assert_eq!(policy.filename, "AGENTS.override.md");
+            assert_eq!(
+                FilePolicy {
+                    filename: agents.filename,
+                    ..policy
+                },
+                agents
+            );
+        }
+    }
+
+    #[test]
+    fn global_config_policy_creates_root() {
+        let policy = policy_for(FileScope::Global, FileKind::Config).expect("policy");
+        assert_eq!(policy.filename, "config.toml");
+        assert_eq!(policy.root_context, "CODEX_HOME");
+        assert!(policy.root_may_be_missing);
+        assert!(policy.create_root);
+        assert!(!policy.allow_external_symlink_target);
+    }
+
+    #[test]
+    fn skip_dir_policy_follows_enabled_categories_and_custom_names() {
+        let policy = SkipDirPolicy::from_settings(&SkipDirSettings::default());
+        for name in [".git", ".jj", "target", "dist", "node_modules"] {
+            assert!(policy.skips(name), "{name}");
+        }
+        assert!(!policy.skips("src"));
+        assert!(!policy.skips(".github"));
+
+        let policy = SkipDirPolicy::from_settings(&SkipDirSettings {
+            build_artifacts: false,
+            package_dirs: false,
+            custom: vec![
+                " .venv/ ".to_string(),
+                String::new(),
+                "coverage".to_string(),
+            ],
+            ..SkipDirSettings::default()
+        });
+        assert!(policy.skips(".git"));
+        assert!(!policy.skips("target"));
+        assert!(!policy.skips("node_modules"));
+        assert!(policy.skips(".venv"));
+        assert!(policy.skips("coverage"));
+        assert!(!policy.skips(""));
+    }
+
+    #[test]
+    fn workspace_config_is_rejected() {
+        let result = policy_for(FileScope::Workspace, FileKind::Config);
+        assert!(result.is_err());
+    }
+}
@@ -4,10 +4,11 @@ use std::path::{Path, PathBuf};
 use git2::{DiffOptions, Repository, Tree};
 use ignore::WalkBuilder;
 
+use crate::files::policy::SkipDirPolicy;
 use crate::types::{GitLogEntry, WorkspaceEntry};
 use crate::utils::normalize_git_path;
 
-pub(crate) fn image_mime_type(path: &str) -> Option<&'static str> {
+pub fn image_mime_type(path: &str) -> Option<&'static str> {
     let ext = Path::new(path)
         .extension()
         .and_then(|value| value.to_str())?
@@ -24,7 +25,7 @@ pub(crate) fn image_mime_type(path: &str) -> Option<&'static str> {
     }
 }
 
-pub(crate) fn commit_to_entry(commit: git2::Commit) -> GitLogEntry {
+pub fn commit_to_entry(commit: git2::Commit) -> GitLogEntry {
     let summary = commit.summary().unwrap_or("").to_string();
     let author = commit.author().name().unwrap_or("").to_string();
     let timestamp = commit.time().seconds();
@@ -36,7 +37,7 @@ pub(crate) fn commit_to_entry(commit: git2::Commit) -> GitLogEntry {
     }
 }
 
-pub(crate) fn checkout_branch(repo: &Repository, name: &str) -> Result<(), git2::Error> {
+pub fn checkout_branch(repo: &Repository, name: &str) -> Result<(), git2::Error> {
     let refname = format!("refs/heads/{name}");
     let target = repo.revparse_single(&refname)?;
 
@@ -47,7 +48,7 @@ pub(crate) fn checkout_branch(repo: &Repository, name: &str) -> Result<(), git2:
     Ok(())
 }
 
-pub(crate) fn diff_stats_for_path(
+pub fn diff_stats_for_path(
     repo: &Repository,
     head_tree: Option<&Tree>,
     path: &str,
@@ -82,7 +83,7 @@ pub(crate) fn diff_stats_for_path(
     Ok((additions, deletions))
 }
 
-pub(crate) fn diff_patch_to_string(patch: &mut git2::Patch) -> Result<String, git2::Error> {
+pub fn diff_patch_to_string(patch: &mut git2::Patch) -> Result<String, git2::Error> {
     let buf = patch.to_buf()?;
     Ok(buf
         .as_str()
@@ -131,7 +132,81 @@ mod tests {
     }
 }
 
-pub(crate) fn parse_github_repo(remote_url: &str) -> Option<String> {
+fn build_combined_diff(diff: &git2::Diff) -> String {
+    let mut combined_diff = String::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path());
+        let Some(path) = path else {
+            continue;
+        };
+        let patch = match git2::Patch::from_diff(diff, index) {
+            Ok(patch) => patch,
+            Err(_) => continue,
+        };
+        let Some(mut patch) = patch else {
+            continue;
+        };
+        let content = match diff_patch_to_string(&mut patch) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        if content.trim().is_empty() {
+            continue;
+        }
+        if !combined_diff.is_empty() {
+            combined_diff.push_str("\n\n");
+        }
+        combined_diff.push_str(&format!("=== {} ===\n", path.display()));
+        combined_diff.push_str(&content);
+    }
+    combined_diff
+}
+
+/// Diff text used for commit messages. `Some(true)` is the staged changes only,
+/// `Some(false)` the unstaged ones (untracked files included), and `None` prefers
+/// staged changes and falls back to everything uncommitted.
+pub fn collect_workspace_diff(
+    repo_root: &Path,
+    staged: Option<bool>,
+) -> Result<String, String> {
+    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
+    let head_tree = repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_tree().ok());
+
+    let mut untracked_options = DiffOptions::new();
+    untracked_options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    if staged == Some(false) {
+        let diff = repo
+            .diff_index_to_workdir(None, Some(&mut untracked_options))
+            .map_err(|e| e.to_string())?;
+        return Ok(build_combined_diff(&diff));
+    }
+
+    let mut options = DiffOptions::new();
+    let index = repo.index().map_err(|e| e.to_string())?;
+    let diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), Some(&index), Some(&mut options))
+        .map_err(|e| e.to_string())?;
+    let combined_diff = build_combined_diff(&diff);
+    if staged == Some(true) || !combined_diff.trim().is_empty() {
+        return Ok(combined_diff);
+    }
+
+    let diff = repo
+        .diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut untracked_options))
+        .map_err(|e| e.to_string())?;
+    Ok(build_combined_diff(&diff))
+}
+
+pub fn parse_github_repo(remote_url: &str) -> Option<String> {
     let trimmed = remote_url.trim();
     if trimmed.is_empty() {
         return None;
@@ -153,7 +228,30 @@ pub(crate) fn parse_github_repo(remote_url: &str) -> Option<String> {
     }
 }
 
-pub(crate) fn resolve_git_root(entry: &WorkspaceEntry) -> Result<PathBuf, String> {
+pub fn github_repo_from_path(path: &Path) -> Result<String, String> {
+    let repo = Repository::open(path).map_err(|e| e.to_string())?;
+    let remotes = repo.remotes().map_err(|e| e.to_string())?;
+    let name = if remotes.iter().any(|remote| remote == Some("origin")) {
+        "origin".to_string()
+    } else {
+        remotes
+            .iter()
+            .flatten()
+            .next()
+            .unwrap_or("")
+            .to_string()
+    };
+    if name.is_empty() {
+        return Err("No git remote configured.".to_string());
+    }
+    let remote = repo.find_remote(&name).map_err(|e| e.to_string())?;
+    let remote_url = remote
+        .url()
+        .ok_or("Remote has no URL configured.")?;
+    parse_github_repo(remote_url).ok_or("Remote is not a GitHub repository.".to_string())
+}
+
+pub fn resolve_git_root(entry: &WorkspaceEntry) -> Result<PathBuf, String> {
     let base = PathBuf::from(&entry.path);
     let root = entry
         .settings
@@ -176,14 +274,7 @@ pub(crate) fn resolve_git_root(entry: &WorkspaceEntry) -> Result<PathBuf, String
     }
 }
 
-fn should_skip_dir(name: &str) -> bool {
-    matches!(
-        name,
-        ".git" | "node_modules" | "dist" | "target" | "release-artifacts"
-    )
-}
-
-pub(crate) fn list_git_roots(
+pub fn list_git_roots(
     root: &Path,
     max_depth: usize,
     max_results: usize,
@@ -195,17 +286,19 @@ pub(crate) fn list_git_roots(
     let mut results = Vec::new();
     let mut seen = HashSet::new();
     let max_depth = max_depth.max(1);
+    let skip_dirs = SkipDirPolicy::current();
     let walker = WalkBuilder::new(root)
         .hidden(false)
         .follow_links(false)
         .max_depth(Some(max_depth))
-        .filter_entry(|entry| {
+        .filter_entry(move |entry| {
             if entry.depth() == 0 {
                 return true;
             }
             if entry.file_type().is_some_and(|ft| ft.is_dir()) {
                 let name = entry.file_name().to_string_lossy();
-                if should_skip_dir(&name) {
+                // Repositories are found by their `.git`, never by walking into one.
+                if name == ".git" || skip_dirs.skips(&name) {
                     return false;
                 }
             }
@@ -0,0 +1,17 @@
+//! Workspace, file, git and Codex session logic shared by the desktop app and the
+//! `codex_monitor_daemon`/`fridexctl` binaries. Anything both sides run lives here, so
+//! the two never drift apart; transport (Tauri commands, daemon RPC) stays in the callers.
+
+pub mod backend;
+pub mod codex;
+pub mod files;
+pub mod git_utils;
+pub mod rules;
+pub mod shared;
+pub mod storage;
+pub mod types;
+pub mod utils;
+pub mod workspaces;
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,648 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::types::{
+    ApprovalAccess, ApprovalPolicyDecision, ApprovalPolicyRule, ApprovalRequestKind,
+};
+
+const RULES_DIR: &str = "rules";
+const DEFAULT_RULES_FILE: &str = "default.rules";
+
+pub fn default_rules_path(codex_home: &Path) -> PathBuf {
+    codex_home.join(RULES_DIR).join(DEFAULT_RULES_FILE)
+}
+
+pub fn append_prefix_rule(path: &Path, pattern: &[String]) -> Result<(), String> {
+    if pattern.is_empty() {
+        return Err("empty command pattern".to_string());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+
+    let _lock = acquire_rules_lock(path)?;
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    if rule_already_present(&existing, pattern) {
+        return Ok(());
+    }
+    let mut updated = existing;
+
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+
+    let rule = format_prefix_rule(pattern);
+    updated.push_str(&rule);
+
+    if !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+
+    fs::write(path, updated).map_err(|err| err.to_string())
+}
+
+struct RulesFileLock {
+    path: PathBuf,
+}
+
+impl Drop for RulesFileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Replaces the rules file with `contents` unless it changed since `expected` was read.
+pub fn replace_rules_file(
+    path: &Path,
+    expected: &str,
+    contents: &str,
+) -> Result<(), String> {
+    let _lock = acquire_rules_lock(path)?;
+    let current = fs::read_to_string(path).unwrap_or_default();
+    if current != expected {
+        return Err("rules file changed while it was being formatted".to_string());
+    }
+    fs::write(path, contents).map_err(|err| err.to_string())
+}
+
+fn acquire_rules_lock(path: &Path) -> Result<RulesFileLock, String> {
+    let lock_path = path.with_extension("lock");
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let stale_after = Duration::from_secs(30);
+
+    loop {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        {
+            Ok(_) => return Ok(RulesFileLock { path: lock_path }),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                if is_lock_stale(&lock_path, stale_after) {
+                    let _ = fs::remove_file(&lock_path);
+                    continue;
+                }
+                if Instant::now() >= deadline {
+                    return Err("timed out waiting for rules file lock".to_string());
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+}
+
+fn is_lock_stale(path: &Path, stale_after: Duration) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    let Ok(modified) = metadata.modified() else {
+        return false;
+    };
+    let Ok(age) = SystemTime::now().duration_since(modified) else {
+        return false;
+    };
+    age > stale_after
+}
+
+fn format_prefix_rule(pattern: &[String]) -> String {
+    let items = format_pattern_list(pattern);
+    format!("prefix_rule(\n    pattern = [{items}],\n    decision = \"allow\",\n)\n")
+}
+
+fn format_pattern_list(pattern: &[String]) -> String {
+    pattern
+        .iter()
+        .map(|item| format!("\"{}\"", escape_string(item)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn rule_already_present(contents: &str, pattern: &[String]) -> bool {
+    let target_pattern = normalize_rule_value(&format!("[{}]", format_pattern_list(pattern)));
+    let mut in_rule = false;
+    let mut pattern_matches = false;
+    let mut decision_allows = false;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("prefix_rule(") {
+            in_rule = true;
+            pattern_matches = false;
+            decision_allows = false;
+            continue;
+        }
+        if !in_rule {
+            continue;
+        }
+        if trimmed.starts_with("pattern") {
+            if let Some((_, value)) = trimmed.split_once('=') {
+                let candidate = value.trim().trim_end_matches(',');
+                if normalize_rule_value(candidate) == target_pattern {
+                    pattern_matches = true;
+                }
+            }
+        } else if trimmed.starts_with("decision") {
+            if let Some((_, value)) = trimmed.split_once('=') {
+                let candidate = value.trim().trim_end_matches(',');
+                if candidate.contains("\"allow\"") || candidate.contains("'allow'") {
+                    decision_allows = true;
+                }
+            }
+        } else if trimmed.starts_with(')') {
+            if pattern_matches && decision_allows {
+                return true;
+            }
+            in_rule = false;
+        }
+    }
+    false
+}
+
+fn normalize_rule_value(value: &str) -> String {
+    value.chars().filter(|ch| !ch.is_whitespace()).collect()
+}
+
+fn escape_string(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+const COMMAND_KEYS: &[&str] = &[
+    "argv",
+    "args",
+    "command",
+    "cmd",
+    "exec",
+    "shellCommand",
+    "script",
+];
+const SHELL_WRAPPERS: &[&str] = &["bash", "sh", "zsh"];
+const SHELL_CONTROL_FRAGMENTS: &[&str] = &[">", "<", "|", ";", "&", "$(", "`"];
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "cat", "head", "tail", "ls", "pwd", "wc", "grep", "rg", "stat", "file", "tree", "which",
+];
+const READ_ONLY_GIT_SUBCOMMANDS: &[&str] = &[
+    "status",
+    "diff",
+    "log",
+    "show",
+    "blame",
+    "rev-parse",
+    "ls-files",
+];
+
+/// What the approval policy looks at in an exec or patch approval request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRequestFacts {
+    pub kind: ApprovalRequestKind,
+    /// Command tokens, with `bash -lc "<script>"` wrappers unwrapped.
+    pub command: Vec<String>,
+    /// Touched paths, relative to the workspace root when inside it.
+    pub paths: Vec<String>,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalPolicyMatch {
+    pub rule_id: String,
+    pub decision: ApprovalPolicyDecision,
+}
+
+pub fn is_approval_request(message: &Value) -> bool {
+    message.get("id").is_some()
+        && message
+            .get("method")
+            .and_then(Value::as_str)
+            .is_some_and(|method| method.ends_with("Approval"))
+}
+
+pub fn approval_request_facts(
+    method: &str,
+    params: &Value,
+    workspace_root: &Path,
+) -> ApprovalRequestFacts {
+    let lower = method.to_ascii_lowercase();
+    let kind = if lower.contains("patch") || lower.contains("filechange") {
+        ApprovalRequestKind::Patch
+    } else if lower.contains("exec") || lower.contains("command") {
+        ApprovalRequestKind::Exec
+    } else {
+        ApprovalRequestKind::Any
+    };
+
+    let mut command = Vec::new();
+    let mut paths = Vec::new();
+    match kind {
+        ApprovalRequestKind::Exec => {
+            command = unwrap_shell(extract_command(params).unwrap_or_default());
+            if let Some(cwd) = params.get("cwd").and_then(Value::as_str) {
+                paths.push(cwd.to_string());
+            }
+        }
+        ApprovalRequestKind::Patch => {
+            for key in ["fileChanges", "changes"] {
+                match params.get(key) {
+                    Some(Value::Object(map)) => paths.extend(map.keys().cloned()),
+                    Some(Value::Array(items)) => paths.extend(
+                        items
+                            .iter()
+                            .filter_map(|item| item.get("path").and_then(Value::as_str))
+                            .map(str::to_string),
+                    ),
+                    _ => {}
+                }
+            }
+            if let Some(root) = params.get("grantRoot").and_then(Value::as_str) {
+                paths.push(root.to_string());
+            }
+        }
+        ApprovalRequestKind::Any => {}
+    }
+
+    let read_only = kind == ApprovalRequestKind::Exec && is_read_only_command(&command);
+    let paths = paths
+        .iter()
+        .map(|path| relative_to_root(path, workspace_root))
+        .collect();
+    ApprovalRequestFacts {
+        kind,
+        command,
+        paths,
+        read_only,
+    }
+}
+
+/// Deny rules win over allow rules; requests of an unknown kind are never decided. Patches
+/// touching a path in `protected_globs` are never auto-accepted, so they fall to a human.
+pub fn evaluate_approval_policy(
+    rules: &[ApprovalPolicyRule],
+    facts: &ApprovalRequestFacts,
+    protected_globs: &[String],
+) -> Option<ApprovalPolicyMatch> {
+    if facts.kind == ApprovalRequestKind::Any {
+        return None;
+    }
+    let decisions = if touches_protected_path(facts, protected_globs) {
+        &[ApprovalPolicyDecision::Deny][..]
+    } else {
+        &[ApprovalPolicyDecision::Deny, ApprovalPolicyDecision::Allow][..]
+    };
+    decisions.iter().copied().find_map(|decision| {
+        rules
+            .iter()
+            .filter(|rule| rule.decision == decision)
+            .find(|rule| rule_matches(rule, facts))
+            .map(|rule| ApprovalPolicyMatch {
+                rule_id: rule.id.clone(),
+                decision,
+            })
+    })
+}
+
+fn touches_protected_path(facts: &ApprovalRequestFacts, protected_globs: &[String]) -> bool {
+    facts.kind == ApprovalRequestKind::Patch
+        && facts.paths.iter().any(|path| {
+            protected_globs
+                .iter()
+                .any(|pattern| glob_matches(&expand_home(pattern.trim()), path))
+        })
+}
+
+fn rule_matches(rule: &ApprovalPolicyRule, facts: &ApprovalRequestFacts) -> bool {
+    if rule.kind != ApprovalRequestKind::Any && rule.kind != facts.kind {
+        return false;
+    }
+    if !rule.command_prefix.is_empty()
+        && (facts.command.len() < rule.command_prefix.len()
+            || facts.command[..rule.command_prefix.len()] != rule.command_prefix[..])
+    {
+        return false;
+    }
+    if !rule.path_globs.is_empty() {
+        let covered = |path: &String| {
+            rule.path_globs
+                .iter()
+                .any(|pattern| glob_matches(&expand_home(pattern), path))
+        };
+        // Allowing needs every path covered; denying needs just one.
+        let matched = match rule.decision {
+            ApprovalPolicyDecision::Allow => facts.paths.iter().all(covered),
+            ApprovalPolicyDecision::Deny => facts.paths.iter().any(covered),
+        };
+        if facts.paths.is_empty() || !matched {
+            return false;
+        }
+    }
+    match rule.access {
+        ApprovalAccess::Any => true,
+        ApprovalAccess::ReadOnly => facts.read_only,
+        ApprovalAccess::Write => !facts.read_only,
+    }
+}
+
+fn extract_command(params: &Value) -> Option<Vec<String>> {
+    match params {
+        Value::Array(items) => {
+            let tokens = items
+                .iter()
+                .map(|item| item.as_str().map(|value| value.trim().to_string()))
+                .collect::<Option<Vec<_>>>()?;
+            let tokens = tokens
+                .into_iter()
+                .filter(|token| !token.is_empty())
+                .collect::<Vec<_>>();
+            (!tokens.is_empty()).then_some(tokens)
+        }
+        Value::String(value) => {
+            let tokens = split_command_line(value);
+            (!tokens.is_empty()).then_some(tokens)
+        }
+        Value::Object(map) => COMMAND_KEYS
+            .iter()
+            .find_map(|key| map.get(*key).and_then(extract_command)),
+        _ => None,
+    }
+}
+
+fn unwrap_shell(command: Vec<String>) -> Vec<String> {
+    let is_wrapper = command.len() == 3
+        && SHELL_WRAPPERS.contains(&command_name(&command[0]))
+        && (command[1] == "-c" || command[1] == "-lc");
+    if is_wrapper {
+        split_command_line(&command[2])
+    } else {
+        command
+    }
+}
+
+fn command_name(token: &str) -> &str {
+    token.rsplit('/').next().unwrap_or(token)
+}
+
+fn is_read_only_command(command: &[String]) -> bool {
+    let Some(program) = command.first() else {
+        return false;
+    };
+    if command.iter().any(|token| {
+        SHELL_CONTROL_FRAGMENTS
+            .iter()
+            .any(|fragment| token.contains(fragment))
+    }) {
+        return false;
+    }
+    let program = command_name(program);
+    if program == "git" {
+        return command
+            .get(1)
+            .is_some_and(|subcommand| READ_ONLY_GIT_SUBCOMMANDS.contains(&subcommand.as_str()));
+    }
+    READ_ONLY_COMMANDS.contains(&program)
+}
+
+fn split_command_line(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for ch in input.chars() {
+        if escaped {
+            current.push(ch);
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if let Some(open) = quote {
+            if ch == open {
+                quote = None;
+            } else {
+                current.push(ch);
+            }
+        } else if ch == '"' || ch == '\'' {
+            quote = Some(ch);
+        } else if ch.is_whitespace() {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(ch);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn relative_to_root(path: &str, workspace_root: &Path) -> String {
+    let candidate = Path::new(path);
+    let relative = candidate
+        .strip_prefix(workspace_root)
+        .map(|relative| relative.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    let relative = relative.replace('\\', "/");
+    let relative = relative.trim_start_matches("./");
+    if relative.is_empty() {
+        ".".to_string()
+    } else {
+        relative.to_string()
+    }
+}
+
+pub fn expand_home(pattern: &str) -> String {
+    match (pattern.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{rest}", home.trim_end_matches('/')),
+        _ => pattern.to_string(),
+    }
+}
+
+/// Matches `/`-separated paths; `**` spans any number of segments.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    let path = path
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    segments_match(&pattern, &path)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((head, rest)) => match path.split_first() {
+            Some((segment, path_rest)) => {
+                segment_matches(head.as_bytes(), segment.as_bytes())
+                    && segments_match(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn segment_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| segment_matches(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && segment_matches(rest, &text[1..]),
+        Some((byte, rest)) => text.first() == Some(byte) && segment_matches(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(id: &str, decision: ApprovalPolicyDecision) -> ApprovalPolicyRule {
+        ApprovalPolicyRule {
+            id: id.to_string(),
+            decision,
+            kind: ApprovalRequestKind::Any,
+            command_prefix: Vec::new(),
+            path_globs: Vec::new(),
+            access: ApprovalAccess::Any,
+        }
+    }
+
+    #[test]
+    fn extracts_exec_and_patch_facts() {
+        let root = Path::new("/repo");
+        let exec = approval_request_facts(
+            "item/commandExecution/requestApproval",
+            &json!({ "command": ["bash", "-lc", "git status --short"], "cwd": "/repo/src" }),
+            root,
+        );
+        assert_eq!(exec.kind, ApprovalRequestKind::Exec);
+        assert_eq!(exec.command, vec!["git", "status", "--short"]);
+        assert_eq!(exec.paths, vec!["src"]);
+        assert!(exec.read_only);
+
+        let piped = approval_request_facts(
+            "execCommandApproval",
+            &json!({ "command": "cat a.txt > b.txt" }),
+            root,
+        );
+        assert!(!piped.read_only);
+
+        let patch = approval_request_facts(
+            "applyPatchApproval",
+            &json!({ "fileChanges": { "/repo/docs/a.md": {}, "/repo/.git/config": {} } }),
+            root,
+        );
+        assert_eq!(patch.kind, ApprovalRequestKind::Patch);
+        assert_eq!(patch.paths.len(), 2);
+    }
+
+    #[test]
+    fn deny_rules_win_and_all_criteria_must_match() {
+        let mut allow_reads = rule("allow-reads", ApprovalPolicyDecision::Allow);
+        allow_reads.access = ApprovalAccess::ReadOnly;
+        let mut allow_docs = rule("allow-docs", ApprovalPolicyDecision::Allow);
+        allow_docs.kind = ApprovalRequestKind::Patch;
+        allow_docs.path_globs = vec!["docs/**".to_string()];
+        let mut deny_git = rule("deny-git", ApprovalPolicyDecision::Deny);
+        deny_git.path_globs = vec!["**/.git/**".to_string()];
+        let mut deny_rm = rule("deny-rm", ApprovalPolicyDecision::Deny);
+        deny_rm.command_prefix = vec!["rm".to_string()];
+        let rules = vec![allow_reads, allow_docs, deny_git, deny_rm];
+        let root = Path::new("/repo");
+
+        let decide = |method: &str, params: Value| {
+            evaluate_approval_policy(&rules, &approval_request_facts(method, &params, root), &[])
+                .map(|matched| matched.rule_id)
+        };
+        assert_eq!(
+            decide("execCommandApproval", json!({ "command": ["ls", "-la"] })).as_deref(),
+            Some("allow-reads")
+        );
+        assert_eq!(
+            decide(
+                "execCommandApproval",
+                json!({ "command": ["rm", "-rf", "x"] })
+            )
+            .as_deref(),
+            Some("deny-rm")
+        );
+        assert_eq!(
+            decide(
+                "applyPatchApproval",
+                json!({ "fileChanges": { "docs/guide/a.md": {} } })
+            )
+            .as_deref(),
+            Some("allow-docs")
+        );
+        assert_eq!(
+            decide(
+                "applyPatchApproval",
+                json!({ "fileChanges": { "docs/a.md": {}, "src/.git/HEAD": {} } })
+            )
+            .as_deref(),
+            Some("deny-git")
+        );
+        assert_eq!(
+            decide(
+                "applyPatchApproval",
+                json!({ "fileChanges": { "src/lib.rs": {} } })
+            ),
+            None
+        );
+        assert_eq!(decide("item/tool/requestUserInput", json!({})), None);
+    }
+
+    #[test]
+    fn protected_paths_are_never_auto_accepted() {
+        let mut deny_keys = rule("deny-keys", ApprovalPolicyDecision::Deny);
+        deny_keys.path_globs = vec!["**/*.key".to_string()];
+        let rules = vec![rule("allow-all", ApprovalPolicyDecision::Allow), deny_keys];
+        let protected = crate::shared::protected_paths_core::DEFAULT_PROTECTED_PATH_GLOBS
+            .iter()
+            .map(|glob| glob.to_string())
+            .collect::<Vec<_>>();
+        let root = Path::new("/repo");
+        let decide = |params: Value| {
+            let facts = approval_request_facts("applyPatchApproval", &params, root);
+            evaluate_approval_policy(&rules, &facts, &protected).map(|matched| matched.rule_id)
+        };
+
+        assert_eq!(decide(json!({ "fileChanges": { "/repo/.env": {} } })), None);
+        assert_eq!(
+            decide(json!({ "fileChanges": { "src/main.rs": {}, "/repo/.git/HEAD": {} } })),
+            None
+        );
+        assert_eq!(
+            decide(json!({ "fileChanges": { "certs/server.key": {} } })).as_deref(),
+            Some("deny-keys")
+        );
+        assert_eq!(
+            decide(json!({ "fileChanges": { "src/main.rs": {} } })).as_deref(),
+            Some("allow-all")
+        );
+    }
+
+    #[test]
+    fn glob_matching_handles_double_star() {
+        assert!(glob_matches("**/.git/**", ".git/config"));
+        assert!(glob_matches("src/*.rs", "src/lib.rs"));
+        assert!(!glob_matches("src/*.rs", "src/nested/lib.rs"));
+        assert!(glob_matches("/home/me/.ssh/**", "/home/me/.ssh/id_ed25519"));
+        assert!(glob_matches("file-?.txt", "file-1.txt"));
+    }
+}
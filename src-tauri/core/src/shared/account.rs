@@ -4,12 +4,12 @@ use std::fs;
 use std::path::PathBuf;
 
 #[derive(Clone, Debug)]
-pub(crate) struct AuthAccount {
-    pub(crate) email: Option<String>,
-    pub(crate) plan_type: Option<String>,
+pub struct AuthAccount {
+    pub email: Option<String>,
+    pub plan_type: Option<String>,
 }
 
-pub(crate) fn build_account_response(response: Option<Value>, fallback: Option<AuthAccount>) -> Value {
+pub fn build_account_response(response: Option<Value>, fallback: Option<AuthAccount>) -> Value {
     let mut account = response
         .as_ref()
         .and_then(extract_account_map)
@@ -57,7 +57,7 @@ pub(crate) fn build_account_response(response: Option<Value>, fallback: Option<A
     Value::Object(result)
 }
 
-pub(crate) fn read_auth_account(codex_home: Option<PathBuf>) -> Option<AuthAccount> {
+pub fn read_auth_account(codex_home: Option<PathBuf>) -> Option<AuthAccount> {
     let codex_home = codex_home?;
     let auth_path = codex_home.join("auth.json");
     let data = fs::read(auth_path).ok()?;
@@ -128,7 +128,7 @@ fn extract_requires_openai_auth(value: &Value) -> Option<bool> {
         .and_then(|value| value.as_bool())
 }
 
-fn decode_jwt_payload(token: &str) -> Option<Value> {
+pub fn decode_jwt_payload(token: &str) -> Option<Value> {
     let payload = token.split('.').nth(1)?;
     let decoded = base64::engine::general_purpose::URL_SAFE_NO_PAD
         .decode(payload.as_bytes())
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codex::home::{
+    normalize_account_profile_home, resolve_account_profile_home, set_account_profile_homes,
+};
+use crate::shared::account::read_auth_account;
+use crate::shared::data_files_core::data_file_path;
+use crate::types::WorkspaceEntry;
+
+pub const ACCOUNT_PROFILES_FILE: &str = "account-profiles.json";
+
+const MAX_PROFILE_NAME_LEN: usize = 64;
+
+/// A named Codex home, e.g. "work" and "personal" for two signed-in accounts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProfile {
+    pub name: String,
+    /// As entered; `~` and environment variables are expanded when resolved.
+    pub codex_home: String,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProfileInfo {
+    #[serde(flatten)]
+    pub profile: AccountProfile,
+    pub resolved_home: Option<String>,
+    /// `auth.json` in the profile's home holds a ChatGPT sign-in.
+    pub signed_in: bool,
+    pub email: Option<String>,
+    pub plan_type: Option<String>,
+    /// Workspaces that select this profile.
+    pub workspace_ids: Vec<String>,
+}
+
+/// Profiles persisted in `<data-dir>/account-profiles.json`, keyed by name. Every
+/// change is mirrored into the table `resolve_workspace_codex_home` reads.
+pub struct AccountProfiles {
+    path: PathBuf,
+    entries: Mutex<BTreeMap<String, AccountProfile>>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn normalize_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name is required".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_LEN {
+        return Err(format!(
+            "Profile name is longer than {MAX_PROFILE_NAME_LEN} characters"
+        ));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("Profile name contains control characters".to_string());
+    }
+    Ok(name.to_string())
+}
+
+fn workspaces_using(workspaces: &HashMap<String, WorkspaceEntry>, name: &str) -> Vec<String> {
+    let mut ids = workspaces
+        .values()
+        .filter(|entry| entry.settings.account_profile.as_deref() == Some(name))
+        .map(|entry| entry.id.clone())
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+impl AccountProfiles {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_file_path(data_dir, ACCOUNT_PROFILES_FILE);
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let profiles = Self {
+            path,
+            entries: Mutex::new(entries),
+        };
+        profiles.publish(&profiles.lock());
+        profiles
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, AccountProfile>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn publish(&self, entries: &BTreeMap<String, AccountProfile>) {
+        set_account_profile_homes(
+            entries
+                .iter()
+                .map(|(name, profile)| (name.clone(), profile.codex_home.clone()))
+                .collect(),
+        );
+    }
+
+    fn persist(&self, entries: &BTreeMap<String, AccountProfile>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(entries).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, data).map_err(|err| err.to_string())?;
+        self.publish(entries);
+        Ok(())
+    }
+
+    /// Profiles sorted by name, with the account signed in under each.
+    pub fn list(
+        &self,
+        workspaces: &HashMap<String, WorkspaceEntry>,
+    ) -> Vec<AccountProfileInfo> {
+        self.lock()
+            .values()
+            .map(|profile| {
+                let resolved_home = resolve_account_profile_home(&profile.name);
+                let account = read_auth_account(resolved_home.clone());
+                AccountProfileInfo {
+                    resolved_home: resolved_home.map(|path| path.to_string_lossy().to_string()),
+                    signed_in: account.is_some(),
+                    email: account.as_ref().and_then(|account| account.email.clone()),
+                    plan_type: account.and_then(|account| account.plan_type),
+                    workspace_ids: workspaces_using(workspaces, &profile.name),
+                    profile: profile.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Creates the profile or points an existing one at another Codex home. Running
+    /// sessions keep their home until they reconnect.
+    pub fn save(&self, name: &str, codex_home: &str) -> Result<AccountProfile, String> {
+        let name = normalize_name(name)?;
+        let codex_home = codex_home.trim();
+        if normalize_account_profile_home(codex_home).is_none() {
+            return Err(format!(
+                "Codex home for profile {name} must be an absolute path: {codex_home}"
+            ));
+        }
+        let profile = AccountProfile {
+            name: name.clone(),
+            codex_home: codex_home.to_string(),
+            updated_at: now_millis(),
+        };
+        let mut entries = self.lock();
+        entries.insert(name, profile.clone());
+        self.persist(&entries)?;
+        Ok(profile)
+    }
+
+    /// Returns whether a profile was removed. Profiles still selected by a workspace
+    /// can't be deleted.
+    pub fn delete(
+        &self,
+        name: &str,
+        workspaces: &HashMap<String, WorkspaceEntry>,
+    ) -> Result<bool, String> {
+        let name = name.trim();
+        let in_use = workspaces_using(workspaces, name);
+        if !in_use.is_empty() {
+            return Err(format!(
+                "Account profile {name} is used by workspaces: {}",
+                in_use.join(", ")
+            ));
+        }
+        let mut entries = self.lock();
+        if entries.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.persist(&entries)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codex::home::resolve_workspace_codex_home;
+    use crate::types::{WorkspaceKind, WorkspaceSettings};
+    use uuid::Uuid;
+
+    #[test]
+    fn profiles_resolve_workspace_codex_homes() {
+        let data_dir =
+            std::env::temp_dir().join(format!("codex-monitor-profiles-{}", Uuid::new_v4()));
+        let profiles = AccountProfiles::load(&data_dir);
+        profiles.save("work", "/tmp/codex-work").expect("save");
+        assert!(profiles.save("relative", "codex-home").is_err());
+
+        let entry = WorkspaceEntry {
+            id: "ws-1".to_string(),
+            name: "repo".to_string(),
+            path: "/repo".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                account_profile: Some("work".to_string()),
+                ..WorkspaceSettings::default()
+            },
+        };
+        assert_eq!(
+            resolve_workspace_codex_home(&entry, None),
+            Some(PathBuf::from("/tmp/codex-work"))
+        );
+
+        let workspaces = HashMap::from([(entry.id.clone(), entry)]);
+        let reloaded = AccountProfiles::load(&data_dir);
+        let listed = reloaded.list(&workspaces);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].workspace_ids, vec!["ws-1".to_string()]);
+        assert!(!listed[0].signed_in);
+        assert!(reloaded.delete("work", &workspaces).is_err());
+        assert_eq!(reloaded.delete("work", &HashMap::new()), Ok(true));
+        assert_eq!(resolve_account_profile_home("work"), None);
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
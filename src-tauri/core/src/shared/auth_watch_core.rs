@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::backend::events::{AppServerEvent, EventSink};
+use crate::codex::home::{resolve_default_codex_home, resolve_workspace_codex_home};
+use crate::shared::account::decode_jwt_payload;
+use crate::types::WorkspaceEntry;
+
+/// How often the background watcher checks the sign-in of each connected workspace.
+pub const AUTH_CHECK_INTERVAL_SECS: u64 = 300;
+/// How far ahead of a token's expiry `auth-expiring` is emitted.
+const AUTH_EXPIRY_WARNING_MS: u64 = 30 * 60 * 1000;
+const ACCOUNT_READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What `auth.json` says about how long the sign-in lasts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthTokens {
+    /// `exp` of the access token (or id token), in ms.
+    pub expires_at: Option<u64>,
+    /// Codex renews the access token itself while it has a refresh token.
+    pub refreshable: bool,
+    /// Signed in with an API key, which doesn't expire.
+    pub api_key: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthAlertKind {
+    Expiring,
+    Expired,
+}
+
+impl AuthAlertKind {
+    fn event_method(self) -> &'static str {
+        match self {
+            AuthAlertKind::Expiring => "auth-expiring",
+            AuthAlertKind::Expired => "auth-expired",
+        }
+    }
+}
+
+/// Params of the `auth-expiring` and `auth-expired` events.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthAlert {
+    pub workspace_id: String,
+    pub codex_home: Option<String>,
+    pub expires_at: Option<u64>,
+    /// The app-server reported that it needs a new sign-in.
+    pub requires_login: bool,
+    /// The RPC that starts a new sign-in for this workspace.
+    pub login: Value,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn token_expiry_ms(token: Option<&str>) -> Option<u64> {
+    let payload = decode_jwt_payload(token?)?;
+    payload
+        .get("exp")
+        .and_then(Value::as_u64)
+        .map(|exp| exp.saturating_mul(1000))
+}
+
+pub fn parse_auth_tokens(auth: &Value) -> AuthTokens {
+    let api_key = auth
+        .get("OPENAI_API_KEY")
+        .and_then(Value::as_str)
+        .is_some_and(|key| !key.trim().is_empty());
+    let Some(tokens) = auth.get("tokens").filter(|tokens| tokens.is_object()) else {
+        return AuthTokens {
+            api_key,
+            ..AuthTokens::default()
+        };
+    };
+    let token = |snake: &str, camel: &str| {
+        tokens
+            .get(snake)
+            .or_else(|| tokens.get(camel))
+            .and_then(Value::as_str)
+            .filter(|value| !value.trim().is_empty())
+    };
+    AuthTokens {
+        expires_at: token_expiry_ms(token("access_token", "accessToken"))
+            .or_else(|| token_expiry_ms(token("id_token", "idToken"))),
+        refreshable: token("refresh_token", "refreshToken").is_some(),
+        api_key,
+    }
+}
+
+fn read_auth_tokens(codex_home: &Path) -> Option<AuthTokens> {
+    let data = std::fs::read(codex_home.join("auth.json")).ok()?;
+    let auth = serde_json::from_slice::<Value>(&data).ok()?;
+    Some(parse_auth_tokens(&auth))
+}
+
+/// Whether an `account/read` response says the app-server has no usable sign-in.
+fn requires_login(response: &Value) -> bool {
+    let result = response.get("result").unwrap_or(response);
+    result.get("requiresOpenaiAuth").and_then(Value::as_bool) == Some(true)
+        && result.get("account").is_none_or(Value::is_null)
+}
+
+/// An expired token only matters when Codex can't renew it; a refreshable token that
+/// has stopped working shows up through `account/read` instead. Without `auth.json`
+/// the workspace was never signed in, so there is nothing to expire.
+pub fn classify_auth(
+    tokens: Option<&AuthTokens>,
+    requires_login: bool,
+    now: u64,
+) -> Option<AuthAlertKind> {
+    let tokens = tokens?;
+    if requires_login {
+        return Some(AuthAlertKind::Expired);
+    }
+    if tokens.api_key || tokens.refreshable {
+        return None;
+    }
+    let expires_at = tokens.expires_at?;
+    if expires_at <= now {
+        Some(AuthAlertKind::Expired)
+    } else if expires_at <= now + AUTH_EXPIRY_WARNING_MS {
+        Some(AuthAlertKind::Expiring)
+    } else {
+        None
+    }
+}
+
+/// Remembers the last alert per workspace so each one is emitted once.
+#[derive(Default)]
+pub struct AuthWatcher {
+    alerts: Mutex<HashMap<String, AuthAlertKind>>,
+}
+
+impl AuthWatcher {
+    /// Checks `auth.json` and `account/read` for every connected workspace. Emits
+    /// `auth-expiring` ahead of an expiry Codex can't renew and `auth-expired` once the
+    /// sign-in is gone, each carrying the `codex_login` call that fixes it.
+    pub async fn check_once<E: EventSink>(
+        &self,
+        workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+        sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+        event_sink: &E,
+    ) {
+        let sessions = sessions
+            .lock()
+            .await
+            .iter()
+            .map(|(id, session)| (id.clone(), Arc::clone(session)))
+            .collect::<Vec<_>>();
+        self.alerts
+            .lock()
+            .await
+            .retain(|id, _| sessions.iter().any(|(session_id, _)| session_id == id));
+
+        for (workspace_id, session) in sessions {
+            let entry = session.entry();
+            let parent = match entry.parent_id.as_ref() {
+                Some(parent_id) => workspaces.lock().await.get(parent_id).cloned(),
+                None => None,
+            };
+            let codex_home = resolve_workspace_codex_home(&entry, parent.as_ref())
+                .or_else(resolve_default_codex_home);
+            let tokens = codex_home.as_deref().and_then(read_auth_tokens);
+            let response = tokio::time::timeout(
+                ACCOUNT_READ_TIMEOUT,
+                session.send_request("account/read", Value::Null),
+            )
+            .await;
+            let needs_login = matches!(&response, Ok(Ok(response)) if requires_login(response));
+            let kind = classify_auth(tokens.as_ref(), needs_login, now_millis());
+
+            let mut alerts = self.alerts.lock().await;
+            let previous = match kind {
+                Some(kind) => alerts.insert(workspace_id.clone(), kind),
+                None => alerts.remove(&workspace_id),
+            };
+            let Some(kind) = kind.filter(|kind| previous != Some(*kind)) else {
+                continue;
+            };
+            drop(alerts);
+            let alert = AuthAlert {
+                workspace_id: workspace_id.clone(),
+                codex_home: codex_home.map(|path| path.to_string_lossy().to_string()),
+                expires_at: tokens.and_then(|tokens| tokens.expires_at),
+                requires_login: needs_login,
+                login: json!({
+                    "method": "codex_login",
+                    "params": { "workspaceId": workspace_id },
+                }),
+            };
+            event_sink.emit_app_server_event(AppServerEvent {
+                workspace_id,
+                message: json!({
+                    "method": kind.event_method(),
+                    "params": alert,
+                }),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    fn jwt(claims: Value) -> String {
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&claims).expect("claims"));
+        format!("e30.{payload}.sig")
+    }
+
+    #[test]
+    fn classifies_auth_expiry() {
+        let now = 1_800_000_000_000;
+        let expiring = parse_auth_tokens(&json!({
+            "tokens": { "access_token": jwt(json!({ "exp": now / 1000 + 600 })) }
+        }));
+        assert_eq!(expiring.expires_at, Some(now + 600_000));
+        assert_eq!(
+            classify_auth(Some(&expiring), false, now),
+            Some(AuthAlertKind::Expiring)
+        );
+        assert_eq!(
+            classify_auth(Some(&expiring), false, now + 600_000),
+            Some(AuthAlertKind::Expired)
+        );
+
+        let refreshable = parse_auth_tokens(&json!({
+            "tokens": {
+                "access_token": jwt(json!({ "exp": now / 1000 - 60 })),
+                "refresh_token": "rt"
+            }
+        }));
+        assert_eq!(classify_auth(Some(&refreshable), false, now), None);
+        assert_eq!(
+            classify_auth(Some(&refreshable), true, now),
+            Some(AuthAlertKind::Expired)
+        );
+
+        let api_key = parse_auth_tokens(&json!({ "OPENAI_API_KEY": "sk-test" }));
+        assert_eq!(classify_auth(Some(&api_key), false, now), None);
+        assert_eq!(classify_auth(None, true, now), None);
+        assert!(requires_login(
+            &json!({ "account": null, "requiresOpenaiAuth": true })
+        ));
+        assert!(!requires_login(
+            &json!({ "account": null, "requiresOpenaiAuth": false })
+        ));
+    }
+}
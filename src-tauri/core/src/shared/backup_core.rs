@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::codex::home::resolve_default_codex_home;
+use crate::files::policy;
+use crate::rules;
+use crate::shared::data_files_core::{
+    current_data_file_name, DATA_DIRS, DATA_FILES, SETTINGS_FILE, WORKSPACES_FILE,
+};
+use crate::shared::i18n_core;
+use crate::shared::thread_branches_core::THREAD_BRANCHES_FILE;
+use crate::storage::{read_settings, read_thread_branches, read_workspaces};
+use crate::types::{AppSettings, ThreadBranchRecord, WorkspaceEntry};
+
+const BACKUP_ARCHIVE_PREFIX: &str = "codex-monitor-backup";
+const BACKUP_MANIFEST: &str = "manifest.json";
+const BACKUP_FORMAT_VERSION: u32 = 1;
+const SAFETY_BACKUPS_DIR: &str = "backups";
+const RULES_ENTRY: &str = "codex/rules/default.rules";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifest {
+    version: u32,
+    created_at: String,
+    entries: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupResult {
+    pub path: String,
+    pub created_at: String,
+    pub entries: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    pub restored: Vec<String>,
+    pub safety_backup: Option<String>,
+}
+
+/// Rules file included in backups; approval rules live in the default CODEX_HOME.
+pub fn default_backup_rules_path() -> Option<PathBuf> {
+    resolve_default_codex_home().map(|home| rules::default_rules_path(&home))
+}
+
+fn timestamp_label() -> (String, String) {
+    let now = chrono::Local::now();
+    (now.format("%Y%m%d-%H%M%S").to_string(), now.to_rfc3339())
+}
+
+fn collect_backup_entries(
+    data_dir: &Path,
+    rules_path: Option<&Path>,
+) -> Result<Vec<(String, PathBuf)>, String> {
+    let mut entries = Vec::new();
+    for name in DATA_FILES {
+        let path = data_dir.join(name);
+        if path.is_file() {
+            entries.push((name.to_string(), path));
+        }
+    }
+    for dir_name in DATA_DIRS {
+        let dir = data_dir.join(dir_name);
+        if !dir.is_dir() {
+            continue;
+        }
+        let read_dir =
+            std::fs::read_dir(&dir).map_err(|err| format!("Failed to read {dir_name}: {err}"))?;
+        let mut files = read_dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        files.sort();
+        for path in files {
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            entries.push((format!("{dir_name}/{file_name}"), path.clone()));
+        }
+    }
+    if let Some(rules_path) = rules_path.filter(|path| path.is_file()) {
+        entries.push((RULES_ENTRY.to_string(), rules_path.to_path_buf()));
+    }
+    Ok(entries)
+}
+
+fn write_archive(
+    archive_path: &Path,
+    created_at: &str,
+    entries: &[(String, PathBuf)],
+) -> Result<(), String> {
+    let file = File::create(archive_path)
+        .map_err(|err| format!("Failed to create backup archive: {err}"))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    let manifest = BackupManifest {
+        version: BACKUP_FORMAT_VERSION,
+        created_at: created_at.to_string(),
+        entries: entries.iter().map(|(name, _)| name.clone()).collect(),
+    };
+    let manifest_data = serde_json::to_vec_pretty(&manifest).map_err(|err| err.to_string())?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, BACKUP_MANIFEST, manifest_data.as_slice())
+        .map_err(|err| format!("Failed to write backup manifest: {err}"))?;
+
+    for (name, path) in entries {
+        builder
+            .append_path_with_name(path, name)
+            .map_err(|err| format!("Failed to add {name} to backup: {err}"))?;
+    }
+
+    let encoder = builder
+        .into_inner()
+        .map_err(|err| format!("Failed to finish backup archive: {err}"))?;
+    encoder
+        .finish()
+        .map_err(|err| format!("Failed to finish backup archive: {err}"))?;
+    Ok(())
+}
+
+pub fn backup_data_core(
+    data_dir: &Path,
+    destination: &Path,
+    rules_path: Option<&Path>,
+) -> Result<BackupResult, String> {
+    std::fs::create_dir_all(destination)
+        .map_err(|err| format!("Failed to create backup destination: {err}"))?;
+    let (label, created_at) = timestamp_label();
+    let mut archive_path = destination.join(format!("{BACKUP_ARCHIVE_PREFIX}-{label}.tar.gz"));
+    let mut suffix = 2;
+    while archive_path.exists() {
+        archive_path = destination.join(format!("{BACKUP_ARCHIVE_PREFIX}-{label}-{suffix}.tar.gz"));
+        suffix += 1;
+    }
+
+    let entries = collect_backup_entries(data_dir, rules_path)?;
+    if let Err(err) = write_archive(&archive_path, &created_at, &entries) {
+        let _ = std::fs::remove_file(&archive_path);
+        return Err(err);
+    }
+    Ok(BackupResult {
+        path: archive_path.to_string_lossy().to_string(),
+        created_at,
+        entries: entries.into_iter().map(|(name, _)| name).collect(),
+    })
+}
+
+/// Maps an archive entry name to its restore target, rejecting anything unexpected.
+fn restore_target(name: &str, data_dir: &Path, rules_path: Option<&Path>) -> Option<PathBuf> {
+    if name == RULES_ENTRY {
+        return rules_path.map(Path::to_path_buf);
+    }
+    let path = Path::new(name);
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    // Archives made before a data file was renamed restore it under its current name.
+    let current = current_data_file_name(name);
+    if DATA_FILES.contains(&current) {
+        return Some(data_dir.join(current));
+    }
+    let mut components = path.components();
+    let first = components.next()?.as_os_str().to_str()?;
+    let file_name = components.next()?;
+    if components.next().is_some() || !DATA_DIRS.contains(&first) {
+        return None;
+    }
+    Some(data_dir.join(first).join(file_name))
+}
+
+fn read_archive(archive_path: &Path) -> Result<(BackupManifest, HashMap<String, Vec<u8>>), String> {
+    let file = File::open(archive_path)
+        .map_err(|err| format!("Failed to open backup archive: {err}"))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut manifest: Option<BackupManifest> = None;
+    let mut files = HashMap::new();
+    let entries = archive
+        .entries()
+        .map_err(|err| format!("Failed to read backup archive: {err}"))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|err| format!("Failed to read backup archive: {err}"))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry
+            .path()
+            .map_err(|err| format!("Failed to read backup archive: {err}"))?
+            .to_string_lossy()
+            .to_string();
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|err| format!("Failed to read {name} from backup: {err}"))?;
+        if name == BACKUP_MANIFEST {
+            manifest = Some(
+                serde_json::from_slice(&data)
+                    .map_err(|err| format!("Invalid backup manifest: {err}"))?,
+            );
+        } else {
+            files.insert(name, data);
+        }
+    }
+    let manifest = manifest.ok_or_else(|| "Not a CodexMonitor backup archive.".to_string())?;
+    if manifest.version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "Backup format version {} is newer than supported version {BACKUP_FORMAT_VERSION}.",
+            manifest.version
+        ));
+    }
+    Ok((manifest, files))
+}
+
+/// Where a restore writes and the loaded state it replaces afterwards.
+pub struct RestoreTargets<'a> {
+    pub data_dir: &'a Path,
+    pub rules_path: Option<&'a Path>,
+    pub workspaces: &'a Mutex<HashMap<String, WorkspaceEntry>>,
+    pub app_settings: &'a Mutex<AppSettings>,
+    pub thread_branches: &'a Mutex<HashMap<String, ThreadBranchRecord>>,
+    pub storage_path: &'a Path,
+    pub settings_path: &'a Path,
+}
+
+/// Replaces the data dir files in the archive. Workspaces, settings and thread branches
+/// are reloaded in place; the other files take effect on the next start.
+pub async fn restore_data_core(
+    archive_path: &Path,
+    confirm: bool,
+    targets: RestoreTargets<'_>,
+) -> Result<RestoreResult, String> {
+    let RestoreTargets {
+        data_dir,
+        rules_path,
+        workspaces,
+        app_settings,
+        thread_branches,
+        storage_path,
+        settings_path,
+    } = targets;
+    let (manifest, files) = read_archive(archive_path)?;
+    if !confirm {
+        return Err(format!(
+            "Restoring replaces {} item(s) in the data dir; resend with `confirm: true` to continue.",
+            manifest.entries.len()
+        ));
+    }
+    if let Some(data) = files.get(WORKSPACES_FILE) {
+        serde_json::from_slice::<Vec<WorkspaceEntry>>(data)
+            .map_err(|err| format!("Backup contains invalid workspaces.json: {err}"))?;
+    }
+    if let Some(data) = files.get(SETTINGS_FILE) {
+        serde_json::from_slice::<AppSettings>(data)
+            .map_err(|err| format!("Backup contains invalid settings.json: {err}"))?;
+    }
+
+    let safety_backup = if collect_backup_entries(data_dir, rules_path)?.is_empty() {
+        None
+    } else {
+        Some(backup_data_core(data_dir, &data_dir.join(SAFETY_BACKUPS_DIR), rules_path)?.path)
+    };
+
+    let mut restored = Vec::new();
+    for name in &manifest.entries {
+        let Some(data) = files.get(name) else {
+            continue;
+        };
+        let Some(target) = restore_target(name, data_dir, rules_path) else {
+            continue;
+        };
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|err| format!("Failed to restore {name}: {err}"))?;
+        }
+        std::fs::write(&target, data).map_err(|err| format!("Failed to restore {name}: {err}"))?;
+        restored.push(name.clone());
+    }
+
+    let next_workspaces = read_workspaces(&storage_path.to_path_buf())?;
+    let next_settings = read_settings(&settings_path.to_path_buf())?;
+    let next_thread_branches = read_thread_branches(&data_dir.join(THREAD_BRANCHES_FILE))?;
+    *workspaces.lock().await = next_workspaces;
+    i18n_core::set_locale(&next_settings.locale);
+    policy::set_skip_dirs(&next_settings.skip_dirs);
+    *app_settings.lock().await = next_settings;
+    *thread_branches.lock().await = next_thread_branches;
+
+    Ok(RestoreResult {
+        restored,
+        safety_backup,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::data_files_core::FANOUT_RUNS_FILE;
+    use crate::shared::drafts_core::DRAFTS_FILE;
+    use crate::shared::workspaces_core::WORKTREE_SETUP_MARKERS_DIR;
+    use uuid::Uuid;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-{label}-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        dir
+    }
+
+    #[test]
+    fn restore_target_rejects_unknown_and_traversal_entries() {
+        let data_dir = PathBuf::from("/data");
+        assert_eq!(
+            restore_target("settings.json", &data_dir, None),
+            Some(data_dir.join("settings.json"))
+        );
+        assert_eq!(
+            restore_target("worktree-setup/ws.ran", &data_dir, None),
+            Some(data_dir.join("worktree-setup").join("ws.ran"))
+        );
+        assert_eq!(
+            restore_target("pinned_context.json", &data_dir, None),
+            Some(data_dir.join("pinned-context.json"))
+        );
+        assert_eq!(restore_target("../settings.json", &data_dir, None), None);
+        assert_eq!(restore_target("worktrees/ws/file", &data_dir, None), None);
+        assert_eq!(restore_target("other.json", &data_dir, None), None);
+        assert_eq!(restore_target(RULES_ENTRY, &data_dir, None), None);
+    }
+
+    #[test]
+    fn backup_and_restore_round_trip() {
+        let data_dir = temp_dir("backup-data");
+        let backups = temp_dir("backup-out");
+        let storage_path = data_dir.join("workspaces.json");
+        let settings_path = data_dir.join("settings.json");
+        std::fs::write(&storage_path, "[]").expect("write workspaces");
+        std::fs::write(&settings_path, "{}").expect("write settings");
+        std::fs::write(data_dir.join(DRAFTS_FILE), "[]").expect("write drafts");
+        std::fs::write(data_dir.join(FANOUT_RUNS_FILE), "[]").expect("write fan-out runs");
+        std::fs::write(data_dir.join("models_cache.json"), "{}").expect("write cache");
+        std::fs::create_dir_all(data_dir.join(WORKTREE_SETUP_MARKERS_DIR)).expect("markers");
+        std::fs::write(
+            data_dir.join(WORKTREE_SETUP_MARKERS_DIR).join("ws.ran"),
+            "ran_at=1\n",
+        )
+        .expect("write marker");
+
+        let backup = backup_data_core(&data_dir, &backups, None).expect("backup");
+        assert!(backup.path.ends_with(".tar.gz"));
+        assert_eq!(
+            backup.entries,
+            vec![
+                "workspaces.json",
+                "settings.json",
+                DRAFTS_FILE,
+                FANOUT_RUNS_FILE,
+                "worktree-setup/ws.ran"
+            ]
+        );
+
+        std::fs::remove_file(data_dir.join(WORKTREE_SETUP_MARKERS_DIR).join("ws.ran"))
+            .expect("remove marker");
+        std::fs::write(data_dir.join(DRAFTS_FILE), "[{}]").expect("change drafts");
+        let workspaces = Mutex::new(HashMap::new());
+        let app_settings = Mutex::new(AppSettings::default());
+        let thread_branches = Mutex::new(HashMap::new());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+
+        let targets = || RestoreTargets {
+            data_dir: &data_dir,
+            rules_path: None,
+            workspaces: &workspaces,
+            app_settings: &app_settings,
+            thread_branches: &thread_branches,
+            storage_path: &storage_path,
+            settings_path: &settings_path,
+        };
+
+        let unconfirmed =
+            runtime.block_on(restore_data_core(Path::new(&backup.path), false, targets()));
+        assert!(unconfirmed.is_err());
+        assert!(!data_dir
+            .join(WORKTREE_SETUP_MARKERS_DIR)
+            .join("ws.ran")
+            .exists());
+
+        let restored = runtime
+            .block_on(restore_data_core(Path::new(&backup.path), true, targets()))
+            .expect("restore");
+        assert_eq!(restored.restored.len(), 5);
+        assert!(restored.safety_backup.is_some());
+        assert!(data_dir
+            .join(WORKTREE_SETUP_MARKERS_DIR)
+            .join("ws.ran")
+            .exists());
+        assert_eq!(
+            std::fs::read_to_string(data_dir.join(DRAFTS_FILE)).expect("drafts"),
+            "[]"
+        );
+
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let _ = std::fs::remove_dir_all(&backups);
+    }
+}
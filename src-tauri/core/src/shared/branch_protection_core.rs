@@ -0,0 +1,105 @@
+use crate::rules::glob_matches;
+use crate::types::WorkspaceEntry;
+
+fn branch_name(branch: &str) -> &str {
+    let branch = branch.trim();
+    branch.strip_prefix("refs/heads/").unwrap_or(branch)
+}
+
+/// Trims the `protectedBranches` setting and drops blank or repeated patterns.
+pub fn normalize_branch_patterns(patterns: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for pattern in patterns {
+        let pattern = branch_name(&pattern).to_string();
+        if !pattern.is_empty() && !normalized.contains(&pattern) {
+            normalized.push(pattern);
+        }
+    }
+    normalized
+}
+
+/// Protected branch patterns that apply in `entry`: its own plus, for a worktree, its
+/// parent's.
+pub fn protected_branch_patterns(
+    entry: &WorkspaceEntry,
+    parent: Option<&WorkspaceEntry>,
+) -> Vec<String> {
+    let inherited = parent
+        .map(|parent| parent.settings.protected_branches.clone())
+        .unwrap_or_default();
+    normalize_branch_patterns(
+        inherited
+            .into_iter()
+            .chain(entry.settings.protected_branches.iter().cloned())
+            .collect(),
+    )
+}
+
+/// The first pattern protecting `branch`. Patterns are `/`-separated globs, so
+/// `release/*` covers `release/1.2` but not `release/1.2/hotfix`; `release/**` covers both.
+pub fn protected_branch_match(patterns: &[String], branch: &str) -> Option<String> {
+    let branch = branch_name(branch);
+    patterns
+        .iter()
+        .find(|pattern| glob_matches(branch_name(pattern), branch))
+        .cloned()
+}
+
+/// Refuses to `action` a protected branch unless `allow_protected` overrides it.
+pub fn ensure_branch_allowed(
+    patterns: &[String],
+    branch: &str,
+    allow_protected: bool,
+    action: &str,
+) -> Result<(), String> {
+    if allow_protected {
+        return Ok(());
+    }
+    match protected_branch_match(patterns, branch) {
+        Some(pattern) => Err(format!(
+            "Refusing to {action} protected branch `{}` (matches `{pattern}`); pass allowProtectedBranch to override.",
+            branch_name(branch)
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn matches_exact_names_and_globs() {
+        let protected = patterns(&["main", "release/*", "hotfix/**"]);
+        assert_eq!(
+            protected_branch_match(&protected, "refs/heads/main").as_deref(),
+            Some("main")
+        );
+        assert!(protected_branch_match(&protected, "main-next").is_none());
+        assert!(protected_branch_match(&protected, "release/1.2").is_some());
+        assert!(protected_branch_match(&protected, "release/1.2/rc").is_none());
+        assert!(protected_branch_match(&protected, "hotfix/a/b").is_some());
+    }
+
+    #[test]
+    fn override_flag_allows_protected_branches() {
+        let protected = patterns(&["main"]);
+        let error = ensure_branch_allowed(&protected, "main", false, "create a worktree on")
+            .expect_err("protected");
+        assert!(error.contains("`main`"));
+        assert!(ensure_branch_allowed(&protected, "main", true, "create a worktree on").is_ok());
+        assert!(ensure_branch_allowed(&protected, "feature", false, "rename").is_ok());
+    }
+
+    #[test]
+    fn normalizes_patterns() {
+        assert_eq!(
+            normalize_branch_patterns(patterns(&[" main ", "", "refs/heads/main", "release/*"])),
+            patterns(&["main", "release/*"])
+        );
+    }
+}
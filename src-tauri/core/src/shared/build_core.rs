@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::packages_core::{detect_packages, PackageEcosystem, WorkspacePackage};
+use crate::shared::problems_core::{sort_problems, Problem, ProblemSeverity, Problems};
+use crate::shared::process_core::tokio_command;
+use crate::shared::process_limits_core::{acquire_process_slot, ProcessKind};
+use crate::types::WorkspaceEntry;
+
+/// Cold builds of large crates take a while; anything slower is treated as stuck.
+const BUILD_TIMEOUT: Duration = Duration::from_secs(600);
+const MAX_BUILD_TARGETS: usize = 6;
+const OUTPUT_TAIL_LINES: usize = 40;
+/// `Problems` producer name for build results.
+pub const BUILD_PROBLEMS: &str = "build";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildTool {
+    Cargo,
+    Tsc,
+    Go,
+}
+
+impl BuildTool {
+    fn source(self) -> &'static str {
+        match self {
+            BuildTool::Cargo => "cargo",
+            BuildTool::Tsc => "tsc",
+            BuildTool::Go => "go vet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildTarget {
+    pub tool: BuildTool,
+    /// Workspace-relative ("" for the root).
+    pub cwd: String,
+    pub argv: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildRun {
+    pub tool: BuildTool,
+    pub command: String,
+    pub cwd: String,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub error_count: usize,
+    pub warning_count: usize,
+    /// Set when the command couldn't run or timed out.
+    pub error: Option<String>,
+    /// Last lines of output of a failed run, for failures no parser understood.
+    pub output_tail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildReport {
+    pub workspace_id: String,
+    pub success: bool,
+    pub runs: Vec<BuildRun>,
+    pub problems: Vec<Problem>,
+}
+
+fn is_nested(path: &str, others: &[&str]) -> bool {
+    others
+        .iter()
+        .any(|other| *other != path && (other.is_empty() || path.starts_with(&format!("{other}/"))))
+}
+
+/// Keeps only the outermost packages, so a Cargo workspace is checked once from its
+/// root rather than once per member.
+fn outermost<'a>(packages: &[&'a WorkspacePackage]) -> Vec<&'a WorkspacePackage> {
+    let paths = packages
+        .iter()
+        .map(|package| package.path.as_str())
+        .collect::<Vec<_>>();
+    packages
+        .iter()
+        .copied()
+        .filter(|package| !is_nested(&package.path, &paths))
+        .collect()
+}
+
+fn argv(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+fn tsc_argv(root: &Path, package: &WorkspacePackage) -> Vec<String> {
+    let dir = root.join(&package.path);
+    let mut command = if package.ecosystem == PackageEcosystem::Pnpm {
+        argv(&["pnpm", "exec", "tsc"])
+    } else if dir.join("yarn.lock").is_file() {
+        argv(&["yarn", "tsc"])
+    } else {
+        argv(&["npx", "--no-install", "tsc"])
+    };
+    command.extend(argv(&["--noEmit", "--pretty", "false"]));
+    command
+}
+
+/// The check commands for a workspace: `cargo check` for Cargo projects, `tsc
+/// --noEmit` for TypeScript packages with a `tsconfig.json`, and `go vet` for Go
+/// modules.
+pub fn build_targets(root: &Path) -> Vec<BuildTarget> {
+    let packages = detect_packages(root);
+    let of = |ecosystems: &[PackageEcosystem]| {
+        packages
+            .iter()
+            .filter(|package| ecosystems.contains(&package.ecosystem))
+            .collect::<Vec<_>>()
+    };
+    let mut targets = Vec::new();
+    for package in outermost(&of(&[PackageEcosystem::Cargo])) {
+        targets.push(BuildTarget {
+            tool: BuildTool::Cargo,
+            cwd: package.path.clone(),
+            argv: argv(&[
+                "cargo",
+                "check",
+                "--all-targets",
+                "--message-format=short",
+                "--color",
+                "never",
+            ]),
+        });
+    }
+    let typescript = of(&[PackageEcosystem::Npm, PackageEcosystem::Pnpm])
+        .into_iter()
+        .filter(|package| root.join(&package.path).join("tsconfig.json").is_file())
+        .collect::<Vec<_>>();
+    for package in outermost(&typescript) {
+        targets.push(BuildTarget {
+            tool: BuildTool::Tsc,
+            cwd: package.path.clone(),
+            argv: tsc_argv(root, package),
+        });
+    }
+    for package in outermost(&of(&[PackageEcosystem::Go])) {
+        targets.push(BuildTarget {
+            tool: BuildTool::Go,
+            cwd: package.path.clone(),
+            argv: argv(&["go", "vet", "./..."]),
+        });
+    }
+    targets.truncate(MAX_BUILD_TARGETS);
+    targets
+}
+
+/// Splits `path:line:col: rest` (the column is optional), skipping colons that can't
+/// start a location, such as a Windows drive letter.
+fn split_colon_location(line: &str) -> Option<(&str, usize, Option<usize>, &str)> {
+    for (index, _) in line.match_indices(':').filter(|(index, _)| *index > 0) {
+        let mut parts = line[index + 1..].splitn(3, ':');
+        let Some(line_number) = parts.next().and_then(|part| part.parse::<usize>().ok()) else {
+            continue;
+        };
+        let second = parts.next();
+        let (column, rest) = match second.and_then(|part| part.parse::<usize>().ok()) {
+            Some(column) => (Some(column), parts.next().unwrap_or("")),
+            None => {
+                let tail = line[index + 1..].split_once(':').map(|(_, tail)| tail);
+                (None, tail.unwrap_or(""))
+            }
+        };
+        return Some((&line[..index], line_number, column, rest.trim_start()));
+    }
+    None
+}
+
+/// Splits tsc's `path(line,col): rest`.
+fn split_paren_location(line: &str) -> Option<(&str, usize, Option<usize>, &str)> {
+    let close = line.find("): ")?;
+    let open = line[..close].rfind('(')?;
+    let (line_number, column) = line[open + 1..close].split_once(',')?;
+    Some((
+        &line[..open],
+        line_number.trim().parse().ok()?,
+        column.trim().parse().ok(),
+        &line[close + 3..],
+    ))
+}
+
+/// Reads `error[E0425]: msg`, `warning: msg` or `error TS2322: msg`.
+fn split_severity(rest: &str) -> Option<(ProblemSeverity, Option<String>, String)> {
+    let (head, message) = rest.split_once(": ")?;
+    let (severity, code) = match head.split_once(['[', ' ']) {
+        Some((severity, code)) => (severity, Some(code.trim_end_matches(']').to_string())),
+        None => (head, None),
+    };
+    let severity = match severity {
+        "error" => ProblemSeverity::Error,
+        "warning" => ProblemSeverity::Warning,
+        "note" | "info" => ProblemSeverity::Info,
+        _ => return None,
+    };
+    Some((
+        severity,
+        code.filter(|code| !code.is_empty()),
+        message.trim().to_string(),
+    ))
+}
+
+fn normalize_path(root: &Path, cwd: &str, path: &str) -> String {
+    let path = Path::new(path.trim());
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(cwd).join(path)
+    };
+    let mut parts: Vec<String> = Vec::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            _ => {}
+        }
+    }
+    let root_parts = root
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if parts.starts_with(&root_parts) {
+        parts.drain(..root_parts.len());
+    }
+    parts.join("/")
+}
+
+/// Parses the diagnostics of one tool's output into problems with workspace-relative
+/// paths. Summary lines without a location are skipped.
+pub fn parse_build_output(
+    tool: BuildTool,
+    root: &Path,
+    cwd: &str,
+    output: &str,
+) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = Vec::new();
+    for line in output.lines().map(str::trim_end) {
+        let parsed = match tool {
+            BuildTool::Cargo => {
+                split_colon_location(line).and_then(|(path, line, column, rest)| {
+                    let (severity, code, message) = split_severity(rest)?;
+                    Some((path, line, column, severity, code, message))
+                })
+            }
+            BuildTool::Tsc => split_paren_location(line).and_then(|(path, line, column, rest)| {
+                let (severity, code, message) = split_severity(rest)?;
+                Some((path, line, column, severity, code, message))
+            }),
+            BuildTool::Go => {
+                let line = line.strip_prefix("vet: ").unwrap_or(line);
+                split_colon_location(line)
+                    .filter(|(path, ..)| path.ends_with(".go"))
+                    .map(|(path, line, column, rest)| {
+                        (
+                            path,
+                            line,
+                            column,
+                            ProblemSeverity::Error,
+                            None,
+                            rest.to_string(),
+                        )
+                    })
+            }
+        };
+        let Some((path, line, column, severity, code, message)) = parsed else {
+            continue;
+        };
+        let problem = Problem {
+            source: tool.source().to_string(),
+            path: normalize_path(root, cwd, path),
+            line,
+            column,
+            severity,
+            code,
+            message,
+        };
+        // `--all-targets` reports a shared module once per target.
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+    problems
+}
+
+pub fn output_tail(output: &str) -> String {
+    let lines = output.lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+}
+
+async fn run_target(root: &Path, target: &BuildTarget) -> (BuildRun, Vec<Problem>) {
+    let started = Instant::now();
+    let command = target.argv.join(" ");
+    let mut run = BuildRun {
+        tool: target.tool,
+        command: command.clone(),
+        cwd: target.cwd.clone(),
+        exit_code: None,
+        success: false,
+        duration_ms: 0,
+        error_count: 0,
+        warning_count: 0,
+        error: None,
+        output_tail: None,
+    };
+    let output = match acquire_process_slot(ProcessKind::Tool).await {
+        Ok(_slot) => timeout(
+            BUILD_TIMEOUT,
+            tokio_command(&target.argv[0])
+                .args(&target.argv[1..])
+                .current_dir(root.join(&target.cwd))
+                .env("CARGO_TERM_COLOR", "never")
+                .env("NO_COLOR", "1")
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await
+        .map_err(|_| format!("{command} timed out"))
+        .and_then(|output| output.map_err(|err| format!("Failed to run {command}: {err}"))),
+        Err(err) => Err(err),
+    };
+    run.duration_ms = started.elapsed().as_millis() as u64;
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            run.error = Some(err);
+            return (run, Vec::new());
+        }
+    };
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let problems = parse_build_output(target.tool, root, &target.cwd, &text);
+    run.exit_code = output.status.code();
+    run.success = output.status.success();
+    run.error_count = problems
+        .iter()
+        .filter(|problem| problem.severity == ProblemSeverity::Error)
+        .count();
+    run.warning_count = problems
+        .iter()
+        .filter(|problem| problem.severity == ProblemSeverity::Warning)
+        .count();
+    if !run.success {
+        run.output_tail = Some(output_tail(text.trim()));
+    }
+    (run, problems)
+}
+
+/// Runs the workspace's check commands one after another and replaces its build
+/// problems with what they report. `permit` vets each argv before it is spawned.
+pub async fn run_build_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    problems: &Problems,
+    workspace_id: &str,
+    permit: impl Fn(&[String]) -> Result<(), String>,
+) -> Result<BuildReport, String> {
+    let root = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        PathBuf::from(&entry.path)
+    };
+    let target_root = root.clone();
+    let targets = tokio::task::spawn_blocking(move || build_targets(&target_root))
+        .await
+        .map_err(|err| format!("Build detection failed: {err}"))?;
+    if targets.is_empty() {
+        return Err("No build or typecheck command found for this workspace".to_string());
+    }
+
+    let mut runs = Vec::new();
+    let mut found = Vec::new();
+    for target in targets {
+        if let Err(err) = permit(&target.argv) {
+            runs.push(BuildRun {
+                tool: target.tool,
+                command: target.argv.join(" "),
+                cwd: target.cwd,
+                exit_code: None,
+                success: false,
+                duration_ms: 0,
+                error_count: 0,
+                warning_count: 0,
+                error: Some(err),
+                output_tail: None,
+            });
+            continue;
+        }
+        let (run, run_problems) = run_target(&root, &target).await;
+        runs.push(run);
+        found.extend(run_problems);
+    }
+    sort_problems(&mut found);
+    problems.replace(workspace_id, BUILD_PROBLEMS, found.clone());
+    Ok(BuildReport {
+        workspace_id: workspace_id.to_string(),
+        success: runs.iter().all(|run| run.success),
+        runs,
+        problems: found,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn parses_rustc_tsc_and_go_diagnostics() {
+        let root = Path::new("/repo");
+        let cargo = "    Checking demo v0.1.0 (/repo/app)\n\
+            src/main.rs:3:5: error[E0425]: cannot find value `x` in this scope\n\
+            src/lib.rs:10:9: warning: unused variable: `y`\n\
+            src/lib.rs:10:9: warning: unused variable: `y`\n\
+            warning: `demo` (lib) generated 1 warning\n\
+            error: could not compile `demo` (bin \"demo\") due to 1 previous error\n";
+        let problems = parse_build_output(BuildTool::Cargo, root, "app", cargo);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].path, "app/src/main.rs");
+        assert_eq!((problems[0].line, problems[0].column), (3, Some(5)));
+        assert_eq!(problems[0].code.as_deref(), Some("E0425"));
+        assert_eq!(problems[0].message, "cannot find value `x` in this scope");
+        assert_eq!(problems[1].severity, ProblemSeverity::Warning);
+        assert_eq!(problems[1].code, None);
+
+        let tsc =
+            "src/App.tsx(12,7): error TS2322: Type 'string' is not assignable to type 'number'.\n\
+            Found 1 error.\n";
+        let problems = parse_build_output(BuildTool::Tsc, root, "", tsc);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, "src/App.tsx");
+        assert_eq!((problems[0].line, problems[0].column), (12, Some(7)));
+        assert_eq!(problems[0].code.as_deref(), Some("TS2322"));
+
+        let go = "# example.com/demo\n\
+            vet: ./main.go:8:2: undefined: fmt.Printn\n\
+            ../shared/util.go:4: unreachable code\n";
+        let problems = parse_build_output(BuildTool::Go, root, "svc", go);
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| (problem.path.as_str(), problem.line, problem.column))
+                .collect::<Vec<_>>(),
+            vec![("svc/main.go", 8, Some(2)), ("shared/util.go", 4, None)]
+        );
+        assert_eq!(problems[0].message, "undefined: fmt.Printn");
+    }
+
+    #[test]
+    fn picks_outermost_check_targets() {
+        let root = std::env::temp_dir().join(format!("fridex-build-{}", Uuid::new_v4()));
+        let write = |path: &str, contents: &str| {
+            let target = root.join(path);
+            std::fs::create_dir_all(target.parent().expect("parent")).expect("create dirs");
+            std::fs::write(target, contents).expect("write file");
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"crates/a\"]\n");
+        write("crates/a/Cargo.toml", "[package]\nname = \"a\"\n");
+        write("web/package.json", r#"{ "name": "web" }"#);
+        write("web/tsconfig.json", "{}");
+        write("docs/package.json", r#"{ "name": "docs" }"#);
+
+        let targets = build_targets(&root);
+        assert_eq!(
+            targets
+                .iter()
+                .map(|target| (target.tool, target.cwd.as_str(), target.argv[0].as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (BuildTool::Cargo, "", "cargo"),
+                (BuildTool::Tsc, "web", "npx")
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
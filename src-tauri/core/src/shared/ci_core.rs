@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::backend::events::{AppServerEvent, EventSink};
+use crate::git_utils::{github_repo_from_path, resolve_git_root};
+use crate::shared::git_core::run_git_command;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::process_core::tokio_command;
+use crate::shared::process_limits_core::{acquire_process_slot, ProcessKind};
+use crate::types::{WorkspaceEntry, WorkspaceKind};
+
+/// How often the background poller checks pushed worktree branches.
+pub const CI_POLL_INTERVAL_SECS: u64 = 60;
+const PUSH_REMOTE: &str = "origin";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CiState {
+    Pending,
+    Success,
+    Failure,
+    /// No checks or statuses reported for the commit.
+    None,
+}
+
+impl CiState {
+    fn is_terminal(self) -> bool {
+        matches!(self, CiState::Success | CiState::Failure)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CiCheck {
+    pub name: String,
+    pub state: CiState,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CiStatus {
+    pub workspace_id: String,
+    pub branch: String,
+    pub repo: String,
+    /// Commit on `origin/<branch>` the checks belong to; `None` when the branch was
+    /// never fetched from origin and GitHub resolved the branch name itself.
+    pub sha: Option<String>,
+    pub state: CiState,
+    pub checks: Vec<CiCheck>,
+}
+
+fn check_run_state(status: &str, conclusion: Option<&str>) -> CiState {
+    if status != "completed" {
+        return CiState::Pending;
+    }
+    match conclusion {
+        Some("success") | Some("neutral") | Some("skipped") => CiState::Success,
+        Some(_) => CiState::Failure,
+        None => CiState::Pending,
+    }
+}
+
+fn commit_status_state(state: &str) -> CiState {
+    match state {
+        "success" => CiState::Success,
+        "failure" | "error" => CiState::Failure,
+        _ => CiState::Pending,
+    }
+}
+
+/// Red if anything failed, yellow while anything runs, green otherwise.
+fn aggregate_state(checks: &[CiCheck]) -> CiState {
+    if checks.is_empty() {
+        CiState::None
+    } else if checks.iter().any(|check| check.state == CiState::Failure) {
+        CiState::Failure
+    } else if checks.iter().any(|check| check.state == CiState::Pending) {
+        CiState::Pending
+    } else {
+        CiState::Success
+    }
+}
+
+fn parse_check_runs(value: &Value) -> Vec<CiCheck> {
+    value
+        .get("check_runs")
+        .and_then(Value::as_array)
+        .map(|runs| {
+            runs.iter()
+                .map(|run| CiCheck {
+                    name: run
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or("check")
+                        .to_string(),
+                    state: check_run_state(
+                        run.get("status").and_then(Value::as_str).unwrap_or(""),
+                        run.get("conclusion").and_then(Value::as_str),
+                    ),
+                    url: run
+                        .get("html_url")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_commit_statuses(value: &Value) -> Vec<CiCheck> {
+    value
+        .get("statuses")
+        .and_then(Value::as_array)
+        .map(|statuses| {
+            statuses
+                .iter()
+                .map(|status| CiCheck {
+                    name: status
+                        .get("context")
+                        .and_then(Value::as_str)
+                        .unwrap_or("status")
+                        .to_string(),
+                    state: commit_status_state(
+                        status.get("state").and_then(Value::as_str).unwrap_or(""),
+                    ),
+                    url: status
+                        .get("target_url")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn gh_api(
+    repo_root: &PathBuf,
+    endpoint: &str,
+    permit: &impl Fn(&[String]) -> Result<(), String>,
+) -> Result<Value, String> {
+    let argv = vec!["gh".to_string(), "api".to_string(), endpoint.to_string()];
+    permit(&argv)?;
+    let _slot = acquire_process_slot(ProcessKind::Tool).await?;
+    let output = tokio_command(&argv[0])
+        .args(&argv[1..])
+        .current_dir(repo_root)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run gh: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = stderr.trim();
+        if detail.is_empty() {
+            return Err("GitHub CLI command failed.".to_string());
+        }
+        return Err(detail.to_string());
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())
+}
+
+async fn pushed_sha(repo_root: &PathBuf, branch: &str) -> Option<String> {
+    let reference = format!("refs/remotes/{PUSH_REMOTE}/{branch}^{{commit}}");
+    run_git_command(repo_root, &["rev-parse", "--verify", "--quiet", &reference])
+        .await
+        .ok()
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+}
+
+async fn fetch_ci_status(
+    entry: &WorkspaceEntry,
+    branch: &str,
+    sha: Option<String>,
+    permit: &impl Fn(&[String]) -> Result<(), String>,
+) -> Result<CiStatus, String> {
+    let repo_root = resolve_git_root(entry)?;
+    let repo = github_repo_from_path(&repo_root)?;
+    let reference = sha.clone().unwrap_or_else(|| branch.to_string());
+    let check_runs = gh_api(
+        &repo_root,
+        &format!("repos/{repo}/commits/{reference}/check-runs?per_page=100"),
+        permit,
+    )
+    .await?;
+    let statuses = gh_api(
+        &repo_root,
+        &format!("repos/{repo}/commits/{reference}/status"),
+        permit,
+    )
+    .await?;
+    let mut checks = parse_check_runs(&check_runs);
+    checks.extend(parse_commit_statuses(&statuses));
+    Ok(CiStatus {
+        workspace_id: entry.id.clone(),
+        branch: branch.to_string(),
+        repo,
+        sha,
+        state: aggregate_state(&checks),
+        checks,
+    })
+}
+
+/// Combined GitHub check runs and commit statuses for `branch` of a workspace's repo.
+/// `permit` vets each `gh` argv before it is spawned.
+pub async fn ci_status_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    branch: &str,
+    permit: impl Fn(&[String]) -> Result<(), String>,
+) -> Result<CiStatus, String> {
+    let branch = branch.trim();
+    if branch.is_empty() {
+        return Err("branch is required".to_string());
+    }
+    let entry = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
+    };
+    let repo_root = resolve_git_root(&entry)?;
+    let sha = pushed_sha(&repo_root, branch).await;
+    fetch_ci_status(&entry, branch, sha, &permit).await
+}
+
+/// Tracks the last CI result per worktree so only transitions are announced.
+#[derive(Default)]
+pub struct CiPoller {
+    last: HashMap<String, (String, CiState)>,
+}
+
+impl CiPoller {
+    /// Whether a freshly observed result should be announced. The first sighting of a
+    /// worktree is only recorded, so restarts don't replay old results.
+    fn observe(&mut self, workspace_id: &str, sha: &str, state: CiState) -> bool {
+        let previous = self
+            .last
+            .insert(workspace_id.to_string(), (sha.to_string(), state));
+        match previous {
+            Some((previous_sha, previous_state)) => {
+                state.is_terminal() && (previous_sha != sha || previous_state != state)
+            }
+            None => false,
+        }
+    }
+
+    /// Checks every pushed worktree branch once and emits `ci/statusChanged` when one
+    /// turns green or red. Commits already known to be finished are not re-queried.
+    /// `permit` vets each `gh` argv, given the id of the worktree it is for.
+    pub async fn poll_once<E: EventSink>(
+        &mut self,
+        workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+        event_sink: &E,
+        permit: impl Fn(&str, &[String]) -> Result<(), String>,
+    ) {
+        let worktrees = {
+            let workspaces = workspaces.lock().await;
+            workspaces
+                .values()
+                .filter(|entry| matches!(entry.kind, WorkspaceKind::Worktree))
+                .filter_map(|entry| {
+                    let branch = entry.worktree.as_ref()?.branch.clone();
+                    Some((entry.clone(), branch))
+                })
+                .collect::<Vec<_>>()
+        };
+        self.last
+            .retain(|id, _| worktrees.iter().any(|(entry, _)| &entry.id == id));
+
+        for (entry, branch) in worktrees {
+            let Ok(repo_root) = resolve_git_root(&entry) else {
+                continue;
+            };
+            let Some(sha) = pushed_sha(&repo_root, &branch).await else {
+                continue;
+            };
+            if let Some((last_sha, last_state)) = self.last.get(&entry.id) {
+                if *last_sha == sha && last_state.is_terminal() {
+                    continue;
+                }
+            }
+            let permit = |argv: &[String]| permit(&entry.id, argv);
+            let Ok(status) = fetch_ci_status(&entry, &branch, Some(sha.clone()), &permit).await
+            else {
+                continue;
+            };
+            if self.observe(&entry.id, &sha, status.state) {
+                event_sink.emit_app_server_event(AppServerEvent {
+                    workspace_id: entry.id.clone(),
+                    message: json!({
+                        "method": "ci/statusChanged",
+                        "params": status,
+                    }),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_check_runs_and_statuses() {
+        let runs = json!({ "check_runs": [
+            { "name": "build", "status": "completed", "conclusion": "success", "html_url": "https://ci/1" },
+            { "name": "lint", "status": "in_progress", "conclusion": null }
+        ] });
+        let statuses = json!({ "statuses": [
+            { "context": "deploy", "state": "success", "target_url": null }
+        ] });
+        let mut checks = parse_check_runs(&runs);
+        checks.extend(parse_commit_statuses(&statuses));
+        assert_eq!(checks.len(), 3);
+        assert_eq!(aggregate_state(&checks), CiState::Pending);
+
+        checks[1].state = check_run_state("completed", Some("timed_out"));
+        assert_eq!(aggregate_state(&checks), CiState::Failure);
+        assert_eq!(aggregate_state(&[]), CiState::None);
+    }
+
+    #[test]
+    fn announces_only_transitions_to_green_or_red() {
+        let mut poller = CiPoller::default();
+        assert!(!poller.observe("wt-1", "abc", CiState::Success));
+        assert!(!poller.observe("wt-1", "abc", CiState::Success));
+        assert!(!poller.observe("wt-1", "def", CiState::Pending));
+        assert!(poller.observe("wt-1", "def", CiState::Failure));
+        assert!(poller.observe("wt-1", "0f1", CiState::Success));
+    }
+}
@@ -0,0 +1,960 @@
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, Mutex, oneshot};
+use tokio::sync::oneshot::error::TryRecvError;
+use tokio::time::timeout;
+use tokio::time::Instant;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::backend::events::{AppServerEvent, EventSink};
+use crate::codex::config as codex_config;
+use crate::codex::home::{
+    resolve_default_codex_home, resolve_home_dir, resolve_workspace_codex_home,
+};
+use crate::rules;
+use crate::shared::account::{build_account_response, read_auth_account};
+use crate::shared::collaboration_presets_core::CollaborationPresets;
+use crate::shared::documents_core;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::model_capabilities_core;
+use crate::shared::pinned_context_core::{self, PinnedContext};
+use crate::shared::protected_paths_core::filter_writable_roots;
+use crate::shared::slash_commands_core::{self, SlashResolution};
+use crate::shared::turn_snapshots_core::{self, TurnSnapshots};
+use crate::types::{AppSettings, SandboxMode, SandboxPreset, WorkspaceEntry};
+
+const LOGIN_START_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub enum CodexLoginCancelState {
+    PendingStart(oneshot::Sender<()>),
+    LoginId(String),
+}
+
+async fn get_session_clone(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: &str,
+) -> Result<Arc<WorkspaceSession>, String> {
+    let sessions = sessions.lock().await;
+    sessions
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotConnected))
+}
+
+pub async fn resolve_workspace_and_parent(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<(WorkspaceEntry, Option<WorkspaceEntry>), String> {
+    let workspaces = workspaces.lock().await;
+    let entry = workspaces
+        .get(workspace_id)
+        .cloned()
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    let parent_entry = entry
+        .parent_id
+        .as_ref()
+        .and_then(|parent_id| workspaces.get(parent_id))
+        .cloned();
+    Ok((entry, parent_entry))
+}
+
+pub async fn resolve_codex_home_for_workspace_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<PathBuf, String> {
+    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, workspace_id).await?;
+    resolve_workspace_codex_home(&entry, parent_entry.as_ref())
+        .or_else(resolve_default_codex_home)
+        .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())
+}
+
+/// Resolves a workspace-relative thread directory, refusing anything outside the workspace.
+pub fn resolve_thread_cwd(workspace_path: &str, cwd: Option<&str>) -> Result<String, String> {
+    let relative = cwd
+        .map(|value| value.trim().trim_matches('/'))
+        .filter(|value| !value.is_empty() && *value != ".");
+    let Some(relative) = relative else {
+        return Ok(workspace_path.to_string());
+    };
+    let root = PathBuf::from(workspace_path);
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let canonical = root
+        .join(relative)
+        .canonicalize()
+        .map_err(|_| format!("Thread directory not found: {relative}"))?;
+    let inside = canonical
+        .strip_prefix(&canonical_root)
+        .map_err(|_| format!("Thread directory must be inside the workspace: {relative}"))?;
+    if !canonical.is_dir() {
+        return Err(format!("Thread directory is not a folder: {relative}"));
+    }
+    Ok(root.join(inside).to_string_lossy().to_string())
+}
+
+pub async fn start_thread_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    cwd: Option<String>,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let cwd = resolve_thread_cwd(&session.workspace_path(), cwd.as_deref())?;
+    let params = json!({
+        "cwd": cwd,
+        "approvalPolicy": "on-request"
+    });
+    session.send_request("thread/start", params).await
+}
+
+pub async fn resume_thread_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/resume", params).await
+}
+
+pub async fn fork_thread_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/fork", params).await
+}
+
+pub async fn list_threads_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+    sort_key: Option<String>,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "cursor": cursor, "limit": limit, "sortKey": sort_key });
+    session.send_request("thread/list", params).await
+}
+
+pub async fn list_mcp_server_status_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "cursor": cursor, "limit": limit });
+    session.send_request("mcpServerStatus/list", params).await
+}
+
+/// Starts an OAuth sign-in for an MCP server; the response carries the authorization URL.
+pub async fn mcp_server_oauth_login_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    name: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    session
+        .send_request("mcpServer/oauth/login", json!({ "name": name }))
+        .await
+}
+
+pub async fn archive_thread_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/archive", params).await
+}
+
+pub async fn compact_thread_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id });
+    session.send_request("thread/compact/start", params).await
+}
+
+pub async fn set_thread_name_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    name: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "name": name });
+    session.send_request("thread/name/set", params).await
+}
+
+/// Builds the `sandboxPolicy` payload for a preset; `root_path` (the thread's directory) is
+/// always writable and anchors relative writable roots.
+pub fn sandbox_policy_for_preset(preset: &SandboxPreset, root_path: &str) -> Value {
+    match preset.mode {
+        SandboxMode::DangerFullAccess => json!({ "type": "dangerFullAccess" }),
+        SandboxMode::ReadOnly => json!({ "type": "readOnly" }),
+        SandboxMode::WorkspaceWrite => {
+            let root = PathBuf::from(root_path);
+            let mut writable_roots = vec![root_path.to_string()];
+            for entry in &preset.writable_roots {
+                let trimmed = entry.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                let resolved = match trimmed.strip_prefix("~/") {
+                    Some(rest) => resolve_home_dir()
+                        .map(|home| home.join(rest))
+                        .unwrap_or_else(|| PathBuf::from(trimmed)),
+                    None if PathBuf::from(trimmed).is_absolute() => PathBuf::from(trimmed),
+                    None => root.join(trimmed),
+                };
+                let resolved = resolved.to_string_lossy().to_string();
+                if !writable_roots.contains(&resolved) {
+                    writable_roots.push(resolved);
+                }
+            }
+            json!({
+                "type": "workspaceWrite",
+                "writableRoots": writable_roots,
+                "networkAccess": preset.network_access
+            })
+        }
+    }
+}
+
+/// One user turn: the message, its attachments and the per-turn overrides of the
+/// workspace defaults. Also the `send_user_message` RPC params.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SendUserMessageRequest {
+    pub workspace_id: String,
+    pub thread_id: String,
+    pub text: String,
+    pub model: Option<String>,
+    pub effort: Option<String>,
+    pub access_mode: Option<String>,
+    pub sandbox_preset: Option<String>,
+    pub cwd: Option<String>,
+    pub images: Option<Vec<String>>,
+    pub documents: Option<Vec<String>>,
+    pub collaboration_mode: Option<Value>,
+    pub collaboration_mode_preset: Option<String>,
+}
+
+pub async fn send_user_message_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    turn_snapshots: &TurnSnapshots,
+    collaboration_presets: &CollaborationPresets,
+    pinned_context: &PinnedContext,
+    request: SendUserMessageRequest,
+) -> Result<Value, String> {
+    let SendUserMessageRequest {
+        workspace_id,
+        thread_id,
+        text,
+        model,
+        effort,
+        access_mode,
+        sandbox_preset,
+        cwd,
+        images,
+        documents,
+        collaboration_mode,
+        collaboration_mode_preset,
+    } = request;
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let defaults = session.entry().settings;
+    let model = model.or(defaults.default_model);
+    let effort = effort.or(defaults.default_effort);
+    let access_mode = access_mode.or(defaults.default_access_mode);
+    let collaboration_mode = collaboration_presets.resolve(
+        collaboration_mode,
+        collaboration_mode_preset.as_deref(),
+        defaults.default_collaboration_preset.as_deref(),
+    )?;
+    let resolution = {
+        let settings = app_settings.lock().await;
+        slash_commands_core::resolve_slash_command(&text, &settings.slash_commands)?
+    };
+    let text = match resolution {
+        Some(SlashResolution::Prompt(prompt)) => prompt,
+        Some(SlashResolution::Rpc { method, params }) => {
+            return run_slash_rpc(sessions, workspace_id, thread_id, method, params).await;
+        }
+        None => text,
+    };
+    let cwd = resolve_thread_cwd(&session.workspace_path(), cwd.as_deref())?;
+    let (preset, protected, snapshot_before_turn, pinned_budget) = {
+        let settings = app_settings.lock().await;
+        let preset = match sandbox_preset.filter(|id| !id.trim().is_empty()) {
+            Some(id) => Some(
+                settings
+                    .sandbox_presets
+                    .iter()
+                    .find(|preset| preset.id == id)
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown sandbox preset: {id}"))?,
+            ),
+            None => None,
+        };
+        (
+            preset,
+            settings.protected_path_globs.clone(),
+            settings.snapshot_before_turns,
+            settings.pinned_context_budget_tokens,
+        )
+    };
+    let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
+    let mut sandbox_policy = match (&preset, access_mode.as_str()) {
+        (Some(preset), _) => sandbox_policy_for_preset(preset, &cwd),
+        (None, "full-access") => json!({ "type": "dangerFullAccess" }),
+        (None, "read-only") => json!({ "type": "readOnly" }),
+        (None, _) => json!({
+            "type": "workspaceWrite",
+            "writableRoots": [cwd],
+            "networkAccess": true
+        }),
+    };
+
+    if let Some(roots) = sandbox_policy
+        .get("writableRoots")
+        .and_then(Value::as_array)
+        .cloned()
+    {
+        let roots = roots
+            .into_iter()
+            .filter_map(|root| root.as_str().map(str::to_string))
+            .collect();
+        sandbox_policy["writableRoots"] = json!(filter_writable_roots(&protected, roots));
+    }
+
+    let full_access = match &preset {
+        Some(preset) => preset.mode == SandboxMode::DangerFullAccess,
+        None => access_mode == "full-access",
+    };
+    let approval_policy = if full_access { "never" } else { "on-request" };
+
+    let trimmed_text = text.trim();
+    let mut input: Vec<Value> = Vec::new();
+    if !trimmed_text.is_empty() {
+        input.push(json!({ "type": "text", "text": trimmed_text }));
+    }
+    if let Some(paths) = images {
+        for path in paths {
+            let trimmed = path.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if trimmed.starts_with("data:")
+                || trimmed.starts_with("http://")
+                || trimmed.starts_with("https://")
+            {
+                input.push(json!({ "type": "image", "url": trimmed }));
+            } else {
+                input.push(json!({ "type": "localImage", "path": trimmed }));
+            }
+        }
+    }
+    if let Some(paths) = documents {
+        let root = PathBuf::from(session.workspace_path());
+        for text in documents_core::document_inputs(root, paths).await? {
+            input.push(json!({ "type": "text", "text": text }));
+        }
+    }
+    if input.is_empty() {
+        return Err("empty user message".to_string());
+    }
+    let pins = pinned_context.list(&workspace_id, &thread_id);
+    if !pins.is_empty() {
+        let root = PathBuf::from(session.workspace_path());
+        let plan = pinned_context_core::plan_pins(
+            &root,
+            &pins,
+            trimmed_text,
+            model.as_deref(),
+            pinned_budget,
+        );
+        if let Some(pinned) = pinned_context_core::pinned_input_text(&plan) {
+            input.insert(0, json!({ "type": "text", "text": pinned }));
+        }
+    }
+
+    let mut params = Map::new();
+    params.insert("threadId".to_string(), json!(thread_id));
+    params.insert("input".to_string(), json!(input));
+    params.insert("cwd".to_string(), json!(cwd));
+    params.insert("approvalPolicy".to_string(), json!(approval_policy));
+    params.insert("sandboxPolicy".to_string(), json!(sandbox_policy));
+    params.insert("model".to_string(), json!(model));
+    params.insert("effort".to_string(), json!(effort));
+    if let Some(mode) = collaboration_mode {
+        if !mode.is_null() {
+            params.insert("collaborationMode".to_string(), mode);
+        }
+    }
+
+    // A failed snapshot never blocks the turn; it just can't be restored later.
+    let can_write = sandbox_policy.get("type").and_then(Value::as_str) != Some("readOnly");
+    let snapshot = if snapshot_before_turn && can_write {
+        turn_snapshots_core::capture_snapshot(&session.entry())
+            .await
+            .map_err(|err| tracing::warn!(workspace_id, "failed to snapshot workspace: {err}"))
+            .ok()
+    } else {
+        None
+    };
+    let response = session
+        .send_request("turn/start", Value::Object(params))
+        .await?;
+    if let (Some(snapshot), Some(turn_id)) = (
+        snapshot,
+        turn_snapshots_core::turn_id_from_response(&response),
+    ) {
+        if let Err(err) = turn_snapshots
+            .record(snapshot, &turn_id, &workspace_id, &thread_id)
+            .await
+        {
+            tracing::warn!(
+                workspace_id,
+                turn_id,
+                "failed to record turn snapshot: {err}"
+            );
+        }
+    }
+    Ok(response)
+}
+
+pub async fn collaboration_mode_list_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    session
+        .send_request("collaborationMode/list", json!({}))
+        .await
+}
+
+pub async fn turn_interrupt_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "threadId": thread_id, "turnId": turn_id });
+    session.send_request("turn/interrupt", params).await
+}
+
+/// Runs the backend method an `rpc` slash command maps to, on the message's thread.
+async fn run_slash_rpc(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    method: &str,
+    params: Value,
+) -> Result<Value, String> {
+    match method {
+        "start_review" => {
+            let target = params.get("target").cloned().unwrap_or(Value::Null);
+            start_review_core(sessions, workspace_id, thread_id, target, None).await
+        }
+        _ => Err(format!("Unsupported slash command method: {method}")),
+    }
+}
+
+pub async fn start_review_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    thread_id: String,
+    target: Value,
+    delivery: Option<String>,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let mut params = Map::new();
+    params.insert("threadId".to_string(), json!(thread_id));
+    params.insert("target".to_string(), target);
+    if let Some(delivery) = delivery {
+        params.insert("delivery".to_string(), json!(delivery));
+    }
+    session
+        .send_request("review/start", Value::Object(params))
+        .await
+}
+
+pub async fn model_list_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let response = session.send_request("model/list", json!({})).await?;
+    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, &workspace_id).await?;
+    let codex_home = resolve_workspace_codex_home(&entry, parent_entry.as_ref())
+        .or_else(resolve_default_codex_home);
+    let cache = model_capabilities_core::read_models_cache(codex_home.as_deref());
+    Ok(model_capabilities_core::enrich_model_list(response, &cache))
+}
+
+pub async fn account_rate_limits_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    session
+        .send_request("account/rateLimits/read", Value::Null)
+        .await
+}
+
+pub async fn account_read_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let session = {
+        let sessions = sessions.lock().await;
+        sessions.get(&workspace_id).cloned()
+    };
+    let response = if let Some(session) = session {
+        session.send_request("account/read", Value::Null).await.ok()
+    } else {
+        None
+    };
+
+    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, &workspace_id).await?;
+    let codex_home = resolve_workspace_codex_home(&entry, parent_entry.as_ref())
+        .or_else(resolve_default_codex_home);
+    let fallback = read_auth_account(codex_home);
+
+    Ok(build_account_response(response, fallback))
+}
+
+pub async fn codex_login_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    codex_login_cancels: &Mutex<HashMap<String, CodexLoginCancelState>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+    {
+        let mut cancels = codex_login_cancels.lock().await;
+        if let Some(existing) = cancels.remove(&workspace_id) {
+            match existing {
+                CodexLoginCancelState::PendingStart(tx) => {
+                    let _ = tx.send(());
+                }
+                CodexLoginCancelState::LoginId(_) => {}
+            }
+        }
+        cancels.insert(workspace_id.clone(), CodexLoginCancelState::PendingStart(cancel_tx));
+    }
+
+    let start = Instant::now();
+    let mut cancel_rx = cancel_rx;
+    let mut login_request: Pin<Box<_>> = Box::pin(
+        session.send_request("account/login/start", json!({ "type": "chatgpt" })),
+    );
+
+    let response = loop {
+        match cancel_rx.try_recv() {
+            Ok(_) => {
+                let mut cancels = codex_login_cancels.lock().await;
+                cancels.remove(&workspace_id);
+                return Err("Codex login canceled.".to_string());
+            }
+            Err(TryRecvError::Closed) => {
+                let mut cancels = codex_login_cancels.lock().await;
+                cancels.remove(&workspace_id);
+                return Err("Codex login canceled.".to_string());
+            }
+            Err(TryRecvError::Empty) => {}
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= LOGIN_START_TIMEOUT {
+            let mut cancels = codex_login_cancels.lock().await;
+            cancels.remove(&workspace_id);
+            return Err("Codex login start timed out.".to_string());
+        }
+
+        let tick = Duration::from_millis(150);
+        let remaining = LOGIN_START_TIMEOUT.saturating_sub(elapsed);
+        let wait_for = remaining.min(tick);
+
+        match timeout(wait_for, &mut login_request).await {
+            Ok(result) => break result?,
+            Err(_elapsed) => continue,
+        }
+    };
+
+    let payload = response.get("result").unwrap_or(&response);
+    let login_id = payload
+        .get("loginId")
+        .or_else(|| payload.get("login_id"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "missing login id in account/login/start response".to_string())?;
+    let auth_url = payload
+        .get("authUrl")
+        .or_else(|| payload.get("auth_url"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "missing auth url in account/login/start response".to_string())?;
+
+    {
+        let mut cancels = codex_login_cancels.lock().await;
+        cancels.insert(workspace_id, CodexLoginCancelState::LoginId(login_id.clone()));
+    }
+
+    Ok(json!({
+        "loginId": login_id,
+        "authUrl": auth_url,
+        "raw": response,
+    }))
+}
+
+pub async fn codex_login_cancel_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    codex_login_cancels: &Mutex<HashMap<String, CodexLoginCancelState>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let cancel_state = {
+        let mut cancels = codex_login_cancels.lock().await;
+        cancels.remove(&workspace_id)
+    };
+
+    let Some(cancel_state) = cancel_state else {
+        return Ok(json!({ "canceled": false }));
+    };
+
+    match cancel_state {
+        CodexLoginCancelState::PendingStart(cancel_tx) => {
+            let _ = cancel_tx.send(());
+            return Ok(json!({
+                "canceled": true,
+                "status": "canceled",
+            }));
+        }
+        CodexLoginCancelState::LoginId(login_id) => {
+            let session = get_session_clone(sessions, &workspace_id).await?;
+            let response = session
+                .send_request(
+                    "account/login/cancel",
+                    json!({
+                        "loginId": login_id,
+                    }),
+                )
+                .await?;
+
+            let payload = response.get("result").unwrap_or(&response);
+            let status = payload
+                .get("status")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let canceled = status.eq_ignore_ascii_case("canceled");
+
+            Ok(json!({
+                "canceled": canceled,
+                "status": status,
+                "raw": response,
+            }))
+        }
+    }
+}
+
+pub async fn skills_list_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "cwd": session.workspace_path() });
+    session.send_request("skills/list", params).await
+}
+
+/// `global` is the CODEX_HOME the workspace's session runs with; `workspace` is the
+/// project's own `.codex` directory, which Codex layers on top of it.
+async fn resolve_skills_config_root(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    scope: Option<&str>,
+) -> Result<(&'static str, PathBuf), String> {
+    match scope.map(str::trim).unwrap_or("workspace") {
+        "global" => {
+            let root = resolve_codex_home_for_workspace_core(workspaces, workspace_id).await?;
+            Ok(("global", root))
+        }
+        "workspace" => {
+            let (entry, _) = resolve_workspace_and_parent(workspaces, workspace_id).await?;
+            Ok(("workspace", PathBuf::from(&entry.path).join(".codex")))
+        }
+        other => Err(format!("Unknown skills config scope: {other}")),
+    }
+}
+
+pub async fn skills_config_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+    scope: Option<String>,
+) -> Result<Value, String> {
+    let (scope, root) =
+        resolve_skills_config_root(workspaces, &workspace_id, scope.as_deref()).await?;
+    let entries = codex_config::read_skill_configs(&root)?;
+    Ok(json!({
+        "scope": scope,
+        "configPath": root.join("config.toml").to_string_lossy(),
+        "entries": entries,
+    }))
+}
+
+/// Writes the skill's `enabled` flag, then has the running session re-scan its skills
+/// so the change applies without a restart. `skills` is the reloaded list, or null
+/// when the workspace isn't connected.
+pub async fn skills_set_enabled_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    path: String,
+    enabled: bool,
+    scope: Option<String>,
+) -> Result<Value, String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err("Skill path is required".to_string());
+    }
+    let (scope, root) =
+        resolve_skills_config_root(workspaces, &workspace_id, scope.as_deref()).await?;
+    codex_config::write_skill_enabled(&root, path, enabled)?;
+    let entries = codex_config::read_skill_configs(&root)?;
+    let skills = match get_session_clone(sessions, &workspace_id).await {
+        Ok(session) => {
+            let params = json!({ "cwd": session.workspace_path(), "forceReload": true });
+            Some(session.send_request("skills/list", params).await?)
+        }
+        Err(_) => None,
+    };
+    Ok(json!({
+        "scope": scope,
+        "configPath": root.join("config.toml").to_string_lossy(),
+        "entries": entries,
+        "skills": skills,
+    }))
+}
+
+pub async fn apps_list_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> Result<Value, String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    let params = json!({ "cursor": cursor, "limit": limit });
+    session.send_request("app/list", params).await
+}
+
+/// Runs `prompt` on a hidden, read-only helper thread and returns the assistant's text.
+/// The thread is archived afterwards and never shown in the sidebar.
+pub async fn run_background_prompt_core<E: EventSink>(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: &str,
+    prompt: String,
+    event_sink: &E,
+) -> Result<String, String> {
+    let session = get_session_clone(sessions, workspace_id).await?;
+
+    // Create a background thread
+    let thread_params = json!({
+        "cwd": session.workspace_path(),
+        "approvalPolicy": "never"  // Never ask for approval in background
+    });
+    let thread_result = session.send_request("thread/start", thread_params).await?;
+
+    // Handle error response
+    if let Some(error) = thread_result.get("error") {
+        let error_msg = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error starting thread");
+        return Err(error_msg.to_string());
+    }
+
+    // Extract threadId - try multiple paths since response format may vary
+    let thread_id = thread_result
+        .get("result")
+        .and_then(|r| r.get("threadId"))
+        .or_else(|| thread_result.get("result").and_then(|r| r.get("thread")).and_then(|t| t.get("id")))
+        .or_else(|| thread_result.get("threadId"))
+        .or_else(|| thread_result.get("thread").and_then(|t| t.get("id")))
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| format!("Failed to get threadId from thread/start response: {:?}", thread_result))?
+        .to_string();
+
+    // Hide background helper threads from the sidebar, even if a thread/started event leaked.
+    event_sink.emit_app_server_event(AppServerEvent {
+        workspace_id: workspace_id.to_string(),
+        message: json!({
+            "method": "codex/backgroundThread",
+            "params": {
+                "threadId": thread_id,
+                "action": "hide"
+            }
+        }),
+    });
+
+    // Create channel for receiving events
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+
+    // Register callback for this thread
+    {
+        let mut callbacks = session.background_thread_callbacks.lock().await;
+        callbacks.insert(thread_id.clone(), tx);
+    }
+
+    // Start a turn with the prompt
+    let turn_params = json!({
+        "threadId": thread_id,
+        "input": [{ "type": "text", "text": prompt }],
+        "cwd": session.workspace_path(),
+        "approvalPolicy": "never",
+        "sandboxPolicy": { "type": "readOnly" },
+    });
+    let turn_result = session.send_request("turn/start", turn_params).await;
+    let turn_result = match turn_result {
+        Ok(result) => result,
+        Err(error) => {
+            // Clean up if turn fails to start
+            {
+                let mut callbacks = session.background_thread_callbacks.lock().await;
+                callbacks.remove(&thread_id);
+            }
+            let archive_params = json!({ "threadId": thread_id.as_str() });
+            let _ = session.send_request("thread/archive", archive_params).await;
+            return Err(error);
+        }
+    };
+
+    if let Some(error) = turn_result.get("error") {
+        let error_msg = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error starting turn");
+        {
+            let mut callbacks = session.background_thread_callbacks.lock().await;
+            callbacks.remove(&thread_id);
+        }
+        let archive_params = json!({ "threadId": thread_id.as_str() });
+        let _ = session.send_request("thread/archive", archive_params).await;
+        return Err(error_msg.to_string());
+    }
+
+    // Collect assistant text from events
+    let mut output = String::new();
+    let timeout_duration = Duration::from_secs(60);
+    let collect_result = timeout(timeout_duration, async {
+        while let Some(event) = rx.recv().await {
+            let method = event.get("method").and_then(|m| m.as_str()).unwrap_or("");
+
+            match method {
+                "item/agentMessage/delta" => {
+                    // Extract text delta from agent messages
+                    if let Some(params) = event.get("params") {
+                        if let Some(delta) = params.get("delta").and_then(|d| d.as_str()) {
+                            output.push_str(delta);
+                        }
+                    }
+                }
+                "turn/completed" => {
+                    // Turn completed, we can stop listening
+                    break;
+                }
+                "turn/error" => {
+                    // Error occurred
+                    let error_msg = event
+                        .get("params")
+                        .and_then(|p| p.get("error"))
+                        .and_then(|e| e.as_str())
+                        .unwrap_or("Unknown error during background generation");
+                    return Err(error_msg.to_string());
+                }
+                _ => {
+                    // Ignore other events (turn/started, item/started, item/completed, reasoning events, etc.)
+                }
+            }
+        }
+        Ok(())
+    })
+    .await;
+
+    // Unregister callback
+    {
+        let mut callbacks = session.background_thread_callbacks.lock().await;
+        callbacks.remove(&thread_id);
+    }
+
+    // Archive the thread to clean up
+    let archive_params = json!({ "threadId": thread_id });
+    let _ = session.send_request("thread/archive", archive_params).await;
+
+    // Handle timeout or collection error
+    match collect_result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return Err(e),
+        Err(_) => return Err("Timeout waiting for background generation".to_string()),
+    }
+
+    Ok(output)
+}
+
+pub async fn respond_to_server_request_core(
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: String,
+    request_id: Value,
+    result: Value,
+) -> Result<(), String> {
+    let session = get_session_clone(sessions, &workspace_id).await?;
+    session.send_response(request_id, result).await
+}
+
+pub async fn remember_approval_rule_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+    command: Vec<String>,
+) -> Result<Value, String> {
+    let command = command
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect::<Vec<_>>();
+    if command.is_empty() {
+        return Err("empty command".to_string());
+    }
+
+    let codex_home = resolve_codex_home_for_workspace_core(workspaces, &workspace_id).await?;
+    let rules_path = rules::default_rules_path(&codex_home);
+    rules::append_prefix_rule(&rules_path, &command)?;
+
+    Ok(json!({
+        "ok": true,
+        "rulesPath": rules_path,
+    }))
+}
+
+pub async fn get_config_model_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: String,
+) -> Result<Value, String> {
+    let codex_home = resolve_codex_home_for_workspace_core(workspaces, &workspace_id).await?;
+    let model = codex_config::read_config_model(Some(codex_home))?;
+    Ok(json!({ "model": model }))
+}
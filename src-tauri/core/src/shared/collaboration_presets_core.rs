@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::shared::data_files_core::data_file_path;
+
+pub const COLLABORATION_PRESETS_FILE: &str = "collaboration-presets.json";
+
+const MAX_PRESET_NAME_LEN: usize = 64;
+
+/// A named `collaborationMode` object that `send_user_message` can reference by name.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CollaborationPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Sent as-is as the turn's `collaborationMode`.
+    pub collaboration_mode: Value,
+    pub updated_at: u64,
+}
+
+/// Presets persisted in `<data-dir>/collaboration-presets.json`, keyed by name.
+pub struct CollaborationPresets {
+    path: PathBuf,
+    entries: Mutex<BTreeMap<String, CollaborationPreset>>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn normalize_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset name is required".to_string());
+    }
+    if name.chars().count() > MAX_PRESET_NAME_LEN {
+        return Err(format!(
+            "Preset name is longer than {MAX_PRESET_NAME_LEN} characters"
+        ));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("Preset name contains control characters".to_string());
+    }
+    Ok(name.to_string())
+}
+
+impl CollaborationPresets {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_file_path(data_dir, COLLABORATION_PRESETS_FILE);
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, CollaborationPreset>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, entries: &BTreeMap<String, CollaborationPreset>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(entries).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, data).map_err(|err| err.to_string())
+    }
+
+    /// Presets sorted by name.
+    pub fn list(&self) -> Vec<CollaborationPreset> {
+        self.lock().values().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<CollaborationPreset> {
+        self.lock().get(name.trim()).cloned()
+    }
+
+    /// Creates the preset or replaces the one with the same name.
+    pub fn save(
+        &self,
+        name: &str,
+        description: Option<String>,
+        collaboration_mode: Value,
+    ) -> Result<CollaborationPreset, String> {
+        let name = normalize_name(name)?;
+        if !collaboration_mode.is_object() {
+            return Err("collaborationMode must be a JSON object".to_string());
+        }
+        let preset = CollaborationPreset {
+            name: name.clone(),
+            description: description
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+            collaboration_mode,
+            updated_at: now_millis(),
+        };
+        let mut entries = self.lock();
+        entries.insert(name, preset.clone());
+        self.persist(&entries)?;
+        Ok(preset)
+    }
+
+    /// Returns whether a preset was removed.
+    pub fn delete(&self, name: &str) -> Result<bool, String> {
+        let mut entries = self.lock();
+        if entries.remove(name.trim()).is_none() {
+            return Ok(false);
+        }
+        self.persist(&entries)?;
+        Ok(true)
+    }
+
+    /// Picks the `collaborationMode` for a turn. An explicit object wins, then the named
+    /// preset, then the workspace's default preset. Unknown explicit names are an error;
+    /// a default that was deleted since is skipped.
+    pub fn resolve(
+        &self,
+        collaboration_mode: Option<Value>,
+        preset: Option<&str>,
+        workspace_default: Option<&str>,
+    ) -> Result<Option<Value>, String> {
+        if let Some(mode) = collaboration_mode.filter(|mode| !mode.is_null()) {
+            return Ok(Some(mode));
+        }
+        if let Some(name) = preset.map(str::trim).filter(|name| !name.is_empty()) {
+            return self
+                .get(name)
+                .map(|preset| Some(preset.collaboration_mode))
+                .ok_or_else(|| format!("Unknown collaboration mode preset: {name}"));
+        }
+        Ok(workspace_default
+            .and_then(|name| self.get(name))
+            .map(|preset| preset.collaboration_mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn saves_resolves_and_deletes_presets() {
+        let data_dir =
+            std::env::temp_dir().join(format!("codex-monitor-presets-{}", Uuid::new_v4()));
+        let presets = CollaborationPresets::load(&data_dir);
+        let pairing = json!({ "mode": "pair", "settings": { "model": "gpt-5" } });
+        presets
+            .save(
+                " pairing ",
+                Some("Pair programming".to_string()),
+                pairing.clone(),
+            )
+            .expect("save");
+        assert!(presets.save("bad", None, json!("plan")).is_err());
+        assert!(presets.save("  ", None, json!({})).is_err());
+
+        let reloaded = CollaborationPresets::load(&data_dir);
+        assert_eq!(reloaded.list().len(), 1);
+        assert_eq!(
+            reloaded.resolve(None, Some("pairing"), None),
+            Ok(Some(pairing.clone()))
+        );
+        assert_eq!(
+            reloaded.resolve(Some(json!({ "mode": "plan" })), Some("pairing"), None),
+            Ok(Some(json!({ "mode": "plan" })))
+        );
+        assert_eq!(
+            reloaded.resolve(None, None, Some("pairing")),
+            Ok(Some(pairing))
+        );
+        assert!(reloaded.resolve(None, Some("missing"), None).is_err());
+        assert_eq!(reloaded.resolve(None, None, Some("missing")), Ok(None));
+
+        assert_eq!(reloaded.delete("pairing"), Ok(true));
+        assert_eq!(reloaded.delete("pairing"), Ok(false));
+        assert!(CollaborationPresets::load(&data_dir).list().is_empty());
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::backend::events::EventSink;
+use crate::git_utils::{collect_workspace_diff, resolve_git_root};
+use crate::shared::codex_core;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::types::WorkspaceEntry;
+
+/// Diffs beyond this many bytes are cut so huge changes still fit the model's context.
+const MAX_DIFF_BYTES: usize = 60_000;
+const CONVENTIONAL_TYPES: &[&str] = &[
+    "feat", "fix", "refactor", "docs", "test", "chore", "perf", "build", "ci", "style", "revert",
+];
+
+pub fn build_commit_message_prompt(diff: &str) -> String {
+    format!(
+        "Generate a concise git commit message for the following changes. \
+Follow conventional commit format (e.g., feat:, fix:, refactor:, docs:, etc.). \
+Keep the summary line under 72 characters. \
+Only output the commit message, nothing else.\n\n\
+Changes:\n{diff}"
+    )
+}
+
+fn truncate_diff(diff: &str) -> String {
+    if diff.len() <= MAX_DIFF_BYTES {
+        return diff.to_string();
+    }
+    let mut end = MAX_DIFF_BYTES;
+    while !diff.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!(
+        "{}\n\n[diff truncated: {} of {} bytes shown]",
+        &diff[..end],
+        end,
+        diff.len()
+    )
+}
+
+fn has_conventional_prefix(summary: &str) -> bool {
+    let Some((head, _)) = summary.split_once(": ") else {
+        return false;
+    };
+    let head = head.trim_end_matches('!');
+    let kind = head.split_once('(').map(|(kind, _)| kind).unwrap_or(head);
+    CONVENTIONAL_TYPES.contains(&kind)
+}
+
+/// Cleans model output into a message `git commit -m` can take as-is: code fences and
+/// wrapping quotes go, the summary gets a conventional type when it lacks one, and the
+/// body is separated from it by a blank line.
+pub fn normalize_commit_message(raw: &str) -> Option<String> {
+    let lines = raw
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>();
+    let text = lines.join("\n");
+    let mut lines = text.trim().lines();
+    let summary = lines
+        .next()?
+        .trim()
+        .trim_matches(['"', '`'])
+        .trim();
+    if summary.is_empty() {
+        return None;
+    }
+    let summary = if has_conventional_prefix(summary) {
+        summary.to_string()
+    } else {
+        format!("chore: {summary}")
+    };
+    let body = lines.collect::<Vec<_>>().join("\n");
+    let body = body.trim().trim_end_matches(['"', '`']).trim();
+    if body.is_empty() {
+        Some(summary)
+    } else {
+        Some(format!("{summary}\n\n{body}"))
+    }
+}
+
+/// Collects the diff server-side and asks the workspace's codex session for a
+/// conventional-commit message; see `collect_workspace_diff` for `staged`.
+pub async fn generate_commit_message_core<E: EventSink>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    workspace_id: &str,
+    staged: Option<bool>,
+    event_sink: &E,
+) -> Result<String, String> {
+    let entry = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
+    };
+    let repo_root = resolve_git_root(&entry)?;
+    let diff = tokio::task::spawn_blocking(move || collect_workspace_diff(&repo_root, staged))
+        .await
+        .map_err(|err| err.to_string())??;
+    if diff.trim().is_empty() {
+        return Err("No changes to generate commit message for".to_string());
+    }
+
+    let prompt = build_commit_message_prompt(&truncate_diff(&diff));
+    let output =
+        codex_core::run_background_prompt_core(sessions, workspace_id, prompt, event_sink).await?;
+    normalize_commit_message(&output).ok_or_else(|| "No commit message was generated".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_model_output() {
+        assert_eq!(
+            normalize_commit_message("```\nfeat(ui): add stats panel\n```").as_deref(),
+            Some("feat(ui): add stats panel")
+        );
+        assert_eq!(
+            normalize_commit_message("\"Update README\"\nExplain setup steps.").as_deref(),
+            Some("chore: Update README\n\nExplain setup steps.")
+        );
+        assert_eq!(
+            normalize_commit_message("fix!: drop legacy flag").as_deref(),
+            Some("fix!: drop legacy flag")
+        );
+        assert_eq!(normalize_commit_message("```\n```"), None);
+    }
+
+    #[test]
+    fn truncates_large_diffs_on_char_boundaries() {
+        let diff = "é".repeat(MAX_DIFF_BYTES);
+        let truncated = truncate_diff(&diff);
+        assert!(truncated.contains("[diff truncated:"));
+        assert!(truncated.len() < diff.len());
+        assert_eq!(truncate_diff("small"), "small");
+    }
+}
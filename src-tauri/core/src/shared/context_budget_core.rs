@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::shared::mentions_core::{self, MentionKind, MentionResolution, ResolvedMention};
+use crate::shared::model_capabilities_core;
+use crate::types::WorkspaceEntry;
+
+/// Used when the model is unknown or not given.
+const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+/// Share of the context window attachments may fill; the rest stays free for the
+/// conversation so far and the reply.
+const ATTACHMENT_SHARE_PERCENT: usize = 50;
+/// Below this much remaining budget an attachment is dropped instead of trimmed.
+const MIN_TRIMMED_TOKENS: usize = 200;
+/// Path header and code fence each attachment gets in the context block.
+const ATTACHMENT_OVERHEAD_TOKENS: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentStatus {
+    Included,
+    /// Cut at a line boundary to fit; `endLine` is where it now stops.
+    Trimmed,
+    Excluded,
+    Unresolved,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedAttachment {
+    pub raw: String,
+    pub kind: MentionKind,
+    pub path: Option<String>,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    /// Estimated cost of the whole resolved snippet.
+    pub estimated_tokens: usize,
+    /// Estimated cost of what made it into `contextBlock`.
+    pub included_tokens: usize,
+    pub status: AttachmentStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextPlan {
+    pub model: Option<String>,
+    pub context_window: usize,
+    pub budget_tokens: usize,
+    pub message_tokens: usize,
+    pub used_tokens: usize,
+    pub attachments: Vec<PlannedAttachment>,
+    pub context_block: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Letter,
+    Digit,
+    Space,
+    Newline,
+    Punct,
+    Other,
+}
+
+fn char_class(ch: char) -> CharClass {
+    match ch {
+        '\n' | '\r' => CharClass::Newline,
+        ' ' | '\t' => CharClass::Space,
+        _ if ch.is_ascii_alphabetic() => CharClass::Letter,
+        _ if ch.is_ascii_digit() => CharClass::Digit,
+        _ if ch.is_ascii() => CharClass::Punct,
+        _ => CharClass::Other,
+    }
+}
+
+/// Rough BPE-style token count, modeled on how tiktoken's cl100k/o200k pre-tokenizers
+/// split text: words of up to ~6 letters are one token, digits go in groups of three,
+/// a single space joins the next word, and non-ASCII characters cost a token each.
+/// Tends to err slightly high for code.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let class = char_class(ch);
+        let mut run: usize = 1;
+        if class != CharClass::Other {
+            while chars.peek().is_some_and(|next| char_class(*next) == class) {
+                chars.next();
+                run += 1;
+            }
+        }
+        tokens += match class {
+            CharClass::Letter => 1 + run / 6,
+            CharClass::Digit => run.div_ceil(3),
+            CharClass::Space if run == 1 => 0,
+            CharClass::Space | CharClass::Newline => 1,
+            CharClass::Punct => run.div_ceil(2),
+            CharClass::Other => 1,
+        };
+    }
+    tokens
+}
+
+pub fn context_window_for(model: Option<&str>) -> usize {
+    let Some(model) = model.map(|value| value.trim().to_lowercase()) else {
+        return DEFAULT_CONTEXT_WINDOW;
+    };
+    model_capabilities_core::bundled_context_window(&model)
+        .map(|window| window as usize)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// Keeps the leading lines of `snippet` that fit in `budget` tokens.
+fn trim_to_budget(snippet: &str, budget: usize) -> Option<(String, usize, usize)> {
+    let mut kept = Vec::new();
+    let mut tokens = 0;
+    for line in snippet.lines() {
+        let cost = estimate_tokens(line) + 1;
+        if tokens + cost > budget {
+            break;
+        }
+        tokens += cost;
+        kept.push(line);
+    }
+    (!kept.is_empty()).then(|| (kept.join("\n"), kept.len(), tokens))
+}
+
+/// Packs resolved mentions into the budget in the order they were written. An
+/// attachment that doesn't fit is trimmed when enough budget is left, else excluded.
+pub fn plan_context(
+    resolution: MentionResolution,
+    text: &str,
+    model: Option<&str>,
+    budget_tokens: Option<usize>,
+) -> ContextPlan {
+    let context_window = context_window_for(model);
+    let message_tokens = estimate_tokens(text);
+    let budget = budget_tokens.unwrap_or_else(|| {
+        (context_window * ATTACHMENT_SHARE_PERCENT / 100).saturating_sub(message_tokens)
+    });
+    let mut used = 0;
+    let mut kept: Vec<ResolvedMention> = Vec::new();
+    let mut attachments = Vec::new();
+    for mut mention in resolution.mentions {
+        let Some(snippet) = mention.snippet.clone() else {
+            attachments.push(PlannedAttachment {
+                raw: mention.raw,
+                kind: mention.kind,
+                path: mention.path,
+                start_line: None,
+                end_line: None,
+                estimated_tokens: 0,
+                included_tokens: 0,
+                status: AttachmentStatus::Unresolved,
+            });
+            continue;
+        };
+        let estimated = estimate_tokens(&snippet) + ATTACHMENT_OVERHEAD_TOKENS;
+        let remaining = budget.saturating_sub(used);
+        let (status, included) = if estimated <= remaining {
+            (AttachmentStatus::Included, estimated)
+        } else if remaining >= MIN_TRIMMED_TOKENS {
+            match trim_to_budget(&snippet, remaining - ATTACHMENT_OVERHEAD_TOKENS) {
+                Some((trimmed, lines, tokens)) => {
+                    let start = mention.start_line.unwrap_or(1);
+                    mention.end_line = Some(start + lines - 1);
+                    mention.snippet = Some(trimmed);
+                    mention.truncated = true;
+                    (
+                        AttachmentStatus::Trimmed,
+                        tokens + ATTACHMENT_OVERHEAD_TOKENS,
+                    )
+                }
+                None => (AttachmentStatus::Excluded, 0),
+            }
+        } else {
+            (AttachmentStatus::Excluded, 0)
+        };
+        used += included;
+        attachments.push(PlannedAttachment {
+            raw: mention.raw.clone(),
+            kind: mention.kind.clone(),
+            path: mention.path.clone(),
+            start_line: mention.start_line,
+            end_line: mention.end_line,
+            estimated_tokens: estimated,
+            included_tokens: included,
+            status,
+        });
+        if status != AttachmentStatus::Excluded {
+            kept.push(mention);
+        }
+    }
+    ContextPlan {
+        model: model.map(str::to_string),
+        context_window,
+        budget_tokens: budget,
+        message_tokens,
+        used_tokens: used,
+        attachments,
+        context_block: mentions_core::build_context_block(&kept),
+    }
+}
+
+pub async fn plan_context_core<F>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    text: &str,
+    model: Option<&str>,
+    budget_tokens: Option<usize>,
+    list_files: F,
+) -> Result<ContextPlan, String>
+where
+    F: Fn(&PathBuf, usize) -> Vec<String>,
+{
+    let resolution =
+        mentions_core::resolve_mentions_core(workspaces, workspace_id, text, list_files).await?;
+    Ok(plan_context(resolution, text, model, budget_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mention(raw: &str, lines: usize) -> ResolvedMention {
+        let snippet = (0..lines)
+            .map(|index| format!("let value_{index} = compute({index});"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        ResolvedMention {
+            raw: raw.to_string(),
+            kind: MentionKind::File,
+            path: Some(format!("src/{raw}")),
+            start_line: Some(1),
+            end_line: Some(lines),
+            snippet: Some(snippet),
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn estimates_tokens_like_bpe() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("hello world"), 2);
+        assert_eq!(estimate_tokens("1234567"), 3);
+        assert_eq!(estimate_tokens("fn main() {}"), 4);
+        assert_eq!(context_window_for(Some("gpt-5.1-codex")), 272_000);
+        assert_eq!(context_window_for(Some("mystery")), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn includes_trims_and_excludes_to_fit_budget() {
+        let resolution = MentionResolution {
+            mentions: vec![
+                mention("small.rs", 5),
+                mention("large.rs", 200),
+                mention("late.rs", 5),
+                ResolvedMention {
+                    raw: "missing".to_string(),
+                    kind: MentionKind::Unresolved,
+                    path: None,
+                    start_line: None,
+                    end_line: None,
+                    snippet: None,
+                    truncated: false,
+                },
+            ],
+            context_block: String::new(),
+        };
+
+        let plan = plan_context(resolution, "look at these", None, Some(600));
+        let statuses = plan
+            .attachments
+            .iter()
+            .map(|attachment| attachment.status)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                AttachmentStatus::Included,
+                AttachmentStatus::Trimmed,
+                AttachmentStatus::Excluded,
+                AttachmentStatus::Unresolved,
+            ]
+        );
+        assert!(plan.used_tokens <= plan.budget_tokens);
+        let trimmed = &plan.attachments[1];
+        assert!(trimmed.end_line.unwrap() < 200);
+        assert!(trimmed.included_tokens < trimmed.estimated_tokens);
+        assert!(plan.context_block.contains("src/small.rs (lines 1-5)"));
+        assert!(!plan.context_block.contains("src/late.rs"));
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use crate::shared::account_profiles_core::ACCOUNT_PROFILES_FILE;
+use crate::shared::collaboration_presets_core::COLLABORATION_PRESETS_FILE;
+use crate::shared::drafts_core::DRAFTS_FILE;
+use crate::shared::pinned_context_core::PINNED_CONTEXT_FILE;
+use crate::shared::thread_branches_core::THREAD_BRANCHES_FILE;
+use crate::shared::thread_usage_core::THREAD_USAGE_FILE;
+use crate::shared::time_tracking_core::TIME_TRACKING_FILE;
+use crate::shared::turn_snapshots_core::TURN_SNAPSHOTS_FILE;
+use crate::shared::workspaces_core::WORKTREE_SETUP_MARKERS_DIR;
+
+pub const WORKSPACES_FILE: &str = "workspaces.json";
+pub const SETTINGS_FILE: &str = "settings.json";
+/// Daemon only: fan-out runs and their branches.
+pub const FANOUT_RUNS_FILE: &str = "fanout-runs.json";
+/// Daemon only: server requests still waiting for an answer.
+pub const PENDING_APPROVALS_FILE: &str = "pending-approvals.json";
+
+/// Every top-level data dir file that holds user state. Backups capture and restore
+/// exactly these; a feature that persists state in the data dir adds its file here.
+/// Caches that rebuild themselves (thumbnails, search indexes, the models cache) are
+/// left out.
+pub const DATA_FILES: &[&str] = &[
+    WORKSPACES_FILE,
+    SETTINGS_FILE,
+    THREAD_BRANCHES_FILE,
+    DRAFTS_FILE,
+    ACCOUNT_PROFILES_FILE,
+    COLLABORATION_PRESETS_FILE,
+    PINNED_CONTEXT_FILE,
+    THREAD_USAGE_FILE,
+    TIME_TRACKING_FILE,
+    TURN_SNAPSHOTS_FILE,
+    FANOUT_RUNS_FILE,
+    PENDING_APPROVALS_FILE,
+];
+
+/// Data dir folders of small metadata files that backups capture (never worktrees).
+pub const DATA_DIRS: &[&str] = &[WORKTREE_SETUP_MARKERS_DIR];
+
+/// Data files that were renamed to kebab-case, as `(current, legacy)` names.
+const RENAMED_DATA_FILES: &[(&str, &str)] = &[
+    (ACCOUNT_PROFILES_FILE, "account_profiles.json"),
+    (COLLABORATION_PRESETS_FILE, "collaboration_presets.json"),
+    (PINNED_CONTEXT_FILE, "pinned_context.json"),
+];
+
+/// The current name of a data file, given the name it has now or had before a rename.
+pub fn current_data_file_name(name: &str) -> &str {
+    RENAMED_DATA_FILES
+        .iter()
+        .find(|(_, legacy)| *legacy == name)
+        .map_or(name, |(current, _)| current)
+}
+
+/// Where data file `name` lives in `data_dir`. A file still under its legacy name is
+/// moved to the current one first, so loads, saves and backups all see the same file.
+pub fn data_file_path(data_dir: &Path, name: &str) -> PathBuf {
+    let path = data_dir.join(name);
+    let Some((_, legacy)) = RENAMED_DATA_FILES
+        .iter()
+        .find(|(current, _)| *current == name)
+    else {
+        return path;
+    };
+    let legacy = data_dir.join(legacy);
+    if path.exists() || !legacy.is_file() {
+        return path;
+    }
+    match std::fs::rename(&legacy, &path) {
+        Ok(()) => path,
+        Err(err) => {
+            tracing::warn!(path = %legacy.display(), "failed to rename data file: {err}");
+            legacy
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    #[test]
+    fn data_files_are_listed_once() {
+        let unique = DATA_FILES.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), DATA_FILES.len());
+        assert!(DATA_FILES.iter().all(|name| !DATA_DIRS.contains(name)));
+        assert!(RENAMED_DATA_FILES
+            .iter()
+            .all(|(current, legacy)| DATA_FILES.contains(current) && !DATA_FILES.contains(legacy)));
+    }
+
+    #[test]
+    fn moves_legacy_file_names_to_the_current_ones() {
+        let data_dir =
+            std::env::temp_dir().join(format!("codex-monitor-data-files-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).expect("create data dir");
+        std::fs::write(data_dir.join("pinned_context.json"), "{}").expect("write legacy");
+
+        let path = data_file_path(&data_dir, PINNED_CONTEXT_FILE);
+        assert_eq!(path, data_dir.join("pinned-context.json"));
+        assert_eq!(std::fs::read_to_string(&path).expect("moved"), "{}");
+        assert!(!data_dir.join("pinned_context.json").exists());
+        assert_eq!(
+            data_file_path(&data_dir, ACCOUNT_PROFILES_FILE),
+            data_dir.join("account-profiles.json")
+        );
+        assert_eq!(
+            current_data_file_name("collaboration_presets.json"),
+            COLLABORATION_PRESETS_FILE
+        );
+        assert_eq!(current_data_file_name(DRAFTS_FILE), DRAFTS_FILE);
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
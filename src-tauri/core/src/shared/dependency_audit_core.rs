@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::packages_core::{detect_packages, PackageEcosystem};
+use crate::shared::process_core::tokio_command;
+use crate::shared::process_limits_core::{acquire_process_slot, ProcessKind};
+use crate::types::WorkspaceEntry;
+
+/// Audits fetch advisory databases, so a cold run can take a while.
+const AUDIT_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditEcosystem {
+    Cargo,
+    Npm,
+    Pnpm,
+    Python,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSeverity {
+    /// The tool reports no severity (cargo audit and pip-audit only carry advisory ids).
+    Unknown,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl AuditSeverity {
+    fn parse(value: Option<&str>) -> Self {
+        match value.map(str::to_ascii_lowercase).as_deref() {
+            Some("critical") => AuditSeverity::Critical,
+            Some("high") => AuditSeverity::High,
+            Some("moderate") | Some("medium") => AuditSeverity::Moderate,
+            Some("low") | Some("info") => AuditSeverity::Low,
+            _ => AuditSeverity::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFinding {
+    pub ecosystem: AuditEcosystem,
+    /// Workspace-relative directory the audit ran in ("" for the root).
+    pub path: String,
+    pub package: String,
+    /// Installed version, or the affected range when the tool doesn't report one (npm).
+    pub version: Option<String>,
+    pub advisory_id: String,
+    pub title: Option<String>,
+    pub severity: AuditSeverity,
+    pub url: Option<String>,
+    pub fixed_in: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRun {
+    pub ecosystem: AuditEcosystem,
+    pub path: String,
+    pub tool: String,
+    /// Set when the tool is missing, timed out or printed something we couldn't parse.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyAudit {
+    pub workspace_id: String,
+    pub runs: Vec<AuditRun>,
+    /// Most severe first.
+    pub findings: Vec<AuditFinding>,
+}
+
+struct AuditTarget {
+    ecosystem: AuditEcosystem,
+    path: String,
+    program: &'static str,
+    args: Vec<String>,
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `cargo audit --json`: `vulnerabilities.list[].{advisory, package, versions}`.
+fn parse_cargo_audit(path: &str, report: &Value) -> Vec<AuditFinding> {
+    let Some(list) = report
+        .pointer("/vulnerabilities/list")
+        .and_then(Value::as_array)
+    else {
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|entry| {
+            let advisory = entry.get("advisory")?;
+            let package = entry.get("package")?;
+            Some(AuditFinding {
+                ecosystem: AuditEcosystem::Cargo,
+                path: path.to_string(),
+                package: string_field(package, "name")?,
+                version: string_field(package, "version"),
+                advisory_id: string_field(advisory, "id")?,
+                title: string_field(advisory, "title"),
+                severity: AuditSeverity::parse(advisory.get("severity").and_then(Value::as_str)),
+                url: string_field(advisory, "url"),
+                fixed_in: string_list(entry.pointer("/versions/patched")),
+            })
+        })
+        .collect()
+}
+
+/// `npm audit --json` (npm 7+): `vulnerabilities.<name>.via[]`, where advisory entries are
+/// objects and strings only point at other vulnerable packages.
+fn parse_npm_audit(path: &str, report: &Value) -> Vec<AuditFinding> {
+    let Some(vulnerabilities) = report.get("vulnerabilities").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    for (name, vulnerability) in vulnerabilities {
+        let fixed_in = vulnerability
+            .get("fixAvailable")
+            .and_then(|fix| fix.get("version"))
+            .and_then(Value::as_str)
+            .map(|version| vec![version.to_string()])
+            .unwrap_or_default();
+        let Some(via) = vulnerability.get("via").and_then(Value::as_array) else {
+            continue;
+        };
+        for advisory in via.iter().filter(|advisory| advisory.is_object()) {
+            let Some(advisory_id) = advisory
+                .get("source")
+                .map(|source| source.to_string().trim_matches('"').to_string())
+                .or_else(|| string_field(advisory, "url"))
+            else {
+                continue;
+            };
+            findings.push(AuditFinding {
+                ecosystem: AuditEcosystem::Npm,
+                path: path.to_string(),
+                package: string_field(advisory, "name").unwrap_or_else(|| name.clone()),
+                version: string_field(advisory, "range"),
+                advisory_id,
+                title: string_field(advisory, "title"),
+                severity: AuditSeverity::parse(advisory.get("severity").and_then(Value::as_str)),
+                url: string_field(advisory, "url"),
+                fixed_in: fixed_in.clone(),
+            });
+        }
+    }
+    findings
+}
+
+/// `pnpm audit --json`: npm 6 style `advisories.<id>` with per-version `findings`.
+fn parse_pnpm_audit(path: &str, report: &Value) -> Vec<AuditFinding> {
+    let Some(advisories) = report.get("advisories").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    advisories
+        .iter()
+        .filter_map(|(id, advisory)| {
+            let version = advisory
+                .get("findings")
+                .and_then(Value::as_array)
+                .and_then(|findings| findings.first())
+                .and_then(|finding| string_field(finding, "version"));
+            Some(AuditFinding {
+                ecosystem: AuditEcosystem::Pnpm,
+                path: path.to_string(),
+                package: string_field(advisory, "module_name")?,
+                version,
+                advisory_id: string_field(advisory, "github_advisory_id").unwrap_or(id.clone()),
+                title: string_field(advisory, "title"),
+                severity: AuditSeverity::parse(advisory.get("severity").and_then(Value::as_str)),
+                url: string_field(advisory, "url"),
+                fixed_in: string_field(advisory, "patched_versions")
+                    .into_iter()
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// `pip-audit -f json`: `dependencies[].vulns[]` (older releases print the list bare).
+fn parse_pip_audit(path: &str, report: &Value) -> Vec<AuditFinding> {
+    let dependencies = report
+        .get("dependencies")
+        .or(Some(report))
+        .and_then(Value::as_array);
+    let Some(dependencies) = dependencies else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    for dependency in dependencies {
+        let Some(package) = string_field(dependency, "name") else {
+            continue;
+        };
+        let Some(vulns) = dependency.get("vulns").and_then(Value::as_array) else {
+            continue;
+        };
+        for vuln in vulns {
+            let Some(advisory_id) = string_field(vuln, "id") else {
+                continue;
+            };
+            findings.push(AuditFinding {
+                ecosystem: AuditEcosystem::Python,
+                path: path.to_string(),
+                package: package.clone(),
+                version: string_field(dependency, "version"),
+                url: Some(format!("https://osv.dev/vulnerability/{advisory_id}")),
+                advisory_id,
+                title: string_field(vuln, "description")
+                    .map(|description| description.lines().next().unwrap_or("").to_string()),
+                severity: AuditSeverity::Unknown,
+                fixed_in: string_list(vuln.get("fix_versions")),
+            });
+        }
+    }
+    findings
+}
+
+/// One audit per lockfile: Cargo and npm/pnpm packages come from `detect_packages`, Python
+/// projects are only looked for at the workspace root.
+fn audit_targets(root: &Path) -> Vec<AuditTarget> {
+    let mut targets = Vec::new();
+    for package in detect_packages(root) {
+        let dir = root.join(&package.path);
+        let (ecosystem, program, args, lockfile) = match package.ecosystem {
+            PackageEcosystem::Cargo => (
+                AuditEcosystem::Cargo,
+                "cargo",
+                vec!["audit", "--json"],
+                "Cargo.lock",
+            ),
+            PackageEcosystem::Npm => (
+                AuditEcosystem::Npm,
+                "npm",
+                vec!["audit", "--json"],
+                "package-lock.json",
+            ),
+            PackageEcosystem::Pnpm => (
+                AuditEcosystem::Pnpm,
+                "pnpm",
+                vec!["audit", "--json"],
+                "pnpm-lock.yaml",
+            ),
+            PackageEcosystem::Go => continue,
+        };
+        if !dir.join(lockfile).is_file() {
+            continue;
+        }
+        targets.push(AuditTarget {
+            ecosystem,
+            path: package.path,
+            program,
+            args: args.into_iter().map(str::to_string).collect(),
+        });
+    }
+    let python_args = if root.join("requirements.txt").is_file() {
+        Some(vec!["-r", "requirements.txt"])
+    } else if root.join("pyproject.toml").is_file() {
+        Some(vec!["."])
+    } else {
+        None
+    };
+    if let Some(python_args) = python_args {
+        targets.push(AuditTarget {
+            ecosystem: AuditEcosystem::Python,
+            path: String::new(),
+            program: "pip-audit",
+            args: ["-f", "json", "--progress-spinner", "off"]
+                .into_iter()
+                .chain(python_args)
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+    targets
+}
+
+fn tool_label(target: &AuditTarget) -> String {
+    match target.args.first() {
+        Some(subcommand) if target.program != "pip-audit" => {
+            format!("{} {subcommand}", target.program)
+        }
+        _ => target.program.to_string(),
+    }
+}
+
+/// Audit tools exit non-zero when they find something, so success is judged by whether
+/// stdout holds a JSON report. `permit` vets the argv before it is spawned.
+async fn run_audit(
+    root: &Path,
+    target: &AuditTarget,
+    permit: &impl Fn(&[String]) -> Result<(), String>,
+) -> Result<Vec<AuditFinding>, String> {
+    let argv = std::iter::once(target.program.to_string())
+        .chain(target.args.iter().cloned())
+        .collect::<Vec<_>>();
+    permit(&argv)?;
+    let _slot = acquire_process_slot(ProcessKind::Tool).await?;
+    let output = timeout(
+        AUDIT_TIMEOUT,
+        tokio_command(target.program)
+            .args(&target.args)
+            .current_dir(root.join(&target.path))
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("{} timed out", tool_label(target)))?
+    .map_err(|err| format!("Failed to run {}: {err}", tool_label(target)))?;
+    let report: Value = serde_json::from_slice(&output.stdout).map_err(|_| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = stderr.trim();
+        if detail.is_empty() {
+            format!("{} produced no report", tool_label(target))
+        } else {
+            detail.to_string()
+        }
+    })?;
+    Ok(match target.ecosystem {
+        AuditEcosystem::Cargo => parse_cargo_audit(&target.path, &report),
+        AuditEcosystem::Npm => parse_npm_audit(&target.path, &report),
+        AuditEcosystem::Pnpm => parse_pnpm_audit(&target.path, &report),
+        AuditEcosystem::Python => parse_pip_audit(&target.path, &report),
+    })
+}
+
+/// Runs `cargo audit`, `npm audit`/`pnpm audit` and `pip-audit` wherever the workspace has a
+/// matching lockfile and merges their findings into one list. `permit` vets each argv
+/// before it is spawned; a refused tool shows up as a failed run.
+pub async fn audit_dependencies_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    permit: impl Fn(&[String]) -> Result<(), String>,
+) -> Result<DependencyAudit, String> {
+    let root = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        PathBuf::from(&entry.path)
+    };
+    let target_root = root.clone();
+    let targets = tokio::task::spawn_blocking(move || audit_targets(&target_root))
+        .await
+        .map_err(|err| format!("Dependency audit failed: {err}"))?;
+
+    let mut runs = Vec::new();
+    let mut findings = Vec::new();
+    for target in targets {
+        let result = run_audit(&root, &target, &permit).await;
+        runs.push(AuditRun {
+            ecosystem: target.ecosystem,
+            path: target.path.clone(),
+            tool: tool_label(&target),
+            error: result.as_ref().err().cloned(),
+        });
+        findings.extend(result.unwrap_or_default());
+    }
+    findings.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.package.cmp(&b.package))
+    });
+    Ok(DependencyAudit {
+        workspace_id: workspace_id.to_string(),
+        runs,
+        findings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalizes_cargo_and_npm_reports() {
+        let cargo = json!({ "vulnerabilities": { "found": true, "count": 1, "list": [{
+            "advisory": { "id": "RUSTSEC-2024-0001", "title": "Overflow", "url": "https://rustsec.org/1" },
+            "versions": { "patched": [">=1.2.3"] },
+            "package": { "name": "smallvec", "version": "1.2.0" }
+        }] } });
+        let findings = parse_cargo_audit("", &cargo);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].package, "smallvec");
+        assert_eq!(findings[0].severity, AuditSeverity::Unknown);
+        assert_eq!(findings[0].fixed_in, vec![">=1.2.3".to_string()]);
+
+        let npm = json!({ "vulnerabilities": {
+            "lodash": {
+                "name": "lodash",
+                "severity": "critical",
+                "via": [{ "source": 1096305, "name": "lodash", "title": "Prototype Pollution",
+                          "url": "https://github.com/advisories/GHSA-1", "severity": "critical",
+                          "range": "<4.17.21" }],
+                "fixAvailable": { "name": "lodash", "version": "4.17.21" }
+            },
+            "wrapper": { "name": "wrapper", "severity": "critical", "via": ["lodash"], "fixAvailable": true }
+        } });
+        let findings = parse_npm_audit("web", &npm);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].advisory_id, "1096305");
+        assert_eq!(findings[0].severity, AuditSeverity::Critical);
+        assert_eq!(findings[0].version.as_deref(), Some("<4.17.21"));
+        assert_eq!(findings[0].path, "web");
+    }
+
+    #[test]
+    fn normalizes_pnpm_and_pip_reports() {
+        let pnpm = json!({ "advisories": { "1005": {
+            "module_name": "minimist", "severity": "moderate", "title": "Prototype Pollution",
+            "url": "https://github.com/advisories/GHSA-2", "patched_versions": ">=1.2.6",
+            "findings": [{ "version": "1.2.5", "paths": ["minimist"] }]
+        } } });
+        let findings = parse_pnpm_audit("", &pnpm);
+        assert_eq!(findings[0].advisory_id, "1005");
+        assert_eq!(findings[0].version.as_deref(), Some("1.2.5"));
+        assert_eq!(findings[0].severity, AuditSeverity::Moderate);
+
+        let pip = json!({ "dependencies": [
+            { "name": "requests", "version": "2.19.0", "vulns": [
+                { "id": "PYSEC-2018-28", "fix_versions": ["2.20.0"], "description": "Leaks auth.\nMore." }
+            ] },
+            { "name": "idna", "version": "3.7", "vulns": [] }
+        ] });
+        let findings = parse_pip_audit("", &pip);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].title.as_deref(), Some("Leaks auth."));
+        assert_eq!(findings[0].fixed_in, vec!["2.20.0".to_string()]);
+    }
+
+    #[test]
+    fn refused_tools_are_not_spawned() {
+        let target = AuditTarget {
+            ecosystem: AuditEcosystem::Cargo,
+            path: String::new(),
+            program: "cargo",
+            args: vec!["audit".to_string(), "--json".to_string()],
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        let seen = std::sync::Mutex::new(Vec::new());
+        let error = runtime
+            .block_on(run_audit(Path::new("/nonexistent"), &target, &|argv| {
+                seen.lock().unwrap().push(argv.to_vec());
+                Err("`cargo audit --json` is not allowed".to_string())
+            }))
+            .expect_err("refused");
+        assert!(error.contains("not allowed"));
+        assert_eq!(
+            seen.into_inner().unwrap(),
+            vec![vec![
+                "cargo".to_string(),
+                "audit".to_string(),
+                "--json".to_string()
+            ]]
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::backend::events::{AppServerEvent, EventSink};
+use crate::types::{DiskQuota, WorkspaceEntry};
+
+/// How often the daemon re-measures workspaces and its data dir.
+pub const DISK_USAGE_INTERVAL_SECS: u64 = 10 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDiskUsage {
+    pub workspace_id: String,
+    pub parent_id: Option<String>,
+    pub path: String,
+    /// Everything under `path`, ignored files and `.git` included.
+    pub bytes: u64,
+    /// For a main workspace, the sum over its worktrees; `0` for worktrees.
+    pub worktree_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum DiskUsageScope {
+    Workspace,
+    Worktree,
+    DataDir,
+}
+
+/// Params of the `disk/usageWarning` event.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsageWarning {
+    pub scope: DiskUsageScope,
+    /// `None` for the data dir.
+    pub workspace_id: Option<String>,
+    pub path: String,
+    pub bytes: u64,
+    pub threshold_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsageReport {
+    pub data_dir: String,
+    /// Includes the worktrees, which live under `<data-dir>/worktrees`.
+    pub data_dir_bytes: u64,
+    pub workspaces: Vec<WorkspaceDiskUsage>,
+    /// Everything currently over its `diskQuota` threshold.
+    pub warnings: Vec<DiskUsageWarning>,
+    /// Unix ms.
+    pub measured_at: u64,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Apparent size of every file under `root`. Symlinks are not followed.
+pub fn dir_bytes(root: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if metadata.is_file() {
+                total += metadata.len();
+            }
+        }
+    }
+    total
+}
+
+fn measure(entries: Vec<WorkspaceEntry>, data_dir: &Path) -> (u64, Vec<WorkspaceDiskUsage>) {
+    let mut usage = entries
+        .into_iter()
+        .map(|entry| WorkspaceDiskUsage {
+            bytes: dir_bytes(Path::new(&entry.path)),
+            workspace_id: entry.id,
+            parent_id: entry.parent_id,
+            path: entry.path,
+            worktree_bytes: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut by_parent: HashMap<String, u64> = HashMap::new();
+    for worktree in &usage {
+        if let Some(parent_id) = &worktree.parent_id {
+            *by_parent.entry(parent_id.clone()).or_default() += worktree.bytes;
+        }
+    }
+    for workspace in &mut usage {
+        workspace.worktree_bytes = by_parent.remove(&workspace.workspace_id).unwrap_or(0);
+    }
+    usage.sort_by(|a, b| {
+        (b.bytes + b.worktree_bytes)
+            .cmp(&(a.bytes + a.worktree_bytes))
+            .then_with(|| a.workspace_id.cmp(&b.workspace_id))
+    });
+    (dir_bytes(data_dir), usage)
+}
+
+/// Entries of `report` over their `quota` threshold.
+pub fn quota_warnings(report: &DiskUsageReport, quota: &DiskQuota) -> Vec<DiskUsageWarning> {
+    let mut warnings = Vec::new();
+    for workspace in &report.workspaces {
+        let (scope, bytes, threshold) = match workspace.parent_id {
+            Some(_) => (
+                DiskUsageScope::Worktree,
+                workspace.bytes,
+                quota.worktree_warning_bytes,
+            ),
+            None => (
+                DiskUsageScope::Workspace,
+                workspace.bytes + workspace.worktree_bytes,
+                quota.workspace_warning_bytes,
+            ),
+        };
+        if let Some(threshold) = threshold.filter(|threshold| bytes > *threshold) {
+            warnings.push(DiskUsageWarning {
+                scope,
+                workspace_id: Some(workspace.workspace_id.clone()),
+                path: workspace.path.clone(),
+                bytes,
+                threshold_bytes: threshold,
+            });
+        }
+    }
+    if let Some(threshold) = quota
+        .data_dir_warning_bytes
+        .filter(|threshold| report.data_dir_bytes > *threshold)
+    {
+        warnings.push(DiskUsageWarning {
+            scope: DiskUsageScope::DataDir,
+            workspace_id: None,
+            path: report.data_dir.clone(),
+            bytes: report.data_dir_bytes,
+            threshold_bytes: threshold,
+        });
+    }
+    warnings
+}
+
+/// Keeps only `workspace_id` and its worktrees; the data dir totals stay as they are.
+pub fn report_for_workspace(
+    mut report: DiskUsageReport,
+    workspace_id: &str,
+) -> DiskUsageReport {
+    report.workspaces.retain(|usage| {
+        usage.workspace_id == workspace_id || usage.parent_id.as_deref() == Some(workspace_id)
+    });
+    let ids = report
+        .workspaces
+        .iter()
+        .map(|usage| usage.workspace_id.clone())
+        .collect::<HashSet<_>>();
+    report.warnings.retain(|warning| {
+        warning
+            .workspace_id
+            .as_ref()
+            .is_some_and(|id| ids.contains(id))
+    });
+    report
+}
+
+/// Measures every workspace and the data dir. Walks the trees on a blocking thread.
+pub async fn disk_usage_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    data_dir: &Path,
+    quota: &DiskQuota,
+) -> Result<DiskUsageReport, String> {
+    let entries = workspaces
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let scan_dir = data_dir.to_path_buf();
+    let (data_dir_bytes, workspaces) =
+        tokio::task::spawn_blocking(move || measure(entries, &scan_dir))
+            .await
+            .map_err(|err| format!("Disk usage scan failed: {err}"))?;
+    let mut report = DiskUsageReport {
+        data_dir: data_dir.to_string_lossy().to_string(),
+        data_dir_bytes,
+        workspaces,
+        warnings: Vec::new(),
+        measured_at: now_millis(),
+    };
+    report.warnings = quota_warnings(&report, quota);
+    Ok(report)
+}
+
+fn warning_key(warning: &DiskUsageWarning) -> (DiskUsageScope, Option<String>) {
+    (warning.scope, warning.workspace_id.clone())
+}
+
+/// Last measurement plus what is currently over quota, so `disk/usageWarning` goes out
+/// once per crossing rather than on every check.
+#[derive(Default)]
+pub struct DiskMonitor {
+    latest: std::sync::Mutex<Option<DiskUsageReport>>,
+    over_quota: std::sync::Mutex<HashSet<(DiskUsageScope, Option<String>)>>,
+}
+
+impl DiskMonitor {
+    pub fn latest(&self) -> Option<DiskUsageReport> {
+        self.latest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Stores `report` and returns the warnings that weren't active on the previous one.
+    pub fn record(&self, report: DiskUsageReport) -> Vec<DiskUsageWarning> {
+        let mut over_quota = self
+            .over_quota
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let crossed = report
+            .warnings
+            .iter()
+            .filter(|warning| !over_quota.contains(&warning_key(warning)))
+            .cloned()
+            .collect();
+        *over_quota = report.warnings.iter().map(warning_key).collect();
+        *self
+            .latest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(report);
+        crossed
+    }
+
+    pub async fn check_once<E: EventSink>(
+        &self,
+        workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+        data_dir: &Path,
+        quota: &DiskQuota,
+        event_sink: &E,
+    ) -> Result<DiskUsageReport, String> {
+        let report = disk_usage_core(workspaces, data_dir, quota).await?;
+        for warning in self.record(report.clone()) {
+            event_sink.emit_app_server_event(AppServerEvent {
+                workspace_id: warning.workspace_id.clone().unwrap_or_default(),
+                message: json!({
+                    "method": "disk/usageWarning",
+                    "params": warning,
+                }),
+            });
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn usage(
+        id: &str,
+        parent_id: Option<&str>,
+        bytes: u64,
+        worktree_bytes: u64,
+    ) -> WorkspaceDiskUsage {
+        WorkspaceDiskUsage {
+            workspace_id: id.to_string(),
+            parent_id: parent_id.map(str::to_string),
+            path: format!("/work/{id}"),
+            bytes,
+            worktree_bytes,
+        }
+    }
+
+    #[test]
+    fn measures_files_and_sums_worktrees_into_their_parent() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-disk-{}", Uuid::new_v4()));
+        let main = root.join("main");
+        let worktree = root.join("data").join("worktrees").join("wt");
+        std::fs::create_dir_all(main.join("target")).expect("create main");
+        std::fs::create_dir_all(&worktree).expect("create worktree");
+        std::fs::write(main.join("a.txt"), [0u8; 10]).expect("write a");
+        std::fs::write(main.join("target").join("big.bin"), [0u8; 100]).expect("write big");
+        std::fs::write(worktree.join("b.txt"), [0u8; 5]).expect("write b");
+
+        let entry = |id: &str, path: &Path, parent_id: Option<&str>| {
+            serde_json::from_value::<WorkspaceEntry>(json!({
+                "id": id,
+                "name": id,
+                "path": path,
+                "codex_bin": null,
+                "parentId": parent_id,
+            }))
+            .expect("entry")
+        };
+        let (data_dir_bytes, usage) = measure(
+            vec![
+                entry("wt", &worktree, Some("main")),
+                entry("main", &main, None),
+            ],
+            &root.join("data"),
+        );
+        assert_eq!(data_dir_bytes, 5);
+        assert_eq!(
+            usage
+                .iter()
+                .map(|usage| (
+                    usage.workspace_id.as_str(),
+                    usage.bytes,
+                    usage.worktree_bytes
+                ))
+                .collect::<Vec<_>>(),
+            vec![("main", 110, 5), ("wt", 5, 0)]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn warns_once_per_crossing() {
+        let mut report = DiskUsageReport {
+            data_dir: "/data".to_string(),
+            data_dir_bytes: 50,
+            workspaces: vec![
+                usage("main", None, 60, 50),
+                usage("wt", Some("main"), 50, 0),
+            ],
+            warnings: Vec::new(),
+            measured_at: 0,
+        };
+        let quota = DiskQuota {
+            workspace_warning_bytes: Some(100),
+            worktree_warning_bytes: Some(80),
+            data_dir_warning_bytes: None,
+        };
+        report.warnings = quota_warnings(&report, &quota);
+        assert_eq!(
+            report
+                .warnings
+                .iter()
+                .map(|warning| (warning.scope, warning.bytes))
+                .collect::<Vec<_>>(),
+            vec![(DiskUsageScope::Workspace, 110)]
+        );
+
+        let monitor = DiskMonitor::default();
+        assert_eq!(monitor.record(report.clone()).len(), 1);
+        assert!(monitor.record(report.clone()).is_empty());
+        let mut below = report.clone();
+        below.warnings.clear();
+        monitor.record(below);
+        assert_eq!(monitor.record(report.clone()).len(), 1);
+
+        let filtered = report_for_workspace(report, "other");
+        assert!(filtered.workspaces.is_empty() && filtered.warnings.is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::files::policy::FileScope;
+use crate::shared::files_core::resolve_root_core;
+use crate::types::WorkspaceEntry;
+
+const MAX_DOCUMENT_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_DOCX_XML_BYTES: u64 = 64 * 1024 * 1024;
+/// Text kept per document; later pages are dropped and the document marked truncated.
+const MAX_DOCUMENT_CHARS: usize = 120_000;
+const CHUNK_CHARS: usize = 4_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+}
+
+impl DocumentKind {
+    fn label(self) -> &'static str {
+        match self {
+            DocumentKind::Pdf => "PDF",
+            DocumentKind::Docx => "Word document",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentChunk {
+    /// 1-based. Word documents are paged at the breaks Word last rendered.
+    pub page: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedDocument {
+    pub path: String,
+    pub kind: DocumentKind,
+    pub pages: usize,
+    pub chars: usize,
+    pub truncated: bool,
+    pub chunks: Vec<DocumentChunk>,
+}
+
+pub fn document_kind(path: &str) -> Option<DocumentKind> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "pdf" => Some(DocumentKind::Pdf),
+        "docx" => Some(DocumentKind::Docx),
+        _ => None,
+    }
+}
+
+/// pdf-extract panics on some malformed files instead of returning an error.
+fn pdf_pages(bytes: &[u8]) -> Result<Vec<String>, String> {
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| "Failed to read PDF: unsupported or corrupt file".to_string())?
+        .map_err(|err| format!("Failed to read PDF: {err}"))
+}
+
+fn is_page_break(element: &BytesStart) -> bool {
+    match element.local_name().as_ref() {
+        b"lastRenderedPageBreak" => true,
+        b"br" => element
+            .attributes()
+            .flatten()
+            .any(|attr| attr.key.local_name().as_ref() == b"type" && &*attr.value == b"page"),
+        _ => false,
+    }
+}
+
+/// Splits `word/document.xml` into pages of paragraphs. Consecutive breaks with no
+/// text between them count once, since Word records both the explicit break and
+/// where it last rendered one.
+pub fn docx_xml_pages(xml: &str) -> Result<Vec<String>, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut in_text = false;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|err| format!("Failed to read Word document: {err}"))?;
+        match event {
+            Event::Start(element) if element.local_name().as_ref() == b"t" => in_text = true,
+            Event::End(element) => match element.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => page.push('\n'),
+                _ => {}
+            },
+            Event::Text(text) if in_text => {
+                let text = text
+                    .unescape()
+                    .map_err(|err| format!("Failed to read Word document: {err}"))?;
+                page.push_str(&text);
+            }
+            Event::Start(element) | Event::Empty(element) => {
+                if is_page_break(&element) {
+                    if !page.trim().is_empty() {
+                        pages.push(std::mem::take(&mut page));
+                    }
+                } else {
+                    match element.local_name().as_ref() {
+                        b"tab" => page.push('\t'),
+                        b"br" | b"cr" => page.push('\n'),
+                        _ => {}
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !page.trim().is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+    Ok(pages)
+}
+
+fn docx_pages(bytes: &[u8]) -> Result<Vec<String>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|err| format!("Failed to open Word document: {err}"))?;
+    let entry = archive
+        .by_name("word/document.xml")
+        .map_err(|err| format!("Failed to open Word document: {err}"))?;
+    let mut xml = String::new();
+    entry
+        .take(MAX_DOCX_XML_BYTES)
+        .read_to_string(&mut xml)
+        .map_err(|err| format!("Failed to read Word document: {err}"))?;
+    docx_xml_pages(&xml)
+}
+
+fn clean_page(text: &str) -> String {
+    let mut cleaned = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !cleaned.is_empty() {
+            cleaned.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        blank_lines = 0;
+        cleaned.push_str(line);
+    }
+    cleaned
+}
+
+/// Cuts `text` into pieces of at most `limit` chars, at the last line break that
+/// fits and mid-line only when a single line is longer than `limit`.
+fn split_chunks(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.chars().count() > limit {
+        let hard_end = rest
+            .char_indices()
+            .nth(limit)
+            .map(|(index, _)| index)
+            .unwrap_or(rest.len());
+        let end = rest[..hard_end]
+            .rfind('\n')
+            .filter(|index| *index > 0)
+            .unwrap_or(hard_end);
+        chunks.push(rest[..end].trim_end().to_string());
+        rest = rest[end..].trim_start();
+    }
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// Chunks each page and stops once `max_chars` would be exceeded.
+pub fn chunk_pages(
+    pages: &[String],
+    chunk_chars: usize,
+    max_chars: usize,
+) -> (Vec<DocumentChunk>, usize, bool) {
+    let mut chunks = Vec::new();
+    let mut chars = 0;
+    for (index, page) in pages.iter().enumerate() {
+        for text in split_chunks(&clean_page(page), chunk_chars) {
+            let len = text.chars().count();
+            if chars + len > max_chars {
+                return (chunks, chars, true);
+            }
+            chars += len;
+            chunks.push(DocumentChunk {
+                page: index + 1,
+                text,
+            });
+        }
+    }
+    (chunks, chars, false)
+}
+
+/// Extracts the text of a PDF or Word document inside `root`.
+pub fn extract_document(root: &Path, path: &str) -> Result<ExtractedDocument, String> {
+    let kind = document_kind(path)
+        .ok_or_else(|| format!("Unsupported document type: {path} (expected .pdf or .docx)"))?;
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let canonical_path = canonical_root
+        .join(path)
+        .canonicalize()
+        .map_err(|err| format!("Failed to open {path}: {err}"))?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err("Document path is outside the workspace".to_string());
+    }
+    let metadata = std::fs::metadata(&canonical_path).map_err(|err| err.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {path}"));
+    }
+    if metadata.len() > MAX_DOCUMENT_BYTES {
+        return Err(format!(
+            "Document is larger than {MAX_DOCUMENT_BYTES} bytes"
+        ));
+    }
+    let bytes = std::fs::read(&canonical_path).map_err(|err| err.to_string())?;
+    let pages = match kind {
+        DocumentKind::Pdf => pdf_pages(&bytes)?,
+        DocumentKind::Docx => docx_pages(&bytes)?,
+    };
+    let (chunks, chars, truncated) = chunk_pages(&pages, CHUNK_CHARS, MAX_DOCUMENT_CHARS);
+    let relative = canonical_path
+        .strip_prefix(&canonical_root)
+        .unwrap_or(&canonical_path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    Ok(ExtractedDocument {
+        path: relative,
+        kind,
+        pages: pages.len(),
+        chars,
+        truncated,
+        chunks,
+    })
+}
+
+/// The input item `send_user_message` adds for an attached document.
+pub fn document_input_text(document: &ExtractedDocument) -> String {
+    let pages = if document.pages == 1 {
+        "1 page".to_string()
+    } else {
+        format!("{} pages", document.pages)
+    };
+    let mut text = format!(
+        "Attached document `{}` ({}, {pages}):",
+        document.path,
+        document.kind.label()
+    );
+    for chunk in &document.chunks {
+        text.push_str(&format!(
+            "\n\n[{} p. {}]\n{}",
+            document.path, chunk.page, chunk.text
+        ));
+    }
+    if document.chunks.is_empty() {
+        text.push_str("\n\n(no extractable text)");
+    }
+    if document.truncated {
+        let last_page = document.chunks.last().map(|chunk| chunk.page).unwrap_or(0);
+        text.push_str(&format!(
+            "\n\n[truncated after p. {last_page} of {}]",
+            document.pages
+        ));
+    }
+    text
+}
+
+/// Extracts every attached document, failing the message if one can't be read.
+pub async fn document_inputs(
+    root: PathBuf,
+    paths: Vec<String>,
+) -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(move || {
+        paths
+            .iter()
+            .map(|path| path.trim())
+            .filter(|path| !path.is_empty())
+            .map(|path| extract_document(&root, path).map(|doc| document_input_text(&doc)))
+            .collect()
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+pub async fn extract_document_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    path: String,
+) -> Result<ExtractedDocument, String> {
+    let root = resolve_root_core(workspaces, FileScope::Workspace, Some(workspace_id)).await?;
+    tokio::task::spawn_blocking(move || extract_document(&root, &path))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use uuid::Uuid;
+
+    fn minimal_pdf(pages: &[&str]) -> Vec<u8> {
+        let count = pages.len();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {count} >>",
+                (0..count)
+                    .map(|index| format!("{} 0 R", 4 + index * 2))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+        for (index, text) in pages.iter().enumerate() {
+            let stream = format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET");
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                5 + index * 2
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{stream}\nendstream",
+                stream.len()
+            ));
+        }
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n{object}\nendobj\n", index + 1).bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+        for offset in offsets {
+            pdf.extend(format!("{offset:010} 00000 n \n").bytes());
+        }
+        pdf.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+                objects.len() + 1
+            )
+            .bytes(),
+        );
+        pdf
+    }
+
+    fn minimal_docx(body: &str) -> Vec<u8> {
+        let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        archive
+            .start_file("word/document.xml", options)
+            .expect("start entry");
+        archive
+            .write_all(
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                     <w:document xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+                     <w:body>{body}</w:body></w:document>"
+                )
+                .as_bytes(),
+            )
+            .expect("write entry");
+        archive.finish().expect("finish").into_inner()
+    }
+
+    #[test]
+    fn extracts_pages_from_pdf_and_docx() {
+        let root = std::env::temp_dir().join(format!("fridex-docs-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        std::fs::write(
+            root.join("spec.pdf"),
+            minimal_pdf(&["First page", "Second page"]),
+        )
+        .expect("write pdf");
+        std::fs::write(
+            root.join("notes.docx"),
+            minimal_docx(
+                "<w:p><w:r><w:t>Intro &amp; scope</w:t><w:tab/><w:t>v2</w:t></w:r></w:p>\
+                 <w:p><w:r><w:br w:type=\"page\"/></w:r>\
+                 <w:r><w:lastRenderedPageBreak/><w:t>Details</w:t></w:r></w:p>",
+            ),
+        )
+        .expect("write docx");
+
+        let pdf = extract_document(&root, "spec.pdf").expect("pdf");
+        assert_eq!(pdf.kind, DocumentKind::Pdf);
+        assert_eq!(pdf.pages, 2);
+        assert_eq!(
+            pdf.chunks
+                .iter()
+                .map(|chunk| (chunk.page, chunk.text.trim()))
+                .collect::<Vec<_>>(),
+            vec![(1, "First page"), (2, "Second page")]
+        );
+
+        let docx = extract_document(&root, "notes.docx").expect("docx");
+        assert_eq!(docx.kind, DocumentKind::Docx);
+        assert_eq!(docx.pages, 2);
+        assert_eq!(docx.chunks[0].text, "Intro & scope\tv2");
+        assert_eq!(
+            (docx.chunks[1].page, docx.chunks[1].text.as_str()),
+            (2, "Details")
+        );
+        let input = document_input_text(&docx);
+        assert!(input.starts_with("Attached document `notes.docx` (Word document, 2 pages):"));
+        assert!(input.contains("[notes.docx p. 2]\nDetails"));
+
+        std::fs::write(root.join("broken.pdf"), b"%PDF-1.4 nope").expect("write broken");
+        assert!(extract_document(&root, "broken.pdf").is_err());
+        assert!(extract_document(&root, "notes.txt").is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn chunks_pages_within_limits() {
+        let pages = vec![
+            "alpha\n\n\n\nbeta   \ngamma".to_string(),
+            "x".repeat(25),
+            "tail".to_string(),
+        ];
+        let (chunks, chars, truncated) = chunk_pages(&pages, 10, 40);
+        assert_eq!(chunks[0].text, "alpha");
+        assert_eq!(chunks[1].text, "beta\ngamma");
+        assert_eq!(
+            chunks[2..]
+                .iter()
+                .map(|chunk| (chunk.page, chunk.text.len()))
+                .collect::<Vec<_>>(),
+            vec![(2, 10), (2, 10), (2, 5)]
+        );
+        assert_eq!(chars, 40);
+        assert!(truncated);
+    }
+}
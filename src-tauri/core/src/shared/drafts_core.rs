@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+pub const DRAFTS_FILE: &str = "drafts.json";
+
+/// Unsent composer contents for one thread, shared by every client of the backend.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadDraft {
+    pub workspace_id: String,
+    pub thread_id: String,
+    pub text: String,
+    /// Image paths or data URLs, in the same form `send_user_message` accepts.
+    #[serde(default)]
+    pub images: Vec<String>,
+    pub updated_at: u64,
+}
+
+/// Drafts persisted in `<data-dir>/drafts.json`, keyed by workspace and thread.
+pub struct Drafts {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, ThreadDraft>>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn draft_key(workspace_id: &str, thread_id: &str) -> String {
+    format!("{workspace_id}/{thread_id}")
+}
+
+impl Drafts {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(DRAFTS_FILE);
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ThreadDraft>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, entries: &HashMap<String, ThreadDraft>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(entries).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, data).map_err(|err| err.to_string())
+    }
+
+    /// Stores the draft, replacing any earlier one. Saving blank text without images
+    /// clears the draft and returns `None`.
+    pub fn save(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        text: String,
+        images: Vec<String>,
+    ) -> Result<Option<ThreadDraft>, String> {
+        let key = draft_key(workspace_id, thread_id);
+        let mut entries = self.lock();
+        let draft = if text.trim().is_empty() && images.is_empty() {
+            if entries.remove(&key).is_none() {
+                return Ok(None);
+            }
+            None
+        } else {
+            let draft = ThreadDraft {
+                workspace_id: workspace_id.to_string(),
+                thread_id: thread_id.to_string(),
+                text,
+                images,
+                updated_at: now_millis(),
+            };
+            entries.insert(key, draft.clone());
+            Some(draft)
+        };
+        self.persist(&entries)?;
+        Ok(draft)
+    }
+
+    pub fn get(&self, workspace_id: &str, thread_id: &str) -> Option<ThreadDraft> {
+        self.lock()
+            .get(&draft_key(workspace_id, thread_id))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn saves_reloads_and_clears_drafts() {
+        let data_dir =
+            std::env::temp_dir().join(format!("codex-monitor-drafts-{}", Uuid::new_v4()));
+        let drafts = Drafts::load(&data_dir);
+        let saved = drafts
+            .save(
+                "ws-1",
+                "thr-1",
+                "half a prompt".to_string(),
+                vec!["/tmp/shot.png".to_string()],
+            )
+            .expect("save")
+            .expect("draft");
+        assert_eq!(saved.images, vec!["/tmp/shot.png".to_string()]);
+        assert_eq!(drafts.get("ws-1", "thr-2"), None);
+
+        let reloaded = Drafts::load(&data_dir);
+        assert_eq!(reloaded.get("ws-1", "thr-1"), Some(saved));
+
+        let cleared = reloaded
+            .save("ws-1", "thr-1", "  ".to_string(), Vec::new())
+            .expect("clear");
+        assert_eq!(cleared, None);
+        assert_eq!(Drafts::load(&data_dir).get("ws-1", "thr-1"), None);
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
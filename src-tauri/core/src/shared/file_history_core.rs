@@ -0,0 +1,329 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::shared::protected_paths_core::ensure_write_allowed;
+
+/// Directory under the data dir holding `index.json` plus content-addressed `blobs/`.
+pub const FILE_HISTORY_DIR: &str = "file-history";
+const FILE_HISTORY_INDEX: &str = "index.json";
+const MAX_VERSIONS_PER_FILE: usize = 20;
+
+/// One content state of a file written through the backend.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileVersion {
+    pub version: String,
+    pub path: String,
+    /// SHA-256 of the content after the write.
+    pub hash: String,
+    /// SHA-256 of the content the write replaced; `None` when the file was created.
+    pub previous_hash: Option<String>,
+    pub size: u64,
+    /// `file_write`, `commit_write`, `agent` or `revert`; `original` / `external` mark
+    /// content found on disk before the first tracked write / after an untracked change.
+    pub source: String,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHistoryResponse {
+    pub path: String,
+    /// Newest first.
+    pub versions: Vec<FileVersion>,
+}
+
+/// Capped per-file write history kept in `<data-dir>/file-history`.
+pub struct FileHistory {
+    dir: PathBuf,
+    entries: Mutex<HashMap<String, Vec<FileVersion>>>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Hex SHA-256 of `content`, as stored in `FileVersion::hash`.
+pub fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// History is keyed by canonical path so symlinked and relative spellings agree.
+fn history_key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+impl FileHistory {
+    pub fn load(data_dir: &Path) -> Self {
+        let dir = data_dir.join(FILE_HISTORY_DIR);
+        let entries = std::fs::read_to_string(dir.join(FILE_HISTORY_INDEX))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            dir,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<FileVersion>>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join("blobs").join(hash)
+    }
+
+    fn store_blob(&self, hash: &str, content: &[u8]) -> Result<(), String> {
+        let path = self.blob_path(hash);
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        std::fs::write(&path, content).map_err(|err| err.to_string())
+    }
+
+    fn persist(&self, entries: &HashMap<String, Vec<FileVersion>>) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        let data = serde_json::to_string_pretty(entries).map_err(|err| err.to_string())?;
+        std::fs::write(self.dir.join(FILE_HISTORY_INDEX), data).map_err(|err| err.to_string())
+    }
+
+    /// Deletes blobs no remaining version points at.
+    fn collect_blobs(
+        &self,
+        entries: &HashMap<String, Vec<FileVersion>>,
+        dropped: Vec<FileVersion>,
+    ) {
+        let live = entries
+            .values()
+            .flatten()
+            .map(|version| version.hash.as_str())
+            .collect::<HashSet<_>>();
+        for version in dropped {
+            if !live.contains(version.hash.as_str()) {
+                let _ = std::fs::remove_file(self.blob_path(&version.hash));
+            }
+        }
+    }
+
+    /// Records a completed write of `after` over `before` (`None` when the file was new).
+    /// Content on disk that history doesn't know yet is kept as its own version first.
+    pub fn record_write(
+        &self,
+        path: &Path,
+        before: Option<&[u8]>,
+        after: &[u8],
+        source: &str,
+    ) -> Result<FileVersion, String> {
+        let key = history_key(path);
+        let previous_hash = before.map(content_hash);
+        let hash = content_hash(after);
+        let mut entries = self.lock();
+        let versions = entries.entry(key.clone()).or_default();
+        if let (Some(before), Some(previous_hash)) = (before, previous_hash.as_ref()) {
+            let known = versions
+                .last()
+                .is_some_and(|latest| &latest.hash == previous_hash);
+            if !known {
+                self.store_blob(previous_hash, before)?;
+                versions.push(FileVersion {
+                    version: Uuid::new_v4().to_string(),
+                    path: key.clone(),
+                    hash: previous_hash.clone(),
+                    previous_hash: None,
+                    size: before.len() as u64,
+                    source: if versions.is_empty() {
+                        "original".to_string()
+                    } else {
+                        "external".to_string()
+                    },
+                    created_at: now_millis(),
+                });
+            }
+        }
+        self.store_blob(&hash, after)?;
+        let version = FileVersion {
+            version: Uuid::new_v4().to_string(),
+            path: key,
+            hash,
+            previous_hash,
+            size: after.len() as u64,
+            source: source.to_string(),
+            created_at: now_millis(),
+        };
+        versions.push(version.clone());
+        let excess = versions.len().saturating_sub(MAX_VERSIONS_PER_FILE);
+        let dropped = versions.drain(..excess).collect::<Vec<_>>();
+        self.persist(&entries)?;
+        self.collect_blobs(&entries, dropped);
+        Ok(version)
+    }
+
+    /// Records `after` for a writer that didn't report what it replaced, such as an agent's
+    /// `fileChange`. The latest tracked version stands in for the old content; content
+    /// history already ends with records nothing.
+    pub fn record_untracked_write(
+        &self,
+        path: &Path,
+        after: &[u8],
+        source: &str,
+    ) -> Result<Option<FileVersion>, String> {
+        let latest = self
+            .lock()
+            .get(&history_key(path))
+            .and_then(|versions| versions.last())
+            .map(|latest| latest.hash.clone());
+        if latest.as_deref() == Some(content_hash(after).as_str()) {
+            return Ok(None);
+        }
+        let before = latest.and_then(|hash| std::fs::read(self.blob_path(&hash)).ok());
+        self.record_write(path, before.as_deref(), after, source)
+            .map(Some)
+    }
+
+    pub fn history(&self, path: &str) -> FileHistoryResponse {
+        let key = history_key(Path::new(path));
+        let mut versions = self.lock().get(&key).cloned().unwrap_or_default();
+        versions.reverse();
+        FileHistoryResponse {
+            path: key,
+            versions,
+        }
+    }
+
+    /// Writes the content of `version` back to its file and records that as a `revert`.
+    pub fn revert_to(
+        &self,
+        version: &str,
+        protected_globs: &[String],
+    ) -> Result<FileVersion, String> {
+        let target = self
+            .lock()
+            .values()
+            .flatten()
+            .find(|entry| entry.version == version)
+            .cloned()
+            .ok_or_else(|| format!("Unknown file version: {version}"))?;
+        let path = PathBuf::from(&target.path);
+        ensure_write_allowed(protected_globs, None, &path)?;
+        let content = std::fs::read(self.blob_path(&target.hash))
+            .map_err(|err| format!("Failed to read stored version: {err}"))?;
+        let before = std::fs::read(&path).ok();
+        std::fs::write(&path, &content)
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+        self.record_write(&path, before.as_deref(), &content, "revert")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_caps_and_reverts_versions() {
+        let root =
+            std::env::temp_dir().join(format!("codex-monitor-file-history-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        let file = root.join("AGENTS.md");
+        std::fs::write(&file, "original").expect("write original");
+        let history = FileHistory::load(&root.join("data"));
+
+        std::fs::write(&file, "first").expect("write first");
+        let first = history
+            .record_write(&file, Some(b"original"), b"first", "file_write")
+            .expect("record first");
+        std::fs::write(&file, "second").expect("write second");
+        history
+            .record_write(&file, Some(b"first"), b"second", "file_write")
+            .expect("record second");
+
+        let listed = history.history(&file.to_string_lossy());
+        let sources = listed
+            .versions
+            .iter()
+            .map(|version| version.source.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(sources, vec!["file_write", "file_write", "original"]);
+        assert_eq!(listed.versions[0].previous_hash, Some(first.hash.clone()));
+
+        let reverted = history.revert_to(&first.version, &[]).expect("revert");
+        assert_eq!(reverted.hash, first.hash);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "first");
+        assert!(history.revert_to("missing", &[]).is_err());
+
+        for index in 0..MAX_VERSIONS_PER_FILE + 5 {
+            let content = format!("v{index}");
+            history
+                .record_write(&file, None, content.as_bytes(), "file_write")
+                .expect("record");
+        }
+        assert_eq!(
+            history.history(&file.to_string_lossy()).versions.len(),
+            MAX_VERSIONS_PER_FILE
+        );
+        assert!(!history.blob_path(&first.hash).exists());
+
+        let reloaded = FileHistory::load(&root.join("data"));
+        assert_eq!(
+            reloaded.history(&file.to_string_lossy()).versions.len(),
+            MAX_VERSIONS_PER_FILE
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn records_agent_edits_after_the_latest_version() {
+        let root =
+            std::env::temp_dir().join(format!("codex-monitor-agent-edit-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        let file = root.join("lib.rs");
+        let history = FileHistory::load(&root.join("data"));
+
+        let created = history
+            .record_untracked_write(&file, b"fn a() {}", "agent")
+            .expect("record created")
+            .expect("new content");
+        assert_eq!(created.previous_hash, None);
+        let edited = history
+            .record_untracked_write(&file, b"fn b() {}", "agent")
+            .expect("record edit")
+            .expect("new content");
+        assert_eq!(edited.previous_hash, Some(created.hash.clone()));
+        assert_eq!(
+            history
+                .record_untracked_write(&file, b"fn b() {}", "agent")
+                .expect("record repeat"),
+            None
+        );
+
+        let sources = history
+            .history(&file.to_string_lossy())
+            .versions
+            .iter()
+            .map(|version| version.source.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(sources, vec!["agent", "agent"]);
+        history.revert_to(&created.version, &[]).expect("revert");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn a() {}");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
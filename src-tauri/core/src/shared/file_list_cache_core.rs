@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Files added below the top level of a non-git (or untracked) tree don't change the
+/// fingerprint, so a listing is never reused for longer than this.
+const MAX_LISTING_AGE: Duration = Duration::from_secs(30);
+
+/// Cheap stand-in for "did the tree change": `HEAD` and the branch it points at, the git
+/// index mtime (bumped by `add`, `commit`, `checkout`, `stash`…) and the root's own
+/// mtime (bumped when top-level entries are added or removed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TreeFingerprint {
+    head: Option<String>,
+    index_modified: Option<SystemTime>,
+    root_modified: Option<SystemTime>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// `.git` is a file holding `gitdir: <path>` in linked worktrees and submodules.
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = std::fs::read_to_string(&dot_git).ok()?;
+    let target = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(root.join(target))
+}
+
+impl TreeFingerprint {
+    fn read(root: &Path) -> Self {
+        let git_dir = git_dir(root);
+        let head = git_dir.as_ref().and_then(|git_dir| {
+            let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+            let head = head.trim().to_string();
+            let branch_tip = head
+                .strip_prefix("ref:")
+                .and_then(|reference| std::fs::read_to_string(git_dir.join(reference.trim())).ok())
+                .unwrap_or_default();
+            Some(format!("{head} {}", branch_tip.trim()))
+        });
+        Self {
+            head,
+            index_modified: git_dir.and_then(|git_dir| modified(&git_dir.join("index"))),
+            root_modified: modified(root),
+        }
+    }
+}
+
+struct CachedListing {
+    fingerprint: TreeFingerprint,
+    listed_at: Instant,
+    max_files: usize,
+    files: Vec<String>,
+}
+
+impl CachedListing {
+    /// A listing that hit its own cap can't answer a request for more files.
+    fn covers(&self, max_files: usize) -> bool {
+        max_files <= self.max_files || self.files.len() < self.max_files
+    }
+}
+
+/// Workspace file listings keyed by root, reused while the tree's fingerprint holds.
+#[derive(Default)]
+pub struct FileListCache {
+    entries: Mutex<HashMap<PathBuf, CachedListing>>,
+}
+
+impl FileListCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, CachedListing>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns up to `max_files` paths under `root`, walking with `list_files` only when
+    /// there is no fresh listing. The walk runs without holding the lock.
+    pub fn list<F>(&self, root: &PathBuf, max_files: usize, list_files: F) -> Vec<String>
+    where
+        F: FnOnce(&PathBuf, usize) -> Vec<String>,
+    {
+        let fingerprint = TreeFingerprint::read(root);
+        if let Some(entry) = self.lock().get(root) {
+            let fresh = entry.fingerprint == fingerprint
+                && entry.listed_at.elapsed() < MAX_LISTING_AGE
+                && entry.covers(max_files);
+            if fresh {
+                return entry.files.iter().take(max_files).cloned().collect();
+            }
+        }
+        let files = list_files(root, max_files);
+        self.lock().insert(
+            root.clone(),
+            CachedListing {
+                fingerprint,
+                listed_at: Instant::now(),
+                max_files,
+                files: files.clone(),
+            },
+        );
+        files
+    }
+
+    /// Drops the listing for `root`. Call after changing files under it outside of git,
+    /// e.g. restoring a snapshot or when a file watcher reports changes.
+    pub fn invalidate(&self, root: &Path) {
+        self.lock().remove(root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use uuid::Uuid;
+
+    #[test]
+    fn reuses_listing_until_fingerprint_changes() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-file-list-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join(".git")).expect("git dir");
+        std::fs::write(root.join(".git/HEAD"), "ref: refs/heads/main\n").expect("head");
+        let cache = FileListCache::default();
+        let walks = Cell::new(0);
+        let list_files = |_: &PathBuf, max: usize| {
+            walks.set(walks.get() + 1);
+            vec!["a.rs".to_string(), "b.rs".to_string()]
+                .into_iter()
+                .take(max)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(cache.list(&root, 10, list_files).len(), 2);
+        assert_eq!(cache.list(&root, 1, list_files), vec!["a.rs".to_string()]);
+        assert_eq!(walks.get(), 1);
+
+        std::fs::write(root.join(".git/HEAD"), "ref: refs/heads/feature\n").expect("head");
+        cache.list(&root, 10, list_files);
+        assert_eq!(walks.get(), 2);
+
+        cache.invalidate(&root);
+        cache.list(&root, 10, list_files);
+        assert_eq!(walks.get(), 3);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
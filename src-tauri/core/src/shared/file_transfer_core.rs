@@ -0,0 +1,614 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::shared::file_history_core::FileHistory;
+use crate::shared::file_list_cache_core::FileListCache;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::protected_paths_core::ensure_write_allowed;
+use crate::types::{AppSettings, FileLimits, WorkspaceEntry};
+
+/// `read_workspace_file` truncates past this unless `fileLimits.maxReadBytes` is set.
+pub const DEFAULT_MAX_READ_BYTES: u64 = 400_000;
+/// Largest file a streamed write may produce unless `fileLimits.maxWriteBytes` is set.
+pub const DEFAULT_MAX_WRITE_BYTES: u64 = 64 * 1024 * 1024;
+/// Largest decoded `write_chunk` payload.
+pub const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+/// A streamed write nobody has touched for this long is dropped with its partial file.
+pub const WRITE_IDLE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// File size limits as clients see them in `get_capabilities` and the `auth` response.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCapabilities {
+    pub max_read_bytes: u64,
+    pub max_write_bytes: u64,
+    pub max_chunk_bytes: usize,
+    pub streaming_writes: bool,
+}
+
+/// What `get_capabilities` returns.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub files: FileCapabilities,
+}
+
+pub fn capabilities(settings: &AppSettings) -> Capabilities {
+    Capabilities {
+        files: file_capabilities(&settings.file_limits),
+    }
+}
+
+pub fn max_read_bytes(limits: &FileLimits) -> u64 {
+    limits.max_read_bytes.unwrap_or(DEFAULT_MAX_READ_BYTES)
+}
+
+pub fn max_write_bytes(limits: &FileLimits) -> u64 {
+    limits.max_write_bytes.unwrap_or(DEFAULT_MAX_WRITE_BYTES)
+}
+
+pub fn file_capabilities(limits: &FileLimits) -> FileCapabilities {
+    FileCapabilities {
+        max_read_bytes: max_read_bytes(limits),
+        max_write_bytes: max_write_bytes(limits),
+        max_chunk_bytes: MAX_CHUNK_BYTES,
+        streaming_writes: true,
+    }
+}
+
+/// Returned by `begin_write`; send chunks to `writeId` in order, then commit.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteSession {
+    pub write_id: String,
+    pub path: String,
+    pub max_chunk_bytes: usize,
+    pub max_write_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteProgress {
+    pub write_id: String,
+    /// Bytes received so far; the offset the next chunk must start at.
+    pub written: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CommittedWrite {
+    pub workspace_id: String,
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+struct PendingWrite {
+    workspace_id: String,
+    root: PathBuf,
+    relative_path: String,
+    target: PathBuf,
+    partial: PathBuf,
+    file: File,
+    hasher: Sha256,
+    written: u64,
+    expected_bytes: Option<u64>,
+    max_bytes: u64,
+    touched: Instant,
+}
+
+impl PendingWrite {
+    fn discard(self) {
+        drop(self.file);
+        let _ = std::fs::remove_file(&self.partial);
+    }
+}
+
+/// A commit that replaced its target, with what was there before for file history.
+#[derive(Debug)]
+struct Committed {
+    root: PathBuf,
+    target: PathBuf,
+    before: Option<Vec<u8>>,
+    write: CommittedWrite,
+}
+
+/// Streamed writes in progress. Chunks land in a hidden partial file in the target's
+/// nearest existing directory, which only replaces the target on commit, so readers
+/// never see a half-written file.
+#[derive(Default)]
+pub struct PendingWrites {
+    writes: StdMutex<HashMap<String, PendingWrite>>,
+}
+
+impl PendingWrites {
+    fn expire(&self, now: Instant) {
+        let mut writes = self.writes.lock().unwrap_or_else(|err| err.into_inner());
+        let expired: Vec<String> = writes
+            .iter()
+            .filter(|(_, write)| now.duration_since(write.touched) >= WRITE_IDLE_TTL)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in expired {
+            if let Some(write) = writes.remove(&id) {
+                write.discard();
+            }
+        }
+    }
+
+    fn begin(
+        &self,
+        workspace_id: &str,
+        root: &Path,
+        relative_path: &str,
+        expected_bytes: Option<u64>,
+        max_bytes: u64,
+        protected: &[String],
+    ) -> Result<WriteSession, String> {
+        self.expire(Instant::now());
+        if let Some(expected) = expected_bytes.filter(|expected| *expected > max_bytes) {
+            return Err(too_large(expected, max_bytes));
+        }
+        let (root, target, existing) = resolve_write_target(root, relative_path)?;
+        ensure_write_allowed(protected, Some(&root), &target)?;
+        let write_id = Uuid::new_v4().to_string();
+        let file_name = target
+            .file_name()
+            .ok_or_else(|| i18n_core::message(MessageKey::FileInvalidPath))?
+            .to_string_lossy();
+        let partial = existing.join(format!(".{file_name}.{}.partial", &write_id[..8]));
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&partial)
+            .map_err(|err| format!("Failed to create {}: {err}", partial.display()))?;
+        self.writes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(
+                write_id.clone(),
+                PendingWrite {
+                    workspace_id: workspace_id.to_string(),
+                    root,
+                    relative_path: relative_path.to_string(),
+                    target,
+                    partial,
+                    file,
+                    hasher: Sha256::new(),
+                    written: 0,
+                    expected_bytes,
+                    max_bytes,
+                    touched: Instant::now(),
+                },
+            );
+        Ok(WriteSession {
+            write_id,
+            path: relative_path.to_string(),
+            max_chunk_bytes: MAX_CHUNK_BYTES,
+            max_write_bytes: max_bytes,
+        })
+    }
+
+    fn write_chunk(
+        &self,
+        write_id: &str,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<WriteProgress, String> {
+        self.expire(Instant::now());
+        if data.len() > MAX_CHUNK_BYTES {
+            return Err(format!(
+                "Chunk of {} bytes exceeds the {MAX_CHUNK_BYTES} byte limit",
+                data.len()
+            ));
+        }
+        let mut writes = self.writes.lock().unwrap_or_else(|err| err.into_inner());
+        let write = writes
+            .get_mut(write_id)
+            .ok_or_else(|| unknown_write(write_id))?;
+        if offset != write.written {
+            return Err(format!(
+                "Chunk offset {offset} does not match the {} bytes written so far",
+                write.written
+            ));
+        }
+        let total = write.written + data.len() as u64;
+        let limit = write.expected_bytes.unwrap_or(write.max_bytes);
+        if total > limit {
+            return Err(too_large(total, limit));
+        }
+        write
+            .file
+            .write_all(data)
+            .map_err(|err| format!("Failed to write {}: {err}", write.partial.display()))?;
+        write.hasher.update(data);
+        write.written = total;
+        write.touched = Instant::now();
+        Ok(WriteProgress {
+            write_id: write_id.to_string(),
+            written: total,
+        })
+    }
+
+    fn commit(&self, write_id: &str, sha256: Option<&str>) -> Result<Committed, String> {
+        self.expire(Instant::now());
+        let write = self
+            .writes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(write_id)
+            .ok_or_else(|| unknown_write(write_id))?;
+        if let Some(expected) = write
+            .expected_bytes
+            .filter(|expected| *expected != write.written)
+        {
+            let written = write.written;
+            write.discard();
+            return Err(format!(
+                "Write ended after {written} of {expected} announced bytes"
+            ));
+        }
+        let digest = write
+            .hasher
+            .clone()
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        if let Some(expected) = sha256.filter(|expected| !expected.eq_ignore_ascii_case(&digest)) {
+            write.discard();
+            return Err(format!(
+                "Checksum mismatch: expected {expected}, received {digest}"
+            ));
+        }
+        if let Err(err) = write.file.sync_all() {
+            let message = format!("Failed to flush {}: {err}", write.partial.display());
+            write.discard();
+            return Err(message);
+        }
+        if let Err(message) = create_target_dir(&write.root, &write.target) {
+            write.discard();
+            return Err(message);
+        }
+        if let Ok(metadata) = std::fs::metadata(&write.target) {
+            let _ = std::fs::set_permissions(&write.partial, metadata.permissions());
+        }
+        let before = std::fs::read(&write.target).ok();
+        if let Err(err) = std::fs::rename(&write.partial, &write.target) {
+            let message = format!("Failed to replace {}: {err}", write.target.display());
+            write.discard();
+            return Err(message);
+        }
+        Ok(Committed {
+            root: write.root,
+            target: write.target,
+            before,
+            write: CommittedWrite {
+                workspace_id: write.workspace_id,
+                path: write.relative_path,
+                bytes: write.written,
+                sha256: digest,
+            },
+        })
+    }
+
+    fn abort(&self, write_id: &str) -> Result<(), String> {
+        let write = self
+            .writes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(write_id)
+            .ok_or_else(|| unknown_write(write_id))?;
+        write.discard();
+        Ok(())
+    }
+}
+
+fn unknown_write(write_id: &str) -> String {
+    format!("Unknown or expired write {write_id}; start over with begin_write.")
+}
+
+fn too_large(bytes: u64, limit: u64) -> String {
+    format!("File of {bytes} bytes exceeds the {limit} byte write limit")
+}
+
+/// The canonical workspace root, the target inside it and the target's nearest existing
+/// directory, which must itself be inside the workspace. Nothing is created here; missing
+/// directories are made on commit.
+fn resolve_write_target(
+    root: &Path,
+    relative_path: &str,
+) -> Result<(PathBuf, PathBuf, PathBuf), String> {
+    let relative = Path::new(relative_path);
+    let normal = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if relative_path.trim().is_empty() || !normal || relative.file_name().is_none() {
+        return Err(i18n_core::message(MessageKey::FileInvalidPath));
+    }
+    let root = root
+        .canonicalize()
+        .map_err(|err| i18n_core::message_with(MessageKey::WorkspaceRootResolveFailed, err))?;
+    let joined = root.join(relative);
+    let parent = joined
+        .parent()
+        .ok_or_else(|| i18n_core::message(MessageKey::FileInvalidPath))?;
+    let existing = parent
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| i18n_core::message(MessageKey::FileInvalidPath))?;
+    let missing = parent.strip_prefix(existing).unwrap_or(Path::new(""));
+    let existing = existing
+        .canonicalize()
+        .map_err(|err| i18n_core::message_with(MessageKey::FileMetadataFailed, err))?;
+    if !existing.starts_with(&root) || !existing.is_dir() {
+        return Err(i18n_core::message(MessageKey::FileInvalidPath));
+    }
+    let target = existing
+        .join(missing)
+        .join(relative.file_name().unwrap_or_default());
+    if target.is_dir() {
+        return Err(i18n_core::message(MessageKey::FileNotAFile));
+    }
+    Ok((root, target, existing))
+}
+
+/// Creates the target's missing parent directories and checks they still resolve inside
+/// the workspace.
+fn create_target_dir(root: &Path, target: &Path) -> Result<(), String> {
+    let parent = target
+        .parent()
+        .ok_or_else(|| i18n_core::message(MessageKey::FileInvalidPath))?;
+    std::fs::create_dir_all(parent)
+        .map_err(|err| format!("Failed to create {}: {err}", parent.display()))?;
+    let parent = parent
+        .canonicalize()
+        .map_err(|err| i18n_core::message_with(MessageKey::FileMetadataFailed, err))?;
+    if !parent.starts_with(root) {
+        return Err(i18n_core::message(MessageKey::FileInvalidPath));
+    }
+    if target.is_dir() {
+        return Err(i18n_core::message(MessageKey::FileNotAFile));
+    }
+    Ok(())
+}
+
+pub async fn begin_write_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    pending: &PendingWrites,
+    workspace_id: &str,
+    path: &str,
+    size: Option<u64>,
+) -> Result<WriteSession, String> {
+    let root = workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .map(|entry| PathBuf::from(&entry.path))
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    let (protected, max_bytes) = {
+        let settings = app_settings.lock().await;
+        (
+            settings.protected_path_globs.clone(),
+            max_write_bytes(&settings.file_limits),
+        )
+    };
+    pending.begin(workspace_id, &root, path, size, max_bytes, &protected)
+}
+
+/// `data` is base64; `offset` must equal the bytes written so far, so a retried chunk
+/// fails loudly instead of being appended twice.
+pub fn write_chunk_core(
+    pending: &PendingWrites,
+    write_id: &str,
+    offset: u64,
+    data: &str,
+) -> Result<WriteProgress, String> {
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|err| format!("Invalid base64 chunk: {err}"))?;
+    pending.write_chunk(write_id, offset, &data)
+}
+
+/// Moves the finished file into place, after checking the announced size and `sha256`,
+/// and records the write in file history.
+pub fn commit_write_core(
+    pending: &PendingWrites,
+    file_lists: &FileListCache,
+    file_history: &FileHistory,
+    write_id: &str,
+    sha256: Option<&str>,
+) -> Result<CommittedWrite, String> {
+    let committed = pending.commit(write_id, sha256)?;
+    file_lists.invalidate(&committed.root);
+    // History is best effort; the write itself already succeeded.
+    let recorded = std::fs::read(&committed.target)
+        .map_err(|err| err.to_string())
+        .and_then(|after| {
+            file_history.record_write(
+                &committed.target,
+                committed.before.as_deref(),
+                &after,
+                "commit_write",
+            )
+        });
+    if let Err(err) = recorded {
+        tracing::warn!(
+            path = %committed.target.display(),
+            "failed to record file history: {err}"
+        );
+    }
+    Ok(committed.write)
+}
+
+pub fn abort_write_core(pending: &PendingWrites, write_id: &str) -> Result<(), String> {
+    pending.abort(write_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("codex-monitor-transfer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        root
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        Sha256::digest(data)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    #[test]
+    fn limits_fall_back_to_the_defaults() {
+        let capabilities = file_capabilities(&FileLimits::default());
+        assert_eq!(capabilities.max_read_bytes, DEFAULT_MAX_READ_BYTES);
+        assert_eq!(capabilities.max_write_bytes, DEFAULT_MAX_WRITE_BYTES);
+        let limits = FileLimits {
+            max_read_bytes: Some(1_000),
+            max_write_bytes: Some(2_000),
+        };
+        assert_eq!(max_read_bytes(&limits), 1_000);
+        assert_eq!(file_capabilities(&limits).max_write_bytes, 2_000);
+    }
+
+    #[test]
+    fn streams_chunks_into_place_on_commit() {
+        let root = temp_root();
+        std::fs::write(root.join("Cargo.lock"), "old").expect("write old");
+        let pending = PendingWrites::default();
+        let cache = FileListCache::default();
+        let history = FileHistory::load(&root.join("data"));
+        let session = pending
+            .begin("ws-1", &root, "Cargo.lock", Some(11), 1_000, &[])
+            .expect("begin");
+        let id = session.write_id.as_str();
+        write_chunk_core(&pending, id, 0, "aGVsbG8g").expect("first chunk");
+        let error = pending
+            .write_chunk(id, 0, b"again")
+            .expect_err("replayed chunk");
+        assert!(error.contains("offset 0"), "{error}");
+        assert_eq!(
+            std::fs::read_to_string(root.join("Cargo.lock")).expect("read"),
+            "old",
+            "the target is untouched until commit"
+        );
+        let progress = pending.write_chunk(id, 6, b"world").expect("second chunk");
+        assert_eq!(progress.written, 11);
+
+        let committed = commit_write_core(
+            &pending,
+            &cache,
+            &history,
+            id,
+            Some(&sha256_hex(b"hello world")),
+        )
+        .expect("commit");
+        assert_eq!(committed.bytes, 11);
+        assert_eq!(
+            std::fs::read_to_string(root.join("Cargo.lock")).expect("read"),
+            "hello world"
+        );
+        let lock = root.canonicalize().expect("root").join("Cargo.lock");
+        let versions = history.history(&lock.to_string_lossy()).versions;
+        assert_eq!(
+            versions
+                .iter()
+                .map(|version| version.source.as_str())
+                .collect::<Vec<_>>(),
+            ["commit_write", "original"]
+        );
+        assert_eq!(std::fs::read_dir(&root).expect("list").count(), 2);
+        assert!(pending.commit(id, None).is_err(), "commit is single-use");
+
+        let session = pending
+            .begin("ws-1", &root, "assets/gen/logo.bin", None, 1_000, &[])
+            .expect("begin in a new directory");
+        assert!(
+            !root.join("assets").exists(),
+            "directories are only created on commit"
+        );
+        pending
+            .write_chunk(&session.write_id, 0, b"x")
+            .expect("chunk");
+        let error = pending
+            .commit(&session.write_id, Some("00"))
+            .expect_err("bad checksum");
+        assert!(error.contains("Checksum mismatch"), "{error}");
+        assert_eq!(
+            std::fs::read_dir(&root).expect("list").count(),
+            2,
+            "a failed commit removes the partial file"
+        );
+        assert!(!root.join("assets").exists());
+
+        let session = pending
+            .begin("ws-1", &root, "assets/gen/logo.bin", Some(1), 1_000, &[])
+            .expect("begin again");
+        pending
+            .write_chunk(&session.write_id, 0, b"x")
+            .expect("chunk");
+        let committed =
+            commit_write_core(&pending, &cache, &history, &session.write_id, None).expect("commit");
+        assert_eq!(committed.workspace_id, "ws-1");
+        assert_eq!(
+            std::fs::read(root.join("assets/gen/logo.bin")).expect("read"),
+            b"x"
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn refuses_escapes_protected_paths_and_oversized_writes() {
+        let root = temp_root();
+        let pending = PendingWrites::default();
+        for path in ["../outside", "/etc/passwd", "", "src/.."] {
+            assert!(
+                pending
+                    .begin("ws-1", &root, path, None, 1_000, &[])
+                    .is_err(),
+                "{path:?} must be refused"
+            );
+        }
+        let protected = vec![".env".to_string()];
+        let error = pending
+            .begin("ws-1", &root, ".env", None, 1_000, &protected)
+            .expect_err("protected");
+        assert!(error.contains("protected"), "{error}");
+        assert!(pending
+            .begin("ws-1", &root, "big.bin", Some(2_000), 1_000, &[])
+            .is_err());
+
+        let session = pending
+            .begin("ws-1", &root, "big.bin", None, 4, &[])
+            .expect("begin");
+        let error = pending
+            .write_chunk(&session.write_id, 0, b"12345")
+            .expect_err("over the limit");
+        assert!(error.contains("write limit"), "{error}");
+        pending.abort(&session.write_id).expect("abort");
+        assert!(pending.write_chunk(&session.write_id, 0, b"1").is_err());
+
+        let session = pending
+            .begin("ws-1", &root, "idle.bin", None, 4, &[])
+            .expect("begin");
+        pending.expire(Instant::now() + WRITE_IDLE_TTL);
+        assert!(pending.commit(&session.write_id, None).is_err());
+        assert_eq!(std::fs::read_dir(&root).expect("list").count(), 0);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
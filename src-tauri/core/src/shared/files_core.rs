@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::codex::config::{read_project_doc_config, ProjectDocConfig};
+use crate::codex::home as codex_home;
+use crate::files::io::TextFileResponse;
+use crate::files::ops::{delete_with_policy, read_with_policy, write_with_policy};
+use crate::files::policy::{policy_for, FileKind, FileScope};
+use crate::shared::codex_core::{
+    resolve_codex_home_for_workspace_core, resolve_workspace_and_parent,
+};
+use crate::shared::file_history_core::FileHistory;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::protected_paths_core::ensure_write_allowed;
+use crate::types::{AppSettings, WorkspaceEntry};
+
+const AGENTS_FILENAME: &str = "AGENTS.md";
+const AGENTS_OVERRIDE_FILENAME: &str = "AGENTS.override.md";
+/// Joins the global instructions and the project docs in what Codex sends.
+const PROJECT_DOC_SEPARATOR: &str = "\n\n--- project-doc ---\n\n";
+
+/// One instruction file that contributes to a workspace's effective instructions.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionSource {
+    pub scope: FileScope,
+    pub path: String,
+    /// Size on disk.
+    pub bytes: u64,
+    /// Cut short because the project doc budget ran out.
+    pub truncated: bool,
+    /// Lower-priority files in the same directory that Codex skips because of this one.
+    pub shadows: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveInstructions {
+    pub codex_home: Option<String>,
+    /// Nearest ancestor of the workspace with a `.git`, or the workspace itself.
+    pub project_root: String,
+    pub max_bytes: u64,
+    /// Global file first, then project files from the project root down.
+    pub sources: Vec<InstructionSource>,
+    /// The instructions as the agent receives them.
+    pub content: String,
+}
+
+fn resolve_default_codex_home() -> Result<PathBuf, String> {
+    codex_home::resolve_default_codex_home()
+        .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())
+}
+
+async fn resolve_workspace_root(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<PathBuf, String> {
+    let workspaces = workspaces.lock().await;
+    let entry = workspaces
+        .get(workspace_id)
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    Ok(PathBuf::from(&entry.path))
+}
+
+pub async fn resolve_root_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    scope: FileScope,
+    workspace_id: Option<&str>,
+) -> Result<PathBuf, String> {
+    match scope {
+        FileScope::Global => resolve_default_codex_home(),
+        FileScope::Workspace => {
+            let workspace_id =
+                workspace_id.ok_or_else(|| "workspaceId is required".to_string())?;
+            resolve_workspace_root(workspaces, workspace_id).await
+        }
+    }
+}
+
+pub async fn file_read_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    scope: FileScope,
+    kind: FileKind,
+    workspace_id: Option<String>,
+) -> Result<TextFileResponse, String> {
+    let policy = policy_for(scope, kind)?;
+    let root = resolve_root_core(workspaces, scope, workspace_id.as_deref()).await?;
+    read_with_policy(&root, policy)
+}
+
+pub async fn file_write_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    file_history: &FileHistory,
+    scope: FileScope,
+    kind: FileKind,
+    workspace_id: Option<String>,
+    content: String,
+) -> Result<(), String> {
+    let policy = policy_for(scope, kind)?;
+    let root = resolve_root_core(workspaces, scope, workspace_id.as_deref()).await?;
+    let protected = app_settings.lock().await.protected_path_globs.clone();
+    let target = root.join(policy.filename);
+    ensure_write_allowed(&protected, Some(&root), &target)?;
+    let before = std::fs::read(&target).ok();
+    write_with_policy(&root, policy, &content)?;
+    // History is best effort; the write itself already succeeded.
+    if let Err(err) =
+        file_history.record_write(&target, before.as_deref(), content.as_bytes(), "file_write")
+    {
+        tracing::warn!(path = %target.display(), "failed to record file history: {err}");
+    }
+    Ok(())
+}
+
+pub async fn file_delete_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+    scope: FileScope,
+    kind: FileKind,
+    workspace_id: Option<String>,
+) -> Result<bool, String> {
+    if kind == FileKind::Config {
+        return Err("config.toml can't be deleted".to_string());
+    }
+    let policy = policy_for(scope, kind)?;
+    let root = resolve_root_core(workspaces, scope, workspace_id.as_deref()).await?;
+    let protected = app_settings.lock().await.protected_path_globs.clone();
+    ensure_write_allowed(&protected, Some(&root), &root.join(policy.filename))?;
+    delete_with_policy(&root, policy)
+}
+
+fn find_project_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+/// Reads up to `limit` bytes; `None` for missing, unreadable or blank files.
+fn read_instruction_file(path: &Path, limit: u64) -> Option<(String, u64)> {
+    let metadata = std::fs::metadata(path).ok().filter(|meta| meta.is_file())?;
+    let mut buffer = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(limit)
+        .read_to_end(&mut buffer)
+        .ok()?;
+    let content = String::from_utf8_lossy(&buffer).to_string();
+    if content.trim().is_empty() {
+        return None;
+    }
+    Some((content, metadata.len()))
+}
+
+/// The first non-blank candidate in `dir`, with the candidates it shadows.
+fn pick_instruction_file(
+    dir: &Path,
+    candidates: &[&str],
+    limit: u64,
+) -> Option<(PathBuf, String, u64, Vec<String>)> {
+    let mut found = None;
+    let mut shadows = Vec::new();
+    for name in candidates {
+        let path = dir.join(name);
+        if found.is_some() {
+            if path.is_file() {
+                shadows.push(path.to_string_lossy().to_string());
+            }
+            continue;
+        }
+        if let Some((content, bytes)) = read_instruction_file(&path, limit) {
+            found = Some((path, content, bytes));
+        }
+    }
+    found.map(|(path, content, bytes)| (path, content, bytes, shadows))
+}
+
+/// Mirrors how Codex assembles instructions: `AGENTS.override.md` or `AGENTS.md` from
+/// the Codex home, then one file per directory from the project root down to `cwd`
+/// (override first, then `AGENTS.md`, then the configured fallbacks), limited to
+/// `max_bytes` in total.
+pub fn collect_effective_instructions(
+    codex_home: Option<&Path>,
+    cwd: &Path,
+    config: &ProjectDocConfig,
+) -> EffectiveInstructions {
+    let mut sources = Vec::new();
+    let global = codex_home.and_then(|home| {
+        pick_instruction_file(home, &[AGENTS_OVERRIDE_FILENAME, AGENTS_FILENAME], u64::MAX)
+    });
+    let global_content = global.map(|(path, content, bytes, shadows)| {
+        sources.push(InstructionSource {
+            scope: FileScope::Global,
+            path: path.to_string_lossy().to_string(),
+            bytes,
+            truncated: false,
+            shadows,
+        });
+        content.trim().to_string()
+    });
+
+    let project_root = find_project_root(cwd);
+    let mut dirs = cwd
+        .ancestors()
+        .take_while(|dir| dir.starts_with(&project_root))
+        .collect::<Vec<_>>();
+    dirs.reverse();
+    let mut candidates = vec![AGENTS_OVERRIDE_FILENAME, AGENTS_FILENAME];
+    for name in &config.fallback_filenames {
+        if !candidates.contains(&name.as_str()) {
+            candidates.push(name);
+        }
+    }
+    let mut remaining = config.max_bytes;
+    let mut project_docs = Vec::new();
+    for dir in dirs {
+        if remaining == 0 {
+            break;
+        }
+        let Some((path, content, bytes, shadows)) =
+            pick_instruction_file(dir, &candidates, remaining)
+        else {
+            continue;
+        };
+        let truncated = bytes > remaining;
+        remaining -= bytes.min(remaining);
+        sources.push(InstructionSource {
+            scope: FileScope::Workspace,
+            path: path.to_string_lossy().to_string(),
+            truncated,
+            bytes,
+            shadows,
+        });
+        project_docs.push(content);
+    }
+
+    let project_content = (!project_docs.is_empty()).then(|| project_docs.join("\n\n"));
+    let content = match (global_content, project_content) {
+        (Some(global), Some(project)) => format!("{global}{PROJECT_DOC_SEPARATOR}{project}"),
+        (Some(only), None) | (None, Some(only)) => only,
+        (None, None) => String::new(),
+    };
+    EffectiveInstructions {
+        codex_home: codex_home.map(|home| home.to_string_lossy().to_string()),
+        project_root: project_root.to_string_lossy().to_string(),
+        max_bytes: config.max_bytes,
+        sources,
+        content,
+    }
+}
+
+/// The merged instructions an agent started in the workspace will see.
+pub async fn effective_instructions_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<EffectiveInstructions, String> {
+    let (entry, _) = resolve_workspace_and_parent(workspaces, workspace_id).await?;
+    let codex_home = resolve_codex_home_for_workspace_core(workspaces, workspace_id)
+        .await
+        .ok();
+    let config = codex_home
+        .as_deref()
+        .map(read_project_doc_config)
+        .unwrap_or_default();
+    Ok(collect_effective_instructions(
+        codex_home.as_deref(),
+        Path::new(&entry.path),
+        &config,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn merges_global_and_project_instructions() {
+        let base =
+            std::env::temp_dir().join(format!("codex-monitor-instructions-{}", Uuid::new_v4()));
+        let home = base.join("home");
+        let repo = base.join("repo");
+        let cwd = repo.join("crates").join("core");
+        std::fs::create_dir_all(&home).expect("home");
+        std::fs::create_dir_all(repo.join(".git")).expect("git dir");
+        std::fs::create_dir_all(&cwd).expect("cwd");
+        std::fs::write(home.join("AGENTS.md"), "global\n").expect("global agents");
+        std::fs::write(repo.join("AGENTS.md"), "shadowed").expect("repo agents");
+        std::fs::write(repo.join("AGENTS.override.md"), "repo override").expect("override");
+        std::fs::write(repo.join("crates").join("NOTES.md"), "   ").expect("blank fallback");
+        std::fs::write(cwd.join("NOTES.md"), "core notes that run long").expect("fallback");
+
+        let config = ProjectDocConfig {
+            max_bytes: 20,
+            fallback_filenames: vec!["NOTES.md".to_string()],
+        };
+        let effective = collect_effective_instructions(Some(&home), &cwd, &config);
+        assert_eq!(
+            effective.content,
+            "global\n\n--- project-doc ---\n\nrepo override\n\ncore no"
+        );
+        assert_eq!(effective.project_root, repo.to_string_lossy());
+        let summary = effective
+            .sources
+            .iter()
+            .map(|source| (source.scope, source.truncated, source.shadows.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (FileScope::Global, false, 0),
+                (FileScope::Workspace, false, 1),
+                (FileScope::Workspace, true, 0),
+            ]
+        );
+
+        let without_home = collect_effective_instructions(None, &cwd, &ProjectDocConfig::default());
+        assert_eq!(without_home.content, "repo override");
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -3,6 +3,7 @@
 use std::path::PathBuf;
 
 use crate::shared::process_core::tokio_command;
+use crate::shared::process_limits_core::{acquire_process_slot, ProcessKind};
 use crate::utils::{git_env_path, resolve_git_binary};
 
 fn format_git_error(stdout: &[u8], stderr: &[u8]) -> String {
@@ -20,8 +21,9 @@ fn format_git_error(stdout: &[u8], stderr: &[u8]) -> String {
     }
 }
 
-pub(crate) async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Result<String, String> {
+pub async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Result<String, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
     let output = tokio_command(git_bin)
         .args(args)
         .current_dir(repo_path)
@@ -35,7 +37,29 @@ pub(crate) async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Resul
     Err(format_git_error(&output.stdout, &output.stderr))
 }
 
-pub(crate) async fn run_git_command_owned(
+/// `run_git_command` with extra environment, e.g. `GIT_INDEX_FILE` for a scratch index.
+pub async fn run_git_command_with_env(
+    repo_path: &PathBuf,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Result<String, String> {
+    let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
+    let output = tokio_command(git_bin)
+        .args(args)
+        .current_dir(repo_path)
+        .env("PATH", git_env_path())
+        .envs(envs.iter().copied())
+        .output()
+        .await
+        .map_err(|err| format!("Failed to run git: {err}"))?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+    Err(format_git_error(&output.stdout, &output.stderr))
+}
+
+pub async fn run_git_command_owned(
     repo_path: PathBuf,
     args_owned: Vec<String>,
 ) -> Result<String, String> {
@@ -46,11 +70,12 @@ pub(crate) async fn run_git_command_owned(
     run_git_command(&repo_path, &arg_refs).await
 }
 
-pub(crate) async fn run_git_command_bytes(
+pub async fn run_git_command_bytes(
     repo_path: &PathBuf,
     args: &[&str],
 ) -> Result<Vec<u8>, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
     let output = tokio_command(git_bin)
         .args(args)
         .current_dir(repo_path)
@@ -64,8 +89,9 @@ pub(crate) async fn run_git_command_bytes(
     Err(format_git_error(&output.stdout, &output.stderr))
 }
 
-pub(crate) async fn run_git_diff(repo_path: &PathBuf, args: &[&str]) -> Result<Vec<u8>, String> {
+pub async fn run_git_diff(repo_path: &PathBuf, args: &[&str]) -> Result<Vec<u8>, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
     let output = tokio_command(git_bin)
         .args(args)
         .current_dir(repo_path)
@@ -79,12 +105,13 @@ pub(crate) async fn run_git_diff(repo_path: &PathBuf, args: &[&str]) -> Result<V
     Err(format_git_error(&output.stdout, &output.stderr))
 }
 
-pub(crate) fn is_missing_worktree_error(error: &str) -> bool {
+pub fn is_missing_worktree_error(error: &str) -> bool {
     error.contains("is not a working tree")
 }
 
-pub(crate) async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Result<bool, String> {
+pub async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Result<bool, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
     let status = tokio_command(git_bin)
         .args(["show-ref", "--verify", &format!("refs/heads/{branch}")])
         .current_dir(repo_path)
@@ -95,8 +122,9 @@ pub(crate) async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Resu
     Ok(status.success())
 }
 
-pub(crate) async fn git_remote_exists(repo_path: &PathBuf, remote: &str) -> Result<bool, String> {
+pub async fn git_remote_exists(repo_path: &PathBuf, remote: &str) -> Result<bool, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
     let status = tokio_command(git_bin)
         .args(["remote", "get-url", remote])
         .current_dir(repo_path)
@@ -107,12 +135,13 @@ pub(crate) async fn git_remote_exists(repo_path: &PathBuf, remote: &str) -> Resu
     Ok(status.success())
 }
 
-pub(crate) async fn git_remote_branch_exists_live(
+pub async fn git_remote_branch_exists_live(
     repo_path: &PathBuf,
     remote: &str,
     branch: &str,
 ) -> Result<bool, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
     let output = tokio_command(git_bin)
         .args([
             "ls-remote",
@@ -131,12 +160,13 @@ pub(crate) async fn git_remote_branch_exists_live(
     Err(format_git_error(&output.stdout, &output.stderr))
 }
 
-pub(crate) async fn git_remote_branch_exists_local(
+pub async fn git_remote_branch_exists_local(
     repo_path: &PathBuf,
     remote: &str,
     branch: &str,
 ) -> Result<bool, String> {
     let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
     let status = tokio_command(git_bin)
         .args([
             "show-ref",
@@ -151,7 +181,7 @@ pub(crate) async fn git_remote_branch_exists_local(
     Ok(status.success())
 }
 
-pub(crate) async fn git_list_remotes(repo_path: &PathBuf) -> Result<Vec<String>, String> {
+pub async fn git_list_remotes(repo_path: &PathBuf) -> Result<Vec<String>, String> {
     let output = run_git_command(repo_path, &["remote"]).await?;
     Ok(output
         .lines()
@@ -161,7 +191,7 @@ pub(crate) async fn git_list_remotes(repo_path: &PathBuf) -> Result<Vec<String>,
         .collect())
 }
 
-pub(crate) async fn git_find_remote_for_branch_live(
+pub async fn git_find_remote_for_branch_live(
     repo_path: &PathBuf,
     branch: &str,
 ) -> Result<Option<String>, String> {
@@ -183,7 +213,7 @@ pub(crate) async fn git_find_remote_for_branch_live(
     Ok(None)
 }
 
-pub(crate) async fn git_find_remote_tracking_branch_local(
+pub async fn git_find_remote_tracking_branch_local(
     repo_path: &PathBuf,
     branch: &str,
 ) -> Result<Option<String>, String> {
@@ -203,7 +233,7 @@ pub(crate) async fn git_find_remote_tracking_branch_local(
     Ok(None)
 }
 
-pub(crate) async fn unique_branch_name_live(
+pub async fn unique_branch_name_live(
     repo_path: &PathBuf,
     desired: &str,
     remote: Option<&str>,
@@ -234,7 +264,7 @@ pub(crate) async fn unique_branch_name_live(
     Err("Unable to find an available branch name.".to_string())
 }
 
-pub(crate) async fn git_get_origin_url(repo_path: &PathBuf) -> Option<String> {
+pub async fn git_get_origin_url(repo_path: &PathBuf) -> Option<String> {
     run_git_command(repo_path, &["remote", "get-url", "origin"])
         .await
         .ok()
@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Locales the backend can render user-facing messages in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Tr,
+}
+
+impl Locale {
+    /// Accepts tags like `tr`, `tr-TR` or `tr_TR`; anything unknown falls back to English.
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag
+            .trim()
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "tr" => Locale::Tr,
+            _ => Locale::En,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Locale::En => 0,
+            Locale::Tr => 1,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Locale::Tr,
+            _ => Locale::En,
+        }
+    }
+}
+
+/// Keys for user-facing backend messages; each key has a template per locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKey {
+    WorkspaceNotFound,
+    WorkspaceNotConnected,
+    WorkspaceRootResolveFailed,
+    FileOpenFailed,
+    FileInvalidPath,
+    FileMetadataFailed,
+    FileNotAFile,
+    FileReadFailed,
+    FileNotUtf8,
+}
+
+impl MessageKey {
+    /// Message template; `{error}` is replaced with the underlying error detail.
+    fn template(self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (MessageKey::WorkspaceNotFound, Locale::En) => "workspace not found",
+            (MessageKey::WorkspaceNotFound, Locale::Tr) => "çalışma alanı bulunamadı",
+            (MessageKey::WorkspaceNotConnected, Locale::En) => "workspace not connected",
+            (MessageKey::WorkspaceNotConnected, Locale::Tr) => "çalışma alanı bağlı değil",
+            (MessageKey::WorkspaceRootResolveFailed, Locale::En) => {
+                "Failed to resolve workspace root: {error}"
+            }
+            (MessageKey::WorkspaceRootResolveFailed, Locale::Tr) => {
+                "Çalışma alanı kökü çözümlenemedi: {error}"
+            }
+            (MessageKey::FileOpenFailed, Locale::En) => "Failed to open file: {error}",
+            (MessageKey::FileOpenFailed, Locale::Tr) => "Dosya açılamadı: {error}",
+            (MessageKey::FileInvalidPath, Locale::En) => "Invalid file path",
+            (MessageKey::FileInvalidPath, Locale::Tr) => "Geçersiz dosya yolu",
+            (MessageKey::FileMetadataFailed, Locale::En) => {
+                "Failed to read file metadata: {error}"
+            }
+            (MessageKey::FileMetadataFailed, Locale::Tr) => {
+                "Dosya bilgileri okunamadı: {error}"
+            }
+            (MessageKey::FileNotAFile, Locale::En) => "Path is not a file",
+            (MessageKey::FileNotAFile, Locale::Tr) => "Yol bir dosya değil",
+            (MessageKey::FileReadFailed, Locale::En) => "Failed to read file: {error}",
+            (MessageKey::FileReadFailed, Locale::Tr) => "Dosya okunamadı: {error}",
+            (MessageKey::FileNotUtf8, Locale::En) => "File is not valid UTF-8",
+            (MessageKey::FileNotUtf8, Locale::Tr) => "Dosya geçerli UTF-8 değil",
+        }
+    }
+}
+
+static CURRENT_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+/// Switches the process-wide message locale; called when settings load or change.
+pub fn set_locale(tag: &str) {
+    CURRENT_LOCALE.store(Locale::from_tag(tag).as_u8(), Ordering::Relaxed);
+}
+
+pub fn current_locale() -> Locale {
+    Locale::from_u8(CURRENT_LOCALE.load(Ordering::Relaxed))
+}
+
+pub fn message_in(locale: Locale, key: MessageKey, error: Option<&str>) -> String {
+    let template = key.template(locale);
+    match error {
+        Some(error) => template.replace("{error}", error),
+        None => template.to_string(),
+    }
+}
+
+/// Renders `key` in the current locale.
+pub fn message(key: MessageKey) -> String {
+    message_in(current_locale(), key, None)
+}
+
+/// Renders `key` in the current locale with `error` substituted for `{error}`.
+pub fn message_with(key: MessageKey, error: impl std::fmt::Display) -> String {
+    message_in(current_locale(), key, Some(&error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_locale_tags() {
+        assert_eq!(Locale::from_tag("tr-TR"), Locale::Tr);
+        assert_eq!(Locale::from_tag(" TR_tr "), Locale::Tr);
+        assert_eq!(Locale::from_tag("en-US"), Locale::En);
+        assert_eq!(Locale::from_tag("de"), Locale::En);
+        assert_eq!(Locale::from_tag(""), Locale::En);
+    }
+
+    #[test]
+    fn renders_templates_per_locale() {
+        assert_eq!(
+            message_in(Locale::En, MessageKey::FileOpenFailed, Some("denied")),
+            "Failed to open file: denied"
+        );
+        assert_eq!(
+            message_in(Locale::Tr, MessageKey::FileOpenFailed, Some("denied")),
+            "Dosya açılamadı: denied"
+        );
+        assert_eq!(
+            message_in(Locale::Tr, MessageKey::WorkspaceNotFound, None),
+            "çalışma alanı bulunamadı"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+/// How long a cached list page stays fresh.
+pub const LIST_CACHE_TTL: Duration = Duration::from_secs(15);
+/// Upper bound on pages walked for `aggregate`, in case the server keeps returning a cursor.
+const MAX_AGGREGATE_PAGES: usize = 100;
+
+/// `aggregate` returns every page at once; `refresh` skips the cache for this call.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListOptions {
+    pub aggregate: bool,
+    pub refresh: bool,
+}
+
+fn list_result(response: &Value) -> &Value {
+    response.get("result").unwrap_or(response)
+}
+
+fn next_cursor(response: &Value) -> Option<String> {
+    let result = list_result(response);
+    result
+        .get("nextCursor")
+        .or_else(|| result.get("next_cursor"))
+        .and_then(Value::as_str)
+        .filter(|cursor| !cursor.is_empty())
+        .map(str::to_string)
+}
+
+/// Fetches the page at `cursor`, or with `aggregate` walks every page from the start and
+/// returns `{ data, nextCursor, pages }` with all items. `nextCursor` is only set when
+/// the page limit was hit before the end of the list.
+pub async fn fetch_list<F, Fut>(
+    cursor: Option<String>,
+    aggregate: bool,
+    mut fetch_page: F,
+) -> Result<Value, String>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<Value, String>>,
+{
+    if !aggregate {
+        return fetch_page(cursor).await;
+    }
+    let mut data = Vec::new();
+    let mut cursor = None;
+    let mut pages = 0;
+    while pages < MAX_AGGREGATE_PAGES {
+        let response = fetch_page(cursor.take()).await?;
+        pages += 1;
+        if let Some(items) = list_result(&response).get("data").and_then(Value::as_array) {
+            data.extend(items.iter().cloned());
+        }
+        cursor = next_cursor(&response);
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(json!({ "data": data, "nextCursor": cursor, "pages": pages }))
+}
+
+struct CachedList {
+    workspace_id: String,
+    stored_at: Instant,
+    value: Value,
+}
+
+/// Short-lived cache of list responses, keyed by method, workspace and request params.
+pub struct ListCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedList>>,
+}
+
+impl Default for ListCache {
+    fn default() -> Self {
+        Self::new(LIST_CACHE_TTL)
+    }
+}
+
+impl ListCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn key(method: &str, workspace_id: &str, params: &Value) -> String {
+        format!("{method}\u{0}{workspace_id}\u{0}{params}")
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CachedList>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the cached value for `key` unless it expired or `refresh` is set; otherwise
+    /// runs `fetch` and caches a successful result.
+    pub async fn get_or_fetch<F, Fut>(
+        &self,
+        key: String,
+        workspace_id: &str,
+        refresh: bool,
+        fetch: F,
+    ) -> Result<Value, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, String>>,
+    {
+        if !refresh {
+            if let Some(entry) = self.lock().get(&key) {
+                if entry.stored_at.elapsed() < self.ttl {
+                    return Ok(entry.value.clone());
+                }
+            }
+        }
+        let value = fetch().await?;
+        let mut entries = self.lock();
+        entries.retain(|_, entry| entry.stored_at.elapsed() < self.ttl);
+        entries.insert(
+            key,
+            CachedList {
+                workspace_id: workspace_id.to_string(),
+                stored_at: Instant::now(),
+                value: value.clone(),
+            },
+        );
+        Ok(value)
+    }
+
+    /// Drops every cached list of the workspace, e.g. after a thread was started or archived.
+    pub fn invalidate_workspace(&self, workspace_id: &str) {
+        self.lock()
+            .retain(|_, entry| entry.workspace_id != workspace_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn aggregates_pages_and_caches_until_invalidated() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let calls = Cell::new(0);
+        let fetch_page = |cursor: Option<String>| {
+            calls.set(calls.get() + 1);
+            let response = match cursor.as_deref() {
+                None => json!({ "result": { "data": [1, 2], "nextCursor": "p2" } }),
+                Some("p2") => json!({ "result": { "data": [3], "nextCursor": "p3" } }),
+                _ => json!({ "result": { "data": [4], "nextCursor": null } }),
+            };
+            async move { Ok(response) }
+        };
+
+        let single = runtime
+            .block_on(fetch_list(Some("p2".to_string()), false, fetch_page))
+            .expect("page");
+        assert_eq!(single["result"]["data"], json!([3]));
+
+        let cache = ListCache::default();
+        let key = ListCache::key("apps_list", "ws-1", &json!({ "aggregate": true }));
+        calls.set(0);
+        let all = runtime
+            .block_on(cache.get_or_fetch(key.clone(), "ws-1", false, || {
+                fetch_list(None, true, fetch_page)
+            }))
+            .expect("aggregate");
+        assert_eq!(
+            all,
+            json!({ "data": [1, 2, 3, 4], "nextCursor": null, "pages": 3 })
+        );
+        assert_eq!(calls.get(), 3);
+
+        let cached = runtime
+            .block_on(cache.get_or_fetch(key.clone(), "ws-1", false, || async {
+                Err("should not fetch".to_string())
+            }))
+            .expect("cached");
+        assert_eq!(cached, all);
+
+        cache.invalidate_workspace("ws-1");
+        let refetched = runtime.block_on(
+            cache.get_or_fetch(key, "ws-1", false, || async { Err("fetched".to_string()) }),
+        );
+        assert_eq!(refetched, Err("fetched".to_string()));
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::backend::events::{AppServerEvent, EventSink};
+
+/// How often the background prober lists MCP servers on each connected workspace.
+pub const MCP_PROBE_INTERVAL_SECS: u64 = 120;
+const MCP_PROBE_TIMEOUT: Duration = Duration::from_secs(20);
+const MAX_PROBE_HISTORY: usize = 30;
+/// How far ahead of an OAuth token's expiry `mcp-auth-expiring` is emitted.
+const MCP_AUTH_EXPIRY_WARNING_MS: u64 = 15 * 60 * 1000;
+/// `authStatus` values that mean the server is signed in through OAuth.
+const OAUTH_STATUSES: &[&str] = &["oAuth", "oauth"];
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpProbe {
+    pub at: u64,
+    pub ok: bool,
+    /// Round trip of the `mcpServerStatus/list` call that covered this server.
+    pub latency_ms: Option<u64>,
+    pub tool_count: Option<usize>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerHealth {
+    pub workspace_id: String,
+    pub server: String,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub last_latency_ms: Option<u64>,
+    /// Mean latency of the successful probes in `history`.
+    pub avg_latency_ms: Option<u64>,
+    /// Oldest first.
+    pub history: Vec<McpProbe>,
+    /// `authStatus` from the last successful probe, e.g. `oAuth` or `notLoggedIn`.
+    pub auth_status: Option<String>,
+    /// Token expiry (ms) when the app-server reports one.
+    pub auth_expires_at: Option<u64>,
+    /// Set once `mcp-auth-expiring` went out for the current token.
+    #[serde(skip)]
+    auth_warned: bool,
+}
+
+/// Params of the `mcp-auth-expiring` event.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct McpAuthExpiring {
+    pub workspace_id: String,
+    pub server: String,
+    pub auth_status: Option<String>,
+    pub expires_at: Option<u64>,
+    /// The token already expired or the server dropped to `notLoggedIn`.
+    pub expired: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct McpAuth {
+    status: Option<String>,
+    expires_at: Option<u64>,
+}
+
+/// Probe history per workspace and MCP server, fed by `probe_once`.
+#[derive(Default)]
+pub struct McpHealthMonitor {
+    servers: std::sync::Mutex<HashMap<(String, String), McpServerHealth>>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Server name plus tool count, or the startup error the app-server reported for it.
+fn parse_server_statuses(response: &Value) -> Vec<(String, Result<usize, String>)> {
+    let result = response.get("result").unwrap_or(response);
+    let entries = result
+        .get("data")
+        .and_then(Value::as_array)
+        .or_else(|| result.as_array());
+    let Some(entries) = entries else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let name = entry.get("name").and_then(Value::as_str)?.to_string();
+            let error = entry
+                .get("error")
+                .or_else(|| entry.get("startupError"))
+                .and_then(Value::as_str)
+                .filter(|error| !error.trim().is_empty());
+            let status = match error {
+                Some(error) => Err(error.to_string()),
+                None => Ok(entry
+                    .get("tools")
+                    .map(|tools| {
+                        tools
+                            .as_object()
+                            .map(|map| map.len())
+                            .or_else(|| tools.as_array().map(Vec::len))
+                            .unwrap_or(0)
+                    })
+                    .unwrap_or(0)),
+            };
+            Some((name, status))
+        })
+        .collect()
+}
+
+/// Accepts seconds or milliseconds since the epoch, as a number or numeric string.
+fn timestamp_millis(value: &Value) -> Option<u64> {
+    let raw = value
+        .as_u64()
+        .or_else(|| value.as_f64().map(|value| value as u64))
+        .or_else(|| value.as_str().and_then(|value| value.trim().parse().ok()))?;
+    Some(if raw < 10_000_000_000 {
+        raw * 1000
+    } else {
+        raw
+    })
+}
+
+/// `authStatus` and token expiry per server, where the app-server exposes them.
+fn parse_server_auth(response: &Value) -> Vec<(String, McpAuth)> {
+    let result = response.get("result").unwrap_or(response);
+    let entries = result
+        .get("data")
+        .and_then(Value::as_array)
+        .or_else(|| result.as_array());
+    let Some(entries) = entries else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let name = entry.get("name").and_then(Value::as_str)?.to_string();
+            let status = entry
+                .get("authStatus")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let expires_at = ["expiresAt", "oauthExpiresAt", "tokenExpiresAt"]
+                .iter()
+                .find_map(|key| {
+                    entry
+                        .get(*key)
+                        .or_else(|| entry.get("auth").and_then(|auth| auth.get(*key)))
+                })
+                .and_then(timestamp_millis);
+            Some((name, McpAuth { status, expires_at }))
+        })
+        .collect()
+}
+
+impl McpHealthMonitor {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), McpServerHealth>> {
+        self.servers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Adds a probe result. Returns the updated health when it should be announced: the
+    /// server started failing (including on first sight) or recovered.
+    fn record(&self, workspace_id: &str, server: &str, probe: McpProbe) -> Option<McpServerHealth> {
+        let mut servers = self.lock();
+        let key = (workspace_id.to_string(), server.to_string());
+        let is_new = !servers.contains_key(&key);
+        let health = servers.entry(key).or_insert_with(|| McpServerHealth {
+            workspace_id: workspace_id.to_string(),
+            server: server.to_string(),
+            healthy: true,
+            consecutive_failures: 0,
+            last_latency_ms: None,
+            avg_latency_ms: None,
+            history: Vec::new(),
+            auth_status: None,
+            auth_expires_at: None,
+            auth_warned: false,
+        });
+        let was_healthy = health.healthy;
+        health.healthy = probe.ok;
+        health.consecutive_failures = if probe.ok {
+            0
+        } else {
+            health.consecutive_failures + 1
+        };
+        health.last_latency_ms = probe.latency_ms;
+        health.history.push(probe);
+        let excess = health.history.len().saturating_sub(MAX_PROBE_HISTORY);
+        health.history.drain(..excess);
+        let latencies = health
+            .history
+            .iter()
+            .filter(|probe| probe.ok)
+            .filter_map(|probe| probe.latency_ms)
+            .collect::<Vec<_>>();
+        health.avg_latency_ms =
+            (!latencies.is_empty()).then(|| latencies.iter().sum::<u64>() / latencies.len() as u64);
+        let changed = if is_new {
+            !health.healthy
+        } else {
+            was_healthy != health.healthy
+        };
+        changed.then(|| health.clone())
+    }
+
+    /// Updates a probed server's auth state. Returns the `mcp-auth-expiring` payload once
+    /// per token when it is about to expire, and when an OAuth server turns `notLoggedIn`.
+    fn record_auth(
+        &self,
+        workspace_id: &str,
+        server: &str,
+        auth: McpAuth,
+        now: u64,
+    ) -> Option<McpAuthExpiring> {
+        let mut servers = self.lock();
+        let health = servers.get_mut(&(workspace_id.to_string(), server.to_string()))?;
+        let was_oauth = health
+            .auth_status
+            .as_deref()
+            .is_some_and(|status| OAUTH_STATUSES.contains(&status));
+        let logged_out = was_oauth && auth.status.as_deref() == Some("notLoggedIn");
+        if health.auth_expires_at != auth.expires_at && !logged_out {
+            health.auth_warned = false;
+        }
+        health.auth_status = auth.status;
+        health.auth_expires_at = auth.expires_at;
+        let expiring = auth
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now + MCP_AUTH_EXPIRY_WARNING_MS);
+        if !(logged_out || (expiring && !health.auth_warned)) {
+            return None;
+        }
+        health.auth_warned = true;
+        Some(McpAuthExpiring {
+            workspace_id: workspace_id.to_string(),
+            server: server.to_string(),
+            auth_status: health.auth_status.clone(),
+            expires_at: health.auth_expires_at,
+            expired: logged_out || auth.expires_at.is_some_and(|expires_at| expires_at <= now),
+        })
+    }
+
+    /// Health of every probed server, optionally for one workspace, sorted by workspace
+    /// and server name.
+    pub fn snapshot(&self, workspace_id: Option<&str>) -> Vec<McpServerHealth> {
+        let mut servers = self
+            .lock()
+            .values()
+            .filter(|health| workspace_id.is_none_or(|id| health.workspace_id == id))
+            .cloned()
+            .collect::<Vec<_>>();
+        servers.sort_by(|a, b| {
+            a.workspace_id
+                .cmp(&b.workspace_id)
+                .then(a.server.cmp(&b.server))
+        });
+        servers
+    }
+
+    fn known_servers(&self, workspace_id: &str) -> Vec<String> {
+        self.lock()
+            .keys()
+            .filter(|(id, _)| id == workspace_id)
+            .map(|(_, server)| server.clone())
+            .collect()
+    }
+
+    /// Lists MCP servers on every connected workspace and records one probe per server.
+    /// The app-server doesn't time servers individually, so each server is credited with
+    /// the round trip of the list call. A server that disappears from the list, or a list
+    /// call that fails, counts as a failure. Emits `mcp/healthChanged` when a server
+    /// starts failing or recovers, and `mcp-auth-expiring` ahead of OAuth token expiry.
+    /// The app-server refreshes tokens on its own when it can; the event is for the
+    /// case where the user has to sign in again via `mcp_server_oauth_login`.
+    pub async fn probe_once<E: EventSink>(
+        &self,
+        sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+        event_sink: &E,
+    ) {
+        let sessions = sessions
+            .lock()
+            .await
+            .iter()
+            .map(|(id, session)| (id.clone(), Arc::clone(session)))
+            .collect::<Vec<_>>();
+        self.lock()
+            .retain(|(id, _), _| sessions.iter().any(|(session_id, _)| session_id == id));
+
+        for (workspace_id, session) in sessions {
+            let started = Instant::now();
+            let response = tokio::time::timeout(
+                MCP_PROBE_TIMEOUT,
+                session.send_request(
+                    "mcpServerStatus/list",
+                    json!({ "cursor": null, "limit": null }),
+                ),
+            )
+            .await;
+            let latency_ms = started.elapsed().as_millis() as u64;
+            let at = now_millis();
+            let mut auths = Vec::new();
+            let mut results = match response {
+                Ok(Ok(response)) => {
+                    auths = parse_server_auth(&response);
+                    parse_server_statuses(&response)
+                        .into_iter()
+                        .map(|(server, status)| {
+                            let probe = match status {
+                                Ok(tool_count) => McpProbe {
+                                    at,
+                                    ok: true,
+                                    latency_ms: Some(latency_ms),
+                                    tool_count: Some(tool_count),
+                                    error: None,
+                                },
+                                Err(error) => McpProbe {
+                                    at,
+                                    ok: false,
+                                    latency_ms: Some(latency_ms),
+                                    tool_count: None,
+                                    error: Some(error),
+                                },
+                            };
+                            (server, probe)
+                        })
+                        .collect::<Vec<_>>()
+                }
+                Ok(Err(error)) => self.fail_known(&workspace_id, at, error),
+                Err(_) => self.fail_known(&workspace_id, at, "MCP status request timed out".into()),
+            };
+            for server in self.known_servers(&workspace_id) {
+                if !results.iter().any(|(name, _)| *name == server) {
+                    results.push((
+                        server,
+                        McpProbe {
+                            at,
+                            ok: false,
+                            latency_ms: None,
+                            tool_count: None,
+                            error: Some("Server is no longer reported by the app-server".into()),
+                        },
+                    ));
+                }
+            }
+            for (server, probe) in results {
+                if let Some(health) = self.record(&workspace_id, &server, probe) {
+                    event_sink.emit_app_server_event(AppServerEvent {
+                        workspace_id: workspace_id.clone(),
+                        message: json!({
+                            "method": "mcp/healthChanged",
+                            "params": health,
+                        }),
+                    });
+                }
+            }
+            for (server, auth) in auths {
+                if let Some(expiring) = self.record_auth(&workspace_id, &server, auth, at) {
+                    event_sink.emit_app_server_event(AppServerEvent {
+                        workspace_id: workspace_id.clone(),
+                        message: json!({
+                            "method": "mcp-auth-expiring",
+                            "params": expiring,
+                        }),
+                    });
+                }
+            }
+        }
+    }
+
+    fn fail_known(&self, workspace_id: &str, at: u64, error: String) -> Vec<(String, McpProbe)> {
+        self.known_servers(workspace_id)
+            .into_iter()
+            .map(|server| {
+                (
+                    server,
+                    McpProbe {
+                        at,
+                        ok: false,
+                        latency_ms: None,
+                        tool_count: None,
+                        error: Some(error.clone()),
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(ok: bool, latency_ms: u64) -> McpProbe {
+        McpProbe {
+            at: 0,
+            ok,
+            latency_ms: Some(latency_ms),
+            tool_count: ok.then_some(3),
+            error: (!ok).then(|| "boom".to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_statuses_and_startup_errors() {
+        let response = json!({ "result": { "data": [
+            { "name": "github", "tools": { "a": {}, "b": {} } },
+            { "name": "broken", "tools": {}, "startupError": "spawn failed" }
+        ] } });
+        assert_eq!(
+            parse_server_statuses(&response),
+            vec![
+                ("github".to_string(), Ok(2)),
+                ("broken".to_string(), Err("spawn failed".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn announces_failures_and_recoveries_only() {
+        let monitor = McpHealthMonitor::default();
+        assert!(monitor.record("ws-1", "github", probe(true, 40)).is_none());
+        assert!(monitor.record("ws-1", "github", probe(true, 60)).is_none());
+        let failing = monitor
+            .record("ws-1", "github", probe(false, 10))
+            .expect("failure announced");
+        assert!(!failing.healthy);
+        assert_eq!(failing.avg_latency_ms, Some(50));
+        assert!(monitor.record("ws-1", "github", probe(false, 10)).is_none());
+        assert!(monitor.record("ws-1", "github", probe(true, 20)).is_some());
+        assert!(monitor.record("ws-1", "broken", probe(false, 5)).is_some());
+
+        let health = monitor.snapshot(Some("ws-1"));
+        assert_eq!(health.len(), 2);
+        assert_eq!(health[1].server, "github");
+        assert_eq!(health[1].history.len(), 5);
+        assert_eq!(health[0].consecutive_failures, 1);
+        assert!(monitor.snapshot(Some("ws-2")).is_empty());
+    }
+
+    #[test]
+    fn warns_once_before_oauth_expiry_and_on_logout() {
+        let monitor = McpHealthMonitor::default();
+        monitor.record("ws-1", "linear", probe(true, 10));
+        let now = 1_700_000_000_000;
+        let auth = |status: &str, expires_at: Option<u64>| McpAuth {
+            status: Some(status.to_string()),
+            expires_at,
+        };
+        let far = Some(now + 2 * MCP_AUTH_EXPIRY_WARNING_MS);
+        let soon = Some(now + 60_000);
+
+        assert!(monitor
+            .record_auth("ws-1", "linear", auth("oAuth", far), now)
+            .is_none());
+        let expiring = monitor
+            .record_auth("ws-1", "linear", auth("oAuth", soon), now)
+            .expect("expiry warning");
+        assert!(!expiring.expired);
+        assert!(monitor
+            .record_auth("ws-1", "linear", auth("oAuth", soon), now)
+            .is_none());
+        let logged_out = monitor
+            .record_auth("ws-1", "linear", auth("notLoggedIn", None), now)
+            .expect("logout warning");
+        assert!(logged_out.expired);
+        assert!(monitor
+            .record_auth("ws-1", "unknown", auth("oAuth", soon), now)
+            .is_none());
+
+        let parsed = parse_server_auth(&json!({ "data": [
+            { "name": "linear", "authStatus": "oAuth", "expiresAt": 1_700_000_000 }
+        ] }));
+        assert_eq!(
+            parsed,
+            vec![(
+                "linear".to_string(),
+                McpAuth {
+                    status: Some("oAuth".to_string()),
+                    expires_at: Some(1_700_000_000_000),
+                }
+            )]
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::shared::file_transfer_core::DEFAULT_MAX_READ_BYTES;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::types::WorkspaceEntry;
+
+const MAX_MENTIONS: usize = 20;
+const MAX_SNIPPET_LINES: usize = 200;
+const MAX_SNIPPET_BYTES: usize = 32_000;
+const MAX_SYMBOL_SCAN_FILES: usize = 5_000;
+const SYMBOL_CONTEXT_LINES: usize = 40;
+const MAX_MENTION_FILES: usize = 20_000;
+
+/// Keywords that introduce a definition in common languages; used to find `@symbol` targets.
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn", "struct", "enum", "trait", "impl", "type", "mod", "const", "static", "class",
+    "interface", "function", "def", "let", "var", "func",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MentionKind {
+    File,
+    Symbol,
+    /// Text pinned to a thread rather than read from the workspace.
+    Snippet,
+    Unresolved,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedMention {
+    pub raw: String,
+    pub kind: MentionKind,
+    pub path: Option<String>,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    pub snippet: Option<String>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MentionResolution {
+    pub mentions: Vec<ResolvedMention>,
+    pub context_block: String,
+}
+
+struct MentionTarget {
+    raw: String,
+    name: String,
+    line_range: Option<(usize, usize)>,
+}
+
+/// Extracts `@token` mentions that start a word; trailing punctuation is dropped.
+fn parse_mentions(text: &str) -> Vec<MentionTarget> {
+    let mut targets: Vec<MentionTarget> = Vec::new();
+    let mut previous: Option<char> = None;
+    for (index, ch) in text.char_indices() {
+        let starts_word = previous.is_none_or(|value| value.is_whitespace() || value == '(');
+        previous = Some(ch);
+        if ch != '@' || !starts_word {
+            continue;
+        }
+        let rest = &text[index + 1..];
+        let token_len = rest
+            .find(|value: char| value.is_whitespace() || matches!(value, ')' | '`' | '"' | '\''))
+            .unwrap_or(rest.len());
+        let token = rest[..token_len].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if token.is_empty() {
+            continue;
+        }
+        let (name, line_range) = split_line_range(token);
+        if name.is_empty() || targets.iter().any(|target| target.raw == token) {
+            continue;
+        }
+        targets.push(MentionTarget {
+            raw: token.to_string(),
+            name: name.to_string(),
+            line_range,
+        });
+        if targets.len() >= MAX_MENTIONS {
+            break;
+        }
+    }
+    targets
+}
+
+/// Splits `path:10` or `path:10-20` into the path and a 1-based inclusive line range.
+fn split_line_range(token: &str) -> (&str, Option<(usize, usize)>) {
+    let Some((name, range)) = token.rsplit_once(':') else {
+        return (token, None);
+    };
+    let parsed = match range.split_once('-') {
+        Some((start, end)) => start.parse::<usize>().ok().zip(end.parse::<usize>().ok()),
+        None => range.parse::<usize>().ok().map(|line| (line, line)),
+    };
+    match parsed {
+        Some((start, end)) if start >= 1 && end >= start => (name, Some((start, end))),
+        _ => (token, None),
+    }
+}
+
+fn read_text_within(root: &Path, relative_path: &str, max_bytes: u64) -> Option<String> {
+    let canonical_root = root.canonicalize().ok()?;
+    let canonical_path = canonical_root.join(relative_path).canonicalize().ok()?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return None;
+    }
+    let metadata = std::fs::metadata(&canonical_path).ok()?;
+    if !metadata.is_file() || metadata.len() > max_bytes {
+        return None;
+    }
+    let bytes = std::fs::read(&canonical_path).ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn slice_lines(content: &str, start: usize, end: usize) -> (String, usize, usize, bool) {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = start.max(1).min(lines.len().max(1));
+    let mut end = end.min(lines.len()).max(start);
+    let mut truncated = false;
+    if end - start + 1 > MAX_SNIPPET_LINES {
+        end = start + MAX_SNIPPET_LINES - 1;
+        truncated = true;
+    }
+    let mut snippet = lines
+        .get(start - 1..end)
+        .map(|slice| slice.join("\n"))
+        .unwrap_or_default();
+    if snippet.len() > MAX_SNIPPET_BYTES {
+        let mut cut = MAX_SNIPPET_BYTES;
+        while !snippet.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        snippet.truncate(cut);
+        truncated = true;
+    }
+    (snippet, start, end, truncated)
+}
+
+fn resolve_file_path(name: &str, files: &[String]) -> Option<String> {
+    let normalized = name.trim_start_matches("./").replace('\\', "/");
+    if files.iter().any(|file| file == &normalized) {
+        return Some(normalized);
+    }
+    // Fall back to a unique file-name or path-suffix match (e.g. `@main.rs`).
+    let suffix = format!("/{normalized}");
+    let mut matches = files
+        .iter()
+        .filter(|file| file.ends_with(&suffix) || file.as_str() == normalized);
+    let first = matches.next()?;
+    if matches.next().is_some() {
+        return None;
+    }
+    Some(first.clone())
+}
+
+fn is_identifier(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '$')
+}
+
+fn defines_symbol(line: &str, symbol: &str) -> bool {
+    let tokens: Vec<&str> = line
+        .split(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '$'))
+        .filter(|token| !token.is_empty())
+        .collect();
+    tokens.windows(2).any(|pair| {
+        DEFINITION_KEYWORDS.contains(&pair[0]) && pair[1] == symbol
+    })
+}
+
+fn find_symbol(root: &Path, symbol: &str, files: &[String]) -> Option<(String, usize)> {
+    for file in files.iter().take(MAX_SYMBOL_SCAN_FILES) {
+        let Some(content) = read_text_within(root, file, DEFAULT_MAX_READ_BYTES) else {
+            continue;
+        };
+        if !content.contains(symbol) {
+            continue;
+        }
+        if let Some(index) = content.lines().position(|line| defines_symbol(line, symbol)) {
+            return Some((file.clone(), index + 1));
+        }
+    }
+    None
+}
+
+/// Reads `path` (relative to `root`) or the given 1-based line range of it.
+pub fn resolve_file_range(
+    root: &Path,
+    raw: &str,
+    path: &str,
+    line_range: Option<(usize, usize)>,
+) -> Option<ResolvedMention> {
+    let content = read_text_within(root, path, DEFAULT_MAX_READ_BYTES)?;
+    let (start, end) = line_range.unwrap_or((1, usize::MAX));
+    let (snippet, start, end, truncated) = slice_lines(&content, start, end);
+    Some(ResolvedMention {
+        raw: raw.to_string(),
+        kind: MentionKind::File,
+        path: Some(path.to_string()),
+        start_line: Some(start),
+        end_line: Some(end),
+        snippet: Some(snippet),
+        truncated,
+    })
+}
+
+fn resolve_target(root: &Path, target: &MentionTarget, files: &[String]) -> ResolvedMention {
+    if let Some(path) = resolve_file_path(&target.name, files) {
+        if let Some(mention) = resolve_file_range(root, &target.raw, &path, target.line_range) {
+            return mention;
+        }
+    }
+    if is_identifier(&target.name) {
+        if let Some((path, line)) = find_symbol(root, &target.name, files) {
+            if let Some(content) = read_text_within(root, &path, DEFAULT_MAX_READ_BYTES) {
+                let (snippet, start, end, truncated) =
+                    slice_lines(&content, line, line + SYMBOL_CONTEXT_LINES - 1);
+                return ResolvedMention {
+                    raw: target.raw.clone(),
+                    kind: MentionKind::Symbol,
+                    path: Some(path),
+                    start_line: Some(start),
+                    end_line: Some(end),
+                    snippet: Some(snippet),
+                    truncated,
+                };
+            }
+        }
+    }
+    ResolvedMention {
+        raw: target.raw.clone(),
+        kind: MentionKind::Unresolved,
+        path: None,
+        start_line: None,
+        end_line: None,
+        snippet: None,
+        truncated: false,
+    }
+}
+
+pub fn build_context_block(mentions: &[ResolvedMention]) -> String {
+    let mut block = String::new();
+    for mention in mentions {
+        let (Some(path), Some(snippet)) = (&mention.path, &mention.snippet) else {
+            continue;
+        };
+        if !block.is_empty() {
+            block.push('\n');
+        }
+        if mention.kind == MentionKind::Snippet {
+            block.push_str(&format!("{path}\n```\n{snippet}\n```\n"));
+            continue;
+        }
+        let start = mention.start_line.unwrap_or(1);
+        let end = mention.end_line.unwrap_or(start);
+        block.push_str(&format!("{path} (lines {start}-{end})\n```\n{snippet}\n```\n"));
+    }
+    block
+}
+
+pub fn resolve_mentions_inner(
+    root: &Path,
+    text: &str,
+    files: &[String],
+) -> MentionResolution {
+    let mentions = parse_mentions(text)
+        .iter()
+        .map(|target| resolve_target(root, target, files))
+        .collect::<Vec<_>>();
+    let context_block = build_context_block(&mentions);
+    MentionResolution {
+        mentions,
+        context_block,
+    }
+}
+
+pub async fn resolve_mentions_core<F>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    text: &str,
+    list_files: F,
+) -> Result<MentionResolution, String>
+where
+    F: Fn(&PathBuf, usize) -> Vec<String>,
+{
+    let root = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        PathBuf::from(&entry.path)
+    };
+    let files = list_files(&root, MAX_MENTION_FILES);
+    Ok(resolve_mentions_inner(&root, text, &files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn parses_mentions_with_line_ranges_and_punctuation() {
+        let targets = parse_mentions("See @src/main.rs:3-4, then @Widget. Email a@b.com @");
+        let raws = targets.iter().map(|t| t.raw.as_str()).collect::<Vec<_>>();
+        assert_eq!(raws, vec!["src/main.rs:3-4", "Widget"]);
+        assert_eq!(targets[0].name, "src/main.rs");
+        assert_eq!(targets[0].line_range, Some((3, 4)));
+        assert_eq!(targets[1].line_range, None);
+    }
+
+    #[test]
+    fn resolves_files_symbols_and_unknown_mentions() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-mentions-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create src");
+        std::fs::write(root.join("src/main.rs"), "fn main() {\n    run();\n}\n")
+            .expect("write main");
+        std::fs::write(
+            root.join("src/widget.rs"),
+            "// widget\npub struct Widget {\n    id: u32,\n}\n",
+        )
+        .expect("write widget");
+        let files = vec!["src/main.rs".to_string(), "src/widget.rs".to_string()];
+
+        let resolution = resolve_mentions_inner(
+            &root,
+            "Check @main.rs:2 and @Widget and @missing",
+            &files,
+        );
+        assert_eq!(resolution.mentions.len(), 3);
+        assert_eq!(resolution.mentions[0].kind, MentionKind::File);
+        assert_eq!(resolution.mentions[0].path.as_deref(), Some("src/main.rs"));
+        assert_eq!(resolution.mentions[0].snippet.as_deref(), Some("    run();"));
+        assert_eq!(resolution.mentions[1].kind, MentionKind::Symbol);
+        assert_eq!(resolution.mentions[1].start_line, Some(2));
+        assert_eq!(resolution.mentions[2].kind, MentionKind::Unresolved);
+        assert!(resolution.context_block.contains("src/main.rs (lines 2-2)"));
+        assert!(resolution.context_block.contains("src/widget.rs (lines 2-4)"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,60 @@
+pub mod account;
+pub mod account_profiles_core;
+pub mod auth_watch_core;
+pub mod backup_core;
+pub mod branch_protection_core;
+pub mod build_core;
+pub mod ci_core;
+pub mod codex_core;
+pub mod collaboration_presets_core;
+pub mod commit_message_core;
+pub mod context_budget_core;
+pub mod data_files_core;
+pub mod dependency_audit_core;
+pub mod disk_usage_core;
+pub mod documents_core;
+pub mod drafts_core;
+pub mod file_history_core;
+pub mod file_list_cache_core;
+pub mod file_transfer_core;
+pub mod files_core;
+pub mod git_core;
+pub mod i18n_core;
+pub mod list_cache_core;
+pub mod local_usage_core;
+pub mod mcp_health_core;
+pub mod mentions_core;
+pub mod model_capabilities_core;
+pub mod outline_core;
+pub mod packages_core;
+pub mod pinned_context_core;
+pub mod preview_core;
+pub mod process_core;
+pub mod process_limits_core;
+pub mod problems_core;
+pub mod protected_paths_core;
+pub mod removal_core;
+pub mod retention_core;
+pub mod revisions_core;
+pub mod rules_lint_core;
+pub mod safety_policy_core;
+pub mod search_index_core;
+pub mod session_log_core;
+pub mod settings_core;
+pub mod slash_commands_core;
+pub mod sparse_checkout_core;
+pub mod stale_branches_core;
+pub mod symbols_core;
+pub mod thread_branches_core;
+pub mod thread_usage_core;
+pub mod thumbnails_core;
+pub mod time_tracking_core;
+pub mod tls_core;
+pub mod turn_snapshots_core;
+pub mod workspace_analysis_core;
+pub mod workspace_deltas_core;
+pub mod workspace_stats_core;
+pub mod worktree_core;
+pub mod worktree_diff_core;
+pub mod worktree_reconcile_core;
+pub mod workspaces_core;
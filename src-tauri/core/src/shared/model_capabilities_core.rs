@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Written by the Codex CLI next to `config.toml` when it fetches the model list.
+const MODELS_CACHE_FILE: &str = "models_cache.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PricingTier {
+    Low,
+    Standard,
+    High,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CapabilitySource {
+    /// Vision or context window came from the app-server response or the models cache.
+    Cache,
+    /// Only the bundled table knew the model.
+    Bundled,
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCapabilities {
+    /// Accepts image input.
+    pub vision: bool,
+    pub max_context_tokens: Option<u64>,
+    pub collaboration_modes: bool,
+    pub pricing_tier: Option<PricingTier>,
+    pub source: CapabilitySource,
+}
+
+struct BundledModel {
+    prefix: &'static str,
+    vision: bool,
+    max_context_tokens: u64,
+    collaboration_modes: bool,
+    pricing_tier: PricingTier,
+}
+
+const fn bundled(
+    prefix: &'static str,
+    vision: bool,
+    max_context_tokens: u64,
+    collaboration_modes: bool,
+    pricing_tier: PricingTier,
+) -> BundledModel {
+    BundledModel {
+        prefix,
+        vision,
+        max_context_tokens,
+        collaboration_modes,
+        pricing_tier,
+    }
+}
+
+/// Known model families by id prefix; the longest matching prefix wins.
+const BUNDLED_MODELS: &[BundledModel] = &[
+    bundled("gpt-5", true, 272_000, true, PricingTier::Standard),
+    bundled("gpt-5-mini", true, 272_000, true, PricingTier::Low),
+    bundled("gpt-5-nano", true, 272_000, false, PricingTier::Low),
+    bundled("gpt-5-codex-mini", true, 272_000, true, PricingTier::Low),
+    bundled("gpt-5.1-codex-mini", true, 272_000, true, PricingTier::Low),
+    bundled("gpt-5.1-codex-max", true, 272_000, true, PricingTier::High),
+    bundled("gpt-4.1", true, 1_047_576, false, PricingTier::Standard),
+    bundled("gpt-4.1-mini", true, 1_047_576, false, PricingTier::Low),
+    bundled("gpt-4o", true, 128_000, false, PricingTier::Standard),
+    bundled("gpt-4o-mini", true, 128_000, false, PricingTier::Low),
+    bundled("o3", true, 200_000, false, PricingTier::High),
+    bundled("o3-mini", false, 200_000, false, PricingTier::Low),
+    bundled("o4-mini", true, 200_000, false, PricingTier::Low),
+];
+
+/// What the models cache says about one model. Missing fields stay `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CachedModel {
+    pub vision: Option<bool>,
+    pub max_context_tokens: Option<u64>,
+}
+
+fn bundled_model(model: &str) -> Option<&'static BundledModel> {
+    let model = model.trim().to_lowercase();
+    BUNDLED_MODELS
+        .iter()
+        .filter(|entry| model.starts_with(entry.prefix))
+        .max_by_key(|entry| entry.prefix.len())
+}
+
+/// Context window of a known model family.
+pub fn bundled_context_window(model: &str) -> Option<u64> {
+    bundled_model(model).map(|entry| entry.max_context_tokens)
+}
+
+fn string_field<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| value.get(*key).and_then(Value::as_str))
+}
+
+/// Reads vision and context window from a model object, accepting both the
+/// cache's snake_case and the app-server's camelCase field names.
+fn cached_model_from(value: &Value) -> CachedModel {
+    let vision = ["input_modalities", "inputModalities"]
+        .iter()
+        .find_map(|key| value.get(*key).and_then(Value::as_array))
+        .map(|modalities| {
+            modalities
+                .iter()
+                .any(|modality| modality.as_str() == Some("image"))
+        });
+    let max_context_tokens = ["context_window", "contextWindow", "max_context_window"]
+        .iter()
+        .find_map(|key| value.get(*key).and_then(Value::as_u64));
+    CachedModel {
+        vision,
+        max_context_tokens,
+    }
+}
+
+pub fn parse_models_cache(contents: &str) -> HashMap<String, CachedModel> {
+    let Ok(value) = serde_json::from_str::<Value>(contents) else {
+        return HashMap::new();
+    };
+    let models = value
+        .get("models")
+        .and_then(Value::as_array)
+        .or_else(|| value.as_array());
+    models
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            let id = string_field(model, &["slug", "id", "model"])?;
+            Some((id.to_string(), cached_model_from(model)))
+        })
+        .collect()
+}
+
+/// Reads `models_cache.json` from a Codex home; a missing or unreadable cache is empty.
+pub fn read_models_cache(codex_home: Option<&Path>) -> HashMap<String, CachedModel> {
+    codex_home
+        .and_then(|home| std::fs::read_to_string(home.join(MODELS_CACHE_FILE)).ok())
+        .map(|contents| parse_models_cache(&contents))
+        .unwrap_or_default()
+}
+
+/// Merges, in order of precedence, what the app-server reported for the model,
+/// the models cache and the bundled table.
+pub fn capabilities_for(
+    model: &str,
+    reported: &CachedModel,
+    cached: Option<&CachedModel>,
+) -> ModelCapabilities {
+    let bundled = bundled_model(model);
+    let vision = reported
+        .vision
+        .or_else(|| cached.and_then(|cached| cached.vision));
+    let max_context_tokens = reported
+        .max_context_tokens
+        .or_else(|| cached.and_then(|cached| cached.max_context_tokens));
+    let from_cache = cached.is_some_and(|cached| *cached != CachedModel::default())
+        || *reported != CachedModel::default();
+    let source = if from_cache {
+        CapabilitySource::Cache
+    } else if bundled.is_some() {
+        CapabilitySource::Bundled
+    } else {
+        CapabilitySource::Unknown
+    };
+    ModelCapabilities {
+        vision: vision
+            .or(bundled.map(|entry| entry.vision))
+            .unwrap_or(false),
+        max_context_tokens: max_context_tokens.or(bundled.map(|entry| entry.max_context_tokens)),
+        collaboration_modes: bundled.is_some_and(|entry| entry.collaboration_modes),
+        pricing_tier: bundled.map(|entry| entry.pricing_tier),
+        source,
+    }
+}
+
+/// Adds a `capabilities` object to every entry of a `model/list` response.
+pub fn enrich_model_list(
+    mut response: Value,
+    cache: &HashMap<String, CachedModel>,
+) -> Value {
+    let data = match response.get_mut("data") {
+        Some(data) => data,
+        None => match response
+            .get_mut("result")
+            .and_then(|result| result.get_mut("data"))
+        {
+            Some(data) => data,
+            None => return response,
+        },
+    };
+    let Some(models) = data.as_array_mut() else {
+        return response;
+    };
+    for model in models {
+        let Some(id) = string_field(model, &["model", "id"]).map(str::to_string) else {
+            continue;
+        };
+        let reported = cached_model_from(model);
+        let capabilities = capabilities_for(&id, &reported, cache.get(&id));
+        if let (Some(object), Ok(capabilities)) =
+            (model.as_object_mut(), serde_json::to_value(capabilities))
+        {
+            object.insert("capabilities".to_string(), capabilities);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bundled_table_prefers_longest_prefix() {
+        let mini = capabilities_for("gpt-5.1-codex-mini", &CachedModel::default(), None);
+        assert_eq!(mini.pricing_tier, Some(PricingTier::Low));
+        assert_eq!(mini.source, CapabilitySource::Bundled);
+        let o3_mini = capabilities_for("o3-mini", &CachedModel::default(), None);
+        assert!(!o3_mini.vision);
+        assert_eq!(bundled_context_window("GPT-5.1-codex"), Some(272_000));
+        let unknown = capabilities_for("mystery", &CachedModel::default(), None);
+        assert_eq!(unknown.source, CapabilitySource::Unknown);
+        assert_eq!(unknown.max_context_tokens, None);
+    }
+
+    #[test]
+    fn enriches_model_list_from_cache() {
+        let cache = parse_models_cache(
+            r#"{"fetched_at":"2026-01-01T00:00:00Z","models":[
+                {"slug":"o3-mini","context_window":100000,"input_modalities":["text","image"]},
+                {"slug":"local-model","input_modalities":["text"]}
+            ]}"#,
+        );
+        let response = enrich_model_list(
+            json!({ "data": [
+                { "id": "o3-mini", "model": "o3-mini" },
+                { "id": "local-model", "model": "local-model" },
+            ] }),
+            &cache,
+        );
+        let o3_mini = &response["data"][0]["capabilities"];
+        assert_eq!(o3_mini["vision"], json!(true));
+        assert_eq!(o3_mini["maxContextTokens"], json!(100_000));
+        assert_eq!(o3_mini["pricingTier"], json!("low"));
+        assert_eq!(o3_mini["source"], json!("cache"));
+        let local = &response["data"][1]["capabilities"];
+        assert_eq!(local["vision"], json!(false));
+        assert_eq!(local["maxContextTokens"], Value::Null);
+    }
+}
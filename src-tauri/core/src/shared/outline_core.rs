@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tree_sitter::{Node, Parser};
+
+use crate::files::policy::FileScope;
+use crate::shared::files_core::resolve_root_core;
+use crate::types::WorkspaceEntry;
+
+/// Larger files are not parsed; generated bundles rarely have a useful outline.
+pub const MAX_OUTLINE_BYTES: u64 = 1024 * 1024;
+/// Syntax nesting below which symbols are no longer collected.
+const MAX_OUTLINE_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutlineLanguage {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+    Java,
+    C,
+    Cpp,
+}
+
+impl OutlineLanguage {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        Some(match ext.as_str() {
+            "rs" => Self::Rust,
+            "py" | "pyi" => Self::Python,
+            "js" | "jsx" | "mjs" | "cjs" => Self::JavaScript,
+            "ts" | "mts" | "cts" => Self::TypeScript,
+            "tsx" => Self::Tsx,
+            "go" => Self::Go,
+            "java" => Self::Java,
+            "c" | "h" => Self::C,
+            "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => Self::Cpp,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::JavaScript => "javascript",
+            Self::TypeScript => "typescript",
+            Self::Tsx => "tsx",
+            Self::Go => "go",
+            Self::Java => "java",
+            Self::C => "c",
+            Self::Cpp => "cpp",
+        }
+    }
+
+    fn grammar(self) -> tree_sitter::Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+            Self::Java => tree_sitter_java::LANGUAGE.into(),
+            Self::C => tree_sitter_c::LANGUAGE.into(),
+            Self::Cpp => tree_sitter_cpp::LANGUAGE.into(),
+        }
+    }
+
+    fn is_javascript_like(self) -> bool {
+        matches!(self, Self::JavaScript | Self::TypeScript | Self::Tsx)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Module,
+    Class,
+    Struct,
+    Enum,
+    Interface,
+    Trait,
+    Impl,
+    Function,
+    Method,
+    Constant,
+    Type,
+}
+
+impl SymbolKind {
+    fn holds_methods(self) -> bool {
+        matches!(
+            self,
+            Self::Class | Self::Struct | Self::Interface | Self::Trait | Self::Impl
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-based, inclusive lines of the whole declaration.
+    pub start_line: usize,
+    pub end_line: usize,
+    /// 1-based position of the name, where "jump to symbol" lands. Columns count
+    /// characters.
+    pub name_line: usize,
+    pub name_column: usize,
+    pub children: Vec<OutlineSymbol>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOutline {
+    pub path: String,
+    /// `None` when no grammar covers the file; `symbols` is then empty.
+    pub language: Option<String>,
+    pub symbols: Vec<OutlineSymbol>,
+}
+
+fn node_text(node: Node, source: &[u8]) -> String {
+    node.utf8_text(source).unwrap_or_default().to_string()
+}
+
+/// The name inside a C declarator chain, e.g. `main` in `*main(void)`.
+fn declarator_name(node: Node) -> Option<Node> {
+    let mut current = node.child_by_field_name("declarator")?;
+    loop {
+        match current.kind() {
+            "identifier"
+            | "field_identifier"
+            | "type_identifier"
+            | "qualified_identifier"
+            | "destructor_name"
+            | "operator_name" => return Some(current),
+            _ => {
+                current = current
+                    .child_by_field_name("declarator")
+                    .or_else(|| current.named_child(0))?;
+            }
+        }
+    }
+}
+
+/// Kind, display name and name node of a declaration, or `None` for other nodes.
+fn classify<'tree>(
+    language: OutlineLanguage,
+    node: Node<'tree>,
+    source: &[u8],
+) -> Option<(SymbolKind, String, Node<'tree>)> {
+    use OutlineLanguage as Lang;
+    let named = |kind| {
+        node.child_by_field_name("name")
+            .map(|name| (kind, node_text(name, source), name))
+    };
+    let has_body = node.child_by_field_name("body").is_some();
+    match (language, node.kind()) {
+        (Lang::Rust, "function_item" | "function_signature_item") => named(SymbolKind::Function),
+        (Lang::Rust, "struct_item" | "union_item") => named(SymbolKind::Struct),
+        (Lang::Rust, "enum_item") => named(SymbolKind::Enum),
+        (Lang::Rust, "trait_item") => named(SymbolKind::Trait),
+        (Lang::Rust, "mod_item") => named(SymbolKind::Module),
+        (Lang::Rust, "const_item" | "static_item") => named(SymbolKind::Constant),
+        (Lang::Rust, "type_item") => named(SymbolKind::Type),
+        (Lang::Rust, "impl_item") => {
+            let target = node.child_by_field_name("type")?;
+            let name = match node.child_by_field_name("trait") {
+                Some(trait_name) => format!(
+                    "{} for {}",
+                    node_text(trait_name, source),
+                    node_text(target, source)
+                ),
+                None => node_text(target, source),
+            };
+            Some((SymbolKind::Impl, name, target))
+        }
+        (Lang::Python, "function_definition") => named(SymbolKind::Function),
+        (Lang::Python, "class_definition") => named(SymbolKind::Class),
+        (
+            lang,
+            "function_declaration" | "generator_function_declaration" | "function_signature",
+        ) if lang.is_javascript_like() => named(SymbolKind::Function),
+        (lang, "class_declaration" | "abstract_class_declaration" | "class")
+            if lang.is_javascript_like() =>
+        {
+            named(SymbolKind::Class)
+        }
+        (lang, "method_definition" | "method_signature" | "abstract_method_signature")
+            if lang.is_javascript_like() =>
+        {
+            named(SymbolKind::Method)
+        }
+        (lang, "variable_declarator") if lang.is_javascript_like() => {
+            let value = node.child_by_field_name("value")?;
+            let is_function = matches!(
+                value.kind(),
+                "arrow_function" | "function_expression" | "function" | "generator_function"
+            );
+            let name = node.child_by_field_name("name")?;
+            (is_function && name.kind() == "identifier")
+                .then(|| (SymbolKind::Function, node_text(name, source), name))
+        }
+        (Lang::TypeScript | Lang::Tsx, "interface_declaration") => named(SymbolKind::Interface),
+        (Lang::TypeScript | Lang::Tsx, "type_alias_declaration") => named(SymbolKind::Type),
+        (Lang::TypeScript | Lang::Tsx, "enum_declaration") => named(SymbolKind::Enum),
+        (Lang::TypeScript | Lang::Tsx, "internal_module" | "module") => named(SymbolKind::Module),
+        (Lang::Go, "function_declaration") => named(SymbolKind::Function),
+        (Lang::Go, "method_declaration") => named(SymbolKind::Method),
+        (Lang::Go, "type_spec") => {
+            let kind = match node.child_by_field_name("type").map(|ty| ty.kind()) {
+                Some("struct_type") => SymbolKind::Struct,
+                Some("interface_type") => SymbolKind::Interface,
+                _ => SymbolKind::Type,
+            };
+            named(kind)
+        }
+        (Lang::Java, "class_declaration" | "record_declaration") => named(SymbolKind::Class),
+        (Lang::Java, "interface_declaration" | "annotation_type_declaration") => {
+            named(SymbolKind::Interface)
+        }
+        (Lang::Java, "enum_declaration") => named(SymbolKind::Enum),
+        (Lang::Java, "method_declaration" | "constructor_declaration") => named(SymbolKind::Method),
+        (Lang::C | Lang::Cpp, "function_definition") => {
+            let name = declarator_name(node)?;
+            Some((SymbolKind::Function, node_text(name, source), name))
+        }
+        (Lang::C | Lang::Cpp, "type_definition") => {
+            let name = declarator_name(node)?;
+            Some((SymbolKind::Type, node_text(name, source), name))
+        }
+        (Lang::C | Lang::Cpp, "struct_specifier" | "union_specifier") if has_body => {
+            named(SymbolKind::Struct)
+        }
+        (Lang::C | Lang::Cpp, "enum_specifier") if has_body => named(SymbolKind::Enum),
+        (Lang::Cpp, "class_specifier") if has_body => named(SymbolKind::Class),
+        (Lang::Cpp, "namespace_definition") => named(SymbolKind::Module),
+        _ => None,
+    }
+}
+
+/// 1-based line and character column of a node's start.
+fn position(node: Node, source: &[u8]) -> (usize, usize) {
+    let point = node.start_position();
+    let line_start = node.start_byte().saturating_sub(point.column);
+    let column = String::from_utf8_lossy(&source[line_start..node.start_byte()])
+        .chars()
+        .count();
+    (point.row + 1, column + 1)
+}
+
+fn collect_symbols(
+    language: OutlineLanguage,
+    node: Node,
+    source: &[u8],
+    parent: Option<SymbolKind>,
+    depth: usize,
+    symbols: &mut Vec<OutlineSymbol>,
+) {
+    if depth >= MAX_OUTLINE_DEPTH {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let Some((mut kind, name, name_node)) = classify(language, child, source) else {
+            collect_symbols(language, child, source, parent, depth + 1, symbols);
+            continue;
+        };
+        if kind == SymbolKind::Function && parent.is_some_and(SymbolKind::holds_methods) {
+            kind = SymbolKind::Method;
+        }
+        let (name_line, name_column) = position(name_node, source);
+        let mut symbol = OutlineSymbol {
+            name,
+            kind,
+            start_line: child.start_position().row + 1,
+            end_line: child.end_position().row + 1,
+            name_line,
+            name_column,
+            children: Vec::new(),
+        };
+        collect_symbols(
+            language,
+            child,
+            source,
+            Some(kind),
+            depth + 1,
+            &mut symbol.children,
+        );
+        symbols.push(symbol);
+    }
+}
+
+/// Symbol tree of `source`, in document order.
+pub fn outline_source(
+    language: OutlineLanguage,
+    source: &str,
+) -> Result<Vec<OutlineSymbol>, String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language.grammar())
+        .map_err(|err| format!("Failed to load the {} grammar: {err}", language.name()))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| format!("Failed to parse {} source", language.name()))?;
+    let mut symbols = Vec::new();
+    collect_symbols(
+        language,
+        tree.root_node(),
+        source.as_bytes(),
+        None,
+        0,
+        &mut symbols,
+    );
+    Ok(symbols)
+}
+
+pub fn file_outline(root: &Path, path: &str) -> Result<FileOutline, String> {
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let canonical_path = canonical_root
+        .join(path)
+        .canonicalize()
+        .map_err(|err| format!("Failed to open {path}: {err}"))?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err("Path is outside the workspace".to_string());
+    }
+    let relative = canonical_path
+        .strip_prefix(&canonical_root)
+        .unwrap_or(&canonical_path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    let Some(language) = OutlineLanguage::from_path(&relative) else {
+        return Ok(FileOutline {
+            path: relative,
+            language: None,
+            symbols: Vec::new(),
+        });
+    };
+    let metadata = std::fs::metadata(&canonical_path).map_err(|err| err.to_string())?;
+    if metadata.len() > MAX_OUTLINE_BYTES {
+        return Err(format!("{path} is larger than {MAX_OUTLINE_BYTES} bytes"));
+    }
+    let bytes = std::fs::read(&canonical_path).map_err(|err| err.to_string())?;
+    let symbols = outline_source(language, &String::from_utf8_lossy(&bytes))?;
+    Ok(FileOutline {
+        path: relative,
+        language: Some(language.name().to_string()),
+        symbols,
+    })
+}
+
+pub async fn file_outline_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    path: String,
+) -> Result<FileOutline, String> {
+    let root = resolve_root_core(workspaces, FileScope::Workspace, Some(workspace_id)).await?;
+    tokio::task::spawn_blocking(move || file_outline(&root, &path))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(symbols: &[OutlineSymbol]) -> Vec<String> {
+        symbols
+            .iter()
+            .map(|symbol| {
+                let children = summary(&symbol.children);
+                let kind = serde_json::to_value(symbol.kind).expect("kind");
+                let kind = kind.as_str().unwrap_or_default();
+                if children.is_empty() {
+                    format!("{kind} {}", symbol.name)
+                } else {
+                    format!("{kind} {} [{}]", symbol.name, children.join(", "))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn outlines_rust_and_python() {
+        let rust = "mod net {\n    pub struct Server;\n\n    impl Drop for Server {\n        fn drop(&mut self) {}\n    }\n}\n\nconst LIMIT: u32 = 1;\n\nfn main() {}\n";
+        let symbols = outline_source(OutlineLanguage::Rust, rust).expect("rust outline");
+        assert_eq!(
+            summary(&symbols),
+            vec![
+                "module net [struct Server, impl Drop for Server [method drop]]",
+                "constant LIMIT",
+                "function main",
+            ]
+        );
+        let drop = &symbols[0].children[1].children[0];
+        assert_eq!(
+            (
+                drop.start_line,
+                drop.end_line,
+                drop.name_line,
+                drop.name_column
+            ),
+            (5, 5, 5, 12)
+        );
+
+        let python = "class Greeter:\n    def hello(self):\n        pass\n\n@cached\ndef helper():\n    pass\n";
+        assert_eq!(
+            summary(&outline_source(OutlineLanguage::Python, python).expect("python outline")),
+            vec!["class Greeter [method hello]", "function helper"]
+        );
+    }
+
+    #[test]
+    fn outlines_typescript_go_and_cpp() {
+        let typescript = "export interface Props { id: string }\nexport class Store {\n  load(): void {}\n}\nexport const useStore = () => new Store();\nconst value = 1;\n";
+        assert_eq!(
+            summary(&outline_source(OutlineLanguage::TypeScript, typescript).expect("ts outline")),
+            vec![
+                "interface Props",
+                "class Store [method load]",
+                "function useStore",
+            ]
+        );
+        let go = "package main\n\ntype Server struct{}\n\nfunc (s *Server) Start() {}\n\nfunc main() {}\n";
+        assert_eq!(
+            summary(&outline_source(OutlineLanguage::Go, go).expect("go outline")),
+            vec!["struct Server", "method Start", "function main"]
+        );
+        let cpp = "namespace app {\nclass Widget {\n  void draw() {}\n};\n}\nint *make(void) { return 0; }\n";
+        assert_eq!(
+            summary(&outline_source(OutlineLanguage::Cpp, cpp).expect("cpp outline")),
+            vec!["module app [class Widget [method draw]]", "function make"]
+        );
+        assert_eq!(
+            OutlineLanguage::from_path("src/App.TSX"),
+            Some(OutlineLanguage::Tsx)
+        );
+        assert_eq!(OutlineLanguage::from_path("README.md"), None);
+    }
+}
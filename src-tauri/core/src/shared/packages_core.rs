@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::files::policy::SkipDirPolicy;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::types::WorkspaceEntry;
+
+/// Deep enough for `crates/<group>/<name>` style layouts without walking whole trees.
+const MAX_MANIFEST_DEPTH: usize = 6;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageEcosystem {
+    Cargo,
+    Npm,
+    Pnpm,
+    Go,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub ecosystem: PackageEcosystem,
+    /// Workspace-relative directory ("" for the root); usable as a thread `cwd` or a
+    /// search `pathPrefix`.
+    pub path: String,
+    pub manifest: String,
+    /// Declares members itself (`[workspace]`, `workspaces`, `pnpm-workspace.yaml`, `go.work`).
+    pub workspace_root: bool,
+}
+
+fn relative_dir(root: &Path, dir: &Path) -> String {
+    dir.strip_prefix(root)
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default()
+}
+
+fn dir_name(root: &Path, dir: &Path) -> String {
+    dir.file_name()
+        .or_else(|| root.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string())
+}
+
+fn cargo_package(root: &Path, manifest: &Path) -> Option<WorkspacePackage> {
+    let contents = std::fs::read_to_string(manifest).ok()?;
+    let parsed: toml::Value = toml::from_str(&contents).ok()?;
+    let dir = manifest.parent()?;
+    let workspace_root = parsed.get("workspace").is_some();
+    let name = parsed
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(toml::Value::as_str)
+        .map(str::to_string);
+    if name.is_none() && !workspace_root {
+        return None;
+    }
+    Some(WorkspacePackage {
+        name: name.unwrap_or_else(|| dir_name(root, dir)),
+        ecosystem: PackageEcosystem::Cargo,
+        path: relative_dir(root, dir),
+        manifest: relative_dir(root, manifest),
+        workspace_root,
+    })
+}
+
+fn npm_package(root: &Path, manifest: &Path) -> Option<WorkspacePackage> {
+    let contents = std::fs::read_to_string(manifest).ok()?;
+    let parsed: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    let dir = manifest.parent()?;
+    let pnpm_workspace = dir.join("pnpm-workspace.yaml").is_file();
+    let pnpm = pnpm_workspace || dir.join("pnpm-lock.yaml").is_file();
+    Some(WorkspacePackage {
+        name: parsed
+            .get("name")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| dir_name(root, dir)),
+        ecosystem: if pnpm {
+            PackageEcosystem::Pnpm
+        } else {
+            PackageEcosystem::Npm
+        },
+        path: relative_dir(root, dir),
+        manifest: relative_dir(root, manifest),
+        workspace_root: pnpm_workspace || parsed.get("workspaces").is_some(),
+    })
+}
+
+fn go_module(root: &Path, manifest: &Path) -> Option<WorkspacePackage> {
+    let contents = std::fs::read_to_string(manifest).ok()?;
+    let dir = manifest.parent()?;
+    let name = contents
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .map(|module| module.trim().trim_matches('"').to_string())
+        .unwrap_or_else(|| dir_name(root, dir));
+    Some(WorkspacePackage {
+        name,
+        ecosystem: PackageEcosystem::Go,
+        path: relative_dir(root, dir),
+        manifest: relative_dir(root, manifest),
+        workspace_root: dir.join("go.work").is_file(),
+    })
+}
+
+/// Finds Cargo crates, npm/pnpm packages and Go modules below `root`, honoring
+/// `.gitignore` and the skip-dir policy so `node_modules`, `target` and vendored trees
+/// are skipped.
+pub fn detect_packages(root: &Path) -> Vec<WorkspacePackage> {
+    let skip_dirs = SkipDirPolicy::current();
+    let walker = WalkBuilder::new(root)
+        .max_depth(Some(MAX_MANIFEST_DEPTH))
+        .follow_links(false)
+        .require_git(false)
+        .filter_entry(move |entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_some_and(|ft| ft.is_dir())
+                || !skip_dirs.skips(&entry.file_name().to_string_lossy())
+        })
+        .build();
+    let mut packages = Vec::new();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        let package = match entry.file_name().to_str() {
+            Some("Cargo.toml") => cargo_package(root, path),
+            Some("package.json") => npm_package(root, path),
+            Some("go.mod") => go_module(root, path),
+            _ => None,
+        };
+        packages.extend(package);
+    }
+    packages.sort_by(|a, b| a.path.cmp(&b.path).then(a.ecosystem.cmp(&b.ecosystem)));
+    packages
+}
+
+pub async fn list_packages_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<Vec<WorkspacePackage>, String> {
+    let root = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        PathBuf::from(&entry.path)
+    };
+    tokio::task::spawn_blocking(move || detect_packages(&root))
+        .await
+        .map_err(|err| format!("Package detection failed: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let target = root.join(path);
+        std::fs::create_dir_all(target.parent().expect("parent")).expect("create dir");
+        std::fs::write(target, contents).expect("write manifest");
+    }
+
+    #[test]
+    fn detects_cargo_npm_and_go_packages() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-packages-{}", Uuid::new_v4()));
+        write(&root, "Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n");
+        write(&root, "crates/core/Cargo.toml", "[package]\nname = \"app-core\"\n");
+        write(&root, "web/package.json", r#"{ "name": "@app/web", "workspaces": ["ui"] }"#);
+        write(&root, "web/node_modules/dep/package.json", r#"{ "name": "dep" }"#);
+        write(&root, "services/api/go.mod", "module example.com/api\n\ngo 1.22\n");
+
+        let packages = detect_packages(&root);
+        let summary = packages
+            .iter()
+            .map(|package| (package.path.as_str(), package.name.as_str(), package.workspace_root))
+            .collect::<Vec<_>>();
+        let root_name = root.file_name().expect("name").to_string_lossy().to_string();
+        assert_eq!(
+            summary,
+            vec![
+                ("", root_name.as_str(), true),
+                ("crates/core", "app-core", false),
+                ("services/api", "example.com/api", false),
+                ("web", "@app/web", true),
+            ]
+        );
+        assert_eq!(packages[3].manifest, "web/package.json");
+        assert_eq!(packages[2].ecosystem, PackageEcosystem::Go);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::shared::context_budget_core::{self, ContextPlan};
+use crate::shared::data_files_core::data_file_path;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::mentions_core::{self, MentionKind, MentionResolution, ResolvedMention};
+use crate::types::{AppSettings, WorkspaceEntry};
+
+pub const PINNED_CONTEXT_FILE: &str = "pinned-context.json";
+
+const MAX_PINS_PER_THREAD: usize = 20;
+const MAX_SNIPPET_PIN_BYTES: usize = 32_000;
+/// First line of the input item that carries a thread's pins.
+const PINNED_CONTEXT_HEADER: &str = "Pinned context (attached to every message in this thread):";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PinKind {
+    File,
+    Snippet,
+}
+
+/// A file, line range or piece of text attached to every message of a thread.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextPin {
+    pub id: String,
+    pub kind: PinKind,
+    /// Workspace-relative; files only.
+    pub path: Option<String>,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    /// Snippets only.
+    pub text: Option<String>,
+    pub label: Option<String>,
+    pub created_at: u64,
+}
+
+/// What `pin_context` accepts: `text` pins a snippet, otherwise `path` pins a file.
+#[derive(Debug, Clone, Default)]
+pub struct PinInput {
+    pub path: Option<String>,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    pub text: Option<String>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedContextView {
+    pub pins: Vec<ContextPin>,
+    /// How the pins fit the budget of the next message.
+    pub plan: ContextPlan,
+}
+
+/// Pins persisted in `<data-dir>/pinned-context.json`, keyed by workspace and thread.
+pub struct PinnedContext {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Vec<ContextPin>>>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn pin_key(workspace_id: &str, thread_id: &str) -> String {
+    format!("{workspace_id}/{thread_id}")
+}
+
+fn normalize_pin_path(path: &str) -> Result<String, String> {
+    let path = path.trim().trim_start_matches("./").replace('\\', "/");
+    if path.is_empty() {
+        return Err("path or text is required".to_string());
+    }
+    let relative = Path::new(&path);
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("Pinned path must be inside the workspace: {path}"));
+    }
+    Ok(path)
+}
+
+fn build_pin(input: PinInput) -> Result<ContextPin, String> {
+    let label = input
+        .label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty());
+    let mut pin = ContextPin {
+        id: Uuid::new_v4().to_string(),
+        kind: PinKind::File,
+        path: None,
+        start_line: None,
+        end_line: None,
+        text: None,
+        label,
+        created_at: now_millis(),
+    };
+    if let Some(text) = input.text.filter(|text| !text.trim().is_empty()) {
+        if text.len() > MAX_SNIPPET_PIN_BYTES {
+            return Err(format!(
+                "Pinned text is larger than {MAX_SNIPPET_PIN_BYTES} bytes"
+            ));
+        }
+        pin.kind = PinKind::Snippet;
+        pin.text = Some(text);
+        return Ok(pin);
+    }
+    pin.path = Some(normalize_pin_path(input.path.as_deref().unwrap_or(""))?);
+    match (input.start_line, input.end_line) {
+        (None, None) => {}
+        (Some(start), end) if start >= 1 && end.is_none_or(|end| end >= start) => {
+            pin.start_line = Some(start);
+            pin.end_line = Some(end.unwrap_or(start));
+        }
+        _ => return Err("Invalid line range".to_string()),
+    }
+    Ok(pin)
+}
+
+impl ContextPin {
+    /// How the pin shows up in plans, e.g. `src/lib.rs:10-20`.
+    fn display_name(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        match (&self.path, self.start_line, self.end_line) {
+            (Some(path), Some(start), Some(end)) => format!("{path}:{start}-{end}"),
+            (Some(path), _, _) => path.clone(),
+            _ => "pinned snippet".to_string(),
+        }
+    }
+
+    fn resolve(&self, root: &Path) -> ResolvedMention {
+        let raw = self.display_name();
+        if let Some(text) = &self.text {
+            return ResolvedMention {
+                raw: raw.clone(),
+                kind: MentionKind::Snippet,
+                path: Some(raw),
+                start_line: None,
+                end_line: None,
+                snippet: Some(text.clone()),
+                truncated: false,
+            };
+        }
+        let range = self.start_line.zip(self.end_line);
+        self.path
+            .as_deref()
+            .and_then(|path| mentions_core::resolve_file_range(root, &raw, path, range))
+            .unwrap_or(ResolvedMention {
+                raw,
+                kind: MentionKind::Unresolved,
+                path: self.path.clone(),
+                start_line: None,
+                end_line: None,
+                snippet: None,
+                truncated: false,
+            })
+    }
+}
+
+/// Reads the pins from `root` and packs them into the budget, oldest pin first.
+/// Files that are gone or unreadable show up as `unresolved`.
+pub fn plan_pins(
+    root: &Path,
+    pins: &[ContextPin],
+    text: &str,
+    model: Option<&str>,
+    budget_tokens: Option<usize>,
+) -> ContextPlan {
+    let mentions = pins.iter().map(|pin| pin.resolve(root)).collect::<Vec<_>>();
+    let resolution = MentionResolution {
+        context_block: mentions_core::build_context_block(&mentions),
+        mentions,
+    };
+    context_budget_core::plan_context(resolution, text, model, budget_tokens)
+}
+
+/// The input item `send_user_message` puts before the user's text, if any pin fits.
+pub fn pinned_input_text(plan: &ContextPlan) -> Option<String> {
+    (!plan.context_block.trim().is_empty())
+        .then(|| format!("{PINNED_CONTEXT_HEADER}\n{}", plan.context_block))
+}
+
+/// The thread's pins with the plan for a message sent now.
+pub async fn pinned_context_view_core(
+    workspaces: &tokio::sync::Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &tokio::sync::Mutex<AppSettings>,
+    pinned_context: &PinnedContext,
+    workspace_id: &str,
+    thread_id: &str,
+    model: Option<&str>,
+) -> Result<PinnedContextView, String> {
+    let root = workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .map(|entry| PathBuf::from(&entry.path))
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    let budget = app_settings.lock().await.pinned_context_budget_tokens;
+    let pins = pinned_context.list(workspace_id, thread_id);
+    let plan = plan_pins(&root, &pins, "", model, budget);
+    Ok(PinnedContextView { pins, plan })
+}
+
+impl PinnedContext {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_file_path(data_dir, PINNED_CONTEXT_FILE);
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<ContextPin>>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, entries: &HashMap<String, Vec<ContextPin>>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(entries).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, data).map_err(|err| err.to_string())
+    }
+
+    /// Pins of the thread, oldest first.
+    pub fn list(&self, workspace_id: &str, thread_id: &str) -> Vec<ContextPin> {
+        self.lock()
+            .get(&pin_key(workspace_id, thread_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn pin(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        input: PinInput,
+    ) -> Result<ContextPin, String> {
+        let pin = build_pin(input)?;
+        let mut entries = self.lock();
+        let pins = entries.entry(pin_key(workspace_id, thread_id)).or_default();
+        if pins.len() >= MAX_PINS_PER_THREAD {
+            return Err(format!(
+                "A thread can have at most {MAX_PINS_PER_THREAD} pins"
+            ));
+        }
+        pins.push(pin.clone());
+        self.persist(&entries)?;
+        Ok(pin)
+    }
+
+    /// Removes one pin, or every pin of the thread when `pin_id` is `None`. Returns how
+    /// many were removed.
+    pub fn unpin(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        pin_id: Option<&str>,
+    ) -> Result<usize, String> {
+        let key = pin_key(workspace_id, thread_id);
+        let mut entries = self.lock();
+        let Some(pins) = entries.get_mut(&key) else {
+            return Ok(0);
+        };
+        let before = pins.len();
+        match pin_id {
+            Some(pin_id) => pins.retain(|pin| pin.id != pin_id),
+            None => pins.clear(),
+        }
+        let removed = before - pins.len();
+        if pins.is_empty() {
+            entries.remove(&key);
+        }
+        if removed > 0 {
+            self.persist(&entries)?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::context_budget_core::AttachmentStatus;
+
+    #[test]
+    fn pins_persist_and_fit_the_budget() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-pins-{}", Uuid::new_v4()));
+        let root = base.join("repo");
+        std::fs::create_dir_all(&root).expect("root");
+        std::fs::write(root.join("notes.md"), "one\ntwo\nthree\n").expect("notes");
+
+        let pinned = PinnedContext::load(&base.join("data"));
+        pinned
+            .pin(
+                "ws-1",
+                "thr-1",
+                PinInput {
+                    path: Some("./notes.md".to_string()),
+                    start_line: Some(2),
+                    ..PinInput::default()
+                },
+            )
+            .expect("pin file");
+        let snippet = pinned
+            .pin(
+                "ws-1",
+                "thr-1",
+                PinInput {
+                    text: Some("Use tabs.".to_string()),
+                    label: Some("style".to_string()),
+                    ..PinInput::default()
+                },
+            )
+            .expect("pin snippet");
+        for path in ["../secret", "/etc/passwd", " "] {
+            let input = PinInput {
+                path: Some(path.to_string()),
+                ..PinInput::default()
+            };
+            assert!(pinned.pin("ws-1", "thr-1", input).is_err(), "{path}");
+        }
+
+        let reloaded = PinnedContext::load(&base.join("data"));
+        let pins = reloaded.list("ws-1", "thr-1");
+        assert_eq!(pins.len(), 2);
+        assert!(reloaded.list("ws-1", "thr-2").is_empty());
+
+        let plan = plan_pins(&root, &pins, "hi", None, None);
+        assert_eq!(
+            pinned_input_text(&plan).expect("pinned text"),
+            format!(
+                "{PINNED_CONTEXT_HEADER}\nnotes.md (lines 2-2)\n```\ntwo\n```\n\nstyle\n```\nUse tabs.\n```\n"
+            )
+        );
+        let tight = plan_pins(&root, &pins, "hi", None, Some(20));
+        assert_eq!(tight.attachments[0].status, AttachmentStatus::Included);
+        assert_eq!(tight.attachments[1].status, AttachmentStatus::Excluded);
+
+        assert_eq!(reloaded.unpin("ws-1", "thr-1", Some(&snippet.id)), Ok(1));
+        assert_eq!(reloaded.unpin("ws-1", "thr-1", None), Ok(1));
+        assert!(PinnedContext::load(&base.join("data"))
+            .list("ws-1", "thr-1")
+            .is_empty());
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,823 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use pulldown_cmark::{Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::files::policy::FileScope;
+use crate::shared::files_core::resolve_root_core;
+use crate::types::WorkspaceEntry;
+
+const MAX_MARKDOWN_BYTES: u64 = 2 * 1024 * 1024;
+const MAX_NOTEBOOK_BYTES: u64 = 32 * 1024 * 1024;
+/// Workspace images up to this size are inlined as `data:` URLs.
+const MAX_INLINE_IMAGE_BYTES: u64 = 2 * 1024 * 1024;
+/// Inlined image bytes per rendered file; later images only carry `data-path`.
+const MAX_INLINE_IMAGES_TOTAL_BYTES: u64 = 8 * 1024 * 1024;
+/// Text kept per notebook output.
+const MAX_OUTPUT_CHARS: usize = 20_000;
+/// Tags nested deeper than this (blockquotes, lists, emphasis) are dropped, keeping their text.
+const MAX_NESTING: usize = 32;
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+const IMAGE_SCHEMES: &[&str] = &["http", "https"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedMarkdown {
+    pub path: String,
+    pub html: String,
+    /// Text of the first level-1 heading.
+    pub title: Option<String>,
+    /// Workspace-relative targets of relative links and images, in document order.
+    pub links: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookOutput {
+    /// `stream`, `execute_result`, `display_data` or `error`.
+    pub output_type: String,
+    /// `stdout` or `stderr` for streams.
+    pub name: Option<String>,
+    /// Plain text with ANSI escapes removed; error outputs carry the traceback.
+    pub text: Option<String>,
+    /// `data:` URL of an image result.
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookCell {
+    /// `markdown`, `code` or `raw`.
+    pub cell_type: String,
+    pub source: String,
+    pub execution_count: Option<u64>,
+    /// Rendered like `render_markdown`, for markdown cells.
+    pub html: Option<String>,
+    pub outputs: Vec<NotebookOutput>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Notebook {
+    pub path: String,
+    /// Kernel language, e.g. `python`.
+    pub language: Option<String>,
+    pub cells: Vec<NotebookCell>,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        push_escaped(&mut escaped, ch);
+    }
+    escaped
+}
+
+fn push_escaped(out: &mut String, ch: char) {
+    match ch {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        '\'' => out.push_str("&#39;"),
+        _ => out.push(ch),
+    }
+}
+
+fn image_mime(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    }
+}
+
+fn url_scheme(url: &str) -> Option<String> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|ch| ch.is_ascii_alphabetic())
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'));
+    valid.then(|| scheme.to_ascii_lowercase())
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = text
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = hex {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Second line of defence behind `MarkdownRenderer`: only the tags, attributes and URL
+/// schemes the renderer itself emits survive. `data:` URLs are kept for image sources only.
+fn sanitizer() -> ammonia::Builder<'static> {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .link_rel(None)
+        .url_schemes(HashSet::from(["http", "https", "mailto", "data"]))
+        .add_tags(["input"])
+        .add_tag_attributes("a", ["data-path", "rel"])
+        .add_tag_attributes("img", ["data-path"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("th", ["style"])
+        .add_tag_attributes("td", ["style"])
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            ("img", "src") => Some(value.into()),
+            (_, "href" | "src") if url_scheme(value).as_deref() == Some("data") => None,
+            ("th" | "td", "style") => value.starts_with("text-align: ").then(|| value.into()),
+            _ => Some(value.into()),
+        });
+    for heading in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+        builder.add_tag_attributes(heading, ["id"]);
+    }
+    builder
+}
+
+/// An image whose alt text is still being collected.
+struct PendingImage {
+    url: String,
+    title: String,
+    alt: String,
+    /// Images nested in the alt text that are still open.
+    nested: usize,
+}
+
+/// CommonMark with the GitHub table, task list and strikethrough extensions, parsed by
+/// pulldown-cmark and cleaned by ammonia. Raw HTML is escaped rather than passed
+/// through, and links keep only `http`, `https`, `mailto` and workspace-relative targets.
+struct MarkdownRenderer<'a> {
+    /// Canonical workspace root; images are only inlined when set.
+    root: Option<&'a Path>,
+    /// Workspace-relative directory of the document, `/`-separated.
+    base_dir: String,
+    slugs: HashMap<String, usize>,
+    links: Vec<String>,
+    title: Option<String>,
+    inline_budget: u64,
+}
+
+impl<'a> MarkdownRenderer<'a> {
+    fn new(root: Option<&'a Path>, base_dir: String) -> Self {
+        Self {
+            root,
+            base_dir,
+            slugs: HashMap::new(),
+            links: Vec::new(),
+            title: None,
+            inline_budget: MAX_INLINE_IMAGES_TOTAL_BYTES,
+        }
+    }
+
+    fn render(&mut self, source: &str) -> String {
+        let options =
+            Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+        let mut events = Vec::new();
+        // Whether each open tag was kept; dropped links and tags nested too deeply aren't.
+        let mut open: Vec<bool> = Vec::new();
+        let mut heading_start = None;
+        let mut image: Option<PendingImage> = None;
+        for event in Parser::new_ext(source, options) {
+            if let Some(pending) = image.as_mut() {
+                match event {
+                    Event::Start(Tag::Image { .. }) => pending.nested += 1,
+                    Event::End(TagEnd::Image) if pending.nested > 0 => pending.nested -= 1,
+                    Event::End(TagEnd::Image) => {
+                        if let Some(pending) = image.take() {
+                            let title = Some(pending.title.as_str()).filter(|t| !t.is_empty());
+                            let html = self.image_html(&pending.alt, &pending.url, title);
+                            events.push(Event::InlineHtml(html.into()));
+                        }
+                    }
+                    Event::Text(text) | Event::Code(text) => pending.alt.push_str(&text),
+                    _ => {}
+                }
+                continue;
+            }
+            match event {
+                Event::Start(Tag::Image {
+                    dest_url, title, ..
+                }) => {
+                    image = Some(PendingImage {
+                        url: dest_url.to_string(),
+                        title: title.to_string(),
+                        alt: String::new(),
+                        nested: 0,
+                    });
+                }
+                Event::Start(_) if open.len() >= MAX_NESTING => open.push(false),
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    ..
+                }) => {
+                    let url = match link_type {
+                        LinkType::Email => format!("mailto:{dest_url}"),
+                        _ => dest_url.to_string(),
+                    };
+                    let title = Some(title.as_ref()).filter(|title| !title.is_empty());
+                    match self.link_open_tag(&url, title) {
+                        Some(tag) => {
+                            events.push(Event::InlineHtml(tag.into()));
+                            open.push(true);
+                        }
+                        None => open.push(false),
+                    }
+                }
+                Event::Start(tag) => {
+                    if matches!(tag, Tag::Heading { .. }) {
+                        heading_start = Some(events.len());
+                    }
+                    events.push(Event::Start(tag));
+                    open.push(true);
+                }
+                Event::End(end) => {
+                    if !open.pop().unwrap_or(false) {
+                        continue;
+                    }
+                    match end {
+                        TagEnd::Link => events.push(Event::InlineHtml("</a>".into())),
+                        TagEnd::Heading(level) => {
+                            if let Some(start) = heading_start.take() {
+                                self.name_heading(&mut events, start, level);
+                            }
+                            events.push(Event::End(end));
+                        }
+                        end => events.push(Event::End(end)),
+                    }
+                }
+                Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+                event => events.push(event),
+            }
+        }
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, events.into_iter());
+        sanitizer().clean(&html).to_string()
+    }
+
+    /// Gives the heading opened at `events[start]` a GitHub-style `id`; the first
+    /// level-1 heading becomes the title.
+    fn name_heading(&mut self, events: &mut [Event], start: usize, level: HeadingLevel) {
+        let text = events[start + 1..]
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+        let text = text.trim();
+        if level == HeadingLevel::H1 && self.title.is_none() && !text.is_empty() {
+            self.title = Some(text.to_string());
+        }
+        let mut slug = String::new();
+        for ch in text.chars() {
+            if ch.is_alphanumeric() || ch == '_' {
+                slug.extend(ch.to_lowercase());
+            } else if ch == ' ' || ch == '-' {
+                slug.push('-');
+            }
+        }
+        let seen = self.slugs.entry(slug.clone()).or_insert(0);
+        if *seen > 0 {
+            slug = format!("{slug}-{seen}");
+        }
+        *seen += 1;
+        events[start] = Event::Start(Tag::Heading {
+            level,
+            id: Some(slug.into()),
+            classes: Vec::new(),
+            attrs: Vec::new(),
+        });
+    }
+
+    fn push_link(&mut self, path: &str) {
+        if !self.links.iter().any(|link| link == path) {
+            self.links.push(path.to_string());
+        }
+    }
+
+    /// Workspace-relative path for a relative URL, or `None` when it leaves the
+    /// workspace or could be read as a URL scheme.
+    fn resolve_relative(&self, url: &str) -> Option<String> {
+        let decoded = percent_decode(url);
+        if decoded
+            .chars()
+            .any(|ch| ch.is_control() || ch == ':' || ch == '\\')
+        {
+            return None;
+        }
+        let joined = match decoded.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None if self.base_dir.is_empty() => decoded,
+            None => format!("{}/{decoded}", self.base_dir),
+        };
+        let mut parts = Vec::new();
+        for part in joined.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop()?;
+                }
+                part => parts.push(part),
+            }
+        }
+        (!parts.is_empty()).then(|| parts.join("/"))
+    }
+
+    /// `(href, workspace path)`; `None` drops the link but keeps its text.
+    fn resolve_href(&mut self, url: &str) -> Option<(String, Option<String>)> {
+        let url = url.trim();
+        if url.starts_with('#') {
+            return Some((url.to_string(), None));
+        }
+        if let Some(scheme) = url_scheme(url) {
+            return LINK_SCHEMES
+                .contains(&scheme.as_str())
+                .then(|| (url.to_string(), None));
+        }
+        if url.starts_with("//") {
+            return Some((format!("https:{url}"), None));
+        }
+        let (path, fragment) = match url.find('#') {
+            Some(at) => (&url[..at], &url[at..]),
+            None => (url, ""),
+        };
+        let path = path.split('?').next().unwrap_or("");
+        let relative = self.resolve_relative(path)?;
+        self.push_link(&relative);
+        Some((format!("{relative}{fragment}"), Some(relative)))
+    }
+
+    /// `(src, workspace path)`. Workspace images are inlined while the budget lasts.
+    fn resolve_image(&mut self, url: &str) -> (Option<String>, Option<String>) {
+        let url = url.trim();
+        if let Some(scheme) = url_scheme(url) {
+            return (
+                IMAGE_SCHEMES
+                    .contains(&scheme.as_str())
+                    .then(|| url.to_string()),
+                None,
+            );
+        }
+        if url.starts_with("//") {
+            return (Some(format!("https:{url}")), None);
+        }
+        let path = url.split(['?', '#']).next().unwrap_or("");
+        let Some(relative) = self.resolve_relative(path) else {
+            return (None, None);
+        };
+        self.push_link(&relative);
+        (self.inline_image(&relative), Some(relative))
+    }
+
+    fn inline_image(&mut self, relative: &str) -> Option<String> {
+        let root = self.root?;
+        let mime = image_mime(relative)?;
+        let path = root.join(relative).canonicalize().ok()?;
+        if !path.starts_with(root) {
+            return None;
+        }
+        let len = std::fs::metadata(&path).ok()?.len();
+        if len > MAX_INLINE_IMAGE_BYTES || len > self.inline_budget {
+            return None;
+        }
+        let bytes = std::fs::read(&path).ok()?;
+        self.inline_budget -= len;
+        Some(format!(
+            "data:{mime};base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ))
+    }
+
+    /// The `<a>` tag for a link, or `None` when the target is dropped.
+    fn link_open_tag(&mut self, url: &str, title: Option<&str>) -> Option<String> {
+        let (href, path) = self.resolve_href(url)?;
+        let mut attrs = format!(" href=\"{}\"", escape_html(&href));
+        match path {
+            Some(path) => attrs.push_str(&format!(" data-path=\"{}\"", escape_html(&path))),
+            None if !href.starts_with('#') => attrs.push_str(" rel=\"noopener noreferrer\""),
+            None => {}
+        }
+        if let Some(title) = title {
+            attrs.push_str(&format!(" title=\"{}\"", escape_html(title)));
+        }
+        Some(format!("<a{attrs}>"))
+    }
+
+    fn image_html(&mut self, alt: &str, url: &str, title: Option<&str>) -> String {
+        let (src, path) = self.resolve_image(url);
+        let mut attrs = String::new();
+        if let Some(src) = src {
+            attrs.push_str(&format!(" src=\"{}\"", escape_html(&src)));
+        }
+        attrs.push_str(&format!(" alt=\"{}\"", escape_html(alt)));
+        if let Some(path) = path {
+            attrs.push_str(&format!(" data-path=\"{}\"", escape_html(&path)));
+        }
+        if let Some(title) = title {
+            attrs.push_str(&format!(" title=\"{}\"", escape_html(title)));
+        }
+        format!("<img{attrs} />")
+    }
+}
+
+/// Text of a notebook field stored as a string or a list of lines.
+fn notebook_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\u{1b}' && chars.peek() == Some(&'[') {
+            chars.next();
+            for next in chars.by_ref() {
+                if ('@'..='~').contains(&next) {
+                    break;
+                }
+            }
+            continue;
+        }
+        out.push(ch);
+    }
+    out
+}
+
+fn output_text(text: String) -> String {
+    let text = strip_ansi(&text);
+    match text.char_indices().nth(MAX_OUTPUT_CHARS) {
+        Some((cut, _)) => format!("{}\n[output truncated]", &text[..cut]),
+        None => text,
+    }
+}
+
+fn notebook_output(output: &Value) -> Option<NotebookOutput> {
+    let output_type = output.get("output_type")?.as_str()?.to_string();
+    let (name, text, image) = match output_type.as_str() {
+        "stream" => (
+            output
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            Some(output_text(notebook_text(output.get("text")))),
+            None,
+        ),
+        "execute_result" | "display_data" => {
+            let data = output.get("data")?;
+            let image = ["image/png", "image/jpeg", "image/gif"]
+                .iter()
+                .find_map(|mime| {
+                    let encoded = notebook_text(data.get(*mime))
+                        .split_whitespace()
+                        .collect::<String>();
+                    let valid = !encoded.is_empty()
+                        && encoded
+                            .chars()
+                            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '/' | '='));
+                    valid.then(|| format!("data:{mime};base64,{encoded}"))
+                })
+                .or_else(|| {
+                    let svg = notebook_text(data.get("image/svg+xml"));
+                    (!svg.is_empty()).then(|| {
+                        format!(
+                            "data:image/svg+xml;base64,{}",
+                            base64::engine::general_purpose::STANDARD.encode(svg)
+                        )
+                    })
+                });
+            let text = data
+                .get("text/plain")
+                .map(|text| output_text(notebook_text(Some(text))));
+            (None, text, image)
+        }
+        "error" => {
+            let traceback = output
+                .get("traceback")
+                .and_then(Value::as_array)
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default();
+            let text = if traceback.is_empty() {
+                format!(
+                    "{}: {}",
+                    output
+                        .get("ename")
+                        .and_then(Value::as_str)
+                        .unwrap_or("Error"),
+                    output.get("evalue").and_then(Value::as_str).unwrap_or("")
+                )
+            } else {
+                traceback
+            };
+            (None, Some(output_text(text)), None)
+        }
+        _ => return None,
+    };
+    Some(NotebookOutput {
+        output_type,
+        name,
+        text,
+        image,
+    })
+}
+
+fn parse_notebook(notebook: &Value, renderer: &mut MarkdownRenderer) -> Result<Notebook, String> {
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "Not a Jupyter notebook (no cells)".to_string())?;
+    let metadata = notebook.get("metadata");
+    let language = metadata
+        .and_then(|metadata| metadata.pointer("/kernelspec/language"))
+        .or_else(|| metadata.and_then(|metadata| metadata.pointer("/language_info/name")))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let cells = cells
+        .iter()
+        .map(|cell| {
+            let cell_type = cell
+                .get("cell_type")
+                .and_then(Value::as_str)
+                .unwrap_or("raw")
+                .to_string();
+            let source = notebook_text(cell.get("source"));
+            let html = (cell_type == "markdown").then(|| renderer.render(&source));
+            NotebookCell {
+                execution_count: cell.get("execution_count").and_then(Value::as_u64),
+                outputs: cell
+                    .get("outputs")
+                    .and_then(Value::as_array)
+                    .map(|outputs| outputs.iter().filter_map(notebook_output).collect())
+                    .unwrap_or_default(),
+                cell_type,
+                source,
+                html,
+            }
+        })
+        .collect();
+    Ok(Notebook {
+        path: String::new(),
+        language,
+        cells,
+    })
+}
+
+/// `(canonical root, workspace-relative path, contents)` of a file inside `root`.
+fn read_workspace_file(
+    root: &Path,
+    path: &str,
+    max_bytes: u64,
+) -> Result<(PathBuf, String, String), String> {
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let canonical_path = canonical_root
+        .join(path)
+        .canonicalize()
+        .map_err(|err| format!("Failed to open {path}: {err}"))?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err("Path is outside the workspace".to_string());
+    }
+    let metadata = std::fs::metadata(&canonical_path).map_err(|err| err.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {path}"));
+    }
+    if metadata.len() > max_bytes {
+        return Err(format!("{path} is larger than {max_bytes} bytes"));
+    }
+    let bytes = std::fs::read(&canonical_path).map_err(|err| err.to_string())?;
+    let relative = canonical_path
+        .strip_prefix(&canonical_root)
+        .unwrap_or(&canonical_path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    Ok((
+        canonical_root,
+        relative,
+        String::from_utf8_lossy(&bytes).to_string(),
+    ))
+}
+
+fn parent_dir(relative: &str) -> String {
+    relative
+        .rsplit_once('/')
+        .map(|(dir, _)| dir.to_string())
+        .unwrap_or_default()
+}
+
+pub fn render_markdown(root: &Path, path: &str) -> Result<RenderedMarkdown, String> {
+    let (root, relative, source) = read_workspace_file(root, path, MAX_MARKDOWN_BYTES)?;
+    let mut renderer = MarkdownRenderer::new(Some(&root), parent_dir(&relative));
+    let html = renderer.render(&source);
+    Ok(RenderedMarkdown {
+        path: relative,
+        html,
+        title: renderer.title,
+        links: renderer.links,
+    })
+}
+
+pub fn render_notebook(root: &Path, path: &str) -> Result<Notebook, String> {
+    let (root, relative, source) = read_workspace_file(root, path, MAX_NOTEBOOK_BYTES)?;
+    let notebook: Value = serde_json::from_str(&source)
+        .map_err(|err| format!("Failed to parse notebook {relative}: {err}"))?;
+    let mut renderer = MarkdownRenderer::new(Some(&root), parent_dir(&relative));
+    let notebook = parse_notebook(&notebook, &mut renderer)?;
+    Ok(Notebook {
+        path: relative,
+        ..notebook
+    })
+}
+
+pub async fn render_markdown_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    path: String,
+) -> Result<RenderedMarkdown, String> {
+    let root = resolve_root_core(workspaces, FileScope::Workspace, Some(workspace_id)).await?;
+    tokio::task::spawn_blocking(move || render_markdown(&root, &path))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+pub async fn render_notebook_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    path: String,
+) -> Result<Notebook, String> {
+    let root = resolve_root_core(workspaces, FileScope::Workspace, Some(workspace_id)).await?;
+    tokio::task::spawn_blocking(move || render_notebook(&root, &path))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn render(source: &str) -> String {
+        MarkdownRenderer::new(None, "docs".to_string()).render(source)
+    }
+
+    #[test]
+    fn renders_blocks_and_inline_markup() {
+        let html = render(
+            "# Guide\n\nSome *em*, **strong**, ~~gone~~ and `a < b`.\n\n\
+             - [x] done\n- todo\n  1. nested\n\n\
+             | Name | Size |\n| :--- | ---: |\n| a | 1 |\n\n\
+             ```rust\nfn main() {}\n```\n\n> quoted\n\n---\n",
+        );
+        for expected in [
+            "<h1 id=\"guide\">Guide</h1>",
+            "<em>em</em>, <strong>strong</strong>, <del>gone</del> and <code>a &lt; b</code>",
+            "<li><input disabled=\"\" type=\"checkbox\" checked=\"\">\ndone</li>",
+            "<li>todo\n<ol>\n<li>nested</li>\n</ol>\n</li>",
+            "<th style=\"text-align: left\">Name</th>",
+            "<td style=\"text-align: right\">1</td>",
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>",
+            "<blockquote>\n<p>quoted</p>\n</blockquote>",
+            "<hr>",
+        ] {
+            assert!(html.contains(expected), "missing {expected:?} in {html}");
+        }
+    }
+
+    #[test]
+    fn sanitizes_html_and_resolves_links() {
+        let mut renderer = MarkdownRenderer::new(None, "docs/guide".to_string());
+        let html = renderer.render(
+            "<script>alert(1)</script>\n\n\
+             [up](../README.md#usage) [out](../../../etc/passwd) [js](javascript:alert(1)) \
+             [ref][site] <https://example.com> ![logo](img/logo.png)\n\n\
+             [site]: https://example.org \"Site\"\n",
+        );
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(
+            html.contains("<a href=\"docs/README.md#usage\" data-path=\"docs/README.md\">up</a>")
+        );
+        assert!(html.contains(" out "));
+        assert!(!html.contains("javascript"));
+        assert!(html.contains(
+            "<a href=\"https://example.org\" rel=\"noopener noreferrer\" title=\"Site\">ref</a>"
+        ));
+        assert!(html.contains("<a href=\"https://example.com\""));
+        assert!(html.contains("<img alt=\"logo\" data-path=\"docs/guide/img/logo.png\">"));
+        assert_eq!(
+            renderer.links,
+            vec![
+                "docs/README.md".to_string(),
+                "docs/guide/img/logo.png".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_raw_html_and_hostile_urls() {
+        let html = render(
+            "[t](https://e.com \"ti\\\"tle\" ) [x](&#106;avascript:alert(1)) \
+             [y](java&#x09;script:alert(1)) <javascript:alert(1)> <a@b.com>\n\n\
+             Hi <img src=x onerror=alert(1)> and <a href=\"javascript:x\">z</a>\n\n\
+             ![svg](data:image/svg+xml;base64,PHN2Zz4= \"t\") ![png](//e.com/a.png)\n\n\
+             <div onclick=\"x\">block</div>\n",
+        );
+        assert!(html.contains(
+            "<a href=\"https://e.com\" rel=\"noopener noreferrer\" title=\"ti&quot;tle\">t</a>"
+        ));
+        assert!(html.contains(" x y javascript:alert(1) "));
+        assert!(html.contains("<a href=\"mailto:a@b.com\""));
+        assert!(html.contains("Hi &lt;img src=x onerror=alert(1)&gt; and &lt;a href="));
+        assert!(html.contains("&lt;div onclick=\"x\"&gt;block&lt;/div&gt;"));
+        assert!(html.contains("<img alt=\"svg\" title=\"t\">"));
+        assert!(html.contains("<img src=\"https://e.com/a.png\" alt=\"png\">"));
+        assert!(!html.contains("data-path"));
+        assert!(!html.contains("<a href=\"javascript") && !html.contains("src=\"data:"));
+
+        let nested = render(&">".repeat(10_000));
+        assert_eq!(nested.matches("<blockquote>").count(), MAX_NESTING);
+    }
+
+    #[test]
+    fn extracts_notebook_cells_and_outputs() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-preview-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("nb")).expect("create dir");
+        let notebook = json!({
+            "metadata": { "kernelspec": { "language": "python" } },
+            "cells": [
+                { "cell_type": "markdown", "source": ["# Analysis\n", "See [data](data.csv)."] },
+                {
+                    "cell_type": "code",
+                    "execution_count": 3,
+                    "source": "print(1)\n1/0",
+                    "outputs": [
+                        { "output_type": "stream", "name": "stdout", "text": ["1\n"] },
+                        { "output_type": "display_data", "data": { "image/png": "iVBORw0K\nGgo=\n", "text/plain": "<Figure>" } },
+                        {
+                            "output_type": "error",
+                            "ename": "ZeroDivisionError",
+                            "evalue": "division by zero",
+                            "traceback": ["\u{1b}[0;31mZeroDivisionError\u{1b}[0m: division by zero"]
+                        }
+                    ]
+                }
+            ]
+        });
+        std::fs::write(root.join("nb").join("a.ipynb"), notebook.to_string()).expect("write");
+
+        let notebook = render_notebook(&root, "nb/a.ipynb").expect("notebook");
+        assert_eq!(notebook.path, "nb/a.ipynb");
+        assert_eq!(notebook.language.as_deref(), Some("python"));
+        let html = notebook.cells[0].html.as_deref().expect("markdown html");
+        assert!(html.contains("<h1 id=\"analysis\">Analysis</h1>"));
+        assert!(html.contains("href=\"nb/data.csv\""));
+        let code = &notebook.cells[1];
+        assert_eq!(
+            (code.cell_type.as_str(), code.execution_count),
+            ("code", Some(3))
+        );
+        assert_eq!(code.outputs[0].text.as_deref(), Some("1\n"));
+        assert_eq!(
+            code.outputs[1].image.as_deref(),
+            Some("data:image/png;base64,iVBORw0KGgo=")
+        );
+        assert_eq!(
+            code.outputs[2].text.as_deref(),
+            Some("ZeroDivisionError: division by zero")
+        );
+        assert!(render_notebook(&root, "../outside.ipynb").is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
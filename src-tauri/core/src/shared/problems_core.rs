@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ProblemSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// One diagnostic, whatever produced it: a build run today, language servers later.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    /// The tool that reported it, e.g. `cargo`, `tsc` or `go vet`.
+    pub source: String,
+    /// Workspace-relative, with `/` separators.
+    pub path: String,
+    /// 1-based.
+    pub line: usize,
+    pub column: Option<usize>,
+    pub severity: ProblemSeverity,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemsView {
+    pub workspace_id: String,
+    pub error_count: usize,
+    pub warning_count: usize,
+    /// Errors first, then by path and line.
+    pub problems: Vec<Problem>,
+}
+
+/// Errors first, then by path and position.
+pub fn sort_problems(problems: &mut [Problem]) {
+    problems.sort_by(|a, b| {
+        a.severity
+            .cmp(&b.severity)
+            .then_with(|| a.path.cmp(&b.path))
+            .then(a.line.cmp(&b.line))
+            .then(a.column.cmp(&b.column))
+    });
+}
+
+/// Latest problems per workspace, kept per producer so one producer's refresh
+/// (a new build run, a language server's publish) doesn't clear the others'.
+#[derive(Default)]
+pub struct Problems {
+    entries: Mutex<HashMap<String, HashMap<String, Vec<Problem>>>>,
+}
+
+impl Problems {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, HashMap<String, Vec<Problem>>>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn replace(&self, workspace_id: &str, producer: &str, problems: Vec<Problem>) {
+        let mut entries = self.lock();
+        let producers = entries.entry(workspace_id.to_string()).or_default();
+        if problems.is_empty() {
+            producers.remove(producer);
+        } else {
+            producers.insert(producer.to_string(), problems);
+        }
+        if producers.is_empty() {
+            entries.remove(workspace_id);
+        }
+    }
+
+    pub fn view(&self, workspace_id: &str) -> ProblemsView {
+        let mut problems = self
+            .lock()
+            .get(workspace_id)
+            .map(|producers| producers.values().flatten().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        sort_problems(&mut problems);
+        let count = |severity| {
+            problems
+                .iter()
+                .filter(|problem| problem.severity == severity)
+                .count()
+        };
+        ProblemsView {
+            workspace_id: workspace_id.to_string(),
+            error_count: count(ProblemSeverity::Error),
+            warning_count: count(ProblemSeverity::Warning),
+            problems,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(source: &str, path: &str, line: usize, severity: ProblemSeverity) -> Problem {
+        Problem {
+            source: source.to_string(),
+            path: path.to_string(),
+            line,
+            column: None,
+            severity,
+            code: None,
+            message: "boom".to_string(),
+        }
+    }
+
+    #[test]
+    fn keeps_problems_per_producer() {
+        let problems = Problems::default();
+        problems.replace(
+            "ws",
+            "build",
+            vec![
+                problem("cargo", "src/b.rs", 3, ProblemSeverity::Warning),
+                problem("cargo", "src/a.rs", 9, ProblemSeverity::Error),
+            ],
+        );
+        problems.replace(
+            "ws",
+            "lsp",
+            vec![problem("tsc", "src/a.ts", 1, ProblemSeverity::Error)],
+        );
+        let view = problems.view("ws");
+        assert_eq!((view.error_count, view.warning_count), (2, 1));
+        assert_eq!(
+            view.problems
+                .iter()
+                .map(|problem| problem.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["src/a.rs", "src/a.ts", "src/b.rs"]
+        );
+
+        problems.replace("ws", "build", Vec::new());
+        assert_eq!(problems.view("ws").problems.len(), 1);
+        problems.replace("ws", "lsp", Vec::new());
+        assert!(problems.view("ws").problems.is_empty());
+    }
+}
@@ -19,13 +19,13 @@ fn hide_console_on_windows(_command: &mut std::process::Command) {
     }
 }
 
-pub(crate) fn tokio_command(program: impl AsRef<OsStr>) -> Command {
+pub fn tokio_command(program: impl AsRef<OsStr>) -> Command {
     let mut command = Command::new(program);
     hide_console_on_windows(command.as_std_mut());
     command
 }
 
-pub(crate) async fn kill_child_process_tree(child: &mut Child) {
+pub async fn kill_child_process_tree(child: &mut Child) {
     #[cfg(windows)]
     {
         if let Some(pid) = child.id() {
@@ -45,7 +45,7 @@ pub(crate) async fn kill_child_process_tree(child: &mut Child) {
 }
 
 #[cfg(target_os = "windows")]
-pub(crate) fn resolve_windows_executable(program: &str, path_env: Option<&str>) -> Option<PathBuf> {
+pub fn resolve_windows_executable(program: &str, path_env: Option<&str>) -> Option<PathBuf> {
     let trimmed = program.trim();
     if trimmed.is_empty() {
         return None;
@@ -145,7 +145,7 @@ fn quote_cmd_token(value: &str) -> Result<String, String> {
 /// Returns a string that already includes the required outer quotes, suitable to be passed as
 /// *one* argument after `/C` (usually with `/S`).
 #[cfg(target_os = "windows")]
-pub(crate) fn build_cmd_c_command(program: &Path, args: &[String]) -> Result<String, String> {
+pub fn build_cmd_c_command(program: &Path, args: &[String]) -> Result<String, String> {
     let program_str = program.to_string_lossy();
     let mut parts: Vec<String> = Vec::with_capacity(args.len() + 1);
     parts.push(quote_cmd_token(program_str.as_ref())?);
@@ -0,0 +1,172 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// What a child process is for; each kind has its own concurrency limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessKind {
+    /// Long-lived `codex app-server` sessions; the slot is held until the session drops.
+    Session,
+    Git,
+    /// Everything else: `gh`, audit tools, `codex --version`…
+    Tool,
+}
+
+impl ProcessKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::Git => "git",
+            Self::Tool => "tool",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessLimits {
+    pub sessions: usize,
+    pub git: usize,
+    pub tools: usize,
+    /// How long a spawn waits for a free slot before failing.
+    pub queue_timeout: Duration,
+}
+
+impl Default for ProcessLimits {
+    fn default() -> Self {
+        Self {
+            sessions: 32,
+            git: 16,
+            tools: 8,
+            queue_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ProcessLimits {
+    /// Applies one `<kind>=<n>` value, e.g. `git=8`.
+    pub fn apply(&mut self, value: &str) -> Result<(), String> {
+        let (kind, limit) = value
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid process limit `{value}`; expected <kind>=<n>."))?;
+        let limit = limit
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|limit| *limit > 0)
+            .ok_or_else(|| format!("Invalid process limit `{value}`; <n> must be at least 1."))?;
+        match kind.trim() {
+            "session" | "sessions" => self.sessions = limit,
+            "git" => self.git = limit,
+            "tool" | "tools" => self.tools = limit,
+            other => {
+                return Err(format!(
+                    "Unknown process kind `{other}`; expected session, git or tool."
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One semaphore per process kind. Spawns queue for a slot up to the queue timeout.
+pub struct ProcessLimiter {
+    sessions: Arc<Semaphore>,
+    git: Arc<Semaphore>,
+    tools: Arc<Semaphore>,
+    limits: ProcessLimits,
+}
+
+impl ProcessLimiter {
+    pub fn new(limits: ProcessLimits) -> Self {
+        Self {
+            sessions: Arc::new(Semaphore::new(limits.sessions)),
+            git: Arc::new(Semaphore::new(limits.git)),
+            tools: Arc::new(Semaphore::new(limits.tools)),
+            limits,
+        }
+    }
+
+    fn semaphore(&self, kind: ProcessKind) -> (&Arc<Semaphore>, usize) {
+        match kind {
+            ProcessKind::Session => (&self.sessions, self.limits.sessions),
+            ProcessKind::Git => (&self.git, self.limits.git),
+            ProcessKind::Tool => (&self.tools, self.limits.tools),
+        }
+    }
+
+    /// Waits for a free `kind` slot. Keep the permit alive until the process has exited.
+    pub async fn acquire(&self, kind: ProcessKind) -> Result<OwnedSemaphorePermit, String> {
+        let (semaphore, limit) = self.semaphore(kind);
+        let waited = tokio::time::timeout(
+            self.limits.queue_timeout,
+            Arc::clone(semaphore).acquire_owned(),
+        )
+        .await;
+        match waited {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(_)) => Err("Process limiter is closed.".to_string()),
+            Err(_) => Err(format!(
+                "Timed out after {}s waiting for a {} process slot ({limit} already running).",
+                self.limits.queue_timeout.as_secs(),
+                kind.label()
+            )),
+        }
+    }
+}
+
+static PROCESS_LIMITER: OnceLock<ProcessLimiter> = OnceLock::new();
+
+/// Sets the global limits; must run before the first spawn, later calls fail.
+#[allow(dead_code)]
+pub fn configure_process_limits(limits: ProcessLimits) -> Result<(), String> {
+    PROCESS_LIMITER
+        .set(ProcessLimiter::new(limits))
+        .map_err(|_| "Process limits are already configured.".to_string())
+}
+
+/// Waits for a slot in the global limiter, which uses the default limits unless
+/// `configure_process_limits` ran first.
+pub async fn acquire_process_slot(
+    kind: ProcessKind,
+) -> Result<OwnedSemaphorePermit, String> {
+    PROCESS_LIMITER
+        .get_or_init(|| ProcessLimiter::new(ProcessLimits::default()))
+        .acquire(kind)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queues_until_a_slot_frees_up_or_times_out() {
+        let mut limits = ProcessLimits {
+            queue_timeout: Duration::from_millis(50),
+            ..ProcessLimits::default()
+        };
+        limits.apply("git=1").expect("git limit");
+        assert!(limits.apply("git=0").is_err());
+        assert!(limits.apply("rg=2").is_err());
+        assert_eq!(limits.git, 1);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let limiter = ProcessLimiter::new(limits);
+            let first = limiter.acquire(ProcessKind::Git).await.expect("first slot");
+            let error = limiter.acquire(ProcessKind::Git).await.unwrap_err();
+            assert!(error.contains("git process slot"), "{error}");
+            let _tool = limiter
+                .acquire(ProcessKind::Tool)
+                .await
+                .expect("other kind");
+
+            drop(first);
+            let _freed = limiter.acquire(ProcessKind::Git).await.expect("freed slot");
+        });
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use crate::rules::{expand_home, glob_matches};
+
+/// Paths neither clients nor agents may write unless the `protectedPathGlobs` setting is edited.
+pub const DEFAULT_PROTECTED_PATH_GLOBS: &[&str] = &[
+    "**/.git/**",
+    "~/.ssh/**",
+    "~/.aws/**",
+    "~/.gnupg/**",
+    "~/.kube/**",
+    "**/.env",
+    "**/.env.*",
+    "**/*.pem",
+    "**/*.key",
+    "**/id_rsa*",
+    "**/id_ed25519*",
+    "**/*.tfstate",
+    "**/*.tfstate.backup",
+];
+
+/// Returns the first glob protecting `target`. Globs are tried against the absolute path and,
+/// when `target` is inside `workspace_root`, against the workspace-relative path too.
+pub fn protected_match(
+    globs: &[String],
+    workspace_root: Option<&Path>,
+    target: &Path,
+) -> Option<String> {
+    let absolute = target.to_string_lossy().replace('\\', "/");
+    let relative = workspace_root
+        .and_then(|root| target.strip_prefix(root).ok())
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"));
+    globs
+        .iter()
+        .find(|glob| {
+            let pattern = expand_home(glob.trim());
+            glob_matches(&pattern, &absolute)
+                || relative
+                    .as_deref()
+                    .is_some_and(|relative| glob_matches(&pattern, relative))
+        })
+        .cloned()
+}
+
+pub fn ensure_write_allowed(
+    globs: &[String],
+    workspace_root: Option<&Path>,
+    target: &Path,
+) -> Result<(), String> {
+    match protected_match(globs, workspace_root, target) {
+        Some(glob) => Err(format!(
+            "Refusing to write protected path {} (matches `{glob}`)",
+            target.display()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Drops sandbox writable roots that are themselves protected.
+pub fn filter_writable_roots(globs: &[String], roots: Vec<String>) -> Vec<String> {
+    roots
+        .into_iter()
+        .filter(|root| protected_match(globs, None, Path::new(root)).is_none())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<String> {
+        DEFAULT_PROTECTED_PATH_GLOBS
+            .iter()
+            .map(|glob| glob.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn blocks_git_internals_and_secrets() {
+        let globs = defaults();
+        let root = Path::new("/work/repo");
+        assert!(ensure_write_allowed(&globs, Some(root), &root.join(".git/config")).is_err());
+        assert!(ensure_write_allowed(&globs, Some(root), &root.join("deploy/.env")).is_err());
+        assert!(ensure_write_allowed(&globs, Some(root), &root.join("AGENTS.md")).is_ok());
+        assert_eq!(
+            protected_match(&globs, Some(root), &root.join("certs/server.pem")).as_deref(),
+            Some("**/*.pem")
+        );
+    }
+
+    #[test]
+    fn filters_protected_writable_roots() {
+        let globs = vec!["/work/infra/**".to_string()];
+        let roots = vec!["/work/repo".to_string(), "/work/infra/prod".to_string()];
+        assert_eq!(
+            filter_writable_roots(&globs, roots),
+            vec!["/work/repo".to_string()]
+        );
+    }
+}
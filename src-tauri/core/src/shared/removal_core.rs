@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::shared::git_core::run_git_command;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::types::WorkspaceEntry;
+
+/// How long a removal plan's confirmation token can be redeemed.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A folder that removal deletes from disk.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovalFolder {
+    pub workspace_id: String,
+    pub name: String,
+    pub path: String,
+    pub branch: Option<String>,
+    /// Files `git status` reports as changed or untracked; `None` if git couldn't tell.
+    pub uncommitted_files: Option<usize>,
+}
+
+/// What `remove_workspace`/`remove_worktree` would do, returned instead of removing.
+/// Pass `confirmToken` back within the TTL to go ahead.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovalPlan {
+    pub confirm_token: String,
+    pub workspace_id: String,
+    pub name: String,
+    pub path: String,
+    /// Every workspace entry that will be unregistered, the target first.
+    pub unregistered: Vec<String>,
+    /// Worktree folders deleted from disk. A main workspace's own folder is never deleted.
+    pub deleted_folders: Vec<RemovalFolder>,
+    pub expires_at: u64,
+}
+
+struct PendingRemoval {
+    workspace_id: String,
+    fingerprint: Vec<String>,
+    expires_at: u64,
+}
+
+/// Outstanding confirmation tokens. Each is single-use and bound to the exact set of
+/// entries and folders its plan listed.
+#[derive(Default)]
+pub struct RemovalConfirmations {
+    pending: StdMutex<HashMap<String, PendingRemoval>>,
+}
+
+impl RemovalConfirmations {
+    fn issue(&self, workspace_id: &str, fingerprint: Vec<String>, now: u64) -> (String, u64) {
+        let token = Uuid::new_v4().to_string();
+        let expires_at = now + CONFIRMATION_TTL.as_millis() as u64;
+        let mut pending = self.pending.lock().unwrap_or_else(|err| err.into_inner());
+        pending.retain(|_, removal| removal.expires_at > now);
+        pending.insert(
+            token.clone(),
+            PendingRemoval {
+                workspace_id: workspace_id.to_string(),
+                fingerprint,
+                expires_at,
+            },
+        );
+        (token, expires_at)
+    }
+
+    fn redeem(
+        &self,
+        token: &str,
+        workspace_id: &str,
+        fingerprint: &[String],
+        now: u64,
+    ) -> Result<(), String> {
+        let removal = self
+            .pending
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(token)
+            .filter(|removal| removal.workspace_id == workspace_id)
+            .ok_or("Unknown or already used confirmation token; request a new removal plan.")?;
+        if removal.expires_at <= now {
+            return Err("Confirmation token expired; request a new removal plan.".to_string());
+        }
+        if removal.fingerprint != fingerprint {
+            return Err(
+                "The workspace changed since the removal plan was made; request a new one."
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn protected_error(entry: &WorkspaceEntry) -> String {
+    format!(
+        "\"{}\" is protected; turn off its protected setting before removing it.",
+        entry.name
+    )
+}
+
+/// The target entry plus the worktrees removed with it, checked against the kind of
+/// removal and the protected flag.
+fn removal_targets(
+    workspaces: &HashMap<String, WorkspaceEntry>,
+    id: &str,
+    worktree: bool,
+) -> Result<(WorkspaceEntry, Vec<WorkspaceEntry>), String> {
+    let entry = workspaces
+        .get(id)
+        .cloned()
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    match (worktree, entry.kind.is_worktree()) {
+        (false, true) => return Err("Use remove_worktree for worktree agents.".to_string()),
+        (true, false) => return Err("Not a worktree workspace.".to_string()),
+        _ => {}
+    }
+    let mut children = if worktree {
+        Vec::new()
+    } else {
+        workspaces
+            .values()
+            .filter(|workspace| workspace.parent_id.as_deref() == Some(id))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    children.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+    ensure_removable(&entry, &children)?;
+    Ok((entry, children))
+}
+
+/// Refuses removal when the workspace or any worktree removed with it is protected.
+pub fn ensure_removable(
+    entry: &WorkspaceEntry,
+    children: &[WorkspaceEntry],
+) -> Result<(), String> {
+    if entry.settings.protected {
+        return Err(protected_error(entry));
+    }
+    if let Some(child) = children.iter().find(|child| child.settings.protected) {
+        return Err(protected_error(child));
+    }
+    Ok(())
+}
+
+fn fingerprint(entry: &WorkspaceEntry, children: &[WorkspaceEntry]) -> Vec<String> {
+    std::iter::once(entry)
+        .chain(children)
+        .map(|workspace| format!("{}\n{}", workspace.id, workspace.path))
+        .collect()
+}
+
+async fn removal_folder(entry: &WorkspaceEntry) -> RemovalFolder {
+    let status = run_git_command(
+        &PathBuf::from(&entry.path),
+        &["status", "--porcelain", "--untracked-files=all"],
+    )
+    .await;
+    RemovalFolder {
+        workspace_id: entry.id.clone(),
+        name: entry.name.clone(),
+        path: entry.path.clone(),
+        branch: entry.worktree.as_ref().map(|info| info.branch.clone()),
+        uncommitted_files: status
+            .ok()
+            .map(|status| status.lines().filter(|line| !line.is_empty()).count()),
+    }
+}
+
+/// First step of a removal: describes what would be deleted and issues a token.
+pub async fn plan_removal_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    confirmations: &RemovalConfirmations,
+    id: &str,
+    worktree: bool,
+) -> Result<RemovalPlan, String> {
+    let (entry, children) = removal_targets(&*workspaces.lock().await, id, worktree)?;
+    let mut deleted_folders = Vec::new();
+    let deleted = if worktree {
+        std::slice::from_ref(&entry)
+    } else {
+        &children[..]
+    };
+    for workspace in deleted {
+        deleted_folders.push(removal_folder(workspace).await);
+    }
+    let (confirm_token, expires_at) =
+        confirmations.issue(id, fingerprint(&entry, &children), now_millis());
+    Ok(RemovalPlan {
+        confirm_token,
+        workspace_id: entry.id.clone(),
+        name: entry.name.clone(),
+        path: entry.path.clone(),
+        unregistered: std::iter::once(&entry)
+            .chain(&children)
+            .map(|workspace| workspace.id.clone())
+            .collect(),
+        deleted_folders,
+        expires_at,
+    })
+}
+
+/// Second step: consumes the token if it still matches the workspace as it is now.
+pub async fn confirm_removal_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    confirmations: &RemovalConfirmations,
+    id: &str,
+    worktree: bool,
+    token: &str,
+) -> Result<(), String> {
+    let (entry, children) = removal_targets(&*workspaces.lock().await, id, worktree)?;
+    confirmations.redeem(token, id, &fingerprint(&entry, &children), now_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{WorkspaceKind, WorkspaceSettings, WorktreeInfo};
+
+    fn entry(id: &str, parent: Option<&str>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: format!("/nonexistent/{id}"),
+            codex_bin: None,
+            kind: if parent.is_some() {
+                WorkspaceKind::Worktree
+            } else {
+                WorkspaceKind::Main
+            },
+            parent_id: parent.map(str::to_string),
+            worktree: parent.map(|_| WorktreeInfo {
+                branch: format!("feat/{id}"),
+            }),
+            settings: WorkspaceSettings::default(),
+        }
+    }
+
+    fn workspaces(entries: Vec<WorkspaceEntry>) -> Mutex<HashMap<String, WorkspaceEntry>> {
+        Mutex::new(
+            entries
+                .into_iter()
+                .map(|entry| (entry.id.clone(), entry))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn plans_and_confirms_a_workspace_removal_once() {
+        let workspaces = workspaces(vec![
+            entry("main", None),
+            entry("wt-b", Some("main")),
+            entry("wt-a", Some("main")),
+        ]);
+        let confirmations = RemovalConfirmations::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let plan = plan_removal_core(&workspaces, &confirmations, "main", false)
+                .await
+                .expect("plan");
+            assert_eq!(plan.unregistered, vec!["main", "wt-a", "wt-b"]);
+            let folders: Vec<_> = plan
+                .deleted_folders
+                .iter()
+                .map(|folder| (folder.name.as_str(), folder.branch.as_deref()))
+                .collect();
+            assert_eq!(
+                folders,
+                vec![("wt-a", Some("feat/wt-a")), ("wt-b", Some("feat/wt-b"))]
+            );
+            assert_eq!(plan.deleted_folders[0].uncommitted_files, None);
+
+            assert!(
+                confirm_removal_core(
+                    &workspaces,
+                    &confirmations,
+                    "wt-a",
+                    true,
+                    &plan.confirm_token
+                )
+                .await
+                .is_err(),
+                "a token only confirms the workspace it was issued for"
+            );
+            let plan = plan_removal_core(&workspaces, &confirmations, "main", false)
+                .await
+                .expect("plan");
+            workspaces
+                .lock()
+                .await
+                .insert("wt-c".to_string(), entry("wt-c", Some("main")));
+            let changed = confirm_removal_core(
+                &workspaces,
+                &confirmations,
+                "main",
+                false,
+                &plan.confirm_token,
+            )
+            .await
+            .expect_err("changed");
+            assert!(changed.contains("changed"), "{changed}");
+
+            let plan = plan_removal_core(&workspaces, &confirmations, "main", false)
+                .await
+                .expect("plan");
+            confirm_removal_core(
+                &workspaces,
+                &confirmations,
+                "main",
+                false,
+                &plan.confirm_token,
+            )
+            .await
+            .expect("confirm");
+            assert!(
+                confirm_removal_core(
+                    &workspaces,
+                    &confirmations,
+                    "main",
+                    false,
+                    &plan.confirm_token
+                )
+                .await
+                .is_err(),
+                "tokens are single-use"
+            );
+        });
+    }
+
+    #[test]
+    fn protected_workspaces_and_expired_tokens_are_refused() {
+        let mut protected_child = entry("wt-a", Some("main"));
+        protected_child.settings.protected = true;
+        let workspaces = workspaces(vec![entry("main", None), protected_child]);
+        let confirmations = RemovalConfirmations::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let error = plan_removal_core(&workspaces, &confirmations, "main", false)
+                .await
+                .expect_err("protected child");
+            assert!(error.contains("\"wt-a\" is protected"), "{error}");
+            assert!(plan_removal_core(&workspaces, &confirmations, "wt-a", true)
+                .await
+                .is_err());
+            assert!(plan_removal_core(&workspaces, &confirmations, "main", true)
+                .await
+                .is_err());
+        });
+
+        let fingerprint = vec!["main".to_string()];
+        let (token, expires_at) = confirmations.issue("main", fingerprint.clone(), 1_000);
+        let error = confirmations
+            .redeem(&token, "main", &fingerprint, expires_at)
+            .expect_err("expired");
+        assert!(error.contains("expired"), "{error}");
+    }
+}
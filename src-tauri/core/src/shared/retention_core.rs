@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::shared::codex_core;
+use crate::shared::list_cache_core;
+use crate::types::{RetentionPolicy, WorkspaceEntry};
+
+/// How often the daemon applies the retention policy.
+pub const RETENTION_INTERVAL_SECS: u64 = 60 * 60;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+/// Where Codex moves a thread's rollout when it is archived, under `CODEX_HOME`.
+const ARCHIVED_SESSIONS_DIR: &str = "archived_sessions";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetainedThread {
+    pub workspace_id: String,
+    pub thread_id: String,
+    pub idle_days: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgedRollout {
+    pub path: String,
+    pub bytes: u64,
+    pub age_days: u64,
+    pub error: Option<String>,
+}
+
+/// What one retention pass archived and purged, or with `dry_run` would have.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub policy: RetentionPolicy,
+    pub archived: Vec<RetainedThread>,
+    pub purged: Vec<PurgedRollout>,
+    pub bytes_freed: u64,
+    /// Workspaces or Codex homes that could not be inspected.
+    pub errors: Vec<String>,
+}
+
+impl RetentionReport {
+    pub fn is_empty(&self) -> bool {
+        self.archived.is_empty() && self.purged.is_empty()
+    }
+}
+
+/// Thread timestamps come in seconds or milliseconds depending on the Codex version.
+fn timestamp_secs(value: &Value) -> Option<u64> {
+    let raw = value
+        .as_u64()
+        .or_else(|| value.as_f64().filter(|v| *v >= 0.0).map(|v| v as u64))?;
+    Some(if raw >= 1_000_000_000_000 {
+        raw / 1000
+    } else {
+        raw
+    })
+}
+
+/// Threads in a `thread/list` response idle for at least `idle_days`, with their idle
+/// days. Threads without a timestamp are left alone.
+pub fn idle_threads(list: &Value, now_secs: u64, idle_days: u32) -> Vec<(String, u64)> {
+    let result = list.get("result").unwrap_or(list);
+    let Some(data) = result.get("data").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    data.iter()
+        .filter_map(|thread| {
+            let id = thread.get("id").and_then(Value::as_str)?;
+            let updated = thread
+                .get("updatedAt")
+                .and_then(timestamp_secs)
+                .or_else(|| thread.get("createdAt").and_then(timestamp_secs))?;
+            let idle = now_secs.saturating_sub(updated) / SECS_PER_DAY;
+            (idle >= u64::from(idle_days)).then(|| (id.to_string(), idle))
+        })
+        .collect()
+}
+
+fn rollout_cwd(path: &Path) -> Option<PathBuf> {
+    use std::io::BufRead;
+    let file = std::fs::File::open(path).ok()?;
+    let mut line = String::new();
+    std::io::BufReader::new(file).read_line(&mut line).ok()?;
+    let value: Value = serde_json::from_str(line.trim()).ok()?;
+    value
+        .get("payload")
+        .and_then(|payload| payload.get("cwd"))
+        .and_then(Value::as_str)
+        .map(PathBuf::from)
+}
+
+/// Archived rollouts under `codex_home` older than `purge_days` whose session ran
+/// inside one of `workspace_paths`; rollouts from other projects sharing the Codex
+/// home are never touched.
+pub fn purge_candidates(
+    codex_home: &Path,
+    workspace_paths: &[PathBuf],
+    now: SystemTime,
+    purge_days: u32,
+) -> Vec<PurgedRollout> {
+    let mut candidates = Vec::new();
+    let mut pending = vec![codex_home.join(ARCHIVED_SESSIONS_DIR)];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let age_days = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .map(|age| age.as_secs() / SECS_PER_DAY)
+                .unwrap_or(0);
+            if age_days < u64::from(purge_days) {
+                continue;
+            }
+            let Some(cwd) = rollout_cwd(&path) else {
+                continue;
+            };
+            if !workspace_paths.iter().any(|root| cwd.starts_with(root)) {
+                continue;
+            }
+            candidates.push(PurgedRollout {
+                path: path.to_string_lossy().to_string(),
+                bytes: metadata.len(),
+                age_days,
+                error: None,
+            });
+        }
+    }
+    candidates.sort_by(|a, b| a.path.cmp(&b.path));
+    candidates
+}
+
+/// Applies `policy` to every connected workspace. Each thread is archived once even
+/// when several workspaces list it.
+pub async fn run_retention_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    policy: RetentionPolicy,
+    dry_run: bool,
+) -> RetentionReport {
+    let mut report = RetentionReport {
+        dry_run,
+        policy: policy.clone(),
+        archived: Vec::new(),
+        purged: Vec::new(),
+        bytes_freed: 0,
+        errors: Vec::new(),
+    };
+    let now = SystemTime::now();
+    let now_secs = now
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+
+    if let Some(idle_days) = policy.archive_idle_days {
+        let mut connected = sessions.lock().await.keys().cloned().collect::<Vec<_>>();
+        connected.sort();
+        let mut seen = HashSet::new();
+        for workspace_id in connected {
+            let list = list_cache_core::fetch_list(None, true, |cursor| {
+                codex_core::list_threads_core(
+                    sessions,
+                    workspace_id.clone(),
+                    cursor,
+                    Some(100),
+                    Some("updated_at".to_string()),
+                )
+            })
+            .await;
+            let list = match list {
+                Ok(list) => list,
+                Err(err) => {
+                    report.errors.push(format!("{workspace_id}: {err}"));
+                    continue;
+                }
+            };
+            for (thread_id, idle_days) in idle_threads(&list, now_secs, idle_days) {
+                if !seen.insert(thread_id.clone()) {
+                    continue;
+                }
+                let error = if dry_run {
+                    None
+                } else {
+                    codex_core::archive_thread_core(
+                        sessions,
+                        workspace_id.clone(),
+                        thread_id.clone(),
+                    )
+                    .await
+                    .err()
+                };
+                report.archived.push(RetainedThread {
+                    workspace_id: workspace_id.clone(),
+                    thread_id,
+                    idle_days,
+                    error,
+                });
+            }
+        }
+    }
+
+    if let Some(purge_days) = policy.purge_archived_days {
+        let entries = workspaces.lock().await.clone();
+        let workspace_paths = entries
+            .values()
+            .map(|entry| PathBuf::from(&entry.path))
+            .collect::<Vec<_>>();
+        let mut ids = entries.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        let mut homes = Vec::new();
+        for id in ids {
+            match codex_core::resolve_codex_home_for_workspace_core(workspaces, &id).await {
+                Ok(home) if !homes.contains(&home) => homes.push(home),
+                Ok(_) => {}
+                Err(err) => report.errors.push(format!("{id}: {err}")),
+            }
+        }
+        for home in homes {
+            for mut rollout in purge_candidates(&home, &workspace_paths, now, purge_days) {
+                if !dry_run {
+                    rollout.error = std::fs::remove_file(&rollout.path)
+                        .err()
+                        .map(|err| format!("Failed to delete rollout: {err}"));
+                }
+                if rollout.error.is_none() {
+                    report.bytes_freed += rollout.bytes;
+                }
+                report.purged.push(rollout);
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn picks_idle_threads_in_seconds_or_milliseconds() {
+        let day = |days_ago: u64| 1_800_000_000 - days_ago * SECS_PER_DAY;
+        let list = json!({
+            "data": [
+                { "id": "old", "updatedAt": day(90) },
+                { "id": "old-ms", "updatedAt": day(60) * 1000 },
+                { "id": "fresh", "updatedAt": day(5) },
+                { "id": "created-only", "createdAt": day(50) },
+                { "id": "no-timestamp" }
+            ]
+        });
+        assert_eq!(
+            idle_threads(&list, day(0), 30),
+            vec![
+                ("old".to_string(), 90),
+                ("old-ms".to_string(), 60),
+                ("created-only".to_string(), 50),
+            ]
+        );
+    }
+
+    #[test]
+    fn purges_only_old_rollouts_from_known_workspaces() {
+        let home = std::env::temp_dir().join(format!("codex-monitor-retention-{}", Uuid::new_v4()));
+        let archived = home.join(ARCHIVED_SESSIONS_DIR);
+        std::fs::create_dir_all(&archived).expect("create archive dir");
+        let meta = |cwd: &str| {
+            format!(
+                "{}\n",
+                json!({ "type": "session_meta", "payload": { "cwd": cwd } })
+            )
+        };
+        std::fs::write(archived.join("mine.jsonl"), meta("/work/app/src")).expect("write mine");
+        std::fs::write(archived.join("other.jsonl"), meta("/elsewhere")).expect("write other");
+        std::fs::write(archived.join("notes.txt"), meta("/work/app")).expect("write notes");
+
+        let paths = vec![PathBuf::from("/work/app")];
+        let later = SystemTime::now() + Duration::from_secs(10 * SECS_PER_DAY);
+        let candidates = purge_candidates(&home, &paths, later, 7);
+        assert_eq!(candidates.len(), 1);
+        assert!(candidates[0].path.ends_with("mine.jsonl"));
+        assert!(candidates[0].age_days >= 9);
+        assert!(purge_candidates(&home, &paths, SystemTime::now(), 7).is_empty());
+        let _ = std::fs::remove_dir_all(&home);
+    }
+}
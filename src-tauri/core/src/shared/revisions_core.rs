@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Prefix of every revision conflict error so clients can detect it without parsing.
+pub const REVISION_CONFLICT_PREFIX: &str = "revision conflict";
+
+/// Rejects a write whose `expected` revision no longer matches `current`.
+///
+/// Writes without an expected revision keep the historical last-write-wins behavior.
+pub fn check_revision(
+    entity: &str,
+    expected: Option<u64>,
+    current: u64,
+) -> Result<(), String> {
+    match expected {
+        Some(expected) if expected != current => Err(format!(
+            "{REVISION_CONFLICT_PREFIX}: {entity} is at revision {current}, expected {expected}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    pub merged: Value,
+    /// Dotted paths changed differently by both sides; `merged` keeps the current value there.
+    pub conflicts: Vec<String>,
+}
+
+/// Three-way merge of JSON objects: applies the changes `proposed` made to `base` on top of
+/// `current`, descending into nested objects. Arrays and scalars are replaced wholesale.
+pub fn merge_values(base: &Value, current: &Value, proposed: &Value) -> MergeResult {
+    let mut conflicts = Vec::new();
+    let merged = merge_at("", base, current, proposed, &mut conflicts);
+    MergeResult { merged, conflicts }
+}
+
+fn merge_at(
+    path: &str,
+    base: &Value,
+    current: &Value,
+    proposed: &Value,
+    conflicts: &mut Vec<String>,
+) -> Value {
+    if proposed == base {
+        return current.clone();
+    }
+    if current == base || current == proposed {
+        return proposed.clone();
+    }
+    let (Value::Object(base_map), Value::Object(current_map), Value::Object(proposed_map)) =
+        (base, current, proposed)
+    else {
+        conflicts.push(if path.is_empty() { "$".to_string() } else { path.to_string() });
+        return current.clone();
+    };
+
+    let mut keys: Vec<&String> = base_map
+        .keys()
+        .chain(current_map.keys())
+        .chain(proposed_map.keys())
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut merged = Map::new();
+    for key in keys {
+        let child_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        let base_value = base_map.get(key).unwrap_or(&Value::Null);
+        let current_value = current_map.get(key).unwrap_or(&Value::Null);
+        let proposed_value = proposed_map.get(key).unwrap_or(&Value::Null);
+        let value = merge_at(
+            &child_path,
+            base_value,
+            current_value,
+            proposed_value,
+            conflicts,
+        );
+        // A null taken from a side that lacks the key means the key was removed.
+        let removed = value.is_null()
+            && (!current_map.contains_key(key) || !proposed_map.contains_key(key));
+        if !removed {
+            merged.insert(key.clone(), value);
+        }
+    }
+    Value::Object(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn check_revision_only_fails_on_mismatch() {
+        assert!(check_revision("settings", None, 4).is_ok());
+        assert!(check_revision("settings", Some(4), 4).is_ok());
+        let error = check_revision("settings", Some(3), 4).expect_err("conflict");
+        assert!(error.starts_with(REVISION_CONFLICT_PREFIX));
+        assert!(error.contains("revision 4, expected 3"));
+    }
+
+    #[test]
+    fn merges_disjoint_changes_and_reports_overlaps() {
+        let base = json!({ "theme": "dark", "ui": { "scale": 1, "font": "a" }, "tags": [1] });
+        let current = json!({ "theme": "light", "ui": { "scale": 1, "font": "b" }, "tags": [1] });
+        let proposed = json!({ "theme": "dark", "ui": { "scale": 2, "font": "c" }, "tags": [2] });
+
+        let result = merge_values(&base, &current, &proposed);
+        assert_eq!(
+            result.merged,
+            json!({ "theme": "light", "ui": { "scale": 2, "font": "b" }, "tags": [2] })
+        );
+        assert_eq!(result.conflicts, vec!["ui.font".to_string()]);
+    }
+}
@@ -0,0 +1,656 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::rules;
+use crate::shared::codex_core::resolve_codex_home_for_workspace_core;
+use crate::types::WorkspaceEntry;
+
+const RULE_FUNCTION: &str = "prefix_rule";
+const DECISIONS: &[&str] = &["allow", "prompt", "forbidden"];
+/// Keyword arguments in the order the formatter writes them.
+const RULE_ARGS: &[&str] = &["pattern", "decision", "justification", "match", "not_match"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RulesSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesDiagnostic {
+    /// 1-based.
+    pub line: usize,
+    /// 1-based, in characters.
+    pub column: usize,
+    pub severity: RulesSeverity,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesValidation {
+    pub path: String,
+    pub exists: bool,
+    /// No errors; warnings don't count.
+    pub valid: bool,
+    pub rule_count: usize,
+    pub diagnostics: Vec<RulesDiagnostic>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesFormatResult {
+    pub path: String,
+    /// The formatted file differs from the one on disk.
+    pub changed: bool,
+    pub written: bool,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Str(String),
+    Number(String),
+    Punct(char),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RuleValue {
+    Str(String),
+    List(Vec<RuleValue>),
+    Other(String),
+}
+
+#[derive(Debug, Clone)]
+struct RuleCall {
+    line: usize,
+    args: Vec<(String, RuleValue)>,
+}
+
+struct Parsed {
+    rules: Vec<RuleCall>,
+    /// Comment lines with the 1-based line they sit on.
+    comments: Vec<(usize, String)>,
+    diagnostics: Vec<RulesDiagnostic>,
+}
+
+fn diagnostic(
+    line: usize,
+    column: usize,
+    severity: RulesSeverity,
+    message: String,
+) -> RulesDiagnostic {
+    RulesDiagnostic {
+        line,
+        column,
+        severity,
+        message,
+    }
+}
+
+fn tokenize(
+    source: &str,
+    comments: &mut Vec<(usize, String)>,
+) -> Result<Vec<Token>, RulesDiagnostic> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+    while let Some(&ch) = chars.peek() {
+        let (start_line, start_column) = (line, column);
+        let mut advance = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            let ch = chars.next();
+            if ch == Some('\n') {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            ch
+        };
+        if ch.is_whitespace() {
+            advance(&mut chars);
+        } else if ch == '#' {
+            let mut comment = String::new();
+            while chars.peek().is_some_and(|ch| *ch != '\n') {
+                comment.extend(advance(&mut chars));
+            }
+            comments.push((start_line, comment.trim_end().to_string()));
+        } else if ch == '"' || ch == '\'' {
+            advance(&mut chars);
+            let mut value = String::new();
+            loop {
+                match advance(&mut chars) {
+                    None | Some('\n') => {
+                        return Err(diagnostic(
+                            start_line,
+                            start_column,
+                            RulesSeverity::Error,
+                            "unterminated string".to_string(),
+                        ))
+                    }
+                    Some('\\') => match advance(&mut chars) {
+                        Some('n') => value.push('\n'),
+                        Some('r') => value.push('\r'),
+                        Some('t') => value.push('\t'),
+                        Some(other) => value.push(other),
+                        None => continue,
+                    },
+                    Some(end) if end == ch => break,
+                    Some(other) => value.push(other),
+                }
+            }
+            tokens.push(Token {
+                kind: TokenKind::Str(value),
+                line: start_line,
+                column: start_column,
+            });
+        } else if ch.is_ascii_alphabetic() || ch == '_' || ch.is_ascii_digit() {
+            let mut word = String::new();
+            while chars
+                .peek()
+                .is_some_and(|ch| ch.is_ascii_alphanumeric() || *ch == '_' || *ch == '.')
+            {
+                word.extend(advance(&mut chars));
+            }
+            let kind = if ch.is_ascii_digit() {
+                TokenKind::Number(word)
+            } else {
+                TokenKind::Ident(word)
+            };
+            tokens.push(Token {
+                kind,
+                line: start_line,
+                column: start_column,
+            });
+        } else if "()[]=,".contains(ch) {
+            advance(&mut chars);
+            tokens.push(Token {
+                kind: TokenKind::Punct(ch),
+                line: start_line,
+                column: start_column,
+            });
+        } else {
+            return Err(diagnostic(
+                start_line,
+                start_column,
+                RulesSeverity::Error,
+                format!("unexpected character `{ch}`"),
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A call as written: its name token, name and keyword arguments.
+type ParsedCall = (Token, String, Vec<(Token, String, RuleValue)>);
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    end: (usize, usize),
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn error_at(&self, token: Option<&Token>, message: String) -> RulesDiagnostic {
+        let (line, column) = token.map_or(self.end, |token| (token.line, token.column));
+        diagnostic(line, column, RulesSeverity::Error, message)
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Result<Token, RulesDiagnostic> {
+        match self.next() {
+            Some(token) if token.kind == TokenKind::Punct(punct) => Ok(token),
+            other => Err(self.error_at(other.as_ref(), format!("expected `{punct}`"))),
+        }
+    }
+
+    fn value(&mut self) -> Result<RuleValue, RulesDiagnostic> {
+        let token = self.next();
+        match token.as_ref().map(|token| &token.kind) {
+            Some(TokenKind::Str(value)) => Ok(RuleValue::Str(value.clone())),
+            Some(TokenKind::Number(value)) | Some(TokenKind::Ident(value)) => {
+                Ok(RuleValue::Other(value.clone()))
+            }
+            Some(TokenKind::Punct('[')) => {
+                let mut items = Vec::new();
+                loop {
+                    if self.peek().map(|token| &token.kind) == Some(&TokenKind::Punct(']')) {
+                        self.next();
+                        return Ok(RuleValue::List(items));
+                    }
+                    items.push(self.value()?);
+                    match self.next() {
+                        Some(token) if token.kind == TokenKind::Punct(',') => {}
+                        Some(token) if token.kind == TokenKind::Punct(']') => {
+                            return Ok(RuleValue::List(items))
+                        }
+                        other => {
+                            return Err(
+                                self.error_at(other.as_ref(), "expected `,` or `]`".to_string())
+                            )
+                        }
+                    }
+                }
+            }
+            _ => Err(self.error_at(token.as_ref(), "expected a string or a list".to_string())),
+        }
+    }
+
+    fn call(&mut self) -> Result<ParsedCall, RulesDiagnostic> {
+        let start = self.next();
+        let name = match start.as_ref().map(|token| &token.kind) {
+            Some(TokenKind::Ident(name)) => name.clone(),
+            _ => {
+                return Err(self.error_at(
+                    start.as_ref(),
+                    "expected a rule such as `prefix_rule(...)`".to_string(),
+                ))
+            }
+        };
+        let start = start.expect("checked above");
+        self.expect_punct('(')?;
+        let mut args = Vec::new();
+        loop {
+            if self.peek().map(|token| &token.kind) == Some(&TokenKind::Punct(')')) {
+                self.next();
+                return Ok((start, name, args));
+            }
+            let key = self.next();
+            let key_name = match key.as_ref().map(|token| &token.kind) {
+                Some(TokenKind::Ident(key)) => key.clone(),
+                _ => {
+                    return Err(
+                        self.error_at(key.as_ref(), "expected a keyword argument".to_string())
+                    )
+                }
+            };
+            self.expect_punct('=')?;
+            let value = self.value()?;
+            args.push((key.expect("checked above"), key_name, value));
+            match self.next() {
+                Some(token) if token.kind == TokenKind::Punct(',') => {}
+                Some(token) if token.kind == TokenKind::Punct(')') => {
+                    return Ok((start, name, args))
+                }
+                other => {
+                    return Err(self.error_at(other.as_ref(), "expected `,` or `)`".to_string()))
+                }
+            }
+        }
+    }
+}
+
+fn is_string_list(value: &RuleValue) -> bool {
+    matches!(value, RuleValue::List(items) if items.iter().all(|item| matches!(item, RuleValue::Str(_))))
+}
+
+/// Semantic checks for one `prefix_rule(...)` call.
+fn check_rule(
+    start: &Token,
+    name: &str,
+    args: &[(Token, String, RuleValue)],
+    diagnostics: &mut Vec<RulesDiagnostic>,
+) -> bool {
+    let error = |token: &Token, message: String| {
+        diagnostic(token.line, token.column, RulesSeverity::Error, message)
+    };
+    if name != RULE_FUNCTION {
+        diagnostics.push(error(
+            start,
+            format!("unknown rule `{name}`, expected `{RULE_FUNCTION}`"),
+        ));
+        return false;
+    }
+    let before = diagnostics.len();
+    let mut seen = HashSet::new();
+    for (token, key, value) in args {
+        if !seen.insert(key.as_str()) {
+            diagnostics.push(error(token, format!("`{key}` is given more than once")));
+            continue;
+        }
+        match key.as_str() {
+            "pattern" => {
+                let RuleValue::List(items) = value else {
+                    diagnostics.push(error(token, "`pattern` must be a list".to_string()));
+                    continue;
+                };
+                if items.is_empty() {
+                    diagnostics.push(error(token, "`pattern` must not be empty".to_string()));
+                }
+                let valid_items = items.iter().all(|item| match item {
+                    RuleValue::Str(value) => !value.is_empty(),
+                    RuleValue::List(alternatives) => {
+                        !alternatives.is_empty() && is_string_list(item)
+                    }
+                    RuleValue::Other(_) => false,
+                });
+                if !valid_items {
+                    diagnostics.push(error(
+                        token,
+                        "`pattern` items must be non-empty strings or lists of strings".to_string(),
+                    ));
+                }
+            }
+            "decision" => match value {
+                RuleValue::Str(decision) if DECISIONS.contains(&decision.as_str()) => {}
+                _ => diagnostics.push(error(
+                    token,
+                    format!("`decision` must be one of {}", DECISIONS.join(", ")),
+                )),
+            },
+            "justification" => {
+                if !matches!(value, RuleValue::Str(_)) {
+                    diagnostics.push(error(token, "`justification` must be a string".to_string()));
+                }
+            }
+            "match" | "not_match" => {
+                let valid = match value {
+                    RuleValue::List(examples) => examples.iter().all(|example| {
+                        matches!(example, RuleValue::Str(_)) || is_string_list(example)
+                    }),
+                    _ => false,
+                };
+                if !valid {
+                    diagnostics.push(error(
+                        token,
+                        format!("`{key}` must be a list of command strings or token lists"),
+                    ));
+                }
+            }
+            _ => diagnostics.push(error(token, format!("unknown argument `{key}`"))),
+        }
+    }
+    if !seen.contains("pattern") {
+        diagnostics.push(error(start, "`pattern` is required".to_string()));
+    }
+    diagnostics.len() == before
+}
+
+fn parse(source: &str) -> Parsed {
+    let mut comments = Vec::new();
+    let mut diagnostics = Vec::new();
+    let tokens = match tokenize(source, &mut comments) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            return Parsed {
+                rules: Vec::new(),
+                comments,
+                diagnostics: vec![error],
+            }
+        }
+    };
+    let end = source.lines().count().max(1);
+    let end_column = source
+        .lines()
+        .last()
+        .map_or(1, |line| line.chars().count() + 1);
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        end: (end, end_column),
+    };
+    let mut rules = Vec::new();
+    let mut seen_patterns: HashMap<Vec<RuleValue>, usize> = HashMap::new();
+    while parser.peek().is_some() {
+        match parser.call() {
+            Ok((start, name, args)) => {
+                if !check_rule(&start, &name, &args, &mut diagnostics) {
+                    continue;
+                }
+                let pattern = args
+                    .iter()
+                    .find(|(_, key, _)| key == "pattern")
+                    .map(|(_, _, value)| value.clone());
+                if let Some(RuleValue::List(pattern)) = pattern {
+                    match seen_patterns.get(&pattern) {
+                        Some(first) => diagnostics.push(diagnostic(
+                            start.line,
+                            start.column,
+                            RulesSeverity::Warning,
+                            format!("same pattern as the rule on line {first}"),
+                        )),
+                        None => {
+                            seen_patterns.insert(pattern, start.line);
+                        }
+                    }
+                }
+                rules.push(RuleCall {
+                    line: start.line,
+                    args: args
+                        .into_iter()
+                        .map(|(_, key, value)| (key, value))
+                        .collect(),
+                });
+            }
+            Err(error) => {
+                // Everything after a syntax error is unreliable.
+                diagnostics.push(error);
+                break;
+            }
+        }
+    }
+    Parsed {
+        rules,
+        comments,
+        diagnostics,
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+fn format_value(value: &RuleValue) -> String {
+    match value {
+        RuleValue::Str(value) => format!("\"{}\"", escape(value)),
+        RuleValue::List(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(format_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        RuleValue::Other(value) => value.clone(),
+    }
+}
+
+fn format_rule(rule: &RuleCall) -> String {
+    let mut args = rule.args.iter().collect::<Vec<_>>();
+    args.sort_by_key(|(key, _)| RULE_ARGS.iter().position(|known| known == key));
+    let mut out = format!("{RULE_FUNCTION}(\n");
+    for (key, value) in args {
+        out.push_str(&format!("    {key} = {},\n", format_value(value)));
+    }
+    out.push_str(")\n");
+    out
+}
+
+/// Checks a rules file's syntax and arguments.
+pub fn lint_rules(source: &str) -> (usize, Vec<RulesDiagnostic>) {
+    let parsed = parse(source);
+    (parsed.rules.len(), parsed.diagnostics)
+}
+
+/// Rewrites the file in the layout `append_prefix_rule` uses: one blank line between
+/// rules, one argument per line in a fixed order, double-quoted strings. Comments are
+/// kept above the rule they preceded. Refuses files with errors.
+pub fn format_rules(source: &str) -> Result<String, Vec<RulesDiagnostic>> {
+    let parsed = parse(source);
+    let errors = parsed
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == RulesSeverity::Error)
+        .cloned()
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let mut blocks = Vec::new();
+    let mut comments = parsed.comments.into_iter().peekable();
+    for rule in &parsed.rules {
+        let mut block = String::new();
+        while let Some((_, comment)) = comments.next_if(|(line, _)| *line <= rule.line) {
+            block.push_str(&comment);
+            block.push('\n');
+        }
+        block.push_str(&format_rule(rule));
+        blocks.push(block);
+    }
+    let trailing = comments
+        .map(|(_, comment)| format!("{comment}\n"))
+        .collect::<String>();
+    if !trailing.is_empty() {
+        blocks.push(trailing);
+    }
+    Ok(blocks.join("\n"))
+}
+
+async fn resolve_rules_path(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<PathBuf, String> {
+    let codex_home = resolve_codex_home_for_workspace_core(workspaces, workspace_id).await?;
+    Ok(rules::default_rules_path(&codex_home))
+}
+
+pub async fn validate_rules_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<RulesValidation, String> {
+    let path = resolve_rules_path(workspaces, workspace_id).await?;
+    let (exists, source) = match std::fs::read_to_string(&path) {
+        Ok(source) => (true, source),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => (false, String::new()),
+        Err(err) => return Err(format!("Failed to read {}: {err}", path.display())),
+    };
+    let (rule_count, diagnostics) = lint_rules(&source);
+    Ok(RulesValidation {
+        path: path.to_string_lossy().to_string(),
+        exists,
+        valid: diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity != RulesSeverity::Error),
+        rule_count,
+        diagnostics,
+    })
+}
+
+/// Formats the workspace's rules file; only writes it when `write` is set.
+pub async fn format_rules_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    write: bool,
+) -> Result<RulesFormatResult, String> {
+    let path = resolve_rules_path(workspaces, workspace_id).await?;
+    let source = std::fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    let contents = format_rules(&source).map_err(|errors| {
+        let first = &errors[0];
+        format!(
+            "{} has {} error(s); first at line {}: {}",
+            path.display(),
+            errors.len(),
+            first.line,
+            first.message
+        )
+    })?;
+    let changed = contents != source;
+    let written = write && changed;
+    if written {
+        rules::replace_rules_file(&path, &source, &contents)?;
+    }
+    Ok(RulesFormatResult {
+        path: path.to_string_lossy().to_string(),
+        changed,
+        written,
+        contents,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let source =
+            "prefix_rule(\n    pattern = [\"git\", \"push\"],\n    decision = \"maybe\",\n)\n\n\
+                      prefix_rule(pattern = [\"ls\"], colour = \"red\")\n\
+                      prefix_rule(pattern = [\"git\", \"push\"])\n\
+                      prefix_rule(pattern = [\"rm\"]\n";
+        let (count, diagnostics) = lint_rules(source);
+        assert_eq!(count, 1);
+        let summary = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.line,
+                    diagnostic.severity,
+                    diagnostic.message.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    3,
+                    RulesSeverity::Error,
+                    "`decision` must be one of allow, prompt, forbidden"
+                ),
+                (6, RulesSeverity::Error, "unknown argument `colour`"),
+                (8, RulesSeverity::Error, "expected `,` or `)`"),
+            ]
+        );
+        let (_, unterminated) = lint_rules("prefix_rule(pattern = [\"ls])\n");
+        assert_eq!(unterminated[0].message, "unterminated string");
+        assert_eq!((unterminated[0].line, unterminated[0].column), (1, 24));
+    }
+
+    #[test]
+    fn formats_rules_and_keeps_comments() {
+        let source =
+            "# team rules\nprefix_rule(decision='prompt', pattern=['git',['push','pull']],)\n\
+                      prefix_rule(pattern = [\"ls\"]) # listing\n";
+        let formatted = format_rules(source).expect("format");
+        assert_eq!(
+            formatted,
+            "# team rules\nprefix_rule(\n    pattern = [\"git\", [\"push\", \"pull\"]],\n    decision = \"prompt\",\n)\n\n\
+             # listing\nprefix_rule(\n    pattern = [\"ls\"],\n)\n"
+        );
+        assert_eq!(format_rules(&formatted), Ok(formatted.clone()));
+        assert!(format_rules("prefix_rule(").is_err());
+        let (_, duplicate) = lint_rules(&format!("{formatted}{formatted}"));
+        assert!(duplicate
+            .iter()
+            .all(|diagnostic| diagnostic.severity == RulesSeverity::Warning));
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::rules;
+use crate::shared::settings_core::update_app_settings_core;
+use crate::types::{AppSettings, ApprovalPolicyRule, SandboxPreset};
+
+const SAFETY_POLICY_FORMAT_VERSION: u32 = 1;
+
+/// The settings that decide what agents may do, as one file a team can hand out. Sections
+/// left out of a bundle are not touched on import.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetyPolicyBundle {
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at: Option<String>,
+    #[serde(default)]
+    pub approval_policy_rules: Option<Vec<ApprovalPolicyRule>>,
+    #[serde(default)]
+    pub sandbox_presets: Option<Vec<SandboxPreset>>,
+    #[serde(default)]
+    pub protected_path_globs: Option<Vec<String>>,
+    /// Contents of `CODEX_HOME/rules/default.rules`.
+    #[serde(default)]
+    pub rules: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SafetyPolicyImportMode {
+    /// Rules and presets replace local ones with the same id and are added otherwise;
+    /// globs and rules-file text are added when missing.
+    #[default]
+    Merge,
+    /// Each section in the bundle replaces the local one.
+    Replace,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetyPolicyImportResult {
+    pub approval_policy_rules: usize,
+    pub sandbox_presets: usize,
+    pub protected_path_globs: usize,
+    pub rules_file_updated: bool,
+    pub revision: u64,
+}
+
+pub async fn export_safety_policy_core(
+    app_settings: &Mutex<AppSettings>,
+    rules_path: Option<&Path>,
+) -> SafetyPolicyBundle {
+    let settings = app_settings.lock().await.clone();
+    SafetyPolicyBundle {
+        version: SAFETY_POLICY_FORMAT_VERSION,
+        exported_at: Some(chrono::Local::now().to_rfc3339()),
+        approval_policy_rules: Some(settings.approval_policy_rules),
+        sandbox_presets: Some(settings.sandbox_presets),
+        protected_path_globs: Some(settings.protected_path_globs),
+        rules: rules_path.and_then(|path| std::fs::read_to_string(path).ok()),
+    }
+}
+
+fn ensure_unique_ids<'a>(
+    section: &str,
+    ids: impl Iterator<Item = &'a str>,
+) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for id in ids {
+        if id.trim().is_empty() {
+            return Err(format!("{section} entries need a non-empty id"));
+        }
+        if !seen.insert(id) {
+            return Err(format!("{section} has more than one entry with id `{id}`"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_bundle(bundle: &SafetyPolicyBundle) -> Result<(), String> {
+    if bundle.version > SAFETY_POLICY_FORMAT_VERSION {
+        return Err(format!(
+            "Safety policy format version {} is newer than supported version \
+             {SAFETY_POLICY_FORMAT_VERSION}.",
+            bundle.version
+        ));
+    }
+    if let Some(rules) = &bundle.approval_policy_rules {
+        ensure_unique_ids("approvalPolicyRules", rules.iter().map(|rule| rule.id.as_str()))?;
+    }
+    if let Some(presets) = &bundle.sandbox_presets {
+        ensure_unique_ids("sandboxPresets", presets.iter().map(|preset| preset.id.as_str()))?;
+    }
+    if let Some(globs) = &bundle.protected_path_globs {
+        if globs.iter().any(|glob| glob.trim().is_empty()) {
+            return Err("protectedPathGlobs entries must not be empty".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn upsert_by_id<T: Clone>(current: &mut Vec<T>, incoming: &[T], id: impl Fn(&T) -> &str) {
+    for item in incoming {
+        match current.iter_mut().find(|existing| id(existing) == id(item)) {
+            Some(existing) => *existing = item.clone(),
+            None => current.push(item.clone()),
+        }
+    }
+}
+
+/// Applies the bundle's settings sections to `settings`.
+pub fn merge_safety_policy(
+    settings: &mut AppSettings,
+    bundle: &SafetyPolicyBundle,
+    mode: SafetyPolicyImportMode,
+) {
+    let replace = mode == SafetyPolicyImportMode::Replace;
+    if let Some(rules) = &bundle.approval_policy_rules {
+        if replace {
+            settings.approval_policy_rules = rules.clone();
+        } else {
+            upsert_by_id(&mut settings.approval_policy_rules, rules, |rule| &rule.id);
+        }
+    }
+    if let Some(presets) = &bundle.sandbox_presets {
+        if replace {
+            settings.sandbox_presets = presets.clone();
+        } else {
+            upsert_by_id(&mut settings.sandbox_presets, presets, |preset| &preset.id);
+        }
+    }
+    if let Some(globs) = &bundle.protected_path_globs {
+        if replace {
+            settings.protected_path_globs.clear();
+        }
+        for glob in globs {
+            if !settings.protected_path_globs.contains(glob) {
+                settings.protected_path_globs.push(glob.clone());
+            }
+        }
+    }
+}
+
+/// The rules file after importing `incoming`, or `None` when it doesn't change.
+fn merged_rules_file(
+    current: &str,
+    incoming: &str,
+    mode: SafetyPolicyImportMode,
+) -> Option<String> {
+    let incoming_trimmed = incoming.trim();
+    let next = match mode {
+        SafetyPolicyImportMode::Replace => incoming.to_string(),
+        SafetyPolicyImportMode::Merge if incoming_trimmed.is_empty() => return None,
+        SafetyPolicyImportMode::Merge if current.contains(incoming_trimmed) => return None,
+        SafetyPolicyImportMode::Merge if current.trim().is_empty() => incoming.to_string(),
+        SafetyPolicyImportMode::Merge => {
+            format!("{}\n\n{incoming_trimmed}\n", current.trim_end())
+        }
+    };
+    (next != current).then_some(next)
+}
+
+fn write_rules_file(
+    path: &Path,
+    incoming: &str,
+    mode: SafetyPolicyImportMode,
+) -> Result<bool, String> {
+    let current = std::fs::read_to_string(path).unwrap_or_default();
+    let Some(next) = merged_rules_file(&current, incoming, mode) else {
+        return Ok(false);
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    rules::replace_rules_file(path, &current, &next)?;
+    Ok(true)
+}
+
+/// Validates the whole bundle before saving anything, then writes the settings and, when
+/// the bundle has one and `rules_path` is known, the rules file.
+pub async fn import_safety_policy_core(
+    bundle: SafetyPolicyBundle,
+    mode: SafetyPolicyImportMode,
+    app_settings: &Mutex<AppSettings>,
+    settings_path: &PathBuf,
+    rules_path: Option<&Path>,
+) -> Result<SafetyPolicyImportResult, String> {
+    validate_bundle(&bundle)?;
+    let mut settings = app_settings.lock().await.clone();
+    let revision = settings.revision;
+    merge_safety_policy(&mut settings, &bundle, mode);
+    let updated =
+        update_app_settings_core(settings, Some(revision), app_settings, settings_path).await?;
+    let rules_file_updated = match (bundle.rules.as_deref(), rules_path) {
+        (Some(incoming), Some(path)) => write_rules_file(path, incoming, mode)?,
+        _ => false,
+    };
+    Ok(SafetyPolicyImportResult {
+        approval_policy_rules: updated.approval_policy_rules.len(),
+        sandbox_presets: updated.sandbox_presets.len(),
+        protected_path_globs: updated.protected_path_globs.len(),
+        rules_file_updated,
+        revision: updated.revision,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ApprovalPolicyDecision;
+
+    fn rule(id: &str, decision: ApprovalPolicyDecision) -> ApprovalPolicyRule {
+        serde_json::from_value(serde_json::json!({ "id": id, "decision": decision }))
+            .expect("rule")
+    }
+
+    #[test]
+    fn merge_upserts_by_id_and_replace_overwrites() {
+        let settings = AppSettings {
+            approval_policy_rules: vec![
+                rule("a", ApprovalPolicyDecision::Allow),
+                rule("b", ApprovalPolicyDecision::Allow),
+            ],
+            protected_path_globs: vec!["**/.env".to_string()],
+            ..AppSettings::default()
+        };
+        let bundle = SafetyPolicyBundle {
+            version: 1,
+            approval_policy_rules: Some(vec![
+                rule("b", ApprovalPolicyDecision::Deny),
+                rule("c", ApprovalPolicyDecision::Deny),
+            ]),
+            protected_path_globs: Some(vec!["**/.env".to_string(), "**/*.pem".to_string()]),
+            ..SafetyPolicyBundle::default()
+        };
+
+        let mut merged = settings.clone();
+        merge_safety_policy(&mut merged, &bundle, SafetyPolicyImportMode::Merge);
+        let ids: Vec<_> = merged
+            .approval_policy_rules
+            .iter()
+            .map(|rule| (rule.id.as_str(), rule.decision))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("a", ApprovalPolicyDecision::Allow),
+                ("b", ApprovalPolicyDecision::Deny),
+                ("c", ApprovalPolicyDecision::Deny),
+            ]
+        );
+        assert_eq!(merged.protected_path_globs, vec!["**/.env", "**/*.pem"]);
+
+        let mut replaced = settings.clone();
+        replaced.sandbox_presets = vec![serde_json::from_value(
+            serde_json::json!({ "id": "ci", "name": "CI" }),
+        )
+        .expect("preset")];
+        merge_safety_policy(&mut replaced, &bundle, SafetyPolicyImportMode::Replace);
+        assert_eq!(replaced.approval_policy_rules.len(), 2);
+        assert_eq!(replaced.sandbox_presets.len(), 1, "sections left out are kept");
+    }
+
+    #[test]
+    fn rejects_newer_formats_and_duplicate_ids() {
+        let newer = SafetyPolicyBundle {
+            version: SAFETY_POLICY_FORMAT_VERSION + 1,
+            ..SafetyPolicyBundle::default()
+        };
+        assert!(validate_bundle(&newer).is_err());
+        let duplicates = SafetyPolicyBundle {
+            version: 1,
+            approval_policy_rules: Some(vec![
+                rule("a", ApprovalPolicyDecision::Allow),
+                rule("a", ApprovalPolicyDecision::Deny),
+            ]),
+            ..SafetyPolicyBundle::default()
+        };
+        let error = validate_bundle(&duplicates).expect_err("duplicate ids");
+        assert!(error.contains("`a`"));
+    }
+
+    #[test]
+    fn merges_rules_file_text_once() {
+        let incoming = "prefix_rule(\n    pattern = [\"ls\"],\n    decision = \"allow\",\n)\n";
+        let merge = SafetyPolicyImportMode::Merge;
+        assert_eq!(merged_rules_file("", incoming, merge).as_deref(), Some(incoming));
+        let existing = "prefix_rule(\n    pattern = [\"pwd\"],\n    decision = \"allow\",\n)\n";
+        let next = merged_rules_file(existing, incoming, merge).expect("appended");
+        assert!(next.starts_with(existing));
+        assert!(next.ends_with(incoming));
+        assert_eq!(merged_rules_file(&next, incoming, merge), None);
+        assert_eq!(
+            merged_rules_file(existing, incoming, SafetyPolicyImportMode::Replace).as_deref(),
+            Some(incoming)
+        );
+    }
+}
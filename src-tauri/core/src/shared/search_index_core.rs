@@ -0,0 +1,853 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tantivy::collector::TopDocs;
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RegexQuery};
+use tantivy::schema::{Field, Schema, Value, STORED, STRING, TEXT};
+use tantivy::{doc, Index, IndexReader, TantivyDocument};
+use tokio::sync::Mutex;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::types::WorkspaceEntry;
+
+const MAX_INDEX_FILES: usize = 100_000;
+const MAX_INDEX_FILE_BYTES: u64 = 1_000_000;
+const INDEX_WRITER_HEAP_BYTES: usize = 50_000_000;
+const INDEX_PROGRESS_EVERY: usize = 250;
+/// No file watcher exists yet, so indexes older than this are rebuilt in the background
+/// while the previous index keeps serving queries.
+const INDEX_REFRESH_AFTER: Duration = Duration::from_secs(60);
+const DEFAULT_RESULT_LIMIT: usize = 200;
+const MAX_RESULT_LIMIT: usize = 1_000;
+const MAX_MATCHES_PER_FILE: usize = 20;
+const MAX_PREVIEW_CHARS: usize = 300;
+
+/// Per-workspace index slots shared with the background build tasks.
+pub type SearchIndexes = Arc<Mutex<HashMap<String, SearchIndexSlot>>>;
+
+#[derive(Default)]
+pub struct SearchIndexSlot {
+    built: Option<Arc<BuiltIndex>>,
+    building: bool,
+    last_error: Option<String>,
+}
+
+pub struct BuiltIndex {
+    index: Index,
+    reader: IndexReader,
+    path_field: Field,
+    content_field: Field,
+    file_count: usize,
+    built_at: Instant,
+    built_at_ms: u64,
+}
+
+/// How the query is matched against lines. By default every word of the query must occur
+/// in a file, in any case; `regex` matches the whole query as one pattern instead.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+    pub match_case: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+}
+
+/// One indexed search: the query text, how many results to return (clamped to
+/// `MAX_RESULT_LIMIT`), an optional path prefix to restrict matches to and the match options.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchQuery<'a> {
+    pub query: &'a str,
+    pub limit: Option<usize>,
+    pub path_prefix: Option<&'a str>,
+    pub options: SearchOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexStatus {
+    Building,
+    Ready,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedSearchMatch {
+    pub path: String,
+    pub line: usize,
+    pub preview: String,
+    /// `[start, end)` character offsets into `preview` to highlight.
+    pub ranges: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedSearchResponse {
+    pub status: IndexStatus,
+    pub refreshing: bool,
+    pub indexed_files: usize,
+    pub built_at: Option<u64>,
+    pub elapsed_ms: u64,
+    pub results: Vec<IndexedSearchMatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceSearchMatch {
+    pub workspace_id: String,
+    pub workspace_name: String,
+    pub path: String,
+    pub line: usize,
+    pub preview: String,
+    pub ranges: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceSearchError {
+    pub workspace_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiWorkspaceSearchResponse {
+    pub results: Vec<WorkspaceSearchMatch>,
+    /// Workspaces whose index is still being built and therefore contributed no results.
+    pub building: Vec<String>,
+    pub errors: Vec<WorkspaceSearchError>,
+    pub elapsed_ms: u64,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn read_indexable_text(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_INDEX_FILE_BYTES {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Builds an in-memory index over the text files of `root`.
+pub fn build_index(root: &Path, files: &[String]) -> Result<BuiltIndex, String> {
+    build_index_with_progress(root, files, &|_, _| {})
+}
+
+/// `build_index` that reports `(files visited, total files)` every few hundred files.
+fn build_index_with_progress(
+    root: &Path,
+    files: &[String],
+    on_progress: &dyn Fn(usize, usize),
+) -> Result<BuiltIndex, String> {
+    let mut schema_builder = Schema::builder();
+    let path_field = schema_builder.add_text_field("path", STRING | STORED);
+    let content_field = schema_builder.add_text_field("content", TEXT);
+    let index = Index::create_in_ram(schema_builder.build());
+    let mut writer = index
+        .writer_with_num_threads::<TantivyDocument>(1, INDEX_WRITER_HEAP_BYTES)
+        .map_err(|err| format!("Failed to create search index writer: {err}"))?;
+    let mut file_count = 0;
+    for (visited, file) in files.iter().enumerate() {
+        if visited % INDEX_PROGRESS_EVERY == 0 {
+            on_progress(visited, files.len());
+        }
+        let Some(content) = read_indexable_text(&root.join(file)) else {
+            continue;
+        };
+        writer
+            .add_document(doc!(path_field => file.as_str(), content_field => content))
+            .map_err(|err| format!("Failed to index {file}: {err}"))?;
+        file_count += 1;
+    }
+    writer
+        .commit()
+        .map_err(|err| format!("Failed to commit search index: {err}"))?;
+    let reader = index
+        .reader()
+        .map_err(|err| format!("Failed to open search index: {err}"))?;
+    Ok(BuiltIndex {
+        index,
+        reader,
+        path_field,
+        content_field,
+        file_count,
+        built_at: Instant::now(),
+        built_at_ms: now_millis(),
+    })
+}
+
+/// Alphanumeric terms, split the way the default tokenizer splits the query. Lowercased
+/// unless the search matches case.
+fn query_terms(query: &str, match_case: bool) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for token in query.split(|ch: char| !ch.is_alphanumeric()) {
+        if token.is_empty() || matches!(token, "AND" | "OR" | "NOT") {
+            continue;
+        }
+        let term = if match_case {
+            token.to_string()
+        } else {
+            token.to_lowercase()
+        };
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms
+}
+
+/// The pattern that finds matches within a line, or `None` for an empty query.
+fn line_matcher(query: &str, options: SearchOptions) -> Result<Option<Regex>, String> {
+    let pattern = if options.regex {
+        if query.trim().is_empty() {
+            return Ok(None);
+        }
+        // Check the pattern as typed so the error doesn't quote the wrapping below.
+        Regex::new(query).map_err(|err| format!("Invalid regex: {err}"))?;
+        query.to_string()
+    } else {
+        let terms = query_terms(query, options.match_case);
+        if terms.is_empty() {
+            return Ok(None);
+        }
+        terms
+            .iter()
+            .map(|term| regex::escape(term))
+            .collect::<Vec<_>>()
+            .join("|")
+    };
+    let pattern = if options.whole_word {
+        format!(r"\b(?:{pattern})\b")
+    } else {
+        pattern
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.match_case)
+        .build()
+        .map(Some)
+        .map_err(|err| format!("Invalid regex: {err}"))
+}
+
+fn highlight_ranges(line: &str, matcher: &Regex) -> Vec<(usize, usize)> {
+    matcher
+        .find_iter(line)
+        .filter(|found| !found.is_empty())
+        .map(|found| {
+            let start = line[..found.start()].chars().count();
+            (start, start + found.as_str().chars().count())
+        })
+        .collect()
+}
+
+fn preview_line(line: &str, ranges: Vec<(usize, usize)>) -> (String, Vec<(usize, usize)>) {
+    let preview: String = line.chars().take(MAX_PREVIEW_CHARS).collect();
+    let ranges = ranges
+        .into_iter()
+        .filter(|(start, _)| *start < MAX_PREVIEW_CHARS)
+        .map(|(start, end)| (start, end.min(MAX_PREVIEW_CHARS)))
+        .collect();
+    (preview, ranges)
+}
+
+/// Normalizes a workspace-relative directory (e.g. a package path) into `dir/`, or `None`
+/// when it names the workspace root.
+fn normalize_path_prefix(prefix: Option<&str>) -> Option<String> {
+    let trimmed = prefix?
+        .trim()
+        .trim_start_matches("./")
+        .trim_matches('/')
+        .replace('\\', "/");
+    if trimmed.is_empty() || trimmed == "." {
+        return None;
+    }
+    Some(format!("{trimmed}/"))
+}
+
+fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if "\\.+*?()|[]{}^$#&-~\"<>@".contains(ch) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Runs `query` against a built index and returns line-level matches with highlight ranges.
+/// `path_prefix` restricts results to files below that workspace-relative directory.
+///
+/// The index only narrows down candidate files; lines are matched on disk, so `regex`
+/// searches, which the index can't narrow, read every indexed file until `limit` is hit.
+pub fn search_built_index(
+    root: &Path,
+    built: &BuiltIndex,
+    query: &str,
+    limit: usize,
+    path_prefix: Option<&str>,
+    options: SearchOptions,
+) -> Result<Vec<IndexedSearchMatch>, String> {
+    let Some(matcher) = line_matcher(query, options)? else {
+        return Ok(Vec::new());
+    };
+    let mut parsed: Box<dyn Query> = if options.regex {
+        Box::new(AllQuery)
+    } else {
+        let mut parser = QueryParser::for_index(&built.index, vec![built.content_field]);
+        parser.set_conjunction_by_default();
+        parser.parse_query_lenient(query).0
+    };
+    if let Some(prefix) = normalize_path_prefix(path_prefix) {
+        let pattern = format!("{}.*", escape_regex(&prefix));
+        let scope = RegexQuery::from_pattern(&pattern, built.path_field)
+            .map_err(|err| format!("Invalid path prefix: {err}"))?;
+        let clauses: Vec<(Occur, Box<dyn Query>)> =
+            vec![(Occur::Must, parsed), (Occur::Must, Box::new(scope))];
+        parsed = Box::new(BooleanQuery::new(clauses));
+    }
+    // Candidates can come up empty once case and word boundaries are checked.
+    let candidates = if options == SearchOptions::default() {
+        limit
+    } else {
+        built.file_count.max(1)
+    };
+    let searcher = built.reader.searcher();
+    let top_docs = searcher
+        .search(&parsed, &TopDocs::with_limit(candidates))
+        .map_err(|err| format!("Search failed: {err}"))?;
+
+    let mut results = Vec::new();
+    for (_score, address) in top_docs {
+        let document: TantivyDocument = searcher
+            .doc(address)
+            .map_err(|err| format!("Failed to load search result: {err}"))?;
+        let Some(path) = document
+            .get_first(built.path_field)
+            .and_then(|value| value.as_str())
+        else {
+            continue;
+        };
+        // Lines come from disk so previews reflect edits made since the last build.
+        let Some(content) = read_indexable_text(&root.join(path)) else {
+            continue;
+        };
+        let mut file_matches = 0;
+        for (index, line) in content.lines().enumerate() {
+            let ranges = highlight_ranges(line, &matcher);
+            if ranges.is_empty() {
+                continue;
+            }
+            let (preview, ranges) = preview_line(line, ranges);
+            results.push(IndexedSearchMatch {
+                path: path.to_string(),
+                line: index + 1,
+                preview,
+                ranges,
+            });
+            file_matches += 1;
+            if file_matches >= MAX_MATCHES_PER_FILE || results.len() >= limit {
+                break;
+            }
+        }
+        if results.len() >= limit {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+fn spawn_index_build(
+    indexes: SearchIndexes,
+    workspace_id: String,
+    root: PathBuf,
+    list_files: fn(&PathBuf, usize) -> Vec<String>,
+) {
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            let files = list_files(&root, MAX_INDEX_FILES);
+            build_index(&root, &files)
+        })
+        .await
+        .map_err(|err| format!("Search index build failed: {err}"))
+        .and_then(|result| result);
+        let mut indexes = indexes.lock().await;
+        let slot = indexes.entry(workspace_id.clone()).or_default();
+        slot.building = false;
+        match result {
+            Ok(built) => {
+                slot.built = Some(Arc::new(built));
+                slot.last_error = None;
+            }
+            Err(error) => {
+                tracing::warn!(workspace_id, "search index build failed: {error}");
+                slot.last_error = Some(error);
+            }
+        }
+    });
+}
+
+/// Returns the current index for a workspace (if any) and whether a build is in flight,
+/// kicking off a background build when the index is missing or stale.
+async fn acquire_index(
+    indexes: &SearchIndexes,
+    workspace_id: &str,
+    root: &Path,
+    list_files: fn(&PathBuf, usize) -> Vec<String>,
+) -> Result<(Option<Arc<BuiltIndex>>, bool), String> {
+    let mut slots = indexes.lock().await;
+    let slot = slots.entry(workspace_id.to_string()).or_default();
+    if let Some(error) = slot.last_error.take() {
+        if slot.built.is_none() {
+            return Err(error);
+        }
+    }
+    let stale = slot
+        .built
+        .as_ref()
+        .is_none_or(|built| built.built_at.elapsed() >= INDEX_REFRESH_AFTER);
+    if stale && !slot.building {
+        slot.building = true;
+        spawn_index_build(
+            Arc::clone(indexes),
+            workspace_id.to_string(),
+            root.to_path_buf(),
+            list_files,
+        );
+    }
+    Ok((slot.built.clone(), slot.building))
+}
+
+/// Rebuilds a workspace's index in the foreground, for callers that track progress
+/// themselves; queries keep using the previous index until the new one is swapped in.
+pub async fn rebuild_index_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    indexes: &SearchIndexes,
+    workspace_id: &str,
+    list_files: fn(&PathBuf, usize) -> Vec<String>,
+    on_progress: impl Fn(usize, usize) + Send + 'static,
+) -> Result<usize, String> {
+    let root = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        PathBuf::from(&entry.path)
+    };
+    {
+        let mut slots = indexes.lock().await;
+        let slot = slots.entry(workspace_id.to_string()).or_default();
+        if slot.building {
+            return Err("Search index is already being built".to_string());
+        }
+        slot.building = true;
+    }
+    // Detached so the slot is released even if the caller is dropped mid-build.
+    let indexes = Arc::clone(indexes);
+    let workspace_id = workspace_id.to_string();
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            let files = list_files(&root, MAX_INDEX_FILES);
+            build_index_with_progress(&root, &files, &on_progress)
+        })
+        .await
+        .map_err(|err| format!("Search index build failed: {err}"))
+        .and_then(|result| result);
+        let mut slots = indexes.lock().await;
+        let slot = slots.entry(workspace_id).or_default();
+        slot.building = false;
+        let built = result?;
+        let file_count = built.file_count;
+        slot.built = Some(Arc::new(built));
+        slot.last_error = None;
+        Ok(file_count)
+    })
+    .await
+    .map_err(|err| format!("Search index build failed: {err}"))?
+}
+
+/// Searches the workspace index, building it in the background on first use.
+///
+/// Until the first build finishes the response has `status: "building"` and no results; a stale
+/// index keeps answering (`refreshing: true`) while its replacement is built.
+pub async fn indexed_search_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    indexes: &SearchIndexes,
+    workspace_id: &str,
+    search: SearchQuery<'_>,
+    list_files: fn(&PathBuf, usize) -> Vec<String>,
+) -> Result<IndexedSearchResponse, String> {
+    let started = Instant::now();
+    let SearchQuery {
+        query,
+        limit,
+        path_prefix,
+        options,
+    } = search;
+    let root = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        PathBuf::from(&entry.path)
+    };
+    let limit = limit
+        .unwrap_or(DEFAULT_RESULT_LIMIT)
+        .clamp(1, MAX_RESULT_LIMIT);
+
+    let (built, refreshing) = acquire_index(indexes, workspace_id, &root, list_files).await?;
+
+    let Some(built) = built else {
+        return Ok(IndexedSearchResponse {
+            status: IndexStatus::Building,
+            refreshing,
+            indexed_files: 0,
+            built_at: None,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            results: Vec::new(),
+        });
+    };
+    let query = query.to_string();
+    let path_prefix = path_prefix.map(str::to_string);
+    let search_root = root.clone();
+    let search_index = Arc::clone(&built);
+    let results = tokio::task::spawn_blocking(move || {
+        search_built_index(
+            &search_root,
+            &search_index,
+            &query,
+            limit,
+            path_prefix.as_deref(),
+            options,
+        )
+    })
+    .await
+    .map_err(|err| format!("Search failed: {err}"))??;
+    Ok(IndexedSearchResponse {
+        status: IndexStatus::Ready,
+        refreshing,
+        indexed_files: built.file_count,
+        built_at: Some(built.built_at_ms),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        results,
+    })
+}
+
+/// Runs the indexed search in every workspace (or the given subset) concurrently and merges
+/// the results round-robin so one busy repo cannot crowd out the others.
+pub async fn search_all_workspaces_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    indexes: &SearchIndexes,
+    query: &str,
+    workspace_ids: Option<Vec<String>>,
+    limit: Option<usize>,
+    options: SearchOptions,
+    list_files: fn(&PathBuf, usize) -> Vec<String>,
+) -> Result<MultiWorkspaceSearchResponse, String> {
+    let started = Instant::now();
+    // Reject a bad pattern once instead of once per workspace.
+    line_matcher(query, options)?;
+    let limit = limit
+        .unwrap_or(DEFAULT_RESULT_LIMIT)
+        .clamp(1, MAX_RESULT_LIMIT);
+    let mut targets: Vec<(String, String, PathBuf)> = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .values()
+            .filter(|entry| {
+                workspace_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.iter().any(|id| id == &entry.id))
+            })
+            .map(|entry| {
+                (
+                    entry.id.clone(),
+                    entry.name.clone(),
+                    PathBuf::from(&entry.path),
+                )
+            })
+            .collect()
+    };
+    targets.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut building = Vec::new();
+    let mut errors = Vec::new();
+    let mut searches = tokio::task::JoinSet::new();
+    for (order, (workspace_id, workspace_name, root)) in targets.into_iter().enumerate() {
+        let built = match acquire_index(indexes, &workspace_id, &root, list_files).await {
+            Ok((Some(built), _)) => built,
+            Ok((None, _)) => {
+                building.push(workspace_id);
+                continue;
+            }
+            Err(error) => {
+                errors.push(WorkspaceSearchError {
+                    workspace_id,
+                    error,
+                });
+                continue;
+            }
+        };
+        let query = query.to_string();
+        searches.spawn_blocking(move || {
+            let result = search_built_index(&root, &built, &query, limit, None, options);
+            (order, workspace_id, workspace_name, result)
+        });
+    }
+
+    let mut per_workspace = Vec::new();
+    while let Some(joined) = searches.join_next().await {
+        let (order, workspace_id, workspace_name, result) =
+            joined.map_err(|err| format!("Search failed: {err}"))?;
+        match result {
+            Ok(matches) => per_workspace.push((order, workspace_id, workspace_name, matches)),
+            Err(error) => errors.push(WorkspaceSearchError {
+                workspace_id,
+                error,
+            }),
+        }
+    }
+    per_workspace.sort_by_key(|(order, ..)| *order);
+
+    let mut queues = per_workspace
+        .into_iter()
+        .map(|(_, workspace_id, workspace_name, matches)| {
+            (workspace_id, workspace_name, matches.into_iter())
+        })
+        .collect::<Vec<_>>();
+    let mut results = Vec::new();
+    'merge: loop {
+        let mut progressed = false;
+        for (workspace_id, workspace_name, matches) in queues.iter_mut() {
+            let Some(found) = matches.next() else {
+                continue;
+            };
+            progressed = true;
+            results.push(WorkspaceSearchMatch {
+                workspace_id: workspace_id.clone(),
+                workspace_name: workspace_name.clone(),
+                path: found.path,
+                line: found.line,
+                preview: found.preview,
+                ranges: found.ranges,
+            });
+            if results.len() >= limit {
+                break 'merge;
+            }
+        }
+        if !progressed {
+            break;
+        }
+    }
+
+    Ok(MultiWorkspaceSearchResponse {
+        results,
+        building,
+        errors,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn query_terms_skip_operators_and_duplicates() {
+        assert_eq!(
+            query_terms("Spawn_session AND spawn NOT \"foo\"", false),
+            vec!["spawn", "session", "foo"]
+        );
+        assert_eq!(
+            query_terms("Spawn_session AND spawn", true),
+            vec!["Spawn", "session", "spawn"]
+        );
+        let matcher = line_matcher("spawn", SearchOptions::default())
+            .expect("matcher")
+            .expect("non-empty");
+        assert_eq!(
+            highlight_ranges("let Spawn = spawn;", &matcher),
+            vec![(4, 9), (12, 17)]
+        );
+    }
+
+    #[test]
+    fn match_case_whole_word_and_regex_options_filter_lines() {
+        let matches = |query: &str, options: SearchOptions, line: &str| {
+            let matcher = line_matcher(query, options)
+                .expect("matcher")
+                .expect("non-empty");
+            highlight_ranges(line, &matcher)
+        };
+        let match_case = SearchOptions {
+            match_case: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(matches("Spawn", match_case, "spawn Spawn"), vec![(6, 11)]);
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            matches("spawn", whole_word, "spawned spawn_x spawn"),
+            vec![(16, 21)]
+        );
+        let regex = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(matches(r"fn \w+\(", regex, "pub FN go() {}"), vec![(4, 10)]);
+        assert_eq!(matches("x*", regex, "abc"), Vec::<(usize, usize)>::new());
+        let error = line_matcher("(unclosed", regex).expect_err("invalid");
+        assert!(error.starts_with("Invalid regex"), "{error}");
+        assert!(line_matcher("  ", regex).expect("empty").is_none());
+    }
+
+    #[test]
+    fn indexes_text_files_and_returns_highlighted_lines() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-index-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create src");
+        std::fs::write(
+            root.join("src/lib.rs"),
+            "fn start() {}\nfn spawn_session() {\n    start();\n}\n",
+        )
+        .expect("write lib");
+        std::fs::write(root.join("src/other.rs"), "fn unrelated() {}\n").expect("write other");
+        std::fs::write(root.join("blob.bin"), [0u8, 1, 2, b's']).expect("write blob");
+        let files = vec![
+            "src/lib.rs".to_string(),
+            "src/other.rs".to_string(),
+            "blob.bin".to_string(),
+        ];
+
+        let built = build_index(&root, &files).expect("build index");
+        assert_eq!(built.file_count, 2);
+
+        let search = |query: &str, prefix: Option<&str>| {
+            search_built_index(&root, &built, query, 10, prefix, SearchOptions::default())
+        };
+        let results = search("spawn session", None).expect("search");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "src/lib.rs");
+        assert_eq!(results[0].line, 2);
+        assert_eq!(results[0].ranges, vec![(3, 8), (9, 16)]);
+        assert!(search("missing", None).expect("search").is_empty());
+        assert_eq!(
+            search("spawn", Some("./src/"))
+                .expect("scoped search")
+                .len(),
+            1
+        );
+        assert!(search("spawn", Some("lib"))
+            .expect("scoped search")
+            .is_empty());
+
+        let regex = SearchOptions {
+            regex: true,
+            match_case: true,
+            ..SearchOptions::default()
+        };
+        let results =
+            search_built_index(&root, &built, r"^\s+start\(", 10, None, regex).expect("regex");
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            (results[0].path.as_str(), results[0].line),
+            ("src/lib.rs", 3)
+        );
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let results =
+            search_built_index(&root, &built, "spawn", 10, None, whole_word).expect("words");
+        assert!(results.is_empty(), "spawn_session is one word");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn list_fixture_files(_root: &PathBuf, _max: usize) -> Vec<String> {
+        vec!["shared.rs".to_string()]
+    }
+
+    fn fixture_workspace(name: &str, contents: &str) -> WorkspaceEntry {
+        let root = std::env::temp_dir().join(format!("codex-monitor-index-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        std::fs::write(root.join("shared.rs"), contents).expect("write file");
+        WorkspaceEntry {
+            id: name.to_string(),
+            name: name.to_string(),
+            path: root.to_string_lossy().to_string(),
+            codex_bin: None,
+            kind: crate::types::WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: crate::types::WorkspaceSettings::default(),
+        }
+    }
+
+    #[test]
+    fn search_all_workspaces_tags_and_interleaves_results() {
+        let alpha = fixture_workspace("alpha", "const MAX_RETRIES: u32 = 3;\nuse MAX_RETRIES;\n");
+        let beta = fixture_workspace("beta", "pub const MAX_RETRIES: u32 = 5;\n");
+        let gamma = fixture_workspace("gamma", "nothing here\n");
+        let roots = [&alpha, &beta, &gamma]
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect::<Vec<_>>();
+        let workspaces = Mutex::new(HashMap::from([
+            (alpha.id.clone(), alpha),
+            (beta.id.clone(), beta),
+            (gamma.id.clone(), gamma),
+        ]));
+        let indexes = SearchIndexes::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+
+        let response = runtime.block_on(async {
+            loop {
+                let response = search_all_workspaces_core(
+                    &workspaces,
+                    &indexes,
+                    "max_retries",
+                    None,
+                    Some(2),
+                    SearchOptions::default(),
+                    list_fixture_files,
+                )
+                .await
+                .expect("search all");
+                if response.building.is_empty() {
+                    return response;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        assert!(response.errors.is_empty());
+        let tagged = response
+            .results
+            .iter()
+            .map(|found| (found.workspace_id.as_str(), found.line))
+            .collect::<Vec<_>>();
+        assert_eq!(tagged, vec![("alpha", 1), ("beta", 1)]);
+
+        for root in roots {
+            let _ = std::fs::remove_dir_all(root);
+        }
+    }
+}
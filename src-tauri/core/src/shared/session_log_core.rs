@@ -0,0 +1,174 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// Directory under the data dir holding one `<workspace-id>.log` per workspace.
+pub const SESSION_LOGS_DIR: &str = "session-logs";
+const MAX_SESSION_LOG_BYTES: u64 = 1024 * 1024;
+const MAX_ROTATED_FILES: usize = 3;
+const DEFAULT_TAIL_LINES: usize = 200;
+const MAX_TAIL_LINES: usize = 10_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLogTail {
+    pub workspace_id: String,
+    pub path: String,
+    /// Oldest first; reaches into rotated files when the current one is short.
+    pub lines: Vec<String>,
+}
+
+/// Rotating capture of a codex app-server's stderr plus spawn/exit markers.
+pub struct SessionLog {
+    path: PathBuf,
+    max_bytes: u64,
+    lock: Mutex<()>,
+}
+
+fn log_path(dir: &Path, workspace_id: &str) -> PathBuf {
+    let name = workspace_id
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    dir.join(format!("{name}.log"))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+impl SessionLog {
+    pub fn new(dir: &Path, workspace_id: &str) -> Self {
+        Self {
+            path: log_path(dir, workspace_id),
+            max_bytes: MAX_SESSION_LOG_BYTES,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Appends one timestamped line; failures go to our own stderr and never reach the session.
+    pub fn append(&self, line: &str) {
+        let _guard = self
+            .lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stamped = format!(
+            "[{}] {}",
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            line.trim_end()
+        );
+        if let Err(err) = self
+            .rotate_if_needed()
+            .and_then(|_| append_line(&self.path, &stamped))
+        {
+            tracing::warn!(path = %self.path.display(), "failed to write session log: {err}");
+        }
+    }
+
+    fn rotate_if_needed(&self) -> Result<(), String> {
+        let size = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Ok(()),
+        };
+        if size < self.max_bytes {
+            return Ok(());
+        }
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, index + 1))
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        std::fs::rename(&self.path, rotated_path(&self.path, 1)).map_err(|err| err.to_string())
+    }
+}
+
+fn append_line(path: &Path, line: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| err.to_string())?;
+    writeln!(file, "{line}").map_err(|err| err.to_string())
+}
+
+/// Returns the last `tail` lines (default 200) of a workspace's session log.
+pub fn session_log_tail_core(
+    dir: &Path,
+    workspace_id: &str,
+    tail: Option<usize>,
+) -> Result<SessionLogTail, String> {
+    let limit = tail.unwrap_or(DEFAULT_TAIL_LINES).clamp(1, MAX_TAIL_LINES);
+    let path = log_path(dir, workspace_id);
+    let mut lines: Vec<String> = Vec::new();
+    for index in 0..=MAX_ROTATED_FILES {
+        if lines.len() >= limit {
+            break;
+        }
+        let rotated = rotated_path(&path, index);
+        let contents = match std::fs::read_to_string(&rotated) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("Failed to read {}: {err}", rotated.display())),
+        };
+        let mut older = contents
+            .lines()
+            .rev()
+            .take(limit - lines.len())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        lines.append(&mut older);
+    }
+    lines.reverse();
+    Ok(SessionLogTail {
+        workspace_id: workspace_id.to_string(),
+        path: path.to_string_lossy().to_string(),
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn rotates_and_tails_across_files() {
+        let dir =
+            std::env::temp_dir().join(format!("codex-monitor-session-log-{}", Uuid::new_v4()));
+        let mut log = SessionLog::new(&dir, "ws/1");
+        log.max_bytes = 120;
+        for index in 0..8 {
+            log.append(&format!("line {index}"));
+        }
+        assert!(rotated_path(&log.path, 1).exists());
+        assert!(log.path.ends_with("ws_1.log"));
+
+        let tail = session_log_tail_core(&dir, "ws/1", Some(3)).expect("tail");
+        assert_eq!(tail.lines.len(), 3);
+        assert!(tail.lines[0].ends_with("line 5"));
+        assert!(tail.lines[2].ends_with("line 7"));
+
+        let missing = session_log_tail_core(&dir, "other", None).expect("missing");
+        assert!(missing.lines.is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -2,7 +2,11 @@ use std::path::PathBuf;
 
 use tokio::sync::Mutex;
 
+use crate::codex::args::validate_codex_args;
 use crate::codex::config as codex_config;
+use crate::files::policy;
+use crate::shared::i18n_core;
+use crate::shared::revisions_core::check_revision;
 use crate::storage::write_settings;
 use crate::types::AppSettings;
 
@@ -14,7 +18,7 @@ fn normalize_personality(value: &str) -> Option<&'static str> {
     }
 }
 
-pub(crate) async fn get_app_settings_core(app_settings: &Mutex<AppSettings>) -> AppSettings {
+pub async fn get_app_settings_core(app_settings: &Mutex<AppSettings>) -> AppSettings {
     let mut settings = app_settings.lock().await.clone();
     if let Ok(Some(collab_enabled)) = codex_config::read_collab_enabled() {
         settings.experimental_collab_enabled = collab_enabled;
@@ -42,11 +46,16 @@ pub(crate) async fn get_app_settings_core(app_settings: &Mutex<AppSettings>) ->
     settings
 }
 
-pub(crate) async fn update_app_settings_core(
-    settings: AppSettings,
+pub async fn update_app_settings_core(
+    mut settings: AppSettings,
+    expected_revision: Option<u64>,
     app_settings: &Mutex<AppSettings>,
     settings_path: &PathBuf,
 ) -> Result<AppSettings, String> {
+    validate_codex_args(settings.codex_args.as_deref(), settings.codex_launch.as_ref())?;
+    let mut current = app_settings.lock().await;
+    check_revision("settings", expected_revision, current.revision)?;
+    settings.revision = current.revision + 1;
     let _ = codex_config::write_collab_enabled(settings.experimental_collab_enabled);
     let _ = codex_config::write_collaboration_modes_enabled(
         settings.collaboration_modes_enabled,
@@ -56,12 +65,13 @@ pub(crate) async fn update_app_settings_core(
     let _ = codex_config::write_apps_enabled(settings.experimental_apps_enabled);
     let _ = codex_config::write_personality(settings.personality.as_str());
     write_settings(settings_path, &settings)?;
-    let mut current = app_settings.lock().await;
+    i18n_core::set_locale(&settings.locale);
+    policy::set_skip_dirs(&settings.skip_dirs);
     *current = settings.clone();
     Ok(settings)
 }
 
-pub(crate) fn get_codex_config_path_core() -> Result<String, String> {
+pub fn get_codex_config_path_core() -> Result<String, String> {
     codex_config::config_toml_path()
         .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())
         .and_then(|path| {
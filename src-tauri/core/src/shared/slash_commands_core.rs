@@ -0,0 +1,264 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::types::CustomSlashCommand;
+
+/// Replaced with everything typed after the command name.
+const ARGS_PLACEHOLDER: &str = "{{args}}";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SlashCommandKind {
+    /// Expands into a prompt that is sent as the user message.
+    Prompt,
+    /// Runs a backend method instead of sending a message.
+    Rpc,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SlashCommandSource {
+    Builtin,
+    Custom,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlashCommandInfo {
+    pub name: String,
+    pub description: String,
+    pub argument_hint: Option<String>,
+    pub kind: SlashCommandKind,
+    /// Method a `rpc` command runs.
+    pub rpc: Option<String>,
+    pub source: SlashCommandSource,
+}
+
+/// What a message starting with a registered command turns into.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlashResolution {
+    Prompt(String),
+    Rpc { method: &'static str, params: Value },
+}
+
+struct BuiltinCommand {
+    name: &'static str,
+    description: &'static str,
+    argument_hint: Option<&'static str>,
+    args_required: bool,
+    template: Option<&'static str>,
+    rpc: Option<&'static str>,
+}
+
+const BUILTIN_COMMANDS: &[BuiltinCommand] = &[
+    BuiltinCommand {
+        name: "explain",
+        description: "explain a file or folder",
+        argument_hint: Some("<path>"),
+        args_required: true,
+        template: Some(
+            "Explain what `{{args}}` does: its purpose, how it fits into the rest of the \
+             codebase, and anything non-obvious about how it works.",
+        ),
+        rpc: None,
+    },
+    BuiltinCommand {
+        name: "review",
+        description: "review uncommitted changes, or the diff against a base branch",
+        argument_hint: Some("[base-branch]"),
+        args_required: false,
+        template: None,
+        rpc: Some("start_review"),
+    },
+    BuiltinCommand {
+        name: "test",
+        description: "run the test suite and fix failures",
+        argument_hint: Some("[focus]"),
+        args_required: false,
+        template: Some(
+            "Run this project's tests and fix any failures. Summarize what failed and what \
+             you changed.\n\n{{args}}",
+        ),
+        rpc: None,
+    },
+];
+
+/// Lowercased command name without the leading `/`; `None` when it isn't a usable name.
+fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim().trim_start_matches('/').to_lowercase();
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+    valid.then_some(name)
+}
+
+fn expand_template(template: &str, args: &str) -> String {
+    let expanded = if template.contains(ARGS_PLACEHOLDER) {
+        template.replace(ARGS_PLACEHOLDER, args)
+    } else if args.is_empty() {
+        template.to_string()
+    } else {
+        format!("{template}\n\n{args}")
+    };
+    expanded.trim().to_string()
+}
+
+fn find_custom<'a>(custom: &'a [CustomSlashCommand], name: &str) -> Option<&'a CustomSlashCommand> {
+    custom
+        .iter()
+        .find(|command| normalize_name(&command.name).as_deref() == Some(name))
+}
+
+/// Built-in commands followed by the valid user-defined ones from settings. Custom
+/// commands can't shadow a built-in; the first definition of a name wins.
+pub fn list_slash_commands(custom: &[CustomSlashCommand]) -> Vec<SlashCommandInfo> {
+    let mut commands = BUILTIN_COMMANDS
+        .iter()
+        .map(|command| SlashCommandInfo {
+            name: command.name.to_string(),
+            description: command.description.to_string(),
+            argument_hint: command.argument_hint.map(str::to_string),
+            kind: if command.rpc.is_some() {
+                SlashCommandKind::Rpc
+            } else {
+                SlashCommandKind::Prompt
+            },
+            rpc: command.rpc.map(str::to_string),
+            source: SlashCommandSource::Builtin,
+        })
+        .collect::<Vec<_>>();
+    let mut custom_commands: Vec<SlashCommandInfo> = Vec::new();
+    for command in custom {
+        let Some(name) = normalize_name(&command.name) else {
+            continue;
+        };
+        let taken = BUILTIN_COMMANDS.iter().any(|builtin| builtin.name == name)
+            || custom_commands.iter().any(|existing| existing.name == name);
+        if taken {
+            continue;
+        }
+        custom_commands.push(SlashCommandInfo {
+            name,
+            description: command.description.clone().unwrap_or_default(),
+            argument_hint: command
+                .prompt
+                .contains(ARGS_PLACEHOLDER)
+                .then(|| "[args]".to_string()),
+            kind: SlashCommandKind::Prompt,
+            rpc: None,
+            source: SlashCommandSource::Custom,
+        });
+    }
+    custom_commands.sort_by(|a, b| a.name.cmp(&b.name));
+    commands.extend(custom_commands);
+    commands
+}
+
+/// Resolves a message that starts with a registered `/command`. Anything else, including
+/// unknown commands and plain paths, returns `None` and is sent unchanged.
+pub fn resolve_slash_command(
+    text: &str,
+    custom: &[CustomSlashCommand],
+) -> Result<Option<SlashResolution>, String> {
+    let Some(rest) = text.trim_start().strip_prefix('/') else {
+        return Ok(None);
+    };
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let Some(name) = normalize_name(name) else {
+        return Ok(None);
+    };
+    let args = args.trim();
+
+    if let Some(builtin) = BUILTIN_COMMANDS.iter().find(|command| command.name == name) {
+        if builtin.args_required && args.is_empty() {
+            return Err(format!(
+                "Usage: /{} {}",
+                builtin.name,
+                builtin.argument_hint.unwrap_or_default()
+            ));
+        }
+        return Ok(Some(match (builtin.template, builtin.rpc) {
+            (Some(template), _) => SlashResolution::Prompt(expand_template(template, args)),
+            (None, Some("start_review")) => SlashResolution::Rpc {
+                method: "start_review",
+                params: if args.is_empty() {
+                    json!({ "target": { "type": "uncommittedChanges" } })
+                } else {
+                    json!({ "target": { "type": "baseBranch", "branch": args } })
+                },
+            },
+            _ => return Ok(None),
+        }));
+    }
+
+    Ok(find_custom(custom, &name)
+        .map(|command| SlashResolution::Prompt(expand_template(&command.prompt, args))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(name: &str, prompt: &str) -> CustomSlashCommand {
+        CustomSlashCommand {
+            name: name.to_string(),
+            description: None,
+            prompt: prompt.to_string(),
+        }
+    }
+
+    #[test]
+    fn resolves_builtin_and_custom_commands() {
+        let commands = vec![
+            custom("/Changelog", "Draft a changelog entry for {{args}}"),
+            custom("review", "shadowed"),
+            custom("bad name", "ignored"),
+        ];
+
+        assert_eq!(
+            resolve_slash_command("/explain src/main.rs", &commands).unwrap(),
+            Some(SlashResolution::Prompt(
+                "Explain what `src/main.rs` does: its purpose, how it fits into the rest of the \
+                 codebase, and anything non-obvious about how it works."
+                    .to_string()
+            ))
+        );
+        assert!(resolve_slash_command("/explain", &commands).is_err());
+        assert_eq!(
+            resolve_slash_command("/review main", &commands).unwrap(),
+            Some(SlashResolution::Rpc {
+                method: "start_review",
+                params: json!({ "target": { "type": "baseBranch", "branch": "main" } }),
+            })
+        );
+        assert_eq!(
+            resolve_slash_command("/changelog v1.2", &commands).unwrap(),
+            Some(SlashResolution::Prompt(
+                "Draft a changelog entry for v1.2".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve_slash_command("/usr/bin is odd", &commands).unwrap(),
+            None
+        );
+        assert_eq!(
+            resolve_slash_command("hello /test", &commands).unwrap(),
+            None
+        );
+
+        let names = list_slash_commands(&commands)
+            .into_iter()
+            .map(|command| (command.name, command.source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("explain".to_string(), SlashCommandSource::Builtin),
+                ("review".to_string(), SlashCommandSource::Builtin),
+                ("test".to_string(), SlashCommandSource::Builtin),
+                ("changelog".to_string(), SlashCommandSource::Custom),
+            ]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::shared::git_core::run_git_command;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::types::WorkspaceEntry;
+
+/// A checkout's sparse-checkout state. Listing, search and the symbol index walk the
+/// working tree, so a sparse workspace only ever sees its checked-out set.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SparseCheckout {
+    pub enabled: bool,
+    /// Cone mode: `patterns` are directories, checked out with everything below them
+    /// plus the files directly inside their parents.
+    pub cone: bool,
+    pub patterns: Vec<String>,
+}
+
+async fn config_flag(root: &PathBuf, key: &str) -> Result<bool, String> {
+    let value = run_git_command(root, &["config", "--type=bool", "--default=false", key]).await?;
+    Ok(value == "true")
+}
+
+pub async fn read_sparse_checkout(root: &PathBuf) -> Result<SparseCheckout, String> {
+    if !config_flag(root, "core.sparseCheckout").await? {
+        return Ok(SparseCheckout::default());
+    }
+    let patterns = run_git_command(root, &["sparse-checkout", "list"]).await?;
+    Ok(SparseCheckout {
+        enabled: true,
+        cone: config_flag(root, "core.sparseCheckoutCone").await?,
+        patterns: patterns
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+/// Repository-relative cone directories with `/` separators, sorted and deduplicated.
+pub fn normalize_cone_patterns(patterns: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.trim().replace('\\', "/");
+        let pattern = pattern.trim_matches('/');
+        let valid = !pattern.is_empty()
+            && !pattern.starts_with('-')
+            && !pattern.contains(':')
+            && pattern
+                .split('/')
+                .all(|part| !matches!(part, "" | "." | ".."));
+        if !valid {
+            return Err(format!(
+                "Invalid sparse-checkout directory `{pattern}`: use a relative directory inside the repository."
+            ));
+        }
+        normalized.push(pattern.to_string());
+    }
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+/// Gives `root` the same sparse-checkout patterns as `sparse`, then fills in the
+/// working tree. Used for worktrees added with `--no-checkout` from a sparse parent.
+pub async fn apply_sparse_checkout(
+    root: &PathBuf,
+    sparse: &SparseCheckout,
+) -> Result<(), String> {
+    let mode = if sparse.cone { "--cone" } else { "--no-cone" };
+    let mut args = vec!["sparse-checkout", "set", mode, "--"];
+    args.extend(sparse.patterns.iter().map(String::as_str));
+    run_git_command(root, &args).await?;
+    run_git_command(root, &["checkout"]).await?;
+    Ok(())
+}
+
+async fn workspace_root(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<PathBuf, String> {
+    let workspaces = workspaces.lock().await;
+    let entry = workspaces
+        .get(workspace_id)
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    Ok(PathBuf::from(&entry.path))
+}
+
+pub async fn get_sparse_checkout_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<SparseCheckout, String> {
+    let root = workspace_root(workspaces, workspace_id).await?;
+    read_sparse_checkout(&root).await
+}
+
+/// Switches the workspace to a cone-mode sparse checkout of `directories`, or
+/// narrows or widens an existing one. Files at the repository root stay checked out.
+pub async fn set_sparse_checkout_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    directories: &[String],
+) -> Result<(PathBuf, SparseCheckout), String> {
+    let root = workspace_root(workspaces, workspace_id).await?;
+    let directories = normalize_cone_patterns(directories)?;
+    let mut args = vec!["sparse-checkout", "set", "--cone", "--"];
+    args.extend(directories.iter().map(String::as_str));
+    run_git_command(&root, &args).await?;
+    let sparse = read_sparse_checkout(&root).await?;
+    Ok((root, sparse))
+}
+
+pub async fn disable_sparse_checkout_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<(PathBuf, SparseCheckout), String> {
+    let root = workspace_root(workspaces, workspace_id).await?;
+    run_git_command(&root, &["sparse-checkout", "disable"]).await?;
+    let sparse = read_sparse_checkout(&root).await?;
+    Ok((root, sparse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use uuid::Uuid;
+
+    #[test]
+    fn normalizes_cone_directories() {
+        let patterns = ["services/api/", " web ", "services\\api", "docs/guides"]
+            .map(str::to_string)
+            .to_vec();
+        assert_eq!(
+            normalize_cone_patterns(&patterns).expect("valid"),
+            vec!["docs/guides", "services/api", "web"]
+        );
+        for invalid in ["", "/", "../other", "a/./b", "--cone", "C:/repo"] {
+            assert!(
+                normalize_cone_patterns(&[invalid.to_string()]).is_err(),
+                "{invalid:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn narrows_a_checkout_and_copies_it_to_a_new_worktree() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-sparse-{}", Uuid::new_v4()));
+        let root = base.join("repo");
+        for dir in ["services/api", "services/web", "docs"] {
+            std::fs::create_dir_all(root.join(dir)).expect("create dir");
+            std::fs::write(root.join(dir).join("file.txt"), dir).expect("write file");
+        }
+        std::fs::write(root.join("README.md"), "root").expect("write readme");
+        git(&root, &["init", "-q"]);
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "init"]);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            assert!(!read_sparse_checkout(&root).await.expect("read").enabled);
+            run_git_command(
+                &root,
+                &["sparse-checkout", "set", "--cone", "--", "services/api"],
+            )
+            .await
+            .expect("set");
+            let sparse = read_sparse_checkout(&root).await.expect("read");
+            assert_eq!(
+                sparse,
+                SparseCheckout {
+                    enabled: true,
+                    cone: true,
+                    patterns: vec!["services/api".to_string()],
+                }
+            );
+            assert!(root.join("README.md").exists());
+            assert!(!root.join("docs").exists());
+
+            let worktree = base.join("worktree");
+            let worktree_arg = worktree.to_string_lossy().to_string();
+            git(
+                &root,
+                &[
+                    "worktree",
+                    "add",
+                    "-q",
+                    "--no-checkout",
+                    "-b",
+                    "feature",
+                    &worktree_arg,
+                ],
+            );
+            apply_sparse_checkout(&worktree, &sparse)
+                .await
+                .expect("apply");
+            assert!(worktree.join("services/api/file.txt").exists());
+            assert!(!worktree.join("services/web").exists());
+            let status = run_git_command(&worktree, &["status", "--porcelain"])
+                .await
+                .expect("status");
+            assert_eq!(status, "");
+        });
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
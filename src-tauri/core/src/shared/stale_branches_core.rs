@@ -0,0 +1,557 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::shared::branch_protection_core::{protected_branch_match, protected_branch_patterns};
+use crate::shared::git_core::run_git_command;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::worktree_reconcile_core::parse_worktree_list;
+use crate::types::{ThreadBranchRecord, WorkspaceEntry};
+
+/// Branches without a commit for this many days are reported when no cutoff is given.
+pub const DEFAULT_INACTIVE_DAYS: u32 = 30;
+/// How long a report's confirmation token can be redeemed.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(10 * 60);
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StaleReason {
+    /// Every commit is already on the base branch.
+    Merged,
+    /// No commit for at least the report's cutoff.
+    Inactive,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleBranch {
+    pub name: String,
+    pub tip: String,
+    /// Committer time of the tip, in seconds since the epoch.
+    pub last_commit_at: u64,
+    pub inactive_days: u64,
+    pub reasons: Vec<StaleReason>,
+    /// Worktree workspaces registered on this branch.
+    pub worktree_ids: Vec<String>,
+    /// Checked out in the repo or one of its git worktrees, registered or not.
+    pub checked_out: bool,
+    /// Threads started on this branch.
+    pub thread_ids: Vec<String>,
+    /// Matches one of the workspace's `protectedBranches`.
+    pub protected: bool,
+    /// Not checked out, not a worktree's branch and not protected.
+    pub deletable: bool,
+}
+
+/// Local branches that look abandoned, with a token for `delete_stale_branches`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleBranchesReport {
+    pub workspace_id: String,
+    /// The branch `merged` is measured against.
+    pub base: String,
+    pub inactive_days: u32,
+    pub branches: Vec<StaleBranch>,
+    pub confirm_token: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedBranch {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleBranchDeletion {
+    pub deleted: Vec<String>,
+    pub skipped: Vec<SkippedBranch>,
+}
+
+/// One line of `git for-each-ref refs/heads`.
+#[derive(Debug, Clone, PartialEq)]
+struct BranchRef {
+    name: String,
+    tip: String,
+    committed_at: u64,
+}
+
+const BRANCH_REF_FORMAT: &str = "--format=%(refname:short)%09%(objectname)%09%(committerdate:unix)";
+
+fn parse_branch_refs(output: &str) -> Vec<BranchRef> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.trim();
+            let tip = fields.next()?.trim();
+            let committed_at = fields.next()?.trim().parse().ok()?;
+            (!name.is_empty()).then(|| BranchRef {
+                name: name.to_string(),
+                tip: tip.to_string(),
+                committed_at,
+            })
+        })
+        .collect()
+}
+
+struct PendingCleanup {
+    workspace_id: String,
+    /// Branch name to the tip it had when reported.
+    tips: HashMap<String, String>,
+    expires_at: u64,
+}
+
+/// Outstanding report tokens. Each is single-use and only deletes branches its report
+/// listed, still at the commit they were reported at.
+#[derive(Default)]
+pub struct StaleBranchConfirmations {
+    pending: StdMutex<HashMap<String, PendingCleanup>>,
+}
+
+impl StaleBranchConfirmations {
+    fn issue(&self, workspace_id: &str, tips: HashMap<String, String>, now: u64) -> (String, u64) {
+        let token = Uuid::new_v4().to_string();
+        let expires_at = now + CONFIRMATION_TTL.as_millis() as u64;
+        let mut pending = self.pending.lock().unwrap_or_else(|err| err.into_inner());
+        pending.retain(|_, cleanup| cleanup.expires_at > now);
+        pending.insert(
+            token.clone(),
+            PendingCleanup {
+                workspace_id: workspace_id.to_string(),
+                tips,
+                expires_at,
+            },
+        );
+        (token, expires_at)
+    }
+
+    fn redeem(
+        &self,
+        token: &str,
+        workspace_id: &str,
+        now: u64,
+    ) -> Result<HashMap<String, String>, String> {
+        let cleanup = self
+            .pending
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(token)
+            .filter(|cleanup| cleanup.workspace_id == workspace_id)
+            .ok_or(
+                "Unknown or already used confirmation token; request a new stale branch report.",
+            )?;
+        if cleanup.expires_at <= now {
+            return Err(
+                "Confirmation token expired; request a new stale branch report.".to_string(),
+            );
+        }
+        Ok(cleanup.tips)
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Branches live in the main repo, so a worktree's report is its parent's.
+fn repo_entry(
+    workspaces: &HashMap<String, WorkspaceEntry>,
+    workspace_id: &str,
+) -> Result<(WorkspaceEntry, Vec<WorkspaceEntry>), String> {
+    let entry = workspaces
+        .get(workspace_id)
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    let repo = match entry.parent_id.as_deref() {
+        Some(parent_id) if entry.kind.is_worktree() => workspaces
+            .get(parent_id)
+            .ok_or("The worktree's parent workspace no longer exists.")?,
+        _ => entry,
+    };
+    let worktrees = workspaces
+        .values()
+        .filter(|workspace| workspace.parent_id.as_deref() == Some(repo.id.as_str()))
+        .cloned()
+        .collect();
+    Ok((repo.clone(), worktrees))
+}
+
+/// What the repo looks like now: its branches, the ones git has checked out, and the
+/// worktree workspaces on each branch.
+struct RepoBranches {
+    refs: Vec<BranchRef>,
+    checked_out: HashSet<String>,
+    worktree_ids: HashMap<String, Vec<String>>,
+    patterns: Vec<String>,
+}
+
+impl RepoBranches {
+    async fn read(repo: &WorkspaceEntry, worktrees: &[WorkspaceEntry]) -> Result<Self, String> {
+        let root = PathBuf::from(&repo.path);
+        let refs = run_git_command(&root, &["for-each-ref", BRANCH_REF_FORMAT, "refs/heads"])
+            .await
+            .map(|output| parse_branch_refs(&output))?;
+        let checked_out = run_git_command(&root, &["worktree", "list", "--porcelain"])
+            .await
+            .map(|output| parse_worktree_list(&output))?
+            .into_iter()
+            .filter_map(|worktree| worktree.branch)
+            .collect();
+        let mut worktree_ids: HashMap<String, Vec<String>> = HashMap::new();
+        for worktree in worktrees {
+            if let Some(info) = &worktree.worktree {
+                worktree_ids
+                    .entry(info.branch.clone())
+                    .or_default()
+                    .push(worktree.id.clone());
+            }
+        }
+        for ids in worktree_ids.values_mut() {
+            ids.sort();
+        }
+        Ok(Self {
+            refs,
+            checked_out,
+            worktree_ids,
+            patterns: protected_branch_patterns(repo, None),
+        })
+    }
+
+    /// Why `name` must be kept whatever a report said, if anything.
+    fn keep_reason(&self, name: &str) -> Option<String> {
+        if self.checked_out.contains(name) {
+            return Some("checked out in the repo or a worktree".to_string());
+        }
+        if self.worktree_ids.contains_key(name) {
+            return Some("a worktree workspace uses it".to_string());
+        }
+        protected_branch_match(&self.patterns, name)
+            .map(|pattern| format!("protected (matches `{pattern}`)"))
+    }
+}
+
+/// Branches merged into `base` or idle for `inactive_days`, newest activity first.
+fn stale_branches(
+    repo: &RepoBranches,
+    base: &str,
+    merged: &HashSet<String>,
+    thread_ids: &HashMap<String, Vec<String>>,
+    inactive_days: u32,
+    now_secs: u64,
+) -> Vec<StaleBranch> {
+    let mut branches: Vec<StaleBranch> = repo
+        .refs
+        .iter()
+        .filter(|branch| branch.name != base)
+        .filter_map(|branch| {
+            let idle_days = now_secs.saturating_sub(branch.committed_at) / DAY_SECS;
+            let mut reasons = Vec::new();
+            if merged.contains(&branch.name) {
+                reasons.push(StaleReason::Merged);
+            }
+            if idle_days >= u64::from(inactive_days) {
+                reasons.push(StaleReason::Inactive);
+            }
+            if reasons.is_empty() {
+                return None;
+            }
+            let checked_out = repo.checked_out.contains(&branch.name);
+            let worktree_ids = repo
+                .worktree_ids
+                .get(&branch.name)
+                .cloned()
+                .unwrap_or_default();
+            let protected = protected_branch_match(&repo.patterns, &branch.name).is_some();
+            Some(StaleBranch {
+                name: branch.name.clone(),
+                tip: branch.tip.clone(),
+                last_commit_at: branch.committed_at,
+                inactive_days: idle_days,
+                reasons,
+                deletable: !checked_out && worktree_ids.is_empty() && !protected,
+                worktree_ids,
+                checked_out,
+                thread_ids: thread_ids.get(&branch.name).cloned().unwrap_or_default(),
+                protected,
+            })
+        })
+        .collect();
+    branches.sort_by(|a, b| {
+        b.last_commit_at
+            .cmp(&a.last_commit_at)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    branches
+}
+
+/// The branch to measure `merged` against: `base`, else the repo's `worktreeBaseRef`,
+/// else what its checkout is on.
+async fn resolve_base(repo: &WorkspaceEntry, base: Option<String>) -> Result<String, String> {
+    let configured = base
+        .or_else(|| repo.settings.worktree_base_ref.clone())
+        .map(|base| base.trim().to_string())
+        .filter(|base| !base.is_empty());
+    if let Some(base) = configured {
+        return Ok(base);
+    }
+    let head = run_git_command(
+        &PathBuf::from(&repo.path),
+        &["symbolic-ref", "--quiet", "--short", "HEAD"],
+    )
+    .await
+    .map_err(|_| "The repo's HEAD is detached; pass `base` to compare against.".to_string())?;
+    Ok(head.trim().to_string())
+}
+
+/// Lists stale local branches of the repo behind `workspace_id` and issues a token that
+/// lets `delete_stale_branches_core` remove them.
+pub async fn stale_branches_report_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    thread_branches: &Mutex<HashMap<String, ThreadBranchRecord>>,
+    confirmations: &StaleBranchConfirmations,
+    workspace_id: &str,
+    inactive_days: Option<u32>,
+    base: Option<String>,
+) -> Result<StaleBranchesReport, String> {
+    let (repo, worktrees) = repo_entry(&*workspaces.lock().await, workspace_id)?;
+    let inactive_days = inactive_days.unwrap_or(DEFAULT_INACTIVE_DAYS);
+    let base = resolve_base(&repo, base).await?;
+    let branches = RepoBranches::read(&repo, &worktrees).await?;
+    let merged_ref = format!("--merged={base}");
+    let merged = run_git_command(
+        &PathBuf::from(&repo.path),
+        &[
+            "for-each-ref",
+            &merged_ref,
+            "--format=%(refname:short)",
+            "refs/heads",
+        ],
+    )
+    .await?
+    .lines()
+    .map(|line| line.trim().to_string())
+    .collect::<HashSet<_>>();
+
+    let family: HashSet<&str> = std::iter::once(repo.id.as_str())
+        .chain(worktrees.iter().map(|worktree| worktree.id.as_str()))
+        .collect();
+    let mut thread_ids: HashMap<String, Vec<String>> = HashMap::new();
+    for (thread_id, record) in thread_branches.lock().await.iter() {
+        if let Some(branch) = record
+            .branch
+            .as_ref()
+            .filter(|_| family.contains(record.workspace_id.as_str()))
+        {
+            thread_ids
+                .entry(branch.clone())
+                .or_default()
+                .push(thread_id.clone());
+        }
+    }
+    for ids in thread_ids.values_mut() {
+        ids.sort();
+    }
+
+    let now = now_millis();
+    let stale = stale_branches(
+        &branches,
+        &base,
+        &merged,
+        &thread_ids,
+        inactive_days,
+        now / 1000,
+    );
+    let tips = stale
+        .iter()
+        .filter(|branch| branch.deletable)
+        .map(|branch| (branch.name.clone(), branch.tip.clone()))
+        .collect();
+    let (confirm_token, expires_at) = confirmations.issue(workspace_id, tips, now);
+    Ok(StaleBranchesReport {
+        workspace_id: workspace_id.to_string(),
+        base,
+        inactive_days,
+        branches: stale,
+        confirm_token,
+        expires_at,
+    })
+}
+
+/// Deletes `names` from the report `confirm_token` came with. A branch is skipped if the
+/// report didn't list it as deletable, it moved since, or it is now checked out, used
+/// by a worktree or protected. Unmerged branches need `force`.
+pub async fn delete_stale_branches_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    confirmations: &StaleBranchConfirmations,
+    workspace_id: &str,
+    names: &[String],
+    confirm_token: &str,
+    force: bool,
+) -> Result<StaleBranchDeletion, String> {
+    let (repo, worktrees) = repo_entry(&*workspaces.lock().await, workspace_id)?;
+    let reported = confirmations.redeem(confirm_token, workspace_id, now_millis())?;
+    let branches = RepoBranches::read(&repo, &worktrees).await?;
+    let root = PathBuf::from(&repo.path);
+    let mut result = StaleBranchDeletion::default();
+    let mut seen = HashSet::new();
+    for name in names {
+        let name = name.trim();
+        if !seen.insert(name) {
+            continue;
+        }
+        let mut skip = |reason: String| {
+            result.skipped.push(SkippedBranch {
+                name: name.to_string(),
+                reason,
+            })
+        };
+        let Some(reported_tip) = reported.get(name) else {
+            skip("not listed as deletable in the report".to_string());
+            continue;
+        };
+        let current = branches.refs.iter().find(|branch| branch.name == name);
+        match current {
+            None => {
+                skip("no longer exists".to_string());
+                continue;
+            }
+            Some(branch) if &branch.tip != reported_tip => {
+                skip("has new commits since the report".to_string());
+                continue;
+            }
+            Some(_) => {}
+        }
+        if let Some(reason) = branches.keep_reason(name) {
+            skip(reason);
+            continue;
+        }
+        let flag = if force { "-D" } else { "-d" };
+        match run_git_command(&root, &["branch", flag, name]).await {
+            Ok(_) => result.deleted.push(name.to_string()),
+            Err(error) => skip(error),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(refs: &[(&str, &str, u64)]) -> RepoBranches {
+        RepoBranches {
+            refs: refs
+                .iter()
+                .map(|(name, tip, committed_at)| BranchRef {
+                    name: name.to_string(),
+                    tip: tip.to_string(),
+                    committed_at: *committed_at,
+                })
+                .collect(),
+            checked_out: HashSet::from(["main".to_string(), "feat/live".to_string()]),
+            worktree_ids: HashMap::from([("feat/wt".to_string(), vec!["wt-1".to_string()])]),
+            patterns: vec!["release/*".to_string()],
+        }
+    }
+
+    #[test]
+    fn parses_for_each_ref_output() {
+        let refs = parse_branch_refs("main\tabc\t1700000000\nbroken\nfeat/x\tdef\t1700000100\n");
+        assert_eq!(
+            refs,
+            vec![
+                BranchRef {
+                    name: "main".to_string(),
+                    tip: "abc".to_string(),
+                    committed_at: 1_700_000_000,
+                },
+                BranchRef {
+                    name: "feat/x".to_string(),
+                    tip: "def".to_string(),
+                    committed_at: 1_700_000_100,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_merged_and_inactive_branches() {
+        let now = 100 * DAY_SECS;
+        let repo = repo(&[
+            ("main", "m", now),
+            ("feat/merged", "a", now - DAY_SECS),
+            ("feat/old", "b", now - 40 * DAY_SECS),
+            ("feat/fresh", "c", now - 2 * DAY_SECS),
+            ("feat/wt", "d", now - 60 * DAY_SECS),
+            ("release/1.0", "e", now - 90 * DAY_SECS),
+            ("feat/live", "f", now - 50 * DAY_SECS),
+        ]);
+        let merged = HashSet::from(["main".to_string(), "feat/merged".to_string()]);
+        let threads = HashMap::from([("feat/old".to_string(), vec!["thread-1".to_string()])]);
+        let stale = stale_branches(&repo, "main", &merged, &threads, 30, now);
+
+        let summary: Vec<_> = stale
+            .iter()
+            .map(|branch| {
+                (
+                    branch.name.as_str(),
+                    branch.reasons.clone(),
+                    branch.deletable,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("feat/merged", vec![StaleReason::Merged], true),
+                ("feat/old", vec![StaleReason::Inactive], true),
+                ("feat/live", vec![StaleReason::Inactive], false),
+                ("feat/wt", vec![StaleReason::Inactive], false),
+                ("release/1.0", vec![StaleReason::Inactive], false),
+            ]
+        );
+        assert_eq!(stale[1].thread_ids, vec!["thread-1"]);
+        assert_eq!(stale[1].inactive_days, 40);
+        assert!(stale[2].checked_out);
+        assert_eq!(stale[3].worktree_ids, vec!["wt-1"]);
+        assert!(stale[4].protected);
+        assert_eq!(
+            repo.keep_reason("release/1.0").as_deref(),
+            Some("protected (matches `release/*`)")
+        );
+        assert!(repo.keep_reason("feat/old").is_none());
+    }
+
+    #[test]
+    fn tokens_are_single_use_and_bound_to_the_workspace() {
+        let confirmations = StaleBranchConfirmations::default();
+        let tips = HashMap::from([("feat/old".to_string(), "b".to_string())]);
+        let (token, expires_at) = confirmations.issue("ws", tips.clone(), 1_000);
+        assert!(confirmations.redeem(&token, "other", 1_000).is_err());
+
+        let (token, _) = confirmations.issue("ws", tips.clone(), 1_000);
+        assert_eq!(
+            confirmations.redeem(&token, "ws", 1_000).expect("redeem"),
+            tips
+        );
+        assert!(confirmations.redeem(&token, "ws", 1_000).is_err());
+
+        let (token, _) = confirmations.issue("ws", tips, 1_000);
+        let error = confirmations
+            .redeem(&token, "ws", expires_at)
+            .expect_err("expired");
+        assert!(error.contains("expired"), "{error}");
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::outline_core::{
+    outline_source, OutlineLanguage, OutlineSymbol, SymbolKind, MAX_OUTLINE_BYTES,
+};
+use crate::types::WorkspaceEntry;
+
+const MAX_SYMBOL_FILES: usize = 20_000;
+const DEFAULT_SYMBOL_LIMIT: usize = 100;
+const MAX_SYMBOL_LIMIT: usize = 1_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub language: String,
+    /// Workspace-relative, with `/` separators.
+    pub path: String,
+    /// 1-based position of the name.
+    pub line: usize,
+    pub column: usize,
+    /// Name of the enclosing symbol, e.g. the class of a method.
+    pub container: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceSymbolsResponse {
+    /// Best matches first.
+    pub symbols: Vec<WorkspaceSymbol>,
+    pub indexed_files: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DefinitionLookup {
+    /// The identifier under the cursor, if any.
+    pub symbol: Option<String>,
+    /// Declarations with that exact name, most likely first.
+    pub definitions: Vec<WorkspaceSymbol>,
+}
+
+struct IndexedFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    symbols: Vec<WorkspaceSymbol>,
+}
+
+/// Outline symbols per workspace root. Each refresh re-parses only the files whose
+/// size or mtime changed since they were last indexed.
+#[derive(Default)]
+pub struct SymbolIndex {
+    roots: StdMutex<HashMap<PathBuf, HashMap<String, IndexedFile>>>,
+}
+
+fn flatten(
+    symbols: &[OutlineSymbol],
+    language: OutlineLanguage,
+    path: &str,
+    container: Option<&str>,
+    out: &mut Vec<WorkspaceSymbol>,
+) {
+    for symbol in symbols {
+        out.push(WorkspaceSymbol {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            language: language.name().to_string(),
+            path: path.to_string(),
+            line: symbol.name_line,
+            column: symbol.name_column,
+            container: container.map(str::to_string),
+        });
+        flatten(&symbol.children, language, path, Some(&symbol.name), out);
+    }
+}
+
+fn index_file(root: &Path, path: &str, language: OutlineLanguage) -> Vec<WorkspaceSymbol> {
+    let Ok(bytes) = std::fs::read(root.join(path)) else {
+        return Vec::new();
+    };
+    let Ok(outline) = outline_source(language, &String::from_utf8_lossy(&bytes)) else {
+        return Vec::new();
+    };
+    let mut symbols = Vec::new();
+    flatten(&outline, language, path, None, &mut symbols);
+    symbols
+}
+
+impl SymbolIndex {
+    /// Brings the index for `root` in line with `files` and returns every symbol in it.
+    pub fn refresh(&self, root: &Path, files: &[String]) -> Vec<WorkspaceSymbol> {
+        let mut roots = self
+            .roots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let indexed = roots.entry(root.to_path_buf()).or_default();
+        let mut previous = std::mem::take(indexed);
+        for path in files {
+            let Some(language) = OutlineLanguage::from_path(path) else {
+                continue;
+            };
+            let Ok(metadata) = std::fs::metadata(root.join(path)) else {
+                continue;
+            };
+            if !metadata.is_file() || metadata.len() > MAX_OUTLINE_BYTES {
+                continue;
+            }
+            let modified = metadata.modified().ok();
+            let file = match previous.remove(path) {
+                Some(file) if file.modified == modified && file.len == metadata.len() => file,
+                _ => IndexedFile {
+                    modified,
+                    len: metadata.len(),
+                    symbols: index_file(root, path, language),
+                },
+            };
+            indexed.insert(path.clone(), file);
+        }
+        indexed
+            .values()
+            .flat_map(|file| file.symbols.iter().cloned())
+            .collect()
+    }
+}
+
+/// Lower is better: exact, then case-insensitive exact, prefix, substring, and
+/// finally the query's characters in order (`hsr` matches `HttpServer`).
+fn match_rank(name: &str, query: &str) -> Option<u8> {
+    if name == query {
+        return Some(0);
+    }
+    let name_lower = name.to_lowercase();
+    let query_lower = query.to_lowercase();
+    if name_lower == query_lower {
+        return Some(1);
+    }
+    if name_lower.starts_with(&query_lower) {
+        return Some(2);
+    }
+    if name_lower.contains(&query_lower) {
+        return Some(3);
+    }
+    let mut remaining = name_lower.chars();
+    query_lower
+        .chars()
+        .all(|wanted| remaining.any(|ch| ch == wanted))
+        .then_some(4)
+}
+
+pub fn search_symbols(
+    symbols: Vec<WorkspaceSymbol>,
+    query: &str,
+    limit: usize,
+) -> Vec<WorkspaceSymbol> {
+    let query = query.trim();
+    let mut ranked = symbols
+        .into_iter()
+        .filter_map(|symbol| {
+            let rank = if query.is_empty() {
+                0
+            } else {
+                match_rank(&symbol.name, query)?
+            };
+            Some((rank, symbol))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(rank_a, a), (rank_b, b)| {
+        rank_a
+            .cmp(rank_b)
+            .then(a.name.len().cmp(&b.name.len()))
+            .then_with(|| a.path.cmp(&b.path))
+            .then(a.line.cmp(&b.line))
+    });
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, symbol)| symbol)
+        .collect()
+}
+
+/// The identifier touching 1-based character `column` of `line`.
+pub fn identifier_at(line: &str, column: usize) -> Option<String> {
+    let chars = line.chars().collect::<Vec<_>>();
+    let is_ident = |ch: char| ch.is_alphanumeric() || ch == '_' || ch == '$';
+    let mut index = column.checked_sub(1)?.min(chars.len());
+    if !chars.get(index).is_some_and(|ch| is_ident(*ch)) {
+        index = index.checked_sub(1).filter(|prev| is_ident(chars[*prev]))?;
+    }
+    let start = chars[..index]
+        .iter()
+        .rposition(|ch| !is_ident(*ch))
+        .map(|at| at + 1)
+        .unwrap_or(0);
+    let end = chars[index..]
+        .iter()
+        .position(|ch| !is_ident(*ch))
+        .map(|at| at + index)
+        .unwrap_or(chars.len());
+    let identifier = chars[start..end].iter().collect::<String>();
+    (!identifier.chars().next()?.is_ascii_digit()).then_some(identifier)
+}
+
+fn shared_dir_depth(a: &str, b: &str) -> usize {
+    let dirs = |path: &str| {
+        let mut parts = path.split('/').collect::<Vec<_>>();
+        parts.pop();
+        parts.into_iter().map(str::to_string).collect::<Vec<_>>()
+    };
+    dirs(a)
+        .iter()
+        .zip(dirs(b).iter())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Declarations named `name`, ranked by closeness to `from_path`: the same file,
+/// then the same language, then the deepest shared directory.
+pub fn find_definitions(
+    symbols: Vec<WorkspaceSymbol>,
+    name: &str,
+    from_path: &str,
+) -> Vec<WorkspaceSymbol> {
+    let language = OutlineLanguage::from_path(from_path).map(OutlineLanguage::name);
+    let mut definitions = symbols
+        .into_iter()
+        .filter(|symbol| symbol.name == name)
+        .collect::<Vec<_>>();
+    definitions.sort_by_cached_key(|symbol| {
+        (
+            symbol.path != from_path,
+            Some(symbol.language.as_str()) != language,
+            std::cmp::Reverse(shared_dir_depth(&symbol.path, from_path)),
+            symbol.path.clone(),
+            symbol.line,
+        )
+    });
+    definitions.truncate(DEFAULT_SYMBOL_LIMIT);
+    definitions
+}
+
+async fn workspace_root(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+) -> Result<PathBuf, String> {
+    let workspaces = workspaces.lock().await;
+    let entry = workspaces
+        .get(workspace_id)
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+    Ok(PathBuf::from(&entry.path))
+}
+
+async fn indexed_symbols<F>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    index: &Arc<SymbolIndex>,
+    workspace_id: &str,
+    list_files: F,
+) -> Result<(PathBuf, usize, Vec<WorkspaceSymbol>), String>
+where
+    F: Fn(&PathBuf, usize) -> Vec<String>,
+{
+    let root = workspace_root(workspaces, workspace_id).await?;
+    let files = list_files(&root, MAX_SYMBOL_FILES)
+        .into_iter()
+        .filter(|path| OutlineLanguage::from_path(path).is_some())
+        .collect::<Vec<_>>();
+    let index = Arc::clone(index);
+    let walk_root = root.clone();
+    tokio::task::spawn_blocking(move || {
+        let symbols = index.refresh(&walk_root, &files);
+        (walk_root, files.len(), symbols)
+    })
+    .await
+    .map_err(|err| err.to_string())
+}
+
+pub async fn workspace_symbols_core<F>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    index: &Arc<SymbolIndex>,
+    workspace_id: &str,
+    query: &str,
+    limit: Option<usize>,
+    list_files: F,
+) -> Result<WorkspaceSymbolsResponse, String>
+where
+    F: Fn(&PathBuf, usize) -> Vec<String>,
+{
+    let limit = limit
+        .unwrap_or(DEFAULT_SYMBOL_LIMIT)
+        .clamp(1, MAX_SYMBOL_LIMIT);
+    let (_, indexed_files, symbols) =
+        indexed_symbols(workspaces, index, workspace_id, list_files).await?;
+    Ok(WorkspaceSymbolsResponse {
+        symbols: search_symbols(symbols, query, limit),
+        indexed_files,
+    })
+}
+
+pub async fn find_definition_core<F>(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    index: &Arc<SymbolIndex>,
+    workspace_id: &str,
+    path: &str,
+    line: usize,
+    column: usize,
+    list_files: F,
+) -> Result<DefinitionLookup, String>
+where
+    F: Fn(&PathBuf, usize) -> Vec<String>,
+{
+    let root = workspace_root(workspaces, workspace_id).await?;
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let canonical_path = canonical_root
+        .join(path)
+        .canonicalize()
+        .map_err(|err| format!("Failed to open {path}: {err}"))?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err("Path is outside the workspace".to_string());
+    }
+    let source = tokio::fs::read(&canonical_path)
+        .await
+        .map_err(|err| format!("Failed to read {path}: {err}"))?;
+    let symbol = String::from_utf8_lossy(&source)
+        .lines()
+        .nth(line.saturating_sub(1))
+        .and_then(|text| identifier_at(text, column));
+    let Some(name) = symbol.clone() else {
+        return Ok(DefinitionLookup {
+            symbol,
+            definitions: Vec::new(),
+        });
+    };
+    let relative = canonical_path
+        .strip_prefix(&canonical_root)
+        .unwrap_or(&canonical_path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    let (_, _, symbols) = indexed_symbols(workspaces, index, workspace_id, list_files).await?;
+    Ok(DefinitionLookup {
+        symbol,
+        definitions: find_definitions(symbols, &name, &relative),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn indexes_workspace_symbols_and_ranks_matches() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-symbols-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create src");
+        std::fs::write(
+            root.join("src/server.rs"),
+            "pub struct HttpServer;\n\nimpl HttpServer {\n    pub fn start(&self) {}\n}\n",
+        )
+        .expect("write server");
+        std::fs::write(root.join("src/app.py"), "def start():\n    pass\n").expect("write app");
+        std::fs::write(root.join("notes.txt"), "start").expect("write notes");
+        let files = vec![
+            "src/server.rs".to_string(),
+            "src/app.py".to_string(),
+            "notes.txt".to_string(),
+        ];
+        let index = SymbolIndex::default();
+
+        let symbols = index.refresh(&root, &files);
+        assert_eq!(symbols.len(), 4);
+        let found = search_symbols(symbols.clone(), "hsrv", 10);
+        assert_eq!(
+            found.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            vec!["HttpServer", "HttpServer"]
+        );
+        let starts = search_symbols(symbols.clone(), "start", 10);
+        assert_eq!(starts.len(), 2);
+        assert!(starts.iter().any(|symbol| symbol.kind == SymbolKind::Method
+            && symbol.container.as_deref() == Some("HttpServer")
+            && (symbol.line, symbol.column) == (4, 12)));
+
+        let definitions = find_definitions(symbols, "start", "src/main.py");
+        assert_eq!(definitions[0].path, "src/app.py");
+        assert_eq!(definitions[1].path, "src/server.rs");
+
+        std::fs::write(root.join("src/app.py"), "def stop():\n    pass\n").expect("rewrite app");
+        let refreshed = index.refresh(&root, &files[..2]);
+        assert!(refreshed.iter().any(|symbol| symbol.name == "stop"));
+        assert!(!refreshed
+            .iter()
+            .any(|symbol| symbol.language == "python" && symbol.name == "start"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn finds_identifier_under_cursor() {
+        let line = "    let server = HttpServer::new($port);";
+        assert_eq!(identifier_at(line, 18).as_deref(), Some("HttpServer"));
+        assert_eq!(identifier_at(line, 27).as_deref(), Some("HttpServer"));
+        assert_eq!(identifier_at(line, 33).as_deref(), Some("new"));
+        assert_eq!(identifier_at(line, 34).as_deref(), Some("$port"));
+        assert_eq!(identifier_at(line, 2), None);
+        assert_eq!(identifier_at("x = 42", 6), None);
+    }
+}
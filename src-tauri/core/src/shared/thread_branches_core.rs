@@ -0,0 +1,362 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::storage::write_thread_branches;
+use crate::types::{ThreadBranchRecord, WorkspaceEntry};
+
+pub const THREAD_BRANCHES_FILE: &str = "thread-branches.json";
+
+fn current_branch(path: &Path) -> Option<String> {
+    let repo = git2::Repository::discover(path).ok()?;
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    head.shorthand().map(|value| value.to_string())
+}
+
+/// A worktree's recorded branch, otherwise the branch checked out at the workspace path.
+pub fn workspace_branch(entry: &WorkspaceEntry) -> Option<String> {
+    entry
+        .worktree
+        .as_ref()
+        .map(|worktree| worktree.branch.clone())
+        .or_else(|| current_branch(Path::new(&entry.path)))
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn thread_id_from_response(response: &Value) -> Option<String> {
+    let result = response.get("result").unwrap_or(response);
+    result
+        .get("thread")
+        .and_then(|thread| thread.get("id"))
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string())
+}
+
+/// Id of the last turn in a `thread/start` or `thread/fork` response, if turns are included.
+fn last_turn_id(response: &Value) -> Option<String> {
+    let result = response.get("result").unwrap_or(response);
+    result
+        .get("thread")
+        .and_then(|thread| thread.get("turns"))
+        .and_then(|turns| turns.as_array())
+        .and_then(|turns| turns.last())
+        .and_then(|turn| turn.get("id"))
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string())
+}
+
+fn build_record(
+    entry: &WorkspaceEntry,
+    cwd: Option<String>,
+    parent_thread_id: Option<String>,
+    fork_turn_id: Option<String>,
+) -> ThreadBranchRecord {
+    let branch = workspace_branch(entry);
+    ThreadBranchRecord {
+        workspace_id: entry.id.clone(),
+        branch,
+        worktree_path: entry
+            .kind
+            .is_worktree()
+            .then(|| entry.path.clone()),
+        started_at: now_secs(),
+        cwd,
+        parent_thread_id,
+        fork_turn_id,
+    }
+}
+
+pub fn thread_list_data(response: &mut Value) -> Option<&mut Vec<Value>> {
+    let result = if response.get("result").is_some() {
+        &mut response["result"]
+    } else {
+        response
+    };
+    result.get_mut("data").and_then(|data| data.as_array_mut())
+}
+
+/// Remembers the branch (and directory scope) a new or forked thread was started on.
+/// `forked_from` is the parent thread for forks, so `thread_graph` can rebuild lineage.
+pub async fn record_thread_branch_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    thread_branches: &Mutex<HashMap<String, ThreadBranchRecord>>,
+    thread_branches_path: &PathBuf,
+    workspace_id: &str,
+    response: &Value,
+    cwd: Option<String>,
+    forked_from: Option<String>,
+) -> Result<(), String> {
+    let Some(thread_id) = thread_id_from_response(response) else {
+        return Ok(());
+    };
+    let entry = {
+        let workspaces = workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
+    };
+    let fork_turn_id = forked_from.as_ref().and_then(|_| last_turn_id(response));
+    let record = build_record(&entry, cwd, forked_from, fork_turn_id);
+    let mut thread_branches = thread_branches.lock().await;
+    thread_branches.insert(thread_id, record);
+    write_thread_branches(thread_branches_path, &thread_branches)
+}
+
+/// Directory scope a thread was started with, if any.
+pub async fn thread_cwd_core(
+    thread_branches: &Mutex<HashMap<String, ThreadBranchRecord>>,
+    thread_id: &str,
+) -> Option<String> {
+    thread_branches
+        .lock()
+        .await
+        .get(thread_id)
+        .and_then(|record| record.cwd.clone())
+}
+
+/// Adds `branchInfo` to each thread in a `thread/list` response, optionally keeping only
+/// threads started on `branch`. Filtering applies per page, so pages may come back short.
+pub async fn enrich_thread_list_core(
+    thread_branches: &Mutex<HashMap<String, ThreadBranchRecord>>,
+    mut response: Value,
+    branch: Option<&str>,
+) -> Value {
+    let thread_branches = thread_branches.lock().await;
+    let Some(data) = thread_list_data(&mut response) else {
+        return response;
+    };
+    let branch = branch.map(str::trim).filter(|value| !value.is_empty());
+    data.retain_mut(|thread| {
+        let record = thread
+            .get("id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| thread_branches.get(id))
+            .cloned();
+        if let Some(filter) = branch {
+            let matches = record
+                .as_ref()
+                .and_then(|record| record.branch.as_deref())
+                == Some(filter);
+            if !matches {
+                return false;
+            }
+        }
+        if let (Some(record), Some(thread)) = (record, thread.as_object_mut()) {
+            if let Ok(value) = serde_json::to_value(record) {
+                thread.insert("branchInfo".to_string(), value);
+            }
+        }
+        true
+    });
+    response
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadGraphNode {
+    pub thread_id: String,
+    pub parent_thread_id: Option<String>,
+    pub fork_turn_id: Option<String>,
+    pub branch: Option<String>,
+    pub started_at: u64,
+    /// Forks of this thread, oldest first.
+    pub children: Vec<ThreadGraphNode>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadGraph {
+    pub workspace_id: String,
+    /// Threads without a recorded parent in this workspace. A root can still carry a
+    /// `parentThreadId` when the parent was started before lineage was tracked.
+    pub roots: Vec<ThreadGraphNode>,
+}
+
+fn graph_node(
+    thread_id: &str,
+    record: &ThreadBranchRecord,
+    children_of: &HashMap<&str, Vec<(&str, &ThreadBranchRecord)>>,
+) -> ThreadGraphNode {
+    let children = children_of
+        .get(thread_id)
+        .map(|children| {
+            children
+                .iter()
+                .map(|(child_id, child)| graph_node(child_id, child, children_of))
+                .collect()
+        })
+        .unwrap_or_default();
+    ThreadGraphNode {
+        thread_id: thread_id.to_string(),
+        parent_thread_id: record.parent_thread_id.clone(),
+        fork_turn_id: record.fork_turn_id.clone(),
+        branch: record.branch.clone(),
+        started_at: record.started_at,
+        children,
+    }
+}
+
+/// Fork tree of the threads recorded for a workspace.
+pub async fn thread_graph_core(
+    thread_branches: &Mutex<HashMap<String, ThreadBranchRecord>>,
+    workspace_id: &str,
+) -> ThreadGraph {
+    let thread_branches = thread_branches.lock().await;
+    let mut records = thread_branches
+        .iter()
+        .filter(|(_, record)| record.workspace_id == workspace_id)
+        .map(|(thread_id, record)| (thread_id.as_str(), record))
+        .collect::<Vec<_>>();
+    records.sort_by(|a, b| a.1.started_at.cmp(&b.1.started_at).then(a.0.cmp(b.0)));
+    let known = records
+        .iter()
+        .map(|(thread_id, _)| *thread_id)
+        .collect::<HashSet<_>>();
+    let mut roots = Vec::new();
+    let mut children_of: HashMap<&str, Vec<(&str, &ThreadBranchRecord)>> = HashMap::new();
+    for (thread_id, record) in records {
+        match record.parent_thread_id.as_deref() {
+            Some(parent) if known.contains(parent) && parent != thread_id => {
+                children_of
+                    .entry(parent)
+                    .or_default()
+                    .push((thread_id, record));
+            }
+            _ => roots.push((thread_id, record)),
+        }
+    }
+    ThreadGraph {
+        workspace_id: workspace_id.to_string(),
+        roots: roots
+            .into_iter()
+            .map(|(thread_id, record)| graph_node(thread_id, record, &children_of))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(branch: &str) -> ThreadBranchRecord {
+        ThreadBranchRecord {
+            workspace_id: "ws-1".to_string(),
+            branch: Some(branch.to_string()),
+            worktree_path: None,
+            started_at: 1,
+            cwd: None,
+            parent_thread_id: None,
+            fork_turn_id: None,
+        }
+    }
+
+    #[test]
+    fn extracts_thread_id_from_start_response() {
+        let response = json!({ "result": { "thread": { "id": "thr-1" } } });
+        assert_eq!(thread_id_from_response(&response), Some("thr-1".to_string()));
+        assert_eq!(thread_id_from_response(&json!({ "result": {} })), None);
+    }
+
+    #[test]
+    fn enrich_adds_branch_info_and_filters() {
+        let thread_branches = Mutex::new(HashMap::from([
+            ("a".to_string(), record("main")),
+            ("b".to_string(), record("feature/x")),
+        ]));
+        let response = json!({
+            "result": { "data": [{ "id": "a" }, { "id": "b" }, { "id": "c" }] }
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+
+        let all = runtime.block_on(enrich_thread_list_core(
+            &thread_branches,
+            response.clone(),
+            None,
+        ));
+        let data = all["result"]["data"].as_array().expect("data");
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0]["branchInfo"]["branch"], json!("main"));
+        assert!(data[2].get("branchInfo").is_none());
+
+        let filtered = runtime.block_on(enrich_thread_list_core(
+            &thread_branches,
+            response,
+            Some("feature/x"),
+        ));
+        let data = filtered["result"]["data"].as_array().expect("data");
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0]["id"], json!("b"));
+    }
+
+    #[test]
+    fn graph_nests_forks_under_their_parents() {
+        let fork = |parent: &str, started_at: u64| ThreadBranchRecord {
+            parent_thread_id: Some(parent.to_string()),
+            fork_turn_id: Some(format!("turn-{started_at}")),
+            started_at,
+            ..record("main")
+        };
+        let mut other_workspace = record("main");
+        other_workspace.workspace_id = "ws-2".to_string();
+        let thread_branches = Mutex::new(HashMap::from([
+            ("root".to_string(), record("main")),
+            ("fork-b".to_string(), fork("root", 3)),
+            ("fork-a".to_string(), fork("root", 2)),
+            ("nested".to_string(), fork("fork-a", 4)),
+            ("orphan".to_string(), fork("untracked", 5)),
+            ("elsewhere".to_string(), other_workspace),
+        ]));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+
+        let graph = runtime.block_on(thread_graph_core(&thread_branches, "ws-1"));
+        let roots = graph
+            .roots
+            .iter()
+            .map(|node| node.thread_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(roots, vec!["root", "orphan"]);
+        let children = graph.roots[0]
+            .children
+            .iter()
+            .map(|node| node.thread_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(children, vec!["fork-a", "fork-b"]);
+        assert_eq!(graph.roots[0].children[0].children[0].thread_id, "nested");
+        assert_eq!(
+            graph.roots[0].children[0].fork_turn_id.as_deref(),
+            Some("turn-2")
+        );
+        assert_eq!(
+            graph.roots[1].parent_thread_id.as_deref(),
+            Some("untracked")
+        );
+    }
+
+    #[test]
+    fn reads_last_turn_of_fork_response() {
+        let response = json!({
+            "result": { "thread": { "id": "thr-2", "turns": [{ "id": "t1" }, { "id": "t2" }] } }
+        });
+        assert_eq!(last_turn_id(&response), Some("t2".to_string()));
+        assert_eq!(last_turn_id(&json!({ "result": { "thread": {} } })), None);
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::shared::thread_branches_core::thread_list_data;
+
+pub const THREAD_USAGE_FILE: &str = "thread-usage.json";
+
+/// Running totals for one thread, kept up to date from app-server events.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadUsage {
+    /// Completed user and agent messages.
+    pub message_count: u64,
+    pub last_activity_at: Option<u64>,
+    pub total_tokens: u64,
+    /// Branches messages were sent from, in the order they were first used.
+    pub branches: Vec<String>,
+}
+
+/// Per-thread usage persisted in `<data-dir>/thread-usage.json`. Counts only cover what
+/// this backend saw, so threads started elsewhere begin at zero.
+pub struct ThreadUsageStats {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, ThreadUsage>>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl ThreadUsageStats {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(THREAD_USAGE_FILE);
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ThreadUsage>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, entries: &HashMap<String, ThreadUsage>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(entries).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, data).map_err(|err| err.to_string())
+    }
+
+    /// Folds one app-server event into the thread's totals. Totals are written to disk
+    /// when a turn completes rather than on every event.
+    pub fn observe(&self, message: &Value) {
+        let method = message.get("method").and_then(Value::as_str);
+        let Some(params) = message.get("params") else {
+            return;
+        };
+        let Some(thread_id) = params
+            .get("threadId")
+            .or_else(|| params.get("thread_id"))
+            .and_then(Value::as_str)
+        else {
+            return;
+        };
+        let mut entries = self.lock();
+        match method {
+            Some("item/completed") => {
+                let kind = params
+                    .get("item")
+                    .and_then(|item| item.get("type"))
+                    .and_then(Value::as_str);
+                if !matches!(kind, Some("userMessage" | "agentMessage")) {
+                    return;
+                }
+                let usage = entries.entry(thread_id.to_string()).or_default();
+                usage.message_count += 1;
+                usage.last_activity_at = Some(now_millis());
+            }
+            Some("thread/tokenUsage/updated") => {
+                let Some(total) = params
+                    .get("tokenUsage")
+                    .or_else(|| params.get("token_usage"))
+                    .and_then(|usage| usage.get("total"))
+                    .and_then(|total| {
+                        total.get("totalTokens").or_else(|| total.get("total_tokens"))
+                    })
+                    .and_then(Value::as_u64)
+                else {
+                    return;
+                };
+                entries.entry(thread_id.to_string()).or_default().total_tokens = total;
+            }
+            Some("turn/started") => {
+                entries.entry(thread_id.to_string()).or_default().last_activity_at =
+                    Some(now_millis());
+            }
+            Some("turn/completed") => {
+                entries.entry(thread_id.to_string()).or_default().last_activity_at =
+                    Some(now_millis());
+                if let Err(err) = self.persist(&entries) {
+                    tracing::warn!(
+                        path = %self.path.display(),
+                        "failed to save thread usage: {err}"
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Notes the branch a message was sent from.
+    pub fn record_branch(&self, thread_id: &str, branch: Option<String>) {
+        let Some(branch) = branch else {
+            return;
+        };
+        let mut entries = self.lock();
+        let usage = entries.entry(thread_id.to_string()).or_default();
+        if !usage.branches.contains(&branch) {
+            usage.branches.push(branch);
+        }
+    }
+
+    pub fn get(&self, thread_id: &str) -> Option<ThreadUsage> {
+        self.lock().get(thread_id).cloned()
+    }
+
+    /// Adds `usage` to each thread in a `thread/list` response; threads this backend
+    /// hasn't seen get zeroed totals.
+    pub fn enrich_thread_list(&self, mut response: Value) -> Value {
+        let Some(data) = thread_list_data(&mut response) else {
+            return response;
+        };
+        let entries = self.lock();
+        for thread in data.iter_mut() {
+            let usage = thread
+                .get("id")
+                .and_then(Value::as_str)
+                .and_then(|id| entries.get(id))
+                .cloned()
+                .unwrap_or_default();
+            if let (Some(thread), Ok(usage)) = (thread.as_object_mut(), serde_json::to_value(usage))
+            {
+                thread.insert("usage".to_string(), usage);
+            }
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn folds_events_into_per_thread_totals_and_persists_them() {
+        let data_dir =
+            std::env::temp_dir().join(format!("codex-monitor-thread-usage-{}", Uuid::new_v4()));
+        let stats = ThreadUsageStats::load(&data_dir);
+        stats.observe(&json!({
+            "method": "turn/started",
+            "params": { "threadId": "thr-1", "turn": { "id": "turn-1" } },
+        }));
+        for kind in ["userMessage", "reasoning", "agentMessage"] {
+            stats.observe(&json!({
+                "method": "item/completed",
+                "params": { "threadId": "thr-1", "item": { "type": kind } },
+            }));
+        }
+        stats.observe(&json!({
+            "method": "thread/tokenUsage/updated",
+            "params": { "threadId": "thr-1", "tokenUsage": { "total": { "totalTokens": 1200 } } },
+        }));
+        stats.record_branch("thr-1", Some("main".to_string()));
+        stats.record_branch("thr-1", Some("feature/x".to_string()));
+        stats.record_branch("thr-1", Some("main".to_string()));
+        stats.observe(&json!({
+            "method": "turn/completed",
+            "params": { "threadId": "thr-1", "turn": { "id": "turn-1" } },
+        }));
+
+        let usage = ThreadUsageStats::load(&data_dir).get("thr-1").expect("persisted");
+        assert_eq!(usage.message_count, 2);
+        assert_eq!(usage.total_tokens, 1200);
+        assert_eq!(usage.branches, vec!["main", "feature/x"]);
+        assert!(usage.last_activity_at.is_some());
+
+        let listed = stats.enrich_thread_list(json!({
+            "result": { "data": [{ "id": "thr-1" }, { "id": "thr-2" }] },
+        }));
+        assert_eq!(listed["result"]["data"][0]["usage"]["messageCount"], json!(2));
+        assert_eq!(listed["result"]["data"][1]["usage"]["totalTokens"], json!(0));
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
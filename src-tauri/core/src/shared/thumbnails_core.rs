@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use crate::files::policy::FileScope;
+use crate::shared::files_core::resolve_root_core;
+use crate::types::WorkspaceEntry;
+
+/// Directory under the data dir holding the generated previews.
+pub const THUMBNAILS_DIR: &str = "thumbnails";
+/// Longest edge of a preview, in pixels.
+pub const THUMBNAIL_MAX_EDGE: u32 = 256;
+const MAX_SOURCE_BYTES: u64 = 32 * 1024 * 1024;
+const MAX_SOURCE_PIXELS: u64 = 64 * 1024 * 1024;
+const MAX_CACHED_THUMBNAILS: usize = 512;
+const JPEG_QUALITY: u8 = 80;
+const IMAGE_EXTENSIONS: &[&str] = &["gif", "jpeg", "jpg", "png", "webp"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Thumbnail {
+    /// Relative to the workspace root.
+    pub path: String,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub original_width: u32,
+    pub original_height: u32,
+    pub original_bytes: u64,
+    pub data_url: String,
+}
+
+/// Params of the `thumbnail-ready` event.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailReady {
+    pub workspace_id: String,
+    pub thread_id: Option<String>,
+    pub item_id: Option<String>,
+    #[serde(flatten)]
+    pub thumbnail: Thumbnail,
+}
+
+/// An image file an app-server item wrote or looked at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageArtifact {
+    pub thread_id: Option<String>,
+    pub item_id: Option<String>,
+    pub path: String,
+}
+
+pub fn is_image_path(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn change_kind(change: &Value) -> Option<&str> {
+    let kind = change.get("kind")?;
+    kind.as_str()
+        .or_else(|| kind.get("type").and_then(Value::as_str))
+}
+
+/// Paths a `fileChange` item touched, each with whether it was deleted. `changes` is a
+/// list of `{ path, kind }` or a map from path to `{ kind }`.
+pub fn file_change_paths(item: &Value) -> Vec<(&str, bool)> {
+    let deleted = |change: &Value| change_kind(change) == Some("delete");
+    match item.get("changes") {
+        Some(Value::Array(changes)) => changes
+            .iter()
+            .filter_map(|change| {
+                let path = change.get("path").and_then(Value::as_str)?;
+                Some((path, deleted(change)))
+            })
+            .collect(),
+        Some(Value::Object(changes)) => changes
+            .iter()
+            .map(|(path, change)| (path.as_str(), deleted(change)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Images produced by a completed item: files a `fileChange` added or updated, the
+/// file behind an `imageView`, and the saved path of any other image item.
+pub fn image_artifacts(message: &Value) -> Vec<ImageArtifact> {
+    if message.get("method").and_then(Value::as_str) != Some("item/completed") {
+        return Vec::new();
+    }
+    let Some(params) = message.get("params") else {
+        return Vec::new();
+    };
+    let Some(item) = params.get("item") else {
+        return Vec::new();
+    };
+    let status = item.get("status").and_then(Value::as_str).unwrap_or("");
+    if matches!(status, "failed" | "declined") {
+        return Vec::new();
+    }
+    let item_type = item.get("type").and_then(Value::as_str).unwrap_or("");
+    let mut paths = Vec::new();
+    if item_type == "fileChange" {
+        paths.extend(
+            file_change_paths(item)
+                .into_iter()
+                .filter(|(_, deleted)| !deleted)
+                .map(|(path, _)| path),
+        );
+    } else if item_type.to_ascii_lowercase().contains("image") {
+        paths.extend(
+            ["savedPath", "path"]
+                .iter()
+                .find_map(|key| item.get(*key).and_then(Value::as_str)),
+        );
+    }
+
+    let thread_id = params
+        .get("threadId")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let item_id = item.get("id").and_then(Value::as_str).map(str::to_string);
+    let mut artifacts: Vec<ImageArtifact> = Vec::new();
+    for path in paths.into_iter().filter(|path| is_image_path(path)) {
+        if artifacts.iter().any(|artifact| artifact.path == path) {
+            continue;
+        }
+        artifacts.push(ImageArtifact {
+            thread_id: thread_id.clone(),
+            item_id: item_id.clone(),
+            path: path.to_string(),
+        });
+    }
+    artifacts
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn cache_key(path: &Path, metadata: &std::fs::Metadata, max_edge: u32) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    let mut hasher = Sha256::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(format!("\0{}\0{modified}\0{max_edge}", metadata.len()).as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Scales `image` to fit `max_edge` and encodes it as PNG when it has an alpha
+/// channel, JPEG otherwise. Images already small enough keep their size.
+pub fn encode_thumbnail(
+    image: &DynamicImage,
+    max_edge: u32,
+) -> Result<(Vec<u8>, &'static str, u32, u32), String> {
+    let scaled = if image.width() > max_edge || image.height() > max_edge {
+        image.thumbnail(max_edge, max_edge)
+    } else {
+        image.clone()
+    };
+    let mut bytes = Vec::new();
+    let mime = if scaled.color().has_alpha() {
+        scaled
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .map_err(|err| err.to_string())?;
+        "image/png"
+    } else {
+        scaled
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))
+            .map_err(|err| err.to_string())?;
+        "image/jpeg"
+    };
+    Ok((bytes, mime, scaled.width(), scaled.height()))
+}
+
+fn cached_extension(mime: &str) -> &'static str {
+    if mime == "image/png" {
+        "png"
+    } else {
+        "jpg"
+    }
+}
+
+fn read_cached(cache_dir: &Path, key: &str) -> Option<(Vec<u8>, &'static str, u32, u32)> {
+    for mime in ["image/png", "image/jpeg"] {
+        let path = cache_dir.join(format!("{key}.{}", cached_extension(mime)));
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        let (width, height) = ImageReader::new(Cursor::new(&bytes))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok()?;
+        return Some((bytes, mime, width, height));
+    }
+    None
+}
+
+/// Keeps the cache to the newest `MAX_CACHED_THUMBNAILS` files.
+fn prune_cache(cache_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return;
+    };
+    let mut files = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect::<Vec<_>>();
+    if files.len() <= MAX_CACHED_THUMBNAILS {
+        return;
+    }
+    files.sort();
+    let excess = files.len() - MAX_CACHED_THUMBNAILS;
+    for (_, path) in files.into_iter().take(excess) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Builds (or reuses) the preview of an image inside `root`. Paths outside the
+/// workspace are refused so a client can't read arbitrary files through previews.
+pub fn thumbnail_for_path(
+    cache_dir: &Path,
+    root: &Path,
+    path: &str,
+    max_edge: u32,
+) -> Result<Thumbnail, String> {
+    if !is_image_path(path) {
+        return Err(format!("Not a supported image: {path}"));
+    }
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let canonical_path = canonical_root
+        .join(path)
+        .canonicalize()
+        .map_err(|err| format!("Failed to open {path}: {err}"))?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err("Image path is outside the workspace".to_string());
+    }
+    let metadata = std::fs::metadata(&canonical_path).map_err(|err| err.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {path}"));
+    }
+    if metadata.len() > MAX_SOURCE_BYTES {
+        return Err(format!("Image is larger than {MAX_SOURCE_BYTES} bytes"));
+    }
+
+    let open = || {
+        ImageReader::open(&canonical_path)
+            .map_err(|err| err.to_string())?
+            .with_guessed_format()
+            .map_err(|err| err.to_string())
+    };
+    let (original_width, original_height) = open()?
+        .into_dimensions()
+        .map_err(|err| format!("Failed to read {path}: {err}"))?;
+    if u64::from(original_width) * u64::from(original_height) > MAX_SOURCE_PIXELS {
+        return Err(format!(
+            "Image is too large to preview ({original_width}x{original_height})"
+        ));
+    }
+
+    let key = cache_key(&canonical_path, &metadata, max_edge);
+    let (bytes, mime, width, height) = match read_cached(cache_dir, &key) {
+        Some(cached) => cached,
+        None => {
+            let mut limits = Limits::default();
+            limits.max_alloc = Some(MAX_SOURCE_PIXELS * 4);
+            let mut reader = open()?;
+            reader.limits(limits);
+            let image = reader
+                .decode()
+                .map_err(|err| format!("Failed to decode {path}: {err}"))?;
+            let encoded = encode_thumbnail(&image, max_edge)?;
+            if std::fs::create_dir_all(cache_dir).is_ok() {
+                let cached = cache_dir.join(format!("{key}.{}", cached_extension(encoded.1)));
+                if std::fs::write(cached, &encoded.0).is_ok() {
+                    prune_cache(cache_dir);
+                }
+            }
+            encoded
+        }
+    };
+
+    Ok(Thumbnail {
+        path: relative_path(&canonical_root, &canonical_path),
+        mime: mime.to_string(),
+        width,
+        height,
+        original_width,
+        original_height,
+        original_bytes: metadata.len(),
+        data_url: format!(
+            "data:{mime};base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ),
+    })
+}
+
+pub async fn thumbnail_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    cache_dir: PathBuf,
+    workspace_id: &str,
+    path: String,
+) -> Result<Thumbnail, String> {
+    let root = resolve_root_core(workspaces, FileScope::Workspace, Some(workspace_id)).await?;
+    tokio::task::spawn_blocking(move || {
+        thumbnail_for_path(&cache_dir, &root, &path, THUMBNAIL_MAX_EDGE)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn finds_images_in_completed_items() {
+        let artifacts = image_artifacts(&json!({
+            "method": "item/completed",
+            "params": {
+                "threadId": "thread-1",
+                "item": {
+                    "type": "fileChange",
+                    "id": "item-1",
+                    "status": "completed",
+                    "changes": [
+                        { "path": "out/chart.png", "kind": { "type": "add" } },
+                        { "path": "out/old.jpg", "kind": { "type": "delete" } },
+                        { "path": "src/main.rs", "kind": { "type": "update" } },
+                        { "path": "out/chart.png", "kind": { "type": "update" } }
+                    ]
+                }
+            }
+        }));
+        assert_eq!(
+            artifacts,
+            vec![ImageArtifact {
+                thread_id: Some("thread-1".to_string()),
+                item_id: Some("item-1".to_string()),
+                path: "out/chart.png".to_string(),
+            }]
+        );
+
+        let viewed = image_artifacts(&json!({
+            "method": "item/completed",
+            "params": { "item": { "type": "imageView", "id": "item-2", "path": "shot.WEBP" } }
+        }));
+        assert_eq!(viewed.len(), 1);
+        assert_eq!(viewed[0].path, "shot.WEBP");
+
+        assert!(image_artifacts(&json!({
+            "method": "item/started",
+            "params": { "item": { "type": "imageView", "path": "shot.png" } }
+        }))
+        .is_empty());
+        assert!(image_artifacts(&json!({
+            "method": "item/completed",
+            "params": {
+                "item": {
+                    "type": "fileChange",
+                    "status": "declined",
+                    "changes": [{ "path": "a.png", "kind": "add" }]
+                }
+            }
+        }))
+        .is_empty());
+    }
+
+    #[test]
+    fn scales_and_caches_previews() {
+        let root = std::env::temp_dir().join(format!("fridex-thumbs-{}", Uuid::new_v4()));
+        let cache = root.join(".cache");
+        std::fs::create_dir_all(root.join("out")).expect("create root");
+        RgbImage::from_pixel(1024, 512, Rgb([200, 10, 10]))
+            .save(root.join("out/wide.png"))
+            .expect("save opaque");
+        RgbaImage::from_pixel(40, 30, Rgba([0, 0, 0, 128]))
+            .save(root.join("small.png"))
+            .expect("save transparent");
+
+        let wide = thumbnail_for_path(&cache, &root, "out/wide.png", 256).expect("wide");
+        assert_eq!(wide.path, "out/wide.png");
+        assert_eq!((wide.width, wide.height), (256, 128));
+        assert_eq!((wide.original_width, wide.original_height), (1024, 512));
+        assert_eq!(wide.mime, "image/jpeg");
+        assert!(wide.data_url.starts_with("data:image/jpeg;base64,"));
+        assert_eq!(std::fs::read_dir(&cache).expect("cache").count(), 1);
+        assert_eq!(
+            thumbnail_for_path(&cache, &root, "out/wide.png", 256).expect("cached"),
+            wide
+        );
+
+        let small = thumbnail_for_path(&cache, &root, "small.png", 256).expect("small");
+        assert_eq!((small.width, small.height), (40, 30));
+        assert_eq!(small.mime, "image/png");
+
+        assert!(thumbnail_for_path(&cache, &root.join("out"), "../small.png", 256).is_err());
+        assert!(thumbnail_for_path(&cache, &root, "notes.txt", 256).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,509 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const TIME_TRACKING_FILE: &str = "time-tracking.json";
+
+/// Activity further apart than this starts a new span; the idle gap isn't counted.
+pub const IDLE_GAP_MS: u64 = 5 * 60 * 1000;
+
+/// A stretch of continuous activity in one workspace, or one of its threads.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSpan {
+    pub workspace_id: String,
+    /// `None` for focus pings that didn't name a thread.
+    pub thread_id: Option<String>,
+    pub started_at: u64,
+    pub ended_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimePeriod {
+    #[default]
+    Day,
+    /// Monday to Sunday.
+    Week,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadTime {
+    pub thread_id: String,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceTime {
+    pub workspace_id: String,
+    /// Time with any activity in the workspace, so overlapping threads count once.
+    pub total_ms: u64,
+    pub threads: Vec<ThreadTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeBucket {
+    /// First local day of the bucket, `YYYY-MM-DD`.
+    pub start: String,
+    /// Wall-clock time with any activity; less than the sum of the workspaces when
+    /// they overlap.
+    pub total_ms: u64,
+    pub workspaces: Vec<WorkspaceTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSummary {
+    pub period: TimePeriod,
+    /// Oldest first, ending with the current day or week.
+    pub buckets: Vec<TimeBucket>,
+    pub total_ms: u64,
+}
+
+type SpanKey = (String, Option<String>);
+
+#[derive(Default)]
+struct TrackerState {
+    spans: Vec<TimeSpan>,
+    /// Index in `spans` of each key's latest span.
+    latest: HashMap<SpanKey, usize>,
+    /// Threads with a turn in progress; their spans bridge idle gaps until it completes.
+    running: HashSet<SpanKey>,
+}
+
+impl TrackerState {
+    fn new(spans: Vec<TimeSpan>) -> Self {
+        let latest = spans
+            .iter()
+            .enumerate()
+            .map(|(index, span)| ((span.workspace_id.clone(), span.thread_id.clone()), index))
+            .collect();
+        Self {
+            spans,
+            latest,
+            running: HashSet::new(),
+        }
+    }
+
+    /// Records activity at `at`, extending the key's latest span when it is recent
+    /// enough or its turn is still running.
+    fn touch(&mut self, key: SpanKey, at: u64) {
+        if let Some(&index) = self.latest.get(&key) {
+            let span = &mut self.spans[index];
+            if at <= span.ended_at {
+                return;
+            }
+            if self.running.contains(&key) || at - span.ended_at <= IDLE_GAP_MS {
+                span.ended_at = at;
+                return;
+            }
+        }
+        self.spans.push(TimeSpan {
+            workspace_id: key.0.clone(),
+            thread_id: key.1.clone(),
+            started_at: at,
+            ended_at: at,
+        });
+        self.latest.insert(key, self.spans.len() - 1);
+    }
+}
+
+/// Active time per workspace and thread, persisted in `<data-dir>/time-tracking.json`.
+/// Time counts from a turn starting to it completing, and between focus pings that are
+/// at most [`IDLE_GAP_MS`] apart.
+pub struct TimeTracker {
+    path: PathBuf,
+    state: Mutex<TrackerState>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl TimeTracker {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(TIME_TRACKING_FILE);
+        let spans = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            state: Mutex::new(TrackerState::new(spans)),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TrackerState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, state: &TrackerState) {
+        let result = (|| {
+            if let Some(parent) = self.path.parent() {
+                std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+            }
+            let data = serde_json::to_string(&state.spans).map_err(|err| err.to_string())?;
+            std::fs::write(&self.path, data).map_err(|err| err.to_string())
+        })();
+        if let Err(err) = result {
+            tracing::warn!(
+                path = %self.path.display(),
+                "failed to save time tracking: {err}"
+            );
+        }
+    }
+
+    /// Folds one app-server event of `workspace_id` into its threads' time. Spans are
+    /// written to disk when a turn completes.
+    pub fn observe(&self, workspace_id: &str, message: &Value) {
+        let method = message.get("method").and_then(Value::as_str);
+        if method == Some("codex/connected") {
+            // A new app-server process has no turns running.
+            self.lock().running.retain(|key| key.0 != workspace_id);
+            return;
+        }
+        let Some(thread_id) = message
+            .get("params")
+            .and_then(|params| params.get("threadId").or_else(|| params.get("thread_id")))
+            .and_then(Value::as_str)
+        else {
+            return;
+        };
+        let key = (workspace_id.to_string(), Some(thread_id.to_string()));
+        let now = now_millis();
+        let mut state = self.lock();
+        match method {
+            Some("turn/started") => {
+                state.touch(key.clone(), now);
+                state.running.insert(key);
+            }
+            Some("turn/completed") => {
+                state.touch(key.clone(), now);
+                state.running.remove(&key);
+                self.persist(&state);
+            }
+            _ if state.running.contains(&key) => state.touch(key, now),
+            _ => {}
+        }
+    }
+
+    /// Records that a client has the workspace, and optionally one of its threads, in
+    /// focus.
+    pub fn record_focus(&self, workspace_id: &str, thread_id: Option<&str>) {
+        let mut state = self.lock();
+        state.touch(
+            (workspace_id.to_string(), thread_id.map(str::to_string)),
+            now_millis(),
+        );
+        self.persist(&state);
+    }
+
+    /// Threads of `workspace_id` with a turn running right now.
+    pub fn running_threads(&self, workspace_id: &str) -> usize {
+        self.lock()
+            .running
+            .iter()
+            .filter(|key| key.0 == workspace_id)
+            .count()
+    }
+
+    /// The last `count` days or weeks in local time, optionally for one workspace.
+    /// Running turns count up to now.
+    pub fn summary(
+        &self,
+        period: TimePeriod,
+        count: Option<u32>,
+        workspace_id: Option<&str>,
+    ) -> TimeSummary {
+        let now = now_millis();
+        let spans = {
+            let state = self.lock();
+            state
+                .spans
+                .iter()
+                .enumerate()
+                .filter(|(_, span)| workspace_id.is_none_or(|id| span.workspace_id == id))
+                .map(|(index, span)| {
+                    let key = (span.workspace_id.clone(), span.thread_id.clone());
+                    let mut span = span.clone();
+                    if state.running.contains(&key) && state.latest.get(&key) == Some(&index) {
+                        span.ended_at = span.ended_at.max(now);
+                    }
+                    span
+                })
+                .collect::<Vec<_>>()
+        };
+        summarize(&spans, period, count, Local::now().date_naive(), &Local)
+    }
+}
+
+/// Milliseconds of `intervals` after merging overlaps.
+fn union_ms(mut intervals: Vec<(u64, u64)>) -> u64 {
+    intervals.sort_unstable();
+    let mut total = 0;
+    let mut current: Option<(u64, u64)> = None;
+    for (start, end) in intervals {
+        match current {
+            Some((_, current_end)) if start <= current_end => {
+                current = current.map(|(current_start, _)| (current_start, current_end.max(end)));
+            }
+            _ => {
+                if let Some((current_start, current_end)) = current {
+                    total += current_end - current_start;
+                }
+                current = Some((start, end));
+            }
+        }
+    }
+    total + current.map_or(0, |(start, end)| end - start)
+}
+
+fn day_start_ms<Tz: TimeZone>(day: NaiveDate, tz: &Tz) -> u64 {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    tz.from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| tz.from_utc_datetime(&midnight))
+        .timestamp_millis()
+        .max(0) as u64
+}
+
+fn summarize<Tz: TimeZone>(
+    spans: &[TimeSpan],
+    period: TimePeriod,
+    count: Option<u32>,
+    today: NaiveDate,
+    tz: &Tz,
+) -> TimeSummary {
+    let (count, step, current_start) = match period {
+        TimePeriod::Day => (count.unwrap_or(7).clamp(1, 90), 1, today),
+        TimePeriod::Week => (
+            count.unwrap_or(4).clamp(1, 52),
+            7,
+            today - Duration::days(i64::from(today.weekday().num_days_from_monday())),
+        ),
+    };
+    let buckets = (0..count)
+        .rev()
+        .map(|offset| {
+            let start_day = current_start - Duration::days(i64::from(offset * step));
+            let start = day_start_ms(start_day, tz);
+            let end = day_start_ms(start_day + Duration::days(i64::from(step)), tz);
+            bucket(spans, start_day, start, end)
+        })
+        .collect::<Vec<_>>();
+    TimeSummary {
+        period,
+        total_ms: buckets.iter().map(|bucket| bucket.total_ms).sum(),
+        buckets,
+    }
+}
+
+fn bucket(spans: &[TimeSpan], start_day: NaiveDate, start: u64, end: u64) -> TimeBucket {
+    let mut by_workspace: HashMap<&str, Vec<&TimeSpan>> = HashMap::new();
+    let mut all = Vec::new();
+    for span in spans {
+        let clipped = (span.started_at.max(start), span.ended_at.min(end));
+        if clipped.0 < clipped.1 {
+            by_workspace
+                .entry(&span.workspace_id)
+                .or_default()
+                .push(span);
+            all.push(clipped);
+        }
+    }
+    let clip = |span: &TimeSpan| (span.started_at.max(start), span.ended_at.min(end));
+    let mut workspaces = by_workspace
+        .into_iter()
+        .map(|(workspace_id, spans)| {
+            let mut by_thread: HashMap<&str, Vec<(u64, u64)>> = HashMap::new();
+            for span in &spans {
+                if let Some(thread_id) = &span.thread_id {
+                    by_thread.entry(thread_id).or_default().push(clip(span));
+                }
+            }
+            let mut threads = by_thread
+                .into_iter()
+                .map(|(thread_id, intervals)| ThreadTime {
+                    thread_id: thread_id.to_string(),
+                    total_ms: union_ms(intervals),
+                })
+                .collect::<Vec<_>>();
+            threads.sort_by(|a, b| {
+                b.total_ms
+                    .cmp(&a.total_ms)
+                    .then(a.thread_id.cmp(&b.thread_id))
+            });
+            WorkspaceTime {
+                workspace_id: workspace_id.to_string(),
+                total_ms: union_ms(spans.iter().map(|span| clip(span)).collect()),
+                threads,
+            }
+        })
+        .collect::<Vec<_>>();
+    workspaces.sort_by(|a, b| {
+        b.total_ms
+            .cmp(&a.total_ms)
+            .then(a.workspace_id.cmp(&b.workspace_id))
+    });
+    TimeBucket {
+        start: start_day.format("%Y-%m-%d").to_string(),
+        total_ms: union_ms(all),
+        workspaces,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    const MINUTE: u64 = 60 * 1000;
+
+    fn key(workspace_id: &str, thread_id: Option<&str>) -> SpanKey {
+        (workspace_id.to_string(), thread_id.map(str::to_string))
+    }
+
+    fn span(
+        workspace_id: &str,
+        thread_id: Option<&str>,
+        started_at: u64,
+        ended_at: u64,
+    ) -> TimeSpan {
+        TimeSpan {
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.map(str::to_string),
+            started_at,
+            ended_at,
+        }
+    }
+
+    #[test]
+    fn idle_gaps_split_spans_unless_a_turn_is_running() {
+        let mut state = TrackerState::default();
+        state.touch(key("ws-1", None), 0);
+        state.touch(key("ws-1", None), 4 * MINUTE);
+        state.touch(key("ws-1", None), 20 * MINUTE);
+        state.running.insert(key("ws-1", Some("thr-1")));
+        state.touch(key("ws-1", Some("thr-1")), 0);
+        state.touch(key("ws-1", Some("thr-1")), 30 * MINUTE);
+        assert_eq!(
+            state.spans,
+            vec![
+                span("ws-1", None, 0, 4 * MINUTE),
+                span("ws-1", None, 20 * MINUTE, 20 * MINUTE),
+                span("ws-1", Some("thr-1"), 0, 30 * MINUTE),
+            ]
+        );
+    }
+
+    #[test]
+    fn tracks_turns_and_persists_them() {
+        let data_dir =
+            std::env::temp_dir().join(format!("codex-monitor-time-tracking-{}", Uuid::new_v4()));
+        let tracker = TimeTracker::load(&data_dir);
+        tracker.observe(
+            "ws-1",
+            &json!({ "method": "turn/started", "params": { "threadId": "thr-1" } }),
+        );
+        assert_eq!(tracker.running_threads("ws-1"), 1);
+        assert_eq!(tracker.running_threads("ws-2"), 0);
+        tracker.observe(
+            "ws-1",
+            &json!({ "method": "turn/completed", "params": { "threadId": "thr-1" } }),
+        );
+        assert_eq!(tracker.running_threads("ws-1"), 0);
+        tracker.observe(
+            "ws-1",
+            &json!({ "method": "item/completed", "params": { "threadId": "thr-1" } }),
+        );
+        tracker.record_focus("ws-2", None);
+
+        let reloaded = TimeTracker::load(&data_dir);
+        let spans = reloaded.lock().spans.clone();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].thread_id.as_deref(), Some("thr-1"));
+        assert_eq!(spans[1].workspace_id, "ws-2");
+        let summary = reloaded.summary(TimePeriod::Week, Some(2), Some("ws-1"));
+        assert_eq!(summary.buckets.len(), 2);
+        assert!(summary
+            .buckets
+            .iter()
+            .flat_map(|bucket| &bucket.workspaces)
+            .all(|workspace| workspace.workspace_id == "ws-1"));
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn summarizes_days_and_weeks_without_double_counting() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 4).expect("date");
+        let day = |date: NaiveDate| day_start_ms(date, &Utc);
+        let wednesday = day(today);
+        let tuesday = day(today - Duration::days(1));
+        let spans = vec![
+            span("ws-1", Some("thr-1"), wednesday, wednesday + 30 * MINUTE),
+            span(
+                "ws-1",
+                Some("thr-2"),
+                wednesday + 10 * MINUTE,
+                wednesday + 40 * MINUTE,
+            ),
+            span(
+                "ws-2",
+                None,
+                wednesday + 20 * MINUTE,
+                wednesday + 65 * MINUTE,
+            ),
+            // Crosses midnight into Wednesday.
+            span(
+                "ws-1",
+                Some("thr-1"),
+                wednesday - 15 * MINUTE,
+                wednesday + 5 * MINUTE,
+            ),
+            span("ws-1", None, tuesday, tuesday + MINUTE),
+        ];
+
+        let daily = summarize(&spans, TimePeriod::Day, Some(2), today, &Utc);
+        assert_eq!(daily.buckets[0].start, "2026-03-03");
+        assert_eq!(daily.buckets[0].total_ms, 16 * MINUTE);
+        let wednesday_bucket = &daily.buckets[1];
+        assert_eq!(wednesday_bucket.start, "2026-03-04");
+        assert_eq!(wednesday_bucket.total_ms, 65 * MINUTE);
+        assert_eq!(wednesday_bucket.workspaces[0].workspace_id, "ws-2");
+        assert_eq!(wednesday_bucket.workspaces[0].total_ms, 45 * MINUTE);
+        let ws1 = &wednesday_bucket.workspaces[1];
+        assert_eq!(ws1.total_ms, 40 * MINUTE);
+        assert_eq!(
+            ws1.threads,
+            vec![
+                ThreadTime {
+                    thread_id: "thr-1".to_string(),
+                    total_ms: 30 * MINUTE
+                },
+                ThreadTime {
+                    thread_id: "thr-2".to_string(),
+                    total_ms: 30 * MINUTE
+                },
+            ]
+        );
+        assert_eq!(daily.total_ms, 81 * MINUTE);
+
+        let weekly = summarize(&spans, TimePeriod::Week, Some(1), today, &Utc);
+        assert_eq!(weekly.buckets[0].start, "2026-03-02");
+        assert_eq!(weekly.total_ms, 81 * MINUTE);
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::git_utils::resolve_git_root;
+use crate::shared::git_core::{run_git_command, run_git_command_bytes, run_git_command_with_env};
+use crate::types::WorkspaceEntry;
+
+pub const TURN_SNAPSHOTS_FILE: &str = "turn-snapshots.json";
+/// Snapshot commits are pinned here so `git gc` keeps them until they are pruned.
+const SNAPSHOT_REF_PREFIX: &str = "refs/codex-monitor/snapshots";
+const MAX_SNAPSHOTS_PER_WORKSPACE: usize = 50;
+/// Identity for snapshot commits, so they work without `user.name` configured.
+const SNAPSHOT_IDENTITY: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "CodexMonitor"),
+    ("GIT_AUTHOR_EMAIL", "codex-monitor@localhost"),
+    ("GIT_COMMITTER_NAME", "CodexMonitor"),
+    ("GIT_COMMITTER_EMAIL", "codex-monitor@localhost"),
+];
+const RESTORE_PATH_CHUNK: usize = 200;
+
+/// Workspace state captured right before a turn with write access started.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnSnapshot {
+    pub turn_id: String,
+    pub workspace_id: String,
+    pub thread_id: String,
+    pub repo_root: String,
+    /// Commit of the whole working tree (tracked and untracked, minus ignored files).
+    pub commit: String,
+    /// Tree of the index, so staged-vs-unstaged state comes back too.
+    pub index_tree: String,
+    pub head: Option<String>,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotRestore {
+    pub turn_id: String,
+    pub workspace_id: String,
+    pub restored_files: Vec<String>,
+    pub removed_files: Vec<String>,
+    /// The agent moved `HEAD` (e.g. committed); files are restored but `HEAD` is left alone.
+    pub head_changed: bool,
+    /// Snapshot of the state that was just replaced, so the restore itself can be undone.
+    pub replaced_commit: String,
+}
+
+/// A snapshot taken before `turn/start`; it gets its turn id once the turn exists.
+pub struct PendingSnapshot {
+    repo_root: PathBuf,
+    commit: String,
+    index_tree: String,
+    head: Option<String>,
+}
+
+/// Turn snapshots keyed by turn id, mirrored to `<data-dir>/turn-snapshots.json`.
+pub struct TurnSnapshots {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, TurnSnapshot>>,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn snapshot_ref(turn_id: &str) -> String {
+    let name = turn_id
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{SNAPSHOT_REF_PREFIX}/{name}")
+}
+
+pub fn turn_id_from_response(response: &Value) -> Option<String> {
+    let result = response.get("result").unwrap_or(response);
+    result
+        .get("turn")
+        .and_then(|turn| turn.get("id"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+async fn head_commit(repo_root: &PathBuf) -> Option<String> {
+    run_git_command(repo_root, &["rev-parse", "--verify", "--quiet", "HEAD"])
+        .await
+        .ok()
+        .filter(|sha| !sha.is_empty())
+}
+
+/// Commits the working tree through a scratch copy of the index, leaving the real index,
+/// `HEAD` and stash untouched.
+async fn commit_worktree(repo_root: &PathBuf, head: Option<&str>) -> Result<String, String> {
+    let real_index = run_git_command(repo_root, &["rev-parse", "--git-path", "index"]).await?;
+    let scratch_index =
+        std::env::temp_dir().join(format!("codex-monitor-snapshot-{}.index", Uuid::new_v4()));
+    // Seeding from the real index keeps its stat cache, so unchanged files aren't rehashed.
+    if std::fs::copy(repo_root.join(&real_index), &scratch_index).is_err() {
+        let _ = std::fs::remove_file(&scratch_index);
+    }
+    let scratch = scratch_index.to_string_lossy().to_string();
+    let env = [("GIT_INDEX_FILE", scratch.as_str())];
+    let result = async {
+        run_git_command_with_env(repo_root, &["add", "-A", "--", "."], &env).await?;
+        let tree = run_git_command_with_env(repo_root, &["write-tree"], &env).await?;
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", "codex-monitor turn snapshot"];
+        if let Some(head) = head {
+            args.extend(["-p", head]);
+        }
+        run_git_command_with_env(repo_root, &args, &SNAPSHOT_IDENTITY).await
+    }
+    .await;
+    let _ = std::fs::remove_file(&scratch_index);
+    result
+}
+
+/// Captures the workspace's repo before a turn; see [`TurnSnapshots::record`].
+pub async fn capture_snapshot(entry: &WorkspaceEntry) -> Result<PendingSnapshot, String> {
+    let repo_root = resolve_git_root(entry)?;
+    let head = head_commit(&repo_root).await;
+    let index_tree = run_git_command(&repo_root, &["write-tree"]).await?;
+    let commit = commit_worktree(&repo_root, head.as_deref()).await?;
+    Ok(PendingSnapshot {
+        repo_root,
+        commit,
+        index_tree,
+        head,
+    })
+}
+
+fn parse_name_status(output: &[u8]) -> Vec<(String, String)> {
+    let mut fields = output
+        .split(|byte| *byte == 0)
+        .map(|field| String::from_utf8_lossy(field).to_string());
+    let mut changes = Vec::new();
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        if status.is_empty() {
+            break;
+        }
+        changes.push((status, path));
+    }
+    changes
+}
+
+impl TurnSnapshots {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(TURN_SNAPSHOTS_FILE);
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, TurnSnapshot>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn persist(&self, entries: &HashMap<String, TurnSnapshot>) {
+        let result = serde_json::to_string_pretty(entries)
+            .map_err(|err| err.to_string())
+            .and_then(|data| std::fs::write(&self.path, data).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            tracing::warn!(path = %self.path.display(), "failed to save turn snapshots: {err}");
+        }
+    }
+
+    pub fn get(&self, turn_id: &str) -> Option<TurnSnapshot> {
+        self.lock().get(turn_id).cloned()
+    }
+
+    /// Pins a pending snapshot under its turn id and prunes the workspace's oldest ones.
+    pub async fn record(
+        &self,
+        pending: PendingSnapshot,
+        turn_id: &str,
+        workspace_id: &str,
+        thread_id: &str,
+    ) -> Result<TurnSnapshot, String> {
+        run_git_command(
+            &pending.repo_root,
+            &["update-ref", &snapshot_ref(turn_id), &pending.commit],
+        )
+        .await?;
+        let snapshot = TurnSnapshot {
+            turn_id: turn_id.to_string(),
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.to_string(),
+            repo_root: pending.repo_root.to_string_lossy().to_string(),
+            commit: pending.commit,
+            index_tree: pending.index_tree,
+            head: pending.head,
+            created_at: now_secs(),
+        };
+        let pruned = {
+            let mut entries = self.lock();
+            entries.insert(turn_id.to_string(), snapshot.clone());
+            let mut owned = entries
+                .values()
+                .filter(|entry| entry.workspace_id == workspace_id)
+                .map(|entry| (entry.created_at, entry.turn_id.clone()))
+                .collect::<Vec<_>>();
+            owned.sort();
+            let excess = owned.len().saturating_sub(MAX_SNAPSHOTS_PER_WORKSPACE);
+            let pruned = owned
+                .into_iter()
+                .take(excess)
+                .filter_map(|(_, id)| entries.remove(&id))
+                .collect::<Vec<_>>();
+            self.persist(&entries);
+            pruned
+        };
+        for old in pruned {
+            let _ = run_git_command(
+                &PathBuf::from(&old.repo_root),
+                &["update-ref", "-d", &snapshot_ref(&old.turn_id)],
+            )
+            .await;
+        }
+        Ok(snapshot)
+    }
+
+    /// Puts the working tree and index back to how they were before `turn_id` started:
+    /// files changed or deleted since are restored and files created since are removed.
+    /// Ignored files are not part of snapshots and are left as they are.
+    pub async fn restore(&self, turn_id: &str) -> Result<SnapshotRestore, String> {
+        let snapshot = self
+            .get(turn_id)
+            .ok_or_else(|| format!("No snapshot for turn {turn_id}"))?;
+        let repo_root = PathBuf::from(&snapshot.repo_root);
+        let head = head_commit(&repo_root).await;
+        let replaced_commit = commit_worktree(&repo_root, head.as_deref()).await?;
+        run_git_command(
+            &repo_root,
+            &[
+                "update-ref",
+                &format!("{}-replaced", snapshot_ref(turn_id)),
+                &replaced_commit,
+            ],
+        )
+        .await?;
+
+        let diff = run_git_command_bytes(
+            &repo_root,
+            &[
+                "diff",
+                "--name-status",
+                "--no-renames",
+                "-z",
+                &snapshot.commit,
+                &replaced_commit,
+            ],
+        )
+        .await?;
+        let mut restored_files = Vec::new();
+        let mut removed_files = Vec::new();
+        for (status, path) in parse_name_status(&diff) {
+            if status.starts_with('A') {
+                removed_files.push(path);
+            } else {
+                restored_files.push(path);
+            }
+        }
+
+        for path in &removed_files {
+            let target = repo_root.join(path);
+            if let Err(err) = std::fs::remove_file(&target) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(format!("Failed to remove {path}: {err}"));
+                }
+            }
+        }
+        for chunk in restored_files.chunks(RESTORE_PATH_CHUNK) {
+            let source = format!("--source={}", snapshot.commit);
+            let pathspecs = chunk
+                .iter()
+                .map(|path| format!(":(literal){path}"))
+                .collect::<Vec<_>>();
+            let mut args = vec!["restore", source.as_str(), "--worktree", "--"];
+            args.extend(pathspecs.iter().map(String::as_str));
+            run_git_command(&repo_root, &args).await?;
+        }
+        run_git_command(&repo_root, &["read-tree", &snapshot.index_tree]).await?;
+        // Refresh stat info after read-tree; a non-zero exit only means files differ.
+        let _ = run_git_command(&repo_root, &["update-index", "-q", "--refresh"]).await;
+
+        Ok(SnapshotRestore {
+            turn_id: snapshot.turn_id,
+            workspace_id: snapshot.workspace_id,
+            restored_files,
+            removed_files,
+            head_changed: head != snapshot.head,
+            replaced_commit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use crate::types::{WorkspaceKind, WorkspaceSettings};
+
+    #[test]
+    fn restores_worktree_and_index_to_the_snapshot() {
+        let root =
+            std::env::temp_dir().join(format!("codex-monitor-snapshots-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create repo");
+        git(&root, &["init", "-q"]);
+        std::fs::write(root.join("a.txt"), "one\n").expect("write a");
+        std::fs::write(root.join(".gitignore"), "build/\n").expect("write ignore");
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "init"]);
+        std::fs::write(root.join("a.txt"), "two\n").expect("edit a");
+        std::fs::write(root.join("notes.txt"), "draft\n").expect("write notes");
+        git(&root, &["add", "a.txt"]);
+
+        let entry = WorkspaceEntry {
+            id: "ws-1".to_string(),
+            name: "repo".to_string(),
+            path: root.to_string_lossy().to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+        };
+        let snapshots = TurnSnapshots::load(&root.join(".git"));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let pending = capture_snapshot(&entry).await.expect("capture");
+            snapshots
+                .record(pending, "turn-1", "ws-1", "thread-1")
+                .await
+                .expect("record");
+
+            std::fs::write(root.join("a.txt"), "agent\n").expect("agent edit");
+            std::fs::remove_file(root.join("notes.txt")).expect("agent delete");
+            std::fs::write(root.join("new.rs"), "fn main() {}\n").expect("agent create");
+            git(&root, &["add", "-A"]);
+
+            let restore = snapshots.restore("turn-1").await.expect("restore");
+            assert_eq!(restore.removed_files, vec!["new.rs".to_string()]);
+            assert!(!restore.head_changed);
+            assert!(snapshots.restore("missing").await.is_err());
+        });
+
+        assert_eq!(std::fs::read_to_string(root.join("a.txt")).unwrap(), "two\n");
+        assert_eq!(std::fs::read_to_string(root.join("notes.txt")).unwrap(), "draft\n");
+        assert!(!root.join("new.rs").exists());
+        let staged = std::process::Command::new("git")
+            .args(["diff", "--cached", "--name-only"])
+            .current_dir(&root)
+            .output()
+            .expect("git diff");
+        assert_eq!(String::from_utf8_lossy(&staged.stdout), "a.txt\n");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
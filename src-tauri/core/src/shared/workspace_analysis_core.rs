@@ -0,0 +1,659 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::packages_core::{detect_packages, PackageEcosystem, WorkspacePackage};
+use crate::shared::workspace_stats_core::{language_stats, LanguageStats};
+use crate::types::WorkspaceEntry;
+
+const MAX_ANALYSIS_FILES: usize = 50_000;
+const TOP_LANGUAGES: usize = 6;
+/// Packages below the root that get their own tasks when the root has no manifest.
+const MAX_TASK_PACKAGES: usize = 8;
+const MAX_ENTRY_POINTS: usize = 20;
+const README_SUMMARY_CHARS: usize = 600;
+const MAKE_TARGETS: &[&str] = &["build", "test", "check", "lint"];
+const NPM_SCRIPTS: &[&str] = &["build", "test", "lint", "typecheck", "dev", "start"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskKind {
+    Build,
+    Test,
+    Lint,
+    Run,
+}
+
+/// A command guessed from the workspace's manifests, usable as a runner task.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSuggestion {
+    pub kind: TaskKind,
+    pub command: String,
+    /// Workspace-relative directory to run it in ("" for the root).
+    pub cwd: String,
+    /// The file the guess came from.
+    pub source: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildSystem {
+    pub name: String,
+    pub manifest: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryPoint {
+    pub path: String,
+    /// `binary`, `library`, `script` or `web`.
+    pub kind: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadmeSummary {
+    pub path: String,
+    pub title: Option<String>,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceAnalysis {
+    pub workspace_id: String,
+    pub name: String,
+    pub languages: Vec<LanguageStats>,
+    pub build_systems: Vec<BuildSystem>,
+    pub packages: Vec<WorkspacePackage>,
+    pub entry_points: Vec<EntryPoint>,
+    pub tasks: Vec<TaskSuggestion>,
+    /// Workflow files under `.github/workflows`.
+    pub ci: Vec<String>,
+    pub readme: Option<ReadmeSummary>,
+    /// The report as text, ready to open a workspace's first thread with.
+    pub prompt: String,
+}
+
+fn task(kind: TaskKind, command: impl Into<String>, cwd: &str, source: &str) -> TaskSuggestion {
+    TaskSuggestion {
+        kind,
+        command: command.into(),
+        cwd: cwd.to_string(),
+        source: source.to_string(),
+    }
+}
+
+/// The package manager a JS package is driven with, by lockfile.
+fn js_runner(root: &Path, dir: &str, ecosystem: PackageEcosystem) -> &'static str {
+    let dir = root.join(dir);
+    if ecosystem == PackageEcosystem::Pnpm {
+        "pnpm"
+    } else if dir.join("yarn.lock").is_file() {
+        "yarn"
+    } else if dir.join("bun.lockb").is_file() || dir.join("bun.lock").is_file() {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+fn js_script_command(runner: &str, script: &str) -> String {
+    match (runner, script) {
+        ("npm", "test" | "start") => format!("npm {script}"),
+        ("npm", _) => format!("npm run {script}"),
+        _ => format!("{runner} {script}"),
+    }
+}
+
+fn package_tasks(root: &Path, package: &WorkspacePackage) -> Vec<TaskSuggestion> {
+    let cwd = package.path.as_str();
+    let source = package.manifest.as_str();
+    match package.ecosystem {
+        PackageEcosystem::Cargo => vec![
+            task(TaskKind::Build, "cargo build", cwd, source),
+            task(TaskKind::Test, "cargo test", cwd, source),
+            task(TaskKind::Lint, "cargo clippy --all-targets", cwd, source),
+        ],
+        PackageEcosystem::Go => vec![
+            task(TaskKind::Build, "go build ./...", cwd, source),
+            task(TaskKind::Test, "go test ./...", cwd, source),
+            task(TaskKind::Lint, "go vet ./...", cwd, source),
+        ],
+        PackageEcosystem::Npm | PackageEcosystem::Pnpm => {
+            let runner = js_runner(root, cwd, package.ecosystem);
+            let scripts = std::fs::read_to_string(root.join(source))
+                .ok()
+                .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+                .and_then(|manifest| manifest.get("scripts").cloned())
+                .unwrap_or_default();
+            NPM_SCRIPTS
+                .iter()
+                .filter(|script| scripts.get(**script).is_some())
+                .map(|script| {
+                    let kind = match *script {
+                        "build" => TaskKind::Build,
+                        "test" => TaskKind::Test,
+                        "lint" | "typecheck" => TaskKind::Lint,
+                        _ => TaskKind::Run,
+                    };
+                    task(kind, js_script_command(runner, script), cwd, source)
+                })
+                .collect()
+        }
+    }
+}
+
+fn makefile_targets(contents: &str) -> Vec<&'static str> {
+    MAKE_TARGETS
+        .iter()
+        .copied()
+        .filter(|target| {
+            contents.lines().any(|line| {
+                line.strip_prefix(target)
+                    .is_some_and(|rest| rest.trim_start().starts_with(':'))
+            })
+        })
+        .collect()
+}
+
+fn python_tasks(root: &Path, files: &[String]) -> Vec<TaskSuggestion> {
+    let pyproject = std::fs::read_to_string(root.join("pyproject.toml")).unwrap_or_default();
+    let uses_pytest = pyproject.contains("pytest")
+        || root.join("pytest.ini").is_file()
+        || root.join("conftest.py").is_file()
+        || files.iter().any(|file| {
+            let name = file.rsplit('/').next().unwrap_or(file);
+            name.starts_with("test_") && name.ends_with(".py")
+        });
+    let source = if root.join("pyproject.toml").is_file() {
+        "pyproject.toml"
+    } else if root.join("setup.py").is_file() {
+        "setup.py"
+    } else {
+        "requirements.txt"
+    };
+    let mut tasks = Vec::new();
+    if uses_pytest {
+        tasks.push(task(TaskKind::Test, "pytest", "", source));
+    } else if root.join("manage.py").is_file() {
+        tasks.push(task(
+            TaskKind::Test,
+            "python manage.py test",
+            "",
+            "manage.py",
+        ));
+    } else {
+        tasks.push(task(TaskKind::Test, "python -m unittest", "", source));
+    }
+    if pyproject.contains("[tool.ruff") || root.join("ruff.toml").is_file() {
+        tasks.push(task(TaskKind::Lint, "ruff check .", "", source));
+    }
+    tasks
+}
+
+/// Build systems and the tasks they suggest. Only the root and, without a root
+/// manifest, the first few packages below it get tasks, so monorepos don't flood the
+/// list with one set per member.
+fn detect_build(
+    root: &Path,
+    files: &[String],
+    packages: &[WorkspacePackage],
+) -> (Vec<BuildSystem>, Vec<TaskSuggestion>) {
+    let mut systems = Vec::new();
+    let mut tasks = Vec::new();
+
+    let root_packages = packages
+        .iter()
+        .filter(|package| package.path.is_empty())
+        .collect::<Vec<_>>();
+    let task_packages = if root_packages.is_empty() {
+        packages.iter().take(MAX_TASK_PACKAGES).collect()
+    } else {
+        root_packages
+    };
+    for package in &task_packages {
+        let name = match package.ecosystem {
+            PackageEcosystem::Cargo => "cargo",
+            PackageEcosystem::Go => "go",
+            PackageEcosystem::Npm | PackageEcosystem::Pnpm => {
+                js_runner(root, &package.path, package.ecosystem)
+            }
+        };
+        systems.push(BuildSystem {
+            name: name.to_string(),
+            manifest: package.manifest.clone(),
+        });
+        tasks.extend(package_tasks(root, package));
+    }
+
+    if let Ok(contents) = std::fs::read_to_string(root.join("Makefile")) {
+        systems.push(BuildSystem {
+            name: "make".to_string(),
+            manifest: "Makefile".to_string(),
+        });
+        for target in makefile_targets(&contents) {
+            let kind = match target {
+                "build" => TaskKind::Build,
+                "test" | "check" => TaskKind::Test,
+                _ => TaskKind::Lint,
+            };
+            tasks.push(task(kind, format!("make {target}"), "", "Makefile"));
+        }
+    }
+    if root.join("justfile").is_file() {
+        systems.push(BuildSystem {
+            name: "just".to_string(),
+            manifest: "justfile".to_string(),
+        });
+    }
+    if root.join("CMakeLists.txt").is_file() {
+        systems.push(BuildSystem {
+            name: "cmake".to_string(),
+            manifest: "CMakeLists.txt".to_string(),
+        });
+        tasks.push(task(
+            TaskKind::Build,
+            "cmake -S . -B build && cmake --build build",
+            "",
+            "CMakeLists.txt",
+        ));
+        tasks.push(task(
+            TaskKind::Test,
+            "ctest --test-dir build",
+            "",
+            "CMakeLists.txt",
+        ));
+    }
+    let gradle = ["build.gradle.kts", "build.gradle"]
+        .into_iter()
+        .find(|name| root.join(name).is_file());
+    if let Some(manifest) = gradle {
+        let gradle = if root.join("gradlew").is_file() {
+            "./gradlew"
+        } else {
+            "gradle"
+        };
+        systems.push(BuildSystem {
+            name: "gradle".to_string(),
+            manifest: manifest.to_string(),
+        });
+        tasks.push(task(
+            TaskKind::Build,
+            format!("{gradle} build"),
+            "",
+            manifest,
+        ));
+        tasks.push(task(TaskKind::Test, format!("{gradle} test"), "", manifest));
+    } else if root.join("pom.xml").is_file() {
+        systems.push(BuildSystem {
+            name: "maven".to_string(),
+            manifest: "pom.xml".to_string(),
+        });
+        tasks.push(task(TaskKind::Build, "mvn package", "", "pom.xml"));
+        tasks.push(task(TaskKind::Test, "mvn test", "", "pom.xml"));
+    }
+    let python = ["pyproject.toml", "setup.py", "requirements.txt"]
+        .into_iter()
+        .find(|name| root.join(name).is_file());
+    if let Some(manifest) = python {
+        systems.push(BuildSystem {
+            name: "python".to_string(),
+            manifest: manifest.to_string(),
+        });
+        tasks.extend(python_tasks(root, files));
+    }
+
+    let mut seen = Vec::new();
+    tasks.retain(|task| {
+        let key = (task.command.clone(), task.cwd.clone());
+        if seen.contains(&key) {
+            return false;
+        }
+        seen.push(key);
+        true
+    });
+    tasks.sort_by_key(|task| task.kind);
+    (systems, tasks)
+}
+
+fn entry_kind(file: &str) -> Option<&'static str> {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let parent = file.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    let parent_name = parent.rsplit('/').next().unwrap_or(parent);
+    match name {
+        "main.rs" if parent_name == "src" => Some("binary"),
+        "lib.rs" if parent_name == "src" => Some("library"),
+        _ if file.ends_with(".rs") && parent_name == "bin" => Some("binary"),
+        "main.go" => Some("binary"),
+        "__main__.py" | "manage.py" | "main.py" | "app.py" => Some("script"),
+        "main.ts" | "main.tsx" | "index.ts" | "index.tsx" | "main.js" | "index.js"
+            if parent_name == "src" || parent.is_empty() =>
+        {
+            Some("web")
+        }
+        "index.html" if parent.is_empty() || parent_name == "public" => Some("web"),
+        _ => None,
+    }
+}
+
+fn detect_entry_points(files: &[String]) -> Vec<EntryPoint> {
+    let mut entries = files
+        .iter()
+        .filter(|file| {
+            !file.split('/').any(|part| {
+                matches!(
+                    part,
+                    "node_modules" | "vendor" | "target" | "dist" | "tests"
+                )
+            })
+        })
+        .filter_map(|file| {
+            entry_kind(file).map(|kind| EntryPoint {
+                path: file.clone(),
+                kind: kind.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| {
+        let depth = |path: &str| path.matches('/').count();
+        depth(&a.path)
+            .cmp(&depth(&b.path))
+            .then(a.path.cmp(&b.path))
+    });
+    entries.truncate(MAX_ENTRY_POINTS);
+    entries
+}
+
+/// The first heading and the first prose paragraph, skipping badges, HTML and
+/// code blocks.
+pub fn summarize_readme(contents: &str) -> (Option<String>, String) {
+    let mut title = None;
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut in_code = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            if title.is_none() {
+                title = Some(heading.trim_start_matches('#').trim().to_string());
+            }
+            if !paragraph.is_empty() {
+                break;
+            }
+            continue;
+        }
+        let decoration = trimmed.starts_with('<')
+            || trimmed.starts_with("[![")
+            || trimmed.starts_with("![")
+            || trimmed.starts_with("---")
+            || trimmed.starts_with("===");
+        if trimmed.is_empty() || decoration {
+            if !paragraph.is_empty() {
+                break;
+            }
+            continue;
+        }
+        paragraph.push(trimmed);
+    }
+    let summary = paragraph.join(" ");
+    let summary = match summary.char_indices().nth(README_SUMMARY_CHARS) {
+        Some((index, _)) => format!("{}…", summary[..index].trim_end()),
+        None => summary,
+    };
+    (title.filter(|title| !title.is_empty()), summary)
+}
+
+fn read_readme(root: &Path) -> Option<ReadmeSummary> {
+    let entries = std::fs::read_dir(root).ok()?;
+    let mut candidates = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| name.to_ascii_lowercase().starts_with("readme"))
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|name| (!name.to_ascii_lowercase().ends_with(".md"), name.clone()));
+    let path = candidates.into_iter().next()?;
+    let contents = std::fs::read_to_string(root.join(&path)).ok()?;
+    let (title, summary) = summarize_readme(&contents);
+    Some(ReadmeSummary {
+        path,
+        title,
+        summary,
+    })
+}
+
+fn build_prompt(analysis: &WorkspaceAnalysis) -> String {
+    let mut lines = vec![format!("Workspace overview for `{}`:", analysis.name)];
+    if let Some(readme) = &analysis.readme {
+        let title = readme.title.as_deref().unwrap_or(&readme.path);
+        if readme.summary.is_empty() {
+            lines.push(format!("- README: {title}"));
+        } else {
+            lines.push(format!("- README: {title} — {}", readme.summary));
+        }
+    }
+    if !analysis.languages.is_empty() {
+        let languages = analysis
+            .languages
+            .iter()
+            .filter(|language| language.language != "Other")
+            .map(|language| format!("{} ({} files)", language.language, language.files))
+            .collect::<Vec<_>>();
+        if !languages.is_empty() {
+            lines.push(format!("- Languages: {}", languages.join(", ")));
+        }
+    }
+    if !analysis.build_systems.is_empty() {
+        let systems = analysis
+            .build_systems
+            .iter()
+            .map(|system| format!("{} (`{}`)", system.name, system.manifest))
+            .collect::<Vec<_>>();
+        lines.push(format!("- Build systems: {}", systems.join(", ")));
+    }
+    if !analysis.entry_points.is_empty() {
+        let entries = analysis
+            .entry_points
+            .iter()
+            .map(|entry| format!("`{}` ({})", entry.path, entry.kind))
+            .collect::<Vec<_>>();
+        lines.push(format!("- Entry points: {}", entries.join(", ")));
+    }
+    for kind in [TaskKind::Build, TaskKind::Test, TaskKind::Lint] {
+        let commands = analysis
+            .tasks
+            .iter()
+            .filter(|task| task.kind == kind)
+            .map(|task| {
+                if task.cwd.is_empty() {
+                    format!("`{}`", task.command)
+                } else {
+                    format!("`{}` in `{}`", task.command, task.cwd)
+                }
+            })
+            .collect::<Vec<_>>();
+        if commands.is_empty() {
+            continue;
+        }
+        let label = match kind {
+            TaskKind::Build => "Build",
+            TaskKind::Test => "Test",
+            _ => "Lint",
+        };
+        lines.push(format!("- {label}: {}", commands.join(", ")));
+    }
+    if !analysis.ci.is_empty() {
+        lines.push(format!("- CI workflows: {}", analysis.ci.join(", ")));
+    }
+    lines.join("\n")
+}
+
+pub fn analyze_workspace(
+    workspace_id: &str,
+    name: &str,
+    root: &Path,
+    files: &[String],
+) -> WorkspaceAnalysis {
+    let (mut languages, _) = language_stats(root, files);
+    languages.retain(|language| {
+        !matches!(
+            language.language.as_str(),
+            "Markdown" | "JSON" | "YAML" | "TOML"
+        )
+    });
+    languages.truncate(TOP_LANGUAGES);
+    let packages = detect_packages(root);
+    let (build_systems, tasks) = detect_build(root, files, &packages);
+    let ci = files
+        .iter()
+        .filter(|file| file.starts_with(".github/workflows/"))
+        .filter(|file| file.ends_with(".yml") || file.ends_with(".yaml"))
+        .cloned()
+        .collect();
+    let mut analysis = WorkspaceAnalysis {
+        workspace_id: workspace_id.to_string(),
+        name: name.to_string(),
+        languages,
+        build_systems,
+        packages,
+        entry_points: detect_entry_points(files),
+        tasks,
+        ci,
+        readme: read_readme(root),
+        prompt: String::new(),
+    };
+    analysis.prompt = build_prompt(&analysis);
+    analysis
+}
+
+/// Collects repo facts for a workspace's onboarding in one call.
+pub async fn analyze_workspace_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    workspace_id: &str,
+    list_files: fn(&PathBuf, usize) -> Vec<String>,
+) -> Result<WorkspaceAnalysis, String> {
+    let (root, name) = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        (PathBuf::from(&entry.path), entry.name.clone())
+    };
+    let workspace_id = workspace_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let files = list_files(&root, MAX_ANALYSIS_FILES);
+        analyze_workspace(&workspace_id, &name, &root, &files)
+    })
+    .await
+    .map_err(|err| format!("Workspace analysis failed: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let target = root.join(path);
+        std::fs::create_dir_all(target.parent().expect("parent")).expect("create dirs");
+        std::fs::write(target, contents).expect("write file");
+    }
+
+    #[test]
+    fn analyzes_a_mixed_workspace() {
+        let root = std::env::temp_dir().join(format!("fridex-analysis-{}", Uuid::new_v4()));
+        write(&root, "Cargo.toml", "[package]\nname = \"demo\"\n");
+        write(&root, "src/main.rs", "fn main() {}\n");
+        write(&root, "src/bin/tool.rs", "fn main() {}\n");
+        write(
+            &root,
+            "web/package.json",
+            r#"{ "name": "web", "scripts": { "build": "vite build", "test": "vitest" } }"#,
+        );
+        write(&root, "web/src/main.tsx", "export {}\n");
+        write(
+            &root,
+            "Makefile",
+            "build:\n\tcargo build\nrelease: build\n\ttrue\n",
+        );
+        write(&root, ".github/workflows/ci.yml", "on: push\n");
+        write(
+            &root,
+            "README.md",
+            "# Demo\n\n[![ci](badge.svg)](ci)\n\nA small demo that\nshows things.\n\n## Usage\n",
+        );
+        let files = [
+            "Cargo.toml",
+            "src/main.rs",
+            "src/bin/tool.rs",
+            "web/package.json",
+            "web/src/main.tsx",
+            "Makefile",
+            ".github/workflows/ci.yml",
+            "README.md",
+        ]
+        .map(str::to_string);
+
+        let analysis = analyze_workspace("ws-1", "demo", &root, &files);
+        assert_eq!(analysis.languages[0].language, "Rust");
+        assert_eq!(
+            analysis
+                .build_systems
+                .iter()
+                .map(|system| system.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["cargo", "make"]
+        );
+        assert_eq!(
+            analysis
+                .tasks
+                .iter()
+                .map(|task| (task.kind, task.command.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (TaskKind::Build, "cargo build"),
+                (TaskKind::Build, "make build"),
+                (TaskKind::Test, "cargo test"),
+                (TaskKind::Lint, "cargo clippy --all-targets"),
+            ]
+        );
+        assert_eq!(
+            analysis
+                .entry_points
+                .iter()
+                .map(|entry| (entry.path.as_str(), entry.kind.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("src/main.rs", "binary"),
+                ("src/bin/tool.rs", "binary"),
+                ("web/src/main.tsx", "web"),
+            ]
+        );
+        assert_eq!(analysis.ci, vec![".github/workflows/ci.yml".to_string()]);
+        let readme = analysis.readme.as_ref().expect("readme");
+        assert_eq!(readme.title.as_deref(), Some("Demo"));
+        assert_eq!(readme.summary, "A small demo that shows things.");
+        assert!(analysis.prompt.contains("- Test: `cargo test`"));
+
+        std::fs::remove_file(root.join("Cargo.toml")).expect("remove manifest");
+        let analysis = analyze_workspace("ws-1", "demo", &root, &files);
+        assert!(analysis.tasks.contains(&TaskSuggestion {
+            kind: TaskKind::Test,
+            command: "npm test".to_string(),
+            cwd: "web".to_string(),
+            source: "web/package.json".to_string(),
+        }));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,159 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::types::WorkspaceInfo;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceChange {
+    Added,
+    Updated,
+    Removed,
+    /// Only the connection state flipped.
+    Connected,
+    Disconnected,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDelta {
+    /// Increases by one per delta; a gap means the client missed some and should
+    /// refetch `list_workspaces`.
+    pub seq: u64,
+    pub change: WorkspaceChange,
+    pub workspace_id: String,
+    /// The entry as `list_workspaces` would return it; `None` when removed.
+    pub workspace: Option<Value>,
+}
+
+/// Last published workspace list, diffed against the current one to produce deltas.
+#[derive(Default)]
+pub struct WorkspaceDeltaTracker {
+    seq: u64,
+    known: HashMap<String, Value>,
+}
+
+impl WorkspaceDeltaTracker {
+    /// Records `workspaces` as the baseline without producing deltas.
+    pub fn prime(&mut self, workspaces: &[WorkspaceInfo]) {
+        self.known = workspaces
+            .iter()
+            .filter_map(|info| Some((info.id.clone(), serde_json::to_value(info).ok()?)))
+            .collect();
+    }
+
+    /// Deltas from the previous list to `workspaces`, in list order with removals last.
+    pub fn diff(&mut self, workspaces: &[WorkspaceInfo]) -> Vec<WorkspaceDelta> {
+        let mut previous = std::mem::take(&mut self.known);
+        let mut changes = Vec::new();
+        for info in workspaces {
+            let Ok(current) = serde_json::to_value(info) else {
+                continue;
+            };
+            let change = match previous.remove(&info.id) {
+                None => Some(WorkspaceChange::Added),
+                Some(before) if before == current => None,
+                Some(mut before) => {
+                    before["connected"] = current["connected"].clone();
+                    if before != current {
+                        Some(WorkspaceChange::Updated)
+                    } else if info.connected {
+                        Some(WorkspaceChange::Connected)
+                    } else {
+                        Some(WorkspaceChange::Disconnected)
+                    }
+                }
+            };
+            if let Some(change) = change {
+                changes.push((change, info.id.clone(), Some(current.clone())));
+            }
+            self.known.insert(info.id.clone(), current);
+        }
+        let mut removed = previous.into_keys().collect::<Vec<_>>();
+        removed.sort();
+        changes.extend(
+            removed
+                .into_iter()
+                .map(|id| (WorkspaceChange::Removed, id, None)),
+        );
+        changes
+            .into_iter()
+            .map(|(change, workspace_id, workspace)| {
+                self.seq += 1;
+                WorkspaceDelta {
+                    seq: self.seq,
+                    change,
+                    workspace_id,
+                    workspace,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{WorkspaceKind, WorkspaceSettings};
+
+    fn info(id: &str, name: &str, connected: bool) -> WorkspaceInfo {
+        WorkspaceInfo {
+            id: id.to_string(),
+            name: name.to_string(),
+            path: format!("/repos/{id}"),
+            connected,
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+        }
+    }
+
+    #[test]
+    fn reports_added_updated_connection_and_removed_entries() {
+        let mut tracker = WorkspaceDeltaTracker::default();
+        tracker.prime(&[info("a", "alpha", false), info("b", "beta", false)]);
+        assert!(tracker
+            .diff(&[info("a", "alpha", false), info("b", "beta", false)])
+            .is_empty());
+
+        let deltas = tracker.diff(&[
+            info("a", "alpha", true),
+            info("b", "beta two", true),
+            info("c", "gamma", false),
+        ]);
+        let summary = deltas
+            .iter()
+            .map(|delta| (delta.seq, delta.change, delta.workspace_id.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (1, WorkspaceChange::Connected, "a"),
+                (2, WorkspaceChange::Updated, "b"),
+                (3, WorkspaceChange::Added, "c"),
+            ]
+        );
+        assert_eq!(deltas[1].workspace.as_ref().unwrap()["name"], "beta two");
+
+        let deltas = tracker.diff(&[info("a", "alpha", false)]);
+        let summary = deltas
+            .iter()
+            .map(|delta| (delta.seq, delta.change, delta.workspace_id.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (4, WorkspaceChange::Disconnected, "a"),
+                (5, WorkspaceChange::Removed, "b"),
+                (6, WorkspaceChange::Removed, "c"),
+            ]
+        );
+        assert_eq!(deltas[1].workspace, None);
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::local_usage_core::local_usage_snapshot_core;
+use crate::shared::time_tracking_core::TimeTracker;
+use crate::types::{ThreadBranchRecord, WorkspaceEntry};
+
+const MAX_STATS_FILES: usize = 100_000;
+const COMMIT_ACTIVITY_WEEKS: usize = 12;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const TOP_LANGUAGES: usize = 12;
+
+/// Extension → language, checked case-insensitively; anything else counts as "Other".
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("Rust", &["rs"]),
+    ("TypeScript", &["ts", "tsx", "mts", "cts"]),
+    ("JavaScript", &["js", "jsx", "mjs", "cjs"]),
+    ("Python", &["py", "pyi"]),
+    ("Go", &["go"]),
+    ("Java", &["java"]),
+    ("Kotlin", &["kt", "kts"]),
+    ("Swift", &["swift"]),
+    ("C", &["c", "h"]),
+    ("C++", &["cc", "cpp", "cxx", "hpp", "hh"]),
+    ("C#", &["cs"]),
+    ("Ruby", &["rb"]),
+    ("PHP", &["php"]),
+    ("Shell", &["sh", "bash", "zsh"]),
+    ("SQL", &["sql"]),
+    ("HTML", &["html", "htm"]),
+    ("CSS", &["css", "scss", "sass", "less"]),
+    ("Markdown", &["md", "mdx"]),
+    ("JSON", &["json", "jsonc"]),
+    ("YAML", &["yml", "yaml"]),
+    ("TOML", &["toml"]),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitActivity {
+    pub last7_days: usize,
+    pub last30_days: usize,
+    /// Commits per week on HEAD's history, oldest week first.
+    pub weekly: Vec<usize>,
+    pub last_commit_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStats {
+    pub workspace_id: String,
+    pub total_files: usize,
+    /// Sum of the sizes of non-ignored files, i.e. the working tree without `.git`.
+    pub total_bytes: u64,
+    pub truncated: bool,
+    pub languages: Vec<LanguageStats>,
+    pub commits: CommitActivity,
+    /// Threads with a turn running right now.
+    pub active_threads: usize,
+    /// Threads started through this backend for the workspace.
+    pub threads_started: usize,
+    pub threads_started_last7_days: usize,
+    pub tokens_last7_days: i64,
+    pub tokens_last30_days: i64,
+}
+
+fn language_for(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let Some(extension) = extension else {
+        return "Other";
+    };
+    LANGUAGES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(language, _)| *language)
+        .unwrap_or("Other")
+}
+
+pub fn language_stats(root: &Path, files: &[String]) -> (Vec<LanguageStats>, u64) {
+    let mut by_language: HashMap<&'static str, LanguageStats> = HashMap::new();
+    let mut total_bytes = 0;
+    for file in files {
+        let bytes = std::fs::metadata(root.join(file))
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        total_bytes += bytes;
+        let language = language_for(file);
+        let entry = by_language.entry(language).or_insert_with(|| LanguageStats {
+            language: language.to_string(),
+            files: 0,
+            bytes: 0,
+        });
+        entry.files += 1;
+        entry.bytes += bytes;
+    }
+    let mut languages = by_language.into_values().collect::<Vec<_>>();
+    languages.sort_by(|a, b| b.files.cmp(&a.files).then(a.language.cmp(&b.language)));
+    languages.truncate(TOP_LANGUAGES);
+    (languages, total_bytes)
+}
+
+fn commit_activity(root: &Path, now: i64) -> CommitActivity {
+    let mut activity = CommitActivity {
+        weekly: vec![0; COMMIT_ACTIVITY_WEEKS],
+        ..CommitActivity::default()
+    };
+    let Ok(repo) = git2::Repository::discover(root) else {
+        return activity;
+    };
+    let Ok(mut revwalk) = repo.revwalk() else {
+        return activity;
+    };
+    if revwalk.push_head().is_err() {
+        return activity;
+    }
+    let _ = revwalk.set_sorting(git2::Sort::TIME);
+    let window = COMMIT_ACTIVITY_WEEKS as i64 * 7 * SECONDS_PER_DAY;
+    for oid in revwalk.flatten() {
+        let Ok(commit) = repo.find_commit(oid) else {
+            continue;
+        };
+        let time = commit.time().seconds();
+        activity.last_commit_at = activity.last_commit_at.max(Some(time));
+        let age = now - time;
+        if age > window {
+            break;
+        }
+        let age = age.max(0);
+        if age <= 7 * SECONDS_PER_DAY {
+            activity.last7_days += 1;
+        }
+        if age <= 30 * SECONDS_PER_DAY {
+            activity.last30_days += 1;
+        }
+        let week = (age / (7 * SECONDS_PER_DAY)) as usize;
+        if week < COMMIT_ACTIVITY_WEEKS {
+            activity.weekly[COMMIT_ACTIVITY_WEEKS - 1 - week] += 1;
+        }
+    }
+    activity
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Overview numbers for one workspace, computed server-side in a single call.
+pub async fn workspace_stats_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    thread_branches: &Mutex<HashMap<String, ThreadBranchRecord>>,
+    time_tracker: &TimeTracker,
+    workspace_id: &str,
+    list_files: fn(&PathBuf, usize) -> Vec<String>,
+) -> Result<WorkspaceStats, String> {
+    let root = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        PathBuf::from(&entry.path)
+    };
+    let now = now_secs();
+
+    let scan_root = root.clone();
+    let (listed, languages, total_bytes, commits) = tokio::task::spawn_blocking(move || {
+        // One past the cap tells a tree of exactly `MAX_STATS_FILES` files from a bigger one.
+        let mut files = list_files(&scan_root, MAX_STATS_FILES + 1);
+        let listed = files.len();
+        files.truncate(MAX_STATS_FILES);
+        let (languages, total_bytes) = language_stats(&scan_root, &files);
+        let commits = commit_activity(&scan_root, now);
+        (listed, languages, total_bytes, commits)
+    })
+    .await
+    .map_err(|err| format!("Workspace stats failed: {err}"))?;
+
+    let (threads_started, threads_started_last7_days) = {
+        let thread_branches = thread_branches.lock().await;
+        let started = thread_branches
+            .values()
+            .filter(|record| record.workspace_id == workspace_id)
+            .map(|record| record.started_at as i64)
+            .collect::<Vec<_>>();
+        let recent = started
+            .iter()
+            .filter(|started_at| now - **started_at <= 7 * SECONDS_PER_DAY)
+            .count();
+        (started.len(), recent)
+    };
+
+    let usage = local_usage_snapshot_core(
+        workspaces,
+        Some(30),
+        Some(root.to_string_lossy().to_string()),
+    )
+    .await
+    .ok();
+
+    Ok(WorkspaceStats {
+        workspace_id: workspace_id.to_string(),
+        total_files: listed.min(MAX_STATS_FILES),
+        total_bytes,
+        truncated: listed > MAX_STATS_FILES,
+        languages,
+        commits,
+        active_threads: time_tracker.running_threads(workspace_id),
+        threads_started,
+        threads_started_last7_days,
+        tokens_last7_days: usage
+            .as_ref()
+            .map(|usage| usage.totals.last7_days_tokens)
+            .unwrap_or(0),
+        tokens_last30_days: usage
+            .as_ref()
+            .map(|usage| usage.totals.last30_days_tokens)
+            .unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn groups_files_by_language() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-stats-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).expect("create root");
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").expect("write main");
+        std::fs::write(root.join("src/lib.rs"), "pub fn a() {}\n").expect("write lib");
+        std::fs::write(root.join("README.MD"), "# hi\n").expect("write readme");
+        std::fs::write(root.join("Makefile"), "all:\n").expect("write makefile");
+        let files = ["src/main.rs", "src/lib.rs", "README.MD", "Makefile"]
+            .iter()
+            .map(|file| file.to_string())
+            .collect::<Vec<_>>();
+
+        let (languages, total_bytes) = language_stats(&root, &files);
+        assert_eq!(languages[0].language, "Rust");
+        assert_eq!(languages[0].files, 2);
+        assert_eq!(languages[0].bytes, 27);
+        assert_eq!(total_bytes, 37);
+        assert!(languages.iter().any(|stats| stats.language == "Markdown"));
+        assert!(languages.iter().any(|stats| stats.language == "Other"));
+
+        let activity = commit_activity(&root, now_secs());
+        assert_eq!(activity.last30_days, 0);
+        assert_eq!(activity.weekly.len(), COMMIT_ACTIVITY_WEEKS);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -6,9 +6,18 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use crate::backend::app_server::WorkspaceSession;
-use crate::codex::args::resolve_workspace_codex_args;
-use crate::codex::home::resolve_workspace_codex_home;
+use crate::codex::args::{
+    resolve_workspace_codex_args, validate_codex_args, CodexArgs, CodexCommand,
+};
+use crate::codex::home::{resolve_account_profile_home, resolve_workspace_codex_home};
+use crate::shared::branch_protection_core::{
+    ensure_branch_allowed, normalize_branch_patterns, protected_branch_patterns,
+};
+use crate::shared::i18n_core::{self, MessageKey};
 use crate::shared::process_core::kill_child_process_tree;
+use crate::shared::removal_core;
+use crate::shared::revisions_core::check_revision;
+use crate::shared::sparse_checkout_core;
 use crate::storage::write_workspaces;
 use crate::types::{
     AppSettings, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
@@ -16,8 +25,8 @@ use crate::types::{
 };
 use uuid::Uuid;
 
-pub(crate) const WORKTREE_SETUP_MARKERS_DIR: &str = "worktree-setup";
-pub(crate) const WORKTREE_SETUP_MARKER_EXT: &str = "ran";
+pub const WORKTREE_SETUP_MARKERS_DIR: &str = "worktree-setup";
+pub const WORKTREE_SETUP_MARKER_EXT: &str = "ran";
 const AGENTS_MD_FILE_NAME: &str = "AGENTS.md";
 
 fn copy_agents_md_from_parent_to_worktree(
@@ -57,7 +66,7 @@ fn copy_agents_md_from_parent_to_worktree(
     Ok(())
 }
 
-pub(crate) fn normalize_setup_script(script: Option<String>) -> Option<String> {
+pub fn normalize_setup_script(script: Option<String>) -> Option<String> {
     match script {
         Some(value) if value.trim().is_empty() => None,
         Some(value) => Some(value),
@@ -65,17 +74,43 @@ pub(crate) fn normalize_setup_script(script: Option<String>) -> Option<String> {
     }
 }
 
-pub(crate) fn worktree_setup_marker_path(data_dir: &PathBuf, workspace_id: &str) -> PathBuf {
+const ACCESS_MODES: [&str; 3] = ["current", "read-only", "full-access"];
+
+fn trim_setting(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Trims the per-workspace turn defaults and rejects unknown access modes.
+fn normalize_turn_defaults(settings: &mut WorkspaceSettings) -> Result<(), String> {
+    settings.default_model = trim_setting(settings.default_model.take());
+    settings.default_effort = trim_setting(settings.default_effort.take());
+    settings.default_access_mode = trim_setting(settings.default_access_mode.take());
+    settings.default_collaboration_preset =
+        trim_setting(settings.default_collaboration_preset.take());
+    if let Some(mode) = settings.default_access_mode.as_deref() {
+        if !ACCESS_MODES.contains(&mode) {
+            return Err(format!(
+                "Unknown access mode: {mode} (expected one of {})",
+                ACCESS_MODES.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn worktree_setup_marker_path(data_dir: &PathBuf, workspace_id: &str) -> PathBuf {
     data_dir
         .join(WORKTREE_SETUP_MARKERS_DIR)
         .join(format!("{workspace_id}.{WORKTREE_SETUP_MARKER_EXT}"))
 }
 
-pub(crate) fn is_workspace_path_dir_core(path: &str) -> bool {
+pub fn is_workspace_path_dir_core(path: &str) -> bool {
     PathBuf::from(path).is_dir()
 }
 
-pub(crate) async fn list_workspaces_core(
+pub async fn list_workspaces_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
 ) -> Vec<WorkspaceInfo> {
@@ -107,7 +142,7 @@ async fn resolve_entry_and_parent(
     let entry = workspaces
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not found".to_string())?;
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
     let parent_entry = entry
         .parent_id
         .as_ref()
@@ -124,11 +159,11 @@ async fn resolve_workspace_root(
     let entry = workspaces
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not found".to_string())?;
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
     Ok(PathBuf::from(entry.path))
 }
 
-pub(crate) async fn worktree_setup_status_core(
+pub async fn worktree_setup_status_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
     data_dir: &PathBuf,
@@ -138,7 +173,7 @@ pub(crate) async fn worktree_setup_status_core(
         workspaces
             .get(workspace_id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
     };
 
     let script = normalize_setup_script(entry.settings.worktree_setup_script.clone());
@@ -152,7 +187,7 @@ pub(crate) async fn worktree_setup_status_core(
     Ok(WorktreeSetupStatus { should_run, script })
 }
 
-pub(crate) async fn worktree_setup_mark_ran_core(
+pub async fn worktree_setup_mark_ran_core(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
     data_dir: &PathBuf,
@@ -162,7 +197,7 @@ pub(crate) async fn worktree_setup_mark_ran_core(
         workspaces
             .get(workspace_id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
     };
     if !entry.kind.is_worktree() {
         return Err("Not a worktree workspace.".to_string());
@@ -181,7 +216,7 @@ pub(crate) async fn worktree_setup_mark_ran_core(
     Ok(())
 }
 
-pub(crate) async fn add_workspace_core<F, Fut>(
+pub async fn add_workspace_core<F, Fut>(
     path: String,
     codex_bin: Option<String>,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
@@ -191,7 +226,7 @@ pub(crate) async fn add_workspace_core<F, Fut>(
     spawn_session: F,
 ) -> Result<WorkspaceInfo, String>
 where
-    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    F: Fn(WorkspaceEntry, Option<String>, Option<CodexArgs>, Option<PathBuf>) -> Fut,
     Fut: Future<Output = Result<Arc<WorkspaceSession>, String>>,
 {
     if !PathBuf::from(&path).is_dir() {
@@ -254,7 +289,7 @@ where
     })
 }
 
-pub(crate) fn run_git_command_unit<F, Fut>(
+pub fn run_git_command_unit<F, Fut>(
     repo_path: &PathBuf,
     args: &[&str],
     run_git_command: F,
@@ -272,7 +307,76 @@ where
     async move { run_git_command(repo_path, args_owned).await.map(|_output| ()) }
 }
 
-pub(crate) async fn add_worktree_core<
+/// Where `add_worktree` starts a new branch: the call's `baseRef`/`fetchBase`, falling
+/// back to the parent's `worktreeBaseRef`/`worktreeFetchBase`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorktreeBase {
+    pub base_ref: Option<String>,
+    pub fetch: Option<bool>,
+}
+
+impl WorktreeBase {
+    fn resolve(&self, parent: &WorkspaceEntry) -> Option<(String, bool)> {
+        let base_ref = trim_setting(self.base_ref.clone())
+            .or_else(|| trim_setting(parent.settings.worktree_base_ref.clone()))?;
+        let fetch = self
+            .fetch
+            .unwrap_or(parent.settings.worktree_fetch_base);
+        Some((base_ref, fetch))
+    }
+}
+
+/// How `add_worktree` sets up a new worktree. By default it is named after its branch,
+/// gets the parent's `AGENTS.md` and refuses protected branches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorktreeOptions {
+    pub name: Option<String>,
+    pub copy_agents_md: bool,
+    pub allow_protected_branch: bool,
+    pub base: WorktreeBase,
+}
+
+impl Default for WorktreeOptions {
+    fn default() -> Self {
+        Self {
+            name: None,
+            copy_agents_md: true,
+            allow_protected_branch: false,
+            base: WorktreeBase::default(),
+        }
+    }
+}
+
+/// Fetches `base_ref` when it names a remote branch (`origin/main`); local refs are
+/// left alone.
+async fn fetch_base_ref<FRemoteExists, FutRemoteExists, FRunGit, FutRunGit>(
+    repo_path: &PathBuf,
+    base_ref: &str,
+    git_remote_exists: &FRemoteExists,
+    run_git_command: &FRunGit,
+) -> Result<(), String>
+where
+    FRemoteExists: Fn(&PathBuf, &str) -> FutRemoteExists,
+    FutRemoteExists: Future<Output = Result<bool, String>>,
+    FRunGit: Fn(&PathBuf, &[&str]) -> FutRunGit,
+    FutRunGit: Future<Output = Result<(), String>>,
+{
+    let Some((remote, branch)) = base_ref
+        .strip_prefix("refs/remotes/")
+        .unwrap_or(base_ref)
+        .split_once('/')
+    else {
+        return Ok(());
+    };
+    if branch.is_empty() || !git_remote_exists(repo_path, remote).await? {
+        return Ok(());
+    }
+    run_git_command(repo_path, &["fetch", remote, branch])
+        .await
+        .map_err(|err| format!("Failed to fetch {base_ref}: {err}"))
+}
+
+pub async fn add_worktree_core<
     FSpawn,
     FutSpawn,
     FSanitize,
@@ -281,13 +385,14 @@ pub(crate) async fn add_worktree_core<
     FutBranchExists,
     FFindRemoteTracking,
     FutFindRemoteTracking,
+    FRemoteExists,
+    FutRemoteExists,
     FRunGit,
     FutRunGit,
 >(
     parent_id: String,
     branch: String,
-    name: Option<String>,
-    copy_agents_md: bool,
+    options: WorktreeOptions,
     data_dir: &PathBuf,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
@@ -297,11 +402,12 @@ pub(crate) async fn add_worktree_core<
     unique_worktree_path: FUniquePath,
     git_branch_exists: FBranchExists,
     git_find_remote_tracking_branch: Option<FFindRemoteTracking>,
+    git_remote_exists: FRemoteExists,
     run_git_command: FRunGit,
     spawn_session: FSpawn,
 ) -> Result<WorkspaceInfo, String>
 where
-    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> FutSpawn,
+    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<CodexArgs>, Option<PathBuf>) -> FutSpawn,
     FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
     FSanitize: Fn(&str) -> String,
     FUniquePath: Fn(&PathBuf, &str) -> Result<PathBuf, String>,
@@ -309,6 +415,8 @@ where
     FutBranchExists: Future<Output = Result<bool, String>>,
     FFindRemoteTracking: Fn(&PathBuf, &str) -> FutFindRemoteTracking,
     FutFindRemoteTracking: Future<Output = Result<Option<String>, String>>,
+    FRemoteExists: Fn(&PathBuf, &str) -> FutRemoteExists,
+    FutRemoteExists: Future<Output = Result<bool, String>>,
     FRunGit: Fn(&PathBuf, &[&str]) -> FutRunGit,
     FutRunGit: Future<Output = Result<(), String>>,
 {
@@ -316,6 +424,12 @@ where
     if branch.is_empty() {
         return Err("Branch name is required.".to_string());
     }
+    let WorktreeOptions {
+        name,
+        copy_agents_md,
+        allow_protected_branch,
+        base,
+    } = options;
     let name = name
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty());
@@ -331,6 +445,12 @@ where
     if parent_entry.kind.is_worktree() {
         return Err("Cannot create a worktree from another worktree.".to_string());
     }
+    ensure_branch_allowed(
+        &protected_branch_patterns(&parent_entry, None),
+        &branch,
+        allow_protected_branch,
+        "create a worktree on",
+    )?;
 
     let worktree_root = data_dir.join("worktrees").join(&parent_entry.id);
     std::fs::create_dir_all(&worktree_root)
@@ -341,49 +461,53 @@ where
     let worktree_path_string = worktree_path.to_string_lossy().to_string();
 
     let repo_path = PathBuf::from(&parent_entry.path);
+    // A sparse parent's worktree starts empty and is filled with the parent's patterns,
+    // so a huge monorepo is never fully checked out.
+    let sparse = sparse_checkout_core::read_sparse_checkout(&repo_path)
+        .await
+        .ok()
+        .filter(|sparse| sparse.enabled);
+    let mut add_args = vec!["worktree", "add"];
+    if sparse.is_some() {
+        add_args.push("--no-checkout");
+    }
     let branch_exists = git_branch_exists(&repo_path, &branch).await?;
-    if branch_exists {
-        run_git_command(
-            &repo_path,
-            &["worktree", "add", &worktree_path_string, &branch],
-        )
-        .await?;
-    } else if let Some(find_remote_tracking) = git_find_remote_tracking_branch {
-        if let Some(remote_ref) = find_remote_tracking(&repo_path, &branch).await? {
-            run_git_command(
-                &repo_path,
-                &[
-                    "worktree",
-                    "add",
-                    "-b",
-                    &branch,
-                    &worktree_path_string,
-                    &remote_ref,
-                ],
-            )
-            .await?;
-        } else {
-            run_git_command(
-                &repo_path,
-                &["worktree", "add", "-b", &branch, &worktree_path_string],
-            )
-            .await?;
+    let remote_ref = match git_find_remote_tracking_branch {
+        Some(find_remote_tracking) if !branch_exists => {
+            find_remote_tracking(&repo_path, &branch).await?
         }
+        _ => None,
+    };
+    // A branch that already exists, locally or on the remote, keeps its history; only a
+    // new one starts from the base.
+    let base_ref = match (branch_exists || remote_ref.is_some(), base.resolve(&parent_entry)) {
+        (false, Some((base_ref, fetch))) => {
+            if fetch {
+                fetch_base_ref(&repo_path, &base_ref, &git_remote_exists, &run_git_command)
+                    .await?;
+            }
+            Some(base_ref)
+        }
+        _ => None,
+    };
+    if branch_exists {
+        add_args.extend([worktree_path_string.as_str(), branch.as_str()]);
     } else {
-        run_git_command(
-            &repo_path,
-            &["worktree", "add", "-b", &branch, &worktree_path_string],
-        )
-        .await?;
+        add_args.extend(["-b", branch.as_str(), worktree_path_string.as_str()]);
+        if let Some(start) = remote_ref.as_deref().or(base_ref.as_deref()) {
+            add_args.push(start);
+        }
+    }
+    run_git_command(&repo_path, &add_args).await?;
+    if let Some(sparse) = &sparse {
+        sparse_checkout_core::apply_sparse_checkout(&worktree_path, sparse).await?;
     }
 
     if copy_agents_md {
         if let Err(error) = copy_agents_md_from_parent_to_worktree(&repo_path, &worktree_path) {
-            eprintln!(
-                "add_worktree: optional {} copy failed for {}: {}",
-                AGENTS_MD_FILE_NAME,
-                worktree_path.display(),
-                error
+            tracing::warn!(
+                worktree = %worktree_path.display(),
+                "add_worktree: optional {AGENTS_MD_FILE_NAME} copy failed: {error}"
             );
         }
     }
@@ -436,7 +560,7 @@ where
     })
 }
 
-pub(crate) async fn connect_workspace_core<F, Fut>(
+pub async fn connect_workspace_core<F, Fut>(
     workspace_id: String,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
@@ -444,7 +568,7 @@ pub(crate) async fn connect_workspace_core<F, Fut>(
     spawn_session: F,
 ) -> Result<(), String>
 where
-    F: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> Fut,
+    F: Fn(WorkspaceEntry, Option<String>, Option<CodexArgs>, Option<PathBuf>) -> Fut,
     Fut: Future<Output = Result<Arc<WorkspaceSession>, String>>,
 {
     let (entry, parent_entry) = resolve_entry_and_parent(workspaces, &workspace_id).await?;
@@ -461,7 +585,30 @@ where
     Ok(())
 }
 
-async fn kill_session_by_id(
+/// The command `connect_workspace` would spawn for this workspace, without spawning it.
+pub async fn preview_codex_command_core(
+    workspace_id: String,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    app_settings: &Mutex<AppSettings>,
+) -> Result<CodexCommand, String> {
+    let (entry, parent_entry) = resolve_entry_and_parent(workspaces, &workspace_id).await?;
+    let (default_bin, codex_args) = {
+        let settings = app_settings.lock().await;
+        (
+            settings.codex_bin.clone(),
+            resolve_workspace_codex_args(&entry, parent_entry.as_ref(), Some(&settings)),
+        )
+    };
+    let codex_home = resolve_workspace_codex_home(&entry, parent_entry.as_ref());
+    CodexCommand::for_session(
+        &entry,
+        default_bin.as_deref(),
+        codex_args.as_ref(),
+        codex_home.as_deref(),
+    )
+}
+
+pub async fn kill_session_by_id(
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     id: &str,
 ) {
@@ -471,7 +618,7 @@ async fn kill_session_by_id(
     }
 }
 
-pub(crate) async fn remove_workspace_core<
+pub async fn remove_workspace_core<
     FRunGit,
     FutRunGit,
     FIsMissing,
@@ -498,7 +645,7 @@ where
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
         if entry.kind.is_worktree() {
             return Err("Use remove_worktree for worktree agents.".to_string());
         }
@@ -507,6 +654,7 @@ where
             .filter(|workspace| workspace.parent_id.as_deref() == Some(&id))
             .cloned()
             .collect::<Vec<_>>();
+        removal_core::ensure_removable(&entry, &children)?;
         (entry, children)
     };
 
@@ -580,7 +728,7 @@ where
     Ok(())
 }
 
-pub(crate) async fn remove_worktree_core<FRunGit, FutRunGit, FIsMissing, FRemoveDirAll>(
+pub async fn remove_worktree_core<FRunGit, FutRunGit, FIsMissing, FRemoveDirAll>(
     id: String,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
@@ -600,10 +748,11 @@ where
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
         if !entry.kind.is_worktree() {
             return Err("Not a worktree workspace.".to_string());
         }
+        removal_core::ensure_removable(&entry, &[])?;
         let parent_id = entry
             .parent_id
             .clone()
@@ -647,7 +796,7 @@ where
     Ok(())
 }
 
-pub(crate) async fn rename_worktree_core<
+pub async fn rename_worktree_core<
     FSpawn,
     FutSpawn,
     FResolveGitRoot,
@@ -660,6 +809,7 @@ pub(crate) async fn rename_worktree_core<
 >(
     id: String,
     branch: String,
+    allow_protected_branch: bool,
     data_dir: &PathBuf,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
@@ -673,7 +823,7 @@ pub(crate) async fn rename_worktree_core<
     spawn_session: FSpawn,
 ) -> Result<WorkspaceInfo, String>
 where
-    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> FutSpawn,
+    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<CodexArgs>, Option<PathBuf>) -> FutSpawn,
     FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
     FResolveGitRoot: Fn(&WorkspaceEntry) -> Result<PathBuf, String>,
     FUniqueBranch: Fn(&PathBuf, &str) -> FutUniqueBranch,
@@ -693,7 +843,7 @@ where
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
         if !entry.kind.is_worktree() {
             return Err("Not a worktree workspace.".to_string());
         }
@@ -716,12 +866,25 @@ where
     if old_branch == trimmed {
         return Err("Branch name is unchanged.".to_string());
     }
+    let protected_branches = protected_branch_patterns(&entry, Some(&parent));
+    ensure_branch_allowed(
+        &protected_branches,
+        &old_branch,
+        allow_protected_branch,
+        "rename",
+    )?;
 
     let parent_root = resolve_git_root(&parent)?;
     let final_branch = unique_branch_name(&parent_root, trimmed).await?;
     if final_branch == old_branch {
         return Err("Branch name is unchanged.".to_string());
     }
+    ensure_branch_allowed(
+        &protected_branches,
+        &final_branch,
+        allow_protected_branch,
+        "rename a worktree to",
+    )?;
 
     run_git_command(&parent_root, &["branch", "-m", &old_branch, &final_branch]).await?;
 
@@ -729,6 +892,8 @@ where
     std::fs::create_dir_all(&worktree_root)
         .map_err(|err| format!("Failed to create worktree directory: {err}"))?;
 
+    // Read before the move: a `.codexmonitor` home inside the worktree moves with it.
+    let previous_codex_home = resolve_workspace_codex_home(&entry, Some(&parent));
     let safe_name = sanitize_worktree_name(&final_branch);
     let current_path = PathBuf::from(&entry.path);
     let next_path = unique_worktree_path_for_rename(&worktree_root, &safe_name, &current_path)?;
@@ -750,7 +915,7 @@ where
         let mut workspaces = workspaces.lock().await;
         let entry = match workspaces.get_mut(&id) {
             Some(entry) => entry,
-            None => return Err("workspace not found".to_string()),
+            None => return Err(i18n_core::message(MessageKey::WorkspaceNotFound)),
         };
         if entry.name.trim() == old_branch {
             entry.name = final_branch.clone();
@@ -772,8 +937,16 @@ where
     };
     write_workspaces(storage_path, &list)?;
 
-    let was_connected = sessions.lock().await.contains_key(&entry_snapshot.id);
-    if was_connected {
+    // A running app-server outlives its folder being moved, and new threads and turns get
+    // the new path as `cwd`, so the session is kept and its thread state with it. It is only
+    // respawned when its CODEX_HOME changed with the path, or where a process's working
+    // directory can't be moved from under it.
+    let running = sessions.lock().await.get(&entry_snapshot.id).cloned();
+    let relocatable = !cfg!(windows)
+        && resolve_workspace_codex_home(&entry_snapshot, Some(&parent)) == previous_codex_home;
+    if let Some(session) = running.as_ref().filter(|_| relocatable) {
+        session.replace_entry(entry_snapshot.clone());
+    } else if running.is_some() {
         kill_session_by_id(sessions, &entry_snapshot.id).await;
         let (default_bin, codex_args) = {
             let settings = app_settings.lock().await;
@@ -791,9 +964,9 @@ where
                     .insert(entry_snapshot.id.clone(), session);
             }
             Err(error) => {
-                eprintln!(
-                    "rename_worktree: respawn failed for {} after rename: {error}",
-                    entry_snapshot.id
+                tracing::warn!(
+                    workspace_id = %entry_snapshot.id,
+                    "rename_worktree: respawn failed after rename: {error}"
                 );
             }
         }
@@ -813,7 +986,7 @@ where
     })
 }
 
-pub(crate) async fn rename_worktree_upstream_core<
+pub async fn rename_worktree_upstream_core<
     FResolveGitRoot,
     FBranchExists,
     FutBranchExists,
@@ -829,6 +1002,7 @@ pub(crate) async fn rename_worktree_upstream_core<
     id: String,
     old_branch: String,
     new_branch: String,
+    allow_protected_branch: bool,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     resolve_git_root: FResolveGitRoot,
     git_branch_exists: FBranchExists,
@@ -859,12 +1033,12 @@ where
         return Err("Branch name is unchanged.".to_string());
     }
 
-    let (_entry, parent) = {
+    let (entry, parent) = {
         let workspaces = workspaces.lock().await;
         let entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
         if !entry.kind.is_worktree() {
             return Err("Not a worktree workspace.".to_string());
         }
@@ -879,6 +1053,20 @@ where
         (entry, parent)
     };
 
+    let protected_branches = protected_branch_patterns(&entry, Some(&parent));
+    ensure_branch_allowed(
+        &protected_branches,
+        &old_branch,
+        allow_protected_branch,
+        "rename the upstream of",
+    )?;
+    ensure_branch_allowed(
+        &protected_branches,
+        &new_branch,
+        allow_protected_branch,
+        "push",
+    )?;
+
     let parent_root = resolve_git_root(&parent)?;
     if !git_branch_exists(&parent_root, &new_branch).await? {
         return Err("Local branch not found.".to_string());
@@ -925,13 +1113,20 @@ where
     Ok(())
 }
 
-pub(crate) async fn update_workspace_settings_core<
+/// A workspace settings write; it fails when `expected_revision` is stale.
+#[derive(Debug, Clone)]
+pub struct WorkspaceSettingsUpdate {
+    pub id: String,
+    pub settings: WorkspaceSettings,
+    pub expected_revision: Option<u64>,
+}
+
+pub async fn update_workspace_settings_core<
     FApplySettings,
     FSpawn,
     FutSpawn,
 >(
-    id: String,
-    mut settings: WorkspaceSettings,
+    update: WorkspaceSettingsUpdate,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     app_settings: &Mutex<AppSettings>,
@@ -942,10 +1137,25 @@ pub(crate) async fn update_workspace_settings_core<
 where
     FApplySettings: Fn(&mut HashMap<String, WorkspaceEntry>, &str, WorkspaceSettings)
         -> Result<WorkspaceEntry, String>,
-    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<String>, Option<PathBuf>) -> FutSpawn,
+    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<CodexArgs>, Option<PathBuf>) -> FutSpawn,
     FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
 {
+    let WorkspaceSettingsUpdate {
+        id,
+        mut settings,
+        expected_revision,
+    } = update;
     settings.worktree_setup_script = normalize_setup_script(settings.worktree_setup_script);
+    settings.worktree_base_ref = trim_setting(settings.worktree_base_ref.take());
+    normalize_turn_defaults(&mut settings)?;
+    settings.protected_branches = normalize_branch_patterns(settings.protected_branches);
+    validate_codex_args(settings.codex_args.as_deref(), settings.codex_launch.as_ref())?;
+    settings.account_profile = trim_setting(settings.account_profile.take());
+    if let Some(profile) = settings.account_profile.as_deref() {
+        if resolve_account_profile_home(profile).is_none() {
+            return Err(format!("Unknown account profile: {profile}"));
+        }
+    }
 
     let (
         previous_entry,
@@ -960,10 +1170,19 @@ where
         let previous_entry = workspaces
             .get(&id)
             .cloned()
-            .ok_or_else(|| "workspace not found".to_string())?;
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
         let previous_codex_home = previous_entry.settings.codex_home.clone();
-        let previous_codex_args = previous_entry.settings.codex_args.clone();
+        let previous_codex_args = (
+            previous_entry.settings.codex_args.clone(),
+            previous_entry.settings.codex_launch.clone(),
+        );
         let previous_worktree_setup_script = previous_entry.settings.worktree_setup_script.clone();
+        check_revision(
+            &format!("workspace {id}"),
+            expected_revision,
+            previous_entry.settings.revision,
+        )?;
+        settings.revision = previous_entry.settings.revision + 1;
         let entry_snapshot = apply_settings_update(&mut workspaces, &id, settings)?;
         let parent_entry = entry_snapshot
             .parent_id
@@ -986,11 +1205,21 @@ where
         )
     };
 
-    let codex_home_changed = previous_codex_home != entry_snapshot.settings.codex_home;
-    let codex_args_changed = previous_codex_args != entry_snapshot.settings.codex_args;
+    let codex_home_changed = previous_codex_home != entry_snapshot.settings.codex_home
+        || previous_entry.settings.account_profile != entry_snapshot.settings.account_profile;
+    let codex_args_changed = previous_codex_args.0 != entry_snapshot.settings.codex_args
+        || previous_codex_args.1 != entry_snapshot.settings.codex_launch;
     let worktree_setup_script_changed =
         previous_worktree_setup_script != entry_snapshot.settings.worktree_setup_script;
-    let connected = sessions.lock().await.contains_key(&id);
+    let running = sessions.lock().await.get(&id).cloned();
+    let connected = running.is_some();
+    if let Some(session) = &running {
+        if !codex_home_changed && !codex_args_changed {
+            // Keep the session's copy of the settings current so the turn defaults
+            // apply to the next message.
+            session.replace_entry(entry_snapshot.clone());
+        }
+    }
     if connected && (codex_home_changed || codex_args_changed) {
         let rollback_entry = previous_entry.clone();
         let (default_bin, codex_args) = {
@@ -1047,9 +1276,9 @@ where
             {
                 Ok(session) => session,
                 Err(error) => {
-                    eprintln!(
-                        "update_workspace_settings: respawn failed for worktree {} after parent override change: {error}",
-                        child.id
+                    tracing::warn!(
+                        workspace_id = %child.id,
+                        "update_workspace_settings: respawn failed after parent override change: {error}"
                     );
                     continue;
                 }
@@ -1097,9 +1326,10 @@ where
     })
 }
 
-pub(crate) async fn update_workspace_codex_bin_core(
+pub async fn update_workspace_codex_bin_core(
     id: String,
     codex_bin: Option<String>,
+    expected_revision: Option<u64>,
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
     storage_path: &PathBuf,
@@ -1108,10 +1338,16 @@ pub(crate) async fn update_workspace_codex_bin_core(
         let mut workspaces = workspaces.lock().await;
         let entry_snapshot = match workspaces.get_mut(&id) {
             Some(entry) => {
+                check_revision(
+                    &format!("workspace {id}"),
+                    expected_revision,
+                    entry.settings.revision,
+                )?;
+                entry.settings.revision += 1;
                 entry.codex_bin = codex_bin.clone();
                 entry.clone()
             }
-            None => return Err("workspace not found".to_string()),
+            None => return Err(i18n_core::message(MessageKey::WorkspaceNotFound)),
         };
         let list: Vec<_> = workspaces.values().cloned().collect();
         (entry_snapshot, list)
@@ -1132,7 +1368,170 @@ pub(crate) async fn update_workspace_codex_bin_core(
     })
 }
 
-pub(crate) async fn list_workspace_files_core<F>(
+fn relocate_path_prefix(value: &str, old_root: &str, new_root: &str) -> Option<String> {
+    let old_root = old_root.trim_end_matches(['/', '\\']);
+    let rest = value.strip_prefix(old_root)?;
+    if !(rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\')) {
+        return None;
+    }
+    Some(format!("{}{rest}", new_root.trim_end_matches(['/', '\\'])))
+}
+
+/// Moves workspace `id` to `new_path`, an existing folder.
+#[derive(Debug, Clone)]
+pub struct WorkspaceRelocation {
+    pub id: String,
+    pub new_path: String,
+}
+
+pub async fn relocate_workspace_core<FSpawn, FutSpawn, FRunGit, FutRunGit>(
+    relocation: WorkspaceRelocation,
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    app_settings: &Mutex<AppSettings>,
+    storage_path: &PathBuf,
+    run_git_command: FRunGit,
+    spawn_session: FSpawn,
+) -> Result<WorkspaceInfo, String>
+where
+    FSpawn: Fn(WorkspaceEntry, Option<String>, Option<CodexArgs>, Option<PathBuf>) -> FutSpawn,
+    FutSpawn: Future<Output = Result<Arc<WorkspaceSession>, String>>,
+    FRunGit: Fn(&PathBuf, &[&str]) -> FutRunGit,
+    FutRunGit: Future<Output = Result<(), String>>,
+{
+    let WorkspaceRelocation { id, new_path } = relocation;
+    let trimmed = new_path.trim();
+    if trimmed.is_empty() {
+        return Err("New workspace path is required.".to_string());
+    }
+    let next_root = PathBuf::from(trimmed);
+    if !next_root.is_dir() {
+        return Err("Workspace path must be a folder.".to_string());
+    }
+    let next_path = trimmed.to_string();
+
+    let (entry, parent, children) = {
+        let workspaces = workspaces.lock().await;
+        let entry = workspaces
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
+        if entry.path == next_path {
+            return Err("Workspace path is unchanged.".to_string());
+        }
+        if workspaces
+            .values()
+            .any(|other| other.id != id && other.path == next_path)
+        {
+            return Err("Another workspace already uses this path.".to_string());
+        }
+        let parent = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
+        let children = workspaces
+            .values()
+            .filter(|child| child.parent_id.as_deref() == Some(id.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        (entry, parent, children)
+    };
+
+    if (entry.kind.is_worktree() || !children.is_empty()) && !next_root.join(".git").exists() {
+        return Err("New path is not a git repository.".to_string());
+    }
+
+    // Point git's worktree links at the new location before persisting the move.
+    if entry.kind.is_worktree() {
+        let parent = parent.ok_or_else(|| "worktree parent not found".to_string())?;
+        let parent_root = PathBuf::from(&parent.path);
+        run_git_command(&parent_root, &["worktree", "repair", &next_path])
+            .await
+            .map_err(|err| format!("Failed to repair worktree links: {err}"))?;
+    } else if !children.is_empty() {
+        let child_paths = children
+            .iter()
+            .map(|child| child.path.as_str())
+            .filter(|path| PathBuf::from(path).is_dir())
+            .collect::<Vec<_>>();
+        let mut args = vec!["worktree", "repair"];
+        args.extend(child_paths);
+        run_git_command(&next_root, &args)
+            .await
+            .map_err(|err| format!("Failed to repair worktree links: {err}"))?;
+    }
+
+    let (entry_snapshot, parent_snapshot, list) = {
+        let mut workspaces = workspaces.lock().await;
+        let entry = match workspaces.get_mut(&id) {
+            Some(entry) => entry,
+            None => return Err(i18n_core::message(MessageKey::WorkspaceNotFound)),
+        };
+        let old_path = std::mem::replace(&mut entry.path, next_path.clone());
+        if let Some(git_root) = entry.settings.git_root.as_deref() {
+            if let Some(relocated) = relocate_path_prefix(git_root, &old_path, &next_path) {
+                entry.settings.git_root = Some(relocated);
+            }
+        }
+        entry.settings.revision += 1;
+        let snapshot = entry.clone();
+        let parent_snapshot = snapshot
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
+        let list: Vec<_> = workspaces.values().cloned().collect();
+        (snapshot, parent_snapshot, list)
+    };
+    write_workspaces(storage_path, &list)?;
+
+    let was_connected = sessions.lock().await.contains_key(&entry_snapshot.id);
+    if was_connected {
+        kill_session_by_id(sessions, &entry_snapshot.id).await;
+        let (default_bin, codex_args) = {
+            let settings = app_settings.lock().await;
+            (
+                settings.codex_bin.clone(),
+                resolve_workspace_codex_args(
+                    &entry_snapshot,
+                    parent_snapshot.as_ref(),
+                    Some(&settings),
+                ),
+            )
+        };
+        let codex_home = resolve_workspace_codex_home(&entry_snapshot, parent_snapshot.as_ref());
+        match spawn_session(entry_snapshot.clone(), default_bin, codex_args, codex_home).await {
+            Ok(session) => {
+                sessions
+                    .lock()
+                    .await
+                    .insert(entry_snapshot.id.clone(), session);
+            }
+            Err(error) => {
+                tracing::warn!(
+                    workspace_id = %entry_snapshot.id,
+                    "relocate_workspace: respawn failed after relocation: {error}"
+                );
+            }
+        }
+    }
+
+    let connected = sessions.lock().await.contains_key(&entry_snapshot.id);
+    Ok(WorkspaceInfo {
+        id: entry_snapshot.id,
+        name: entry_snapshot.name,
+        path: entry_snapshot.path,
+        codex_bin: entry_snapshot.codex_bin,
+        connected,
+        kind: entry_snapshot.kind,
+        parent_id: entry_snapshot.parent_id,
+        worktree: entry_snapshot.worktree,
+        settings: entry_snapshot.settings,
+    })
+}
+
+pub async fn list_workspace_files_core<F>(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
     list_files: F,
@@ -1144,7 +1543,7 @@ where
     Ok(list_files(&root))
 }
 
-pub(crate) async fn read_workspace_file_core<F, T>(
+pub async fn read_workspace_file_core<F, T>(
     workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
     workspace_id: &str,
     path: &str,
@@ -1157,7 +1556,8 @@ where
     read_file(&root, path)
 }
 
-fn sort_workspaces(workspaces: &mut [WorkspaceInfo]) {
+/// Explicit `sort_order` first, then name, then id; unordered workspaces go last.
+pub fn sort_workspaces(workspaces: &mut [WorkspaceInfo]) {
     workspaces.sort_by(|a, b| {
         let a_order = a.settings.sort_order.unwrap_or(u32::MAX);
         let b_order = b.settings.sort_order.unwrap_or(u32::MAX);
@@ -1173,6 +1573,8 @@ fn sort_workspaces(workspaces: &mut [WorkspaceInfo]) {
 #[cfg(test)]
 mod tests {
     use super::copy_agents_md_from_parent_to_worktree;
+    use super::relocate_path_prefix;
+    use super::{normalize_turn_defaults, WorkspaceSettings};
     use super::AGENTS_MD_FILE_NAME;
     use uuid::Uuid;
 
@@ -1221,4 +1623,35 @@ mod tests {
         let _ = std::fs::remove_dir_all(parent);
         let _ = std::fs::remove_dir_all(worktree);
     }
+
+    #[test]
+    fn normalizes_turn_defaults() {
+        let mut settings = WorkspaceSettings {
+            default_model: Some("  gpt-5  ".to_string()),
+            default_effort: Some(" ".to_string()),
+            default_access_mode: Some("read-only".to_string()),
+            ..WorkspaceSettings::default()
+        };
+        normalize_turn_defaults(&mut settings).expect("valid defaults");
+        assert_eq!(settings.default_model.as_deref(), Some("gpt-5"));
+        assert_eq!(settings.default_effort, None);
+        assert_eq!(settings.default_access_mode.as_deref(), Some("read-only"));
+
+        settings.default_access_mode = Some("yolo".to_string());
+        assert!(normalize_turn_defaults(&mut settings).is_err());
+    }
+
+    #[test]
+    fn relocate_path_prefix_only_rewrites_nested_paths() {
+        assert_eq!(
+            relocate_path_prefix("/old/repo/packages/app", "/old/repo", "/new/repo"),
+            Some("/new/repo/packages/app".to_string())
+        );
+        assert_eq!(
+            relocate_path_prefix("/old/repo", "/old/repo/", "/new/repo"),
+            Some("/new/repo".to_string())
+        );
+        assert_eq!(relocate_path_prefix("/old/repository", "/old/repo", "/new/repo"), None);
+        assert_eq!(relocate_path_prefix("relative/path", "/old/repo", "/new/repo"), None);
+    }
 }
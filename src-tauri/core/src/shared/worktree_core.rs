@@ -19,15 +19,15 @@ fn sanitize_name(value: &str, fallback: &str) -> String {
     }
 }
 
-pub(crate) fn sanitize_worktree_name(branch: &str) -> String {
+pub fn sanitize_worktree_name(branch: &str) -> String {
     sanitize_name(branch, "worktree")
 }
 
-pub(crate) fn sanitize_clone_dir_name(name: &str) -> String {
+pub fn sanitize_clone_dir_name(name: &str) -> String {
     sanitize_name(name, "copy")
 }
 
-pub(crate) fn unique_worktree_path_best_effort(base_dir: &PathBuf, name: &str) -> PathBuf {
+pub fn unique_worktree_path_best_effort(base_dir: &PathBuf, name: &str) -> PathBuf {
     let mut candidate = base_dir.join(name);
     if !candidate.exists() {
         return candidate;
@@ -42,7 +42,7 @@ pub(crate) fn unique_worktree_path_best_effort(base_dir: &PathBuf, name: &str) -
     candidate
 }
 
-pub(crate) fn unique_worktree_path_strict(base_dir: &PathBuf, name: &str) -> Result<PathBuf, String> {
+pub fn unique_worktree_path_strict(base_dir: &PathBuf, name: &str) -> Result<PathBuf, String> {
     let candidate = base_dir.join(name);
     if !candidate.exists() {
         return Ok(candidate);
@@ -59,7 +59,7 @@ pub(crate) fn unique_worktree_path_strict(base_dir: &PathBuf, name: &str) -> Res
     ))
 }
 
-pub(crate) fn unique_worktree_path_for_rename(
+pub fn unique_worktree_path_for_rename(
     base_dir: &PathBuf,
     name: &str,
     current_path: &PathBuf,
@@ -83,12 +83,12 @@ pub(crate) fn unique_worktree_path_for_rename(
     ))
 }
 
-pub(crate) fn build_clone_destination_path(copies_folder: &PathBuf, copy_name: &str) -> PathBuf {
+pub fn build_clone_destination_path(copies_folder: &PathBuf, copy_name: &str) -> PathBuf {
     let safe_name = sanitize_clone_dir_name(copy_name);
     unique_worktree_path_best_effort(copies_folder, &safe_name)
 }
 
-pub(crate) fn null_device_path() -> &'static str {
+pub fn null_device_path() -> &'static str {
     if cfg!(windows) {
         "NUL"
     } else {
@@ -0,0 +1,241 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::shared::git_core::{run_git_command, run_git_command_bytes, run_git_diff};
+use crate::shared::process_core::tokio_command;
+use crate::shared::process_limits_core::{acquire_process_slot, ProcessKind};
+use crate::shared::worktree_core::null_device_path;
+use crate::utils::{git_env_path, resolve_git_binary};
+
+/// Patch text returned inline; larger diffs are cut and flagged `truncated`.
+pub const MAX_INLINE_PATCH_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffFile {
+    pub path: String,
+    /// `None` for binary files.
+    pub additions: Option<u64>,
+    pub deletions: Option<u64>,
+    pub untracked: bool,
+}
+
+/// Everything a worktree changed since `base`: commits, staged and unstaged edits,
+/// and untracked files.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeDiff {
+    pub base: String,
+    pub files: Vec<DiffFile>,
+    pub additions: u64,
+    pub deletions: u64,
+    pub patch: String,
+    pub truncated: bool,
+}
+
+/// Parses `git diff --numstat` output; binary files show `-` counts.
+pub fn parse_numstat(output: &str, untracked: bool) -> Vec<DiffFile> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let additions = parts.next()?;
+            let deletions = parts.next()?;
+            let path = parts.next()?.trim();
+            if path.is_empty() {
+                return None;
+            }
+            Some(DiffFile {
+                path: path.to_string(),
+                additions: additions.parse().ok(),
+                deletions: deletions.parse().ok(),
+                untracked,
+            })
+        })
+        .collect()
+}
+
+async fn untracked_files(root: &PathBuf) -> Result<Vec<String>, String> {
+    let output =
+        run_git_command_bytes(root, &["ls-files", "--others", "--exclude-standard", "-z"]).await?;
+    Ok(output
+        .split(|byte| *byte == 0)
+        .filter(|raw| !raw.is_empty())
+        .map(|raw| String::from_utf8_lossy(raw).to_string())
+        .collect())
+}
+
+/// The commit a worktree's changes are measured from: where its `HEAD` and
+/// `base_commit` meet.
+pub async fn diff_base(root: &PathBuf, base_commit: &str) -> Result<String, String> {
+    run_git_command(root, &["merge-base", base_commit, "HEAD"]).await
+}
+
+/// A binary patch of the worktree against `base`, untracked files included, in the
+/// form `git apply` takes.
+pub async fn worktree_patch(root: &PathBuf, base: &str) -> Result<Vec<u8>, String> {
+    let mut patch = run_git_diff(root, &["diff", "--binary", "--no-color", base]).await?;
+    for path in untracked_files(root).await? {
+        let diff = run_git_diff(
+            root,
+            &[
+                "diff",
+                "--binary",
+                "--no-color",
+                "--no-index",
+                "--",
+                null_device_path(),
+                &path,
+            ],
+        )
+        .await?;
+        patch.extend_from_slice(&diff);
+    }
+    Ok(patch)
+}
+
+/// Applies `patch` to the working tree at `root` with a three-way fallback, the way
+/// worktree changes are brought over to their parent.
+pub async fn apply_patch(root: &PathBuf, patch: &[u8]) -> Result<(), String> {
+    let git_bin = resolve_git_binary().map_err(|err| format!("Failed to run git: {err}"))?;
+    let _slot = acquire_process_slot(ProcessKind::Git).await?;
+    let mut child = tokio_command(git_bin)
+        .args(["apply", "--3way", "--whitespace=nowarn", "-"])
+        .current_dir(root)
+        .env("PATH", git_env_path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to run git: {err}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(patch)
+            .await
+            .map_err(|err| format!("Failed to write git apply input: {err}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|err| format!("Failed to run git: {err}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let detail = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(if detail.is_empty() {
+        "Git apply failed.".to_string()
+    } else {
+        detail
+    })
+}
+
+pub async fn worktree_diff_core(
+    root: &PathBuf,
+    base_commit: &str,
+) -> Result<WorktreeDiff, String> {
+    let base = diff_base(root, base_commit).await?;
+    let numstat = run_git_diff(root, &["diff", "--numstat", "--no-renames", &base]).await?;
+    let mut files = parse_numstat(&String::from_utf8_lossy(&numstat), false);
+    for path in untracked_files(root).await? {
+        let numstat = run_git_diff(
+            root,
+            &[
+                "diff",
+                "--numstat",
+                "--no-index",
+                "--",
+                null_device_path(),
+                &path,
+            ],
+        )
+        .await?;
+        files.extend(
+            parse_numstat(&String::from_utf8_lossy(&numstat), true)
+                .into_iter()
+                .map(|file| DiffFile {
+                    path: path.clone(),
+                    ..file
+                }),
+        );
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let patch = worktree_patch(root, &base).await?;
+    let truncated = patch.len() > MAX_INLINE_PATCH_BYTES;
+    let patch =
+        String::from_utf8_lossy(&patch[..patch.len().min(MAX_INLINE_PATCH_BYTES)]).to_string();
+    Ok(WorktreeDiff {
+        base,
+        additions: files.iter().filter_map(|file| file.additions).sum(),
+        deletions: files.iter().filter_map(|file| file.deletions).sum(),
+        files,
+        patch,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use uuid::Uuid;
+
+    #[test]
+    fn diffs_commits_edits_and_untracked_files_against_the_base() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-wtdiff-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create repo");
+        git(&root, &["init", "-q"]);
+        std::fs::write(root.join("a.txt"), "one\ntwo\n").expect("write a");
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "init"]);
+        let base = std::process::Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(&root)
+            .output()
+            .expect("rev-parse");
+        let base = String::from_utf8_lossy(&base.stdout).trim().to_string();
+        git(&root, &["checkout", "-q", "-b", "attempt"]);
+        std::fs::write(root.join("b.txt"), "committed\n").expect("write b");
+        git(&root, &["add", "b.txt"]);
+        git(&root, &["commit", "-q", "-m", "attempt"]);
+        std::fs::write(root.join("a.txt"), "one\n").expect("edit a");
+        std::fs::write(root.join("c.txt"), "new\nfile\n").expect("write c");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        let diff = runtime
+            .block_on(worktree_diff_core(&root, &base))
+            .expect("diff");
+        assert_eq!(diff.base, base);
+        assert_eq!(
+            diff.files
+                .iter()
+                .map(|file| (
+                    file.path.as_str(),
+                    file.additions,
+                    file.deletions,
+                    file.untracked
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("a.txt", Some(0), Some(1), false),
+                ("b.txt", Some(1), Some(0), false),
+                ("c.txt", Some(2), Some(0), true),
+            ]
+        );
+        assert_eq!((diff.additions, diff.deletions), (3, 1));
+        assert!(diff.patch.contains("+++ b/c.txt"));
+        assert!(!diff.truncated);
+
+        assert_eq!(
+            parse_numstat("-\t-\tlogo.png\n4\t0\tsrc/x.rs\n", false)[0].additions,
+            None
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
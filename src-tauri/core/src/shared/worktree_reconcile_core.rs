@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::backend::app_server::WorkspaceSession;
+use crate::shared::git_core::run_git_command;
+use crate::shared::workspaces_core::kill_session_by_id;
+use crate::storage::write_workspaces;
+use crate::types::WorkspaceEntry;
+
+/// One entry of `git worktree list --porcelain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitWorktree {
+    pub path: PathBuf,
+    /// Short branch name; `None` when detached or bare.
+    pub branch: Option<String>,
+    /// Git noticed the worktree's folder is gone.
+    pub prunable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WorktreeIssue {
+    /// The folder is gone. These entries are pruned.
+    Missing,
+    /// The folder exists but the parent repo doesn't list it as a worktree.
+    Unregistered,
+    /// Git has the worktree on another branch than the stored one, or detached.
+    BranchMismatch,
+    /// The parent workspace entry no longer exists.
+    Orphaned,
+    /// `git worktree list` failed in the parent repo, so the entry couldn't be checked.
+    ParentUnavailable,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeMismatch {
+    pub workspace_id: String,
+    pub name: String,
+    pub path: String,
+    pub parent_id: Option<String>,
+    pub issue: WorktreeIssue,
+    pub stored_branch: Option<String>,
+    pub actual_branch: Option<String>,
+    pub detail: Option<String>,
+    /// Whether the entry was removed from the workspace list.
+    pub pruned: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeReconcileReport {
+    pub checked: usize,
+    pub dry_run: bool,
+    pub mismatches: Vec<WorktreeMismatch>,
+}
+
+pub fn parse_worktree_list(output: &str) -> Vec<GitWorktree> {
+    let mut worktrees = Vec::new();
+    let mut current: Option<GitWorktree> = None;
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            worktrees.extend(current.take());
+            current = Some(GitWorktree {
+                path: PathBuf::from(path),
+                branch: None,
+                prunable: false,
+            });
+            continue;
+        }
+        let Some(worktree) = current.as_mut() else {
+            continue;
+        };
+        if let Some(reference) = line.strip_prefix("branch ") {
+            worktree.branch = Some(
+                reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string(),
+            );
+        } else if line == "prunable" || line.starts_with("prunable ") {
+            worktree.prunable = true;
+        }
+    }
+    worktrees.extend(current);
+    worktrees
+}
+
+fn same_path(left: &Path, right: &Path) -> bool {
+    let normalize =
+        |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    left == right || normalize(left) == normalize(right)
+}
+
+/// What is wrong with a stored worktree entry, if anything. `listing` is the parent
+/// repo's `git worktree list`, or `None` when the parent entry is gone.
+fn classify_worktree(
+    entry: &WorkspaceEntry,
+    folder_exists: bool,
+    listing: Option<&Result<Vec<GitWorktree>, String>>,
+) -> Option<WorktreeMismatch> {
+    let stored_branch = entry
+        .worktree
+        .as_ref()
+        .map(|worktree| worktree.branch.clone());
+    let mismatch = |issue, actual_branch, detail| WorktreeMismatch {
+        workspace_id: entry.id.clone(),
+        name: entry.name.clone(),
+        path: entry.path.clone(),
+        parent_id: entry.parent_id.clone(),
+        issue,
+        stored_branch: stored_branch.clone(),
+        actual_branch,
+        detail,
+        pruned: false,
+    };
+    if !folder_exists {
+        return Some(mismatch(WorktreeIssue::Missing, None, None));
+    }
+    let listing = match listing {
+        None => return Some(mismatch(WorktreeIssue::Orphaned, None, None)),
+        Some(Err(error)) => {
+            return Some(mismatch(
+                WorktreeIssue::ParentUnavailable,
+                None,
+                Some(error.clone()),
+            ))
+        }
+        Some(Ok(listing)) => listing,
+    };
+    let path = Path::new(&entry.path);
+    let Some(listed) = listing.iter().find(|listed| same_path(&listed.path, path)) else {
+        return Some(mismatch(WorktreeIssue::Unregistered, None, None));
+    };
+    if listed.prunable {
+        let detail = Some("git marks the worktree as prunable".to_string());
+        return Some(mismatch(WorktreeIssue::Unregistered, None, detail));
+    }
+    if stored_branch.is_some() && listed.branch != stored_branch {
+        let detail = listed.branch.is_none().then(|| "detached HEAD".to_string());
+        return Some(mismatch(
+            WorktreeIssue::BranchMismatch,
+            listed.branch.clone(),
+            detail,
+        ));
+    }
+    None
+}
+
+/// Compares stored worktree entries against `git worktree list` of their parent repos.
+/// Entries whose folder is gone are dropped (unless `dry_run`) and their sessions closed;
+/// other mismatches are only reported.
+pub async fn reconcile_worktrees_core(
+    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
+    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
+    storage_path: &PathBuf,
+    dry_run: bool,
+) -> Result<WorktreeReconcileReport, String> {
+    let (worktrees, parents) = {
+        let workspaces = workspaces.lock().await;
+        let worktrees = workspaces
+            .values()
+            .filter(|entry| entry.kind.is_worktree())
+            .cloned()
+            .collect::<Vec<_>>();
+        let parents = worktrees
+            .iter()
+            .filter_map(|entry| entry.parent_id.as_ref())
+            .filter_map(|parent_id| workspaces.get(parent_id))
+            .map(|parent| (parent.id.clone(), PathBuf::from(&parent.path)))
+            .collect::<HashMap<_, _>>();
+        (worktrees, parents)
+    };
+
+    let mut listings = HashMap::new();
+    for (parent_id, parent_path) in &parents {
+        let listing = run_git_command(parent_path, &["worktree", "list", "--porcelain"])
+            .await
+            .map(|output| parse_worktree_list(&output));
+        listings.insert(parent_id.clone(), listing);
+    }
+
+    let mut mismatches = worktrees
+        .iter()
+        .filter_map(|entry| {
+            let listing = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| listings.get(parent_id));
+            classify_worktree(entry, Path::new(&entry.path).exists(), listing)
+        })
+        .collect::<Vec<_>>();
+    mismatches.sort_by(|left, right| left.path.cmp(&right.path));
+
+    if !dry_run {
+        let stale = mismatches
+            .iter()
+            .filter(|mismatch| mismatch.issue == WorktreeIssue::Missing)
+            .map(|mismatch| mismatch.workspace_id.clone())
+            .collect::<HashSet<_>>();
+        if !stale.is_empty() {
+            for id in &stale {
+                kill_session_by_id(sessions, id).await;
+            }
+            {
+                let mut workspaces = workspaces.lock().await;
+                workspaces.retain(|id, _| !stale.contains(id));
+                let list: Vec<_> = workspaces.values().cloned().collect();
+                write_workspaces(storage_path, &list)?;
+            }
+            let pruned_parents = mismatches
+                .iter()
+                .filter(|mismatch| stale.contains(&mismatch.workspace_id))
+                .filter_map(|mismatch| mismatch.parent_id.as_ref())
+                .filter_map(|parent_id| parents.get(parent_id))
+                .collect::<HashSet<_>>();
+            for parent_path in pruned_parents {
+                let _ =
+                    run_git_command(parent_path, &["worktree", "prune", "--expire", "now"]).await;
+            }
+            for mismatch in &mut mismatches {
+                mismatch.pruned = stale.contains(&mismatch.workspace_id);
+            }
+        }
+    }
+
+    Ok(WorktreeReconcileReport {
+        checked: worktrees.len(),
+        dry_run,
+        mismatches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{WorkspaceKind, WorkspaceSettings, WorktreeInfo};
+
+    fn worktree_entry(path: &str, branch: &str) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: format!("wt-{branch}"),
+            name: branch.to_string(),
+            path: path.to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some("parent".to_string()),
+            worktree: Some(WorktreeInfo {
+                branch: branch.to_string(),
+            }),
+            settings: WorkspaceSettings::default(),
+        }
+    }
+
+    #[test]
+    fn parses_porcelain_worktree_list() {
+        let output = "worktree /repo\nHEAD abc\nbranch refs/heads/main\n\n\
+                      worktree /repo/wt/detached\nHEAD def\ndetached\n\n\
+                      worktree /repo/wt/gone\nHEAD 123\nbranch refs/heads/feat/x\n\
+                      prunable gitdir file points to non-existent location\n";
+        assert_eq!(
+            parse_worktree_list(output),
+            vec![
+                GitWorktree {
+                    path: PathBuf::from("/repo"),
+                    branch: Some("main".to_string()),
+                    prunable: false,
+                },
+                GitWorktree {
+                    path: PathBuf::from("/repo/wt/detached"),
+                    branch: None,
+                    prunable: false,
+                },
+                GitWorktree {
+                    path: PathBuf::from("/repo/wt/gone"),
+                    branch: Some("feat/x".to_string()),
+                    prunable: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_stored_worktrees() {
+        let listing = Ok(parse_worktree_list(
+            "worktree /repo/wt/a\nbranch refs/heads/a\n\nworktree /repo/wt/b\ndetached\n",
+        ));
+        let issue = |entry: &WorkspaceEntry, exists, listing| {
+            classify_worktree(entry, exists, listing).map(|mismatch| mismatch.issue)
+        };
+
+        let unavailable = Err("not a git repository".to_string());
+        let healthy = worktree_entry("/repo/wt/a", "a");
+        assert_eq!(issue(&healthy, true, Some(&listing)), None);
+        assert_eq!(
+            issue(&healthy, false, Some(&listing)),
+            Some(WorktreeIssue::Missing)
+        );
+        assert_eq!(issue(&healthy, true, None), Some(WorktreeIssue::Orphaned));
+        assert_eq!(
+            issue(&healthy, true, Some(&unavailable)),
+            Some(WorktreeIssue::ParentUnavailable)
+        );
+
+        let detached = classify_worktree(&worktree_entry("/repo/wt/b", "b"), true, Some(&listing))
+            .expect("mismatch");
+        assert_eq!(detached.issue, WorktreeIssue::BranchMismatch);
+        assert_eq!(detached.actual_branch, None);
+        assert_eq!(detached.detail.as_deref(), Some("detached HEAD"));
+
+        assert_eq!(
+            issue(&worktree_entry("/elsewhere", "c"), true, Some(&listing)),
+            Some(WorktreeIssue::Unregistered)
+        );
+    }
+}
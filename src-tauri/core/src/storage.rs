@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-use crate::types::{AppSettings, WorkspaceEntry};
+use crate::types::{AppSettings, ThreadBranchRecord, WorkspaceEntry};
 
-pub(crate) fn read_workspaces(path: &PathBuf) -> Result<HashMap<String, WorkspaceEntry>, String> {
+pub fn read_workspaces(path: &PathBuf) -> Result<HashMap<String, WorkspaceEntry>, String> {
     if !path.exists() {
         return Ok(HashMap::new());
     }
@@ -15,7 +15,7 @@ pub(crate) fn read_workspaces(path: &PathBuf) -> Result<HashMap<String, Workspac
         .collect())
 }
 
-pub(crate) fn write_workspaces(path: &PathBuf, entries: &[WorkspaceEntry]) -> Result<(), String> {
+pub fn write_workspaces(path: &PathBuf, entries: &[WorkspaceEntry]) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
@@ -23,7 +23,7 @@ pub(crate) fn write_workspaces(path: &PathBuf, entries: &[WorkspaceEntry]) -> Re
     std::fs::write(path, data).map_err(|e| e.to_string())
 }
 
-pub(crate) fn read_settings(path: &PathBuf) -> Result<AppSettings, String> {
+pub fn read_settings(path: &PathBuf) -> Result<AppSettings, String> {
     if !path.exists() {
         return Ok(AppSettings::default());
     }
@@ -31,7 +31,7 @@ pub(crate) fn read_settings(path: &PathBuf) -> Result<AppSettings, String> {
     serde_json::from_str(&data).map_err(|e| e.to_string())
 }
 
-pub(crate) fn write_settings(path: &PathBuf, settings: &AppSettings) -> Result<(), String> {
+pub fn write_settings(path: &PathBuf, settings: &AppSettings) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
@@ -39,6 +39,27 @@ pub(crate) fn write_settings(path: &PathBuf, settings: &AppSettings) -> Result<(
     std::fs::write(path, data).map_err(|e| e.to_string())
 }
 
+pub fn read_thread_branches(
+    path: &PathBuf,
+) -> Result<HashMap<String, ThreadBranchRecord>, String> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&data).map_err(|e| e.to_string())
+}
+
+pub fn write_thread_branches(
+    path: &PathBuf,
+    records: &HashMap<String, ThreadBranchRecord>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(records).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::{read_workspaces, write_workspaces};
@@ -0,0 +1,14 @@
+//! Helpers shared by unit tests in several modules.
+
+use std::path::Path;
+
+/// Runs `git` in `root` with a fixed identity, failing the test when it exits non-zero.
+pub(crate) fn git(root: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(root)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
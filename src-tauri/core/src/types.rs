@@ -1,234 +1,238 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::shared::protected_paths_core::DEFAULT_PROTECTED_PATH_GLOBS;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitFileStatus {
-    pub(crate) path: String,
-    pub(crate) status: String,
-    pub(crate) additions: i64,
-    pub(crate) deletions: i64,
+pub struct GitFileStatus {
+    pub path: String,
+    pub status: String,
+    pub additions: i64,
+    pub deletions: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitFileDiff {
-    pub(crate) path: String,
-    pub(crate) diff: String,
+pub struct GitFileDiff {
+    pub path: String,
+    pub diff: String,
     #[serde(default, rename = "oldLines")]
-    pub(crate) old_lines: Option<Vec<String>>,
+    pub old_lines: Option<Vec<String>>,
     #[serde(default, rename = "newLines")]
-    pub(crate) new_lines: Option<Vec<String>>,
+    pub new_lines: Option<Vec<String>>,
     #[serde(default, rename = "isBinary")]
-    pub(crate) is_binary: bool,
+    pub is_binary: bool,
     #[serde(default, rename = "isImage")]
-    pub(crate) is_image: bool,
+    pub is_image: bool,
     #[serde(rename = "oldImageData")]
-    pub(crate) old_image_data: Option<String>,
+    pub old_image_data: Option<String>,
     #[serde(rename = "newImageData")]
-    pub(crate) new_image_data: Option<String>,
+    pub new_image_data: Option<String>,
     #[serde(rename = "oldImageMime")]
-    pub(crate) old_image_mime: Option<String>,
+    pub old_image_mime: Option<String>,
     #[serde(rename = "newImageMime")]
-    pub(crate) new_image_mime: Option<String>,
+    pub new_image_mime: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitCommitDiff {
-    pub(crate) path: String,
-    pub(crate) status: String,
-    pub(crate) diff: String,
+pub struct GitCommitDiff {
+    pub path: String,
+    pub status: String,
+    pub diff: String,
     #[serde(default, rename = "oldLines")]
-    pub(crate) old_lines: Option<Vec<String>>,
+    pub old_lines: Option<Vec<String>>,
     #[serde(default, rename = "newLines")]
-    pub(crate) new_lines: Option<Vec<String>>,
+    pub new_lines: Option<Vec<String>>,
     #[serde(default, rename = "isBinary")]
-    pub(crate) is_binary: bool,
+    pub is_binary: bool,
     #[serde(default, rename = "isImage")]
-    pub(crate) is_image: bool,
+    pub is_image: bool,
     #[serde(rename = "oldImageData")]
-    pub(crate) old_image_data: Option<String>,
+    pub old_image_data: Option<String>,
     #[serde(rename = "newImageData")]
-    pub(crate) new_image_data: Option<String>,
+    pub new_image_data: Option<String>,
     #[serde(rename = "oldImageMime")]
-    pub(crate) old_image_mime: Option<String>,
+    pub old_image_mime: Option<String>,
     #[serde(rename = "newImageMime")]
-    pub(crate) new_image_mime: Option<String>,
+    pub new_image_mime: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitLogEntry {
-    pub(crate) sha: String,
-    pub(crate) summary: String,
-    pub(crate) author: String,
-    pub(crate) timestamp: i64,
+pub struct GitLogEntry {
+    pub sha: String,
+    pub summary: String,
+    pub author: String,
+    pub timestamp: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitLogResponse {
-    pub(crate) total: usize,
-    pub(crate) entries: Vec<GitLogEntry>,
+pub struct GitLogResponse {
+    pub total: usize,
+    pub entries: Vec<GitLogEntry>,
     #[serde(default)]
-    pub(crate) ahead: usize,
+    pub ahead: usize,
     #[serde(default)]
-    pub(crate) behind: usize,
+    pub behind: usize,
     #[serde(default, rename = "aheadEntries")]
-    pub(crate) ahead_entries: Vec<GitLogEntry>,
+    pub ahead_entries: Vec<GitLogEntry>,
     #[serde(default, rename = "behindEntries")]
-    pub(crate) behind_entries: Vec<GitLogEntry>,
+    pub behind_entries: Vec<GitLogEntry>,
     #[serde(default)]
-    pub(crate) upstream: Option<String>,
+    pub upstream: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitHubIssue {
-    pub(crate) number: u64,
-    pub(crate) title: String,
-    pub(crate) url: String,
+pub struct GitHubIssue {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
     #[serde(rename = "updatedAt")]
-    pub(crate) updated_at: String,
+    pub updated_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitHubIssuesResponse {
-    pub(crate) total: usize,
-    pub(crate) issues: Vec<GitHubIssue>,
+pub struct GitHubIssuesResponse {
+    pub total: usize,
+    pub issues: Vec<GitHubIssue>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitHubPullRequestAuthor {
-    pub(crate) login: String,
+pub struct GitHubPullRequestAuthor {
+    pub login: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitHubPullRequest {
-    pub(crate) number: u64,
-    pub(crate) title: String,
-    pub(crate) url: String,
+pub struct GitHubPullRequest {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
     #[serde(rename = "updatedAt")]
-    pub(crate) updated_at: String,
+    pub updated_at: String,
     #[serde(rename = "createdAt")]
-    pub(crate) created_at: String,
-    pub(crate) body: String,
+    pub created_at: String,
+    pub body: String,
     #[serde(rename = "headRefName")]
-    pub(crate) head_ref_name: String,
+    pub head_ref_name: String,
     #[serde(rename = "baseRefName")]
-    pub(crate) base_ref_name: String,
+    pub base_ref_name: String,
     #[serde(rename = "isDraft")]
-    pub(crate) is_draft: bool,
+    pub is_draft: bool,
     #[serde(default)]
-    pub(crate) author: Option<GitHubPullRequestAuthor>,
+    pub author: Option<GitHubPullRequestAuthor>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitHubPullRequestsResponse {
-    pub(crate) total: usize,
+pub struct GitHubPullRequestsResponse {
+    pub total: usize,
     #[serde(rename = "pullRequests")]
-    pub(crate) pull_requests: Vec<GitHubPullRequest>,
+    pub pull_requests: Vec<GitHubPullRequest>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitHubPullRequestDiff {
-    pub(crate) path: String,
-    pub(crate) status: String,
-    pub(crate) diff: String,
+pub struct GitHubPullRequestDiff {
+    pub path: String,
+    pub status: String,
+    pub diff: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct GitHubPullRequestComment {
-    pub(crate) id: u64,
+pub struct GitHubPullRequestComment {
+    pub id: u64,
     #[serde(default)]
-    pub(crate) body: String,
+    pub body: String,
     #[serde(rename = "createdAt")]
-    pub(crate) created_at: String,
+    pub created_at: String,
     #[serde(default)]
-    pub(crate) url: String,
+    pub url: String,
     #[serde(default)]
-    pub(crate) author: Option<GitHubPullRequestAuthor>,
+    pub author: Option<GitHubPullRequestAuthor>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub(crate) struct LocalUsageDay {
-    pub(crate) day: String,
-    pub(crate) input_tokens: i64,
-    pub(crate) cached_input_tokens: i64,
-    pub(crate) output_tokens: i64,
-    pub(crate) total_tokens: i64,
+pub struct LocalUsageDay {
+    pub day: String,
+    pub input_tokens: i64,
+    pub cached_input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
     #[serde(default)]
-    pub(crate) agent_time_ms: i64,
+    pub agent_time_ms: i64,
     #[serde(default)]
-    pub(crate) agent_runs: i64,
+    pub agent_runs: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub(crate) struct LocalUsageTotals {
-    pub(crate) last7_days_tokens: i64,
-    pub(crate) last30_days_tokens: i64,
-    pub(crate) average_daily_tokens: i64,
-    pub(crate) cache_hit_rate_percent: f64,
-    pub(crate) peak_day: Option<String>,
-    pub(crate) peak_day_tokens: i64,
+pub struct LocalUsageTotals {
+    pub last7_days_tokens: i64,
+    pub last30_days_tokens: i64,
+    pub average_daily_tokens: i64,
+    pub cache_hit_rate_percent: f64,
+    pub peak_day: Option<String>,
+    pub peak_day_tokens: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub(crate) struct LocalUsageModel {
-    pub(crate) model: String,
-    pub(crate) tokens: i64,
-    pub(crate) share_percent: f64,
+pub struct LocalUsageModel {
+    pub model: String,
+    pub tokens: i64,
+    pub share_percent: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub(crate) struct LocalUsageSnapshot {
-    pub(crate) updated_at: i64,
-    pub(crate) days: Vec<LocalUsageDay>,
-    pub(crate) totals: LocalUsageTotals,
+pub struct LocalUsageSnapshot {
+    pub updated_at: i64,
+    pub days: Vec<LocalUsageDay>,
+    pub totals: LocalUsageTotals,
     #[serde(default)]
-    pub(crate) top_models: Vec<LocalUsageModel>,
+    pub top_models: Vec<LocalUsageModel>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct BranchInfo {
-    pub(crate) name: String,
-    pub(crate) last_commit: i64,
+pub struct BranchInfo {
+    pub name: String,
+    pub last_commit: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct WorkspaceEntry {
-    pub(crate) id: String,
-    pub(crate) name: String,
-    pub(crate) path: String,
-    pub(crate) codex_bin: Option<String>,
+pub struct WorkspaceEntry {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub codex_bin: Option<String>,
     #[serde(default)]
-    pub(crate) kind: WorkspaceKind,
+    pub kind: WorkspaceKind,
     #[serde(default, rename = "parentId")]
-    pub(crate) parent_id: Option<String>,
+    pub parent_id: Option<String>,
     #[serde(default)]
-    pub(crate) worktree: Option<WorktreeInfo>,
+    pub worktree: Option<WorktreeInfo>,
     #[serde(default)]
-    pub(crate) settings: WorkspaceSettings,
+    pub settings: WorkspaceSettings,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct WorkspaceInfo {
-    pub(crate) id: String,
-    pub(crate) name: String,
-    pub(crate) path: String,
-    pub(crate) connected: bool,
-    pub(crate) codex_bin: Option<String>,
+pub struct WorkspaceInfo {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub connected: bool,
+    pub codex_bin: Option<String>,
     #[serde(default)]
-    pub(crate) kind: WorkspaceKind,
+    pub kind: WorkspaceKind,
     #[serde(default, rename = "parentId")]
-    pub(crate) parent_id: Option<String>,
+    pub parent_id: Option<String>,
     #[serde(default)]
-    pub(crate) worktree: Option<WorktreeInfo>,
+    pub worktree: Option<WorktreeInfo>,
     #[serde(default)]
-    pub(crate) settings: WorkspaceSettings,
+    pub settings: WorkspaceSettings,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "lowercase")]
-pub(crate) enum WorkspaceKind {
+pub enum WorkspaceKind {
     Main,
     Worktree,
 }
@@ -240,283 +244,533 @@ impl Default for WorkspaceKind {
 }
 
 impl WorkspaceKind {
-    pub(crate) fn is_worktree(&self) -> bool {
+    pub fn is_worktree(&self) -> bool {
         matches!(self, WorkspaceKind::Worktree)
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct WorktreeInfo {
-    pub(crate) branch: String,
+pub struct WorktreeInfo {
+    pub branch: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct WorkspaceGroup {
-    pub(crate) id: String,
-    pub(crate) name: String,
+pub struct WorkspaceGroup {
+    pub id: String,
+    pub name: String,
     #[serde(default, rename = "sortOrder")]
-    pub(crate) sort_order: Option<u32>,
+    pub sort_order: Option<u32>,
     #[serde(default, rename = "copiesFolder")]
-    pub(crate) copies_folder: Option<String>,
+    pub copies_folder: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalPolicyDecision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalRequestKind {
+    #[default]
+    Any,
+    Exec,
+    Patch,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ApprovalAccess {
+    #[default]
+    Any,
+    ReadOnly,
+    Write,
+}
+
+/// Daemon-side rule that answers exec/patch approval requests without asking the user.
+/// Every criterion that is set must match; an empty `commandPrefix`/`pathGlobs` matches anything.
+/// Allow rules need every touched path inside `pathGlobs`, deny rules just one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApprovalPolicyRule {
+    pub id: String,
+    pub decision: ApprovalPolicyDecision,
+    #[serde(default)]
+    pub kind: ApprovalRequestKind,
+    #[serde(default, rename = "commandPrefix")]
+    pub command_prefix: Vec<String>,
+    /// Globs relative to the workspace root (`*`, `?`, `**`); absolute globs are matched as-is.
+    #[serde(default, rename = "pathGlobs")]
+    pub path_globs: Vec<String>,
+    #[serde(default)]
+    pub access: ApprovalAccess,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SandboxMode {
+    ReadOnly,
+    #[default]
+    WorkspaceWrite,
+    DangerFullAccess,
+}
+
+/// Named sandbox a message can select instead of the built-in access modes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SandboxPreset {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub mode: SandboxMode,
+    /// Writable in addition to the workspace root; relative entries resolve against it.
+    #[serde(default, rename = "writableRoots")]
+    pub writable_roots: Vec<String>,
+    #[serde(default, rename = "networkAccess")]
+    pub network_access: bool,
+}
+
+/// User-defined `/name` command that expands into `prompt`; see `slash_commands_core`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CustomSlashCommand {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `{{args}}` is replaced with the text after the command name.
+    pub prompt: String,
+}
+
+/// Thread cleanup the daemon runs hourly; see `retention_core`. Unset limits are skipped.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Archive threads with no activity for this many days.
+    #[serde(default, rename = "archiveIdleDays")]
+    pub archive_idle_days: Option<u32>,
+    /// Delete archived rollouts older than this many days.
+    #[serde(default, rename = "purgeArchivedDays")]
+    pub purge_archived_days: Option<u32>,
+}
+
+/// Disk usage that triggers a `disk/usageWarning` event; see `disk_usage_core`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DiskQuota {
+    /// A main workspace's checkout plus all of its worktrees.
+    #[serde(default, rename = "workspaceWarningBytes")]
+    pub workspace_warning_bytes: Option<u64>,
+    /// Each worktree on its own.
+    #[serde(default, rename = "worktreeWarningBytes")]
+    pub worktree_warning_bytes: Option<u64>,
+    #[serde(default, rename = "dataDirWarningBytes")]
+    pub data_dir_warning_bytes: Option<u64>,
+}
+
+/// Size limits for workspace file transfers; unset fields use the defaults in
+/// `file_transfer_core`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FileLimits {
+    /// `read_workspace_file` truncates past this.
+    #[serde(default, rename = "maxReadBytes")]
+    pub max_read_bytes: Option<u64>,
+    /// Largest file a streamed write (`begin_write`) may produce.
+    #[serde(default, rename = "maxWriteBytes")]
+    pub max_write_bytes: Option<u64>,
+}
+
+/// Directories that file listing, search indexing and package/git root discovery leave
+/// out, by category; see `files::policy::SkipDirPolicy` for the names in each.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SkipDirSettings {
+    #[serde(default = "default_skip_dir_category")]
+    pub vcs: bool,
+    #[serde(default = "default_skip_dir_category", rename = "buildArtifacts")]
+    pub build_artifacts: bool,
+    #[serde(default = "default_skip_dir_category", rename = "packageDirs")]
+    pub package_dirs: bool,
+    /// More directory names, matched exactly at any depth.
+    #[serde(default)]
+    pub custom: Vec<String>,
+}
+
+impl Default for SkipDirSettings {
+    fn default() -> Self {
+        Self {
+            vcs: true,
+            build_artifacts: true,
+            package_dirs: true,
+            custom: Vec::new(),
+        }
+    }
+}
+
+fn default_skip_dir_category() -> bool {
+    true
+}
+
+/// Codex launch settings kept as a list instead of the shell-style `codexArgs` string,
+/// which they take precedence over. Args and env values may use `{workspacePath}` and
+/// `{branch}`; see `codex::args`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CodexLaunch {
+    /// Passed before `app-server`, one element per argument.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Set on the Codex process on top of the inherited environment.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
-pub(crate) struct WorkspaceSettings {
+pub struct WorkspaceSettings {
     #[serde(default, rename = "sidebarCollapsed")]
-    pub(crate) sidebar_collapsed: bool,
+    pub sidebar_collapsed: bool,
     #[serde(default, rename = "sortOrder")]
-    pub(crate) sort_order: Option<u32>,
+    pub sort_order: Option<u32>,
     #[serde(default, rename = "groupId")]
-    pub(crate) group_id: Option<String>,
+    pub group_id: Option<String>,
     #[serde(default, rename = "gitRoot")]
-    pub(crate) git_root: Option<String>,
+    pub git_root: Option<String>,
     #[serde(default, rename = "codexHome")]
-    pub(crate) codex_home: Option<String>,
+    pub codex_home: Option<String>,
+    /// Account profile whose Codex home is used when `codexHome` is unset.
+    #[serde(default, rename = "accountProfile")]
+    pub account_profile: Option<String>,
     #[serde(default, rename = "codexArgs")]
-    pub(crate) codex_args: Option<String>,
+    pub codex_args: Option<String>,
+    #[serde(default, rename = "codexLaunch")]
+    pub codex_launch: Option<CodexLaunch>,
     #[serde(default, rename = "launchScript")]
-    pub(crate) launch_script: Option<String>,
+    pub launch_script: Option<String>,
     #[serde(default, rename = "launchScripts")]
-    pub(crate) launch_scripts: Option<Vec<LaunchScriptEntry>>,
+    pub launch_scripts: Option<Vec<LaunchScriptEntry>>,
     #[serde(default, rename = "worktreeSetupScript")]
-    pub(crate) worktree_setup_script: Option<String>,
+    pub worktree_setup_script: Option<String>,
+    /// Start point for branches `add_worktree` creates, e.g. `origin/main`; unset means
+    /// the parent's `HEAD`.
+    #[serde(default, rename = "worktreeBaseRef")]
+    pub worktree_base_ref: Option<String>,
+    /// Fetch `worktreeBaseRef` from its remote before branching off it.
+    #[serde(default, rename = "worktreeFetchBase")]
+    pub worktree_fetch_base: bool,
+    /// Used by `send_user_message` when the client doesn't pass a model.
+    #[serde(default, rename = "defaultModel")]
+    pub default_model: Option<String>,
+    #[serde(default, rename = "defaultEffort")]
+    pub default_effort: Option<String>,
+    /// `current`, `read-only` or `full-access`.
+    #[serde(default, rename = "defaultAccessMode")]
+    pub default_access_mode: Option<String>,
+    /// Name of the collaboration mode preset used when a message names none.
+    #[serde(default, rename = "defaultCollaborationPreset")]
+    pub default_collaboration_preset: Option<String>,
+    /// Blocks `remove_workspace`/`remove_worktree` until turned off again.
+    #[serde(default)]
+    pub protected: bool,
+    /// Branch name globs (`main`, `release/*`) that worktree operations refuse to target
+    /// without `allowProtectedBranch`. A worktree also inherits its parent's.
+    #[serde(default, rename = "protectedBranches")]
+    pub protected_branches: Vec<String>,
+    /// Bumped by every write to this workspace's metadata; see `revisions_core`.
+    #[serde(default)]
+    pub revision: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct LaunchScriptEntry {
-    pub(crate) id: String,
-    pub(crate) script: String,
-    pub(crate) icon: String,
+pub struct LaunchScriptEntry {
+    pub id: String,
+    pub script: String,
+    pub icon: String,
     #[serde(default)]
-    pub(crate) label: Option<String>,
+    pub label: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct WorktreeSetupStatus {
+pub struct WorktreeSetupStatus {
     #[serde(rename = "shouldRun")]
-    pub(crate) should_run: bool,
-    pub(crate) script: Option<String>,
+    pub should_run: bool,
+    pub script: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadBranchRecord {
+    pub workspace_id: String,
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub worktree_path: Option<String>,
+    #[serde(default)]
+    pub started_at: u64,
+    /// Workspace-relative directory the thread is scoped to; `None` means the workspace root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Thread this one was forked from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_thread_id: Option<String>,
+    /// Last parent turn carried into the fork, when the fork response lists turns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_turn_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct OpenAppTarget {
-    pub(crate) id: String,
-    pub(crate) label: String,
-    pub(crate) kind: String,
+pub struct OpenAppTarget {
+    pub id: String,
+    pub label: String,
+    pub kind: String,
     #[serde(default, rename = "appName")]
-    pub(crate) app_name: Option<String>,
+    pub app_name: Option<String>,
     #[serde(default)]
-    pub(crate) command: Option<String>,
+    pub command: Option<String>,
     #[serde(default)]
-    pub(crate) args: Vec<String>,
+    pub args: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct AppSettings {
+pub struct AppSettings {
     #[serde(default, rename = "codexBin")]
-    pub(crate) codex_bin: Option<String>,
+    pub codex_bin: Option<String>,
     #[serde(default, rename = "codexArgs")]
-    pub(crate) codex_args: Option<String>,
+    pub codex_args: Option<String>,
+    #[serde(default, rename = "codexLaunch")]
+    pub codex_launch: Option<CodexLaunch>,
     #[serde(default, rename = "backendMode")]
-    pub(crate) backend_mode: BackendMode,
+    pub backend_mode: BackendMode,
     #[serde(default = "default_remote_backend_host", rename = "remoteBackendHost")]
-    pub(crate) remote_backend_host: String,
+    pub remote_backend_host: String,
     #[serde(default, rename = "remoteBackendToken")]
-    pub(crate) remote_backend_token: Option<String>,
+    pub remote_backend_token: Option<String>,
+    /// Connect to a daemon started with `--tls-cert`.
+    #[serde(default, rename = "remoteBackendTls")]
+    pub remote_backend_tls: bool,
+    /// PEM certificate to trust besides the public roots, e.g. a self-signed daemon's.
+    #[serde(default, rename = "remoteBackendTlsCa")]
+    pub remote_backend_tls_ca: Option<String>,
     #[serde(default = "default_access_mode", rename = "defaultAccessMode")]
-    pub(crate) default_access_mode: String,
+    pub default_access_mode: String,
     #[serde(default = "default_review_delivery_mode", rename = "reviewDeliveryMode")]
-    pub(crate) review_delivery_mode: String,
+    pub review_delivery_mode: String,
     #[serde(
         default = "default_composer_model_shortcut",
         rename = "composerModelShortcut"
     )]
-    pub(crate) composer_model_shortcut: Option<String>,
+    pub composer_model_shortcut: Option<String>,
     #[serde(
         default = "default_composer_access_shortcut",
         rename = "composerAccessShortcut"
     )]
-    pub(crate) composer_access_shortcut: Option<String>,
+    pub composer_access_shortcut: Option<String>,
     #[serde(
         default = "default_composer_reasoning_shortcut",
         rename = "composerReasoningShortcut"
     )]
-    pub(crate) composer_reasoning_shortcut: Option<String>,
+    pub composer_reasoning_shortcut: Option<String>,
     #[serde(default = "default_interrupt_shortcut", rename = "interruptShortcut")]
-    pub(crate) interrupt_shortcut: Option<String>,
+    pub interrupt_shortcut: Option<String>,
     #[serde(
         default = "default_composer_collaboration_shortcut",
         rename = "composerCollaborationShortcut"
     )]
-    pub(crate) composer_collaboration_shortcut: Option<String>,
+    pub composer_collaboration_shortcut: Option<String>,
     #[serde(default = "default_new_agent_shortcut", rename = "newAgentShortcut")]
-    pub(crate) new_agent_shortcut: Option<String>,
+    pub new_agent_shortcut: Option<String>,
     #[serde(
         default = "default_new_worktree_agent_shortcut",
         rename = "newWorktreeAgentShortcut"
     )]
-    pub(crate) new_worktree_agent_shortcut: Option<String>,
+    pub new_worktree_agent_shortcut: Option<String>,
     #[serde(
         default = "default_new_clone_agent_shortcut",
         rename = "newCloneAgentShortcut"
     )]
-    pub(crate) new_clone_agent_shortcut: Option<String>,
+    pub new_clone_agent_shortcut: Option<String>,
     #[serde(
         default = "default_archive_thread_shortcut",
         rename = "archiveThreadShortcut"
     )]
-    pub(crate) archive_thread_shortcut: Option<String>,
+    pub archive_thread_shortcut: Option<String>,
     #[serde(
         default = "default_toggle_projects_sidebar_shortcut",
         rename = "toggleProjectsSidebarShortcut"
     )]
-    pub(crate) toggle_projects_sidebar_shortcut: Option<String>,
+    pub toggle_projects_sidebar_shortcut: Option<String>,
     #[serde(
         default = "default_toggle_git_sidebar_shortcut",
         rename = "toggleGitSidebarShortcut"
     )]
-    pub(crate) toggle_git_sidebar_shortcut: Option<String>,
+    pub toggle_git_sidebar_shortcut: Option<String>,
     #[serde(
         default = "default_toggle_debug_panel_shortcut",
         rename = "toggleDebugPanelShortcut"
     )]
-    pub(crate) toggle_debug_panel_shortcut: Option<String>,
+    pub toggle_debug_panel_shortcut: Option<String>,
     #[serde(
         default = "default_toggle_terminal_shortcut",
         rename = "toggleTerminalShortcut"
     )]
-    pub(crate) toggle_terminal_shortcut: Option<String>,
+    pub toggle_terminal_shortcut: Option<String>,
     #[serde(
         default = "default_cycle_agent_next_shortcut",
         rename = "cycleAgentNextShortcut"
     )]
-    pub(crate) cycle_agent_next_shortcut: Option<String>,
+    pub cycle_agent_next_shortcut: Option<String>,
     #[serde(
         default = "default_cycle_agent_prev_shortcut",
         rename = "cycleAgentPrevShortcut"
     )]
-    pub(crate) cycle_agent_prev_shortcut: Option<String>,
+    pub cycle_agent_prev_shortcut: Option<String>,
     #[serde(
         default = "default_cycle_workspace_next_shortcut",
         rename = "cycleWorkspaceNextShortcut"
     )]
-    pub(crate) cycle_workspace_next_shortcut: Option<String>,
+    pub cycle_workspace_next_shortcut: Option<String>,
     #[serde(
         default = "default_cycle_workspace_prev_shortcut",
         rename = "cycleWorkspacePrevShortcut"
     )]
-    pub(crate) cycle_workspace_prev_shortcut: Option<String>,
+    pub cycle_workspace_prev_shortcut: Option<String>,
     #[serde(default, rename = "lastComposerModelId")]
-    pub(crate) last_composer_model_id: Option<String>,
+    pub last_composer_model_id: Option<String>,
     #[serde(default, rename = "lastComposerReasoningEffort")]
-    pub(crate) last_composer_reasoning_effort: Option<String>,
+    pub last_composer_reasoning_effort: Option<String>,
     #[serde(default = "default_ui_scale", rename = "uiScale")]
-    pub(crate) ui_scale: f64,
+    pub ui_scale: f64,
     #[serde(default = "default_theme", rename = "theme")]
-    pub(crate) theme: String,
+    pub theme: String,
     #[serde(default = "default_usage_show_remaining", rename = "usageShowRemaining")]
-    pub(crate) usage_show_remaining: bool,
+    pub usage_show_remaining: bool,
     #[serde(default = "default_ui_font_family", rename = "uiFontFamily")]
-    pub(crate) ui_font_family: String,
+    pub ui_font_family: String,
     #[serde(default = "default_code_font_family", rename = "codeFontFamily")]
-    pub(crate) code_font_family: String,
+    pub code_font_family: String,
     #[serde(default = "default_code_font_size", rename = "codeFontSize")]
-    pub(crate) code_font_size: u8,
+    pub code_font_size: u8,
     #[serde(
         default = "default_notification_sounds_enabled",
         rename = "notificationSoundsEnabled"
     )]
-    pub(crate) notification_sounds_enabled: bool,
+    pub notification_sounds_enabled: bool,
     #[serde(default = "default_preload_git_diffs", rename = "preloadGitDiffs")]
-    pub(crate) preload_git_diffs: bool,
+    pub preload_git_diffs: bool,
     #[serde(
         default = "default_git_diff_ignore_whitespace_changes",
         rename = "gitDiffIgnoreWhitespaceChanges"
     )]
-    pub(crate) git_diff_ignore_whitespace_changes: bool,
+    pub git_diff_ignore_whitespace_changes: bool,
     #[serde(
         default = "default_system_notifications_enabled",
         rename = "systemNotificationsEnabled"
     )]
-    pub(crate) system_notifications_enabled: bool,
+    pub system_notifications_enabled: bool,
     #[serde(
         default = "default_experimental_collab_enabled",
         rename = "experimentalCollabEnabled"
     )]
-    pub(crate) experimental_collab_enabled: bool,
+    pub experimental_collab_enabled: bool,
     #[serde(
         default = "default_collaboration_modes_enabled",
         rename = "collaborationModesEnabled"
     )]
-    pub(crate) collaboration_modes_enabled: bool,
+    pub collaboration_modes_enabled: bool,
     #[serde(
         default = "default_steer_enabled",
         rename = "steerEnabled",
         alias = "experimentalSteerEnabled"
     )]
-    pub(crate) steer_enabled: bool,
+    pub steer_enabled: bool,
     #[serde(
         default = "default_unified_exec_enabled",
         rename = "unifiedExecEnabled",
         alias = "experimentalUnifiedExecEnabled"
     )]
-    pub(crate) unified_exec_enabled: bool,
+    pub unified_exec_enabled: bool,
     #[serde(
         default = "default_experimental_apps_enabled",
         rename = "experimentalAppsEnabled"
     )]
-    pub(crate) experimental_apps_enabled: bool,
+    pub experimental_apps_enabled: bool,
     #[serde(
         default = "default_personality",
         rename = "personality"
     )]
-    pub(crate) personality: String,
+    pub personality: String,
+    /// Language tag for backend error and status messages (e.g. `en`, `tr`).
+    #[serde(default = "default_locale", rename = "locale")]
+    pub locale: String,
+    /// Makes the daemon trace full RPC payloads (secrets redacted), like `--trace-rpc`.
+    #[serde(default, rename = "traceRpcEnabled")]
+    pub trace_rpc_enabled: bool,
+    /// Bumped by every settings write; see `revisions_core`.
+    #[serde(default)]
+    pub revision: u64,
     #[serde(default = "default_dictation_enabled", rename = "dictationEnabled")]
-    pub(crate) dictation_enabled: bool,
+    pub dictation_enabled: bool,
     #[serde(
         default = "default_dictation_model_id",
         rename = "dictationModelId"
     )]
-    pub(crate) dictation_model_id: String,
+    pub dictation_model_id: String,
     #[serde(default, rename = "dictationPreferredLanguage")]
-    pub(crate) dictation_preferred_language: Option<String>,
+    pub dictation_preferred_language: Option<String>,
     #[serde(
         default = "default_dictation_hold_key",
         rename = "dictationHoldKey"
     )]
-    pub(crate) dictation_hold_key: String,
+    pub dictation_hold_key: String,
     #[serde(default = "default_composer_editor_preset", rename = "composerEditorPreset")]
-    pub(crate) composer_editor_preset: String,
+    pub composer_editor_preset: String,
     #[serde(default = "default_composer_fence_expand_on_space", rename = "composerFenceExpandOnSpace")]
-    pub(crate) composer_fence_expand_on_space: bool,
+    pub composer_fence_expand_on_space: bool,
     #[serde(default = "default_composer_fence_expand_on_enter", rename = "composerFenceExpandOnEnter")]
-    pub(crate) composer_fence_expand_on_enter: bool,
+    pub composer_fence_expand_on_enter: bool,
     #[serde(default = "default_composer_fence_language_tags", rename = "composerFenceLanguageTags")]
-    pub(crate) composer_fence_language_tags: bool,
+    pub composer_fence_language_tags: bool,
     #[serde(default = "default_composer_fence_wrap_selection", rename = "composerFenceWrapSelection")]
-    pub(crate) composer_fence_wrap_selection: bool,
+    pub composer_fence_wrap_selection: bool,
     #[serde(default = "default_composer_fence_auto_wrap_paste_multiline", rename = "composerFenceAutoWrapPasteMultiline")]
-    pub(crate) composer_fence_auto_wrap_paste_multiline: bool,
+    pub composer_fence_auto_wrap_paste_multiline: bool,
     #[serde(default = "default_composer_fence_auto_wrap_paste_code_like", rename = "composerFenceAutoWrapPasteCodeLike")]
-    pub(crate) composer_fence_auto_wrap_paste_code_like: bool,
+    pub composer_fence_auto_wrap_paste_code_like: bool,
     #[serde(default = "default_composer_list_continuation", rename = "composerListContinuation")]
-    pub(crate) composer_list_continuation: bool,
+    pub composer_list_continuation: bool,
     #[serde(
         default = "default_composer_code_block_copy_use_modifier",
         rename = "composerCodeBlockCopyUseModifier"
     )]
-    pub(crate) composer_code_block_copy_use_modifier: bool,
+    pub composer_code_block_copy_use_modifier: bool,
     #[serde(default = "default_workspace_groups", rename = "workspaceGroups")]
-    pub(crate) workspace_groups: Vec<WorkspaceGroup>,
+    pub workspace_groups: Vec<WorkspaceGroup>,
     #[serde(default = "default_open_app_targets", rename = "openAppTargets")]
-    pub(crate) open_app_targets: Vec<OpenAppTarget>,
+    pub open_app_targets: Vec<OpenAppTarget>,
     #[serde(default = "default_selected_open_app_id", rename = "selectedOpenAppId")]
-    pub(crate) selected_open_app_id: String,
+    pub selected_open_app_id: String,
+    /// Evaluated by the daemon before approval requests reach any client; see `rules.rs`.
+    #[serde(default, rename = "approvalPolicyRules")]
+    pub approval_policy_rules: Vec<ApprovalPolicyRule>,
+    #[serde(default, rename = "sandboxPresets")]
+    pub sandbox_presets: Vec<SandboxPreset>,
+    /// Globs no file write or sandbox writable root may touch; see `protected_paths_core`.
+    #[serde(default = "default_protected_path_globs", rename = "protectedPathGlobs")]
+    pub protected_path_globs: Vec<String>,
+    /// Snapshot the repo before each turn that can write, for `restore_snapshot`.
+    #[serde(default, rename = "snapshotBeforeTurns")]
+    pub snapshot_before_turns: bool,
+    #[serde(default, rename = "slashCommands")]
+    pub slash_commands: Vec<CustomSlashCommand>,
+    /// Token budget for a thread's pinned context; half the model's context window when unset.
+    #[serde(default, rename = "pinnedContextBudgetTokens")]
+    pub pinned_context_budget_tokens: Option<usize>,
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    #[serde(default, rename = "diskQuota")]
+    pub disk_quota: DiskQuota,
+    #[serde(default, rename = "fileLimits")]
+    pub file_limits: FileLimits,
+    #[serde(default, rename = "skipDirs")]
+    pub skip_dirs: SkipDirSettings,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "lowercase")]
-pub(crate) enum BackendMode {
+pub enum BackendMode {
     Local,
     Remote,
 }
@@ -747,6 +1001,10 @@ fn default_personality() -> String {
     "friendly".to_string()
 }
 
+fn default_locale() -> String {
+    "en".to_string()
+}
+
 fn default_dictation_enabled() -> bool {
     false
 }
@@ -795,6 +1053,13 @@ fn default_composer_code_block_copy_use_modifier() -> bool {
     false
 }
 
+fn default_protected_path_globs() -> Vec<String> {
+    DEFAULT_PROTECTED_PATH_GLOBS
+        .iter()
+        .map(|glob| glob.to_string())
+        .collect()
+}
+
 fn default_workspace_groups() -> Vec<WorkspaceGroup> {
     Vec::new()
 }
@@ -924,9 +1189,12 @@ impl Default for AppSettings {
         Self {
             codex_bin: None,
             codex_args: None,
+            codex_launch: None,
             backend_mode: BackendMode::Local,
             remote_backend_host: default_remote_backend_host(),
             remote_backend_token: None,
+            remote_backend_tls: false,
+            remote_backend_tls_ca: None,
             default_access_mode: "current".to_string(),
             review_delivery_mode: default_review_delivery_mode(),
             composer_model_shortcut: default_composer_model_shortcut(),
@@ -964,6 +1232,9 @@ impl Default for AppSettings {
             unified_exec_enabled: true,
             experimental_apps_enabled: false,
             personality: default_personality(),
+            locale: default_locale(),
+            trace_rpc_enabled: false,
+            revision: 0,
             dictation_enabled: false,
             dictation_model_id: default_dictation_model_id(),
             dictation_preferred_language: None,
@@ -980,6 +1251,16 @@ impl Default for AppSettings {
             workspace_groups: default_workspace_groups(),
             open_app_targets: default_open_app_targets(),
             selected_open_app_id: default_selected_open_app_id(),
+            approval_policy_rules: Vec::new(),
+            sandbox_presets: Vec::new(),
+            protected_path_globs: default_protected_path_globs(),
+            snapshot_before_turns: false,
+            slash_commands: Vec::new(),
+            pinned_context_budget_tokens: None,
+            retention: RetentionPolicy::default(),
+            disk_quota: DiskQuota::default(),
+            file_limits: FileLimits::default(),
+            skip_dirs: SkipDirSettings::default(),
         }
     }
 }
@@ -987,7 +1268,8 @@ impl Default for AppSettings {
 #[cfg(test)]
 mod tests {
     use super::{
-        AppSettings, BackendMode, WorkspaceEntry, WorkspaceGroup, WorkspaceKind, WorkspaceSettings,
+        AppSettings, BackendMode, DiskQuota, FileLimits, RetentionPolicy, SkipDirSettings,
+        WorkspaceEntry, WorkspaceGroup, WorkspaceKind, WorkspaceSettings,
     };
 
     #[test]
@@ -997,6 +1279,7 @@ mod tests {
         assert!(matches!(settings.backend_mode, BackendMode::Local));
         assert_eq!(settings.remote_backend_host, "127.0.0.1:4732");
         assert!(settings.remote_backend_token.is_none());
+        assert!(!settings.remote_backend_tls);
         assert_eq!(settings.default_access_mode, "current");
         assert_eq!(settings.review_delivery_mode, "inline");
         let expected_primary = if cfg!(target_os = "macos") { "cmd" } else { "ctrl" };
@@ -1092,6 +1375,14 @@ mod tests {
         assert!(settings.unified_exec_enabled);
         assert!(!settings.experimental_apps_enabled);
         assert_eq!(settings.personality, "friendly");
+        assert_eq!(settings.locale, "en");
+        assert!(!settings.trace_rpc_enabled);
+        assert!(!settings.snapshot_before_turns);
+        assert!(settings.slash_commands.is_empty());
+        assert_eq!(settings.retention, RetentionPolicy::default());
+        assert_eq!(settings.disk_quota, DiskQuota::default());
+        assert_eq!(settings.file_limits, FileLimits::default());
+        assert_eq!(settings.skip_dirs, SkipDirSettings::default());
         assert!(!settings.dictation_enabled);
         assert_eq!(settings.dictation_model_id, "base");
         assert!(settings.dictation_preferred_language.is_none());
@@ -3,7 +3,7 @@ use std::ffi::OsString;
 use std::path::PathBuf;
 
 #[allow(dead_code)]
-pub(crate) fn normalize_git_path(path: &str) -> String {
+pub fn normalize_git_path(path: &str) -> String {
     path.replace('\\', "/")
 }
 
@@ -18,7 +18,7 @@ fn find_in_path(binary: &str) -> Option<PathBuf> {
     None
 }
 
-pub(crate) fn resolve_git_binary() -> Result<PathBuf, String> {
+pub fn resolve_git_binary() -> Result<PathBuf, String> {
     if let Some(path) = find_in_path("git") {
         return Ok(path);
     }
@@ -56,7 +56,7 @@ pub(crate) fn resolve_git_binary() -> Result<PathBuf, String> {
     ))
 }
 
-pub(crate) fn git_env_path() -> String {
+pub fn git_env_path() -> String {
     let mut paths: Vec<PathBuf> = env::var_os("PATH")
         .map(|value| env::split_paths(&value).collect())
         .unwrap_or_default();
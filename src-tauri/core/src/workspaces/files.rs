@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+
+use crate::files::policy::SkipDirPolicy;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::utils::normalize_git_path;
+
+/// Paths buffered between the walker threads and the collector before walkers block.
+const WALK_CHANNEL_CAPACITY: usize = 1024;
+
+/// Walks on the `ignore` crate's thread pool and stops every walker once `max_files`
+/// paths were collected, so which files make the cut in a truncated listing varies.
+pub fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
+    let mut results = Vec::new();
+    if max_files == 0 {
+        return results;
+    }
+    let skip_dirs = SkipDirPolicy::current();
+    let walker = WalkBuilder::new(root)
+        // Allow hidden entries.
+        .hidden(false)
+        // Avoid crawling symlink targets.
+        .follow_links(false)
+        // Don't require git to be present to apply to apply git-related ignore rules.
+        .require_git(false)
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            if entry.file_type().is_some_and(|ft| ft.is_dir()) {
+                let name = entry.file_name().to_string_lossy();
+                return !skip_dirs.skips(&name);
+            }
+            true
+        })
+        .build_parallel();
+    let (tx, rx) = mpsc::sync_channel::<String>(WALK_CHANNEL_CAPACITY);
+    let stop = &AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            walker.run(|| {
+                let tx = tx.clone();
+                Box::new(move |entry: Result<DirEntry, ignore::Error>| {
+                    if stop.load(Ordering::Relaxed) {
+                        return WalkState::Quit;
+                    }
+                    let Ok(entry) = entry else {
+                        return WalkState::Continue;
+                    };
+                    if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+                        return WalkState::Continue;
+                    }
+                    let Ok(rel_path) = entry.path().strip_prefix(root) else {
+                        return WalkState::Continue;
+                    };
+                    let normalized = normalize_git_path(&rel_path.to_string_lossy());
+                    if normalized.is_empty() {
+                        return WalkState::Continue;
+                    }
+                    // The collector hangs up once it has enough.
+                    match tx.send(normalized) {
+                        Ok(()) => WalkState::Continue,
+                        Err(_) => WalkState::Quit,
+                    }
+                })
+            });
+        });
+        for path in rx {
+            results.push(path);
+            if results.len() >= max_files {
+                stop.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    });
+
+    results.sort();
+    results
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WorkspaceFileResponse {
+    content: String,
+    truncated: bool,
+}
+
+pub fn read_workspace_file_inner(
+    root: &PathBuf,
+    relative_path: &str,
+    max_bytes: u64,
+) -> Result<WorkspaceFileResponse, String> {
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| i18n_core::message_with(MessageKey::WorkspaceRootResolveFailed, err))?;
+    let candidate = canonical_root.join(relative_path);
+    let canonical_path = candidate
+        .canonicalize()
+        .map_err(|err| i18n_core::message_with(MessageKey::FileOpenFailed, err))?;
+    if !canonical_path.starts_with(&canonical_root) {
+        return Err(i18n_core::message(MessageKey::FileInvalidPath));
+    }
+    let metadata = std::fs::metadata(&canonical_path)
+        .map_err(|err| i18n_core::message_with(MessageKey::FileMetadataFailed, err))?;
+    if !metadata.is_file() {
+        return Err(i18n_core::message(MessageKey::FileNotAFile));
+    }
+
+    let file =
+        File::open(&canonical_path)
+            .map_err(|err| i18n_core::message_with(MessageKey::FileOpenFailed, err))?;
+    let mut buffer = Vec::new();
+    file.take(max_bytes + 1)
+        .read_to_end(&mut buffer)
+        .map_err(|err| i18n_core::message_with(MessageKey::FileReadFailed, err))?;
+
+    let truncated = buffer.len() > max_bytes as usize;
+    if truncated {
+        buffer.truncate(max_bytes as usize);
+    }
+
+    let content =
+        String::from_utf8(buffer).map_err(|_| i18n_core::message(MessageKey::FileNotUtf8))?;
+    Ok(WorkspaceFileResponse { content, truncated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Instant;
+    use uuid::Uuid;
+
+    /// `dirs` packages of `files_per_dir` empty files each, plus a skipped `node_modules`.
+    fn make_tree(dirs: usize, files_per_dir: usize) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("codex-monitor-walk-{}", Uuid::new_v4()));
+        for dir in 0..dirs {
+            let dir_path = root.join(format!("pkg-{dir}/src"));
+            std::fs::create_dir_all(&dir_path).expect("dir");
+            for file in 0..files_per_dir {
+                std::fs::write(dir_path.join(format!("f{file}.rs")), "").expect("file");
+            }
+        }
+        std::fs::create_dir_all(root.join("node_modules/dep")).expect("skipped dir");
+        std::fs::write(root.join("node_modules/dep/index.js"), "").expect("skipped file");
+        root
+    }
+
+    fn time_walk(root: &Path, max_files: usize) -> (usize, std::time::Duration) {
+        let started = Instant::now();
+        let listed = list_workspace_files_inner(&root.to_path_buf(), max_files).len();
+        (listed, started.elapsed())
+    }
+
+    #[test]
+    fn lists_large_tree_in_parallel_and_stops_at_max() {
+        let root = make_tree(40, 50);
+
+        let all = list_workspace_files_inner(&root, usize::MAX);
+        assert_eq!(all.len(), 2000);
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(all.contains(&"pkg-7/src/f12.rs".to_string()));
+        assert!(!all.iter().any(|path| path.starts_with("node_modules/")));
+        assert_eq!(list_workspace_files_inner(&root, 100).len(), 100);
+        assert!(list_workspace_files_inner(&root, 0).is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    /// Benchmark: `cargo test -p fridex-core walk_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark; writes 50,000 files"]
+    fn walk_benchmark() {
+        let root = make_tree(500, 100);
+        // Warm the page cache so the runs below measure the walk, not the first read.
+        time_walk(&root, usize::MAX);
+        let (listed, full_walk) = time_walk(&root, usize::MAX);
+        let (capped, capped_walk) = time_walk(&root, 1_000);
+        println!("full walk: {listed} files in {full_walk:?}");
+        println!("capped walk: {capped} files in {capped_walk:?}");
+        assert_eq!(listed, 50_000);
+        assert_eq!(capped, 1_000);
+        assert!(
+            capped_walk < full_walk,
+            "stopping at max_files should beat a full walk"
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
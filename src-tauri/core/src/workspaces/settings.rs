@@ -0,0 +1,18 @@
+use std::collections::HashMap;
+
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::types::{WorkspaceEntry, WorkspaceSettings};
+
+pub fn apply_workspace_settings_update(
+    workspaces: &mut HashMap<String, WorkspaceEntry>,
+    id: &str,
+    settings: WorkspaceSettings,
+) -> Result<WorkspaceEntry, String> {
+    match workspaces.get_mut(id) {
+        Some(entry) => {
+            entry.settings = settings.clone();
+            Ok(entry.clone())
+        }
+        None => Err(i18n_core::message(MessageKey::WorkspaceNotFound)),
+    }
+}
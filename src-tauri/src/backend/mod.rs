@@ -1,2 +0,0 @@
-pub(crate) mod app_server;
-pub(crate) mod events;
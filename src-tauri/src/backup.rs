@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use serde_json::json;
+use tauri::{AppHandle, Manager, State};
+
+use crate::remote_backend;
+use crate::shared::backup_core::{
+    backup_data_core, default_backup_rules_path, restore_data_core, BackupResult, RestoreResult,
+    RestoreTargets,
+};
+use crate::shared::safety_policy_core::{
+    export_safety_policy_core, import_safety_policy_core, SafetyPolicyBundle,
+    SafetyPolicyImportMode, SafetyPolicyImportResult,
+};
+use crate::state::AppState;
+
+#[tauri::command]
+pub(crate) async fn backup_data(
+    destination: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<BackupResult, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "backup_data",
+            json!({ "destination": destination }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
+    let rules_path = default_backup_rules_path();
+    backup_data_core(&data_dir, Path::new(&destination), rules_path.as_deref())
+}
+
+#[tauri::command]
+pub(crate) async fn restore_data(
+    source: String,
+    confirm: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<RestoreResult, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "restore_data",
+            json!({ "source": source, "confirm": confirm }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
+    let rules_path = default_backup_rules_path();
+    restore_data_core(
+        Path::new(&source),
+        confirm,
+        RestoreTargets {
+            data_dir: &data_dir,
+            rules_path: rules_path.as_deref(),
+            workspaces: &state.workspaces,
+            app_settings: &state.app_settings,
+            thread_branches: &state.thread_branches,
+            storage_path: &state.storage_path,
+            settings_path: &state.settings_path,
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn export_safety_policy(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<SafetyPolicyBundle, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "export_safety_policy", json!({})).await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let rules_path = default_backup_rules_path();
+    Ok(export_safety_policy_core(&state.app_settings, rules_path.as_deref()).await)
+}
+
+#[tauri::command]
+pub(crate) async fn import_safety_policy(
+    bundle: SafetyPolicyBundle,
+    mode: Option<SafetyPolicyImportMode>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<SafetyPolicyImportResult, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "import_safety_policy",
+            json!({ "bundle": bundle, "mode": mode }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let rules_path = default_backup_rules_path();
+    import_safety_policy_core(
+        bundle,
+        mode.unwrap_or_default(),
+        &state.app_settings,
+        &state.settings_path,
+        rules_path.as_deref(),
+    )
+    .await
+}
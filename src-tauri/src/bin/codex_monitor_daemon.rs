@@ -1,107 +1,277 @@
-#[allow(dead_code)]
-#[path = "../backend/mod.rs"]
-mod backend;
-#[path = "../codex/args.rs"]
-mod codex_args;
-#[path = "../codex/home.rs"]
-mod codex_home;
-#[path = "../codex/config.rs"]
-mod codex_config;
-#[path = "../files/io.rs"]
-mod file_io;
-#[path = "../files/ops.rs"]
-mod file_ops;
-#[path = "../files/policy.rs"]
-mod file_policy;
-#[path = "../rules.rs"]
-mod rules;
-#[path = "../storage.rs"]
-mod storage;
-#[path = "../shared/mod.rs"]
-mod shared;
-#[path = "../utils.rs"]
-mod utils;
-#[path = "../workspaces/settings.rs"]
-mod workspace_settings;
-#[allow(dead_code)]
-#[path = "../types.rs"]
-mod types;
-
-// Provide feature-style module paths for shared cores when compiled in the daemon.
-mod codex {
-    pub(crate) mod args {
-        pub(crate) use crate::codex_args::*;
-    }
-    pub(crate) mod config {
-        pub(crate) use crate::codex_config::*;
-    }
-    pub(crate) mod home {
-        pub(crate) use crate::codex_home::*;
-    }
-}
+use fridex_core::codex::args as codex_args;
+use fridex_core::files::{io as file_io, policy as file_policy};
+use fridex_core::workspaces::{files as workspace_files, settings as workspace_settings};
+use fridex_core::{backend, rules, shared, storage, types};
 
-mod files {
-    pub(crate) mod io {
-        pub(crate) use crate::file_io::*;
-    }
-    pub(crate) mod ops {
-        pub(crate) use crate::file_ops::*;
-    }
-    pub(crate) mod policy {
-        pub(crate) use crate::file_policy::*;
-    }
-}
+#[path = "codex_monitor_daemon/audit.rs"]
+mod audit;
+#[path = "codex_monitor_daemon/auto_fix.rs"]
+mod auto_fix;
+#[path = "codex_monitor_daemon/clients.rs"]
+mod clients;
+#[path = "codex_monitor_daemon/compression.rs"]
+mod compression;
+#[path = "codex_monitor_daemon/config_file.rs"]
+mod config_file;
+#[path = "codex_monitor_daemon/etag.rs"]
+mod etag;
+#[path = "codex_monitor_daemon/event_export.rs"]
+mod event_export;
+#[path = "codex_monitor_daemon/event_log.rs"]
+mod event_log;
+#[path = "codex_monitor_daemon/fanout.rs"]
+mod fanout;
+#[path = "codex_monitor_daemon/http_gateway.rs"]
+mod http_gateway;
+#[path = "codex_monitor_daemon/jobs.rs"]
+mod jobs;
+#[path = "codex_monitor_daemon/jsonrpc.rs"]
+mod jsonrpc;
+#[path = "codex_monitor_daemon/logging.rs"]
+mod logging;
+#[path = "codex_monitor_daemon/mock_app_server.rs"]
+mod mock_app_server;
+#[path = "codex_monitor_daemon/open_files.rs"]
+mod open_files;
+#[path = "codex_monitor_daemon/pending_approvals.rs"]
+mod pending_approvals;
+#[path = "codex_monitor_daemon/rate_limit.rs"]
+mod rate_limit;
+#[path = "codex_monitor_daemon/relay.rs"]
+mod relay;
+#[path = "codex_monitor_daemon/requests.rs"]
+mod requests;
+#[path = "codex_monitor_daemon/resume.rs"]
+mod resume;
+#[path = "codex_monitor_daemon/rpc_timeouts.rs"]
+mod rpc_timeouts;
+#[path = "codex_monitor_daemon/rpc_trace.rs"]
+mod rpc_trace;
+#[path = "codex_monitor_daemon/self_test.rs"]
+mod self_test;
+#[path = "codex_monitor_daemon/subscriptions.rs"]
+mod subscriptions;
+#[path = "codex_monitor_daemon/token_scopes.rs"]
+mod token_scopes;
+#[cfg(unix)]
+#[path = "codex_monitor_daemon/unix_socket.rs"]
+mod unix_socket;
+#[path = "codex_monitor_daemon/watchdog.rs"]
+mod watchdog;
+#[path = "codex_monitor_daemon/command_allowlist.rs"]
+mod command_allowlist;
 
-use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::env;
-use std::fs::File;
-use std::io::Read;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
-use ignore::WalkBuilder;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::runtime::RuntimeFlavor;
+use tracing::level_filters::LevelFilter;
+use tracing::Instrument;
+use tokio_rustls::TlsAcceptor;
+use tokio::sync::{broadcast, mpsc, watch, Mutex, Notify};
 
 use backend::app_server::{
     spawn_workspace_session, WorkspaceSession,
 };
-use backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
-use storage::{read_settings, read_workspaces};
-use shared::{codex_core, files_core, git_core, settings_core, workspaces_core, worktree_core};
+use backend::events::{
+    negotiate_event_schema, AppServerEvent, EventSink, TerminalExit, TerminalOutput,
+    EVENT_SCHEMA_VERSION, LEGACY_EVENT_SCHEMA_VERSION,
+};
+use storage::{read_settings, read_thread_branches, read_workspaces};
+use shared::{
+    backup_core, ci_core, codex_core, commit_message_core, context_budget_core,
+    dependency_audit_core, files_core, git_core, i18n_core, mentions_core, packages_core,
+    retention_core, revisions_core, search_index_core, session_log_core, settings_core,
+    slash_commands_core, thread_branches_core, tls_core,
+    turn_snapshots_core, workspace_stats_core, workspaces_core, worktree_core,
+    worktree_diff_core,
+};
+use shared::account_profiles_core::{AccountProfile, AccountProfileInfo, AccountProfiles};
+use shared::auth_watch_core::{AuthWatcher, AUTH_CHECK_INTERVAL_SECS};
 use shared::codex_core::CodexLoginCancelState;
+use shared::collaboration_presets_core::{CollaborationPreset, CollaborationPresets};
+use shared::data_files_core::{SETTINGS_FILE, WORKSPACES_FILE};
+use shared::disk_usage_core::{self, DiskMonitor, DiskUsageReport};
+use shared::documents_core::{self, ExtractedDocument};
+use shared::outline_core::{self, FileOutline};
+use shared::preview_core::{self, Notebook, RenderedMarkdown};
+use shared::safety_policy_core::{self, SafetyPolicyBundle, SafetyPolicyImportResult};
+use shared::thread_usage_core::ThreadUsageStats;
+use shared::time_tracking_core::{TimeSummary, TimeTracker};
+use shared::drafts_core::{Drafts, ThreadDraft};
+use shared::file_history_core::{self, FileHistory, FileHistoryResponse, FileVersion};
+use shared::file_list_cache_core::FileListCache;
+use shared::mcp_health_core::{McpHealthMonitor, McpServerHealth};
+use shared::i18n_core::MessageKey;
+use shared::list_cache_core::{self, ListCache, ListOptions};
+use shared::pinned_context_core::{self, ContextPin, PinInput, PinnedContext, PinnedContextView};
+use shared::build_core::{self, BuildReport};
+use shared::problems_core::{ProblemSeverity, Problems, ProblemsView};
+use shared::process_limits_core::{configure_process_limits, ProcessLimits};
+use shared::rules_lint_core::{self, RulesFormatResult, RulesValidation};
+use shared::removal_core::{self, RemovalConfirmations, RemovalPlan};
+use shared::file_transfer_core::{
+    self, Capabilities, CommittedWrite, PendingWrites, WriteProgress, WriteSession,
+};
+use shared::sparse_checkout_core::{self, SparseCheckout};
+use shared::stale_branches_core::{
+    self, StaleBranchConfirmations, StaleBranchDeletion, StaleBranchesReport,
+};
+use shared::symbols_core::{self, DefinitionLookup, SymbolIndex, WorkspaceSymbolsResponse};
+use shared::thumbnails_core::{self, ImageArtifact, Thumbnail, ThumbnailReady};
+use shared::turn_snapshots_core::TurnSnapshots;
+use shared::workspace_analysis_core::{self, WorkspaceAnalysis};
+use shared::workspace_deltas_core::{WorkspaceDelta, WorkspaceDeltaTracker};
+use shared::workspaces_core::{WorktreeBase, WorktreeOptions};
+use shared::worktree_reconcile_core::{self, WorktreeReconcileReport};
+use workspace_files::{
+    list_workspace_files_inner, read_workspace_file_inner, WorkspaceFileResponse,
+};
 use workspace_settings::apply_workspace_settings_update;
+use audit::AuditLog;
+use clients::{ClientInfo, ClientRegistry};
+use compression::{CompressionState, WireCompression};
+use auto_fix::{
+    AutoFixIteration, AutoFixLimits, AutoFixOutcome, AutoFixReport, DropGuard, TurnSignal,
+};
+use fanout::{
+    FanoutBranchComparison, FanoutComparison, FanoutPickResult, FanoutRun, FanoutRuns,
+    FanoutStatus,
+};
+use jobs::{JobInfo, JobManager, JobProgress};
+use jsonrpc::RequestId;
+use logging::LogFormat;
+use open_files::{
+    AgentWrite, ClientOpenFiles, EditorPresence, FileConflict, FileFocus, OpenFile, OpenFiles,
+};
+use pending_approvals::{PendingApproval, PendingApprovals};
+use relay::RelayConfig;
+use event_export::{parse_export_header, EventExporter, ExporterConfig, ExporterKind};
+use event_log::{EventLog, Sequenced, REPLAY_BUFFER_EVENTS};
+use requests::{
+    parse_request, AddWorkspaceRequest, AddWorktreeRequest, AutoFixRequest, BackupRequest,
+    BeginWriteRequest, CiStatusRequest,
+    CommitMessageRequest, CommitWriteRequest, CompareFanoutRequest, DeleteStaleBranchesRequest,
+    DiskUsageRequest, EmptyRequest,
+    EvaluateApprovalPolicyRequest,
+    FanoutPickRequest, FanoutRunRequest, FanoutStatusRequest, FileReadRequest,
+    FileWriteRequest, FindDefinitionRequest, FormatRulesRequest, IdRequest,
+    ImportSafetyPolicyRequest,
+    IndexedSearchRequest, JobStartRequest,
+    JobStatusRequest, KickClientRequest, ListRequest, ListThreadsRequest, McpOauthLoginRequest,
+    FocusFileRequest, MergeRevisionsRequest, NameRequest, OpenFilesRequest,
+    OptionalWorkspaceRequest, PathRequest, PinContextRequest,
+    PinnedContextRequest, PlanContextRequest,
+    ReconcileWorktreesRequest, RelocateWorkspaceRequest, RememberApprovalRuleRequest,
+    RemoveWorkspaceRequest,
+    RenameWorktreeRequest, RenameWorktreeUpstreamRequest, ResolveMentionsRequest,
+    RespondToServerRequest, RestoreRequest, RestoreSnapshotRequest, ResumeRequest,
+    RevertFileRequest, RunRetentionRequest, SaveAccountProfileRequest,
+    SaveCollaborationPresetRequest, SaveDraftRequest,
+    SearchAllWorkspacesRequest, SendUserMessageRequest, SessionLogRequest,
+    SetSparseCheckoutRequest, SetThreadNameRequest, StaleBranchesReportRequest,
+    SkillsConfigRequest, SkillsSetEnabledRequest, StartReviewRequest, StartThreadRequest,
+    SubscribeEventsRequest, SubscribeSinceRequest, TailTraceRequest, ThreadRequest,
+    TimeTrackingPingRequest, TimeTrackingSummaryRequest, TurnInterruptRequest,
+    UnpinContextRequest, UpdateAppSettingsRequest,
+    UpdateWorkspaceCodexBinRequest, UpdateWorkspaceSettingsRequest, WorkspaceFileRequest,
+    WorkspaceRequest, WorkspaceSymbolsRequest, WriteChunkRequest, WriteIdRequest,
+};
+use resume::{ResumeTickets, RESUME_TICKET_TTL};
+use rate_limit::RateLimits;
+use rpc_timeouts::{RpcError, RpcTimeouts};
+use rpc_trace::RpcTrace;
+use subscriptions::EventSubscriptions;
+use token_scopes::{Grant, TokenSet};
+use watchdog::HealthMonitor;
+use codex_args::{CodexArgs, CodexCommand};
+use config_file::CONFIG_FILE_NAME;
+use command_allowlist::CommandAllowlist;
 use types::{
-    AppSettings, WorkspaceEntry, WorkspaceInfo, WorkspaceSettings, WorktreeSetupStatus,
+    AppSettings, ApprovalPolicyDecision, ThreadBranchRecord, WorkspaceEntry, WorkspaceInfo, WorkspaceSettings,
+    WorktreeSetupStatus,
 };
 
 const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:4732";
+const TLS_HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
 fn spawn_with_client(
-    event_sink: DaemonEventSink,
+    state: &DaemonState,
     client_version: String,
     entry: WorkspaceEntry,
     default_bin: Option<String>,
-    codex_args: Option<String>,
+    codex_args: Option<CodexArgs>,
     codex_home: Option<PathBuf>,
 ) -> impl std::future::Future<Output = Result<Arc<WorkspaceSession>, String>> {
-    spawn_workspace_session(
+    let permitted = CodexCommand::for_session(
+        &entry,
+        default_bin.as_deref(),
+        codex_args.as_ref(),
+        codex_home.as_deref(),
+    )
+    .and_then(|command| {
+        state.ensure_command_allowed("spawn_workspace_session", &entry.id, &command.argv())
+    });
+    let spawn = spawn_workspace_session(
         entry,
         default_bin,
         codex_args,
         codex_home,
+        Some(state.data_dir.join(session_log_core::SESSION_LOGS_DIR)),
         client_version,
-        event_sink,
-    )
+        state.event_sink.clone(),
+    );
+    async move {
+        permitted?;
+        spawn.await
+    }
 }
 
 #[derive(Clone)]
 struct DaemonEventSink {
-    tx: broadcast::Sender<DaemonEvent>,
+    tx: Arc<EventLog<DaemonEvent>>,
+    pending_approvals: Arc<PendingApprovals>,
+    fanout_runs: Arc<FanoutRuns>,
+    thread_usage: Arc<ThreadUsageStats>,
+    time_tracker: Arc<TimeTracker>,
+    /// Approval requests detour through the policy engine before reaching clients.
+    approval_requests: mpsc::UnboundedSender<AppServerEvent>,
+    /// Images that completed items wrote, queued for preview generation.
+    image_artifacts: mpsc::UnboundedSender<(String, Vec<ImageArtifact>)>,
+    /// Files the agent wrote, checked against what clients have open.
+    agent_writes: mpsc::UnboundedSender<(String, AgentWrite)>,
+    exporter: EventExporter,
+}
+
+impl DaemonEventSink {
+    fn publish_app_server_event(&self, event: AppServerEvent) {
+        self.pending_approvals
+            .observe(&event.workspace_id, &event.message);
+        self.fanout_runs
+            .observe(&event.workspace_id, &event.message);
+        self.thread_usage.observe(&event.message);
+        self.time_tracker
+            .observe(&event.workspace_id, &event.message);
+        self.exporter
+            .app_server_event(&event.workspace_id, &event.message);
+        let artifacts = thumbnails_core::image_artifacts(&event.message);
+        if !artifacts.is_empty() {
+            let _ = self
+                .image_artifacts
+                .send((event.workspace_id.clone(), artifacts));
+        }
+        if let Some(write) = open_files::agent_write(&event.message) {
+            let _ = self.agent_writes.send((event.workspace_id.clone(), write));
+        }
+        let _ = self.tx.send(DaemonEvent::AppServer(event));
+    }
 }
 
 #[derive(Clone)]
@@ -111,11 +281,51 @@ enum DaemonEvent {
     TerminalOutput(TerminalOutput),
     #[allow(dead_code)]
     TerminalExit(TerminalExit),
+    ClientPresence(Value),
+    JobUpdate(JobInfo),
+    WorkspaceChanged(WorkspaceDelta),
+    FileConflict(FileConflict),
+    EditorPresence(EditorPresence),
+}
+
+impl DaemonEvent {
+    /// The workspace an event belongs to, for `subscribe_events` filtering.
+    fn workspace_id(&self) -> Option<&str> {
+        match self {
+            Self::AppServer(event) => Some(&event.workspace_id),
+            Self::TerminalOutput(event) => Some(&event.workspace_id),
+            Self::TerminalExit(event) => Some(&event.workspace_id),
+            Self::JobUpdate(job) => job.workspace_id.as_deref(),
+            Self::EditorPresence(presence) => Some(&presence.workspace_id),
+            Self::ClientPresence(_) | Self::WorkspaceChanged(_) | Self::FileConflict(_) => None,
+        }
+    }
+
+    /// The only client that receives the event; `None` broadcasts it.
+    fn target_client(&self) -> Option<&str> {
+        match self {
+            Self::FileConflict(conflict) => Some(&conflict.client_id),
+            _ => None,
+        }
+    }
+
+    /// Whether the connection of `client_id` forwards the event.
+    fn delivered_to(&self, client_id: &str, subscriptions: &EventSubscriptions) -> bool {
+        self.target_client()
+            .is_none_or(|target| target == client_id)
+            && subscriptions.allows(self.workspace_id())
+    }
 }
 
 impl EventSink for DaemonEventSink {
     fn emit_app_server_event(&self, event: AppServerEvent) {
-        let _ = self.tx.send(DaemonEvent::AppServer(event));
+        if rules::is_approval_request(&event.message) {
+            if let Err(mpsc::error::SendError(event)) = self.approval_requests.send(event) {
+                self.publish_app_server_event(event);
+            }
+            return;
+        }
+        self.publish_app_server_event(event);
     }
 
     fn emit_terminal_output(&self, event: TerminalOutput) {
@@ -128,9 +338,32 @@ impl EventSink for DaemonEventSink {
 }
 
 struct DaemonConfig {
-    listen: SocketAddr,
-    token: Option<String>,
+    /// `None` in `--stdio` mode, or with `--listen-unix` and no explicit `--listen`.
+    listen: Option<SocketAddr>,
+    /// Also serve clients on this unix socket.
+    listen_unix: Option<PathBuf>,
+    /// `--token` plus the scoped tokens of `--token-file`; empty without auth.
+    tokens: TokenSet,
     data_dir: PathBuf,
+    allowed_commands: CommandAllowlist,
+    trace_rpc: bool,
+    exporters: Vec<ExporterConfig>,
+    export_headers: Vec<(String, String)>,
+    process_limits: ProcessLimits,
+    rpc_timeouts: RpcTimeouts,
+    rate_limits: RateLimits,
+    /// Serve a single client on stdin/stdout instead of listening on `listen`.
+    stdio: bool,
+    /// Also accept clients through an outbound connection to a relay.
+    relay: Option<RelayConfig>,
+    /// Serve the REST gateway on this address.
+    http_listen: Option<SocketAddr>,
+    /// Wraps accepted TCP connections, including the REST gateway's, in TLS.
+    tls: Option<TlsAcceptor>,
+    log_level: LevelFilter,
+    log_format: LogFormat,
+    /// Runtime worker threads; tokio's default, one per core, when unset.
+    worker_threads: Option<usize>,
 }
 
 struct DaemonState {
@@ -142,20 +375,58 @@ struct DaemonState {
     app_settings: Mutex<AppSettings>,
     event_sink: DaemonEventSink,
     codex_login_cancels: Mutex<HashMap<String, CodexLoginCancelState>>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct WorkspaceFileResponse {
-    content: String,
-    truncated: bool,
+    command_allowlist: CommandAllowlist,
+    audit_log: AuditLog,
+    rpc_trace: RpcTrace,
+    rpc_timeouts: RpcTimeouts,
+    clients: ClientRegistry,
+    open_files: OpenFiles,
+    thread_branches: Mutex<HashMap<String, ThreadBranchRecord>>,
+    thread_branches_path: PathBuf,
+    search_indexes: search_index_core::SearchIndexes,
+    symbol_index: Arc<SymbolIndex>,
+    jobs: Arc<JobManager>,
+    turn_snapshots: TurnSnapshots,
+    file_history: FileHistory,
+    drafts: Drafts,
+    collaboration_presets: CollaborationPresets,
+    pinned_context: PinnedContext,
+    account_profiles: AccountProfiles,
+    mcp_health: McpHealthMonitor,
+    auth_watcher: AuthWatcher,
+    disk_monitor: DiskMonitor,
+    list_cache: ListCache,
+    file_lists: FileListCache,
+    problems: Problems,
+    removal_confirmations: RemovalConfirmations,
+    stale_branch_confirmations: StaleBranchConfirmations,
+    pending_writes: PendingWrites,
+    health: Arc<HealthMonitor>,
+    workspace_deltas: Mutex<WorkspaceDeltaTracker>,
+    resume_tickets: ResumeTickets<ParkedConnection>,
 }
 
 impl DaemonState {
     fn load(config: &DaemonConfig, event_sink: DaemonEventSink) -> Self {
-        let storage_path = config.data_dir.join("workspaces.json");
-        let settings_path = config.data_dir.join("settings.json");
+        let storage_path = config.data_dir.join(WORKSPACES_FILE);
+        let settings_path = config.data_dir.join(SETTINGS_FILE);
         let workspaces = read_workspaces(&storage_path).unwrap_or_default();
         let app_settings = read_settings(&settings_path).unwrap_or_default();
+        i18n_core::set_locale(&app_settings.locale);
+        file_policy::set_skip_dirs(&app_settings.skip_dirs);
+        let rpc_trace = RpcTrace::new(
+            &config.data_dir,
+            config.trace_rpc,
+            app_settings.trace_rpc_enabled,
+        );
+        let thread_branches_path = config
+            .data_dir
+            .join(thread_branches_core::THREAD_BRANCHES_FILE);
+        let thread_branches = read_thread_branches(&thread_branches_path).unwrap_or_default();
+        let job_events = event_sink.tx.clone();
+        let jobs = JobManager::new(move |info| {
+            let _ = job_events.send(DaemonEvent::JobUpdate(info));
+        });
         Self {
             data_dir: config.data_dir.clone(),
             workspaces: Mutex::new(workspaces),
@@ -165,13 +436,89 @@ impl DaemonState {
             app_settings: Mutex::new(app_settings),
             event_sink,
             codex_login_cancels: Mutex::new(HashMap::new()),
+            command_allowlist: config.allowed_commands.clone(),
+            audit_log: AuditLog::new(&config.data_dir),
+            rpc_trace,
+            rpc_timeouts: config.rpc_timeouts.clone(),
+            clients: ClientRegistry::default(),
+            open_files: OpenFiles::default(),
+            thread_branches: Mutex::new(thread_branches),
+            thread_branches_path,
+            search_indexes: search_index_core::SearchIndexes::default(),
+            symbol_index: Arc::default(),
+            jobs: Arc::new(jobs),
+            turn_snapshots: TurnSnapshots::load(&config.data_dir),
+            file_history: FileHistory::load(&config.data_dir),
+            drafts: Drafts::load(&config.data_dir),
+            collaboration_presets: CollaborationPresets::load(&config.data_dir),
+            pinned_context: PinnedContext::load(&config.data_dir),
+            account_profiles: AccountProfiles::load(&config.data_dir),
+            mcp_health: McpHealthMonitor::default(),
+            auth_watcher: AuthWatcher::default(),
+            disk_monitor: DiskMonitor::default(),
+            list_cache: ListCache::default(),
+            file_lists: FileListCache::default(),
+            problems: Problems::default(),
+            removal_confirmations: RemovalConfirmations::default(),
+            stale_branch_confirmations: StaleBranchConfirmations::default(),
+            pending_writes: PendingWrites::default(),
+            health: Arc::new(HealthMonitor::from_env()),
+            workspace_deltas: Mutex::new(WorkspaceDeltaTracker::default()),
+            resume_tickets: ResumeTickets::new(RESUME_TICKET_TTL),
+        }
+    }
+
+    /// Gate for every process the daemon spawns on behalf of a client.
+    fn ensure_command_allowed(
+        &self,
+        context: &str,
+        workspace_id: &str,
+        argv: &[String],
+    ) -> Result<(), String> {
+        if self.command_allowlist.allows(argv) {
+            return Ok(());
         }
+        tracing::warn!(
+            workspace_id,
+            context,
+            command = %argv.join(" "),
+            "denied command"
+        );
+        self.audit_log.record(
+            "command_denied",
+            json!({
+                "context": context,
+                "workspaceId": workspace_id,
+                "command": argv,
+            }),
+        );
+        Err(format!(
+            "Command not allowed by daemon allowlist: {}",
+            argv.first().map(String::as_str).unwrap_or_default()
+        ))
     }
 
     async fn list_workspaces(&self) -> Vec<WorkspaceInfo> {
         workspaces_core::list_workspaces_core(&self.workspaces, &self.sessions).await
     }
 
+    /// Baseline for `workspace-changed` deltas, taken once at startup.
+    async fn prime_workspace_deltas(&self) {
+        let mut tracker = self.workspace_deltas.lock().await;
+        tracker.prime(&self.list_workspaces().await);
+    }
+
+    /// Emits a `workspace-changed` event per workspace added, updated, removed, connected
+    /// or disconnected since the last call. The tracker stays locked while listing so
+    /// concurrent callers can't publish deltas out of order.
+    async fn publish_workspace_deltas(&self) {
+        let mut tracker = self.workspace_deltas.lock().await;
+        let workspaces = self.list_workspaces().await;
+        for delta in tracker.diff(&workspaces) {
+            let _ = self.event_sink.tx.send(DaemonEvent::WorkspaceChanged(delta));
+        }
+    }
+
     async fn is_workspace_path_dir(&self, path: String) -> bool {
         workspaces_core::is_workspace_path_dir_core(&path)
     }
@@ -192,7 +539,7 @@ impl DaemonState {
             &self.storage_path,
             move |entry, default_bin, codex_args, codex_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -208,16 +555,14 @@ impl DaemonState {
         &self,
         parent_id: String,
         branch: String,
-        name: Option<String>,
-        copy_agents_md: bool,
+        options: WorktreeOptions,
         client_version: String,
     ) -> Result<WorkspaceInfo, String> {
         let client_version = client_version.clone();
         workspaces_core::add_worktree_core(
             parent_id,
             branch,
-            name,
-            copy_agents_md,
+            options,
             &self.data_dir,
             &self.workspaces,
             &self.sessions,
@@ -235,12 +580,17 @@ impl DaemonState {
                 let branch_name = branch_name.to_string();
                 async move { git_core::git_find_remote_tracking_branch_local(&root, &branch_name).await }
             }),
+            |root, remote| {
+                let root = root.clone();
+                let remote = remote.to_string();
+                async move { git_core::git_remote_exists(&root, &remote).await }
+            },
             |root, args| {
                 workspaces_core::run_git_command_unit(root, args, git_core::run_git_command_owned)
             },
             move |entry, default_bin, codex_args, codex_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -252,6 +602,226 @@ impl DaemonState {
         .await
     }
 
+    /// Creates a worktree per branch off `parent_id`, starts a thread in each and sends
+    /// them all `prompt`. A branch that can't start is marked failed; the others are
+    /// followed through their thread events.
+    async fn fanout_run(
+        &self,
+        parent_id: String,
+        branches: Vec<String>,
+        prompt: String,
+        client_version: String,
+    ) -> Result<FanoutRun, String> {
+        let branches = fanout::validate_fanout(&branches, &prompt)?;
+        let parent_root = self.workspace_root(&parent_id).await?;
+        let base_commit = git_core::run_git_command(&parent_root, &["rev-parse", "HEAD"]).await?;
+        let runs = &self.event_sink.fanout_runs;
+        let run = runs.create(&parent_id, branches, &prompt, Some(base_commit));
+        for (index, branch) in run.branches.iter().enumerate() {
+            let started = self
+                .start_fanout_branch(
+                    &run.id,
+                    index,
+                    &parent_id,
+                    &branch.branch,
+                    &prompt,
+                    &client_version,
+                )
+                .await;
+            if let Err(error) = started {
+                runs.update_branch(&run.id, index, |branch| {
+                    branch.status = FanoutStatus::Failed;
+                    branch.error = Some(error);
+                });
+            }
+        }
+        runs.get(&run.id)
+            .ok_or_else(|| "Fan-out run was dropped".to_string())
+    }
+
+    /// Side-by-side data for picking a winner: each branch's diff against the run's
+    /// base, its problems (after a fresh check with `run_checks`) and its token use.
+    async fn compare_fanout(
+        &self,
+        run_id: &str,
+        run_checks: bool,
+    ) -> Result<FanoutComparison, String> {
+        let run = self
+            .event_sink
+            .fanout_runs
+            .get(run_id)
+            .ok_or_else(|| format!("Unknown fan-out run: {run_id}"))?;
+        let mut branches = Vec::new();
+        for branch in run.branches {
+            let mut comparison = FanoutBranchComparison {
+                branch: branch.branch,
+                workspace_id: branch.workspace_id.clone(),
+                status: branch.status,
+                tokens_used: branch.tokens_used,
+                summary: branch.summary,
+                diff: None,
+                diff_error: None,
+                build: None,
+                build_error: None,
+                error_count: 0,
+                warning_count: 0,
+            };
+            let Some(workspace_id) = branch.workspace_id else {
+                branches.push(comparison);
+                continue;
+            };
+            let diff = match (self.workspace_root(&workspace_id).await, &run.base_commit) {
+                (Ok(root), Some(base)) => worktree_diff_core::worktree_diff_core(&root, base).await,
+                (Ok(_), None) => Err("The run has no base commit".to_string()),
+                (Err(error), _) => Err(error),
+            };
+            match diff {
+                Ok(diff) => comparison.diff = Some(diff),
+                Err(error) => comparison.diff_error = Some(error),
+            }
+            if run_checks {
+                match self.run_build(workspace_id.clone()).await {
+                    Ok(report) => comparison.build = Some(report),
+                    Err(error) => comparison.build_error = Some(error),
+                }
+            }
+            let problems = self.problems.view(&workspace_id);
+            comparison.error_count = problems.error_count;
+            comparison.warning_count = problems.warning_count;
+            branches.push(comparison);
+        }
+        Ok(FanoutComparison {
+            run_id: run.id,
+            parent_id: run.parent_id,
+            base_commit: run.base_commit,
+            picked: run.picked,
+            branches,
+        })
+    }
+
+    /// Applies the chosen branch's changes to the parent's working tree, then removes
+    /// every worktree of the run and deletes the branches with no commits of their own.
+    async fn fanout_pick(&self, run_id: &str, branch: &str) -> Result<FanoutPickResult, String> {
+        let runs = &self.event_sink.fanout_runs;
+        let run = runs
+            .get(run_id)
+            .ok_or_else(|| format!("Unknown fan-out run: {run_id}"))?;
+        if let Some(picked) = &run.picked {
+            return Err(format!("Branch {picked} was already picked for this run"));
+        }
+        let workspace_id = run
+            .branches
+            .iter()
+            .find(|entry| entry.branch == branch)
+            .ok_or_else(|| format!("Branch {branch} is not part of this run"))?
+            .workspace_id
+            .clone()
+            .ok_or_else(|| format!("Branch {branch} has no worktree"))?;
+        let base_commit = run.base_commit.clone().ok_or("The run has no base commit")?;
+        let parent_root = self.workspace_root(&run.parent_id).await?;
+        let worktree_root = self.workspace_root(&workspace_id).await?;
+
+        let parent_status =
+            git_core::run_git_command(&parent_root, &["status", "--porcelain"]).await?;
+        if !parent_status.trim().is_empty() {
+            return Err("The parent has uncommitted changes. Commit, stash, or discard them \
+                 before picking a branch."
+                .to_string());
+        }
+        let base = worktree_diff_core::diff_base(&worktree_root, &base_commit).await?;
+        let patch = worktree_diff_core::worktree_patch(&worktree_root, &base).await?;
+        let patch_text = String::from_utf8_lossy(&patch);
+        if patch_text.trim().is_empty() {
+            return Err("No changes to apply.".to_string());
+        }
+        worktree_diff_core::apply_patch(&parent_root, &patch).await?;
+        runs.mark_picked(run_id, branch)?;
+
+        let mut result = FanoutPickResult {
+            run_id: run.id.clone(),
+            branch: branch.to_string(),
+            applied_files: patch_text.matches("diff --git ").count(),
+            removed_worktrees: Vec::new(),
+            deleted_branches: Vec::new(),
+            errors: Vec::new(),
+        };
+        for entry in &run.branches {
+            let Some(workspace_id) = &entry.workspace_id else {
+                continue;
+            };
+            if let Err(error) = self.remove_worktree(workspace_id.clone()).await {
+                result.errors.push(format!("{}: {error}", entry.branch));
+                continue;
+            }
+            result.removed_worktrees.push(entry.branch.clone());
+            let range = format!("{base_commit}..{}", entry.branch);
+            let own_commits =
+                git_core::run_git_command(&parent_root, &["rev-list", "--count", &range]).await;
+            let deleted = match own_commits.as_deref() {
+                Ok("0") => {
+                    git_core::run_git_command(&parent_root, &["branch", "-D", &entry.branch]).await
+                }
+                Ok(_) => continue,
+                Err(error) => Err(error.clone()),
+            };
+            match deleted {
+                Ok(_) => result.deleted_branches.push(entry.branch.clone()),
+                Err(error) => result.errors.push(format!("{}: {error}", entry.branch)),
+            }
+        }
+        Ok(result)
+    }
+
+    async fn workspace_root(&self, workspace_id: &str) -> Result<PathBuf, String> {
+        let workspaces = self.workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .map(|entry| PathBuf::from(&entry.path))
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))
+    }
+
+    async fn start_fanout_branch(
+        &self,
+        run_id: &str,
+        index: usize,
+        parent_id: &str,
+        branch: &str,
+        prompt: &str,
+        client_version: &str,
+    ) -> Result<(), String> {
+        let runs = &self.event_sink.fanout_runs;
+        let workspace = self
+            .add_worktree(
+                parent_id.to_string(),
+                branch.to_string(),
+                WorktreeOptions::default(),
+                client_version.to_string(),
+            )
+            .await?;
+        runs.update_branch(run_id, index, |entry| {
+            entry.workspace_id = Some(workspace.id.clone());
+        });
+        let response = self.start_thread(workspace.id.clone(), None).await?;
+        let thread_id = thread_branches_core::thread_id_from_response(&response)
+            .ok_or("Failed to start the branch thread")?;
+        // Running before the prompt goes out, so an early `turn/completed` is caught.
+        runs.update_branch(run_id, index, |entry| {
+            entry.thread_id = Some(thread_id.clone());
+            entry.status = FanoutStatus::Running;
+        });
+        let response = self
+            .send_user_message(SendUserMessageRequest {
+                workspace_id: workspace.id,
+                thread_id,
+                text: prompt.to_string(),
+                ..Default::default()
+            })
+            .await?;
+        let turn_id = turn_snapshots_core::turn_id_from_response(&response);
+        runs.update_branch(run_id, index, |entry| entry.turn_id = turn_id);
+        Ok(())
+    }
+
     async fn worktree_setup_status(&self, workspace_id: String) -> Result<WorktreeSetupStatus, String> {
         workspaces_core::worktree_setup_status_core(&self.workspaces, &workspace_id, &self.data_dir)
             .await
@@ -262,8 +832,25 @@ impl DaemonState {
             .await
     }
 
+    /// First step of `remove_workspace`/`remove_worktree`: what would go, plus a token.
+    async fn plan_removal(&self, id: &str, worktree: bool) -> Result<RemovalPlan, String> {
+        removal_core::plan_removal_core(&self.workspaces, &self.removal_confirmations, id, worktree)
+            .await
+    }
+
+    async fn confirm_removal(&self, id: &str, worktree: bool, token: &str) -> Result<(), String> {
+        removal_core::confirm_removal_core(
+            &self.workspaces,
+            &self.removal_confirmations,
+            id,
+            worktree,
+            token,
+        )
+        .await
+    }
+
     async fn remove_workspace(&self, id: String) -> Result<(), String> {
-        workspaces_core::remove_workspace_core(
+        let result = workspaces_core::remove_workspace_core(
             id,
             &self.workspaces,
             &self.sessions,
@@ -273,17 +860,19 @@ impl DaemonState {
             },
             |error| git_core::is_missing_worktree_error(error),
             |path| {
-                std::fs::remove_dir_all(path)
+                blocking(|| std::fs::remove_dir_all(path))
                     .map_err(|err| format!("Failed to remove worktree folder: {err}"))
             },
             true,
             true,
         )
-        .await
+        .await;
+        self.prune_pending_approvals().await;
+        result
     }
 
     async fn remove_worktree(&self, id: String) -> Result<(), String> {
-        workspaces_core::remove_worktree_core(
+        let result = workspaces_core::remove_worktree_core(
             id,
             &self.workspaces,
             &self.sessions,
@@ -293,23 +882,69 @@ impl DaemonState {
             },
             |error| git_core::is_missing_worktree_error(error),
             |path| {
-                std::fs::remove_dir_all(path)
+                blocking(|| std::fs::remove_dir_all(path))
                     .map_err(|err| format!("Failed to remove worktree folder: {err}"))
             },
         )
+        .await;
+        self.prune_pending_approvals().await;
+        result
+    }
+
+    async fn stale_branches_report(
+        &self,
+        request: StaleBranchesReportRequest,
+    ) -> Result<StaleBranchesReport, String> {
+        stale_branches_core::stale_branches_report_core(
+            &self.workspaces,
+            &self.thread_branches,
+            &self.stale_branch_confirmations,
+            &request.workspace_id,
+            request.inactive_days,
+            request.base,
+        )
         .await
     }
 
+    async fn delete_stale_branches(
+        &self,
+        request: DeleteStaleBranchesRequest,
+    ) -> Result<StaleBranchDeletion, String> {
+        stale_branches_core::delete_stale_branches_core(
+            &self.workspaces,
+            &self.stale_branch_confirmations,
+            &request.workspace_id,
+            &request.branches,
+            &request.confirm_token,
+            request.force.unwrap_or(false),
+        )
+        .await
+    }
+
+    async fn reconcile_worktrees(&self, dry_run: bool) -> Result<WorktreeReconcileReport, String> {
+        let report = worktree_reconcile_core::reconcile_worktrees_core(
+            &self.workspaces,
+            &self.sessions,
+            &self.storage_path,
+            dry_run,
+        )
+        .await;
+        self.prune_pending_approvals().await;
+        report
+    }
+
     async fn rename_worktree(
         &self,
         id: String,
         branch: String,
+        allow_protected_branch: bool,
         client_version: String,
     ) -> Result<WorkspaceInfo, String> {
         let client_version = client_version.clone();
         workspaces_core::rename_worktree_core(
             id,
             branch,
+            allow_protected_branch,
             &self.data_dir,
             &self.workspaces,
             &self.sessions,
@@ -332,7 +967,7 @@ impl DaemonState {
             },
             move |entry, default_bin, codex_args, codex_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -349,11 +984,13 @@ impl DaemonState {
         id: String,
         old_branch: String,
         new_branch: String,
+        allow_protected_branch: bool,
     ) -> Result<(), String> {
         workspaces_core::rename_worktree_upstream_core(
             id,
             old_branch,
             new_branch,
+            allow_protected_branch,
             &self.workspaces,
             |entry| Ok(PathBuf::from(entry.path.clone())),
             |root, branch_name| {
@@ -390,12 +1027,16 @@ impl DaemonState {
         &self,
         id: String,
         settings: WorkspaceSettings,
+        expected_revision: Option<u64>,
         client_version: String,
     ) -> Result<WorkspaceInfo, String> {
         let client_version = client_version.clone();
         workspaces_core::update_workspace_settings_core(
-            id,
-            settings,
+            workspaces_core::WorkspaceSettingsUpdate {
+                id,
+                settings,
+                expected_revision,
+            },
             &self.workspaces,
             &self.sessions,
             &self.app_settings,
@@ -405,7 +1046,7 @@ impl DaemonState {
             },
             move |entry, default_bin, codex_args, codex_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -421,13 +1062,54 @@ impl DaemonState {
         &self,
         id: String,
         codex_bin: Option<String>,
+        expected_revision: Option<u64>,
     ) -> Result<WorkspaceInfo, String> {
         workspaces_core::update_workspace_codex_bin_core(
             id,
             codex_bin,
+            expected_revision,
+            &self.workspaces,
+            &self.sessions,
+            &self.storage_path,
+        )
+        .await
+    }
+
+    async fn relocate_workspace(
+        &self,
+        id: String,
+        new_path: String,
+        client_version: String,
+    ) -> Result<WorkspaceInfo, String> {
+        let client_version = client_version.clone();
+        workspaces_core::relocate_workspace_core(
+            workspaces_core::WorkspaceRelocation { id, new_path },
             &self.workspaces,
             &self.sessions,
+            &self.app_settings,
             &self.storage_path,
+            |root, args| {
+                workspaces_core::run_git_command_unit(root, args, git_core::run_git_command_owned)
+            },
+            move |entry, default_bin, codex_args, codex_home| {
+                spawn_with_client(
+                    self,
+                    client_version.clone(),
+                    entry,
+                    default_bin,
+                    codex_args,
+                    codex_home,
+                )
+            },
+        )
+        .await
+    }
+
+    async fn preview_codex_command(&self, workspace_id: String) -> Result<CodexCommand, String> {
+        workspaces_core::preview_codex_command_core(
+            workspace_id,
+            &self.workspaces,
+            &self.app_settings,
         )
         .await
     }
@@ -439,6 +1121,7 @@ impl DaemonState {
                 return Ok(());
             }
         }
+        self.list_cache.invalidate_workspace(&id);
 
         let client_version = client_version.clone();
         workspaces_core::connect_workspace_core(
@@ -448,7 +1131,7 @@ impl DaemonState {
             &self.app_settings,
             move |entry, default_bin, codex_args, codex_home| {
                 spawn_with_client(
-                    self.event_sink.clone(),
+                    self,
                     client_version.clone(),
                     entry,
                     default_bin,
@@ -464,14 +1147,122 @@ impl DaemonState {
         settings_core::get_app_settings_core(&self.app_settings).await
     }
 
-    async fn update_app_settings(&self, settings: AppSettings) -> Result<AppSettings, String> {
-        settings_core::update_app_settings_core(settings, &self.app_settings, &self.settings_path)
+    async fn update_app_settings(
+        &self,
+        settings: AppSettings,
+        expected_revision: Option<u64>,
+    ) -> Result<AppSettings, String> {
+        let updated = settings_core::update_app_settings_core(
+            settings,
+            expected_revision,
+            &self.app_settings,
+            &self.settings_path,
+        )
+        .await?;
+        self.rpc_trace.apply_setting(updated.trace_rpc_enabled);
+        Ok(updated)
+    }
+
+    /// Broadcasts a presence change (`connected`, `updated`, `disconnected`, `kicked`).
+    fn emit_client_presence(&self, event: &str, client: &ClientInfo) {
+        let _ = self.event_sink.tx.send(DaemonEvent::ClientPresence(json!({
+            "event": event,
+            "client": client,
+        })));
+    }
+
+    fn kick_client(&self, client_id: &str) -> Result<ClientInfo, String> {
+        let client = self
+            .clients
+            .kick(client_id)
+            .ok_or_else(|| format!("client not found: {client_id}"))?;
+        self.audit_log.record(
+            "client_kicked",
+            json!({
+                "clientId": client.id,
+                "deviceName": client.device_name,
+                "remoteAddr": client.remote_addr,
+            }),
+        );
+        Ok(client)
+    }
+
+    fn tail_trace(&self, limit: Option<usize>) -> Result<Value, String> {
+        let entries = self.rpc_trace.tail(limit.unwrap_or(100).clamp(1, 5_000))?;
+        Ok(json!({
+            "enabled": self.rpc_trace.is_enabled(),
+            "path": self.rpc_trace.path().to_string_lossy(),
+            "entries": entries,
+        }))
+    }
+
+    fn get_session_log(
+        &self,
+        workspace_id: String,
+        tail: Option<usize>,
+    ) -> Result<session_log_core::SessionLogTail, String> {
+        session_log_core::session_log_tail_core(
+            &self.data_dir.join(session_log_core::SESSION_LOGS_DIR),
+            &workspace_id,
+            tail,
+        )
+    }
+
+    fn backup_data(&self, destination: String) -> Result<backup_core::BackupResult, String> {
+        let rules_path = backup_core::default_backup_rules_path();
+        backup_core::backup_data_core(
+            &self.data_dir,
+            Path::new(&destination),
+            rules_path.as_deref(),
+        )
+    }
+
+    async fn restore_data(
+        &self,
+        source: String,
+        confirm: bool,
+    ) -> Result<backup_core::RestoreResult, String> {
+        let rules_path = backup_core::default_backup_rules_path();
+        backup_core::restore_data_core(
+            Path::new(&source),
+            confirm,
+            backup_core::RestoreTargets {
+                data_dir: &self.data_dir,
+                rules_path: rules_path.as_deref(),
+                workspaces: &self.workspaces,
+                app_settings: &self.app_settings,
+                thread_branches: &self.thread_branches,
+                storage_path: &self.storage_path,
+                settings_path: &self.settings_path,
+            },
+        )
+        .await
+    }
+
+    async fn export_safety_policy(&self) -> SafetyPolicyBundle {
+        let rules_path = backup_core::default_backup_rules_path();
+        safety_policy_core::export_safety_policy_core(&self.app_settings, rules_path.as_deref())
             .await
     }
 
+    async fn import_safety_policy(
+        &self,
+        request: ImportSafetyPolicyRequest,
+    ) -> Result<SafetyPolicyImportResult, String> {
+        let rules_path = backup_core::default_backup_rules_path();
+        safety_policy_core::import_safety_policy_core(
+            request.bundle,
+            request.mode.unwrap_or_default(),
+            &self.app_settings,
+            &self.settings_path,
+            rules_path.as_deref(),
+        )
+        .await
+    }
+
     async fn list_workspace_files(&self, workspace_id: String) -> Result<Vec<String>, String> {
         workspaces_core::list_workspace_files_core(&self.workspaces, &workspace_id, |root| {
-            list_workspace_files_inner(root, 20000)
+            blocking(|| self.file_lists.list(root, 20000, list_workspace_files_inner))
         })
         .await
     }
@@ -481,66 +1272,1102 @@ impl DaemonState {
         workspace_id: String,
         path: String,
     ) -> Result<WorkspaceFileResponse, String> {
+        let max_bytes =
+            file_transfer_core::max_read_bytes(&self.app_settings.lock().await.file_limits);
         workspaces_core::read_workspace_file_core(
             &self.workspaces,
             &workspace_id,
             &path,
-            |root, rel_path| read_workspace_file_inner(root, rel_path),
+            |root, rel_path| blocking(|| read_workspace_file_inner(root, rel_path, max_bytes)),
         )
         .await
     }
 
-    async fn file_read(
+    async fn capabilities(&self) -> Capabilities {
+        file_transfer_core::capabilities(&*self.app_settings.lock().await)
+    }
+
+    async fn begin_write(
         &self,
-        scope: file_policy::FileScope,
-        kind: file_policy::FileKind,
-        workspace_id: Option<String>,
-    ) -> Result<file_io::TextFileResponse, String> {
-        files_core::file_read_core(&self.workspaces, scope, kind, workspace_id).await
+        workspace_id: String,
+        path: String,
+        size: Option<u64>,
+    ) -> Result<WriteSession, String> {
+        file_transfer_core::begin_write_core(
+            &self.workspaces,
+            &self.app_settings,
+            &self.pending_writes,
+            &workspace_id,
+            &path,
+            size,
+        )
+        .await
     }
 
-    async fn file_write(
+    fn write_chunk(
         &self,
-        scope: file_policy::FileScope,
-        kind: file_policy::FileKind,
-        workspace_id: Option<String>,
-        content: String,
-    ) -> Result<(), String> {
-        files_core::file_write_core(&self.workspaces, scope, kind, workspace_id, content).await
+        write_id: &str,
+        offset: u64,
+        data: &str,
+    ) -> Result<WriteProgress, String> {
+        blocking(|| {
+            file_transfer_core::write_chunk_core(&self.pending_writes, write_id, offset, data)
+        })
     }
 
-    async fn start_thread(&self, workspace_id: String) -> Result<Value, String> {
-        codex_core::start_thread_core(&self.sessions, workspace_id).await
+    fn commit_write(&self, write_id: &str, sha256: Option<&str>) -> Result<CommittedWrite, String> {
+        let committed = blocking(|| {
+            file_transfer_core::commit_write_core(
+                &self.pending_writes,
+                &self.file_lists,
+                &self.file_history,
+                write_id,
+                sha256,
+            )
+        })?;
+        // Clients holding the file hear about it the same way as about an agent's edit.
+        let _ = self.event_sink.agent_writes.send((
+            committed.workspace_id.clone(),
+            AgentWrite {
+                thread_id: None,
+                item_id: None,
+                paths: vec![committed.path.clone()],
+            },
+        ));
+        Ok(committed)
     }
 
-    async fn resume_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
-        codex_core::resume_thread_core(&self.sessions, workspace_id, thread_id).await
+    fn abort_write(&self, write_id: &str) -> Result<(), String> {
+        file_transfer_core::abort_write_core(&self.pending_writes, write_id)
     }
 
-    async fn fork_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
-        codex_core::fork_thread_core(&self.sessions, workspace_id, thread_id).await
+    async fn resolve_mentions(
+        &self,
+        workspace_id: String,
+        text: String,
+    ) -> Result<mentions_core::MentionResolution, String> {
+        mentions_core::resolve_mentions_core(&self.workspaces, &workspace_id, &text, |root, max| {
+            blocking(|| self.file_lists.list(root, max, list_workspace_files_inner))
+        })
+        .await
     }
 
-    async fn list_threads(
+    async fn plan_context(
         &self,
         workspace_id: String,
-        cursor: Option<String>,
-        limit: Option<u32>,
-        sort_key: Option<String>,
-    ) -> Result<Value, String> {
-        codex_core::list_threads_core(&self.sessions, workspace_id, cursor, limit, sort_key).await
+        text: String,
+        model: Option<String>,
+        budget_tokens: Option<usize>,
+    ) -> Result<context_budget_core::ContextPlan, String> {
+        context_budget_core::plan_context_core(
+            &self.workspaces,
+            &workspace_id,
+            &text,
+            model.as_deref(),
+            budget_tokens,
+            |root, max| blocking(|| self.file_lists.list(root, max, list_workspace_files_inner)),
+        )
+        .await
     }
 
-    async fn list_mcp_server_status(
+    async fn indexed_search(
         &self,
         workspace_id: String,
-        cursor: Option<String>,
-        limit: Option<u32>,
-    ) -> Result<Value, String> {
-        codex_core::list_mcp_server_status_core(&self.sessions, workspace_id, cursor, limit).await
+        query: String,
+        limit: Option<usize>,
+        path_prefix: Option<String>,
+        options: search_index_core::SearchOptions,
+    ) -> Result<search_index_core::IndexedSearchResponse, String> {
+        search_index_core::indexed_search_core(
+            &self.workspaces,
+            &self.search_indexes,
+            &workspace_id,
+            search_index_core::SearchQuery {
+                query: &query,
+                limit,
+                path_prefix: path_prefix.as_deref(),
+                options,
+            },
+            list_workspace_files_inner,
+        )
+        .await
+    }
+
+    async fn workspace_symbols(
+        &self,
+        workspace_id: &str,
+        query: &str,
+        limit: Option<usize>,
+    ) -> Result<WorkspaceSymbolsResponse, String> {
+        symbols_core::workspace_symbols_core(
+            &self.workspaces,
+            &self.symbol_index,
+            workspace_id,
+            query,
+            limit,
+            |root, max| blocking(|| self.file_lists.list(root, max, list_workspace_files_inner)),
+        )
+        .await
+    }
+
+    async fn find_definition(
+        &self,
+        request: FindDefinitionRequest,
+    ) -> Result<DefinitionLookup, String> {
+        symbols_core::find_definition_core(
+            &self.workspaces,
+            &self.symbol_index,
+            &request.workspace_id,
+            &request.path,
+            request.line,
+            request.column,
+            |root, max| blocking(|| self.file_lists.list(root, max, list_workspace_files_inner)),
+        )
+        .await
+    }
+
+    async fn search_all_workspaces(
+        &self,
+        query: String,
+        workspace_ids: Option<Vec<String>>,
+        limit: Option<usize>,
+        options: search_index_core::SearchOptions,
+    ) -> Result<search_index_core::MultiWorkspaceSearchResponse, String> {
+        search_index_core::search_all_workspaces_core(
+            &self.workspaces,
+            &self.search_indexes,
+            &query,
+            workspace_ids,
+            limit,
+            options,
+            list_workspace_files_inner,
+        )
+        .await
+    }
+
+    async fn generate_commit_message(
+        &self,
+        workspace_id: String,
+        staged: Option<bool>,
+    ) -> Result<String, String> {
+        commit_message_core::generate_commit_message_core(
+            &self.workspaces,
+            &self.sessions,
+            &workspace_id,
+            staged,
+            &self.event_sink,
+        )
+        .await
+    }
+
+    async fn ci_status(
+        &self,
+        workspace_id: String,
+        branch: String,
+    ) -> Result<ci_core::CiStatus, String> {
+        ci_core::ci_status_core(&self.workspaces, &workspace_id, &branch, |argv| {
+            self.ensure_command_allowed("ci_status", &workspace_id, argv)
+        })
+        .await
+    }
+
+    async fn sparse_checkout(&self, workspace_id: &str) -> Result<SparseCheckout, String> {
+        sparse_checkout_core::get_sparse_checkout_core(&self.workspaces, workspace_id).await
+    }
+
+    async fn set_sparse_checkout(
+        &self,
+        workspace_id: &str,
+        patterns: Option<Vec<String>>,
+    ) -> Result<SparseCheckout, String> {
+        let (root, sparse) = match patterns {
+            Some(patterns) => {
+                sparse_checkout_core::set_sparse_checkout_core(
+                    &self.workspaces,
+                    workspace_id,
+                    &patterns,
+                )
+                .await?
+            }
+            None => {
+                sparse_checkout_core::disable_sparse_checkout_core(&self.workspaces, workspace_id)
+                    .await?
+            }
+        };
+        self.file_lists.invalidate(&root);
+        Ok(sparse)
+    }
+
+    async fn workspace_stats(
+        &self,
+        id: String,
+    ) -> Result<workspace_stats_core::WorkspaceStats, String> {
+        workspace_stats_core::workspace_stats_core(
+            &self.workspaces,
+            &self.thread_branches,
+            &self.event_sink.time_tracker,
+            &id,
+            list_workspace_files_inner,
+        )
+        .await
+    }
+
+    /// The background monitor's last report unless `refresh`; measuring walks every tree.
+    async fn disk_usage(
+        &self,
+        workspace_id: Option<&str>,
+        refresh: bool,
+    ) -> Result<DiskUsageReport, String> {
+        let report = match self.disk_monitor.latest().filter(|_| !refresh) {
+            Some(report) => report,
+            None => {
+                let quota = self.app_settings.lock().await.disk_quota.clone();
+                self.disk_monitor
+                    .check_once(&self.workspaces, &self.data_dir, &quota, &self.event_sink)
+                    .await?
+            }
+        };
+        Ok(match workspace_id {
+            Some(workspace_id) => disk_usage_core::report_for_workspace(report, workspace_id),
+            None => report,
+        })
+    }
+
+    async fn analyze_workspace(&self, id: &str) -> Result<WorkspaceAnalysis, String> {
+        workspace_analysis_core::analyze_workspace_core(
+            &self.workspaces,
+            id,
+            list_workspace_files_inner,
+        )
+        .await
+    }
+
+    async fn list_packages(
+        &self,
+        workspace_id: String,
+    ) -> Result<Vec<packages_core::WorkspacePackage>, String> {
+        packages_core::list_packages_core(&self.workspaces, &workspace_id).await
+    }
+
+    async fn audit_dependencies(
+        &self,
+        workspace_id: String,
+    ) -> Result<dependency_audit_core::DependencyAudit, String> {
+        dependency_audit_core::audit_dependencies_core(&self.workspaces, &workspace_id, |argv| {
+            self.ensure_command_allowed("audit_dependencies", &workspace_id, argv)
+        })
+        .await
+    }
+
+    async fn run_build(&self, workspace_id: String) -> Result<BuildReport, String> {
+        build_core::run_build_core(&self.workspaces, &self.problems, &workspace_id, |argv| {
+            self.ensure_command_allowed("run_build", &workspace_id, argv)
+        })
+        .await
+    }
+
+    fn list_problems(&self, workspace_id: &str) -> ProblemsView {
+        self.problems.view(workspace_id)
+    }
+
+    async fn run_retention(&self, dry_run: bool) -> retention_core::RetentionReport {
+        let policy = self.app_settings.lock().await.retention.clone();
+        let report =
+            retention_core::run_retention_core(&self.workspaces, &self.sessions, policy, dry_run)
+                .await;
+        if !dry_run {
+            for thread in report.archived.iter().filter(|thread| thread.error.is_none()) {
+                self.list_cache.invalidate_workspace(&thread.workspace_id);
+            }
+        }
+        report
+    }
+
+    /// Checks → agent fix → checks again, until they pass or a limit is hit. The fixes
+    /// happen on one regular thread so they can be followed and reviewed like any other.
+    async fn auto_fix(
+        self: &Arc<Self>,
+        request: AutoFixRequest,
+        progress: JobProgress,
+    ) -> Result<AutoFixReport, String> {
+        let AutoFixRequest {
+            workspace_id,
+            goal,
+            test_command,
+            max_iterations,
+            max_tokens,
+        } = request;
+        let limits = AutoFixLimits::new(max_iterations, max_tokens);
+        let root = self.workspace_root(&workspace_id).await?;
+        if let Some(argv) = &test_command {
+            self.ensure_command_allowed("auto_fix", &workspace_id, argv)?;
+        }
+
+        let mut thread_id: Option<String> = None;
+        let mut iterations = Vec::new();
+        let mut tokens_used = 0;
+        let mut attempt = 0;
+        loop {
+            let done = f64::from(attempt) / f64::from(limits.max_iterations);
+            progress.report(Some(done), format!("Running checks (attempt {attempt})"));
+            let build = self.run_build(workspace_id.clone()).await?;
+            let tests = match &test_command {
+                Some(argv) if build.success => Some(auto_fix::run_tests(&root, argv).await),
+                _ => None,
+            };
+            if let Some(error) = auto_fix::check_failure(&build, tests.as_ref()) {
+                return Err(format!("Auto-fix stopped: {error}"));
+            }
+            let outcome = if auto_fix::checks_pass(&build, tests.as_ref()) {
+                Some(AutoFixOutcome::Fixed)
+            } else if attempt == limits.max_iterations {
+                Some(AutoFixOutcome::MaxIterations)
+            } else if tokens_used >= limits.max_tokens {
+                Some(AutoFixOutcome::BudgetExhausted)
+            } else {
+                None
+            };
+            if let Some(outcome) = outcome {
+                return Ok(AutoFixReport {
+                    workspace_id,
+                    thread_id,
+                    outcome,
+                    iterations,
+                    tokens_used,
+                    build,
+                    tests,
+                });
+            }
+
+            attempt += 1;
+            let fix_thread = match &thread_id {
+                Some(thread_id) => thread_id.clone(),
+                None => {
+                    let response = self.start_thread(workspace_id.clone(), None).await?;
+                    let id = thread_branches_core::thread_id_from_response(&response)
+                        .ok_or("Failed to start the fix thread")?;
+                    thread_id = Some(id.clone());
+                    id
+                }
+            };
+            let error_count = build
+                .problems
+                .iter()
+                .filter(|problem| problem.severity == ProblemSeverity::Error)
+                .count();
+            let tests_failed = tests.as_ref().is_some_and(|tests| !tests.success);
+            progress.report(
+                Some(done),
+                format!(
+                    "Fix attempt {attempt} of {}: {error_count} error(s){}",
+                    limits.max_iterations,
+                    if tests_failed { ", failing tests" } else { "" }
+                ),
+            );
+            let prompt = auto_fix::fix_prompt(goal.as_deref(), attempt, &build, tests.as_ref());
+            let (turn_id, used) = self.run_fix_turn(&workspace_id, &fix_thread, prompt).await?;
+            tokens_used = tokens_used.max(used);
+            iterations.push(AutoFixIteration {
+                iteration: attempt,
+                error_count,
+                tests_failed,
+                turn_id,
+                tokens_used,
+            });
+        }
+    }
+
+    /// Sends one fix prompt and waits for its turn to finish, returning the turn id and
+    /// the thread's token total. A cancelled job interrupts the turn it was waiting on.
+    async fn run_fix_turn(
+        self: &Arc<Self>,
+        workspace_id: &str,
+        thread_id: &str,
+        prompt: String,
+    ) -> Result<(Option<String>, u64), String> {
+        // Subscribe first: a quick turn can complete before `turn/start` answers.
+        let mut events = self.event_sink.tx.subscribe();
+        let response = self
+            .send_user_message(SendUserMessageRequest {
+                workspace_id: workspace_id.to_string(),
+                thread_id: thread_id.to_string(),
+                text: prompt,
+                ..Default::default()
+            })
+            .await?;
+        let turn_id = turn_snapshots_core::turn_id_from_response(&response);
+        let interrupt = turn_id.clone().map(|turn_id| {
+            let state = Arc::clone(self);
+            let workspace_id = workspace_id.to_string();
+            let thread_id = thread_id.to_string();
+            DropGuard::new(move || {
+                tokio::spawn(async move {
+                    let _ = state.turn_interrupt(workspace_id, thread_id, turn_id).await;
+                });
+            })
+        });
+
+        let mut tokens_used = 0;
+        let finished = tokio::time::timeout(auto_fix::TURN_TIMEOUT, async {
+            loop {
+                match events.recv().await {
+                    Ok(Sequenced {
+                        event: DaemonEvent::AppServer(event),
+                        ..
+                    }) if event.workspace_id == workspace_id => {
+                        match auto_fix::turn_signal(&event.message, thread_id) {
+                            Some(TurnSignal::TokensUsed(total)) => tokens_used = total,
+                            Some(TurnSignal::Completed { error }) => {
+                                return error.map_or(Ok(()), Err)
+                            }
+                            None => {}
+                        }
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Err("Daemon event stream closed".to_string())
+                    }
+                }
+            }
+        })
+        .await
+        .map_err(|_| "Timed out waiting for the fix turn".to_string())?;
+        // Past this point the turn is over; only a timeout or cancel interrupts it.
+        if let Some(interrupt) = interrupt {
+            interrupt.disarm();
+        }
+        finished?;
+        Ok((turn_id, tokens_used))
+    }
+
+    /// Starts a long-running operation as a job; arguments are validated up front so a
+    /// bad request fails the RPC instead of producing a failed job.
+    fn start_job(self: &Arc<Self>, kind: &str, params: &Value) -> Result<JobInfo, String> {
+        let state = Arc::clone(self);
+        match kind {
+            "index_build" => {
+                let workspace_id = parse_request::<WorkspaceRequest>(params)?.workspace_id;
+                Ok(self.jobs.start(
+                    kind,
+                    Some(workspace_id.clone()),
+                    move |progress| async move {
+                        let indexed_files = search_index_core::rebuild_index_core(
+                            &state.workspaces,
+                            &state.search_indexes,
+                            &workspace_id,
+                            list_workspace_files_inner,
+                            move |visited, total| {
+                                progress.report(
+                                    Some(visited as f64 / total.max(1) as f64),
+                                    format!("Indexed {visited} of {total} files"),
+                                )
+                            },
+                        )
+                        .await?;
+                        Ok(json!({ "indexedFiles": indexed_files }))
+                    },
+                ))
+            }
+            "backup_data" => {
+                let destination = parse_request::<BackupRequest>(params)?.destination;
+                Ok(self.jobs.start(kind, None, move |progress| async move {
+                    progress.report(None, "Writing backup archive");
+                    let result =
+                        tokio::task::spawn_blocking(move || state.backup_data(destination))
+                            .await
+                            .map_err(|err| format!("Backup failed: {err}"))??;
+                    serde_json::to_value(result).map_err(|err| err.to_string())
+                }))
+            }
+            "audit_dependencies" => {
+                let workspace_id = parse_request::<WorkspaceRequest>(params)?.workspace_id;
+                Ok(self.jobs.start(
+                    kind,
+                    Some(workspace_id.clone()),
+                    move |progress| async move {
+                        progress.report(None, "Running dependency audits");
+                        let audit = state.audit_dependencies(workspace_id).await?;
+                        serde_json::to_value(audit).map_err(|err| err.to_string())
+                    },
+                ))
+            }
+            "run_build" => {
+                let workspace_id = parse_request::<WorkspaceRequest>(params)?.workspace_id;
+                Ok(self.jobs.start(
+                    kind,
+                    Some(workspace_id.clone()),
+                    move |progress| async move {
+                        progress.report(None, "Running build checks");
+                        let report = state.run_build(workspace_id).await?;
+                        serde_json::to_value(report).map_err(|err| err.to_string())
+                    },
+                ))
+            }
+            "auto_fix" => {
+                let request = parse_request::<AutoFixRequest>(params)?;
+                Ok(self.jobs.start(
+                    kind,
+                    Some(request.workspace_id.clone()),
+                    move |progress| async move {
+                        let report = state.auto_fix(request, progress).await?;
+                        serde_json::to_value(report).map_err(|err| err.to_string())
+                    },
+                ))
+            }
+            "generate_commit_message" => {
+                let CommitMessageRequest {
+                    workspace_id,
+                    staged,
+                } = parse_request(params)?;
+                Ok(self.jobs.start(
+                    kind,
+                    Some(workspace_id.clone()),
+                    move |progress| async move {
+                        progress.report(None, "Generating commit message");
+                        let message = state.generate_commit_message(workspace_id, staged).await?;
+                        Ok(Value::String(message))
+                    },
+                ))
+            }
+            _ => Err(format!("Unknown job kind: {kind}")),
+        }
+    }
+
+    async fn file_read(
+        &self,
+        scope: file_policy::FileScope,
+        kind: file_policy::FileKind,
+        workspace_id: Option<String>,
+    ) -> Result<file_io::TextFileResponse, String> {
+        files_core::file_read_core(&self.workspaces, scope, kind, workspace_id).await
+    }
+
+    async fn file_write(
+        &self,
+        scope: file_policy::FileScope,
+        kind: file_policy::FileKind,
+        workspace_id: Option<String>,
+        content: String,
+    ) -> Result<(), String> {
+        files_core::file_write_core(
+            &self.workspaces,
+            &self.app_settings,
+            &self.file_history,
+            scope,
+            kind,
+            workspace_id,
+            content,
+        )
+        .await
+    }
+
+    async fn file_delete(
+        &self,
+        scope: file_policy::FileScope,
+        kind: file_policy::FileKind,
+        workspace_id: Option<String>,
+    ) -> Result<bool, String> {
+        files_core::file_delete_core(
+            &self.workspaces,
+            &self.app_settings,
+            scope,
+            kind,
+            workspace_id,
+        )
+        .await
+    }
+
+    async fn effective_instructions(
+        &self,
+        workspace_id: &str,
+    ) -> Result<files_core::EffectiveInstructions, String> {
+        files_core::effective_instructions_core(&self.workspaces, workspace_id).await
+    }
+
+    async fn extract_document(
+        &self,
+        workspace_id: &str,
+        path: String,
+    ) -> Result<ExtractedDocument, String> {
+        documents_core::extract_document_core(&self.workspaces, workspace_id, path).await
+    }
+
+    async fn render_markdown(
+        &self,
+        workspace_id: &str,
+        path: String,
+    ) -> Result<RenderedMarkdown, String> {
+        preview_core::render_markdown_core(&self.workspaces, workspace_id, path).await
+    }
+
+    async fn render_notebook(&self, workspace_id: &str, path: String) -> Result<Notebook, String> {
+        preview_core::render_notebook_core(&self.workspaces, workspace_id, path).await
+    }
+
+    async fn file_outline(&self, workspace_id: &str, path: String) -> Result<FileOutline, String> {
+        outline_core::file_outline_core(&self.workspaces, workspace_id, path).await
+    }
+
+    async fn thumbnail(&self, workspace_id: &str, path: String) -> Result<Thumbnail, String> {
+        thumbnails_core::thumbnail_core(
+            &self.workspaces,
+            self.data_dir.join(thumbnails_core::THUMBNAILS_DIR),
+            workspace_id,
+            path,
+        )
+        .await
+    }
+
+    /// Emits `thumbnail-ready` with a scaled preview of each image an item produced.
+    async fn publish_thumbnails(&self, workspace_id: String, artifacts: Vec<ImageArtifact>) {
+        for artifact in artifacts {
+            let thumbnail = match self.thumbnail(&workspace_id, artifact.path.clone()).await {
+                Ok(thumbnail) => thumbnail,
+                Err(err) => {
+                    tracing::warn!(workspace_id, path = %artifact.path, "thumbnail failed: {err}");
+                    continue;
+                }
+            };
+            let ready = ThumbnailReady {
+                workspace_id: workspace_id.clone(),
+                thread_id: artifact.thread_id,
+                item_id: artifact.item_id,
+                thumbnail,
+            };
+            self.event_sink.emit_app_server_event(AppServerEvent {
+                workspace_id: workspace_id.clone(),
+                message: json!({ "method": "thumbnail-ready", "params": ready }),
+            });
+        }
+    }
+
+    /// Records what a client has open in a workspace, for `file-conflict` events.
+    async fn set_open_files(
+        &self,
+        client_id: &str,
+        workspace_id: &str,
+        files: Vec<OpenFile>,
+    ) -> Result<Vec<OpenFile>, String> {
+        let root = self.workspace_root(workspace_id).await?;
+        let files = files
+            .into_iter()
+            .map(|file| {
+                let path = open_files::workspace_relative(&root, &file.path)
+                    .ok_or_else(|| format!("path is outside the workspace: {}", file.path))?;
+                Ok(OpenFile {
+                    path,
+                    hash: file.hash,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let files = self.open_files.set(client_id, workspace_id, files);
+        self.emit_editor_presence(client_id, workspace_id);
+        Ok(files)
+    }
+
+    /// Moves the client's focus within a workspace; `path: None` clears it.
+    async fn focus_file(
+        &self,
+        client_id: &str,
+        request: FocusFileRequest,
+    ) -> Result<Value, String> {
+        let root = self.workspace_root(&request.workspace_id).await?;
+        let focus = match &request.path {
+            Some(path) => Some(FileFocus {
+                workspace_id: request.workspace_id.clone(),
+                path: open_files::workspace_relative(&root, path)
+                    .ok_or_else(|| format!("path is outside the workspace: {path}"))?,
+                editing: request.editing,
+            }),
+            None => None,
+        };
+        let change = match self
+            .open_files
+            .focus(client_id, focus.clone(), request.force)
+        {
+            Ok(change) => change,
+            Err(editor) => {
+                let device = self
+                    .clients
+                    .get(&editor)
+                    .and_then(|client| client.device_name)
+                    .unwrap_or(editor);
+                let path = focus.map(|focus| focus.path).unwrap_or_default();
+                return Err(format!(
+                    "{path} is being edited on {device}; pass `force` to take it over"
+                ));
+            }
+        };
+        if let Some(editor) = &change.taken_from {
+            self.emit_editor_presence(editor, &request.workspace_id);
+        }
+        self.emit_editor_presence(client_id, &request.workspace_id);
+        if let Some(previous) = change
+            .previous
+            .filter(|previous| previous.workspace_id != request.workspace_id)
+        {
+            self.emit_editor_presence(client_id, &previous.workspace_id);
+        }
+        let viewers = match &focus {
+            Some(focus) => self
+                .list_editor_presence(Some(&request.workspace_id))
+                .into_iter()
+                .filter(|presence| {
+                    presence.client_id != client_id
+                        && presence.focused_path.as_deref() == Some(focus.path.as_str())
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(json!({
+            "presence": self.editor_presence(client_id, &request.workspace_id),
+            "viewers": viewers,
+        }))
+    }
+
+    fn editor_presence(&self, client_id: &str, workspace_id: &str) -> EditorPresence {
+        let mut presence = self.open_files.presence(client_id, workspace_id);
+        presence.device_name = self
+            .clients
+            .get(client_id)
+            .and_then(|client| client.device_name);
+        presence
+    }
+
+    fn list_editor_presence(&self, workspace_id: Option<&str>) -> Vec<EditorPresence> {
+        let mut listed = self.open_files.list_presence(workspace_id);
+        for presence in &mut listed {
+            presence.device_name = self
+                .clients
+                .get(&presence.client_id)
+                .and_then(|client| client.device_name);
+        }
+        listed
+    }
+
+    fn emit_editor_presence(&self, client_id: &str, workspace_id: &str) {
+        let presence = self.editor_presence(client_id, workspace_id);
+        let _ = self
+            .event_sink
+            .tx
+            .send(DaemonEvent::EditorPresence(presence));
+    }
+
+    /// Keeps what an agent's `fileChange` left on disk in file history so it can be
+    /// reverted. Deleted files have nothing to keep.
+    async fn record_agent_edits(&self, workspace_id: &str, write: &AgentWrite) {
+        let Ok(root) = self.workspace_root(workspace_id).await else {
+            return;
+        };
+        for path in &write.paths {
+            let target = root.join(path);
+            let Ok(content) = tokio::fs::read(&target).await else {
+                continue;
+            };
+            let recorded = blocking(|| {
+                self.file_history
+                    .record_untracked_write(&target, &content, "agent")
+            });
+            if let Err(err) = recorded {
+                tracing::warn!(workspace_id, path, "failed to record agent edit: {err}");
+            }
+        }
+    }
+
+    /// Tells each client holding a file the agent just wrote what is on disk now.
+    async fn publish_file_conflicts(&self, workspace_id: String, write: AgentWrite) {
+        let Ok(root) = self.workspace_root(&workspace_id).await else {
+            return;
+        };
+        let canonical_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        for path in write.paths {
+            let Some(path) = open_files::workspace_relative(&root, &path)
+                .or_else(|| open_files::workspace_relative(&canonical_root, &path))
+            else {
+                continue;
+            };
+            if !self.open_files.is_open(&workspace_id, &path) {
+                continue;
+            }
+            let hash = tokio::fs::read(root.join(&path))
+                .await
+                .ok()
+                .map(|content| file_history_core::content_hash(&content));
+            for (client_id, previous_hash) in
+                self.open_files
+                    .conflicts(&workspace_id, &path, hash.as_deref())
+            {
+                let _ = self
+                    .event_sink
+                    .tx
+                    .send(DaemonEvent::FileConflict(FileConflict {
+                        client_id,
+                        workspace_id: workspace_id.clone(),
+                        thread_id: write.thread_id.clone(),
+                        item_id: write.item_id.clone(),
+                        path: path.clone(),
+                        hash: hash.clone(),
+                        previous_hash,
+                        deleted: hash.is_none(),
+                    }));
+            }
+        }
+    }
+
+    fn file_history(&self, path: &str) -> FileHistoryResponse {
+        self.file_history.history(path)
+    }
+
+    async fn revert_file_to(&self, version: &str) -> Result<FileVersion, String> {
+        let protected = self.app_settings.lock().await.protected_path_globs.clone();
+        self.file_history.revert_to(version, &protected)
+    }
+
+    async fn start_thread(
+        &self,
+        workspace_id: String,
+        cwd: Option<String>,
+    ) -> Result<Value, String> {
+        let response =
+            codex_core::start_thread_core(&self.sessions, workspace_id.clone(), cwd.clone())
+                .await?;
+        self.list_cache.invalidate_workspace(&workspace_id);
+        self.record_thread_branch(&workspace_id, &response, cwd, None).await;
+        Ok(response)
+    }
+
+    async fn record_thread_branch(
+        &self,
+        workspace_id: &str,
+        response: &Value,
+        cwd: Option<String>,
+        forked_from: Option<String>,
+    ) {
+        if let Err(error) = thread_branches_core::record_thread_branch_core(
+            &self.workspaces,
+            &self.thread_branches,
+            &self.thread_branches_path,
+            workspace_id,
+            response,
+            cwd,
+            forked_from,
+        )
+        .await
+        {
+            tracing::warn!(workspace_id, "failed to record thread branch: {error}");
+        }
+    }
+
+    async fn resume_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
+        codex_core::resume_thread_core(&self.sessions, workspace_id, thread_id).await
+    }
+
+    async fn fork_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
+        let cwd = thread_branches_core::thread_cwd_core(&self.thread_branches, &thread_id).await;
+        let response =
+            codex_core::fork_thread_core(&self.sessions, workspace_id.clone(), thread_id.clone())
+                .await?;
+        self.list_cache.invalidate_workspace(&workspace_id);
+        self.record_thread_branch(&workspace_id, &response, cwd, Some(thread_id)).await;
+        Ok(response)
+    }
+
+    async fn thread_graph(&self, workspace_id: &str) -> thread_branches_core::ThreadGraph {
+        thread_branches_core::thread_graph_core(&self.thread_branches, workspace_id).await
+    }
+
+    async fn list_slash_commands(&self) -> Vec<slash_commands_core::SlashCommandInfo> {
+        let settings = self.app_settings.lock().await;
+        slash_commands_core::list_slash_commands(&settings.slash_commands)
+    }
+
+    fn save_draft(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        text: String,
+        images: Vec<String>,
+    ) -> Result<Option<ThreadDraft>, String> {
+        self.drafts.save(workspace_id, thread_id, text, images)
+    }
+
+    fn get_draft(&self, workspace_id: &str, thread_id: &str) -> Option<ThreadDraft> {
+        self.drafts.get(workspace_id, thread_id)
+    }
+
+    async fn time_tracking_ping(&self, request: TimeTrackingPingRequest) -> Result<(), String> {
+        if !self.workspaces.lock().await.contains_key(&request.workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        self.event_sink
+            .time_tracker
+            .record_focus(&request.workspace_id, request.thread_id.as_deref());
+        Ok(())
+    }
+
+    fn time_tracking_summary(&self, request: TimeTrackingSummaryRequest) -> TimeSummary {
+        self.event_sink.time_tracker.summary(
+            request.period.unwrap_or_default(),
+            request.count,
+            request.workspace_id.as_deref(),
+        )
+    }
+
+    async fn list_pinned_context(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        model: Option<&str>,
+    ) -> Result<PinnedContextView, String> {
+        pinned_context_core::pinned_context_view_core(
+            &self.workspaces,
+            &self.app_settings,
+            &self.pinned_context,
+            workspace_id,
+            thread_id,
+            model,
+        )
+        .await
+    }
+
+    fn pin_context(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        input: PinInput,
+    ) -> Result<ContextPin, String> {
+        self.pinned_context.pin(workspace_id, thread_id, input)
+    }
+
+    fn unpin_context(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        pin_id: Option<&str>,
+    ) -> Result<usize, String> {
+        self.pinned_context.unpin(workspace_id, thread_id, pin_id)
+    }
+
+    fn list_collaboration_presets(&self) -> Vec<CollaborationPreset> {
+        self.collaboration_presets.list()
+    }
+
+    fn save_collaboration_preset(
+        &self,
+        name: &str,
+        description: Option<String>,
+        collaboration_mode: Value,
+    ) -> Result<CollaborationPreset, String> {
+        self.collaboration_presets.save(name, description, collaboration_mode)
+    }
+
+    fn delete_collaboration_preset(&self, name: &str) -> Result<bool, String> {
+        self.collaboration_presets.delete(name)
+    }
+
+    async fn list_account_profiles(&self) -> Vec<AccountProfileInfo> {
+        let workspaces = self.workspaces.lock().await;
+        self.account_profiles.list(&workspaces)
+    }
+
+    fn save_account_profile(
+        &self,
+        name: &str,
+        codex_home: &str,
+    ) -> Result<AccountProfile, String> {
+        self.account_profiles.save(name, codex_home)
+    }
+
+    async fn delete_account_profile(&self, name: &str) -> Result<bool, String> {
+        let workspaces = self.workspaces.lock().await;
+        self.account_profiles.delete(name, &workspaces)
+    }
+
+    async fn list_threads(
+        &self,
+        workspace_id: String,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        sort_key: Option<String>,
+        branch: Option<String>,
+        options: ListOptions,
+    ) -> Result<Value, String> {
+        let key = ListCache::key(
+            "list_threads",
+            &workspace_id,
+            &json!({
+                "cursor": cursor,
+                "limit": limit,
+                "sortKey": sort_key,
+                "aggregate": options.aggregate,
+            }),
+        );
+        let fetch_page = |cursor| {
+            codex_core::list_threads_core(
+                &self.sessions,
+                workspace_id.clone(),
+                cursor,
+                limit,
+                sort_key.clone(),
+            )
+        };
+        let response = self
+            .list_cache
+            .get_or_fetch(key, &workspace_id, options.refresh, || {
+                list_cache_core::fetch_list(cursor, options.aggregate, fetch_page)
+            })
+            .await?;
+        let response = thread_branches_core::enrich_thread_list_core(
+            &self.thread_branches,
+            response,
+            branch.as_deref(),
+        )
+        .await;
+        Ok(self.event_sink.thread_usage.enrich_thread_list(response))
+    }
+
+    async fn list_mcp_server_status(
+        &self,
+        workspace_id: String,
+        cursor: Option<String>,
+        limit: Option<u32>,
+        options: ListOptions,
+    ) -> Result<Value, String> {
+        let key = ListCache::key(
+            "list_mcp_server_status",
+            &workspace_id,
+            &json!({ "cursor": cursor, "limit": limit, "aggregate": options.aggregate }),
+        );
+        let fetch_page = |cursor| {
+            codex_core::list_mcp_server_status_core(
+                &self.sessions,
+                workspace_id.clone(),
+                cursor,
+                limit,
+            )
+        };
+        self.list_cache
+            .get_or_fetch(key, &workspace_id, options.refresh, || {
+                list_cache_core::fetch_list(cursor, options.aggregate, fetch_page)
+            })
+            .await
+    }
+
+    fn mcp_server_health(&self, workspace_id: Option<&str>) -> Vec<McpServerHealth> {
+        self.mcp_health.snapshot(workspace_id)
+    }
+
+    async fn mcp_server_oauth_login(
+        &self,
+        workspace_id: String,
+        name: String,
+    ) -> Result<Value, String> {
+        self.list_cache.invalidate_workspace(&workspace_id);
+        codex_core::mcp_server_oauth_login_core(&self.sessions, workspace_id, name).await
     }
 
     async fn archive_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
+        self.list_cache.invalidate_workspace(&workspace_id);
         codex_core::archive_thread_core(&self.sessions, workspace_id, thread_id).await
     }
 
@@ -554,30 +2381,39 @@ impl DaemonState {
         thread_id: String,
         name: String,
     ) -> Result<Value, String> {
+        self.list_cache.invalidate_workspace(&workspace_id);
         codex_core::set_thread_name_core(&self.sessions, workspace_id, thread_id, name).await
     }
 
     async fn send_user_message(
         &self,
-        workspace_id: String,
-        thread_id: String,
-        text: String,
-        model: Option<String>,
-        effort: Option<String>,
-        access_mode: Option<String>,
-        images: Option<Vec<String>>,
-        collaboration_mode: Option<Value>,
+        mut request: SendUserMessageRequest,
     ) -> Result<Value, String> {
+        if request.cwd.is_none() {
+            request.cwd =
+                thread_branches_core::thread_cwd_core(&self.thread_branches, &request.thread_id)
+                    .await;
+        }
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(&request.workspace_id)
+            .cloned();
+        if let Some(entry) = entry {
+            self.event_sink.thread_usage.record_branch(
+                &request.thread_id,
+                thread_branches_core::workspace_branch(&entry),
+            );
+        }
+        self.list_cache.invalidate_workspace(&request.workspace_id);
         codex_core::send_user_message_core(
             &self.sessions,
-            workspace_id,
-            thread_id,
-            text,
-            model,
-            effort,
-            access_mode,
-            images,
-            collaboration_mode,
+            &self.app_settings,
+            &self.turn_snapshots,
+            &self.collaboration_presets,
+            &self.pinned_context,
+            request,
         )
         .await
     }
@@ -591,6 +2427,17 @@ impl DaemonState {
         codex_core::turn_interrupt_core(&self.sessions, workspace_id, thread_id, turn_id).await
     }
 
+    async fn restore_snapshot(
+        &self,
+        turn_id: String,
+    ) -> Result<turn_snapshots_core::SnapshotRestore, String> {
+        let restore = self.turn_snapshots.restore(&turn_id).await?;
+        if let Some(entry) = self.workspaces.lock().await.get(&restore.workspace_id) {
+            self.file_lists.invalidate(Path::new(&entry.path));
+        }
+        Ok(restore)
+    }
+
     async fn start_review(
         &self,
         workspace_id: String,
@@ -603,7 +2450,7 @@ impl DaemonState {
     }
 
     async fn model_list(&self, workspace_id: String) -> Result<Value, String> {
-        codex_core::model_list_core(&self.sessions, workspace_id).await
+        codex_core::model_list_core(&self.sessions, &self.workspaces, workspace_id).await
     }
 
     async fn collaboration_mode_list(&self, workspace_id: String) -> Result<Value, String> {
@@ -631,13 +2478,52 @@ impl DaemonState {
         codex_core::skills_list_core(&self.sessions, workspace_id).await
     }
 
+    async fn skills_config(
+        &self,
+        workspace_id: String,
+        scope: Option<String>,
+    ) -> Result<Value, String> {
+        codex_core::skills_config_core(&self.workspaces, workspace_id, scope).await
+    }
+
+    async fn skills_set_enabled(
+        &self,
+        workspace_id: String,
+        path: String,
+        enabled: bool,
+        scope: Option<String>,
+    ) -> Result<Value, String> {
+        codex_core::skills_set_enabled_core(
+            &self.workspaces,
+            &self.sessions,
+            workspace_id,
+            path,
+            enabled,
+            scope,
+        )
+        .await
+    }
+
     async fn apps_list(
         &self,
         workspace_id: String,
         cursor: Option<String>,
         limit: Option<u32>,
+        options: ListOptions,
     ) -> Result<Value, String> {
-        codex_core::apps_list_core(&self.sessions, workspace_id, cursor, limit).await
+        let key = ListCache::key(
+            "apps_list",
+            &workspace_id,
+            &json!({ "cursor": cursor, "limit": limit, "aggregate": options.aggregate }),
+        );
+        let fetch_page = |cursor| {
+            codex_core::apps_list_core(&self.sessions, workspace_id.clone(), cursor, limit)
+        };
+        self.list_cache
+            .get_or_fetch(key, &workspace_id, options.refresh, || {
+                list_cache_core::fetch_list(cursor, options.aggregate, fetch_page)
+            })
+            .await
     }
 
     async fn respond_to_server_request(
@@ -646,11 +2532,124 @@ impl DaemonState {
         request_id: Value,
         result: Value,
     ) -> Result<Value, String> {
-        codex_core::respond_to_server_request_core(&self.sessions, workspace_id, request_id, result)
-            .await?;
+        codex_core::respond_to_server_request_core(
+            &self.sessions,
+            workspace_id.clone(),
+            request_id.clone(),
+            result,
+        )
+        .await?;
+        self.event_sink
+            .pending_approvals
+            .resolve(&workspace_id, &request_id);
         Ok(json!({ "ok": true }))
     }
 
+    async fn approval_request_facts(
+        &self,
+        workspace_id: &str,
+        message: &Value,
+    ) -> Result<rules::ApprovalRequestFacts, String> {
+        let root = {
+            let workspaces = self.workspaces.lock().await;
+            workspaces
+                .get(workspace_id)
+                .map(|entry| PathBuf::from(&entry.path))
+                .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
+        };
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        Ok(rules::approval_request_facts(method, &params, &root))
+    }
+
+    /// Dry run of the approval policy for a request shaped like an app-server message.
+    async fn evaluate_approval_policy(
+        &self,
+        workspace_id: String,
+        message: Value,
+    ) -> Result<Value, String> {
+        let facts = self.approval_request_facts(&workspace_id, &message).await?;
+        let (rules, protected) = {
+            let settings = self.app_settings.lock().await;
+            (
+                settings.approval_policy_rules.clone(),
+                settings.protected_path_globs.clone(),
+            )
+        };
+        let matched = rules::evaluate_approval_policy(&rules, &facts, &protected);
+        Ok(json!({ "facts": facts, "match": matched }))
+    }
+
+    /// Answers an approval request from the configured rules, or hands it to clients.
+    async fn apply_approval_policy(&self, event: AppServerEvent) {
+        let (rules, protected) = {
+            let settings = self.app_settings.lock().await;
+            (
+                settings.approval_policy_rules.clone(),
+                settings.protected_path_globs.clone(),
+            )
+        };
+        let facts = if rules.is_empty() {
+            None
+        } else {
+            self.approval_request_facts(&event.workspace_id, &event.message)
+                .await
+                .ok()
+        };
+        let matched = facts
+            .as_ref()
+            .and_then(|facts| rules::evaluate_approval_policy(&rules, facts, &protected));
+        let (Some(facts), Some(matched)) = (facts, matched) else {
+            self.event_sink.publish_app_server_event(event);
+            return;
+        };
+
+        let request_id = event.message.get("id").cloned().unwrap_or(Value::Null);
+        let decision = match matched.decision {
+            ApprovalPolicyDecision::Allow => "accept",
+            ApprovalPolicyDecision::Deny => "decline",
+        };
+        let result = codex_core::respond_to_server_request_core(
+            &self.sessions,
+            event.workspace_id.clone(),
+            request_id.clone(),
+            json!({ "decision": decision }),
+        )
+        .await;
+        self.audit_log.record(
+            "approval_policy",
+            json!({
+                "workspaceId": event.workspace_id,
+                "requestId": request_id,
+                "method": event.message.get("method"),
+                "ruleId": matched.rule_id,
+                "decision": decision,
+                "facts": facts,
+                "error": result.as_ref().err(),
+            }),
+        );
+        if result.is_err() {
+            // Fall back to asking the user rather than leaving the turn blocked.
+            self.event_sink.publish_app_server_event(event);
+        }
+    }
+
+    fn list_pending_approvals(&self, workspace_id: Option<String>) -> Vec<PendingApproval> {
+        self.event_sink
+            .pending_approvals
+            .list(workspace_id.as_deref())
+    }
+
+    async fn prune_pending_approvals(&self) {
+        let workspaces = self.workspaces.lock().await;
+        self.event_sink
+            .pending_approvals
+            .retain_workspaces(|id| workspaces.contains_key(id));
+    }
+
     async fn remember_approval_rule(
         &self,
         workspace_id: String,
@@ -659,99 +2658,35 @@ impl DaemonState {
         codex_core::remember_approval_rule_core(&self.workspaces, workspace_id, command).await
     }
 
+    async fn validate_rules(&self, workspace_id: &str) -> Result<RulesValidation, String> {
+        rules_lint_core::validate_rules_core(&self.workspaces, workspace_id).await
+    }
+
+    async fn format_rules(
+        &self,
+        workspace_id: &str,
+        write: bool,
+    ) -> Result<RulesFormatResult, String> {
+        rules_lint_core::format_rules_core(&self.workspaces, workspace_id, write).await
+    }
+
     async fn get_config_model(&self, workspace_id: String) -> Result<Value, String> {
         codex_core::get_config_model_core(&self.workspaces, workspace_id).await
     }
 }
 
-fn should_skip_dir(name: &str) -> bool {
-    matches!(
-        name,
-        ".git" | "node_modules" | "dist" | "target" | "release-artifacts"
-    )
-}
-
-fn normalize_git_path(path: &str) -> String {
-    path.replace('\\', "/")
-}
-
-fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
-    let mut results = Vec::new();
-    let walker = WalkBuilder::new(root)
-        .hidden(false)
-        .follow_links(false)
-        .require_git(false)
-        .filter_entry(|entry| {
-            if entry.depth() == 0 {
-                return true;
-            }
-            if entry.file_type().is_some_and(|ft| ft.is_dir()) {
-                let name = entry.file_name().to_string_lossy();
-                return !should_skip_dir(&name);
-            }
-            true
-        })
-        .build();
-
-    for entry in walker {
-        let entry = match entry {
-            Ok(entry) => entry,
-            Err(_) => continue,
-        };
-        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
-            continue;
-        }
-        if let Ok(rel_path) = entry.path().strip_prefix(root) {
-            let normalized = normalize_git_path(&rel_path.to_string_lossy());
-            if !normalized.is_empty() {
-                results.push(normalized);
-            }
-        }
-        if results.len() >= max_files {
-            break;
-        }
+/// Runs blocking filesystem work from async code that can't hand it to `spawn_blocking`,
+/// such as the sync callbacks core functions take. On the multi-thread runtime the worker
+/// passes its other tasks to the rest of the pool first, so a large walk doesn't hold up
+/// other clients.
+fn blocking<T>(work: impl FnOnce() -> T) -> T {
+    let multi_thread = tokio::runtime::Handle::try_current()
+        .is_ok_and(|handle| handle.runtime_flavor() == RuntimeFlavor::MultiThread);
+    if multi_thread {
+        tokio::task::block_in_place(work)
+    } else {
+        work()
     }
-
-    results.sort();
-    results
-}
-
-const MAX_WORKSPACE_FILE_BYTES: u64 = 400_000;
-
-fn read_workspace_file_inner(
-    root: &PathBuf,
-    relative_path: &str,
-) -> Result<WorkspaceFileResponse, String> {
-    let canonical_root = root
-        .canonicalize()
-        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
-    let candidate = canonical_root.join(relative_path);
-    let canonical_path = candidate
-        .canonicalize()
-        .map_err(|err| format!("Failed to open file: {err}"))?;
-    if !canonical_path.starts_with(&canonical_root) {
-        return Err("Invalid file path".to_string());
-    }
-    let metadata = std::fs::metadata(&canonical_path)
-        .map_err(|err| format!("Failed to read file metadata: {err}"))?;
-    if !metadata.is_file() {
-        return Err("Path is not a file".to_string());
-    }
-
-    let file = File::open(&canonical_path).map_err(|err| format!("Failed to open file: {err}"))?;
-    let mut buffer = Vec::new();
-    file.take(MAX_WORKSPACE_FILE_BYTES + 1)
-        .read_to_end(&mut buffer)
-        .map_err(|err| format!("Failed to read file: {err}"))?;
-
-    let truncated = buffer.len() > MAX_WORKSPACE_FILE_BYTES as usize;
-    if truncated {
-        buffer.truncate(MAX_WORKSPACE_FILE_BYTES as usize);
-    }
-
-    let content =
-        String::from_utf8(buffer).map_err(|_| "File is not valid UTF-8".to_string())?;
-    Ok(WorkspaceFileResponse { content, truncated })
 }
 
 fn default_data_dir() -> PathBuf {
@@ -771,8 +2706,8 @@ fn default_data_dir() -> PathBuf {
 fn usage() -> String {
     format!(
         "\
-USAGE:\n  codex-monitor-daemon [--listen <addr>] [--data-dir <path>] [--token <token> | --insecure-no-auth]\n\n\
-OPTIONS:\n  --listen <addr>        Bind address (default: {DEFAULT_LISTEN_ADDR})\n  --data-dir <path>      Data dir holding workspaces.json/settings.json\n  --token <token>        Shared token required by clients\n  --insecure-no-auth      Disable auth (dev only)\n  -h, --help             Show this help\n"
+USAGE:\n  codex-monitor-daemon [--config <path>] [--listen <addr>] [--data-dir <path>] [--token <token>] [--token-file <path>] [--insecure-no-auth] [--allow-command <prefix>]... [--trace-rpc]\n                       [--export-otlp <url>]... [--export-http <url>]... [--export-header <name=value>]...\n                       [--max-processes <kind=n>]... [--process-queue-timeout <secs>] [--stdio]\n                       [--rate-limit <n>] [--max-in-flight <n>] [--worker-threads <n>]\n                       [--relay <host:port> --relay-id <id>] [--http-listen <addr>]\n                       [--listen-unix <path>] [--tls-cert <pem> --tls-key <pem>] [--log-level <level>] [--log-format <text|json>]\n\n\
+OPTIONS:\n  --config <path>        TOML file of default options, keyed by flag name (default:\n                         $CODEX_MONITOR_DAEMON_CONFIG, else {CONFIG_FILE_NAME} in the\n                         data dir if present); flags on the command line win\n  --listen <addr>        Bind address (default: {DEFAULT_LISTEN_ADDR})\n  --data-dir <path>      Data dir holding workspaces.json/settings.json\n  --token <token>        Shared token required by clients\n  --token-file <path>    JSON list of extra tokens with scopes (read, files, git, chat, admin)\n  --insecure-no-auth      Disable auth (dev only)\n  --allow-command <prefix>\n                         Only spawn commands starting with this prefix (repeatable;\n                         denials are written to <data-dir>/audit.log)\n  --trace-rpc            Log full RPC requests/responses (secrets redacted) to\n                         <data-dir>/rpc-trace.log; also enabled by `traceRpcEnabled`\n  --export-otlp <url>    Export app-server events, RPC spans and errors to an OTLP/HTTP\n                         (JSON) collector, e.g. http://localhost:4318 (repeatable)\n  --export-http <url>    POST the same records as JSON batches to <url> (repeatable)\n  --export-header <name=value>\n                         Extra header sent with every export request (repeatable)\n  --max-processes <kind=n>\n                         Cap concurrent child processes of a kind: session (32), git (16)\n                         or tool (8); extra spawns wait in a queue (repeatable)\n  --process-queue-timeout <secs>\n                         Fail a queued spawn after this many seconds (default: 30)\n  --rpc-timeout <method=secs>\n                         Time budget for one RPC method (default: 120s, longer for builds,\n                         searches and worktree changes); `*` sets the default and 0 removes\n                         the limit. Timed-out calls fail with error code `timeout` (repeatable)\n  --slow-rpc-ms <ms>     Log RPCs slower than this to stderr with redacted params\n                         (default: 2000; 0 disables)\n  --rate-limit <n>       Answer a connection's requests past <n> per second (one second of\n                         burst) with error code `rate_limited`; off by default\n  --max-in-flight <n>    Cap the requests one connection has waiting, i.e. the size of a\n                         batch; the rest get `rate_limited`. Off by default\n  --worker-threads <n>   Runtime threads serving clients (default: one per CPU core)\n  --listen-unix <path>   Serve clients on an owner-only unix socket instead of TCP; pass\n                         --listen too to keep the TCP port. --token is optional unless TCP\n                         is also served\n  --tls-cert <pem>        Serve TCP clients and the REST gateway over TLS with this PEM\n                         certificate chain\n  --tls-key <pem>        PEM private key for --tls-cert\n  --stdio                Serve one client on stdin/stdout instead of opening a port, e.g.\n                         `ssh host codex-monitor-daemon --stdio`; --token is optional\n  --relay <host:port>    Also dial out to this relay and serve the clients it forwards over\n                         that one connection, for hosts behind NAT\n  --relay-id <id>        Name clients use to reach this daemon through the relay\n  --http-listen <addr>   Also serve a small REST gateway (GET /workspaces, GET /threads,\n                         POST /workspaces/<id>/message) on this address; requests carry\n                         `Authorization: Bearer <token>`\n  --log-level <level>    off, error, warn, info, debug or trace (default: info); debug adds\n                         a line per RPC with its duration and outcome\n  --log-format <format>  text (default) or json, one object per line on stderr\n  --self-test            Run a scratch daemon against a mock app-server, exercise the\n                         RPC surface end to end and exit non-zero on the first failure\n  -h, --help             Show this help\n"
     )
 }
 
@@ -780,14 +2715,38 @@ fn parse_args() -> Result<DaemonConfig, String> {
     let mut listen = DEFAULT_LISTEN_ADDR
         .parse::<SocketAddr>()
         .map_err(|err| err.to_string())?;
+    let mut listen_explicit = false;
+    let mut listen_unix: Option<PathBuf> = None;
     let mut token = env::var("CODEX_MONITOR_DAEMON_TOKEN")
         .ok()
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty());
+    let mut token_file: Option<PathBuf> = None;
     let mut insecure_no_auth = false;
     let mut data_dir: Option<PathBuf> = None;
+    let mut allowed_commands: Vec<String> = Vec::new();
+    let mut trace_rpc = false;
+    let mut exporters: Vec<ExporterConfig> = Vec::new();
+    let mut export_headers: Vec<(String, String)> = Vec::new();
+    let mut process_limits = ProcessLimits::default();
+    let mut rpc_timeouts = RpcTimeouts::default();
+    let mut rate_limits = RateLimits::default();
+    let mut stdio = false;
+    let mut relay_endpoint: Option<String> = None;
+    let mut relay_id: Option<String> = None;
+    let mut http_listen: Option<SocketAddr> = None;
+    let mut tls_cert: Option<PathBuf> = None;
+    let mut tls_key: Option<PathBuf> = None;
+    let mut log_level = LevelFilter::INFO;
+    let mut log_format = LogFormat::default();
+    let mut worker_threads: Option<usize> = None;
 
-    let mut args = env::args().skip(1);
+    let args = config_file::resolve_args(
+        env::args().skip(1).collect(),
+        env::var_os(config_file::CONFIG_ENV).map(PathBuf::from),
+        default_data_dir,
+    )?;
+    let mut args = args.into_iter();
     while let Some(arg) = args.next() {
         match arg.as_str() {
             "-h" | "--help" => {
@@ -797,6 +2756,18 @@ fn parse_args() -> Result<DaemonConfig, String> {
             "--listen" => {
                 let value = args.next().ok_or("--listen requires a value")?;
                 listen = value.parse::<SocketAddr>().map_err(|err| err.to_string())?;
+                listen_explicit = true;
+            }
+            "--listen-unix" => {
+                let value = args.next().ok_or("--listen-unix requires a value")?;
+                if !cfg!(unix) {
+                    return Err("--listen-unix is only supported on Unix".to_string());
+                }
+                listen_unix = Some(PathBuf::from(value.trim()))
+                    .filter(|path| !path.as_os_str().is_empty());
+                if listen_unix.is_none() {
+                    return Err("--listen-unix requires a non-empty value".to_string());
+                }
             }
             "--token" => {
                 let value = args.next().ok_or("--token requires a value")?;
@@ -806,6 +2777,14 @@ fn parse_args() -> Result<DaemonConfig, String> {
                 }
                 token = Some(trimmed.to_string());
             }
+            "--token-file" => {
+                let value = args.next().ok_or("--token-file requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--token-file requires a non-empty value".to_string());
+                }
+                token_file = Some(PathBuf::from(trimmed));
+            }
             "--data-dir" => {
                 let value = args.next().ok_or("--data-dir requires a value")?;
                 let trimmed = value.trim();
@@ -817,59 +2796,229 @@ fn parse_args() -> Result<DaemonConfig, String> {
             "--insecure-no-auth" => {
                 insecure_no_auth = true;
                 token = None;
+                token_file = None;
+            }
+            "--allow-command" => {
+                let value = args.next().ok_or("--allow-command requires a value")?;
+                allowed_commands.push(value);
+            }
+            "--trace-rpc" => {
+                trace_rpc = true;
+            }
+            "--export-otlp" | "--export-http" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{arg} requires a value"))?;
+                let endpoint = value.trim();
+                if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                    return Err(format!("{arg} requires an http(s) URL"));
+                }
+                let kind = if arg == "--export-otlp" {
+                    ExporterKind::Otlp
+                } else {
+                    ExporterKind::HttpJson
+                };
+                exporters.push(ExporterConfig {
+                    kind,
+                    endpoint: endpoint.to_string(),
+                });
+            }
+            "--export-header" => {
+                let value = args.next().ok_or("--export-header requires a value")?;
+                export_headers.push(parse_export_header(&value)?);
+            }
+            "--max-processes" => {
+                let value = args.next().ok_or("--max-processes requires a value")?;
+                process_limits.apply(&value)?;
+            }
+            "--process-queue-timeout" => {
+                let value = args.next().ok_or("--process-queue-timeout requires a value")?;
+                let seconds = value
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| "--process-queue-timeout requires a number of seconds")?;
+                process_limits.queue_timeout = std::time::Duration::from_secs(seconds);
+            }
+            "--rpc-timeout" => {
+                let value = args.next().ok_or("--rpc-timeout requires a value")?;
+                rpc_timeouts.apply(&value)?;
+            }
+            "--slow-rpc-ms" => {
+                let value = args.next().ok_or("--slow-rpc-ms requires a value")?;
+                let millis = value
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| "--slow-rpc-ms requires a number of milliseconds")?;
+                rpc_timeouts.slow_threshold =
+                    Some(std::time::Duration::from_millis(millis)).filter(|slow| !slow.is_zero());
+            }
+            "--rate-limit" => {
+                let value = args.next().ok_or("--rate-limit requires a value")?;
+                rate_limits.parse_requests_per_sec(&value)?;
+            }
+            "--max-in-flight" => {
+                let value = args.next().ok_or("--max-in-flight requires a value")?;
+                rate_limits.parse_max_in_flight(&value)?;
+            }
+            "--stdio" => {
+                stdio = true;
+            }
+            "--relay" => {
+                let value = args.next().ok_or("--relay requires a value")?;
+                relay_endpoint = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+                if relay_endpoint.is_none() {
+                    return Err("--relay requires a non-empty value".to_string());
+                }
+            }
+            "--http-listen" => {
+                let value = args.next().ok_or("--http-listen requires a value")?;
+                http_listen = Some(value.parse::<SocketAddr>().map_err(|err| err.to_string())?);
+            }
+            "--tls-cert" | "--tls-key" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{arg} requires a value"))?;
+                let path = Some(PathBuf::from(value.trim()));
+                if arg == "--tls-cert" {
+                    tls_cert = path;
+                } else {
+                    tls_key = path;
+                }
+            }
+            "--worker-threads" => {
+                let value = args.next().ok_or("--worker-threads requires a value")?;
+                let threads = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|threads| *threads > 0)
+                    .ok_or("--worker-threads requires a positive number")?;
+                worker_threads = Some(threads);
+            }
+            "--log-level" => {
+                let value = args.next().ok_or("--log-level requires a value")?;
+                log_level = logging::parse_level(&value)?;
+            }
+            "--log-format" => {
+                let value = args.next().ok_or("--log-format requires a value")?;
+                log_format = LogFormat::parse(&value)?;
+            }
+            "--relay-id" => {
+                let value = args.next().ok_or("--relay-id requires a value")?;
+                relay_id = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+                if relay_id.is_none() {
+                    return Err("--relay-id requires a non-empty value".to_string());
+                }
             }
             _ => return Err(format!("Unknown argument: {arg}")),
         }
     }
 
-    if token.is_none() && !insecure_no_auth {
+    let relay = match (relay_endpoint, relay_id) {
+        (Some(_), _) if stdio => {
+            return Err("--relay can't be combined with --stdio".to_string());
+        }
+        (Some(endpoint), Some(daemon_id)) => Some(RelayConfig {
+            endpoint,
+            daemon_id,
+        }),
+        (Some(_), None) => return Err("--relay requires --relay-id".to_string()),
+        (None, Some(_)) => return Err("--relay-id requires --relay".to_string()),
+        (None, None) => None,
+    };
+    if http_listen.is_some() && stdio {
+        return Err("--http-listen can't be combined with --stdio".to_string());
+    }
+    if listen_unix.is_some() && stdio {
+        return Err("--listen-unix can't be combined with --stdio".to_string());
+    }
+    let tls = match (tls_cert, tls_key) {
+        (Some(_), _) | (_, Some(_)) if stdio => {
+            return Err("--tls-cert can't be combined with --stdio".to_string());
+        }
+        (Some(cert), Some(key)) => Some(TlsAcceptor::from(tls_core::server_config(&cert, &key)?)),
+        (Some(_), None) => return Err("--tls-cert requires --tls-key".to_string()),
+        (None, Some(_)) => return Err("--tls-key requires --tls-cert".to_string()),
+        (None, None) => None,
+    };
+    let listen = Some(listen).filter(|_| !stdio && (listen_unix.is_none() || listen_explicit));
+
+    // Over stdio the transport (usually ssh) has already authenticated the caller, and a
+    // unix socket is only reachable by its owner.
+    let network = listen.is_some() || relay.is_some() || http_listen.is_some();
+    let scoped_tokens = match &token_file {
+        Some(path) => token_scopes::load_token_file(path)?,
+        None => Vec::new(),
+    };
+    let tokens = TokenSet::new(token, scoped_tokens);
+    if tokens.is_empty() && !insecure_no_auth && network {
         return Err(
-            "Missing --token (or set CODEX_MONITOR_DAEMON_TOKEN). Use --insecure-no-auth for local dev only."
+            "Missing --token or --token-file (or set CODEX_MONITOR_DAEMON_TOKEN). Use --insecure-no-auth for local dev only."
                 .to_string(),
         );
     }
 
     Ok(DaemonConfig {
         listen,
-        token,
+        listen_unix,
+        tokens,
         data_dir: data_dir.unwrap_or_else(default_data_dir),
+        allowed_commands: CommandAllowlist::parse(&allowed_commands)?,
+        trace_rpc,
+        exporters,
+        export_headers,
+        process_limits,
+        rpc_timeouts,
+        rate_limits,
+        stdio,
+        relay,
+        http_listen,
+        tls,
+        log_level,
+        log_format,
+        worker_threads,
     })
 }
 
-fn build_error_response(id: Option<u64>, message: &str) -> Option<String> {
-    let id = id?;
-    Some(
-        serde_json::to_string(&json!({
-            "id": id,
-            "error": { "message": message }
-        }))
-        .unwrap_or_else(|_| "{\"id\":0,\"error\":{\"message\":\"serialization failed\"}}".to_string()),
-    )
+/// `event_schema` is the app-server event schema the receiving client negotiated.
+/// `seq` goes out as `eventSeq` in the params, for `subscribe_since`.
+fn build_event_notification(
+    event: DaemonEvent,
+    seq: Option<u64>,
+    event_schema: u32,
+) -> Option<String> {
+    let (method, mut params) = match event {
+        DaemonEvent::AppServer(payload) => ("app-server-event", payload.for_schema(event_schema)),
+        DaemonEvent::TerminalOutput(payload) => ("terminal-output", json!(payload)),
+        DaemonEvent::TerminalExit(payload) => ("terminal-exit", json!(payload)),
+        DaemonEvent::ClientPresence(payload) => ("client-presence", json!(payload)),
+        DaemonEvent::JobUpdate(payload) => ("job-update", json!(payload)),
+        DaemonEvent::WorkspaceChanged(payload) => ("workspace-changed", json!(payload)),
+        DaemonEvent::FileConflict(payload) => ("file-conflict", json!(payload)),
+        DaemonEvent::EditorPresence(payload) => ("editor-presence", json!(payload)),
+    };
+    if let (Some(seq), Some(params)) = (seq, params.as_object_mut()) {
+        params.insert("eventSeq".to_string(), json!(seq));
+    }
+    serde_json::to_string(&jsonrpc::notification(method, params)).ok()
 }
 
-fn build_result_response(id: Option<u64>, result: Value) -> Option<String> {
-    let id = id?;
-    Some(serde_json::to_string(&json!({ "id": id, "result": result })).unwrap_or_else(|_| {
-        "{\"id\":0,\"error\":{\"message\":\"serialization failed\"}}".to_string()
-    }))
+/// Device name and client version a client reports in `auth` / `identify_client`.
+fn parse_client_identity(params: &Value) -> (Option<String>, Option<String>) {
+    let field = |key: &str| {
+        params
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+    (field("deviceName"), field("clientVersion"))
 }
 
-fn build_event_notification(event: DaemonEvent) -> Option<String> {
-    let payload = match event {
-        DaemonEvent::AppServer(payload) => json!({
-            "method": "app-server-event",
-            "params": payload,
-        }),
-        DaemonEvent::TerminalOutput(payload) => json!({
-            "method": "terminal-output",
-            "params": payload,
-        }),
-        DaemonEvent::TerminalExit(payload) => json!({
-            "method": "terminal-exit",
-            "params": payload,
-        }),
-    };
-    serde_json::to_string(&payload).ok()
+/// The app-server event schema asked for in `auth` / `identify_client`.
+fn requested_event_schema(params: &Value) -> Option<u64> {
+    params.get("eventSchema").and_then(Value::as_u64)
 }
 
 fn parse_auth_token(params: &Value) -> Option<String> {
@@ -883,207 +3032,393 @@ fn parse_auth_token(params: &Value) -> Option<String> {
     }
 }
 
-fn parse_string(value: &Value, key: &str) -> Result<String, String> {
-    match value {
-        Value::Object(map) => map
-            .get(key)
-            .and_then(|value| value.as_str())
-            .map(|value| value.to_string())
-            .ok_or_else(|| format!("missing or invalid `{key}`")),
-        _ => Err(format!("missing `{key}`")),
-    }
-}
-
-fn parse_optional_string(value: &Value, key: &str) -> Option<String> {
-    match value {
-        Value::Object(map) => map
-            .get(key)
-            .and_then(|value| value.as_str())
-            .map(|v| v.to_string()),
-        _ => None,
-    }
-}
-
-fn parse_optional_u32(value: &Value, key: &str) -> Option<u32> {
-    match value {
-        Value::Object(map) => map.get(key).and_then(|value| value.as_u64()).and_then(|v| {
-            if v > u32::MAX as u64 {
-                None
-            } else {
-                Some(v as u32)
-            }
-        }),
-        _ => None,
-    }
-}
-
-fn parse_optional_bool(value: &Value, key: &str) -> Option<bool> {
-    match value {
-        Value::Object(map) => map.get(key).and_then(|value| value.as_bool()),
-        _ => None,
-    }
-}
-
-fn parse_optional_string_array(value: &Value, key: &str) -> Option<Vec<String>> {
-    match value {
-        Value::Object(map) => map.get(key).and_then(|value| value.as_array()).map(|items| {
-            items
-                .iter()
-                .filter_map(|item| item.as_str().map(|value| value.to_string()))
-                .collect::<Vec<_>>()
-        }),
-        _ => None,
-    }
-}
-
-fn parse_string_array(value: &Value, key: &str) -> Result<Vec<String>, String> {
-    parse_optional_string_array(value, key).ok_or_else(|| format!("missing `{key}`"))
-}
-
-fn parse_optional_value(value: &Value, key: &str) -> Option<Value> {
-    match value {
-        Value::Object(map) => map.get(key).cloned(),
-        _ => None,
-    }
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct FileReadRequest {
-    scope: file_policy::FileScope,
-    kind: file_policy::FileKind,
-    workspace_id: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct FileWriteRequest {
-    scope: file_policy::FileScope,
-    kind: file_policy::FileKind,
-    workspace_id: Option<String>,
-    content: String,
-}
-
-fn parse_file_read_request(params: &Value) -> Result<FileReadRequest, String> {
-    serde_json::from_value(params.clone()).map_err(|err| err.to_string())
-}
-
-fn parse_file_write_request(params: &Value) -> Result<FileWriteRequest, String> {
-    serde_json::from_value(params.clone()).map_err(|err| err.to_string())
-}
-
 async fn handle_rpc_request(
-    state: &DaemonState,
+    state: &Arc<DaemonState>,
     method: &str,
     params: Value,
     client_version: String,
-) -> Result<Value, String> {
+) -> Result<Value, RpcError> {
     match method {
-        "ping" => Ok(json!({ "ok": true })),
+        "ping" => {
+            parse_request::<EmptyRequest>(&params)?;
+            Ok(json!({ "ok": true }))
+        }
+        "health" => {
+            parse_request::<EmptyRequest>(&params)?;
+            serde_json::to_value(state.health.report()).map_err(RpcError::from)
+        }
+        "get_capabilities" => {
+            parse_request::<EmptyRequest>(&params)?;
+            serde_json::to_value(state.capabilities().await).map_err(RpcError::from)
+        }
         "list_workspaces" => {
+            parse_request::<EmptyRequest>(&params)?;
             let workspaces = state.list_workspaces().await;
-            serde_json::to_value(workspaces).map_err(|err| err.to_string())
+            serde_json::to_value(workspaces).map_err(RpcError::from)
         }
         "is_workspace_path_dir" => {
-            let path = parse_string(&params, "path")?;
-            let is_dir = state.is_workspace_path_dir(path).await;
-            serde_json::to_value(is_dir).map_err(|err| err.to_string())
+            let request: PathRequest = parse_request(&params)?;
+            let is_dir = state.is_workspace_path_dir(request.path).await;
+            serde_json::to_value(is_dir).map_err(RpcError::from)
         }
         "add_workspace" => {
-            let path = parse_string(&params, "path")?;
-            let codex_bin = parse_optional_string(&params, "codex_bin");
-            let workspace = state.add_workspace(path, codex_bin, client_version).await?;
-            serde_json::to_value(workspace).map_err(|err| err.to_string())
+            let request: AddWorkspaceRequest = parse_request(&params)?;
+            let workspace = state
+                .add_workspace(request.path, request.codex_bin, client_version)
+                .await?;
+            serde_json::to_value(workspace).map_err(RpcError::from)
         }
         "add_worktree" => {
-            let parent_id = parse_string(&params, "parentId")?;
-            let branch = parse_string(&params, "branch")?;
-            let name = parse_optional_string(&params, "name");
-            let copy_agents_md = parse_optional_bool(&params, "copyAgentsMd").unwrap_or(true);
+            let request: AddWorktreeRequest = parse_request(&params)?;
             let workspace = state
-                .add_worktree(parent_id, branch, name, copy_agents_md, client_version)
+                .add_worktree(
+                    request.parent_id,
+                    request.branch,
+                    WorktreeOptions {
+                        name: request.name,
+                        copy_agents_md: request.copy_agents_md.unwrap_or(true),
+                        allow_protected_branch: request.allow_protected_branch.unwrap_or(false),
+                        base: WorktreeBase {
+                            base_ref: request.base_ref,
+                            fetch: request.fetch_base,
+                        },
+                    },
+                    client_version,
+                )
+                .await?;
+            serde_json::to_value(workspace).map_err(RpcError::from)
+        }
+        "fanout_run" => {
+            let request: FanoutRunRequest = parse_request(&params)?;
+            let run = state
+                .fanout_run(request.parent_id, request.branches, request.prompt, client_version)
+                .await?;
+            serde_json::to_value(run).map_err(RpcError::from)
+        }
+        "fanout_status" => {
+            let runs = &state.event_sink.fanout_runs;
+            match parse_request::<FanoutStatusRequest>(&params)?.run_id {
+                Some(run_id) => {
+                    let run = runs
+                        .get(&run_id)
+                        .ok_or_else(|| format!("Unknown fan-out run: {run_id}"))?;
+                    serde_json::to_value(run).map_err(RpcError::from)
+                }
+                None => serde_json::to_value(runs.list()).map_err(RpcError::from),
+            }
+        }
+        "compare_fanout" => {
+            let request: CompareFanoutRequest = parse_request(&params)?;
+            let comparison = state
+                .compare_fanout(&request.run_id, request.run_checks.unwrap_or(false))
                 .await?;
-            serde_json::to_value(workspace).map_err(|err| err.to_string())
+            serde_json::to_value(comparison).map_err(RpcError::from)
+        }
+        "fanout_pick" => {
+            let request: FanoutPickRequest = parse_request(&params)?;
+            let result = state.fanout_pick(&request.run_id, &request.branch).await?;
+            serde_json::to_value(result).map_err(RpcError::from)
         }
         "worktree_setup_status" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let status = state.worktree_setup_status(workspace_id).await?;
-            serde_json::to_value(status).map_err(|err| err.to_string())
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let status = state.worktree_setup_status(request.workspace_id).await?;
+            serde_json::to_value(status).map_err(RpcError::from)
         }
         "worktree_setup_mark_ran" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.worktree_setup_mark_ran(workspace_id).await?;
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state.worktree_setup_mark_ran(request.workspace_id).await?;
             Ok(json!({ "ok": true }))
         }
+        "preview_codex_command" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let command = state.preview_codex_command(request.workspace_id).await?;
+            serde_json::to_value(command).map_err(RpcError::from)
+        }
         "connect_workspace" => {
-            let id = parse_string(&params, "id")?;
-            state.connect_workspace(id, client_version).await?;
+            let request: IdRequest = parse_request(&params)?;
+            state.connect_workspace(request.id, client_version).await?;
             Ok(json!({ "ok": true }))
         }
-        "remove_workspace" => {
-            let id = parse_string(&params, "id")?;
-            state.remove_workspace(id).await?;
+        "remove_workspace" | "remove_worktree" => {
+            let request: RemoveWorkspaceRequest = parse_request(&params)?;
+            let worktree = method == "remove_worktree";
+            let Some(token) = request.confirm_token else {
+                let plan = state.plan_removal(&request.id, worktree).await?;
+                return serde_json::to_value(plan).map_err(RpcError::from);
+            };
+            state.confirm_removal(&request.id, worktree, &token).await?;
+            if worktree {
+                state.remove_worktree(request.id).await?;
+            } else {
+                state.remove_workspace(request.id).await?;
+            }
             Ok(json!({ "ok": true }))
         }
-        "remove_worktree" => {
-            let id = parse_string(&params, "id")?;
-            state.remove_worktree(id).await?;
-            Ok(json!({ "ok": true }))
+        "reconcile_worktrees" => {
+            let request: ReconcileWorktreesRequest = parse_request(&params)?;
+            let report = state
+                .reconcile_worktrees(request.dry_run.unwrap_or(false))
+                .await?;
+            serde_json::to_value(report).map_err(RpcError::from)
+        }
+        "stale_branches_report" => {
+            let request: StaleBranchesReportRequest = parse_request(&params)?;
+            let report = state.stale_branches_report(request).await?;
+            serde_json::to_value(report).map_err(RpcError::from)
+        }
+        "delete_stale_branches" => {
+            let request: DeleteStaleBranchesRequest = parse_request(&params)?;
+            let deletion = state.delete_stale_branches(request).await?;
+            serde_json::to_value(deletion).map_err(RpcError::from)
         }
         "rename_worktree" => {
-            let id = parse_string(&params, "id")?;
-            let branch = parse_string(&params, "branch")?;
-            let workspace = state.rename_worktree(id, branch, client_version).await?;
-            serde_json::to_value(workspace).map_err(|err| err.to_string())
+            let request: RenameWorktreeRequest = parse_request(&params)?;
+            let workspace = state
+                .rename_worktree(
+                    request.id,
+                    request.branch,
+                    request.allow_protected_branch.unwrap_or(false),
+                    client_version,
+                )
+                .await?;
+            serde_json::to_value(workspace).map_err(RpcError::from)
         }
         "rename_worktree_upstream" => {
-            let id = parse_string(&params, "id")?;
-            let old_branch = parse_string(&params, "oldBranch")?;
-            let new_branch = parse_string(&params, "newBranch")?;
+            let request: RenameWorktreeUpstreamRequest = parse_request(&params)?;
             state
-                .rename_worktree_upstream(id, old_branch, new_branch)
+                .rename_worktree_upstream(
+                    request.id,
+                    request.old_branch,
+                    request.new_branch,
+                    request.allow_protected_branch.unwrap_or(false),
+                )
                 .await?;
             Ok(json!({ "ok": true }))
         }
         "update_workspace_settings" => {
-            let id = parse_string(&params, "id")?;
-            let settings_value = match params {
-                Value::Object(map) => map.get("settings").cloned().unwrap_or(Value::Null),
-                _ => Value::Null,
-            };
-            let settings: WorkspaceSettings =
-                serde_json::from_value(settings_value).map_err(|err| err.to_string())?;
+            let request: UpdateWorkspaceSettingsRequest = parse_request(&params)?;
             let workspace = state
-                .update_workspace_settings(id, settings, client_version)
+                .update_workspace_settings(
+                    request.id,
+                    request.settings,
+                    request.expected_revision,
+                    client_version,
+                )
                 .await?;
-            serde_json::to_value(workspace).map_err(|err| err.to_string())
+            serde_json::to_value(workspace).map_err(RpcError::from)
         }
         "update_workspace_codex_bin" => {
-            let id = parse_string(&params, "id")?;
-            let codex_bin = parse_optional_string(&params, "codex_bin");
-            let workspace = state.update_workspace_codex_bin(id, codex_bin).await?;
-            serde_json::to_value(workspace).map_err(|err| err.to_string())
+            let request: UpdateWorkspaceCodexBinRequest = parse_request(&params)?;
+            let workspace = state
+                .update_workspace_codex_bin(
+                    request.id,
+                    request.codex_bin,
+                    request.expected_revision,
+                )
+                .await?;
+            serde_json::to_value(workspace).map_err(RpcError::from)
+        }
+        "relocate_workspace" => {
+            let request: RelocateWorkspaceRequest = parse_request(&params)?;
+            let workspace = state
+                .relocate_workspace(request.id, request.new_path, client_version)
+                .await?;
+            serde_json::to_value(workspace).map_err(RpcError::from)
         }
         "list_workspace_files" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let files = state.list_workspace_files(workspace_id).await?;
-            serde_json::to_value(files).map_err(|err| err.to_string())
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let files = state.list_workspace_files(request.workspace_id).await?;
+            serde_json::to_value(files).map_err(RpcError::from)
         }
         "read_workspace_file" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let path = parse_string(&params, "path")?;
-            let response = state.read_workspace_file(workspace_id, path).await?;
-            serde_json::to_value(response).map_err(|err| err.to_string())
+            let request: WorkspaceFileRequest = parse_request(&params)?;
+            let response = state
+                .read_workspace_file(request.workspace_id, request.path)
+                .await?;
+            serde_json::to_value(response).map_err(RpcError::from)
+        }
+        "begin_write" => {
+            let request: BeginWriteRequest = parse_request(&params)?;
+            let session = state
+                .begin_write(request.workspace_id, request.path, request.size)
+                .await?;
+            serde_json::to_value(session).map_err(RpcError::from)
+        }
+        "write_chunk" => {
+            let request: WriteChunkRequest = parse_request(&params)?;
+            let progress = state.write_chunk(&request.write_id, request.offset, &request.data)?;
+            serde_json::to_value(progress).map_err(RpcError::from)
+        }
+        "commit_write" => {
+            let request: CommitWriteRequest = parse_request(&params)?;
+            let committed = state.commit_write(&request.write_id, request.sha256.as_deref())?;
+            serde_json::to_value(committed).map_err(RpcError::from)
+        }
+        "abort_write" => {
+            let request: WriteIdRequest = parse_request(&params)?;
+            state.abort_write(&request.write_id)?;
+            Ok(json!({ "ok": true }))
+        }
+        "resolve_mentions" => {
+            let request: ResolveMentionsRequest = parse_request(&params)?;
+            let resolution = state
+                .resolve_mentions(request.workspace_id, request.text)
+                .await?;
+            serde_json::to_value(resolution).map_err(RpcError::from)
+        }
+        "plan_context" => {
+            let request: PlanContextRequest = parse_request(&params)?;
+            let plan = state
+                .plan_context(
+                    request.workspace_id,
+                    request.text,
+                    request.model,
+                    request.budget_tokens.map(|value| value as usize),
+                )
+                .await?;
+            serde_json::to_value(plan).map_err(RpcError::from)
+        }
+        "indexed_search" => {
+            let request: IndexedSearchRequest = parse_request(&params)?;
+            let options = request.options();
+            let response = state
+                .indexed_search(
+                    request.workspace_id,
+                    request.query,
+                    request.limit.map(|value| value as usize),
+                    request.path_prefix,
+                    options,
+                )
+                .await?;
+            serde_json::to_value(response).map_err(RpcError::from)
+        }
+        "workspace_symbols" => {
+            let request: WorkspaceSymbolsRequest = parse_request(&params)?;
+            let response = state
+                .workspace_symbols(
+                    &request.workspace_id,
+                    &request.query,
+                    request.limit.map(|value| value as usize),
+                )
+                .await?;
+            serde_json::to_value(response).map_err(RpcError::from)
+        }
+        "find_definition" => {
+            let request: FindDefinitionRequest = parse_request(&params)?;
+            let lookup = state.find_definition(request).await?;
+            serde_json::to_value(lookup).map_err(RpcError::from)
+        }
+        "search_all_workspaces" => {
+            let request: SearchAllWorkspacesRequest = parse_request(&params)?;
+            let options = request.options();
+            let response = state
+                .search_all_workspaces(
+                    request.query,
+                    request.workspace_ids,
+                    request.limit.map(|value| value as usize),
+                    options,
+                )
+                .await?;
+            serde_json::to_value(response).map_err(RpcError::from)
+        }
+        "generate_commit_message" => {
+            let request: CommitMessageRequest = parse_request(&params)?;
+            let message = state
+                .generate_commit_message(request.workspace_id, request.staged)
+                .await?;
+            Ok(Value::String(message))
+        }
+        "ci_status" => {
+            let request: CiStatusRequest = parse_request(&params)?;
+            let response = state.ci_status(request.workspace_id, request.branch).await?;
+            serde_json::to_value(response).map_err(RpcError::from)
+        }
+        "get_sparse_checkout" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let sparse = state.sparse_checkout(&request.workspace_id).await?;
+            serde_json::to_value(sparse).map_err(RpcError::from)
+        }
+        "set_sparse_checkout" => {
+            let request: SetSparseCheckoutRequest = parse_request(&params)?;
+            let sparse = state
+                .set_sparse_checkout(&request.workspace_id, request.patterns)
+                .await?;
+            serde_json::to_value(sparse).map_err(RpcError::from)
+        }
+        "workspace_stats" => {
+            let request: IdRequest = parse_request(&params)?;
+            let response = state.workspace_stats(request.id).await?;
+            serde_json::to_value(response).map_err(RpcError::from)
+        }
+        "disk_usage" => {
+            let request: DiskUsageRequest = parse_request(&params)?;
+            let report = state
+                .disk_usage(request.workspace_id.as_deref(), request.refresh)
+                .await?;
+            serde_json::to_value(report).map_err(RpcError::from)
+        }
+        "analyze_workspace" => {
+            let request: IdRequest = parse_request(&params)?;
+            let analysis = state.analyze_workspace(&request.id).await?;
+            serde_json::to_value(analysis).map_err(RpcError::from)
+        }
+        "list_packages" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let packages = state.list_packages(request.workspace_id).await?;
+            serde_json::to_value(packages).map_err(RpcError::from)
+        }
+        "audit_dependencies" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let audit = state.audit_dependencies(request.workspace_id).await?;
+            serde_json::to_value(audit).map_err(RpcError::from)
+        }
+        "run_build" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let report = state.run_build(request.workspace_id).await?;
+            serde_json::to_value(report).map_err(RpcError::from)
+        }
+        "list_problems" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            serde_json::to_value(state.list_problems(&request.workspace_id)).map_err(RpcError::from)
+        }
+        "run_retention" => {
+            let request: RunRetentionRequest = parse_request(&params)?;
+            let report = state.run_retention(request.dry_run.unwrap_or(true)).await;
+            serde_json::to_value(report).map_err(RpcError::from)
+        }
+        "auto_fix_start" => {
+            let job = state.start_job("auto_fix", &params)?;
+            serde_json::to_value(job).map_err(RpcError::from)
+        }
+        "job_start" => {
+            let request: JobStartRequest = parse_request(&params)?;
+            let job = state.start_job(&request.kind, &request.params)?;
+            serde_json::to_value(job).map_err(RpcError::from)
+        }
+        "job_status" => match parse_request::<JobStatusRequest>(&params)?.id {
+            Some(id) => {
+                let job = state
+                    .jobs
+                    .status(&id)
+                    .ok_or_else(|| format!("Unknown job: {id}"))?;
+                serde_json::to_value(job).map_err(RpcError::from)
+            }
+            None => serde_json::to_value(state.jobs.list()).map_err(RpcError::from),
+        },
+        "job_cancel" => {
+            let request: IdRequest = parse_request(&params)?;
+            let job = state.jobs.cancel(&request.id)?;
+            serde_json::to_value(job).map_err(RpcError::from)
         }
         "file_read" => {
-            let request = parse_file_read_request(&params)?;
+            let request: FileReadRequest = parse_request(&params)?;
             let response = state
                 .file_read(request.scope, request.kind, request.workspace_id)
                 .await?;
-            serde_json::to_value(response).map_err(|err| err.to_string())
+            serde_json::to_value(response).map_err(RpcError::from)
         }
         "file_write" => {
-            let request = parse_file_write_request(&params)?;
+            let request: FileWriteRequest = parse_request(&params)?;
             state
                 .file_write(
                     request.scope,
@@ -1092,320 +3427,1437 @@ async fn handle_rpc_request(
                     request.content,
                 )
                 .await?;
-            serde_json::to_value(json!({ "ok": true })).map_err(|err| err.to_string())
+            serde_json::to_value(json!({ "ok": true })).map_err(RpcError::from)
+        }
+        "file_delete" => {
+            let request: FileReadRequest = parse_request(&params)?;
+            let deleted = state
+                .file_delete(request.scope, request.kind, request.workspace_id)
+                .await?;
+            Ok(json!({ "deleted": deleted }))
+        }
+        "effective_instructions" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let instructions = state.effective_instructions(&request.workspace_id).await?;
+            serde_json::to_value(instructions).map_err(RpcError::from)
+        }
+        "extract_document" => {
+            let request: WorkspaceFileRequest = parse_request(&params)?;
+            let document = state.extract_document(&request.workspace_id, request.path).await?;
+            serde_json::to_value(document).map_err(RpcError::from)
+        }
+        "render_markdown" => {
+            let request: WorkspaceFileRequest = parse_request(&params)?;
+            let rendered = state.render_markdown(&request.workspace_id, request.path).await?;
+            serde_json::to_value(rendered).map_err(RpcError::from)
+        }
+        "render_notebook" => {
+            let request: WorkspaceFileRequest = parse_request(&params)?;
+            let notebook = state.render_notebook(&request.workspace_id, request.path).await?;
+            serde_json::to_value(notebook).map_err(RpcError::from)
+        }
+        "file_outline" => {
+            let request: WorkspaceFileRequest = parse_request(&params)?;
+            let outline = state.file_outline(&request.workspace_id, request.path).await?;
+            serde_json::to_value(outline).map_err(RpcError::from)
+        }
+        "get_thumbnail" => {
+            let request: WorkspaceFileRequest = parse_request(&params)?;
+            let thumbnail = state.thumbnail(&request.workspace_id, request.path).await?;
+            serde_json::to_value(thumbnail).map_err(RpcError::from)
+        }
+        "file_history" => {
+            let request: PathRequest = parse_request(&params)?;
+            serde_json::to_value(state.file_history(&request.path)).map_err(RpcError::from)
+        }
+        "revert_file_to" => {
+            let request: RevertFileRequest = parse_request(&params)?;
+            let reverted = state.revert_file_to(&request.version).await?;
+            serde_json::to_value(reverted).map_err(RpcError::from)
+        }
+        "get_app_settings" => {
+            parse_request::<EmptyRequest>(&params)?;
+            let settings = state.get_app_settings().await;
+            serde_json::to_value(settings).map_err(RpcError::from)
+        }
+        "update_app_settings" => {
+            let request: UpdateAppSettingsRequest = parse_request(&params)?;
+            let updated = state
+                .update_app_settings(request.settings, request.expected_revision)
+                .await?;
+            serde_json::to_value(updated).map_err(RpcError::from)
+        }
+        "evaluate_approval_policy" => {
+            let request: EvaluateApprovalPolicyRequest = parse_request(&params)?;
+            let message = json!({ "method": request.method, "params": request.params });
+            state
+                .evaluate_approval_policy(request.workspace_id, message)
+                .await
+                .map_err(RpcError::from)
+        }
+        "list_pending_approvals" => {
+            let request: OptionalWorkspaceRequest = parse_request(&params)?;
+            serde_json::to_value(state.list_pending_approvals(request.workspace_id))
+                .map_err(RpcError::from)
+        }
+        "list_clients" => {
+            parse_request::<EmptyRequest>(&params)?;
+            serde_json::to_value(state.clients.list()).map_err(RpcError::from)
+        }
+        "list_editor_presence" => {
+            let request: OptionalWorkspaceRequest = parse_request(&params)?;
+            let listed = state.list_editor_presence(request.workspace_id.as_deref());
+            serde_json::to_value(listed).map_err(RpcError::from)
+        }
+        "kick_client" => {
+            let request: KickClientRequest = parse_request(&params)?;
+            let client = state.kick_client(&request.client_id)?;
+            serde_json::to_value(client).map_err(RpcError::from)
+        }
+        "merge_revisions" => {
+            let request: MergeRevisionsRequest = parse_request(&params)?;
+            let result =
+                revisions_core::merge_values(&request.base, &request.current, &request.proposed);
+            serde_json::to_value(result).map_err(RpcError::from)
+        }
+        "tail_trace" => {
+            let request: TailTraceRequest = parse_request(&params)?;
+            state
+                .tail_trace(request.limit.map(|value| value as usize))
+                .map_err(RpcError::from)
+        }
+        "get_session_log" => {
+            let request: SessionLogRequest = parse_request(&params)?;
+            let response = state.get_session_log(
+                request.workspace_id,
+                request.tail.map(|value| value as usize),
+            )?;
+            serde_json::to_value(response).map_err(RpcError::from)
+        }
+        "backup_data" => {
+            let request: BackupRequest = parse_request(&params)?;
+            let result = state.backup_data(request.destination)?;
+            serde_json::to_value(result).map_err(RpcError::from)
+        }
+        "restore_data" => {
+            let request: RestoreRequest = parse_request(&params)?;
+            let result = state
+                .restore_data(request.source, request.confirm.unwrap_or(false))
+                .await?;
+            serde_json::to_value(result).map_err(RpcError::from)
+        }
+        "export_safety_policy" => {
+            parse_request::<EmptyRequest>(&params)?;
+            serde_json::to_value(state.export_safety_policy().await).map_err(RpcError::from)
+        }
+        "import_safety_policy" => {
+            let request: ImportSafetyPolicyRequest = parse_request(&params)?;
+            let result = state.import_safety_policy(request).await?;
+            serde_json::to_value(result).map_err(RpcError::from)
+        }
+        "get_codex_config_path" => {
+            parse_request::<EmptyRequest>(&params)?;
+            let path = settings_core::get_codex_config_path_core()?;
+            Ok(Value::String(path))
+        }
+        "get_config_model" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .get_config_model(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
+        }
+        "start_thread" => {
+            let request: StartThreadRequest = parse_request(&params)?;
+            state
+                .start_thread(request.workspace_id, request.cwd)
+                .await
+                .map_err(RpcError::from)
+        }
+        "resume_thread" => {
+            let request: ThreadRequest = parse_request(&params)?;
+            state
+                .resume_thread(request.workspace_id, request.thread_id)
+                .await
+                .map_err(RpcError::from)
+        }
+        "fork_thread" => {
+            let request: ThreadRequest = parse_request(&params)?;
+            state
+                .fork_thread(request.workspace_id, request.thread_id)
+                .await
+                .map_err(RpcError::from)
+        }
+        "thread_graph" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let graph = state.thread_graph(&request.workspace_id).await;
+            serde_json::to_value(graph).map_err(RpcError::from)
+        }
+        "list_slash_commands" => {
+            parse_request::<EmptyRequest>(&params)?;
+            let commands = state.list_slash_commands().await;
+            serde_json::to_value(commands).map_err(RpcError::from)
+        }
+        "time_tracking_ping" => {
+            let request: TimeTrackingPingRequest = parse_request(&params)?;
+            state.time_tracking_ping(request).await?;
+            Ok(json!({ "ok": true }))
+        }
+        "time_tracking_summary" => {
+            let request: TimeTrackingSummaryRequest = parse_request(&params)?;
+            let summary = state.time_tracking_summary(request);
+            serde_json::to_value(summary).map_err(RpcError::from)
+        }
+        "save_draft" => {
+            let request: SaveDraftRequest = parse_request(&params)?;
+            let draft = state.save_draft(
+                &request.workspace_id,
+                &request.thread_id,
+                request.text.unwrap_or_default(),
+                request.images.unwrap_or_default(),
+            )?;
+            serde_json::to_value(draft).map_err(RpcError::from)
+        }
+        "get_draft" => {
+            let request: ThreadRequest = parse_request(&params)?;
+            serde_json::to_value(state.get_draft(&request.workspace_id, &request.thread_id))
+                .map_err(RpcError::from)
+        }
+        "list_pinned_context" => {
+            let request: PinnedContextRequest = parse_request(&params)?;
+            let view = state
+                .list_pinned_context(
+                    &request.workspace_id,
+                    &request.thread_id,
+                    request.model.as_deref(),
+                )
+                .await?;
+            serde_json::to_value(view).map_err(RpcError::from)
         }
-        "get_app_settings" => {
-            let settings = state.get_app_settings().await;
-            serde_json::to_value(settings).map_err(|err| err.to_string())
+        "pin_context" => {
+            let request: PinContextRequest = parse_request(&params)?;
+            let pin = state.pin_context(
+                &request.workspace_id,
+                &request.thread_id,
+                PinInput {
+                    path: request.path,
+                    start_line: request.start_line,
+                    end_line: request.end_line,
+                    text: request.text,
+                    label: request.label,
+                },
+            )?;
+            serde_json::to_value(pin).map_err(RpcError::from)
         }
-        "update_app_settings" => {
-            let settings_value = match params {
-                Value::Object(map) => map.get("settings").cloned().unwrap_or(Value::Null),
-                _ => Value::Null,
-            };
-            let settings: AppSettings =
-                serde_json::from_value(settings_value).map_err(|err| err.to_string())?;
-            let updated = state.update_app_settings(settings).await?;
-            serde_json::to_value(updated).map_err(|err| err.to_string())
+        "unpin_context" => {
+            let request: UnpinContextRequest = parse_request(&params)?;
+            let removed = state.unpin_context(
+                &request.workspace_id,
+                &request.thread_id,
+                request.pin_id.as_deref(),
+            )?;
+            Ok(json!({ "removed": removed }))
         }
-        "get_codex_config_path" => {
-            let path = settings_core::get_codex_config_path_core()?;
-            Ok(Value::String(path))
+        "list_collaboration_presets" => {
+            parse_request::<EmptyRequest>(&params)?;
+            serde_json::to_value(state.list_collaboration_presets()).map_err(RpcError::from)
         }
-        "get_config_model" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.get_config_model(workspace_id).await
+        "save_collaboration_preset" => {
+            let request: SaveCollaborationPresetRequest = parse_request(&params)?;
+            let preset = state.save_collaboration_preset(
+                &request.name,
+                request.description,
+                request.collaboration_mode,
+            )?;
+            serde_json::to_value(preset).map_err(RpcError::from)
         }
-        "start_thread" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.start_thread(workspace_id).await
+        "delete_collaboration_preset" => {
+            let request: NameRequest = parse_request(&params)?;
+            let deleted = state.delete_collaboration_preset(&request.name)?;
+            Ok(json!({ "deleted": deleted }))
         }
-        "resume_thread" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            state.resume_thread(workspace_id, thread_id).await
+        "list_account_profiles" => {
+            parse_request::<EmptyRequest>(&params)?;
+            let profiles = state.list_account_profiles().await;
+            serde_json::to_value(profiles).map_err(RpcError::from)
         }
-        "fork_thread" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            state.fork_thread(workspace_id, thread_id).await
+        "save_account_profile" => {
+            let request: SaveAccountProfileRequest = parse_request(&params)?;
+            let profile = state.save_account_profile(&request.name, &request.codex_home)?;
+            serde_json::to_value(profile).map_err(RpcError::from)
+        }
+        "delete_account_profile" => {
+            let request: NameRequest = parse_request(&params)?;
+            let deleted = state.delete_account_profile(&request.name).await?;
+            Ok(json!({ "deleted": deleted }))
         }
         "list_threads" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let cursor = parse_optional_string(&params, "cursor");
-            let limit = parse_optional_u32(&params, "limit");
-            let sort_key = parse_optional_string(&params, "sortKey");
-            state.list_threads(workspace_id, cursor, limit, sort_key).await
+            let request: ListThreadsRequest = parse_request(&params)?;
+            let options = request.options();
+            state
+                .list_threads(
+                    request.workspace_id,
+                    request.cursor,
+                    request.limit,
+                    request.sort_key,
+                    request.branch,
+                    options,
+                )
+                .await
+                .map_err(RpcError::from)
         }
         "list_mcp_server_status" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let cursor = parse_optional_string(&params, "cursor");
-            let limit = parse_optional_u32(&params, "limit");
-            state.list_mcp_server_status(workspace_id, cursor, limit).await
+            let request: ListRequest = parse_request(&params)?;
+            let options = request.options();
+            state
+                .list_mcp_server_status(
+                    request.workspace_id,
+                    request.cursor,
+                    request.limit,
+                    options,
+                )
+                .await
+                .map_err(RpcError::from)
+        }
+        "mcp_server_health" => {
+            let request: OptionalWorkspaceRequest = parse_request(&params)?;
+            serde_json::to_value(state.mcp_server_health(request.workspace_id.as_deref()))
+                .map_err(RpcError::from)
+        }
+        "mcp_server_oauth_login" => {
+            let request: McpOauthLoginRequest = parse_request(&params)?;
+            state
+                .mcp_server_oauth_login(request.workspace_id, request.name)
+                .await
+                .map_err(RpcError::from)
         }
         "archive_thread" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            state.archive_thread(workspace_id, thread_id).await
+            let request: ThreadRequest = parse_request(&params)?;
+            state
+                .archive_thread(request.workspace_id, request.thread_id)
+                .await
+                .map_err(RpcError::from)
         }
         "compact_thread" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            state.compact_thread(workspace_id, thread_id).await
+            let request: ThreadRequest = parse_request(&params)?;
+            state
+                .compact_thread(request.workspace_id, request.thread_id)
+                .await
+                .map_err(RpcError::from)
         }
         "set_thread_name" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            let name = parse_string(&params, "name")?;
-            state.set_thread_name(workspace_id, thread_id, name).await
+            let request: SetThreadNameRequest = parse_request(&params)?;
+            state
+                .set_thread_name(request.workspace_id, request.thread_id, request.name)
+                .await
+                .map_err(RpcError::from)
         }
         "send_user_message" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            let text = parse_string(&params, "text")?;
-            let model = parse_optional_string(&params, "model");
-            let effort = parse_optional_string(&params, "effort");
-            let access_mode = parse_optional_string(&params, "accessMode");
-            let images = parse_optional_string_array(&params, "images");
-            let collaboration_mode = parse_optional_value(&params, "collaborationMode");
+            let request: SendUserMessageRequest = parse_request(&params)?;
             state
-                .send_user_message(
-                    workspace_id,
-                    thread_id,
-                    text,
-                    model,
-                    effort,
-                    access_mode,
-                    images,
-                    collaboration_mode,
-                )
+                .send_user_message(request)
                 .await
+                .map_err(RpcError::from)
         }
         "turn_interrupt" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            let turn_id = parse_string(&params, "turnId")?;
-            state.turn_interrupt(workspace_id, thread_id, turn_id).await
+            let request: TurnInterruptRequest = parse_request(&params)?;
+            state
+                .turn_interrupt(request.workspace_id, request.thread_id, request.turn_id)
+                .await
+                .map_err(RpcError::from)
+        }
+        "restore_snapshot" => {
+            let request: RestoreSnapshotRequest = parse_request(&params)?;
+            let restore = state.restore_snapshot(request.turn_id).await?;
+            serde_json::to_value(restore).map_err(RpcError::from)
         }
         "start_review" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            let target = params
-                .as_object()
-                .and_then(|map| map.get("target"))
-                .cloned()
-                .ok_or("missing `target`")?;
-            let delivery = parse_optional_string(&params, "delivery");
-            state.start_review(workspace_id, thread_id, target, delivery).await
+            let request: StartReviewRequest = parse_request(&params)?;
+            state
+                .start_review(
+                    request.workspace_id,
+                    request.thread_id,
+                    request.target,
+                    request.delivery,
+                )
+                .await
+                .map_err(RpcError::from)
         }
         "model_list" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.model_list(workspace_id).await
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .model_list(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
         }
         "collaboration_mode_list" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.collaboration_mode_list(workspace_id).await
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .collaboration_mode_list(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
         }
         "account_rate_limits" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.account_rate_limits(workspace_id).await
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .account_rate_limits(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
         }
         "account_read" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.account_read(workspace_id).await
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .account_read(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
         }
         "codex_login" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.codex_login(workspace_id).await
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .codex_login(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
         }
         "codex_login_cancel" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.codex_login_cancel(workspace_id).await
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .codex_login_cancel(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
         }
         "skills_list" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            state.skills_list(workspace_id).await
+            let request: WorkspaceRequest = parse_request(&params)?;
+            state
+                .skills_list(request.workspace_id)
+                .await
+                .map_err(RpcError::from)
+        }
+        "skills_config" => {
+            let request: SkillsConfigRequest = parse_request(&params)?;
+            state
+                .skills_config(request.workspace_id, request.scope)
+                .await
+                .map_err(RpcError::from)
+        }
+        "skills_set_enabled" => {
+            let request: SkillsSetEnabledRequest = parse_request(&params)?;
+            state
+                .skills_set_enabled(
+                    request.workspace_id,
+                    request.path,
+                    request.enabled,
+                    request.scope,
+                )
+                .await
+                .map_err(RpcError::from)
         }
         "apps_list" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let cursor = parse_optional_string(&params, "cursor");
-            let limit = parse_optional_u32(&params, "limit");
-            state.apps_list(workspace_id, cursor, limit).await
+            let request: ListRequest = parse_request(&params)?;
+            let options = request.options();
+            state
+                .apps_list(request.workspace_id, request.cursor, request.limit, options)
+                .await
+                .map_err(RpcError::from)
         }
         "respond_to_server_request" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let map = params.as_object().ok_or("missing requestId")?;
-            let request_id = map
-                .get("requestId")
-                .cloned()
-                .filter(|value| value.is_number() || value.is_string())
-                .ok_or("missing requestId")?;
-            let result = map.get("result").cloned().ok_or("missing `result`")?;
+            let request: RespondToServerRequest = parse_request(&params)?;
             state
-                .respond_to_server_request(workspace_id, request_id, result)
+                .respond_to_server_request(request.workspace_id, request.request_id, request.result)
                 .await
+                .map_err(RpcError::from)
         }
         "remember_approval_rule" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let command = parse_string_array(&params, "command")?;
-            state.remember_approval_rule(workspace_id, command).await
+            let request: RememberApprovalRuleRequest = parse_request(&params)?;
+            state
+                .remember_approval_rule(request.workspace_id, request.command)
+                .await
+                .map_err(RpcError::from)
         }
-        _ => Err(format!("unknown method: {method}")),
+        "validate_rules" => {
+            let request: WorkspaceRequest = parse_request(&params)?;
+            let validation = state.validate_rules(&request.workspace_id).await?;
+            serde_json::to_value(validation).map_err(RpcError::from)
+        }
+        "format_rules" => {
+            let request: FormatRulesRequest = parse_request(&params)?;
+            let result = state
+                .format_rules(&request.workspace_id, request.write)
+                .await?;
+            serde_json::to_value(result).map_err(RpcError::from)
+        }
+        _ => Err(RpcError::MethodNotFound(method.to_string())),
+    }
+}
+
+/// Streams broadcast events to one connection until stopped, then hands the receiver
+/// back so a resumable connection can park its place in the stream.
+struct EventForwarder {
+    task: tokio::task::JoinHandle<broadcast::Receiver<Sequenced<DaemonEvent>>>,
+    stop: Arc<Notify>,
+}
+
+impl EventForwarder {
+    fn start(
+        mut rx: broadcast::Receiver<Sequenced<DaemonEvent>>,
+        subscriptions: watch::Receiver<EventSubscriptions>,
+        event_schema: Arc<AtomicU32>,
+        client_id: String,
+        out_tx_events: mpsc::UnboundedSender<String>,
+    ) -> Self {
+        let stop = Arc::new(Notify::new());
+        let stopped = Arc::clone(&stop);
+        let task = tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    event = rx.recv() => event,
+                    _ = stopped.notified() => break,
+                };
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if !event
+                    .event
+                    .delivered_to(&client_id, &subscriptions.borrow())
+                {
+                    continue;
+                }
+
+                let schema = event_schema.load(Ordering::Relaxed);
+                let Some(payload) = build_event_notification(event.event, Some(event.seq), schema)
+                else {
+                    continue;
+                };
+
+                if out_tx_events.send(payload).is_err() {
+                    break;
+                }
+            }
+            rx
+        });
+        Self { task, stop }
+    }
+
+    async fn stop(self) -> Option<broadcast::Receiver<Sequenced<DaemonEvent>>> {
+        self.stop.notify_one();
+        self.task.await.ok()
     }
 }
 
-async fn forward_events(
-    mut rx: broadcast::Receiver<DaemonEvent>,
-    out_tx_events: mpsc::UnboundedSender<String>,
+async fn run_approval_policy(
+    state: Arc<DaemonState>,
+    mut requests: mpsc::UnboundedReceiver<AppServerEvent>,
 ) {
-    loop {
-        let event = match rx.recv().await {
-            Ok(event) => event,
-            Err(broadcast::error::RecvError::Lagged(_)) => continue,
-            Err(broadcast::error::RecvError::Closed) => break,
-        };
+    while let Some(event) = requests.recv().await {
+        state.apply_approval_policy(event).await;
+    }
+}
 
-        let Some(payload) = build_event_notification(event) else {
-            continue;
-        };
+async fn run_thumbnailer(
+    state: Arc<DaemonState>,
+    mut artifacts: mpsc::UnboundedReceiver<(String, Vec<ImageArtifact>)>,
+) {
+    while let Some((workspace_id, artifacts)) = artifacts.recv().await {
+        state.publish_thumbnails(workspace_id, artifacts).await;
+    }
+}
 
-        if out_tx_events.send(payload).is_err() {
-            break;
+async fn run_conflict_notifier(
+    state: Arc<DaemonState>,
+    mut writes: mpsc::UnboundedReceiver<(String, AgentWrite)>,
+) {
+    while let Some((workspace_id, write)) = writes.recv().await {
+        state.record_agent_edits(&workspace_id, &write).await;
+        state.publish_file_conflicts(workspace_id, write).await;
+    }
+}
+
+/// Re-sends still-unanswered server requests to a client that just subscribed to events.
+fn replay_pending_approvals(
+    state: &DaemonState,
+    event_schema: u32,
+    out_tx: &mpsc::UnboundedSender<String>,
+) {
+    for pending in state.list_pending_approvals(None) {
+        let event = DaemonEvent::AppServer(AppServerEvent {
+            workspace_id: pending.workspace_id,
+            message: pending.message,
+        });
+        if let Some(payload) = build_event_notification(event, None, event_schema) {
+            let _ = out_tx.send(payload);
+        }
+    }
+}
+
+/// Refuses a method the grant has no scope for, recording the denial in the audit log.
+fn authorize(
+    state: &DaemonState,
+    client_label: &str,
+    grant: &Grant,
+    method: &str,
+    params: &Value,
+) -> Result<(), RpcError> {
+    grant.check(method).map_err(|message| {
+        state.audit_log.record(
+            "rpc_denied",
+            json!({ "client": client_label, "method": method, "token": grant.token_name }),
+        );
+        let error = RpcError::Forbidden(message);
+        logging::rpc_span(client_label, method, params).in_scope(|| {
+            logging::log_rpc_outcome(std::time::Duration::ZERO, Some(&error))
+        });
+        error
+    })
+}
+
+/// Dispatches one RPC for any transport, exporting its span and tracing the response.
+async fn serve_rpc(
+    state: &Arc<DaemonState>,
+    client_label: &str,
+    grant: &Grant,
+    id: Option<&RequestId>,
+    method: &str,
+    mut params: Value,
+    traced: bool,
+) -> Result<Value, RpcError> {
+    authorize(state, client_label, grant, method, &params)?;
+    let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
+    let started_at = audit::now_millis();
+    let started = std::time::Instant::now();
+    let span_params = params.clone();
+    let span = logging::rpc_span(client_label, method, &span_params);
+    let call = async {
+        let if_none_match = etag::take_if_none_match(method, &mut params)?;
+        let value = handle_rpc_request(state, method, params, client_version).await?;
+        Ok::<_, RpcError>(match if_none_match {
+            Some(if_none_match) => etag::conditional_response(value, if_none_match.as_deref()),
+            None => value,
+        })
+    };
+    let result = state
+        .rpc_timeouts
+        .run(method, call)
+        .instrument(span.clone())
+        .await;
+    let result = match result {
+        Err(RpcError::Failed(message)) if names_unknown_workspace(state, &span_params).await => {
+            Err(RpcError::NotFound(message))
         }
+        result => result,
+    };
+    let error = result.as_ref().err();
+    let elapsed = started.elapsed();
+    span.in_scope(|| {
+        logging::log_rpc_outcome(elapsed, error);
+        state
+            .rpc_timeouts
+            .log_if_slow(client_label, method, &span_params, elapsed, error);
+    });
+    let error_message = error.map(RpcError::message);
+    state.event_sink.exporter.rpc_span(
+        started_at,
+        client_label,
+        id,
+        method,
+        &span_params,
+        error_message.as_deref(),
+    );
+    if traced {
+        let payload = match &result {
+            Ok(result) => json!({ "result": result }),
+            Err(error) => json!({ "error": error.to_json() }),
+        };
+        state
+            .rpc_trace
+            .record("response", client_label, id, method, &payload);
+    }
+    result
+}
+
+/// Whether the params name a workspace the daemon doesn't have. Core reports a missing
+/// workspace with a localized message, so a failed call is checked against the list
+/// instead.
+async fn names_unknown_workspace(state: &DaemonState, params: &Value) -> bool {
+    match logging::rpc_workspace_id(params) {
+        Some(workspace_id) => !state.workspaces.lock().await.contains_key(workspace_id),
+        None => false,
+    }
+}
+
+/// What a closed connection leaves behind under its resume ticket.
+struct ParkedConnection {
+    device_name: Option<String>,
+    client_version: Option<String>,
+    compression: CompressionState,
+    event_schema: u32,
+    subscriptions: EventSubscriptions,
+    grant: Grant,
+    open_files: ClientOpenFiles,
+    /// Positioned right after the last event the old connection forwarded.
+    events: broadcast::Receiver<Sequenced<DaemonEvent>>,
+}
+
+/// Resolves once another connection redeems this connection's resume ticket.
+async fn takeover_requested(resume_ticket: Option<&(String, Arc<Notify>)>) {
+    match resume_ticket {
+        Some((_, takeover)) => takeover.notified().await,
+        None => std::future::pending().await,
     }
 }
 
-async fn handle_client(
+async fn handle_tcp_client(
     socket: TcpStream,
     config: Arc<DaemonConfig>,
     state: Arc<DaemonState>,
-    events: broadcast::Sender<DaemonEvent>,
+    events: Arc<EventLog<DaemonEvent>>,
+) {
+    let client_label = socket
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+    let Some(tls) = config.tls.clone() else {
+        let (reader, writer) = socket.into_split();
+        handle_client(reader, writer, client_label, config, state, events).await;
+        return;
+    };
+    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(socket)).await {
+        Ok(Ok(stream)) => {
+            let (reader, writer) = tokio::io::split(stream);
+            handle_client(reader, writer, client_label, config, state, events).await;
+        }
+        Ok(Err(err)) => tracing::warn!(client = %client_label, "TLS handshake failed: {err}"),
+        Err(_) => tracing::warn!(client = %client_label, "TLS handshake timed out"),
+    }
+}
+
+#[cfg(unix)]
+async fn accept_unix_clients(
+    listener: tokio::net::UnixListener,
+    config: Arc<DaemonConfig>,
+    state: Arc<DaemonState>,
+    events: Arc<EventLog<DaemonEvent>>,
 ) {
-    let (reader, mut writer) = socket.into_split();
+    loop {
+        match listener.accept().await {
+            Ok((socket, _addr)) => {
+                let client_label = unix_socket::client_label(&socket);
+                let (reader, writer) = socket.into_split();
+                tokio::spawn(handle_client(
+                    reader,
+                    writer,
+                    client_label,
+                    Arc::clone(&config),
+                    Arc::clone(&state),
+                    events.clone(),
+                ));
+            }
+            Err(_) => continue,
+        }
+    }
+}
+
+/// Serves one connection over any byte stream: a TCP or unix socket, or stdin/stdout in
+/// `--stdio` mode.
+async fn handle_client<R, W>(
+    reader: R,
+    mut writer: W,
+    client_label: String,
+    config: Arc<DaemonConfig>,
+    state: Arc<DaemonState>,
+    events: Arc<EventLog<DaemonEvent>>,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
     let mut lines = BufReader::new(reader).lines();
 
     let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
-    let write_task = tokio::spawn(async move {
+    let compression = Arc::new(WireCompression::default());
+    let compression_for_writer = Arc::clone(&compression);
+    let mut write_task = tokio::spawn(async move {
         while let Some(message) = out_rx.recv().await {
+            let message = compression_for_writer.encode(message);
             if writer.write_all(message.as_bytes()).await.is_err() {
                 break;
             }
-            if writer.write_all(b"\n").await.is_err() {
+            if writer.write_all(b"\n").await.is_err() || writer.flush().await.is_err() {
                 break;
             }
         }
     });
 
-    let mut authenticated = config.token.is_none();
-    let mut events_task: Option<tokio::task::JoinHandle<()>> = None;
+    let mut authenticated = config.tokens.is_empty();
+    let mut grant = Grant::full();
+    let mut limiter = config.rate_limits.limiter();
+    // Calls served by `serve_rpc` run on their own tasks so a slow one doesn't hold up
+    // the connection.
+    let mut calls = tokio::task::JoinSet::new();
+    let (subscriptions_tx, subscriptions_rx) = watch::channel(EventSubscriptions::default());
+    let event_schema = Arc::new(AtomicU32::new(LEGACY_EVENT_SCHEMA_VERSION));
+    let mut forwarder: Option<EventForwarder> = None;
+    let mut resume_ticket: Option<(String, Arc<Notify>)> = None;
+    let (client_id, kick) = state.clients.register(&client_label);
+    let mut kicked = false;
 
     if authenticated {
-        let rx = events.subscribe();
-        let out_tx_events = out_tx.clone();
-        events_task = Some(tokio::spawn(forward_events(rx, out_tx_events)));
+        forwarder = Some(EventForwarder::start(
+            events.subscribe(),
+            subscriptions_rx.clone(),
+            Arc::clone(&event_schema),
+            client_id.clone(),
+            out_tx.clone(),
+        ));
+        replay_pending_approvals(&state, LEGACY_EVENT_SCHEMA_VERSION, &out_tx);
+        resume_ticket = Some(state.resume_tickets.issue());
+        if let Some(client) = state.clients.identify(&client_id, None, None) {
+            state.emit_client_presence("connected", &client);
+        }
     }
 
-    while let Ok(Some(line)) = lines.next_line().await {
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line,
+            _ = kick.notified() => {
+                kicked = true;
+                break;
+            }
+            // The client came back on a new connection before this one noticed the drop.
+            _ = takeover_requested(resume_ticket.as_ref()) => break,
+        };
+        let Ok(Some(line)) = line else {
+            break;
+        };
         let line = line.trim();
         if line.is_empty() {
             continue;
         }
 
-        let message: Value = match serde_json::from_str(line) {
-            Ok(value) => value,
-            Err(_) => continue,
+        let frame = match jsonrpc::parse_frame(line) {
+            Ok(frame) => frame,
+            Err(response) => {
+                let _ = out_tx.send(response.to_string());
+                continue;
+            }
         };
+        state.clients.touch(&client_id);
+        let mut replies = jsonrpc::Replies::new(&out_tx, frame.batch);
+        for message in frame.messages {
+            let (id, method, params) = match jsonrpc::parse_message(&message) {
+                Ok(request) => (request.id, request.method, request.params),
+                Err(response) => {
+                    replies.send(Some(response));
+                    continue;
+                }
+            };
+            let id = id.as_ref();
+            let slot = match limiter.admit() {
+                Ok(slot) => slot,
+                Err(limited) => {
+                    replies.send(jsonrpc::error_response(id, limited.to_json()));
+                    continue;
+                }
+            };
+            // Trace reads would otherwise echo the trace back into itself.
+            let traced = method != "tail_trace";
+            if traced {
+                state
+                    .rpc_trace
+                    .record("request", &client_label, id, &method, &message);
+            }
 
-        let id = message.get("id").and_then(|value| value.as_u64());
-        let method = message
-            .get("method")
-            .and_then(|value| value.as_str())
-            .unwrap_or("")
-            .to_string();
-        let params = message.get("params").cloned().unwrap_or(Value::Null);
+            if method == "resume" {
+                let redeemed = match parse_request::<ResumeRequest>(&params) {
+                    Ok(request)
+                        if resume_ticket
+                            .as_ref()
+                            .is_some_and(|(own, _)| *own == request.ticket) =>
+                    {
+                        Err(RpcError::Failed(
+                            "resume ticket belongs to this connection".to_string(),
+                        ))
+                    }
+                    Ok(request) => state
+                        .resume_tickets
+                        .redeem(&request.ticket)
+                        .await
+                        .ok_or_else(|| {
+                            RpcError::Failed("invalid or expired resume ticket".to_string())
+                        }),
+                    Err(error) => Err(error.into()),
+                };
+                let parked = match redeemed {
+                    Ok(parked) => parked,
+                    Err(error) => {
+                        replies.send(jsonrpc::error_response(id, error.to_json()));
+                        continue;
+                    }
+                };
 
-        if !authenticated {
-            if method != "auth" {
-                if let Some(response) = build_error_response(id, "unauthorized") {
-                    let _ = out_tx.send(response);
+                grant = parked.grant;
+                let presence = if authenticated {
+                    "updated"
+                } else {
+                    "connected"
+                };
+                authenticated = true;
+                if let Some(forwarder) = forwarder.take() {
+                    forwarder.stop().await;
+                }
+                if let Some((ticket, _)) = resume_ticket.take() {
+                    state.resume_tickets.revoke(&ticket);
                 }
+                let (ticket, takeover) = state.resume_tickets.issue();
+                compression.restore(parked.compression);
+                let reopened = parked.open_files.workspace_ids();
+                state.open_files.restore(&client_id, parked.open_files);
+                event_schema.store(parked.event_schema, Ordering::Relaxed);
+                subscriptions_tx.send_replace(parked.subscriptions.clone());
+                if let Some(client) =
+                    state
+                        .clients
+                        .identify(&client_id, parked.device_name, parked.client_version)
+                {
+                    state.emit_client_presence(presence, &client);
+                }
+                for workspace_id in reopened {
+                    state.emit_editor_presence(&client_id, &workspace_id);
+                }
+                let result = json!({
+                    "ok": true,
+                    "resumed": true,
+                    "clientId": client_id,
+                    "compression": parked.compression.encoding(),
+                    "eventSchema": parked.event_schema,
+                    "latestEventSchema": EVENT_SCHEMA_VERSION,
+                    "resumeTicket": ticket,
+                    "subscriptions": parked.subscriptions,
+                    "scopes": grant.scopes(),
+                    "capabilities": state.capabilities().await,
+                });
+                replies.send(jsonrpc::result_response(id, result));
+                // Started after the response so missed events follow it, in order.
+                forwarder = Some(EventForwarder::start(
+                    parked.events,
+                    subscriptions_rx.clone(),
+                    Arc::clone(&event_schema),
+                    client_id.clone(),
+                    out_tx.clone(),
+                ));
+                resume_ticket = Some((ticket, takeover));
                 continue;
             }
 
-            let expected = config.token.clone().unwrap_or_default();
-            let provided = parse_auth_token(&params).unwrap_or_default();
-            if expected != provided {
-                if let Some(response) = build_error_response(id, "invalid token") {
-                    let _ = out_tx.send(response);
+            if !authenticated {
+                if method != "auth" {
+                    let error = jsonrpc::error_object(jsonrpc::UNAUTHORIZED, "unauthorized", None);
+                    replies.send(jsonrpc::error_response(id, error));
+                    continue;
+                }
+
+                let provided = parse_auth_token(&params).unwrap_or_default();
+                let Some(granted) = config.tokens.authenticate(&provided) else {
+                    let error = jsonrpc::error_object(jsonrpc::UNAUTHORIZED, "invalid token", None);
+                    replies.send(jsonrpc::error_response(id, error));
+                    continue;
+                };
+
+                authenticated = true;
+                grant = granted;
+                let (device_name, client_version) = parse_client_identity(&params);
+                if let Some(client) =
+                    state
+                        .clients
+                        .identify(&client_id, device_name, client_version)
+                {
+                    state.emit_client_presence("connected", &client);
                 }
+                let encoding = compression.negotiate(&params);
+                let schema = negotiate_event_schema(requested_event_schema(&params));
+                event_schema.store(schema, Ordering::Relaxed);
+                let (ticket, takeover) = state.resume_tickets.issue();
+                let result = json!({
+                    "ok": true,
+                    "clientId": client_id,
+                    "compression": encoding,
+                    "eventSchema": schema,
+                    "latestEventSchema": EVENT_SCHEMA_VERSION,
+                    "resumeTicket": ticket,
+                    "scopes": grant.scopes(),
+                    "capabilities": state.capabilities().await,
+                });
+                replies.send(jsonrpc::result_response(id, result));
+                resume_ticket = Some((ticket, takeover));
+
+                forwarder = Some(EventForwarder::start(
+                    events.subscribe(),
+                    subscriptions_rx.clone(),
+                    Arc::clone(&event_schema),
+                    client_id.clone(),
+                    out_tx.clone(),
+                ));
+                replay_pending_approvals(&state, schema, &out_tx);
+
+                continue;
+            }
+
+            // The connection-level methods below are served here rather than by
+            // `serve_rpc`, so they are authorized here too.
+            if let Err(error) = authorize(&state, &client_label, &grant, &method, &params) {
+                replies.send(jsonrpc::error_response(id, error.to_json()));
                 continue;
             }
 
-            authenticated = true;
-            if let Some(response) = build_result_response(id, json!({ "ok": true })) {
-                let _ = out_tx.send(response);
+            if method == "identify_client" {
+                let (device_name, client_version) = parse_client_identity(&params);
+                let result = state
+                    .clients
+                    .identify(&client_id, device_name, client_version)
+                    .map(|client| {
+                        state.emit_client_presence("updated", &client);
+                        let mut result = json!(client);
+                        result["compression"] = json!(compression.negotiate(&params));
+                        if let Some(requested) = requested_event_schema(&params) {
+                            let schema = negotiate_event_schema(Some(requested));
+                            event_schema.store(schema, Ordering::Relaxed);
+                        }
+                        result["eventSchema"] = json!(event_schema.load(Ordering::Relaxed));
+                        result["latestEventSchema"] = json!(EVENT_SCHEMA_VERSION);
+                        result["resumeTicket"] =
+                            json!(resume_ticket.as_ref().map(|(ticket, _)| ticket));
+                        result
+                    })
+                    .ok_or_else(|| "client not found".to_string());
+                let response = match result {
+                    Ok(result) => jsonrpc::result_response(id, result),
+                    Err(message) => jsonrpc::error_response(id, jsonrpc::failure(&message)),
+                };
+                replies.send(response);
+                continue;
             }
 
-            let rx = events.subscribe();
-            let out_tx_events = out_tx.clone();
-            events_task = Some(tokio::spawn(forward_events(rx, out_tx_events)));
+            if method == "subscribe_events" {
+                let response = match parse_request::<SubscribeEventsRequest>(&params) {
+                    Ok(request) => {
+                        let subscriptions = EventSubscriptions {
+                            workspace_ids: request.workspace_ids,
+                        };
+                        let result = json!(subscriptions);
+                        subscriptions_tx.send_replace(subscriptions);
+                        jsonrpc::result_response(id, result)
+                    }
+                    Err(error) => jsonrpc::error_response(id, RpcError::from(error).to_json()),
+                };
+                replies.send(response);
+                continue;
+            }
 
-            continue;
+            if method == "open_files" {
+                let result = match parse_request::<OpenFilesRequest>(&params) {
+                    Ok(request) => state
+                        .set_open_files(&client_id, &request.workspace_id, request.files)
+                        .await
+                        .map_err(RpcError::from),
+                    Err(error) => Err(error.into()),
+                };
+                let response = match result {
+                    Ok(files) => jsonrpc::result_response(id, json!({ "files": files })),
+                    Err(error) => jsonrpc::error_response(id, error.to_json()),
+                };
+                replies.send(response);
+                continue;
+            }
+
+            if method == "focus_file" {
+                let result = match parse_request::<FocusFileRequest>(&params) {
+                    Ok(request) => state
+                        .focus_file(&client_id, request)
+                        .await
+                        .map_err(RpcError::from),
+                    Err(error) => Err(error.into()),
+                };
+                let response = match result {
+                    Ok(result) => jsonrpc::result_response(id, result),
+                    Err(error) => jsonrpc::error_response(id, error.to_json()),
+                };
+                replies.send(response);
+                continue;
+            }
+
+            if method == "subscribe_since" {
+                let request = match parse_request::<SubscribeSinceRequest>(&params) {
+                    Ok(request) => request,
+                    Err(error) => {
+                        replies.send(jsonrpc::error_response(id, RpcError::from(error).to_json()));
+                        continue;
+                    }
+                };
+                if let Some(forwarder) = forwarder.take() {
+                    forwarder.stop().await;
+                }
+                let (missed, summary, rx) = events.subscribe_since(request.seq);
+                replies.send(jsonrpc::result_response(id, json!(summary)));
+                let schema = event_schema.load(Ordering::Relaxed);
+                let subscriptions = subscriptions_rx.borrow().clone();
+                for event in missed {
+                    if !event.event.delivered_to(&client_id, &subscriptions) {
+                        continue;
+                    }
+                    if let Some(payload) =
+                        build_event_notification(event.event, Some(event.seq), schema)
+                    {
+                        let _ = out_tx.send(payload);
+                    }
+                }
+                forwarder = Some(EventForwarder::start(
+                    rx,
+                    subscriptions_rx.clone(),
+                    Arc::clone(&event_schema),
+                    client_id.clone(),
+                    out_tx.clone(),
+                ));
+                continue;
+            }
+
+            let reply = replies.defer();
+            let state = Arc::clone(&state);
+            let client_label = client_label.clone();
+            let grant = grant.clone();
+            let id = id.cloned();
+            calls.spawn(async move {
+                let id = id.as_ref();
+                let result =
+                    serve_rpc(&state, &client_label, &grant, id, &method, params, traced).await;
+                drop(slot);
+                reply.send(match result {
+                    Ok(result) => jsonrpc::result_response(id, result),
+                    Err(error) => jsonrpc::error_response(id, error.to_json()),
+                });
+                state.publish_workspace_deltas().await;
+            });
         }
+        replies.finish();
+        // Reap finished calls so the set only holds running ones.
+        while calls.try_join_next().is_some() {}
+    }
+    // Calls still running get to answer before the connection closes.
+    while calls.join_next().await.is_some() {}
 
-        let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
-        let result = handle_rpc_request(&state, &method, params, client_version).await;
-        let response = match result {
-            Ok(result) => build_result_response(id, result),
-            Err(message) => build_error_response(id, &message),
-        };
-        if let Some(response) = response {
-            let _ = out_tx.send(response);
+    let client = state.clients.unregister(&client_id);
+    let open_files = state.open_files.take(&client_id);
+    if let Some(client) = &client {
+        if client.authenticated {
+            let event = if kicked { "kicked" } else { "disconnected" };
+            state.emit_client_presence(event, client);
+        }
+        for workspace_id in open_files.workspace_ids() {
+            let mut presence = state.open_files.presence(&client_id, &workspace_id);
+            presence.device_name = client.device_name.clone();
+            let _ = state
+                .event_sink
+                .tx
+                .send(DaemonEvent::EditorPresence(presence));
         }
     }
+    if kicked {
+        let notice = jsonrpc::notification("client-kicked", json!({ "clientId": client_id }));
+        let _ = out_tx.send(notice.to_string());
+    }
 
+    let parked_events = match forwarder {
+        Some(forwarder) => forwarder.stop().await,
+        None => None,
+    };
+    if let Some((ticket, _)) = resume_ticket {
+        match (parked_events, client) {
+            (Some(events), Some(client)) if !kicked => state.resume_tickets.park(
+                &ticket,
+                ParkedConnection {
+                    device_name: client.device_name,
+                    client_version: client.client_version,
+                    compression: compression.state(),
+                    event_schema: event_schema.load(Ordering::Relaxed),
+                    subscriptions: subscriptions_tx.borrow().clone(),
+                    grant,
+                    open_files,
+                    events,
+                },
+            ),
+            _ => state.resume_tickets.revoke(&ticket),
+        }
+    }
     drop(out_tx);
-    if let Some(task) = events_task {
-        task.abort();
+    // Let queued responses (and the kick notice) flush before the socket closes.
+    if tokio::time::timeout(std::time::Duration::from_secs(1), &mut write_task)
+        .await
+        .is_err()
+    {
+        write_task.abort();
+    }
+}
+
+async fn run_mcp_health_prober(state: Arc<DaemonState>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        shared::mcp_health_core::MCP_PROBE_INTERVAL_SECS,
+    ));
+    loop {
+        interval.tick().await;
+        state
+            .mcp_health
+            .probe_once(&state.sessions, &state.event_sink)
+            .await;
+    }
+}
+
+async fn run_auth_watcher(state: Arc<DaemonState>) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(AUTH_CHECK_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        state
+            .auth_watcher
+            .check_once(&state.workspaces, &state.sessions, &state.event_sink)
+            .await;
+    }
+}
+
+async fn run_ci_poller(state: Arc<DaemonState>) {
+    let mut poller = ci_core::CiPoller::default();
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        ci_core::CI_POLL_INTERVAL_SECS,
+    ));
+    loop {
+        interval.tick().await;
+        poller
+            .poll_once(&state.workspaces, &state.event_sink, |workspace_id, argv| {
+                state.ensure_command_allowed("ci_poll", workspace_id, argv)
+            })
+            .await;
+    }
+}
+
+async fn run_disk_monitor(state: Arc<DaemonState>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        disk_usage_core::DISK_USAGE_INTERVAL_SECS,
+    ));
+    loop {
+        interval.tick().await;
+        let quota = state.app_settings.lock().await.disk_quota.clone();
+        if let Err(err) = state
+            .disk_monitor
+            .check_once(&state.workspaces, &state.data_dir, &quota, &state.event_sink)
+            .await
+        {
+            tracing::warn!("disk usage check failed: {err}");
+        }
+    }
+}
+
+async fn run_retention(state: Arc<DaemonState>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        retention_core::RETENTION_INTERVAL_SECS,
+    ));
+    loop {
+        interval.tick().await;
+        let policy = state.app_settings.lock().await.retention.clone();
+        if policy.archive_idle_days.is_none() && policy.purge_archived_days.is_none() {
+            continue;
+        }
+        let report = state.run_retention(false).await;
+        if !report.is_empty() {
+            tracing::info!(
+                archived = report.archived.len(),
+                purged = report.purged.len(),
+                bytes_freed = report.bytes_freed,
+                "retention pass"
+            );
+        }
     }
-    write_task.abort();
 }
 
 fn main() {
-    let config = match parse_args() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if env::var_os(mock_app_server::MOCK_APP_SERVER_ENV).is_some() {
+        if let Some(code) = mock_app_server::run(&args) {
+            std::process::exit(code);
+        }
+    }
+    if args.iter().any(|arg| arg == "--self-test") {
+        if let Err(err) = self_test::run() {
+            eprintln!("self-test failed: {err}");
+            std::process::exit(1);
+        }
+        println!("self-test passed");
+        return;
+    }
+
+    let mut config = match parse_args() {
         Ok(config) => config,
         Err(err) => {
             eprintln!("{err}\n\n{}", usage());
             std::process::exit(2);
         }
     };
+    logging::init(config.log_level, config.log_format);
+    if let Err(err) = configure_process_limits(config.process_limits) {
+        tracing::error!("{err}");
+        std::process::exit(2);
+    }
 
-    let runtime = tokio::runtime::Builder::new_current_thread()
+    // Filesystem and search work goes to the blocking pool or `block_in_place`, so one
+    // client's heavy request leaves the other workers free for everyone else.
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    if let Some(threads) = config.worker_threads {
+        runtime.worker_threads(threads);
+    }
+    let runtime = runtime
         .enable_all()
         .build()
         .expect("failed to build tokio runtime");
 
     runtime.block_on(async move {
-        let (events_tx, _events_rx) = broadcast::channel::<DaemonEvent>(2048);
+        let events_tx = Arc::new(EventLog::new(REPLAY_BUFFER_EVENTS));
+        let (approval_tx, approval_rx) = mpsc::unbounded_channel::<AppServerEvent>();
+        let (image_tx, image_rx) = mpsc::unbounded_channel::<(String, Vec<ImageArtifact>)>();
+        let (write_tx, write_rx) = mpsc::unbounded_channel::<(String, AgentWrite)>();
         let event_sink = DaemonEventSink {
             tx: events_tx.clone(),
+            pending_approvals: Arc::new(PendingApprovals::load(&config.data_dir)),
+            fanout_runs: Arc::new(FanoutRuns::load(&config.data_dir)),
+            thread_usage: Arc::new(ThreadUsageStats::load(&config.data_dir)),
+            time_tracker: Arc::new(TimeTracker::load(&config.data_dir)),
+            approval_requests: approval_tx,
+            image_artifacts: image_tx,
+            agent_writes: write_tx,
+            exporter: EventExporter::start(config.exporters.clone(), config.export_headers.clone()),
         };
         let state = Arc::new(DaemonState::load(&config, event_sink));
+        // Worktrees removed outside the app would otherwise linger and fail to connect.
+        match state.reconcile_worktrees(false).await {
+            Ok(report) => {
+                for mismatch in &report.mismatches {
+                    tracing::warn!(
+                        workspace_id = %mismatch.workspace_id,
+                        name = %mismatch.name,
+                        path = %mismatch.path,
+                        issue = ?mismatch.issue,
+                        pruned = mismatch.pruned,
+                        "worktree mismatch"
+                    );
+                }
+            }
+            Err(err) => tracing::error!("failed to reconcile worktrees: {err}"),
+        }
+        state.prime_workspace_deltas().await;
+        tokio::spawn(run_approval_policy(Arc::clone(&state), approval_rx));
+        tokio::spawn(run_thumbnailer(Arc::clone(&state), image_rx));
+        tokio::spawn(run_conflict_notifier(Arc::clone(&state), write_rx));
+        tokio::spawn(run_ci_poller(Arc::clone(&state)));
+        tokio::spawn(run_mcp_health_prober(Arc::clone(&state)));
+        tokio::spawn(run_auth_watcher(Arc::clone(&state)));
+        tokio::spawn(run_retention(Arc::clone(&state)));
+        tokio::spawn(run_disk_monitor(Arc::clone(&state)));
+        let relay = config.relay.take();
         let config = Arc::new(config);
 
-        let listener = TcpListener::bind(config.listen)
-            .await
-            .unwrap_or_else(|err| panic!("failed to bind {}: {err}", config.listen));
-        eprintln!(
-            "codex-monitor-daemon listening on {} (data dir: {})",
-            config.listen,
-            state
+        let listener = match config.listen {
+            Some(addr) => Some(
+                TcpListener::bind(addr)
+                    .await
+                    .unwrap_or_else(|err| panic!("failed to bind {addr}: {err}")),
+            ),
+            None => None,
+        };
+        #[cfg(unix)]
+        let unix_listener = config.listen_unix.as_deref().map(|path| {
+            unix_socket::bind(path).unwrap_or_else(|err| panic!("failed to bind: {err}"))
+        });
+        let endpoints = config
+            .listen
+            .map(|addr| addr.to_string())
+            .into_iter()
+            .chain(config.listen_unix.as_ref().map(|path| path.display().to_string()))
+            .collect::<Vec<_>>();
+        tracing::info!(
+            data_dir = %state
                 .storage_path
                 .parent()
                 .unwrap_or(&state.storage_path)
-                .display()
+                .display(),
+            "codex-monitor-daemon {}",
+            if config.stdio {
+                "serving one client on stdio".to_string()
+            } else {
+                format!("listening on {}", endpoints.join(" and "))
+            }
         );
+        if state.rpc_trace.is_enabled() {
+            tracing::info!(path = %state.rpc_trace.path().display(), "rpc tracing enabled");
+        }
+        for exporter in &config.exporters {
+            tracing::info!(endpoint = %exporter.endpoint, kind = ?exporter.kind, "exporting events");
+        }
+        if state.command_allowlist.is_restricted() {
+            tracing::info!(
+                audit_log = %state.audit_log.path().display(),
+                "command allowlist enabled"
+            );
+        }
+
+        if let Some(relay) = relay {
+            let config = Arc::clone(&config);
+            let state = Arc::clone(&state);
+            let events = events_tx.clone();
+            tokio::spawn(relay::run(relay, move |stream, label| {
+                let (reader, writer) = tokio::io::split(stream);
+                tokio::spawn(handle_client(
+                    reader,
+                    writer,
+                    label,
+                    Arc::clone(&config),
+                    Arc::clone(&state),
+                    events.clone(),
+                ));
+            }));
+        }
+
+        if let Some(addr) = config.http_listen {
+            let listener = TcpListener::bind(addr)
+                .await
+                .unwrap_or_else(|err| panic!("failed to bind {addr}: {err}"));
+            tracing::info!("serving the REST gateway on {addr}");
+            tokio::spawn(http_gateway::serve(
+                listener,
+                config.tls.clone(),
+                config.tokens.clone(),
+                Arc::clone(&state),
+            ));
+        }
 
+        // Every listener is bound, so systemd may now consider the daemon ready.
+        tokio::spawn(Arc::clone(&state.health).run(config.data_dir.clone()));
+        if config.stdio {
+            let (stdin, stdout) = (tokio::io::stdin(), tokio::io::stdout());
+            handle_client(stdin, stdout, "stdio".to_string(), config, state, events_tx).await;
+            return;
+        }
+        #[cfg(unix)]
+        if let Some(unix_listener) = unix_listener {
+            let accept = accept_unix_clients(
+                unix_listener,
+                Arc::clone(&config),
+                Arc::clone(&state),
+                events_tx.clone(),
+            );
+            if listener.is_none() {
+                accept.await;
+                return;
+            }
+            tokio::spawn(accept);
+        }
+        let Some(listener) = listener else {
+            return;
+        };
         loop {
             match listener.accept().await {
                 Ok((socket, _addr)) => {
@@ -1413,11 +4865,13 @@ fn main() {
                     let state = Arc::clone(&state);
                     let events = events_tx.clone();
                     tokio::spawn(async move {
-                        handle_client(socket, config, state, events).await;
+                        handle_tcp_client(socket, config, state, events).await;
                     });
                 }
                 Err(_) => continue,
             }
         }
     });
+    // Don't wait on a stdin read that is still blocked after the stdio client went away.
+    runtime.shutdown_background();
 }
@@ -0,0 +1,98 @@
+use serde_json::{json, Map, Value};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const AUDIT_LOG_FILE: &str = "audit.log";
+
+/// Append-only JSON-lines log of security-relevant daemon decisions.
+pub(crate) struct AuditLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub(crate) fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(AUDIT_LOG_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records an entry; failures are reported on stderr but never block the caller.
+    pub(crate) fn record(&self, event: &str, details: Value) {
+        let line = build_audit_line(event, details, now_millis());
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(err) = append_line(&self.path, &line) {
+            tracing::error!("Failed to write audit log {}: {err}", self.path.display());
+        }
+    }
+}
+
+fn build_audit_line(event: &str, details: Value, timestamp: u64) -> String {
+    let mut entry = Map::new();
+    entry.insert("timestamp".to_string(), json!(timestamp));
+    entry.insert("event".to_string(), json!(event));
+    match details {
+        Value::Object(map) => entry.extend(map),
+        Value::Null => {}
+        other => {
+            entry.insert("details".to_string(), other);
+        }
+    }
+    Value::Object(entry).to_string()
+}
+
+fn append_line(path: &Path, line: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| err.to_string())?;
+    writeln!(file, "{line}").map_err(|err| err.to_string())
+}
+
+pub(crate) fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn audit_line_flattens_object_details() {
+        let line = build_audit_line("command_denied", json!({ "command": ["sh"] }), 42);
+        let value: Value = serde_json::from_str(&line).expect("parse line");
+        assert_eq!(value["timestamp"], json!(42));
+        assert_eq!(value["event"], json!("command_denied"));
+        assert_eq!(value["command"], json!(["sh"]));
+    }
+
+    #[test]
+    fn record_appends_lines() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-audit-{}", Uuid::new_v4()));
+        let log = AuditLog::new(&dir);
+        log.record("first", Value::Null);
+        log.record("second", json!({ "reason": "test" }));
+
+        let contents = std::fs::read_to_string(log.path()).expect("read audit log");
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("\"first\""));
+        assert!(lines[1].contains("\"reason\":\"test\""));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,427 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::timeout;
+
+use crate::shared::build_core::{output_tail, BuildReport};
+use crate::shared::problems_core::ProblemSeverity;
+use crate::shared::process_core::tokio_command;
+use crate::shared::process_limits_core::{acquire_process_slot, ProcessKind};
+
+pub(crate) const DEFAULT_MAX_ITERATIONS: u32 = 5;
+const MAX_ITERATIONS_LIMIT: u32 = 20;
+/// Tokens the fix thread may spend in total, as counted by `thread/tokenUsage/updated`.
+pub(crate) const DEFAULT_MAX_TOKENS: u64 = 2_000_000;
+/// How long one fix turn may take, waiting on approvals included.
+pub(crate) const TURN_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const TEST_TIMEOUT: Duration = Duration::from_secs(900);
+const MAX_PROMPT_PROBLEMS: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AutoFixLimits {
+    pub(crate) max_iterations: u32,
+    pub(crate) max_tokens: u64,
+}
+
+impl AutoFixLimits {
+    pub(crate) fn new(max_iterations: Option<u32>, max_tokens: Option<u64>) -> Self {
+        Self {
+            max_iterations: max_iterations
+                .unwrap_or(DEFAULT_MAX_ITERATIONS)
+                .clamp(1, MAX_ITERATIONS_LIMIT),
+            max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS).max(1),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum AutoFixOutcome {
+    /// The checks pass.
+    Fixed,
+    /// Every allowed fix attempt ran and the checks still fail.
+    MaxIterations,
+    /// The fix thread used up its token budget.
+    BudgetExhausted,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TestRun {
+    pub(crate) command: String,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) success: bool,
+    pub(crate) duration_ms: u64,
+    /// Set when the command couldn't run or timed out.
+    pub(crate) error: Option<String>,
+    pub(crate) output_tail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AutoFixIteration {
+    /// 1-based fix attempt.
+    pub(crate) iteration: u32,
+    /// What the attempt was asked to fix.
+    pub(crate) error_count: usize,
+    pub(crate) tests_failed: bool,
+    pub(crate) turn_id: Option<String>,
+    /// Thread total once the turn finished.
+    pub(crate) tokens_used: u64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AutoFixReport {
+    pub(crate) workspace_id: String,
+    /// The thread the fixes were made on; `None` when the checks passed right away.
+    pub(crate) thread_id: Option<String>,
+    pub(crate) outcome: AutoFixOutcome,
+    pub(crate) iterations: Vec<AutoFixIteration>,
+    pub(crate) tokens_used: u64,
+    /// The last check run.
+    pub(crate) build: BuildReport,
+    pub(crate) tests: Option<TestRun>,
+}
+
+/// What an app-server notification means for the fix turn running on `thread_id`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TurnSignal {
+    Completed { error: Option<String> },
+    TokensUsed(u64),
+}
+
+pub(crate) fn turn_signal(message: &Value, thread_id: &str) -> Option<TurnSignal> {
+    let params = message.get("params")?;
+    if params.get("threadId").and_then(Value::as_str) != Some(thread_id) {
+        return None;
+    }
+    match message.get("method").and_then(Value::as_str)? {
+        "turn/completed" => {
+            let turn = params.get("turn").unwrap_or(&Value::Null);
+            let error = (turn.get("status").and_then(Value::as_str) == Some("failed")).then(|| {
+                turn.get("error")
+                    .and_then(|error| error.get("message"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("The fix turn failed")
+                    .to_string()
+            });
+            Some(TurnSignal::Completed { error })
+        }
+        "thread/tokenUsage/updated" => {
+            let total = params.get("tokenUsage")?.get("total")?;
+            total
+                .get("totalTokens")
+                .or_else(|| total.get("total_tokens"))
+                .and_then(Value::as_u64)
+                .map(TurnSignal::TokensUsed)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the build and, when configured, the tests are green.
+pub(crate) fn checks_pass(build: &BuildReport, tests: Option<&TestRun>) -> bool {
+    build.success && tests.is_none_or(|tests| tests.success)
+}
+
+/// A check the agent can't fix by editing code: a missing tool, a refused or
+/// timed-out command.
+pub(crate) fn check_failure(build: &BuildReport, tests: Option<&TestRun>) -> Option<String> {
+    build
+        .runs
+        .iter()
+        .find_map(|run| {
+            run.error
+                .as_ref()
+                .map(|error| format!("`{}`: {error}", run.command))
+        })
+        .or_else(|| {
+            tests.and_then(|tests| {
+                tests
+                    .error
+                    .as_ref()
+                    .map(|error| format!("`{}`: {error}", tests.command))
+            })
+        })
+}
+
+/// The message sent to the agent for one fix attempt: the goal, the errors the
+/// checks found and the raw output of failures no parser understood.
+pub(crate) fn fix_prompt(
+    goal: Option<&str>,
+    iteration: u32,
+    build: &BuildReport,
+    tests: Option<&TestRun>,
+) -> String {
+    let mut prompt = String::new();
+    let goal = goal.map(str::trim).filter(|goal| !goal.is_empty());
+    prompt.push_str(goal.unwrap_or("Make the build and tests pass."));
+    let errors = build
+        .problems
+        .iter()
+        .filter(|problem| problem.severity == ProblemSeverity::Error)
+        .collect::<Vec<_>>();
+    let _ = write!(
+        prompt,
+        "\n\nAutomated check run {iteration}: the build reports {} error(s)",
+        errors.len()
+    );
+    if tests.is_some_and(|tests| !tests.success) {
+        prompt.push_str(" and the tests fail");
+    }
+    prompt.push_str(
+        ". Fix the underlying causes with minimal changes; don't disable checks or tests. \
+         The checks run again after your turn.",
+    );
+
+    if !errors.is_empty() {
+        prompt.push_str("\n\nErrors:");
+        for problem in errors.iter().take(MAX_PROMPT_PROBLEMS) {
+            let _ = write!(prompt, "\n- {}:{}", problem.path, problem.line);
+            if let Some(column) = problem.column {
+                let _ = write!(prompt, ":{column}");
+            }
+            if let Some(code) = &problem.code {
+                let _ = write!(prompt, " [{code}]");
+            }
+            let _ = write!(prompt, " ({}) {}", problem.source, problem.message);
+        }
+        if errors.len() > MAX_PROMPT_PROBLEMS {
+            let _ = write!(
+                prompt,
+                "\n- …and {} more",
+                errors.len() - MAX_PROMPT_PROBLEMS
+            );
+        }
+    }
+    for run in &build.runs {
+        if run.success || run.error_count > 0 {
+            continue;
+        }
+        if let Some(tail) = &run.output_tail {
+            let _ = write!(prompt, "\n\nOutput of `{}`:\n```\n{tail}\n```", run.command);
+        }
+    }
+    if let Some(tests) = tests.filter(|tests| !tests.success) {
+        if let Some(tail) = &tests.output_tail {
+            let _ = write!(
+                prompt,
+                "\n\nOutput of `{}`:\n```\n{tail}\n```",
+                tests.command
+            );
+        }
+    }
+    prompt
+}
+
+/// Runs the test command at the workspace root; only the exit status and the end
+/// of the output are kept.
+pub(crate) async fn run_tests(root: &Path, argv: &[String]) -> TestRun {
+    let started = Instant::now();
+    let command = argv.join(" ");
+    let mut run = TestRun {
+        command: command.clone(),
+        exit_code: None,
+        success: false,
+        duration_ms: 0,
+        error: None,
+        output_tail: None,
+    };
+    let Some((program, args)) = argv.split_first() else {
+        run.error = Some("Test command is empty".to_string());
+        return run;
+    };
+    let output = match acquire_process_slot(ProcessKind::Tool).await {
+        Ok(_slot) => timeout(
+            TEST_TIMEOUT,
+            tokio_command(program)
+                .args(args)
+                .current_dir(root)
+                .env("NO_COLOR", "1")
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await
+        .map_err(|_| format!("{command} timed out"))
+        .and_then(|output| output.map_err(|err| format!("Failed to run {command}: {err}"))),
+        Err(err) => Err(err),
+    };
+    run.duration_ms = started.elapsed().as_millis() as u64;
+    match output {
+        Ok(output) => {
+            run.exit_code = output.status.code();
+            run.success = output.status.success();
+            if !run.success {
+                let text = format!(
+                    "{}\n{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                run.output_tail = Some(output_tail(text.trim()));
+            }
+        }
+        Err(err) => run.error = Some(err),
+    }
+    run
+}
+
+/// Runs `on_drop` unless disarmed; interrupts a fix turn whose job was cancelled.
+pub(crate) struct DropGuard<F: FnOnce()> {
+    on_drop: Option<F>,
+}
+
+impl<F: FnOnce()> DropGuard<F> {
+    pub(crate) fn new(on_drop: F) -> Self {
+        Self {
+            on_drop: Some(on_drop),
+        }
+    }
+
+    pub(crate) fn disarm(mut self) {
+        self.on_drop = None;
+    }
+}
+
+impl<F: FnOnce()> Drop for DropGuard<F> {
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.on_drop.take() {
+            on_drop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::build_core::{BuildRun, BuildTool};
+    use crate::shared::problems_core::Problem;
+    use serde_json::json;
+
+    fn report(problems: Vec<Problem>, output_tail: Option<&str>) -> BuildReport {
+        let error_count = problems
+            .iter()
+            .filter(|problem| problem.severity == ProblemSeverity::Error)
+            .count();
+        BuildReport {
+            workspace_id: "ws".to_string(),
+            success: false,
+            runs: vec![BuildRun {
+                tool: BuildTool::Cargo,
+                command: "cargo check".to_string(),
+                cwd: String::new(),
+                exit_code: Some(101),
+                success: false,
+                duration_ms: 10,
+                error_count,
+                warning_count: 0,
+                error: None,
+                output_tail: output_tail.map(str::to_string),
+            }],
+            problems,
+        }
+    }
+
+    #[test]
+    fn prompts_with_errors_and_unparsed_output() {
+        let problem = |severity, message: &str| Problem {
+            source: "cargo".to_string(),
+            path: "src/main.rs".to_string(),
+            line: 3,
+            column: Some(7),
+            severity,
+            code: Some("E0308".to_string()),
+            message: message.to_string(),
+        };
+        let build = report(
+            vec![
+                problem(ProblemSeverity::Error, "mismatched types"),
+                problem(ProblemSeverity::Warning, "unused variable"),
+            ],
+            Some("error: could not compile"),
+        );
+        let prompt = fix_prompt(Some("  Fix the parser  "), 2, &build, None);
+        assert!(prompt.starts_with("Fix the parser\n\nAutomated check run 2"));
+        assert!(prompt.contains("- src/main.rs:3:7 [E0308] (cargo) mismatched types"));
+        assert!(!prompt.contains("unused variable"));
+        // The output is only repeated when no problem was parsed from it.
+        assert!(!prompt.contains("could not compile"));
+
+        let tests = TestRun {
+            command: "npm test".to_string(),
+            exit_code: Some(1),
+            success: false,
+            duration_ms: 5,
+            error: None,
+            output_tail: Some("1 failing".to_string()),
+        };
+        let prompt = fix_prompt(
+            None,
+            1,
+            &report(Vec::new(), Some("linker failed")),
+            Some(&tests),
+        );
+        assert!(prompt.starts_with("Make the build and tests pass."));
+        assert!(prompt.contains("0 error(s) and the tests fail"));
+        assert!(prompt.contains("Output of `cargo check`:\n```\nlinker failed\n```"));
+        assert!(prompt.contains("Output of `npm test`:\n```\n1 failing\n```"));
+        assert!(!checks_pass(&build, None));
+        assert_eq!(check_failure(&build, Some(&tests)), None);
+    }
+
+    #[test]
+    fn reads_turn_signals_for_the_fix_thread() {
+        assert_eq!(
+            turn_signal(
+                &json!({
+                    "method": "thread/tokenUsage/updated",
+                    "params": { "threadId": "t1", "tokenUsage": { "total": { "totalTokens": 1200 } } }
+                }),
+                "t1"
+            ),
+            Some(TurnSignal::TokensUsed(1200))
+        );
+        assert_eq!(
+            turn_signal(
+                &json!({
+                    "method": "turn/completed",
+                    "params": { "threadId": "t1", "turn": { "id": "turn-1", "status": "completed" } }
+                }),
+                "t1"
+            ),
+            Some(TurnSignal::Completed { error: None })
+        );
+        assert_eq!(
+            turn_signal(
+                &json!({
+                    "method": "turn/completed",
+                    "params": {
+                        "threadId": "t1",
+                        "turn": { "status": "failed", "error": { "message": "usage limit" } }
+                    }
+                }),
+                "t1"
+            ),
+            Some(TurnSignal::Completed {
+                error: Some("usage limit".to_string())
+            })
+        );
+        assert_eq!(
+            turn_signal(
+                &json!({ "method": "turn/completed", "params": { "threadId": "t2" } }),
+                "t1"
+            ),
+            None
+        );
+        assert_eq!(
+            AutoFixLimits::new(Some(0), None),
+            AutoFixLimits {
+                max_iterations: 1,
+                max_tokens: DEFAULT_MAX_TOKENS
+            }
+        );
+    }
+}
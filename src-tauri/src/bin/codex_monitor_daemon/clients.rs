@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::audit::now_millis;
+
+/// Snapshot of one connected client, as reported by `list_clients` and presence events.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ClientInfo {
+    pub(crate) id: String,
+    pub(crate) device_name: Option<String>,
+    pub(crate) client_version: Option<String>,
+    pub(crate) remote_addr: String,
+    pub(crate) authenticated: bool,
+    pub(crate) connected_at: u64,
+    pub(crate) last_activity_at: u64,
+}
+
+struct ClientEntry {
+    info: ClientInfo,
+    kick: Arc<Notify>,
+}
+
+/// Connected clients keyed by a per-connection id.
+#[derive(Default)]
+pub(crate) struct ClientRegistry {
+    clients: Mutex<HashMap<String, ClientEntry>>,
+}
+
+impl ClientRegistry {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ClientEntry>> {
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Registers a new connection and returns its id plus the handle that fires on kick.
+    pub(crate) fn register(&self, remote_addr: &str) -> (String, Arc<Notify>) {
+        let id = Uuid::new_v4().to_string();
+        let now = now_millis();
+        let kick = Arc::new(Notify::new());
+        self.lock().insert(
+            id.clone(),
+            ClientEntry {
+                info: ClientInfo {
+                    id: id.clone(),
+                    device_name: None,
+                    client_version: None,
+                    remote_addr: remote_addr.to_string(),
+                    authenticated: false,
+                    connected_at: now,
+                    last_activity_at: now,
+                },
+                kick: Arc::clone(&kick),
+            },
+        );
+        (id, kick)
+    }
+
+    /// Marks the client authenticated and stores what it reported about itself.
+    pub(crate) fn identify(
+        &self,
+        id: &str,
+        device_name: Option<String>,
+        client_version: Option<String>,
+    ) -> Option<ClientInfo> {
+        let mut clients = self.lock();
+        let entry = clients.get_mut(id)?;
+        entry.info.authenticated = true;
+        if device_name.is_some() {
+            entry.info.device_name = device_name;
+        }
+        if client_version.is_some() {
+            entry.info.client_version = client_version;
+        }
+        entry.info.last_activity_at = now_millis();
+        Some(entry.info.clone())
+    }
+
+    pub(crate) fn touch(&self, id: &str) {
+        if let Some(entry) = self.lock().get_mut(id) {
+            entry.info.last_activity_at = now_millis();
+        }
+    }
+
+    pub(crate) fn unregister(&self, id: &str) -> Option<ClientInfo> {
+        self.lock().remove(id).map(|entry| entry.info)
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<ClientInfo> {
+        self.lock().get(id).map(|entry| entry.info.clone())
+    }
+
+    /// Authenticated clients, oldest connection first.
+    pub(crate) fn list(&self) -> Vec<ClientInfo> {
+        let mut clients = self
+            .lock()
+            .values()
+            .filter(|entry| entry.info.authenticated)
+            .map(|entry| entry.info.clone())
+            .collect::<Vec<_>>();
+        clients.sort_by(|a, b| a.connected_at.cmp(&b.connected_at).then(a.id.cmp(&b.id)));
+        clients
+    }
+
+    /// Asks the connection to close; returns the client that was kicked.
+    pub(crate) fn kick(&self, id: &str) -> Option<ClientInfo> {
+        let clients = self.lock();
+        let entry = clients.get(id)?;
+        entry.kick.notify_one();
+        Some(entry.info.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_only_identified_clients() {
+        let registry = ClientRegistry::default();
+        let (laptop, _) = registry.register("127.0.0.1:5000");
+        let (pending, _) = registry.register("127.0.0.1:5001");
+        let info = registry
+            .identify(&laptop, Some("laptop".to_string()), Some("0.7.0".to_string()))
+            .expect("identify");
+        assert!(info.authenticated);
+
+        let listed = registry.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].device_name.as_deref(), Some("laptop"));
+
+        assert!(registry.unregister(&pending).is_some());
+        assert!(registry.unregister(&pending).is_none());
+    }
+
+    #[test]
+    fn kick_notifies_the_connection() {
+        let registry = ClientRegistry::default();
+        let (id, kick) = registry.register("127.0.0.1:5000");
+        assert!(registry.kick("missing").is_none());
+        assert!(registry.kick(&id).is_some());
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            tokio::time::timeout(std::time::Duration::from_secs(1), kick.notified())
+                .await
+                .expect("kick delivered");
+        });
+    }
+}
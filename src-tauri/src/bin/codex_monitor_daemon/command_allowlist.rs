@@ -0,0 +1,78 @@
+
+/// Command prefixes the daemon is allowed to spawn on behalf of clients.
+///
+/// Each prefix is a shell-style token list (e.g. `codex` or `/usr/local/bin/codex --profile ci`)
+/// and matches any argv that starts with the same tokens. An empty allowlist permits everything,
+/// which keeps the historical behavior for daemons started without `--allow-command`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct CommandAllowlist {
+    prefixes: Vec<Vec<String>>,
+}
+
+impl CommandAllowlist {
+    pub(crate) fn parse(entries: &[String]) -> Result<Self, String> {
+        let mut prefixes = Vec::new();
+        for entry in entries {
+            let tokens = shell_words::split(entry)
+                .map_err(|err| format!("Invalid --allow-command `{entry}`: {err}"))?;
+            if tokens.is_empty() {
+                return Err("--allow-command requires a non-empty value".to_string());
+            }
+            prefixes.push(tokens);
+        }
+        Ok(Self { prefixes })
+    }
+
+    pub(crate) fn is_restricted(&self) -> bool {
+        !self.prefixes.is_empty()
+    }
+
+    pub(crate) fn allows(&self, argv: &[String]) -> bool {
+        if !self.is_restricted() {
+            return true;
+        }
+        self.prefixes
+            .iter()
+            .any(|prefix| argv.len() >= prefix.len() && argv[..prefix.len()] == prefix[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn empty_allowlist_allows_everything() {
+        let allowlist = CommandAllowlist::default();
+        assert!(!allowlist.is_restricted());
+        assert!(allowlist.allows(&argv(&["/bin/sh", "-c", "true"])));
+    }
+
+    #[test]
+    fn prefixes_match_leading_tokens_only() {
+        let allowlist = CommandAllowlist::parse(&[
+            "codex".to_string(),
+            "'/opt/codex bin/codex' --profile ci".to_string(),
+        ])
+        .expect("parse allowlist");
+        assert!(allowlist.allows(&argv(&["codex", "app-server"])));
+        assert!(allowlist.allows(&argv(&[
+            "/opt/codex bin/codex",
+            "--profile",
+            "ci",
+            "app-server"
+        ])));
+        assert!(!allowlist.allows(&argv(&["/opt/codex bin/codex", "app-server"])));
+        assert!(!allowlist.allows(&argv(&["codex-evil", "app-server"])));
+        assert!(!allowlist.allows(&argv(&["/tmp/codex", "app-server"])));
+    }
+
+    #[test]
+    fn parse_rejects_empty_entries() {
+        assert!(CommandAllowlist::parse(&["  ".to_string()]).is_err());
+    }
+}
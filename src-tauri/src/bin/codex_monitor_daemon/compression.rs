@@ -0,0 +1,137 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use flate2::write::GzEncoder;
+use serde_json::{json, Value};
+
+/// Encodings the daemon can produce, in its own order of preference.
+const SUPPORTED_ENCODINGS: &[&str] = &["gzip"];
+/// Messages shorter than this are sent as-is; below it gzip rarely pays for the base64.
+const DEFAULT_MIN_BYTES: usize = 32 * 1024;
+const MIN_BYTES_FLOOR: usize = 1024;
+
+/// What a connection negotiated, carried over when a client resumes it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CompressionState {
+    gzip: bool,
+    min_bytes: usize,
+}
+
+impl CompressionState {
+    pub(crate) fn encoding(self) -> Option<&'static str> {
+        self.gzip.then_some("gzip")
+    }
+}
+
+/// Per-connection outgoing compression, negotiated in `auth` or `identify_client`.
+pub(crate) struct WireCompression {
+    gzip: AtomicBool,
+    min_bytes: AtomicUsize,
+}
+
+impl Default for WireCompression {
+    fn default() -> Self {
+        Self {
+            gzip: AtomicBool::new(false),
+            min_bytes: AtomicUsize::new(DEFAULT_MIN_BYTES),
+        }
+    }
+}
+
+impl WireCompression {
+    /// Picks the first of the client's `compression` encodings the daemon supports and
+    /// applies `compressionMinBytes`. Returns the chosen encoding, `None` to send plain.
+    pub(crate) fn negotiate(&self, params: &Value) -> Option<&'static str> {
+        let chosen = params
+            .get("compression")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .find_map(|offered| {
+                SUPPORTED_ENCODINGS
+                    .iter()
+                    .copied()
+                    .find(|supported| offered.eq_ignore_ascii_case(supported))
+            });
+        if let Some(min_bytes) = params.get("compressionMinBytes").and_then(Value::as_u64) {
+            let min_bytes = usize::try_from(min_bytes).unwrap_or(usize::MAX);
+            self.min_bytes
+                .store(min_bytes.max(MIN_BYTES_FLOOR), Ordering::Relaxed);
+        }
+        self.gzip.store(chosen.is_some(), Ordering::Relaxed);
+        chosen
+    }
+
+    pub(crate) fn state(&self) -> CompressionState {
+        CompressionState {
+            gzip: self.gzip.load(Ordering::Relaxed),
+            min_bytes: self.min_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn restore(&self, state: CompressionState) {
+        self.gzip.store(state.gzip, Ordering::Relaxed);
+        self.min_bytes.store(state.min_bytes, Ordering::Relaxed);
+    }
+
+    /// Wraps a large outgoing line as `{"compressed":"gzip","data":"<base64>"}`; other
+    /// lines, and lines that wouldn't get smaller, pass through unchanged.
+    pub(crate) fn encode(&self, line: String) -> String {
+        if !self.gzip.load(Ordering::Relaxed) || line.len() < self.min_bytes.load(Ordering::Relaxed)
+        {
+            return line;
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let Ok(compressed) = encoder
+            .write_all(line.as_bytes())
+            .and_then(|()| encoder.finish())
+        else {
+            return line;
+        };
+        let data = STANDARD.encode(compressed);
+        if data.len() >= line.len() {
+            return line;
+        }
+        json!({ "compressed": "gzip", "data": data }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn compresses_large_lines_after_negotiation() {
+        let compression = WireCompression::default();
+        let files = (0..5_000)
+            .map(|index| format!("src/module_{index}/mod.rs"))
+            .collect::<Vec<_>>();
+        let line = json!({ "id": 7, "result": files }).to_string();
+        assert_eq!(compression.encode(line.clone()), line);
+
+        let chosen = compression.negotiate(&json!({ "compression": ["zstd", "GZIP"] }));
+        assert_eq!(chosen, Some("gzip"));
+        let small = json!({ "id": 8, "result": "ok" }).to_string();
+        assert_eq!(compression.encode(small.clone()), small);
+
+        let envelope: Value = serde_json::from_str(&compression.encode(line.clone())).unwrap();
+        assert_eq!(envelope["compressed"], json!("gzip"));
+        let compressed = STANDARD.decode(envelope["data"].as_str().unwrap()).unwrap();
+        assert!(compressed.len() < line.len() / 4);
+        let mut decoded = String::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, line);
+
+        assert_eq!(
+            compression.negotiate(&json!({ "compression": ["br"] })),
+            None
+        );
+        assert_eq!(compression.encode(line.clone()), line);
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Looked up in the data dir when no `--config` is given.
+pub(crate) const CONFIG_FILE_NAME: &str = "fridex-daemon.toml";
+pub(crate) const CONFIG_ENV: &str = "CODEX_MONITOR_DAEMON_CONFIG";
+
+/// `fridex-daemon.toml`. Keys are the command-line flags without their `--`; each one
+/// turns back into its flag, so the file is validated exactly like the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct ConfigFile {
+    listen: Option<String>,
+    listen_unix: Option<PathBuf>,
+    http_listen: Option<String>,
+    data_dir: Option<PathBuf>,
+    token_file: Option<PathBuf>,
+    #[serde(default)]
+    insecure_no_auth: bool,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    log_level: Option<String>,
+    log_format: Option<String>,
+    #[serde(default)]
+    allow_command: Vec<String>,
+    #[serde(default)]
+    trace_rpc: bool,
+    #[serde(default)]
+    export_otlp: Vec<String>,
+    #[serde(default)]
+    export_http: Vec<String>,
+    #[serde(default)]
+    export_header: BTreeMap<String, String>,
+    relay: Option<String>,
+    relay_id: Option<String>,
+    #[serde(default)]
+    limits: LimitsSection,
+}
+
+/// `[limits]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct LimitsSection {
+    /// Kind to cap, e.g. `{ session = 16, git = 8 }`.
+    #[serde(default)]
+    max_processes: BTreeMap<String, u64>,
+    process_queue_timeout: Option<u64>,
+    /// Method to seconds, e.g. `{ "*" = 60, run_build = 3600 }`.
+    #[serde(default)]
+    rpc_timeout: BTreeMap<String, u64>,
+    slow_rpc_ms: Option<u64>,
+    rate_limit: Option<u32>,
+    max_in_flight: Option<u64>,
+    worker_threads: Option<u64>,
+}
+
+impl ConfigFile {
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+
+    /// The command-line arguments equivalent to this file.
+    pub(crate) fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut push = |flag: &str, value: Option<String>| {
+            args.push(format!("--{flag}"));
+            args.extend(value);
+        };
+        let path = |path: &Path| path.display().to_string();
+        if let Some(value) = &self.listen {
+            push("listen", Some(value.clone()));
+        }
+        if let Some(value) = &self.listen_unix {
+            push("listen-unix", Some(path(value)));
+        }
+        if let Some(value) = &self.http_listen {
+            push("http-listen", Some(value.clone()));
+        }
+        if let Some(value) = &self.data_dir {
+            push("data-dir", Some(path(value)));
+        }
+        if let Some(value) = &self.token_file {
+            push("token-file", Some(path(value)));
+        }
+        if self.insecure_no_auth {
+            push("insecure-no-auth", None);
+        }
+        if let Some(value) = &self.tls_cert {
+            push("tls-cert", Some(path(value)));
+        }
+        if let Some(value) = &self.tls_key {
+            push("tls-key", Some(path(value)));
+        }
+        if let Some(value) = &self.log_level {
+            push("log-level", Some(value.clone()));
+        }
+        if let Some(value) = &self.log_format {
+            push("log-format", Some(value.clone()));
+        }
+        for value in &self.allow_command {
+            push("allow-command", Some(value.clone()));
+        }
+        if self.trace_rpc {
+            push("trace-rpc", None);
+        }
+        for value in &self.export_otlp {
+            push("export-otlp", Some(value.clone()));
+        }
+        for value in &self.export_http {
+            push("export-http", Some(value.clone()));
+        }
+        for (name, value) in &self.export_header {
+            push("export-header", Some(format!("{name}={value}")));
+        }
+        if let Some(value) = &self.relay {
+            push("relay", Some(value.clone()));
+        }
+        if let Some(value) = &self.relay_id {
+            push("relay-id", Some(value.clone()));
+        }
+        let limits = &self.limits;
+        for (kind, max) in &limits.max_processes {
+            push("max-processes", Some(format!("{kind}={max}")));
+        }
+        if let Some(value) = limits.process_queue_timeout {
+            push("process-queue-timeout", Some(value.to_string()));
+        }
+        for (method, secs) in &limits.rpc_timeout {
+            push("rpc-timeout", Some(format!("{method}={secs}")));
+        }
+        if let Some(value) = limits.slow_rpc_ms {
+            push("slow-rpc-ms", Some(value.to_string()));
+        }
+        if let Some(value) = limits.rate_limit {
+            push("rate-limit", Some(value.to_string()));
+        }
+        if let Some(value) = limits.max_in_flight {
+            push("max-in-flight", Some(value.to_string()));
+        }
+        if let Some(value) = limits.worker_threads {
+            push("worker-threads", Some(value.to_string()));
+        }
+        args
+    }
+}
+
+/// Flags that take no value, so the next argument starts a new flag.
+const SWITCHES: &[&str] = &["--insecure-no-auth", "--trace-rpc", "--stdio"];
+
+/// Groups arguments into each flag followed by its value, if it takes one.
+fn flag_groups(args: &[String]) -> Vec<&[String]> {
+    let mut groups = Vec::new();
+    let mut index = 0;
+    while index < args.len() {
+        let takes_value =
+            args[index].starts_with("--") && !SWITCHES.contains(&args[index].as_str());
+        let end = (index + 1 + usize::from(takes_value)).min(args.len());
+        groups.push(&args[index..end]);
+        index = end;
+    }
+    groups
+}
+
+/// Removes `--config <path>` from `cli`, returning the path when given.
+fn take_config_flag(cli: &mut Vec<String>) -> Result<Option<PathBuf>, String> {
+    let Some(index) = cli.iter().position(|arg| arg == "--config") else {
+        return Ok(None);
+    };
+    if index + 1 >= cli.len() {
+        return Err("--config requires a value".to_string());
+    }
+    let value = cli.remove(index + 1);
+    cli.remove(index);
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err("--config requires a non-empty value".to_string());
+    }
+    Ok(Some(PathBuf::from(trimmed)))
+}
+
+/// File arguments first, then the command line's; a flag given on the command line
+/// replaces every value the file had for it, repeatable flags included.
+pub(crate) fn merge_args(file: &[String], cli: &[String]) -> Vec<String> {
+    let cli_groups = flag_groups(cli);
+    let overridden = |flag: &str| cli_groups.iter().any(|group| group[0] == flag);
+    flag_groups(file)
+        .into_iter()
+        .filter(|group| !overridden(&group[0]))
+        .chain(cli_groups.iter().copied())
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// The arguments to run with: `cli` on top of the config file, which is `--config`,
+/// else `$CODEX_MONITOR_DAEMON_CONFIG`, else `fridex-daemon.toml` in the data dir when it
+/// exists there. A file named explicitly must exist.
+pub(crate) fn resolve_args(
+    mut cli: Vec<String>,
+    env_path: Option<PathBuf>,
+    default_data_dir: impl FnOnce() -> PathBuf,
+) -> Result<Vec<String>, String> {
+    let explicit = take_config_flag(&mut cli)?.or(env_path);
+    let path = match explicit {
+        Some(path) => path,
+        None => {
+            let data_dir = flag_groups(&cli)
+                .into_iter()
+                .rev()
+                .find(|group| group[0] == "--data-dir" && group.len() == 2)
+                .map(|group| PathBuf::from(group[1].trim()))
+                .unwrap_or_else(default_data_dir);
+            let path = data_dir.join(CONFIG_FILE_NAME);
+            if !path.is_file() {
+                return Ok(cli);
+            }
+            path
+        }
+    };
+    let text = std::fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    let file = ConfigFile::parse(&text)
+        .map_err(|err| format!("Invalid config file {}: {err}", path.display()))?;
+    Ok(merge_args(&file.to_args(), &cli))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn file_becomes_flags() {
+        let file = ConfigFile::parse(
+            r#"
+listen = "0.0.0.0:4732"
+token-file = "/etc/fridex/tokens.json"
+data-dir = "/var/lib/fridex"
+log-level = "warn"
+log-format = "json"
+trace-rpc = true
+allow-command = ["git", "cargo"]
+
+[limits]
+max-processes = { session = 8 }
+rpc-timeout = { "*" = 60, run_build = 3600 }
+rate-limit = 20
+"#,
+        )
+        .expect("parse");
+        assert_eq!(
+            file.to_args(),
+            args(&[
+                "--listen",
+                "0.0.0.0:4732",
+                "--data-dir",
+                "/var/lib/fridex",
+                "--token-file",
+                "/etc/fridex/tokens.json",
+                "--log-level",
+                "warn",
+                "--log-format",
+                "json",
+                "--allow-command",
+                "git",
+                "--allow-command",
+                "cargo",
+                "--trace-rpc",
+                "--max-processes",
+                "session=8",
+                "--rpc-timeout",
+                "*=60",
+                "--rpc-timeout",
+                "run_build=3600",
+                "--rate-limit",
+                "20",
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let error = ConfigFile::parse("listn = \"0.0.0.0:1\"").expect_err("unknown key");
+        assert!(error.contains("listn"));
+        assert!(ConfigFile::parse("[limits]\nmax-procs = {}").is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_file_values() {
+        let file = args(&[
+            "--listen",
+            "0.0.0.0:4732",
+            "--trace-rpc",
+            "--allow-command",
+            "git",
+            "--allow-command",
+            "cargo",
+        ]);
+        let cli = args(&["--allow-command", "npm", "--listen", "127.0.0.1:1"]);
+        assert_eq!(
+            merge_args(&file, &cli),
+            args(&[
+                "--trace-rpc",
+                "--allow-command",
+                "npm",
+                "--listen",
+                "127.0.0.1:1",
+            ])
+        );
+    }
+
+    #[test]
+    fn loads_the_file_from_the_data_dir() {
+        let dir = std::env::temp_dir().join(format!("fridex-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let cli = args(&["--data-dir", dir.to_str().expect("utf-8"), "--stdio"]);
+        let unchanged = resolve_args(cli.clone(), None, || unreachable!()).expect("no file");
+        assert_eq!(unchanged, cli);
+
+        std::fs::write(dir.join(CONFIG_FILE_NAME), "rate-limit = 5\n").expect("write");
+        assert!(resolve_args(cli.clone(), None, || unreachable!()).is_err());
+        std::fs::write(dir.join(CONFIG_FILE_NAME), "[limits]\nrate-limit = 5\n").expect("write");
+        let resolved = resolve_args(cli.clone(), None, || unreachable!()).expect("file");
+        assert_eq!(resolved[..2], args(&["--rate-limit", "5"])[..]);
+
+        let missing = resolve_args(
+            args(&[
+                "--config",
+                dir.join("missing.toml").to_str().expect("utf-8"),
+            ]),
+            None,
+            || unreachable!(),
+        );
+        assert!(missing.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
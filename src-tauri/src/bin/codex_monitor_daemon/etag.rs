@@ -0,0 +1,92 @@
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Heavy read RPCs that polling clients can make conditional with `ifNoneMatch`.
+const CONDITIONAL_METHODS: &[&str] = &[
+    "read_workspace_file",
+    "list_workspace_files",
+    "get_app_settings",
+];
+
+/// Removes `ifNoneMatch` from a conditional method's params before they are parsed.
+/// `Some(None)` means the client opted in without an etag yet (`ifNoneMatch: null`).
+pub(crate) fn take_if_none_match(
+    method: &str,
+    params: &mut Value,
+) -> Result<Option<Option<String>>, String> {
+    if !CONDITIONAL_METHODS.contains(&method) {
+        return Ok(None);
+    }
+    let Some(value) = params
+        .as_object_mut()
+        .and_then(|params| params.remove("ifNoneMatch"))
+    else {
+        return Ok(None);
+    };
+    match value {
+        Value::Null => Ok(Some(None)),
+        Value::String(etag) => Ok(Some(Some(etag))),
+        _ => Err("ifNoneMatch must be a string or null".to_string()),
+    }
+}
+
+/// A strong validator for a response: the first 128 bits of the SHA-256 of its JSON.
+pub(crate) fn etag_of(value: &Value) -> String {
+    Sha256::digest(value.to_string().as_bytes())
+        .iter()
+        .take(16)
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Wraps a conditional response as `{ etag, notModified, value }`, leaving `value` out
+/// when the client's etag still matches.
+pub(crate) fn conditional_response(value: Value, if_none_match: Option<&str>) -> Value {
+    let etag = etag_of(&value);
+    if if_none_match == Some(etag.as_str()) {
+        return json!({ "etag": etag, "notModified": true });
+    }
+    json!({ "etag": etag, "notModified": false, "value": value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_conditional_methods_take_if_none_match() {
+        let mut params = json!({ "workspaceId": "ws-1", "ifNoneMatch": "abc" });
+        assert_eq!(
+            take_if_none_match("list_workspace_files", &mut params),
+            Ok(Some(Some("abc".to_string())))
+        );
+        assert_eq!(params, json!({ "workspaceId": "ws-1" }));
+
+        let mut first_poll = json!({ "ifNoneMatch": null });
+        assert_eq!(
+            take_if_none_match("get_app_settings", &mut first_poll),
+            Ok(Some(None))
+        );
+        let mut other = json!({ "workspaceId": "ws-1", "ifNoneMatch": "abc" });
+        assert_eq!(take_if_none_match("list_threads", &mut other), Ok(None));
+        assert!(other.get("ifNoneMatch").is_some());
+        let mut invalid = json!({ "ifNoneMatch": 7 });
+        assert!(take_if_none_match("get_app_settings", &mut invalid).is_err());
+    }
+
+    #[test]
+    fn matching_etag_skips_the_value() {
+        let files = json!(["README.md", "src/main.rs"]);
+        let first = conditional_response(files.clone(), None);
+        assert_eq!(first["notModified"], json!(false));
+        assert_eq!(first["value"], files);
+        let etag = first["etag"].as_str().expect("etag").to_string();
+        assert_eq!(etag.len(), 32);
+
+        let unchanged = conditional_response(files.clone(), Some(&etag));
+        assert_eq!(unchanged, json!({ "etag": etag, "notModified": true }));
+        let changed = conditional_response(json!(["README.md"]), Some(&etag));
+        assert_eq!(changed["notModified"], json!(false));
+        assert_ne!(changed["etag"], json!(etag));
+    }
+}
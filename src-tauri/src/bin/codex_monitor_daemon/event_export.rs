@@ -0,0 +1,490 @@
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::audit::now_millis;
+use crate::jsonrpc::RequestId;
+use crate::rpc_trace::redact;
+
+const SERVICE_NAME: &str = "codex-monitor-daemon";
+const QUEUE_CAPACITY: usize = 4096;
+const MAX_BATCH: usize = 256;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExporterKind {
+    /// OTLP/HTTP with JSON encoding; the endpoint is the collector base URL.
+    Otlp,
+    /// Batches of records POSTed as `{ service, records }` to one URL.
+    HttpJson,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExporterConfig {
+    pub(crate) kind: ExporterKind,
+    pub(crate) endpoint: String,
+}
+
+/// Parses a `--export-header name=value` argument.
+pub(crate) fn parse_export_header(value: &str) -> Result<(String, String), String> {
+    let (name, header_value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid --export-header `{value}` (expected name=value)"))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("Invalid --export-header `{value}` (empty name)"));
+    }
+    Ok((name.to_string(), header_value.trim().to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExportRecord {
+    AppServerEvent {
+        timestamp: u64,
+        workspace_id: String,
+        method: String,
+        message: Value,
+    },
+    RpcSpan {
+        start: u64,
+        end: u64,
+        client: String,
+        id: Option<RequestId>,
+        method: String,
+        workspace_id: Option<String>,
+        error: Option<String>,
+    },
+    Error {
+        timestamp: u64,
+        source: String,
+        message: String,
+    },
+}
+
+impl ExportRecord {
+    fn to_json(&self) -> Value {
+        match self {
+            ExportRecord::AppServerEvent {
+                timestamp,
+                workspace_id,
+                method,
+                message,
+            } => json!({
+                "type": "appServerEvent",
+                "timestamp": timestamp,
+                "workspaceId": workspace_id,
+                "method": method,
+                "message": message,
+            }),
+            ExportRecord::RpcSpan {
+                start,
+                end,
+                client,
+                id,
+                method,
+                workspace_id,
+                error,
+            } => json!({
+                "type": "rpcSpan",
+                "start": start,
+                "end": end,
+                "durationMs": end.saturating_sub(*start),
+                "client": client,
+                "id": id,
+                "method": method,
+                "workspaceId": workspace_id,
+                "error": error,
+            }),
+            ExportRecord::Error {
+                timestamp,
+                source,
+                message,
+            } => json!({
+                "type": "error",
+                "timestamp": timestamp,
+                "source": source,
+                "message": message,
+            }),
+        }
+    }
+}
+
+/// Streaming deltas would dwarf everything else; completed items carry the same content.
+fn is_exported_method(method: &str) -> bool {
+    !method.ends_with("/delta") && !method.ends_with("Delta")
+}
+
+/// Fan-out of daemon telemetry to the configured sinks. Records are queued without blocking
+/// and dropped (and counted) when the exporters fall behind.
+#[derive(Clone, Default)]
+pub(crate) struct EventExporter {
+    tx: Option<mpsc::Sender<ExportRecord>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventExporter {
+    /// Spawns the export task; with no exporters configured every call is a no-op.
+    pub(crate) fn start(exporters: Vec<ExporterConfig>, headers: Vec<(String, String)>) -> Self {
+        if exporters.is_empty() {
+            return Self::default();
+        }
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        tokio::spawn(run_exporter(exporters, headers, rx, Arc::clone(&dropped)));
+        Self {
+            tx: Some(tx),
+            dropped,
+        }
+    }
+
+    fn push(&self, record: ExportRecord) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if tx.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn app_server_event(&self, workspace_id: &str, message: &Value) {
+        if self.tx.is_none() {
+            return;
+        }
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        if !is_exported_method(&method) {
+            return;
+        }
+        self.push(ExportRecord::AppServerEvent {
+            timestamp: now_millis(),
+            workspace_id: workspace_id.to_string(),
+            method,
+            message: redact(message),
+        });
+    }
+
+    /// Records one handled RPC; failed calls also produce an error record.
+    pub(crate) fn rpc_span(
+        &self,
+        start: u64,
+        client: &str,
+        id: Option<&RequestId>,
+        method: &str,
+        params: &Value,
+        error: Option<&str>,
+    ) {
+        if self.tx.is_none() {
+            return;
+        }
+        let end = now_millis();
+        let workspace_id = params
+            .get("workspaceId")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        self.push(ExportRecord::RpcSpan {
+            start,
+            end,
+            client: client.to_string(),
+            id: id.cloned(),
+            method: method.to_string(),
+            workspace_id,
+            error: error.map(str::to_string),
+        });
+        if let Some(error) = error {
+            self.error(&format!("rpc:{method}"), error);
+        }
+    }
+
+    pub(crate) fn error(&self, source: &str, message: &str) {
+        self.push(ExportRecord::Error {
+            timestamp: now_millis(),
+            source: source.to_string(),
+            message: message.to_string(),
+        });
+    }
+}
+
+async fn run_exporter(
+    exporters: Vec<ExporterConfig>,
+    headers: Vec<(String, String)>,
+    mut rx: mpsc::Receiver<ExportRecord>,
+    dropped: Arc<AtomicU64>,
+) {
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::warn!("event export disabled: {err}");
+            return;
+        }
+    };
+    let mut batch: Vec<ExportRecord> = Vec::new();
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        let closed = tokio::select! {
+            record = rx.recv() => match record {
+                Some(record) => {
+                    batch.push(record);
+                    if batch.len() < MAX_BATCH {
+                        continue;
+                    }
+                    false
+                }
+                None => true,
+            },
+            _ = interval.tick() => false,
+        };
+        let lost = dropped.swap(0, Ordering::Relaxed);
+        if lost > 0 {
+            batch.push(ExportRecord::Error {
+                timestamp: now_millis(),
+                source: "event_export".to_string(),
+                message: format!("dropped {lost} records because the export queue was full"),
+            });
+        }
+        if !batch.is_empty() {
+            let records = std::mem::take(&mut batch);
+            for exporter in &exporters {
+                for (url, body) in export_requests(exporter, &records) {
+                    if let Err(err) = post_json(&client, &url, &headers, body).await {
+                        tracing::warn!("event export to {url} failed: {err}");
+                    }
+                }
+            }
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+async fn post_json(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &[(String, String)],
+    body: Value,
+) -> Result<(), String> {
+    let mut request = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Builds the `(url, body)` pairs one exporter sends for a batch.
+fn export_requests(exporter: &ExporterConfig, records: &[ExportRecord]) -> Vec<(String, Value)> {
+    match exporter.kind {
+        ExporterKind::HttpJson => vec![(
+            exporter.endpoint.clone(),
+            json!({
+                "service": SERVICE_NAME,
+                "records": records.iter().map(ExportRecord::to_json).collect::<Vec<_>>(),
+            }),
+        )],
+        ExporterKind::Otlp => {
+            let base = exporter.endpoint.trim_end_matches('/');
+            let mut requests = Vec::new();
+            let logs = otlp_logs(records);
+            if !logs.is_empty() {
+                requests.push((
+                    format!("{base}/v1/logs"),
+                    otlp_resource("resourceLogs", "scopeLogs", "logRecords", logs),
+                ));
+            }
+            let spans = otlp_spans(records);
+            if !spans.is_empty() {
+                requests.push((
+                    format!("{base}/v1/traces"),
+                    otlp_resource("resourceSpans", "scopeSpans", "spans", spans),
+                ));
+            }
+            requests
+        }
+    }
+}
+
+fn otlp_resource(resource_key: &str, scope_key: &str, items_key: &str, items: Vec<Value>) -> Value {
+    json!({
+        resource_key: [{
+            "resource": {
+                "attributes": [otlp_attribute("service.name", SERVICE_NAME)],
+            },
+            scope_key: [{
+                "scope": { "name": SERVICE_NAME, "version": env!("CARGO_PKG_VERSION") },
+                items_key: items,
+            }],
+        }],
+    })
+}
+
+fn otlp_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn unix_nanos(millis: u64) -> String {
+    (u128::from(millis) * 1_000_000).to_string()
+}
+
+fn otlp_logs(records: &[ExportRecord]) -> Vec<Value> {
+    records
+        .iter()
+        .filter_map(|record| match record {
+            ExportRecord::AppServerEvent {
+                timestamp,
+                workspace_id,
+                method,
+                message,
+            } => {
+                let is_error = method == "error" || method.ends_with("/error");
+                Some(json!({
+                    "timeUnixNano": unix_nanos(*timestamp),
+                    "severityNumber": if is_error { 17 } else { 9 },
+                    "severityText": if is_error { "ERROR" } else { "INFO" },
+                    "body": { "stringValue": message.to_string() },
+                    "attributes": [
+                        otlp_attribute("event.name", method),
+                        otlp_attribute("codex.workspace_id", workspace_id),
+                    ],
+                }))
+            }
+            ExportRecord::Error {
+                timestamp,
+                source,
+                message,
+            } => Some(json!({
+                "timeUnixNano": unix_nanos(*timestamp),
+                "severityNumber": 17,
+                "severityText": "ERROR",
+                "body": { "stringValue": message },
+                "attributes": [otlp_attribute("error.source", source)],
+            })),
+            ExportRecord::RpcSpan { .. } => None,
+        })
+        .collect()
+}
+
+fn otlp_spans(records: &[ExportRecord]) -> Vec<Value> {
+    records
+        .iter()
+        .filter_map(|record| {
+            let ExportRecord::RpcSpan {
+                start,
+                end,
+                client,
+                id,
+                method,
+                workspace_id,
+                error,
+            } = record
+            else {
+                return None;
+            };
+            let mut attributes = vec![
+                otlp_attribute("rpc.system", "jsonrpc"),
+                otlp_attribute("rpc.method", method),
+                otlp_attribute("client.address", client),
+            ];
+            if let Some(id) = id {
+                attributes.push(otlp_attribute("rpc.jsonrpc.request_id", &id.to_string()));
+            }
+            if let Some(workspace_id) = workspace_id {
+                attributes.push(otlp_attribute("codex.workspace_id", workspace_id));
+            }
+            let status = match error {
+                Some(error) => json!({ "code": 2, "message": error }),
+                None => json!({ "code": 1 }),
+            };
+            let mut span_id = Uuid::new_v4().simple().to_string();
+            span_id.truncate(16);
+            Some(json!({
+                "traceId": Uuid::new_v4().simple().to_string(),
+                "spanId": span_id,
+                "name": method,
+                // SPAN_KIND_SERVER
+                "kind": 2,
+                "startTimeUnixNano": unix_nanos(*start),
+                "endTimeUnixNano": unix_nanos(*end),
+                "attributes": attributes,
+                "status": status,
+            }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_records() -> Vec<ExportRecord> {
+        vec![
+            ExportRecord::AppServerEvent {
+                timestamp: 1_000,
+                workspace_id: "ws-1".to_string(),
+                method: "turn/completed".to_string(),
+                message: json!({ "method": "turn/completed" }),
+            },
+            ExportRecord::RpcSpan {
+                start: 1_000,
+                end: 1_250,
+                client: "127.0.0.1:5000".to_string(),
+                id: Some(RequestId::Number(4.into())),
+                method: "send_user_message".to_string(),
+                workspace_id: Some("ws-1".to_string()),
+                error: Some("workspace not connected".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn otlp_splits_logs_and_spans() {
+        let exporter = ExporterConfig {
+            kind: ExporterKind::Otlp,
+            endpoint: "http://collector:4318/".to_string(),
+        };
+        let requests = export_requests(&exporter, &sample_records());
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0, "http://collector:4318/v1/logs");
+        let log = &requests[0].1["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(log["timeUnixNano"], json!("1000000000"));
+        assert_eq!(log["severityText"], json!("INFO"));
+
+        assert_eq!(requests[1].0, "http://collector:4318/v1/traces");
+        let span = &requests[1].1["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["name"], json!("send_user_message"));
+        assert_eq!(span["status"]["code"], json!(2));
+        assert_eq!(span["traceId"].as_str().map(str::len), Some(32));
+        assert_eq!(span["spanId"].as_str().map(str::len), Some(16));
+    }
+
+    #[test]
+    fn http_json_posts_one_batch() {
+        let exporter = ExporterConfig {
+            kind: ExporterKind::HttpJson,
+            endpoint: "https://example.test/ingest".to_string(),
+        };
+        let requests = export_requests(&exporter, &sample_records());
+        assert_eq!(requests.len(), 1);
+        let records = requests[0].1["records"].as_array().expect("records");
+        assert_eq!(records[1]["type"], json!("rpcSpan"));
+        assert_eq!(records[1]["durationMs"], json!(250));
+        assert!(!is_exported_method("item/agentMessage/delta"));
+        assert_eq!(
+            parse_export_header("Authorization=Bearer abc").expect("header"),
+            ("Authorization".to_string(), "Bearer abc".to_string())
+        );
+        assert!(parse_export_header("nope").is_err());
+    }
+}
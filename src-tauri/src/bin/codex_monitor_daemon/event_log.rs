@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Events a connection's forwarder may fall behind by before it skips ahead.
+const CHANNEL_CAPACITY: usize = 2048;
+/// Events kept for `subscribe_since`.
+pub(crate) const REPLAY_BUFFER_EVENTS: usize = 4096;
+
+/// An event and its place in the daemon's event stream. Sequence numbers start at 1 and
+/// grow by one per event, across all kinds and workspaces.
+#[derive(Debug, Clone)]
+pub(crate) struct Sequenced<T> {
+    pub(crate) seq: u64,
+    pub(crate) event: T,
+}
+
+/// What a client missed since the last sequence number it saw.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReplaySummary {
+    pub(crate) latest_seq: u64,
+    pub(crate) replayed: usize,
+    /// Events after `since` that already fell out of the buffer.
+    pub(crate) missed: u64,
+    /// `since` is ahead of the stream, so the daemon restarted; the whole buffer was
+    /// replayed and the client should refetch what it caches.
+    pub(crate) reset: bool,
+}
+
+struct History<T> {
+    next_seq: u64,
+    events: VecDeque<Sequenced<T>>,
+    capacity: usize,
+}
+
+/// The daemon's event broadcast, numbering every event and keeping the most recent ones
+/// so a client that reconnects can catch up.
+pub(crate) struct EventLog<T> {
+    tx: broadcast::Sender<Sequenced<T>>,
+    history: Mutex<History<T>>,
+}
+
+impl<T: Clone> EventLog<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            tx,
+            history: Mutex::new(History {
+                next_seq: 1,
+                events: VecDeque::with_capacity(capacity),
+                capacity,
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, History<T>> {
+        self.history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Numbers, records and broadcasts `event`, returning its sequence number. The lock is
+    /// held across the broadcast so receivers see events in sequence order.
+    pub(crate) fn send(&self, event: T) -> u64 {
+        let mut history = self.lock();
+        let seq = history.next_seq;
+        history.next_seq += 1;
+        let sequenced = Sequenced { seq, event };
+        if history.events.len() == history.capacity {
+            history.events.pop_front();
+        }
+        if history.capacity > 0 {
+            history.events.push_back(sequenced.clone());
+        }
+        let _ = self.tx.send(sequenced);
+        seq
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Sequenced<T>> {
+        self.tx.subscribe()
+    }
+
+    /// The buffered events after `since`, plus a receiver for every event after those,
+    /// so nothing is skipped or delivered twice.
+    pub(crate) fn subscribe_since(
+        &self,
+        since: u64,
+    ) -> (Vec<Sequenced<T>>, ReplaySummary, broadcast::Receiver<Sequenced<T>>) {
+        let history = self.lock();
+        let latest_seq = history.next_seq - 1;
+        let reset = since > latest_seq;
+        let events: Vec<_> = history
+            .events
+            .iter()
+            .filter(|event| reset || event.seq > since)
+            .cloned()
+            .collect();
+        let first_kept = history.events.front().map_or(history.next_seq, |event| event.seq);
+        let missed = if reset {
+            0
+        } else {
+            first_kept.saturating_sub(since + 1)
+        };
+        let summary = ReplaySummary {
+            latest_seq,
+            replayed: events.len(),
+            missed,
+            reset,
+        };
+        (events, summary, self.tx.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs(events: &[Sequenced<&str>]) -> Vec<u64> {
+        events.iter().map(|event| event.seq).collect()
+    }
+
+    #[test]
+    fn replays_buffered_events_and_counts_the_ones_that_fell_out() {
+        let log = EventLog::new(3);
+        for event in ["a", "b", "c", "d", "e"] {
+            log.send(event);
+        }
+        let (events, summary, _) = log.subscribe_since(3);
+        assert_eq!(seqs(&events), vec![4, 5]);
+        assert_eq!(
+            summary,
+            ReplaySummary {
+                latest_seq: 5,
+                replayed: 2,
+                missed: 0,
+                reset: false,
+            }
+        );
+
+        let (events, summary, _) = log.subscribe_since(0);
+        assert_eq!(seqs(&events), vec![3, 4, 5]);
+        assert_eq!(summary.missed, 2);
+
+        let (events, summary, _) = log.subscribe_since(5);
+        assert!(events.is_empty());
+        assert_eq!(summary.missed, 0);
+
+        let (events, summary, _) = log.subscribe_since(40);
+        assert_eq!(seqs(&events), vec![3, 4, 5]);
+        assert!(summary.reset);
+    }
+
+    #[test]
+    fn the_receiver_picks_up_right_after_the_replay() {
+        let log = EventLog::new(8);
+        log.send("a");
+        let (events, _, mut rx) = log.subscribe_since(0);
+        assert_eq!(seqs(&events), vec![1]);
+        assert_eq!(log.send("b"), 2);
+        let next = rx.try_recv().expect("live event");
+        assert_eq!((next.seq, next.event), (2, "b"));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::audit::now_millis;
+use crate::shared::build_core::BuildReport;
+use crate::shared::data_files_core::FANOUT_RUNS_FILE;
+use crate::shared::worktree_diff_core::WorktreeDiff;
+
+pub(crate) const MAX_FANOUT_BRANCHES: usize = 8;
+/// Runs kept for `fanout_status`; the oldest are dropped first.
+const MAX_FANOUT_RUNS: usize = 50;
+const MAX_SUMMARY_CHARS: usize = 4_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FanoutStatus {
+    Starting,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl FanoutStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed)
+    }
+}
+
+/// One attempt of a fan-out: a worktree on `branch` with its own thread.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FanoutBranch {
+    pub(crate) branch: String,
+    /// The worktree workspace, once created.
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) thread_id: Option<String>,
+    pub(crate) turn_id: Option<String>,
+    pub(crate) status: FanoutStatus,
+    pub(crate) error: Option<String>,
+    /// Thread total from `thread/tokenUsage/updated`.
+    pub(crate) tokens_used: u64,
+    /// The agent's last message, shortened.
+    pub(crate) summary: Option<String>,
+    pub(crate) finished_at: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FanoutRun {
+    pub(crate) id: String,
+    pub(crate) parent_id: String,
+    pub(crate) prompt: String,
+    /// The parent's `HEAD` when the run started; branch diffs are measured from it.
+    #[serde(default)]
+    pub(crate) base_commit: Option<String>,
+    pub(crate) created_at: u64,
+    /// Set once every branch has completed or failed.
+    pub(crate) finished_at: Option<u64>,
+    /// The branch merged by `fanout_pick`; the run's worktrees are gone after that.
+    #[serde(default)]
+    pub(crate) picked: Option<String>,
+    pub(crate) branches: Vec<FanoutBranch>,
+}
+
+/// One branch as `compare_fanout` shows it next to the others.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FanoutBranchComparison {
+    pub(crate) branch: String,
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) status: FanoutStatus,
+    pub(crate) tokens_used: u64,
+    pub(crate) summary: Option<String>,
+    pub(crate) diff: Option<WorktreeDiff>,
+    pub(crate) diff_error: Option<String>,
+    /// Set when the comparison ran the checks.
+    pub(crate) build: Option<BuildReport>,
+    pub(crate) build_error: Option<String>,
+    /// Current problems of the branch's workspace.
+    pub(crate) error_count: usize,
+    pub(crate) warning_count: usize,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FanoutComparison {
+    pub(crate) run_id: String,
+    pub(crate) parent_id: String,
+    pub(crate) base_commit: Option<String>,
+    pub(crate) picked: Option<String>,
+    pub(crate) branches: Vec<FanoutBranchComparison>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FanoutPickResult {
+    pub(crate) run_id: String,
+    pub(crate) branch: String,
+    /// Files the winning branch's changes touched in the parent.
+    pub(crate) applied_files: usize,
+    pub(crate) removed_worktrees: Vec<String>,
+    /// Branches deleted because they had no commits of their own.
+    pub(crate) deleted_branches: Vec<String>,
+    /// Cleanup steps that failed; the changes were applied regardless.
+    pub(crate) errors: Vec<String>,
+}
+
+/// Fan-out runs, newest last, mirrored to `<data-dir>/fanout-runs.json`. Branch
+/// progress comes from the app-server events of their threads.
+pub(crate) struct FanoutRuns {
+    path: PathBuf,
+    runs: Mutex<Vec<FanoutRun>>,
+}
+
+/// Checks a fan-out request and returns the branch names, trimmed.
+pub(crate) fn validate_fanout(branches: &[String], prompt: &str) -> Result<Vec<String>, String> {
+    if prompt.trim().is_empty() {
+        return Err("Prompt is empty".to_string());
+    }
+    let branches = branches
+        .iter()
+        .map(|branch| branch.trim().to_string())
+        .collect::<Vec<_>>();
+    if branches.is_empty() {
+        return Err("At least one branch is required".to_string());
+    }
+    if branches.len() > MAX_FANOUT_BRANCHES {
+        return Err(format!(
+            "A fan-out runs at most {MAX_FANOUT_BRANCHES} branches"
+        ));
+    }
+    for (index, branch) in branches.iter().enumerate() {
+        if branch.is_empty() {
+            return Err("Branch names can't be empty".to_string());
+        }
+        if branches[..index].contains(branch) {
+            return Err(format!("Branch {branch} is listed twice"));
+        }
+    }
+    Ok(branches)
+}
+
+fn summary(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+impl FanoutRuns {
+    pub(crate) fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(FANOUT_RUNS_FILE);
+        let mut runs: Vec<FanoutRun> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        // Threads don't survive the daemon; whatever was still going is lost.
+        let now = now_millis();
+        for run in &mut runs {
+            for branch in &mut run.branches {
+                if !branch.status.is_finished() {
+                    branch.status = FanoutStatus::Failed;
+                    branch.error = Some("Interrupted by a daemon restart".to_string());
+                    branch.finished_at = Some(now);
+                }
+            }
+            run.finished_at.get_or_insert(now);
+        }
+        Self {
+            path,
+            runs: Mutex::new(runs),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<FanoutRun>> {
+        self.runs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub(crate) fn create(
+        &self,
+        parent_id: &str,
+        branches: Vec<String>,
+        prompt: &str,
+        base_commit: Option<String>,
+    ) -> FanoutRun {
+        let run = FanoutRun {
+            id: Uuid::new_v4().to_string(),
+            parent_id: parent_id.to_string(),
+            prompt: prompt.to_string(),
+            base_commit,
+            created_at: now_millis(),
+            finished_at: None,
+            picked: None,
+            branches: branches
+                .into_iter()
+                .map(|branch| FanoutBranch {
+                    branch,
+                    workspace_id: None,
+                    thread_id: None,
+                    turn_id: None,
+                    status: FanoutStatus::Starting,
+                    error: None,
+                    tokens_used: 0,
+                    summary: None,
+                    finished_at: None,
+                })
+                .collect(),
+        };
+        let mut runs = self.lock();
+        runs.push(run.clone());
+        let excess = runs.len().saturating_sub(MAX_FANOUT_RUNS);
+        runs.drain(..excess);
+        self.persist(&runs);
+        run
+    }
+
+    /// Applies `change` to one branch of a run and records the run as finished once
+    /// every branch is.
+    pub(crate) fn update_branch(
+        &self,
+        run_id: &str,
+        index: usize,
+        change: impl FnOnce(&mut FanoutBranch),
+    ) {
+        let mut runs = self.lock();
+        let Some(run) = runs.iter_mut().find(|run| run.id == run_id) else {
+            return;
+        };
+        let Some(branch) = run.branches.get_mut(index) else {
+            return;
+        };
+        change(branch);
+        if branch.status.is_finished() {
+            branch.finished_at.get_or_insert_with(now_millis);
+        }
+        if run.finished_at.is_none()
+            && run
+                .branches
+                .iter()
+                .all(|branch| branch.status.is_finished())
+        {
+            run.finished_at = Some(now_millis());
+        }
+        self.persist(&runs);
+    }
+
+    /// Follows the threads of running branches: token totals, the last agent
+    /// message, and completion.
+    pub(crate) fn observe(&self, workspace_id: &str, message: &Value) {
+        let method = message.get("method").and_then(Value::as_str);
+        if !matches!(
+            method,
+            Some("turn/completed" | "thread/tokenUsage/updated" | "item/completed")
+        ) {
+            return;
+        }
+        let Some(params) = message.get("params") else {
+            return;
+        };
+        let Some(thread_id) = params.get("threadId").and_then(Value::as_str) else {
+            return;
+        };
+        let target = {
+            let runs = self.lock();
+            runs.iter().find_map(|run| {
+                run.branches
+                    .iter()
+                    .position(|branch| {
+                        branch.status == FanoutStatus::Running
+                            && branch.workspace_id.as_deref() == Some(workspace_id)
+                            && branch.thread_id.as_deref() == Some(thread_id)
+                    })
+                    .map(|index| (run.id.clone(), index))
+            })
+        };
+        let Some((run_id, index)) = target else {
+            return;
+        };
+        self.update_branch(&run_id, index, |branch| match method {
+            Some("turn/completed") => {
+                let turn = params.get("turn").unwrap_or(&Value::Null);
+                if turn.get("status").and_then(Value::as_str) == Some("failed") {
+                    branch.status = FanoutStatus::Failed;
+                    branch.error = Some(
+                        turn.get("error")
+                            .and_then(|error| error.get("message"))
+                            .and_then(Value::as_str)
+                            .unwrap_or("Turn failed")
+                            .to_string(),
+                    );
+                } else {
+                    branch.status = FanoutStatus::Completed;
+                }
+            }
+            Some("thread/tokenUsage/updated") => {
+                let total = params
+                    .get("tokenUsage")
+                    .and_then(|usage| usage.get("total"));
+                if let Some(tokens) = total
+                    .and_then(|total| {
+                        total
+                            .get("totalTokens")
+                            .or_else(|| total.get("total_tokens"))
+                    })
+                    .and_then(Value::as_u64)
+                {
+                    branch.tokens_used = tokens;
+                }
+            }
+            _ => {
+                let item = params.get("item").unwrap_or(&Value::Null);
+                if item.get("type").and_then(Value::as_str) == Some("agentMessage") {
+                    if let Some(text) = item.get("text").and_then(Value::as_str) {
+                        branch.summary = Some(summary(text));
+                    }
+                }
+            }
+        });
+    }
+
+    /// Records the winner; fails when the run already has one.
+    pub(crate) fn mark_picked(&self, run_id: &str, branch: &str) -> Result<(), String> {
+        let mut runs = self.lock();
+        let run = runs
+            .iter_mut()
+            .find(|run| run.id == run_id)
+            .ok_or_else(|| format!("Unknown fan-out run: {run_id}"))?;
+        if let Some(picked) = &run.picked {
+            return Err(format!("Branch {picked} was already picked for this run"));
+        }
+        run.picked = Some(branch.to_string());
+        self.persist(&runs);
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, run_id: &str) -> Option<FanoutRun> {
+        self.lock().iter().find(|run| run.id == run_id).cloned()
+    }
+
+    /// Newest first.
+    pub(crate) fn list(&self) -> Vec<FanoutRun> {
+        self.lock().iter().rev().cloned().collect()
+    }
+
+    fn persist(&self, runs: &[FanoutRun]) {
+        if let Err(err) = write_runs(&self.path, runs) {
+            tracing::error!(
+                "Failed to write fan-out runs {}: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+fn write_runs(path: &Path, runs: &[FanoutRun]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(runs).map_err(|err| err.to_string())?;
+    std::fs::write(path, data).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tracks_branch_threads_until_the_run_finishes() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-fanout-{}", Uuid::new_v4()));
+        let runs = FanoutRuns::load(&dir);
+        let branches = validate_fanout(&[" a ".to_string(), "b".to_string()], "try it").unwrap();
+        let run = runs.create("parent", branches, "try it", Some("abc".to_string()));
+        for (index, workspace) in ["ws-a", "ws-b"].iter().enumerate() {
+            runs.update_branch(&run.id, index, |branch| {
+                branch.workspace_id = Some(workspace.to_string());
+                branch.thread_id = Some(format!("thread-{index}"));
+                branch.status = FanoutStatus::Running;
+            });
+        }
+
+        runs.observe(
+            "ws-a",
+            &json!({
+                "method": "item/completed",
+                "params": { "threadId": "thread-0", "item": { "type": "agentMessage", "text": " Done. " } }
+            }),
+        );
+        runs.observe(
+            "ws-a",
+            &json!({
+                "method": "thread/tokenUsage/updated",
+                "params": { "threadId": "thread-0", "tokenUsage": { "total": { "totalTokens": 900 } } }
+            }),
+        );
+        // Same thread id, different workspace: not this run's branch.
+        runs.observe(
+            "ws-b",
+            &json!({ "method": "turn/completed", "params": { "threadId": "thread-0", "turn": {} } }),
+        );
+        runs.observe(
+            "ws-a",
+            &json!({ "method": "turn/completed", "params": { "threadId": "thread-0", "turn": {} } }),
+        );
+        let current = runs.get(&run.id).unwrap();
+        assert_eq!(current.branches[0].branch, "a");
+        assert_eq!(current.branches[0].status, FanoutStatus::Completed);
+        assert_eq!(current.branches[0].summary.as_deref(), Some("Done."));
+        assert_eq!(current.branches[0].tokens_used, 900);
+        assert_eq!(current.branches[1].status, FanoutStatus::Running);
+        assert!(current.finished_at.is_none());
+
+        let reloaded = FanoutRuns::load(&dir).get(&run.id).unwrap();
+        assert_eq!(reloaded.branches[1].status, FanoutStatus::Failed);
+        assert!(reloaded.finished_at.is_some());
+        assert_eq!(reloaded.base_commit.as_deref(), Some("abc"));
+        runs.mark_picked(&run.id, "a").unwrap();
+        assert!(runs.mark_picked(&run.id, "b").is_err());
+
+        assert!(validate_fanout(&["a".to_string(), "a".to_string()], "x").is_err());
+        assert!(validate_fanout(&[], "x").is_err());
+        assert!(validate_fanout(&["a".to_string()], " ").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
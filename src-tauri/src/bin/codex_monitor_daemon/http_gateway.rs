@@ -0,0 +1,652 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+use crate::rpc_timeouts::RpcError;
+use crate::shared::file_transfer_core::MAX_CHUNK_BYTES;
+use crate::token_scopes::{Grant, TokenSet};
+use crate::{serve_rpc, DaemonState};
+
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A small REST facade over the RPC dispatch for automations and webhooks:
+///
+/// - `GET /workspaces` → `list_workspaces`
+/// - `GET /threads?workspaceId=…` → `list_threads` (other query keys are its params)
+/// - `POST /workspaces/{id}/message` → `send_user_message`, starting a thread first
+///   when the body has no `threadId`
+/// - `GET /workspaces/{id}/files[/{path}]` → `list_workspace_files` / `read_workspace_file`
+/// - `POST /workspaces/{id}/files` → a streamed write of `{ path, content, encoding? }`
+/// - `GET /health`, `GET /capabilities`
+/// - `POST /rpc/{method}` → any other RPC, with the body as its params
+///
+/// Every request needs `Authorization: Bearer <token>` unless the daemon runs without
+/// auth, and the token's scopes apply as on the socket. Each connection serves one
+/// request, over TLS when the daemon has a certificate.
+pub(crate) async fn serve(
+    listener: TcpListener,
+    tls: Option<TlsAcceptor>,
+    tokens: TokenSet,
+    state: Arc<DaemonState>,
+) {
+    let tokens = Arc::new(tokens);
+    loop {
+        let Ok((socket, addr)) = listener.accept().await else {
+            continue;
+        };
+        let tls = tls.clone();
+        let tokens = Arc::clone(&tokens);
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            let client_label = format!("http:{addr}");
+            let Some(tls) = tls else {
+                handle_connection(socket, client_label, &tokens, &state).await;
+                return;
+            };
+            if let Ok(Ok(stream)) = tokio::time::timeout(READ_TIMEOUT, tls.accept(socket)).await {
+                handle_connection(stream, client_label, &tokens, &state).await;
+            }
+        });
+    }
+}
+
+#[derive(Debug)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// What a request asks for, before any RPC runs.
+#[derive(Debug, PartialEq)]
+enum Route {
+    ListWorkspaces,
+    ListThreads(Value),
+    SendMessage {
+        workspace_id: String,
+        thread_id: Option<String>,
+        params: Map<String, Value>,
+    },
+    WriteFile {
+        workspace_id: String,
+        path: String,
+        content: Vec<u8>,
+    },
+    Rpc {
+        method: String,
+        params: Value,
+    },
+}
+
+async fn handle_connection<S>(
+    socket: S,
+    client_label: String,
+    tokens: &TokenSet,
+    state: &Arc<DaemonState>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(socket);
+    let mut reader = BufReader::new(reader);
+    let result = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut reader)).await {
+        Ok(Ok(request)) => match authorize(&request, tokens) {
+            Ok(grant) => match route(&request) {
+                Ok(route) => run_route(route, &client_label, &grant, state).await,
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        },
+        Ok(Err(err)) => Err(err),
+        Err(_) => Err(HttpError::new(408, "timed out reading the request")),
+    };
+    let (status, body) = match result {
+        Ok(value) => (200, value),
+        Err(err) => (err.status, json!({ "error": { "message": err.message } })),
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        reason_phrase(status),
+        body.len()
+    );
+    let _ = writer.write_all(response.as_bytes()).await;
+    let _ = writer.shutdown().await;
+}
+
+async fn run_route(
+    route: Route,
+    client_label: &str,
+    grant: &Grant,
+    state: &Arc<DaemonState>,
+) -> Result<Value, HttpError> {
+    let call =
+        |method: &'static str, params: Value| call_rpc(state, client_label, grant, method, params);
+    match route {
+        Route::ListWorkspaces => call("list_workspaces", json!({})).await,
+        Route::ListThreads(params) => call("list_threads", params).await,
+        Route::SendMessage {
+            workspace_id,
+            thread_id,
+            mut params,
+        } => {
+            let thread_id = match thread_id {
+                Some(thread_id) => thread_id,
+                None => {
+                    let cwd = params.get("cwd").cloned().unwrap_or(Value::Null);
+                    let started = call(
+                        "start_thread",
+                        json!({ "workspaceId": workspace_id, "cwd": cwd }),
+                    )
+                    .await?;
+                    let thread = started.get("result").unwrap_or(&started);
+                    thread["thread"]["id"]
+                        .as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| HttpError::new(502, "start_thread returned no thread id"))?
+                }
+            };
+            params.insert("workspaceId".to_string(), json!(workspace_id));
+            params.insert("threadId".to_string(), json!(thread_id));
+            let turn = call("send_user_message", Value::Object(params)).await?;
+            Ok(json!({ "threadId": thread_id, "result": turn }))
+        }
+        Route::WriteFile {
+            workspace_id,
+            path,
+            content,
+        } => {
+            let session = call(
+                "begin_write",
+                json!({ "workspaceId": workspace_id, "path": path, "size": content.len() }),
+            )
+            .await?;
+            let write_id = session["writeId"].clone();
+            let mut chunks = content.chunks(MAX_CHUNK_BYTES);
+            let mut offset = 0;
+            let written = loop {
+                let Some(chunk) = chunks.next() else {
+                    break call("commit_write", json!({ "writeId": write_id })).await;
+                };
+                let data = base64::engine::general_purpose::STANDARD.encode(chunk);
+                let params = json!({ "writeId": write_id, "offset": offset, "data": data });
+                if let Err(err) = call("write_chunk", params).await {
+                    break Err(err);
+                }
+                offset += chunk.len();
+            };
+            if written.is_err() {
+                let _ = call("abort_write", json!({ "writeId": write_id })).await;
+            }
+            written
+        }
+        Route::Rpc { method, params } => {
+            call_rpc(state, client_label, grant, &method, params).await
+        }
+    }
+}
+
+async fn call_rpc(
+    state: &Arc<DaemonState>,
+    client_label: &str,
+    grant: &Grant,
+    method: &str,
+    params: Value,
+) -> Result<Value, HttpError> {
+    state
+        .rpc_trace
+        .record("request", client_label, None, method, &params);
+    let result = serve_rpc(state, client_label, grant, None, method, params, true).await;
+    state.publish_workspace_deltas().await;
+    result.map_err(http_error)
+}
+
+fn http_error(error: RpcError) -> HttpError {
+    match error {
+        RpcError::Failed(message) | RpcError::InvalidParams(message) => {
+            HttpError::new(400, message)
+        }
+        RpcError::NotFound(message) => HttpError::new(404, message),
+        RpcError::MethodNotFound(_) => HttpError::new(404, error.message()),
+        RpcError::Forbidden(message) => HttpError::new(403, message),
+        RpcError::TimedOut { .. } => HttpError::new(504, error.message()),
+    }
+}
+
+fn json_body(request: &HttpRequest) -> Result<Map<String, Value>, HttpError> {
+    let body = if request.body.is_empty() {
+        Value::Object(Map::new())
+    } else {
+        serde_json::from_slice(&request.body)
+            .map_err(|err| HttpError::new(400, format!("invalid JSON body: {err}")))?
+    };
+    match body {
+        Value::Object(params) => Ok(params),
+        _ => Err(HttpError::new(400, "body must be a JSON object")),
+    }
+}
+
+fn write_file_route(workspace_id: &str, request: &HttpRequest) -> Result<Route, HttpError> {
+    let mut body = json_body(request)?;
+    let Some(Value::String(path)) = body.remove("path") else {
+        return Err(HttpError::new(400, "`path` is required"));
+    };
+    let Some(Value::String(content)) = body.remove("content") else {
+        return Err(HttpError::new(400, "`content` must be a string"));
+    };
+    let content = match body.remove("encoding").as_ref().and_then(Value::as_str) {
+        None | Some("utf8") => content.into_bytes(),
+        Some("base64") => base64::engine::general_purpose::STANDARD
+            .decode(content.trim())
+            .map_err(|err| HttpError::new(400, format!("invalid base64 content: {err}")))?,
+        Some(other) => {
+            return Err(HttpError::new(
+                400,
+                format!("unknown encoding `{other}`; use utf8 or base64"),
+            ))
+        }
+    };
+    if let Some(key) = body.keys().next() {
+        return Err(HttpError::new(400, format!("unknown field `{key}`")));
+    }
+    Ok(Route::WriteFile {
+        workspace_id: workspace_id.to_string(),
+        path,
+        content,
+    })
+}
+
+fn authorize(request: &HttpRequest, tokens: &TokenSet) -> Result<Grant, HttpError> {
+    if tokens.is_empty() {
+        return Ok(Grant::full());
+    }
+    request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .and_then(|(_, provided)| tokens.authenticate(provided.trim()))
+        .ok_or_else(|| HttpError::new(401, "unauthorized"))
+}
+
+fn route(request: &HttpRequest) -> Result<Route, HttpError> {
+    let segments = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .map(percent_decode)
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["workspaces"]) => Ok(Route::ListWorkspaces),
+        ("GET", ["threads"]) => {
+            let mut params = Map::new();
+            for (key, value) in &request.query {
+                let value = match key.as_str() {
+                    "limit" => json!(value
+                        .parse::<u64>()
+                        .map_err(|_| HttpError::new(400, "`limit` must be a number"))?),
+                    "aggregate" | "refresh" => json!(value == "true" || value == "1"),
+                    _ => json!(value),
+                };
+                params.insert(key.clone(), value);
+            }
+            Ok(Route::ListThreads(Value::Object(params)))
+        }
+        ("POST", ["workspaces", workspace_id, "message"]) => {
+            let mut params = json_body(request)?;
+            if params.contains_key("workspaceId") {
+                return Err(HttpError::new(
+                    400,
+                    "`workspaceId` comes from the path, not the body",
+                ));
+            }
+            let thread_id = match params.remove("threadId") {
+                None | Some(Value::Null) => None,
+                Some(Value::String(thread_id)) => Some(thread_id),
+                Some(_) => return Err(HttpError::new(400, "`threadId` must be a string")),
+            };
+            Ok(Route::SendMessage {
+                workspace_id: workspace_id.to_string(),
+                thread_id,
+                params,
+            })
+        }
+        ("GET", ["workspaces", workspace_id, "files"]) => Ok(Route::Rpc {
+            method: "list_workspace_files".to_string(),
+            params: json!({ "workspaceId": workspace_id }),
+        }),
+        ("GET", ["workspaces", workspace_id, "files", path @ ..]) => Ok(Route::Rpc {
+            method: "read_workspace_file".to_string(),
+            params: json!({ "workspaceId": workspace_id, "path": path.join("/") }),
+        }),
+        ("POST", ["workspaces", workspace_id, "files"]) => write_file_route(workspace_id, request),
+        ("GET", ["health"]) => Ok(Route::Rpc {
+            method: "health".to_string(),
+            params: json!({}),
+        }),
+        ("GET", ["capabilities"]) => Ok(Route::Rpc {
+            method: "get_capabilities".to_string(),
+            params: json!({}),
+        }),
+        ("POST", ["rpc", method]) => Ok(Route::Rpc {
+            method: method.to_string(),
+            params: Value::Object(json_body(request)?),
+        }),
+        (
+            _,
+            ["workspaces"]
+            | ["threads"]
+            | ["health"]
+            | ["capabilities"]
+            | ["rpc", _]
+            | ["workspaces", _, "message"]
+            | ["workspaces", _, "files", ..],
+        ) => Err(HttpError::new(405, "method not allowed")),
+        _ => Err(HttpError::new(404, "not found")),
+    }
+}
+
+async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<HttpRequest, HttpError> {
+    let mut head = Vec::new();
+    loop {
+        // One byte past the cap is enough to tell an oversized head from a full one.
+        let remaining = (MAX_HEADER_BYTES + 1 - head.len()) as u64;
+        let read = (&mut *reader)
+            .take(remaining)
+            .read_until(b'\n', &mut head)
+            .await
+            .map_err(|err| HttpError::new(400, err.to_string()))?;
+        if head.len() > MAX_HEADER_BYTES {
+            return Err(HttpError::new(431, "request headers too large"));
+        }
+        if read == 0 || head.ends_with(b"\r\n\r\n") || head.ends_with(b"\n\n") {
+            break;
+        }
+    }
+    let head = String::from_utf8(head).map_err(|_| HttpError::new(400, "invalid request"))?;
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(HttpError::new(400, "invalid request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut authorization = None;
+    let mut content_length = 0usize;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| HttpError::new(400, "invalid Content-Length"))?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(HttpError::new(
+                411,
+                "send a Content-Length instead of chunks",
+            ));
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err(HttpError::new(413, "request body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .await
+        .map_err(|_| HttpError::new(400, "request body shorter than Content-Length"))?;
+
+    Ok(HttpRequest {
+        method: method.to_ascii_uppercase(),
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode_query_component(key), decode_query_component(value))
+            })
+            .collect(),
+        authorization,
+        body,
+    })
+}
+
+/// Decodes `%XX` escapes. A `+` stays a `+`; only query strings use it for a space.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, hex) {
+            (b'%', Some(value)) => {
+                decoded.push(value);
+                index += 3;
+            }
+            _ => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Decodes a query key or value, where `+` stands for a space.
+fn decode_query_component(value: &str) -> String {
+    percent_decode(&value.replace('+', " "))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        504 => "Gateway Timeout",
+        _ => "Bad Gateway",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &str) -> Result<HttpRequest, HttpError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        runtime.block_on(read_request(&mut raw.as_bytes()))
+    }
+
+    #[test]
+    fn routes_requests_onto_rpc_params() {
+        let request = parse(
+            "GET /threads?workspaceId=ws%201&limit=20&refresh=true HTTP/1.1\r\n\
+             Host: localhost\r\nAuthorization: Bearer secret\r\n\r\n",
+        )
+        .expect("request");
+        let secret = TokenSet::new(Some("secret".to_string()), Vec::new());
+        assert_eq!(authorize(&request, &secret), Ok(Grant::full()));
+        let other = TokenSet::new(Some("other".to_string()), Vec::new());
+        assert_eq!(authorize(&request, &other).unwrap_err().status, 401);
+        let lowercase = parse("GET /workspaces HTTP/1.1\r\nauthorization: bearer secret\r\n\r\n")
+            .expect("request");
+        assert_eq!(authorize(&lowercase, &secret), Ok(Grant::full()));
+        let basic = parse("GET /workspaces HTTP/1.1\r\nAuthorization: Basic secret\r\n\r\n")
+            .expect("request");
+        assert_eq!(authorize(&basic, &secret).unwrap_err().status, 401);
+        assert_eq!(
+            route(&request),
+            Ok(Route::ListThreads(json!({
+                "workspaceId": "ws 1",
+                "limit": 20,
+                "refresh": true,
+            })))
+        );
+
+        let body = r#"{"threadId":"t-1","text":"run the tests"}"#;
+        let request = parse(&format!(
+            "POST /workspaces/ws-1/message HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        ))
+        .expect("request");
+        assert_eq!(authorize(&request, &TokenSet::default()), Ok(Grant::full()));
+        let Ok(Route::SendMessage {
+            workspace_id,
+            thread_id,
+            params,
+        }) = route(&request)
+        else {
+            panic!("expected a message route");
+        };
+        assert_eq!(workspace_id, "ws-1");
+        assert_eq!(thread_id.as_deref(), Some("t-1"));
+        assert_eq!(Value::Object(params), json!({ "text": "run the tests" }));
+
+        let request =
+            parse("GET /workspaces/ws-1/files/src/lib%20a.rs HTTP/1.1\r\n\r\n").expect("request");
+        assert_eq!(
+            route(&request),
+            Ok(Route::Rpc {
+                method: "read_workspace_file".to_string(),
+                params: json!({ "workspaceId": "ws-1", "path": "src/lib a.rs" }),
+            })
+        );
+        let request =
+            parse("GET /workspaces/ws-1/files/c++/main%2B.cpp HTTP/1.1\r\n\r\n").expect("request");
+        assert_eq!(
+            route(&request),
+            Ok(Route::Rpc {
+                method: "read_workspace_file".to_string(),
+                params: json!({ "workspaceId": "ws-1", "path": "c++/main+.cpp" }),
+            })
+        );
+        let request =
+            parse("GET /threads?workspaceId=ws+1&branch=a%2Bb HTTP/1.1\r\n\r\n").expect("request");
+        assert_eq!(
+            route(&request),
+            Ok(Route::ListThreads(
+                json!({ "workspaceId": "ws 1", "branch": "a+b" })
+            ))
+        );
+        let request = parse("POST /rpc/git_status HTTP/1.1\r\n\r\n").expect("request");
+        assert_eq!(
+            route(&request),
+            Ok(Route::Rpc {
+                method: "git_status".to_string(),
+                params: json!({}),
+            })
+        );
+
+        let request = parse("DELETE /workspaces HTTP/1.1\r\n\r\n").expect("request");
+        assert_eq!(route(&request).unwrap_err().status, 405);
+        let request = parse("GET /files HTTP/1.1\r\n\r\n").expect("request");
+        assert_eq!(route(&request).unwrap_err().status, 404);
+        let request =
+            parse("DELETE /workspaces/ws-1/files/a.txt HTTP/1.1\r\n\r\n").expect("request");
+        assert_eq!(route(&request).unwrap_err().status, 405);
+        let error = parse("POST /workspaces/ws-1/message HTTP/1.1\r\nContent-Length: 9\r\n\r\n{}")
+            .unwrap_err();
+        assert_eq!(error.status, 400);
+    }
+
+    #[test]
+    fn maps_rpc_errors_onto_statuses() {
+        let status = |error| http_error(error).status;
+        assert_eq!(status(RpcError::Failed("boom".to_string())), 400);
+        assert_eq!(status(RpcError::InvalidParams("`limit`".to_string())), 400);
+        assert_eq!(
+            status(RpcError::NotFound("workspace not found".to_string())),
+            404
+        );
+        assert_eq!(status(RpcError::MethodNotFound("nope".to_string())), 404);
+        assert_eq!(status(RpcError::Forbidden("scope".to_string())), 403);
+    }
+
+    #[test]
+    fn caps_the_request_head_without_reading_past_it() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let mut endless = BufReader::new(tokio::io::repeat(b'a'));
+        let error = runtime.block_on(read_request(&mut endless)).unwrap_err();
+        assert_eq!(error.status, 431);
+    }
+
+    #[test]
+    fn decodes_file_write_bodies() {
+        let write = |body: &str| {
+            let request = parse(&format!(
+                "POST /workspaces/ws-1/files HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            ))
+            .expect("request");
+            route(&request)
+        };
+        let expected = |content: &[u8]| {
+            Ok(Route::WriteFile {
+                workspace_id: "ws-1".to_string(),
+                path: "yarn.lock".to_string(),
+                content: content.to_vec(),
+            })
+        };
+        assert_eq!(
+            write(r#"{"path":"yarn.lock","content":"lock v1\n"}"#),
+            expected(b"lock v1\n")
+        );
+        assert_eq!(
+            write(r#"{"path":"yarn.lock","content":"AAH/","encoding":"base64"}"#),
+            expected(&[0, 1, 255])
+        );
+        for body in [
+            r#"{"content":"x"}"#,
+            r#"{"path":"yarn.lock","content":"x","encoding":"hex"}"#,
+            r#"{"path":"yarn.lock","content":"x","mode":"append"}"#,
+        ] {
+            assert_eq!(write(body).unwrap_err().status, 400, "{body}");
+        }
+    }
+}
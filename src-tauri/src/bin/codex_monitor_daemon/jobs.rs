@@ -0,0 +1,288 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+
+use crate::audit::now_millis;
+
+/// Finished jobs kept around for `job_status`; older ones are dropped first.
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum JobState {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Snapshot of a background job, as returned by `job_status` and `job-update` events.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JobInfo {
+    pub(crate) id: String,
+    pub(crate) kind: String,
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) state: JobState,
+    /// 0.0–1.0 once the job knows how much work there is.
+    pub(crate) progress: Option<f64>,
+    pub(crate) message: Option<String>,
+    pub(crate) result: Option<Value>,
+    pub(crate) error: Option<String>,
+    pub(crate) started_at: u64,
+    pub(crate) finished_at: Option<u64>,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    abort: Option<AbortHandle>,
+}
+
+type JobListener = Box<dyn Fn(JobInfo) + Send + Sync>;
+
+/// Long-running daemon operations tracked by id; every state or progress change is
+/// handed to the listener so it can be broadcast to clients.
+pub(crate) struct JobManager {
+    jobs: Mutex<HashMap<String, JobEntry>>,
+    listener: JobListener,
+}
+
+/// Handed to a running job so it can report progress.
+#[derive(Clone)]
+pub(crate) struct JobProgress {
+    id: String,
+    manager: Arc<JobManager>,
+}
+
+impl JobProgress {
+    pub(crate) fn report(&self, progress: Option<f64>, message: impl Into<String>) {
+        let message = message.into();
+        self.manager.update(&self.id, |info| {
+            info.progress = progress.map(|value| value.clamp(0.0, 1.0));
+            info.message = Some(message);
+        });
+    }
+}
+
+impl JobManager {
+    pub(crate) fn new(listener: impl Fn(JobInfo) + Send + Sync + 'static) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            listener: Box::new(listener),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, JobEntry>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Applies `change` to a running job and announces the result; finished jobs are left alone.
+    fn update(&self, id: &str, change: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+        let info = {
+            let mut jobs = self.lock();
+            let entry = jobs.get_mut(id)?;
+            if entry.info.state != JobState::Running {
+                return None;
+            }
+            change(&mut entry.info);
+            if entry.info.state != JobState::Running {
+                entry.info.finished_at = Some(now_millis());
+                entry.abort = None;
+            }
+            entry.info.clone()
+        };
+        (self.listener)(info.clone());
+        if info.state != JobState::Running {
+            self.prune_finished();
+        }
+        Some(info)
+    }
+
+    fn prune_finished(&self) {
+        let mut jobs = self.lock();
+        let mut finished = jobs
+            .values()
+            .filter_map(|entry| {
+                entry
+                    .info
+                    .finished_at
+                    .map(|finished_at| (finished_at, entry.info.id.clone()))
+            })
+            .collect::<Vec<_>>();
+        if finished.len() <= MAX_FINISHED_JOBS {
+            return;
+        }
+        finished.sort();
+        for (_, id) in finished.iter().take(finished.len() - MAX_FINISHED_JOBS) {
+            jobs.remove(id);
+        }
+    }
+
+    /// Spawns `run` as a job and returns its initial snapshot right away.
+    pub(crate) fn start<F, Fut>(
+        self: &Arc<Self>,
+        kind: &str,
+        workspace_id: Option<String>,
+        run: F,
+    ) -> JobInfo
+    where
+        F: FnOnce(JobProgress) -> Fut,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        let info = JobInfo {
+            id: Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            workspace_id,
+            state: JobState::Running,
+            progress: None,
+            message: None,
+            result: None,
+            error: None,
+            started_at: now_millis(),
+            finished_at: None,
+        };
+        self.lock().insert(
+            info.id.clone(),
+            JobEntry {
+                info: info.clone(),
+                abort: None,
+            },
+        );
+        (self.listener)(info.clone());
+
+        let future = run(JobProgress {
+            id: info.id.clone(),
+            manager: Arc::clone(self),
+        });
+        let manager = Arc::clone(self);
+        let id = info.id.clone();
+        let handle = tokio::spawn(async move {
+            let result = future.await;
+            manager.update(&id, |info| match result {
+                Ok(value) => {
+                    info.state = JobState::Succeeded;
+                    info.progress = Some(1.0);
+                    info.result = Some(value);
+                }
+                Err(error) => {
+                    info.state = JobState::Failed;
+                    info.error = Some(error);
+                }
+            });
+        });
+        if let Some(entry) = self.lock().get_mut(&info.id) {
+            if entry.info.state == JobState::Running {
+                entry.abort = Some(handle.abort_handle());
+            }
+        }
+        info
+    }
+
+    pub(crate) fn status(&self, id: &str) -> Option<JobInfo> {
+        self.lock().get(id).map(|entry| entry.info.clone())
+    }
+
+    /// Running jobs first, then the most recently started.
+    pub(crate) fn list(&self) -> Vec<JobInfo> {
+        let mut jobs = self
+            .lock()
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect::<Vec<_>>();
+        jobs.sort_by(|a, b| {
+            (b.state == JobState::Running)
+                .cmp(&(a.state == JobState::Running))
+                .then(b.started_at.cmp(&a.started_at))
+        });
+        jobs
+    }
+
+    /// Aborts a running job. Blocking work it already handed to a worker thread finishes
+    /// in the background, but its result is discarded.
+    pub(crate) fn cancel(&self, id: &str) -> Result<JobInfo, String> {
+        let abort = {
+            let jobs = self.lock();
+            let entry = jobs.get(id).ok_or_else(|| format!("Unknown job: {id}"))?;
+            if entry.info.state != JobState::Running {
+                return Ok(entry.info.clone());
+            }
+            entry.abort.clone()
+        };
+        if let Some(abort) = abort {
+            abort.abort();
+        }
+        self.update(id, |info| info.state = JobState::Cancelled)
+            .or_else(|| self.status(id))
+            .ok_or_else(|| format!("Unknown job: {id}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime")
+    }
+
+    #[test]
+    fn reports_progress_and_result() {
+        runtime().block_on(async {
+            let updates = Arc::new(Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&updates);
+            let manager = Arc::new(JobManager::new(move |info: JobInfo| {
+                recorded.lock().unwrap().push((info.state, info.progress));
+            }));
+            let job = manager.start("index_build", None, |progress| async move {
+                progress.report(Some(0.5), "halfway");
+                Ok(json!({ "files": 2 }))
+            });
+            assert_eq!(job.state, JobState::Running);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            let finished = manager.status(&job.id).expect("status");
+            assert_eq!(finished.state, JobState::Succeeded);
+            assert_eq!(finished.result, Some(json!({ "files": 2 })));
+            assert_eq!(finished.message.as_deref(), Some("halfway"));
+            assert_eq!(
+                *updates.lock().unwrap(),
+                vec![
+                    (JobState::Running, None),
+                    (JobState::Running, Some(0.5)),
+                    (JobState::Succeeded, Some(1.0)),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn cancel_aborts_running_jobs() {
+        runtime().block_on(async {
+            let manager = Arc::new(JobManager::new(|_| {}));
+            let job = manager.start("backup_data", None, |_| async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                Ok(Value::Null)
+            });
+            let cancelled = manager.cancel(&job.id).expect("cancel");
+            assert_eq!(cancelled.state, JobState::Cancelled);
+            assert!(cancelled.finished_at.is_some());
+            assert_eq!(
+                manager.cancel(&job.id).expect("cancel again").state,
+                JobState::Cancelled
+            );
+            assert!(manager.cancel("missing").is_err());
+            assert_eq!(manager.list().len(), 1);
+        });
+    }
+}
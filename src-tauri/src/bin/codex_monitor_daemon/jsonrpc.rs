@@ -0,0 +1,420 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{json, Map, Number, Value};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+
+pub(crate) const JSONRPC_VERSION: &str = "2.0";
+
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+/// The method ran and failed; `message` says why.
+pub(crate) const SERVER_ERROR: i64 = -32000;
+/// The method ran out of its `--rpc-timeout` budget.
+pub(crate) const TIMEOUT: i64 = -32001;
+/// The connection has not authenticated, or `auth` was refused.
+pub(crate) const UNAUTHORIZED: i64 = -32002;
+/// The token lacks the scope the method needs.
+pub(crate) const FORBIDDEN: i64 = -32003;
+/// The connection went over `--rate-limit` or `--max-in-flight`.
+pub(crate) const RATE_LIMITED: i64 = -32004;
+
+/// A request id as the client sent it. `Null` is a request that asked for a reply with a
+/// null id, unlike a notification, which has no `id` at all.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum RequestId {
+    Number(Number),
+    String(String),
+    Null,
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{number}"),
+            Self::String(value) => f.write_str(value),
+            Self::Null => f.write_str("null"),
+        }
+    }
+}
+
+/// One request or notification; `id` is `None` for a notification.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Request {
+    pub(crate) id: Option<RequestId>,
+    pub(crate) method: String,
+    pub(crate) params: Value,
+}
+
+/// One line off the wire: a single message, or the elements of a batch array.
+#[derive(Debug)]
+pub(crate) struct Frame {
+    pub(crate) messages: Vec<Value>,
+    pub(crate) batch: bool,
+}
+
+/// Splits a line into messages. Unparseable JSON and an empty batch are answered with
+/// the returned error response.
+pub(crate) fn parse_frame(line: &str) -> Result<Frame, Value> {
+    match serde_json::from_str::<Value>(line) {
+        Ok(Value::Array(messages)) if messages.is_empty() => Err(response(
+            &RequestId::Null,
+            Err(error_object(INVALID_REQUEST, "empty batch", None)),
+        )),
+        Ok(Value::Array(messages)) => Ok(Frame {
+            messages,
+            batch: true,
+        }),
+        Ok(message) => Ok(Frame {
+            messages: vec![message],
+            batch: false,
+        }),
+        Err(err) => Err(response(
+            &RequestId::Null,
+            Err(error_object(
+                PARSE_ERROR,
+                &format!("parse error: {err}"),
+                None,
+            )),
+        )),
+    }
+}
+
+/// Validates one message. `jsonrpc` may be left out by older clients, but anything other
+/// than `"2.0"` is refused. The error response carries the id when it could be read.
+pub(crate) fn parse_message(message: &Value) -> Result<Request, Value> {
+    let invalid = |id: Option<&RequestId>, message: &str| {
+        response(
+            id.unwrap_or(&RequestId::Null),
+            Err(error_object(INVALID_REQUEST, message, None)),
+        )
+    };
+    let Value::Object(map) = message else {
+        return Err(invalid(None, "request must be an object"));
+    };
+    let id = match map.get("id") {
+        None => None,
+        Some(Value::Null) => Some(RequestId::Null),
+        Some(Value::Number(number)) => Some(RequestId::Number(number.clone())),
+        Some(Value::String(value)) => Some(RequestId::String(value.clone())),
+        Some(_) => return Err(invalid(None, "`id` must be a number, a string or null")),
+    };
+    match map.get("jsonrpc") {
+        None => {}
+        Some(Value::String(version)) if version == JSONRPC_VERSION => {}
+        Some(_) => return Err(invalid(id.as_ref(), "`jsonrpc` must be \"2.0\"")),
+    }
+    let Some(Value::String(method)) = map.get("method") else {
+        return Err(invalid(id.as_ref(), "`method` must be a string"));
+    };
+    Ok(Request {
+        id,
+        method: method.clone(),
+        params: map.get("params").cloned().unwrap_or(Value::Null),
+    })
+}
+
+pub(crate) fn error_object(code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = Map::new();
+    error.insert("code".to_string(), json!(code));
+    error.insert("message".to_string(), json!(message));
+    if let Some(data) = data {
+        error.insert("data".to_string(), data);
+    }
+    Value::Object(error)
+}
+
+/// The error object for a method that failed with a plain message.
+pub(crate) fn failure(message: &str) -> Value {
+    error_object(SERVER_ERROR, message, None)
+}
+
+fn response(id: &RequestId, outcome: Result<Value, Value>) -> Value {
+    let mut response = json!({ "jsonrpc": JSONRPC_VERSION, "id": id });
+    match outcome {
+        Ok(result) => response["result"] = result,
+        Err(error) => response["error"] = error,
+    }
+    response
+}
+
+/// `None` for a notification, which gets no reply.
+pub(crate) fn result_response(id: Option<&RequestId>, result: Value) -> Option<Value> {
+    id.map(|id| response(id, Ok(result)))
+}
+
+pub(crate) fn error_response(id: Option<&RequestId>, error: Value) -> Option<Value> {
+    id.map(|id| response(id, Err(error)))
+}
+
+pub(crate) fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": JSONRPC_VERSION, "method": method, "params": params })
+}
+
+/// Where one line's responses go: straight out for a single message, or gathered into
+/// one array for a batch. A batch of notifications gets no reply at all.
+pub(crate) struct Replies<'a> {
+    out: &'a UnboundedSender<String>,
+    batch: Option<Vec<Reply>>,
+}
+
+enum Reply {
+    Ready(Value),
+    Pending(oneshot::Receiver<Option<Value>>),
+}
+
+impl<'a> Replies<'a> {
+    pub(crate) fn new(out: &'a UnboundedSender<String>, batch: bool) -> Self {
+        Self {
+            out,
+            batch: batch.then(Vec::new),
+        }
+    }
+
+    pub(crate) fn send(&mut self, response: Option<Value>) {
+        let Some(response) = response else {
+            return;
+        };
+        match &mut self.batch {
+            Some(responses) => responses.push(Reply::Ready(response)),
+            None => send_line(self.out, &response),
+        }
+    }
+
+    /// Holds the place of a response that another task sends later. A batch keeps its
+    /// messages' order and goes out once every deferred response is in.
+    pub(crate) fn defer(&mut self) -> Deferred {
+        match &mut self.batch {
+            Some(responses) => {
+                let (tx, rx) = oneshot::channel();
+                responses.push(Reply::Pending(rx));
+                Deferred::Batch(tx)
+            }
+            None => Deferred::Line(self.out.clone()),
+        }
+    }
+
+    pub(crate) fn finish(mut self) {
+        let Some(replies) = self.batch.take() else {
+            return;
+        };
+        let pending = replies
+            .iter()
+            .any(|reply| matches!(reply, Reply::Pending(_)));
+        if !pending {
+            let responses = replies
+                .into_iter()
+                .filter_map(|reply| match reply {
+                    Reply::Ready(response) => Some(response),
+                    Reply::Pending(_) => None,
+                })
+                .collect();
+            send_batch(self.out, responses);
+            return;
+        }
+        let out = self.out.clone();
+        tokio::spawn(async move {
+            let mut responses = Vec::new();
+            for reply in replies {
+                match reply {
+                    Reply::Ready(response) => responses.push(response),
+                    Reply::Pending(rx) => responses.extend(rx.await.ok().flatten()),
+                }
+            }
+            send_batch(&out, responses);
+        });
+    }
+}
+
+/// A response promised by `Replies::defer`.
+pub(crate) enum Deferred {
+    Line(UnboundedSender<String>),
+    Batch(oneshot::Sender<Option<Value>>),
+}
+
+impl Deferred {
+    pub(crate) fn send(self, response: Option<Value>) {
+        match self {
+            Self::Line(out) => {
+                if let Some(response) = response {
+                    send_line(&out, &response);
+                }
+            }
+            Self::Batch(tx) => {
+                let _ = tx.send(response);
+            }
+        }
+    }
+}
+
+fn send_batch(out: &UnboundedSender<String>, responses: Vec<Value>) {
+    if !responses.is_empty() {
+        send_line(out, &Value::Array(responses));
+    }
+}
+
+fn send_line(out: &UnboundedSender<String>, response: &Value) {
+    let _ = out.send(response.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn parses_requests_notifications_and_invalid_messages() {
+        let request = parse_message(&json!({
+            "jsonrpc": "2.0", "id": "a-1", "method": "ping", "params": { "x": 1 }
+        }))
+        .expect("request");
+        assert_eq!(request.id, Some(RequestId::String("a-1".to_string())));
+        assert_eq!(request.method, "ping");
+        assert_eq!(request.params, json!({ "x": 1 }));
+
+        let legacy = parse_message(&json!({ "id": 7, "method": "ping" })).expect("legacy");
+        assert_eq!(legacy.id, Some(RequestId::Number(7.into())));
+        assert_eq!(legacy.params, Value::Null);
+        let notification = parse_message(&json!({ "method": "ping" })).expect("notification");
+        assert_eq!(notification.id, None);
+
+        let error = parse_message(&json!({ "jsonrpc": "1.0", "id": 3, "method": "ping" }))
+            .expect_err("wrong version");
+        assert_eq!(error["id"], json!(3));
+        assert_eq!(error["error"]["code"], json!(INVALID_REQUEST));
+        let error = parse_message(&json!({ "id": [1], "method": "ping" })).expect_err("bad id");
+        assert_eq!(error["id"], Value::Null);
+        let error = parse_message(&json!(5)).expect_err("not an object");
+        assert_eq!(error["error"]["code"], json!(INVALID_REQUEST));
+    }
+
+    #[test]
+    fn frames_batches_and_reports_parse_errors() {
+        let frame = parse_frame(r#"[{"id":1,"method":"a"},{"method":"b"}]"#).expect("batch");
+        assert!(frame.batch);
+        assert_eq!(frame.messages.len(), 2);
+        assert!(
+            !parse_frame(r#"{"id":1,"method":"a"}"#)
+                .expect("single")
+                .batch
+        );
+
+        let error = parse_frame("[]").expect_err("empty batch");
+        assert_eq!(error["error"]["code"], json!(INVALID_REQUEST));
+        let error = parse_frame("{nope").expect_err("parse error");
+        assert_eq!(error["jsonrpc"], json!("2.0"));
+        assert_eq!(error["id"], Value::Null);
+        assert_eq!(error["error"]["code"], json!(PARSE_ERROR));
+    }
+
+    #[test]
+    fn builds_responses_with_standard_error_codes() {
+        let id = RequestId::Number(4.into());
+        assert_eq!(
+            result_response(Some(&id), json!({ "ok": true })),
+            Some(json!({ "jsonrpc": "2.0", "id": 4, "result": { "ok": true } }))
+        );
+        assert_eq!(result_response(None, json!(1)), None);
+        assert_eq!(
+            result_response(Some(&RequestId::Null), json!(1)),
+            Some(json!({ "jsonrpc": "2.0", "id": null, "result": 1 }))
+        );
+        let error = error_response(Some(&id), failure("workspace not found")).expect("error");
+        assert_eq!(error["error"]["code"], json!(SERVER_ERROR));
+        assert!(error["error"].get("data").is_none());
+        assert_eq!(failure("unknown method: nope")["code"], json!(SERVER_ERROR));
+        assert_eq!(
+            error_object(TIMEOUT, "slow", Some(json!({ "timeoutMs": 10 })))["data"],
+            json!({ "timeoutMs": 10 })
+        );
+        assert_eq!(id.to_string(), "4");
+    }
+
+    #[test]
+    fn batches_reply_with_one_array_and_skip_notifications() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut replies = Replies::new(&tx, true);
+        replies.send(result_response(
+            Some(&RequestId::Number(1.into())),
+            json!("a"),
+        ));
+        replies.send(result_response(None, json!("skipped")));
+        replies.send(error_response(
+            Some(&RequestId::String("b".to_string())),
+            failure("boom"),
+        ));
+        assert!(rx.try_recv().is_err(), "held until the batch is done");
+        replies.finish();
+        let line: Value = serde_json::from_str(&rx.try_recv().expect("batch")).expect("json");
+        assert_eq!(line[0]["result"], json!("a"));
+        assert_eq!(line[1]["id"], json!("b"));
+        assert_eq!(line.as_array().map(Vec::len), Some(2));
+
+        let mut replies = Replies::new(&tx, true);
+        replies.send(result_response(None, json!("skipped")));
+        replies.finish();
+        assert!(
+            rx.try_recv().is_err(),
+            "all-notification batches get no reply"
+        );
+
+        let mut replies = Replies::new(&tx, false);
+        replies.send(result_response(
+            Some(&RequestId::Number(2.into())),
+            json!("c"),
+        ));
+        assert!(rx.try_recv().is_ok(), "single messages go straight out");
+        replies.finish();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn deferred_replies_keep_their_place_in_a_batch() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let mut replies = Replies::new(&tx, true);
+            let slow = replies.defer();
+            replies.send(result_response(
+                Some(&RequestId::Number(2.into())),
+                json!("fast"),
+            ));
+            let skipped = replies.defer();
+            replies.finish();
+            skipped.send(None);
+            tokio::task::yield_now().await;
+            assert!(rx.try_recv().is_err(), "held until the slow call answers");
+            slow.send(result_response(
+                Some(&RequestId::Number(1.into())),
+                json!("slow"),
+            ));
+            let line: Value = serde_json::from_str(&rx.recv().await.expect("batch")).expect("json");
+            assert_eq!(
+                line,
+                json!([
+                    { "jsonrpc": "2.0", "id": 1, "result": "slow" },
+                    { "jsonrpc": "2.0", "id": 2, "result": "fast" },
+                ])
+            );
+
+            let mut replies = Replies::new(&tx, false);
+            let deferred = replies.defer();
+            replies.finish();
+            deferred.send(result_response(
+                Some(&RequestId::Number(3.into())),
+                json!("later"),
+            ));
+            assert!(
+                rx.try_recv().is_ok(),
+                "single messages answer when they're done"
+            );
+        });
+    }
+}
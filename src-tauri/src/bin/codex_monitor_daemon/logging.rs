@@ -0,0 +1,133 @@
+use std::io::IsTerminal;
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::level_filters::LevelFilter;
+use tracing::Span;
+
+use crate::rpc_timeouts::RpcError;
+
+/// `--log-format`: human-readable lines, or one JSON object per line for log shippers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Invalid log format `{other}`; expected text or json."
+            )),
+        }
+    }
+}
+
+/// `--log-level`: `off`, `error`, `warn`, `info`, `debug` or `trace`.
+pub(crate) fn parse_level(value: &str) -> Result<LevelFilter, String> {
+    let value = value.trim().to_ascii_lowercase();
+    let value = if value == "warning" { "warn" } else { &value };
+    value.parse::<LevelFilter>().map_err(|_| {
+        format!("Invalid log level `{value}`; expected off, error, warn, info, debug or trace.")
+    })
+}
+
+/// Sends daemon logs to stderr. Stdout is left alone; it carries the protocol in
+/// `--stdio` mode.
+pub(crate) fn init(level: LevelFilter, format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_target(false);
+    let result = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    };
+    // Only fails when a subscriber is already set, e.g. by an embedding test.
+    let _ = result;
+}
+
+/// The workspace an RPC's params name.
+pub(crate) fn rpc_workspace_id(params: &Value) -> Option<&str> {
+    params
+        .get("workspaceId")
+        .or_else(|| params.get("parentId"))
+        .and_then(Value::as_str)
+}
+
+/// The span one RPC runs in. Its `workspace_id` is left empty when the params don't name
+/// one.
+pub(crate) fn rpc_span(client: &str, method: &str, params: &Value) -> Span {
+    let span = tracing::info_span!("rpc", method, client, workspace_id = tracing::field::Empty,);
+    if let Some(workspace_id) = rpc_workspace_id(params) {
+        span.record("workspace_id", workspace_id);
+    }
+    span
+}
+
+/// `ok`, `error`, `invalid`, `forbidden` or `timeout`.
+pub(crate) fn rpc_outcome(error: Option<&RpcError>) -> &'static str {
+    match error {
+        None => "ok",
+        Some(RpcError::Failed(_) | RpcError::NotFound(_)) => "error",
+        Some(RpcError::InvalidParams(_) | RpcError::MethodNotFound(_)) => "invalid",
+        Some(RpcError::Forbidden(_)) => "forbidden",
+        Some(RpcError::TimedOut { .. }) => "timeout",
+    }
+}
+
+/// Records how one RPC ended, inside its span: successes at debug, failures and timeouts
+/// at warn.
+pub(crate) fn log_rpc_outcome(elapsed: Duration, error: Option<&RpcError>) {
+    let duration_ms = elapsed.as_millis() as u64;
+    let outcome = rpc_outcome(error);
+    match error {
+        None => tracing::debug!(duration_ms, outcome, "rpc finished"),
+        Some(RpcError::TimedOut { .. }) => {
+            tracing::warn!(duration_ms, outcome, "rpc timed out")
+        }
+        Some(error) => {
+            tracing::warn!(duration_ms, outcome, error = %error.message(), "rpc failed")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_levels_and_formats() {
+        assert_eq!(parse_level(" WARN ").expect("warn"), LevelFilter::WARN);
+        assert_eq!(parse_level("warning").expect("warn"), LevelFilter::WARN);
+        assert_eq!(parse_level("off").expect("off"), LevelFilter::OFF);
+        assert!(parse_level("loud").is_err());
+        assert_eq!(LogFormat::parse("JSON").expect("json"), LogFormat::Json);
+        assert!(LogFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn names_the_workspace_of_an_rpc() {
+        assert_eq!(
+            rpc_workspace_id(&json!({ "workspaceId": "ws-1" })),
+            Some("ws-1")
+        );
+        assert_eq!(
+            rpc_workspace_id(&json!({ "parentId": "ws-2" })),
+            Some("ws-2")
+        );
+        assert_eq!(rpc_workspace_id(&json!({ "id": "ws-3" })), None);
+        assert_eq!(rpc_outcome(None), "ok");
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::{BufRead, BufWriter, Write};
+
+use serde_json::{json, Value};
+
+/// When set, the daemon binary answers `--version` and `app-server` like the codex CLI.
+/// The self-test sets it on the daemon it boots and passes the daemon's own path as
+/// `codex_bin`, so sessions talk to this mock instead of a real codex install.
+pub(crate) const MOCK_APP_SERVER_ENV: &str = "CODEX_MONITOR_MOCK_APP_SERVER";
+
+/// Runs the mock for codex-style invocations; `None` means `args` weren't one of them.
+pub(crate) fn run(args: &[String]) -> Option<i32> {
+    match args.last().map(String::as_str) {
+        Some("--version") => {
+            println!("codex-cli 0.0.0-mock");
+            Some(0)
+        }
+        Some("app-server") => Some(match serve() {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("mock app-server: {err}");
+                1
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// Just enough app-server state to answer the calls the daemon makes.
+#[derive(Default)]
+struct MockAppServer {
+    threads: Vec<Value>,
+    turns: u64,
+}
+
+impl MockAppServer {
+    /// Returns the result (or error) and any notifications that follow it.
+    fn handle(&mut self, method: &str, params: &Value) -> (Result<Value, Value>, Vec<Value>) {
+        let result = match method {
+            "initialize" => json!({ "userAgent": "codex-monitor-mock/0.0.0" }),
+            "thread/start" => {
+                let thread = json!({
+                    "id": format!("mock-thread-{}", self.threads.len() + 1),
+                    "preview": "",
+                    "cwd": params.get("cwd").cloned().unwrap_or(Value::Null),
+                    "createdAt": 0,
+                    "turns": [],
+                });
+                self.threads.push(thread.clone());
+                let started = json!({ "method": "thread/started", "params": { "thread": thread } });
+                return (Ok(json!({ "thread": thread })), vec![started]);
+            }
+            "thread/resume" | "thread/fork" => {
+                let thread_id = params.get("threadId").and_then(Value::as_str).unwrap_or("");
+                match self.threads.iter().find(|thread| thread["id"] == thread_id) {
+                    Some(thread) => json!({ "thread": thread }),
+                    None => {
+                        return (
+                            Err(json!({ "code": -32602, "message": "unknown thread" })),
+                            Vec::new(),
+                        )
+                    }
+                }
+            }
+            "thread/list" => json!({ "data": self.threads, "nextCursor": null }),
+            "turn/start" => {
+                self.turns += 1;
+                let thread_id = params.get("threadId").cloned().unwrap_or(Value::Null);
+                let turn_id = format!("mock-turn-{}", self.turns);
+                let text = params
+                    .get("input")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|item| item.get("text").and_then(Value::as_str))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let item_id = format!("{turn_id}-reply");
+                let turn = json!({ "id": turn_id, "status": "inProgress", "items": [] });
+                let notifications = vec![
+                    json!({
+                        "method": "turn/started",
+                        "params": { "threadId": thread_id, "turn": turn },
+                    }),
+                    json!({
+                        "method": "item/agentMessage/delta",
+                        "params": {
+                            "threadId": thread_id,
+                            "turnId": turn_id,
+                            "itemId": item_id,
+                            "delta": format!("echo: {text}"),
+                        },
+                    }),
+                    json!({
+                        "method": "turn/completed",
+                        "params": {
+                            "threadId": thread_id,
+                            "turn": { "id": turn_id, "status": "completed", "items": [] },
+                        },
+                    }),
+                ];
+                return (Ok(json!({ "turn": turn })), notifications);
+            }
+            "model/list" => json!({
+                "data": [{
+                    "id": "mock-model",
+                    "model": "mock-model",
+                    "displayName": "Mock",
+                    "isDefault": true,
+                }],
+                "nextCursor": null,
+            }),
+            "skills/list" | "app/list" | "mcpServerStatus/list" | "collaborationMode/list" => {
+                json!({ "data": [], "nextCursor": null })
+            }
+            "account/read" => json!({ "account": null, "requiresOpenaiAuth": false }),
+            "thread/archive" | "thread/name/set" | "thread/compact/start" | "turn/interrupt" => {
+                json!({})
+            }
+            _ => {
+                let message = format!("mock app-server does not implement {method}");
+                let error = json!({ "code": -32601, "message": message });
+                return (Err(error), Vec::new());
+            }
+        };
+        (Ok(result), Vec::new())
+    }
+}
+
+fn serve() -> Result<(), String> {
+    let stdin = std::io::stdin();
+    let mut out = BufWriter::new(std::io::stdout());
+    let mut server = MockAppServer::default();
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        // Notifications (`initialized`) and responses to server requests need no reply.
+        let (Some(id), Some(method)) = (
+            message.get("id"),
+            message.get("method").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let (result, notifications) = server.handle(method, &params);
+        let response = match result {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err(error) => json!({ "id": id, "error": error }),
+        };
+        for message in std::iter::once(response).chain(notifications) {
+            writeln!(out, "{message}").map_err(|err| err.to_string())?;
+        }
+        out.flush().map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
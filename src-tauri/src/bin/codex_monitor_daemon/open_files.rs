@@ -0,0 +1,513 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use std::sync::Mutex;
+
+use crate::shared::thumbnails_core::file_change_paths;
+
+/// A file a client has open in its editor, as registered with `open_files`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct OpenFile {
+    /// Relative to the workspace root.
+    pub(crate) path: String,
+    /// SHA-256 of the content the editor loaded, when the client knows it.
+    #[serde(default)]
+    pub(crate) hash: Option<String>,
+}
+
+/// Params of the `file-conflict` event, sent only to `client_id`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileConflict {
+    #[serde(skip)]
+    pub(crate) client_id: String,
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: Option<String>,
+    pub(crate) item_id: Option<String>,
+    pub(crate) path: String,
+    /// SHA-256 of what the agent left on disk; `None` when it deleted the file.
+    pub(crate) hash: Option<String>,
+    /// The hash the client last registered or was told about.
+    pub(crate) previous_hash: Option<String>,
+    pub(crate) deleted: bool,
+}
+
+/// Files a completed `fileChange` item or a `commit_write` wrote, for conflict checks.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AgentWrite {
+    pub(crate) thread_id: Option<String>,
+    pub(crate) item_id: Option<String>,
+    /// Paths as the item reported them, including deleted files.
+    pub(crate) paths: Vec<String>,
+}
+
+/// The agent's file writes in an `item/completed` event; `None` for anything else.
+pub(crate) fn agent_write(message: &Value) -> Option<AgentWrite> {
+    if message.get("method").and_then(Value::as_str) != Some("item/completed") {
+        return None;
+    }
+    let params = message.get("params")?;
+    let item = params.get("item")?;
+    if item.get("type").and_then(Value::as_str) != Some("fileChange") {
+        return None;
+    }
+    let status = item.get("status").and_then(Value::as_str).unwrap_or("");
+    if matches!(status, "failed" | "declined") {
+        return None;
+    }
+    let paths = file_change_paths(item)
+        .into_iter()
+        .map(|(path, _)| path.to_string())
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        return None;
+    }
+    Some(AgentWrite {
+        thread_id: params
+            .get("threadId")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        item_id: item.get("id").and_then(Value::as_str).map(str::to_string),
+        paths,
+    })
+}
+
+/// `path` relative to the workspace `root`, with `/` separators. Absolute paths outside
+/// the root and paths climbing out with `..` give `None`.
+pub(crate) fn workspace_relative(root: &Path, path: &str) -> Option<String> {
+    let path = Path::new(path);
+    let relative = if path.is_absolute() {
+        path.strip_prefix(root).ok()?
+    } else {
+        path
+    };
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// The file a client is looking at, set with `focus_file`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileFocus {
+    pub(crate) workspace_id: String,
+    /// Relative to the workspace root.
+    pub(crate) path: String,
+    /// Whether the client is editing the file rather than just viewing it. Only one
+    /// client edits a file at a time.
+    pub(crate) editing: bool,
+}
+
+/// Params of the `editor-presence` event: one client's files in one workspace.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EditorPresence {
+    pub(crate) client_id: String,
+    pub(crate) device_name: Option<String>,
+    pub(crate) workspace_id: String,
+    /// Sorted; empty once the client closed everything or disconnected.
+    pub(crate) open_paths: Vec<String>,
+    pub(crate) focused_path: Option<String>,
+    pub(crate) editing: bool,
+}
+
+/// What one client has open and in focus.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClientOpenFiles {
+    /// Path → last known hash, per workspace.
+    open: HashMap<String, HashMap<String, Option<String>>>,
+    focus: Option<FileFocus>,
+}
+
+impl ClientOpenFiles {
+    fn is_empty(&self) -> bool {
+        self.open.is_empty() && self.focus.is_none()
+    }
+
+    /// Workspaces the client has a file open or in focus in, sorted.
+    pub(crate) fn workspace_ids(&self) -> Vec<String> {
+        let mut ids = self
+            .open
+            .keys()
+            .chain(self.focus.iter().map(|focus| &focus.workspace_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    fn presence(&self, client_id: &str, workspace_id: &str) -> EditorPresence {
+        let mut open_paths = self
+            .open
+            .get(workspace_id)
+            .map(|open| open.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        open_paths.sort();
+        let focus = self
+            .focus
+            .as_ref()
+            .filter(|focus| focus.workspace_id == workspace_id);
+        EditorPresence {
+            client_id: client_id.to_string(),
+            device_name: None,
+            workspace_id: workspace_id.to_string(),
+            open_paths,
+            focused_path: focus.map(|focus| focus.path.clone()),
+            editing: focus.is_some_and(|focus| focus.editing),
+        }
+    }
+}
+
+/// What `OpenFiles::focus` changed, so the daemon can broadcast presence.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FocusChange {
+    /// The client's focus before the call.
+    pub(crate) previous: Option<FileFocus>,
+    /// The client whose edit session `force` took over.
+    pub(crate) taken_from: Option<String>,
+}
+
+/// What each connected client has open, keyed by client id.
+#[derive(Default)]
+pub(crate) struct OpenFiles {
+    clients: Mutex<HashMap<String, ClientOpenFiles>>,
+}
+
+impl OpenFiles {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ClientOpenFiles>> {
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Replaces the client's open files in one workspace; returns them sorted by path.
+    /// Paths are expected to be workspace-relative already.
+    pub(crate) fn set(
+        &self,
+        client_id: &str,
+        workspace_id: &str,
+        files: Vec<OpenFile>,
+    ) -> Vec<OpenFile> {
+        let mut clients = self.lock();
+        let entry = clients.entry(client_id.to_string()).or_default();
+        if files.is_empty() {
+            entry.open.remove(workspace_id);
+            if entry.is_empty() {
+                clients.remove(client_id);
+            }
+            return Vec::new();
+        }
+        let open = files
+            .into_iter()
+            .map(|file| (file.path, file.hash))
+            .collect::<HashMap<_, _>>();
+        let mut listed = open
+            .iter()
+            .map(|(path, hash)| OpenFile {
+                path: path.clone(),
+                hash: hash.clone(),
+            })
+            .collect::<Vec<_>>();
+        listed.sort_by(|a, b| a.path.cmp(&b.path));
+        entry.open.insert(workspace_id.to_string(), open);
+        listed
+    }
+
+    /// Moves the client's focus; `None` clears it. Focusing a file another client is
+    /// editing with `editing` set fails with that client's id, unless `force` takes the
+    /// edit session over.
+    pub(crate) fn focus(
+        &self,
+        client_id: &str,
+        focus: Option<FileFocus>,
+        force: bool,
+    ) -> Result<FocusChange, String> {
+        let mut clients = self.lock();
+        let mut taken_from = None;
+        if let Some(focus) = focus.as_ref().filter(|focus| focus.editing) {
+            let editor = clients.iter_mut().find(|(id, entry)| {
+                id.as_str() != client_id
+                    && entry.focus.as_ref().is_some_and(|held| {
+                        held.editing
+                            && held.workspace_id == focus.workspace_id
+                            && held.path == focus.path
+                    })
+            });
+            if let Some((editor_id, entry)) = editor {
+                if !force {
+                    return Err(editor_id.clone());
+                }
+                if let Some(held) = entry.focus.as_mut() {
+                    held.editing = false;
+                }
+                taken_from = Some(editor_id.clone());
+            }
+        }
+        let entry = clients.entry(client_id.to_string()).or_default();
+        let previous = std::mem::replace(&mut entry.focus, focus);
+        if entry.is_empty() {
+            clients.remove(client_id);
+        }
+        Ok(FocusChange {
+            previous,
+            taken_from,
+        })
+    }
+
+    /// Drops a closed connection's registrations and hands them back for parking.
+    pub(crate) fn take(&self, client_id: &str) -> ClientOpenFiles {
+        self.lock().remove(client_id).unwrap_or_default()
+    }
+
+    /// Moves parked registrations onto the connection that resumed them.
+    pub(crate) fn restore(&self, client_id: &str, files: ClientOpenFiles) {
+        if !files.is_empty() {
+            self.lock().insert(client_id.to_string(), files);
+        }
+    }
+
+    pub(crate) fn is_open(&self, workspace_id: &str, path: &str) -> bool {
+        self.lock().values().any(|entry| {
+            entry
+                .open
+                .get(workspace_id)
+                .is_some_and(|open| open.contains_key(path))
+        })
+    }
+
+    /// One client's files in a workspace; empty lists when it has none.
+    pub(crate) fn presence(&self, client_id: &str, workspace_id: &str) -> EditorPresence {
+        self.lock()
+            .get(client_id)
+            .cloned()
+            .unwrap_or_default()
+            .presence(client_id, workspace_id)
+    }
+
+    /// Every client's files, per workspace, optionally limited to one workspace.
+    pub(crate) fn list_presence(&self, workspace_id: Option<&str>) -> Vec<EditorPresence> {
+        let mut listed = Vec::new();
+        for (client_id, entry) in self.lock().iter() {
+            for id in entry.workspace_ids() {
+                if workspace_id.is_none_or(|wanted| wanted == id) {
+                    listed.push(entry.presence(client_id, &id));
+                }
+            }
+        }
+        listed.sort_by(|a, b| {
+            a.workspace_id
+                .cmp(&b.workspace_id)
+                .then(a.client_id.cmp(&b.client_id))
+        });
+        listed
+    }
+
+    /// Clients holding `path` with content other than `hash`, each with the hash it had.
+    /// They are recorded as holding `hash` now, so the same write isn't reported twice.
+    pub(crate) fn conflicts(
+        &self,
+        workspace_id: &str,
+        path: &str,
+        hash: Option<&str>,
+    ) -> Vec<(String, Option<String>)> {
+        let mut conflicts = Vec::new();
+        for (client_id, entry) in self.lock().iter_mut() {
+            let Some(known) = entry
+                .open
+                .get_mut(workspace_id)
+                .and_then(|open| open.get_mut(path))
+            else {
+                continue;
+            };
+            if known.is_some() && known.as_deref() == hash {
+                continue;
+            }
+            let previous = std::mem::replace(known, hash.map(str::to_string));
+            conflicts.push((client_id.clone(), previous));
+        }
+        conflicts.sort();
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn open(path: &str, hash: Option<&str>) -> OpenFile {
+        OpenFile {
+            path: path.to_string(),
+            hash: hash.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn reports_each_write_once_per_client() {
+        let files = OpenFiles::default();
+        files.set("phone", "ws-1", vec![open("src/main.rs", Some("aaa"))]);
+        files.set("laptop", "ws-1", vec![open("src/main.rs", None)]);
+        files.set("laptop", "ws-2", vec![open("src/main.rs", Some("aaa"))]);
+
+        assert_eq!(
+            files.conflicts("ws-1", "src/main.rs", Some("aaa")),
+            vec![("laptop".to_string(), None)]
+        );
+        assert_eq!(
+            files.conflicts("ws-1", "src/main.rs", Some("bbb")),
+            vec![
+                ("laptop".to_string(), Some("aaa".to_string())),
+                ("phone".to_string(), Some("aaa".to_string())),
+            ]
+        );
+        assert!(files
+            .conflicts("ws-1", "src/main.rs", Some("bbb"))
+            .is_empty());
+        assert!(files.conflicts("ws-1", "README.md", Some("bbb")).is_empty());
+    }
+
+    #[test]
+    fn set_replaces_and_take_restores() {
+        let files = OpenFiles::default();
+        files.set(
+            "phone",
+            "ws-1",
+            vec![open("a.rs", None), open("b.rs", None)],
+        );
+        let listed = files.set("phone", "ws-1", vec![open("b.rs", Some("bbb"))]);
+        assert_eq!(listed, vec![open("b.rs", Some("bbb"))]);
+        assert!(!files.is_open("ws-1", "a.rs"));
+
+        let parked = files.take("phone");
+        assert!(!files.is_open("ws-1", "b.rs"));
+        files.restore("phone-2", parked);
+        assert!(files.is_open("ws-1", "b.rs"));
+
+        files.set("phone-2", "ws-1", Vec::new());
+        assert!(files.take("phone-2").is_empty());
+    }
+
+    fn focus(workspace_id: &str, path: &str, editing: bool) -> Option<FileFocus> {
+        Some(FileFocus {
+            workspace_id: workspace_id.to_string(),
+            path: path.to_string(),
+            editing,
+        })
+    }
+
+    #[test]
+    fn one_client_edits_a_file_at_a_time() {
+        let files = OpenFiles::default();
+        files
+            .focus("laptop", focus("ws-1", "a.rs", true), false)
+            .expect("laptop edits");
+        files
+            .focus("phone", focus("ws-1", "a.rs", false), false)
+            .expect("viewing is always allowed");
+        assert_eq!(
+            files.focus("phone", focus("ws-1", "a.rs", true), false),
+            Err("laptop".to_string())
+        );
+
+        let change = files
+            .focus("phone", focus("ws-1", "a.rs", true), true)
+            .expect("forced");
+        assert_eq!(change.taken_from.as_deref(), Some("laptop"));
+        assert_eq!(change.previous, focus("ws-1", "a.rs", false));
+        assert!(!files.presence("laptop", "ws-1").editing);
+        assert!(files.presence("phone", "ws-1").editing);
+    }
+
+    #[test]
+    fn lists_presence_per_client_and_workspace() {
+        let files = OpenFiles::default();
+        files.set(
+            "phone",
+            "ws-1",
+            vec![open("b.rs", None), open("a.rs", None)],
+        );
+        files
+            .focus("phone", focus("ws-2", "main.rs", false), false)
+            .expect("focus");
+        files
+            .focus("laptop", focus("ws-1", "a.rs", true), false)
+            .expect("focus");
+
+        let listed = files.list_presence(None);
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed[0].client_id, "laptop");
+        assert_eq!(listed[0].focused_path.as_deref(), Some("a.rs"));
+        assert_eq!(listed[1].open_paths, vec!["a.rs", "b.rs"]);
+        assert_eq!(listed[1].focused_path, None);
+        assert_eq!(files.list_presence(Some("ws-2")).len(), 1);
+
+        files.focus("laptop", None, false).expect("blur");
+        assert_eq!(files.list_presence(Some("ws-1")).len(), 1);
+        assert_eq!(
+            files.take("phone").workspace_ids(),
+            vec!["ws-1".to_string(), "ws-2".to_string()]
+        );
+    }
+
+    #[test]
+    fn resolves_paths_against_the_workspace_root() {
+        let root = Path::new("/work/app");
+        assert_eq!(
+            workspace_relative(root, "/work/app/src/lib.rs").as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(
+            workspace_relative(root, "./src/lib.rs").as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(workspace_relative(root, "/work/other/lib.rs"), None);
+        assert_eq!(workspace_relative(root, "../lib.rs"), None);
+    }
+
+    #[test]
+    fn reads_completed_file_changes_only() {
+        let write = agent_write(&json!({
+            "method": "item/completed",
+            "params": {
+                "threadId": "thread-1",
+                "item": {
+                    "id": "item-1",
+                    "type": "fileChange",
+                    "status": "completed",
+                    "changes": [
+                        { "path": "src/lib.rs", "kind": { "type": "update" } },
+                        { "path": "old.rs", "kind": "delete" }
+                    ]
+                }
+            }
+        }))
+        .expect("file change");
+        assert_eq!(write.thread_id.as_deref(), Some("thread-1"));
+        assert_eq!(
+            write.paths,
+            vec!["src/lib.rs".to_string(), "old.rs".to_string()]
+        );
+
+        assert!(agent_write(&json!({
+            "method": "item/completed",
+            "params": { "item": { "type": "fileChange", "status": "declined",
+                "changes": [{ "path": "a.rs", "kind": "add" }] } }
+        }))
+        .is_none());
+        assert!(agent_write(&json!({
+            "method": "item/started",
+            "params": { "item": { "type": "fileChange",
+                "changes": [{ "path": "a.rs", "kind": "add" }] } }
+        }))
+        .is_none());
+    }
+}
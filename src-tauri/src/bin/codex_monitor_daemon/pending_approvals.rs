@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::audit::now_millis;
+use crate::shared::data_files_core::PENDING_APPROVALS_FILE;
+
+/// A server request from a workspace's app-server still waiting for
+/// `respond_to_server_request`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PendingApproval {
+    pub(crate) workspace_id: String,
+    pub(crate) request_id: Value,
+    pub(crate) method: String,
+    /// The full app-server message, re-emitted verbatim to reconnecting clients.
+    pub(crate) message: Value,
+    pub(crate) received_at: u64,
+}
+
+/// Pending server requests per workspace, mirrored to `<data-dir>/pending-approvals.json`
+/// so they outlive the client connection that first saw them.
+pub(crate) struct PendingApprovals {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Vec<PendingApproval>>>,
+}
+
+impl PendingApprovals {
+    pub(crate) fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(PENDING_APPROVALS_FILE);
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<PendingApproval>>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Inspects an app-server message: server requests are recorded, and a fresh
+    /// `codex/connected` drops the workspace's requests because their ids belonged
+    /// to the previous app-server process.
+    pub(crate) fn observe(&self, workspace_id: &str, message: &Value) {
+        let method = message.get("method").and_then(Value::as_str);
+        let request_id = message.get("id");
+        match (method, request_id) {
+            (Some("codex/connected"), None) => {
+                let mut entries = self.lock();
+                if entries.remove(workspace_id).is_some() {
+                    self.persist(&entries);
+                }
+            }
+            (Some(method), Some(request_id)) => {
+                let mut entries = self.lock();
+                let pending = entries.entry(workspace_id.to_string()).or_default();
+                pending.retain(|entry| &entry.request_id != request_id);
+                pending.push(PendingApproval {
+                    workspace_id: workspace_id.to_string(),
+                    request_id: request_id.clone(),
+                    method: method.to_string(),
+                    message: message.clone(),
+                    received_at: now_millis(),
+                });
+                self.persist(&entries);
+            }
+            _ => {}
+        }
+    }
+
+    /// Forgets a request once it has been answered; returns whether it was pending.
+    pub(crate) fn resolve(&self, workspace_id: &str, request_id: &Value) -> bool {
+        let mut entries = self.lock();
+        let Some(pending) = entries.get_mut(workspace_id) else {
+            return false;
+        };
+        let before = pending.len();
+        pending.retain(|entry| &entry.request_id != request_id);
+        let removed = pending.len() != before;
+        if pending.is_empty() {
+            entries.remove(workspace_id);
+        }
+        if removed {
+            self.persist(&entries);
+        }
+        removed
+    }
+
+    /// Drops requests of workspaces that no longer exist.
+    pub(crate) fn retain_workspaces(&self, keep: impl Fn(&str) -> bool) {
+        let mut entries = self.lock();
+        let before = entries.len();
+        entries.retain(|workspace_id, _| keep(workspace_id));
+        if entries.len() != before {
+            self.persist(&entries);
+        }
+    }
+
+    /// Pending requests, oldest first, optionally limited to one workspace.
+    pub(crate) fn list(&self, workspace_id: Option<&str>) -> Vec<PendingApproval> {
+        let entries = self.lock();
+        let mut pending = entries
+            .iter()
+            .filter(|(id, _)| workspace_id.is_none_or(|wanted| wanted == id.as_str()))
+            .flat_map(|(_, pending)| pending.iter().cloned())
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|entry| entry.received_at);
+        pending
+    }
+
+    fn persist(&self, entries: &HashMap<String, Vec<PendingApproval>>) {
+        if let Err(err) = write_entries(&self.path, entries) {
+            tracing::error!(
+                "Failed to write pending approvals {}: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+fn write_entries(
+    path: &Path,
+    entries: &HashMap<String, Vec<PendingApproval>>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(entries).map_err(|err| err.to_string())?;
+    std::fs::write(path, data).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn records_resolves_and_reloads_requests() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-approvals-{}", Uuid::new_v4()));
+        let approvals = PendingApprovals::load(&dir);
+        approvals.observe(
+            "ws-1",
+            &json!({ "id": 7, "method": "item/commandExecution/requestApproval", "params": {} }),
+        );
+        approvals.observe("ws-1", &json!({ "method": "turn/started", "params": {} }));
+        approvals.observe("ws-2", &json!({ "id": 3, "method": "item/fileChange/requestApproval" }));
+        assert_eq!(approvals.list(None).len(), 2);
+        assert_eq!(approvals.list(Some("ws-1"))[0].request_id, json!(7));
+
+        let reloaded = PendingApprovals::load(&dir);
+        assert_eq!(reloaded.list(None).len(), 2);
+
+        assert!(reloaded.resolve("ws-1", &json!(7)));
+        assert!(!reloaded.resolve("ws-1", &json!(7)));
+        reloaded.observe(
+            "ws-2",
+            &json!({ "method": "codex/connected", "params": { "workspaceId": "ws-2" } }),
+        );
+        assert!(reloaded.list(None).is_empty());
+        assert!(PendingApprovals::load(&dir).list(None).is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
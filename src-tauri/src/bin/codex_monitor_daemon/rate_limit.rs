@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::jsonrpc;
+
+/// `--rate-limit` and `--max-in-flight`, applied to each client connection on its own.
+/// Both are off unless set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct RateLimits {
+    pub(crate) requests_per_sec: Option<u32>,
+    /// Requests a connection may have running at once. Each holds its slot until it
+    /// has been served.
+    pub(crate) max_in_flight: Option<usize>,
+}
+
+impl RateLimits {
+    pub(crate) fn parse_requests_per_sec(&mut self, value: &str) -> Result<(), String> {
+        let rate = value
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| "--rate-limit requires a number of requests per second")?;
+        self.requests_per_sec = Some(rate).filter(|rate| *rate > 0);
+        Ok(())
+    }
+
+    pub(crate) fn parse_max_in_flight(&mut self, value: &str) -> Result<(), String> {
+        let max = value
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| "--max-in-flight requires a number of requests")?;
+        self.max_in_flight = Some(max).filter(|max| *max > 0);
+        Ok(())
+    }
+
+    pub(crate) fn limiter(&self) -> ConnectionLimiter {
+        ConnectionLimiter {
+            limits: *self,
+            in_flight: self.max_in_flight.map(|max| Arc::new(Semaphore::new(max))),
+            tokens: self.requests_per_sec.map(f64::from).unwrap_or(0.0),
+            refilled: Instant::now(),
+        }
+    }
+}
+
+/// Why a message was turned away.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Limited {
+    Rate {
+        requests_per_sec: u32,
+        retry_after: Duration,
+    },
+    InFlight {
+        max_in_flight: usize,
+    },
+}
+
+impl Limited {
+    /// The `rate_limited` JSON-RPC error object.
+    pub(crate) fn to_json(&self) -> Value {
+        let (message, data) = match self {
+            Self::Rate {
+                requests_per_sec,
+                retry_after,
+            } => (
+                format!("rate limited: more than {requests_per_sec} requests per second"),
+                json!({
+                    "reason": "rate",
+                    "requestsPerSec": requests_per_sec,
+                    "retryAfterMs": retry_after.as_millis().max(1) as u64,
+                }),
+            ),
+            Self::InFlight { max_in_flight } => (
+                format!("rate limited: more than {max_in_flight} requests in flight"),
+                json!({ "reason": "inFlight", "maxInFlight": max_in_flight }),
+            ),
+        };
+        jsonrpc::error_object(jsonrpc::RATE_LIMITED, &message, Some(data))
+    }
+}
+
+/// One connection's in-flight slots and its token bucket, which holds one second's worth
+/// of requests as burst.
+#[derive(Debug)]
+pub(crate) struct ConnectionLimiter {
+    limits: RateLimits,
+    in_flight: Option<Arc<Semaphore>>,
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// An admitted request's in-flight slot, given back when it is dropped.
+#[derive(Debug)]
+pub(crate) struct InFlight {
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl ConnectionLimiter {
+    /// Keep the returned slot until the request has been served.
+    pub(crate) fn admit(&mut self) -> Result<InFlight, Limited> {
+        self.admit_at(Instant::now())
+    }
+
+    fn admit_at(&mut self, now: Instant) -> Result<InFlight, Limited> {
+        let slot = match (&self.in_flight, self.limits.max_in_flight) {
+            (Some(slots), Some(max_in_flight)) => Some(
+                Arc::clone(slots)
+                    .try_acquire_owned()
+                    .map_err(|_| Limited::InFlight { max_in_flight })?,
+            ),
+            _ => None,
+        };
+        let Some(requests_per_sec) = self.limits.requests_per_sec else {
+            return Ok(InFlight { _slot: slot });
+        };
+        let rate = f64::from(requests_per_sec);
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(InFlight { _slot: slot });
+        }
+        Err(Limited::Rate {
+            requests_per_sec,
+            retry_after: Duration::from_secs_f64((1.0 - self.tokens) / rate),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket_allows_a_second_of_burst_then_refills() {
+        let mut limits = RateLimits::default();
+        limits.parse_requests_per_sec("4").expect("rate");
+        let mut limiter = limits.limiter();
+        let start = limiter.refilled;
+        let at = |millis: u64| start + Duration::from_millis(millis);
+        for _ in 0..4 {
+            assert!(limiter.admit_at(at(0)).is_ok());
+        }
+        let Err(Limited::Rate { retry_after, .. }) = limiter.admit_at(at(0)) else {
+            panic!("fifth request in the same instant is limited");
+        };
+        assert_eq!(retry_after, Duration::from_millis(250));
+        assert!(limiter.admit_at(at(250)).is_ok());
+        assert!(limiter.admit_at(at(260)).is_err());
+        for _ in 0..4 {
+            assert!(limiter.admit_at(at(10_000)).is_ok());
+        }
+        assert!(
+            limiter.admit_at(at(10_000)).is_err(),
+            "idle time doesn't bank more than one second"
+        );
+    }
+
+    #[test]
+    fn caps_overlapping_requests_until_one_completes() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        let mut limits = RateLimits::default();
+        limits.parse_max_in_flight("2").expect("max in flight");
+        let mut limiter = limits.limiter();
+        runtime.block_on(async {
+            // Two long-running calls, each holding its slot until told to finish.
+            let mut finish = Vec::new();
+            let mut calls = Vec::new();
+            for _ in 0..2 {
+                let slot = limiter.admit().expect("under the cap");
+                let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+                finish.push(tx);
+                calls.push(tokio::spawn(async move {
+                    let _ = rx.await;
+                    drop(slot);
+                }));
+            }
+            let error = limiter
+                .admit()
+                .expect_err("third overlapping call")
+                .to_json();
+            assert_eq!(error["code"], json!(jsonrpc::RATE_LIMITED));
+            assert_eq!(
+                error["data"],
+                json!({ "reason": "inFlight", "maxInFlight": 2 })
+            );
+
+            let _ = finish.remove(0).send(());
+            calls.remove(0).await.expect("call");
+            let slot = limiter.admit().expect("a slot came free");
+            assert!(limiter.admit().is_err(), "still at the cap");
+            drop(slot);
+            let _ = finish.remove(0).send(());
+            calls.remove(0).await.expect("call");
+        });
+        for _ in 0..100 {
+            assert!(
+                limiter.admit().is_ok(),
+                "calls that don't overlap are never capped and no rate limit unless set"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_structured_errors() {
+        let error = Limited::Rate {
+            requests_per_sec: 5,
+            retry_after: Duration::from_micros(10),
+        }
+        .to_json();
+        assert_eq!(error["data"]["retryAfterMs"], json!(1));
+        assert_eq!(error["data"]["reason"], json!("rate"));
+
+        let mut limits = RateLimits::default();
+        assert!(limits.parse_requests_per_sec("fast").is_err());
+        limits.parse_requests_per_sec("0").expect("zero");
+        assert_eq!(limits.requests_per_sec, None);
+    }
+}
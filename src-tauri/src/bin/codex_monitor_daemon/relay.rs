@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+/// Version of the frame protocol below, sent in `hello`.
+const RELAY_PROTOCOL: u32 = 1;
+const SESSION_BUFFER_BYTES: usize = 64 * 1024;
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+pub(crate) struct RelayConfig {
+    /// `host:port` of the relay.
+    pub(crate) endpoint: String,
+    /// Name clients use to reach this daemon through the relay.
+    pub(crate) daemon_id: String,
+}
+
+/// One line on the relay connection. Each client session the relay accepts is
+/// multiplexed over the single outbound connection as `open`, `data`…, `close`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum RelayFrame {
+    Hello {
+        #[serde(rename = "daemonId")]
+        daemon_id: String,
+        protocol: u32,
+    },
+    Open {
+        session: String,
+        #[serde(rename = "remoteAddr", default)]
+        remote_addr: Option<String>,
+    },
+    /// One protocol line, exactly as a directly connected client would send or read it.
+    Data {
+        session: String,
+        line: String,
+    },
+    Close {
+        session: String,
+    },
+}
+
+/// Keeps a connection to the relay open, reconnecting with backoff, and hands each
+/// session the relay opens to `serve` as an in-memory stream plus a label for it.
+pub(crate) async fn run<F>(config: RelayConfig, serve: F)
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    let mut delay = Duration::from_secs(1);
+    loop {
+        match TcpStream::connect(&config.endpoint).await {
+            Ok(stream) => {
+                tracing::info!(
+                    "connected to relay {} as {}",
+                    config.endpoint, config.daemon_id
+                );
+                delay = Duration::from_secs(1);
+                let reason = match serve_uplink(stream, &config.daemon_id, &serve).await {
+                    Ok(()) => "closed by relay".to_string(),
+                    Err(err) => err,
+                };
+                tracing::warn!("relay connection lost: {reason}");
+            }
+            Err(err) => tracing::warn!("failed to connect to relay {}: {err}", config.endpoint),
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+async fn serve_uplink<F>(stream: TcpStream, daemon_id: &str, serve: &F) -> Result<(), String>
+where
+    F: Fn(DuplexStream, String),
+{
+    let (reader, mut writer) = stream.into_split();
+    let (frames_tx, mut frames_rx) = mpsc::unbounded_channel::<RelayFrame>();
+    let write_task = tokio::spawn(async move {
+        while let Some(frame) = frames_rx.recv().await {
+            let Ok(mut line) = serde_json::to_string(&frame) else {
+                continue;
+            };
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+    let _ = frames_tx.send(RelayFrame::Hello {
+        daemon_id: daemon_id.to_string(),
+        protocol: RELAY_PROTOCOL,
+    });
+
+    // Dropping a session's sender ends its writer, which closes the stream the daemon
+    // reads from, so the session sees the same EOF as a dropped socket.
+    let mut sessions: HashMap<String, mpsc::UnboundedSender<String>> = HashMap::new();
+    let mut lines = BufReader::new(reader).lines();
+    let result = loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err.to_string()),
+        };
+        let Ok(frame) = serde_json::from_str::<RelayFrame>(&line) else {
+            continue;
+        };
+        match frame {
+            RelayFrame::Open {
+                session,
+                remote_addr,
+            } => {
+                if sessions.contains_key(&session) {
+                    continue;
+                }
+                let label = format!("relay:{}", remote_addr.unwrap_or_else(|| session.clone()));
+                let stream = open_session(session.clone(), frames_tx.clone(), &mut sessions);
+                serve(stream, label);
+            }
+            RelayFrame::Data { session, line } => {
+                let delivered = sessions
+                    .get(&session)
+                    .is_some_and(|session| session.send(line).is_ok());
+                if !delivered && sessions.remove(&session).is_none() {
+                    let _ = frames_tx.send(RelayFrame::Close { session });
+                }
+            }
+            RelayFrame::Close { session } => {
+                sessions.remove(&session);
+            }
+            RelayFrame::Hello { .. } => {}
+        }
+    };
+    drop(sessions);
+    write_task.abort();
+    result
+}
+
+/// Wires a new session to the uplink and returns the daemon's end of it.
+fn open_session(
+    session: String,
+    frames_tx: mpsc::UnboundedSender<RelayFrame>,
+    sessions: &mut HashMap<String, mpsc::UnboundedSender<String>>,
+) -> DuplexStream {
+    let (daemon_side, relay_side) = tokio::io::duplex(SESSION_BUFFER_BYTES);
+    let (relay_reader, mut relay_writer) = tokio::io::split(relay_side);
+
+    let (incoming_tx, mut incoming_rx) = mpsc::unbounded_channel::<String>();
+    sessions.insert(session.clone(), incoming_tx);
+    tokio::spawn(async move {
+        while let Some(line) = incoming_rx.recv().await {
+            if relay_writer.write_all(line.as_bytes()).await.is_err()
+                || relay_writer.write_all(b"\n").await.is_err()
+            {
+                break;
+            }
+        }
+        let _ = relay_writer.shutdown().await;
+    });
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(relay_reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let frame = RelayFrame::Data {
+                session: session.clone(),
+                line,
+            };
+            if frames_tx.send(frame).is_err() {
+                return;
+            }
+        }
+        let _ = frames_tx.send(RelayFrame::Close { session });
+    });
+
+    daemon_side
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn read_frame(
+        lines: &mut tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    ) -> RelayFrame {
+        let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+            .await
+            .expect("frame in time")
+            .expect("read")
+            .expect("open connection");
+        serde_json::from_str(&line).expect("frame")
+    }
+
+    async fn send_frame(writer: &mut tokio::net::tcp::OwnedWriteHalf, frame: RelayFrame) {
+        let line = serde_json::to_string(&frame).unwrap() + "\n";
+        writer.write_all(line.as_bytes()).await.unwrap();
+    }
+
+    #[test]
+    fn multiplexes_sessions_over_one_connection() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let relay = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let config = RelayConfig {
+                endpoint: relay.local_addr().unwrap().to_string(),
+                daemon_id: "laptop".to_string(),
+            };
+            // Echoes each line back with the session label, until the session closes.
+            tokio::spawn(run(config, |stream, label| {
+                tokio::spawn(async move {
+                    let (reader, mut writer) = tokio::io::split(stream);
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let reply = format!("{label} {line}\n");
+                        writer.write_all(reply.as_bytes()).await.unwrap();
+                    }
+                });
+            }));
+
+            let (uplink, _) = relay.accept().await.unwrap();
+            let (reader, mut writer) = uplink.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            assert_eq!(
+                read_frame(&mut lines).await,
+                RelayFrame::Hello {
+                    daemon_id: "laptop".to_string(),
+                    protocol: RELAY_PROTOCOL,
+                }
+            );
+
+            for (session, addr) in [("a", "10.0.0.1:1"), ("b", "10.0.0.2:2")] {
+                send_frame(
+                    &mut writer,
+                    RelayFrame::Open {
+                        session: session.to_string(),
+                        remote_addr: Some(addr.to_string()),
+                    },
+                )
+                .await;
+            }
+            for session in ["b", "a"] {
+                send_frame(
+                    &mut writer,
+                    RelayFrame::Data {
+                        session: session.to_string(),
+                        line: format!("ping-{session}"),
+                    },
+                )
+                .await;
+            }
+            let mut replies = vec![read_frame(&mut lines).await, read_frame(&mut lines).await];
+            replies.sort_by_key(|frame| format!("{frame:?}"));
+            assert_eq!(
+                replies,
+                vec![
+                    RelayFrame::Data {
+                        session: "a".to_string(),
+                        line: "relay:10.0.0.1:1 ping-a".to_string(),
+                    },
+                    RelayFrame::Data {
+                        session: "b".to_string(),
+                        line: "relay:10.0.0.2:2 ping-b".to_string(),
+                    },
+                ]
+            );
+
+            send_frame(
+                &mut writer,
+                RelayFrame::Close {
+                    session: "a".to_string(),
+                },
+            )
+            .await;
+            assert_eq!(
+                read_frame(&mut lines).await,
+                RelayFrame::Close {
+                    session: "a".to_string(),
+                }
+            );
+            send_frame(
+                &mut writer,
+                RelayFrame::Data {
+                    session: "a".to_string(),
+                    line: "late".to_string(),
+                },
+            )
+            .await;
+            assert_eq!(
+                read_frame(&mut lines).await,
+                RelayFrame::Close {
+                    session: "a".to_string(),
+                }
+            );
+        });
+    }
+}
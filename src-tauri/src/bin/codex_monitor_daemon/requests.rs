@@ -0,0 +1,1081 @@
+use std::cell::RefCell;
+use std::fmt::Write as _;
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::file_policy::{FileKind, FileScope};
+use crate::open_files::OpenFile;
+use crate::rpc_timeouts::RpcError;
+pub(crate) use crate::shared::codex_core::SendUserMessageRequest;
+use crate::shared::list_cache_core::ListOptions;
+use crate::shared::safety_policy_core::{SafetyPolicyBundle, SafetyPolicyImportMode};
+use crate::shared::search_index_core::SearchOptions;
+use crate::shared::time_tracking_core::TimePeriod;
+use crate::types::{AppSettings, WorkspaceSettings};
+
+/// Params that don't fit a method's request type, answered with `-32602`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InvalidParams(pub(crate) String);
+
+impl From<InvalidParams> for RpcError {
+    fn from(error: InvalidParams) -> Self {
+        Self::InvalidParams(error.0)
+    }
+}
+
+impl From<InvalidParams> for String {
+    fn from(error: InvalidParams) -> Self {
+        error.0
+    }
+}
+
+/// Deserializes RPC params into a typed request. Missing params count as `{}`; anything
+/// but an object is rejected. Errors name the offending field, e.g.
+/// `` `settings.codexArgs`: invalid type: integer `1`, expected a string ``.
+pub(crate) fn parse_request<T: DeserializeOwned>(params: &Value) -> Result<T, InvalidParams> {
+    let empty = Value::Object(Map::new());
+    let params = match params {
+        Value::Null => &empty,
+        Value::Object(_) => params,
+        _ => return Err(InvalidParams("params must be an object".to_string())),
+    };
+    let path = RefCell::new(Vec::new());
+    T::deserialize(Tracked {
+        value: params,
+        path: &path,
+    })
+    .map_err(|err| {
+        let path = path.into_inner();
+        if path.is_empty() {
+            return InvalidParams(err.to_string());
+        }
+        let mut rendered = String::new();
+        for segment in &path {
+            match segment {
+                Segment::Key(key) if rendered.is_empty() => rendered.push_str(key),
+                Segment::Key(key) => {
+                    let _ = write!(rendered, ".{key}");
+                }
+                Segment::Index(index) => {
+                    let _ = write!(rendered, "[{index}]");
+                }
+            }
+        }
+        InvalidParams(format!("`{rendered}`: {err}"))
+    })
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A `&Value` deserializer that records the path to the entry being read, so the path
+/// left behind after an error points at the field that caused it.
+struct Tracked<'de, 'p> {
+    value: &'de Value,
+    path: &'p RefCell<Vec<Segment>>,
+}
+
+macro_rules! delegate_to_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.value.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, 'p> Deserializer<'de> for Tracked<'de, 'p> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Object(map) => visitor.visit_map(TrackedMap {
+                entries: map.iter(),
+                value: None,
+                path: self.path,
+                pushed: false,
+            }),
+            Value::Array(items) => visitor.visit_seq(TrackedSeq {
+                items: items.iter().enumerate(),
+                path: self.path,
+                pushed: false,
+            }),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.value.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.value.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Array(_) => self.deserialize_any(visitor),
+            other => other.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Object(_) => self.deserialize_any(visitor),
+            other => other.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Object(_) => self.deserialize_any(visitor),
+            other => other.deserialize_struct(name, fields, visitor),
+        }
+    }
+
+    delegate_to_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_identifier deserialize_ignored_any
+    }
+}
+
+struct TrackedMap<'de, 'p> {
+    entries: serde_json::map::Iter<'de>,
+    value: Option<&'de Value>,
+    path: &'p RefCell<Vec<Segment>>,
+    pushed: bool,
+}
+
+impl<'de> MapAccess<'de> for TrackedMap<'de, '_> {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        if std::mem::take(&mut self.pushed) {
+            self.path.borrow_mut().pop();
+        }
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        self.path.borrow_mut().push(Segment::Key(key.clone()));
+        self.pushed = true;
+        seed.deserialize(BorrowedStrDeserializer::new(key.as_str()))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before key"))?;
+        seed.deserialize(Tracked {
+            value,
+            path: self.path,
+        })
+    }
+}
+
+struct TrackedSeq<'de, 'p> {
+    items: std::iter::Enumerate<std::slice::Iter<'de, Value>>,
+    path: &'p RefCell<Vec<Segment>>,
+    pushed: bool,
+}
+
+impl<'de> SeqAccess<'de> for TrackedSeq<'de, '_> {
+    type Error = serde_json::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if std::mem::take(&mut self.pushed) {
+            self.path.borrow_mut().pop();
+        }
+        let Some((index, value)) = self.items.next() else {
+            return Ok(None);
+        };
+        self.path.borrow_mut().push(Segment::Index(index));
+        self.pushed = true;
+        seed.deserialize(Tracked {
+            value,
+            path: self.path,
+        })
+        .map(Some)
+    }
+}
+
+/// Methods that take no params.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct EmptyRequest {}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct IdRequest {
+    pub(crate) id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct PathRequest {
+    pub(crate) path: String,
+}
+
+/// Without `confirmToken` the call only returns a removal plan.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RemoveWorkspaceRequest {
+    pub(crate) id: String,
+    pub(crate) confirm_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct WorkspaceRequest {
+    pub(crate) workspace_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct OptionalWorkspaceRequest {
+    pub(crate) workspace_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ThreadRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct AddWorkspaceRequest {
+    pub(crate) path: String,
+    #[serde(rename = "codex_bin")]
+    pub(crate) codex_bin: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct AddWorktreeRequest {
+    pub(crate) parent_id: String,
+    pub(crate) branch: String,
+    pub(crate) name: Option<String>,
+    pub(crate) copy_agents_md: Option<bool>,
+    /// Overrides the workspace's `protectedBranches`.
+    pub(crate) allow_protected_branch: Option<bool>,
+    /// Overrides the parent's `worktreeBaseRef` for a new branch.
+    pub(crate) base_ref: Option<String>,
+    pub(crate) fetch_base: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FanoutRunRequest {
+    pub(crate) parent_id: String,
+    pub(crate) branches: Vec<String>,
+    pub(crate) prompt: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct DiskUsageRequest {
+    pub(crate) workspace_id: Option<String>,
+    /// Measure now instead of returning the background monitor's last report.
+    #[serde(default)]
+    pub(crate) refresh: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RunRetentionRequest {
+    /// Defaults to `true`: report what the policy would do without doing it.
+    pub(crate) dry_run: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FanoutStatusRequest {
+    pub(crate) run_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct CompareFanoutRequest {
+    pub(crate) run_id: String,
+    pub(crate) run_checks: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FanoutPickRequest {
+    pub(crate) run_id: String,
+    pub(crate) branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RenameWorktreeRequest {
+    pub(crate) id: String,
+    pub(crate) branch: String,
+    pub(crate) allow_protected_branch: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct StaleBranchesReportRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) inactive_days: Option<u32>,
+    /// Branch `merged` is measured against; defaults to `worktreeBaseRef`, then `HEAD`.
+    pub(crate) base: Option<String>,
+}
+
+/// `confirmToken` comes from `stale_branches_report`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct DeleteStaleBranchesRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) branches: Vec<String>,
+    pub(crate) confirm_token: String,
+    /// Delete with `git branch -D`, so branches not merged into `HEAD` go too.
+    pub(crate) force: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ReconcileWorktreesRequest {
+    pub(crate) dry_run: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RenameWorktreeUpstreamRequest {
+    pub(crate) id: String,
+    pub(crate) old_branch: String,
+    pub(crate) new_branch: String,
+    pub(crate) allow_protected_branch: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct UpdateWorkspaceSettingsRequest {
+    pub(crate) id: String,
+    pub(crate) settings: WorkspaceSettings,
+    pub(crate) expected_revision: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct UpdateWorkspaceCodexBinRequest {
+    pub(crate) id: String,
+    #[serde(rename = "codex_bin")]
+    pub(crate) codex_bin: Option<String>,
+    pub(crate) expected_revision: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RelocateWorkspaceRequest {
+    pub(crate) id: String,
+    pub(crate) new_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct WorkspaceFileRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct BeginWriteRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) path: String,
+    /// Total size, if known up front; the commit fails unless exactly this much arrived.
+    #[serde(default)]
+    pub(crate) size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct WriteChunkRequest {
+    pub(crate) write_id: String,
+    pub(crate) offset: u64,
+    /// Base64-encoded bytes.
+    pub(crate) data: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct CommitWriteRequest {
+    pub(crate) write_id: String,
+    #[serde(default)]
+    pub(crate) sha256: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct WriteIdRequest {
+    pub(crate) write_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ResolveMentionsRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct PlanContextRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) text: String,
+    pub(crate) model: Option<String>,
+    pub(crate) budget_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct IndexedSearchRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) query: String,
+    pub(crate) limit: Option<u32>,
+    pub(crate) path_prefix: Option<String>,
+    #[serde(default)]
+    match_case: bool,
+    #[serde(default)]
+    whole_word: bool,
+    #[serde(default)]
+    regex: bool,
+}
+
+impl IndexedSearchRequest {
+    pub(crate) fn options(&self) -> SearchOptions {
+        SearchOptions {
+            match_case: self.match_case,
+            whole_word: self.whole_word,
+            regex: self.regex,
+        }
+    }
+}
+
+/// `patterns: null` turns sparse checkout off.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SetSparseCheckoutRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) patterns: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct WorkspaceSymbolsRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) query: String,
+    pub(crate) limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FindDefinitionRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) path: String,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SearchAllWorkspacesRequest {
+    pub(crate) query: String,
+    pub(crate) workspace_ids: Option<Vec<String>>,
+    pub(crate) limit: Option<u32>,
+    #[serde(default)]
+    match_case: bool,
+    #[serde(default)]
+    whole_word: bool,
+    #[serde(default)]
+    regex: bool,
+}
+
+impl SearchAllWorkspacesRequest {
+    pub(crate) fn options(&self) -> SearchOptions {
+        SearchOptions {
+            match_case: self.match_case,
+            whole_word: self.whole_word,
+            regex: self.regex,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct CommitMessageRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) staged: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct AutoFixRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) goal: Option<String>,
+    /// argv run after a clean build; its failures are fixed too.
+    pub(crate) test_command: Option<Vec<String>>,
+    pub(crate) max_iterations: Option<u32>,
+    pub(crate) max_tokens: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct CiStatusRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct JobStartRequest {
+    pub(crate) kind: String,
+    /// Checked against the job kind's own request type when the job starts.
+    #[serde(default)]
+    pub(crate) params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct JobStatusRequest {
+    pub(crate) id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FileReadRequest {
+    pub(crate) scope: FileScope,
+    pub(crate) kind: FileKind,
+    pub(crate) workspace_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FileWriteRequest {
+    pub(crate) scope: FileScope,
+    pub(crate) kind: FileKind,
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) content: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RevertFileRequest {
+    pub(crate) version: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct UpdateAppSettingsRequest {
+    pub(crate) settings: AppSettings,
+    pub(crate) expected_revision: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct EvaluateApprovalPolicyRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) method: String,
+    #[serde(default)]
+    pub(crate) params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct KickClientRequest {
+    pub(crate) client_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ResumeRequest {
+    pub(crate) ticket: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SubscribeEventsRequest {
+    pub(crate) workspace_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct OpenFilesRequest {
+    pub(crate) workspace_id: String,
+    /// Everything the client has open in the workspace; an empty list clears it.
+    pub(crate) files: Vec<OpenFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FocusFileRequest {
+    pub(crate) workspace_id: String,
+    /// `None` clears the focus.
+    pub(crate) path: Option<String>,
+    #[serde(default)]
+    pub(crate) editing: bool,
+    /// Takes over another client's edit session on the file.
+    #[serde(default)]
+    pub(crate) force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SubscribeSinceRequest {
+    /// The last `eventSeq` the client saw; 0 for everything still buffered.
+    pub(crate) seq: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct MergeRevisionsRequest {
+    #[serde(default)]
+    pub(crate) base: Value,
+    #[serde(default)]
+    pub(crate) current: Value,
+    #[serde(default)]
+    pub(crate) proposed: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct TailTraceRequest {
+    pub(crate) limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SessionLogRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) tail: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct BackupRequest {
+    pub(crate) destination: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RestoreRequest {
+    pub(crate) source: String,
+    pub(crate) confirm: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ImportSafetyPolicyRequest {
+    pub(crate) bundle: SafetyPolicyBundle,
+    pub(crate) mode: Option<SafetyPolicyImportMode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct StartThreadRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) cwd: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SaveDraftRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) text: Option<String>,
+    pub(crate) images: Option<Vec<String>>,
+}
+
+/// A client has the workspace, and optionally one of its threads, in focus.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct TimeTrackingPingRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct TimeTrackingSummaryRequest {
+    /// `day` (default) or `week`.
+    pub(crate) period: Option<TimePeriod>,
+    /// Days (default 7, at most 90) or weeks (default 4, at most 52).
+    pub(crate) count: Option<u32>,
+    pub(crate) workspace_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct PinnedContextRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct PinContextRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) path: Option<String>,
+    pub(crate) start_line: Option<usize>,
+    pub(crate) end_line: Option<usize>,
+    pub(crate) text: Option<String>,
+    pub(crate) label: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct UnpinContextRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) pin_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SaveCollaborationPresetRequest {
+    pub(crate) name: String,
+    pub(crate) description: Option<String>,
+    pub(crate) collaboration_mode: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SaveAccountProfileRequest {
+    pub(crate) name: String,
+    pub(crate) codex_home: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct NameRequest {
+    pub(crate) name: String,
+}
+
+fn list_options(aggregate: Option<bool>, refresh: Option<bool>) -> ListOptions {
+    ListOptions {
+        aggregate: aggregate.unwrap_or(false),
+        refresh: refresh.unwrap_or(false),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ListThreadsRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) cursor: Option<String>,
+    pub(crate) limit: Option<u32>,
+    pub(crate) sort_key: Option<String>,
+    pub(crate) branch: Option<String>,
+    aggregate: Option<bool>,
+    refresh: Option<bool>,
+}
+
+impl ListThreadsRequest {
+    pub(crate) fn options(&self) -> ListOptions {
+        list_options(self.aggregate, self.refresh)
+    }
+}
+
+/// Paginated app-server lists: `list_mcp_server_status` and `apps_list`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ListRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) cursor: Option<String>,
+    pub(crate) limit: Option<u32>,
+    aggregate: Option<bool>,
+    refresh: Option<bool>,
+}
+
+impl ListRequest {
+    pub(crate) fn options(&self) -> ListOptions {
+        list_options(self.aggregate, self.refresh)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct McpOauthLoginRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SetThreadNameRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct TurnInterruptRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RestoreSnapshotRequest {
+    pub(crate) turn_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct StartReviewRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) target: Value,
+    pub(crate) delivery: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SkillsConfigRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) scope: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SkillsSetEnabledRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) path: String,
+    pub(crate) enabled: bool,
+    pub(crate) scope: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RespondToServerRequest {
+    pub(crate) workspace_id: String,
+    #[serde(deserialize_with = "deserialize_request_id")]
+    pub(crate) request_id: Value,
+    pub(crate) result: Value,
+}
+
+/// App-server request ids are JSON-RPC ids: a number or a string.
+fn deserialize_request_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    if value.is_number() || value.is_string() {
+        Ok(value)
+    } else {
+        Err(de::Error::invalid_type(
+            de::Unexpected::Other(&value.to_string()),
+            &"a number or a string",
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct RememberApprovalRuleRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) command: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct FormatRulesRequest {
+    pub(crate) workspace_id: String,
+    #[serde(default)]
+    pub(crate) write: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    struct Outer {
+        workspace_id: String,
+        items: Vec<Inner>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Inner {
+        limit: u32,
+    }
+
+    #[test]
+    fn reports_field_paths_and_rejects_unknown_fields() {
+        let parsed = parse_request::<Outer>(&json!({
+            "workspaceId": "ws-1",
+            "items": [{ "limit": 1 }],
+        }))
+        .expect("valid params");
+        assert_eq!(parsed.workspace_id, "ws-1");
+        assert_eq!(parsed.items[0].limit, 1);
+
+        let error = parse_request::<Outer>(&json!({
+            "workspaceId": "ws-1",
+            "items": [{ "limit": 1 }, { "limit": "two" }],
+        }))
+        .unwrap_err()
+        .0;
+        assert!(
+            error.starts_with("`items[1].limit`: invalid type"),
+            "{error}"
+        );
+
+        let error = parse_request::<Outer>(&json!({ "workspaceId": 7, "items": [] }))
+            .unwrap_err()
+            .0;
+        assert!(error.starts_with("`workspaceId`: invalid type"), "{error}");
+
+        let error = parse_request::<Outer>(&json!({ "items": [{ "limit": 1 }] }))
+            .unwrap_err()
+            .0;
+        assert_eq!(error, "missing field `workspaceId`");
+
+        let error = parse_request::<Outer>(&json!({
+            "workspaceId": "ws-1",
+            "items": [{ "limit": 1, "extra": true }],
+        }))
+        .unwrap_err()
+        .0;
+        assert!(
+            error.starts_with("`items[0].extra`: unknown field `extra`"),
+            "{error}"
+        );
+
+        let error = parse_request::<WorkspaceRequest>(&json!(["ws-1"]))
+            .unwrap_err()
+            .0;
+        assert_eq!(error, "params must be an object");
+        parse_request::<EmptyRequest>(&Value::Null).expect("null params");
+    }
+
+    #[test]
+    fn invalid_params_map_to_their_own_error_code() {
+        let error = RpcError::from(parse_request::<Outer>(&json!({ "items": 1 })).unwrap_err());
+        assert_eq!(error.to_json()["code"], json!(jsonrpc::INVALID_PARAMS));
+        let error = RpcError::MethodNotFound("nope".to_string()).to_json();
+        assert_eq!(error["code"], json!(jsonrpc::METHOD_NOT_FOUND));
+        assert_eq!(error["message"], json!("unknown method: nope"));
+        let error = RpcError::from("params must be an object".to_string()).to_json();
+        assert_eq!(error["code"], json!(jsonrpc::SERVER_ERROR));
+    }
+
+    #[test]
+    fn parses_method_requests() {
+        let request = parse_request::<SendUserMessageRequest>(&json!({
+            "workspaceId": "ws-1",
+            "threadId": "thread-1",
+            "text": "hi",
+            "model": null,
+            "images": ["/tmp/a.png"],
+            "collaborationMode": { "mode": "plan" },
+        }))
+        .expect("send_user_message");
+        assert_eq!(request.images, Some(vec!["/tmp/a.png".to_string()]));
+        assert_eq!(request.collaboration_mode, Some(json!({ "mode": "plan" })));
+
+        let request = parse_request::<ListThreadsRequest>(&json!({
+            "workspaceId": "ws-1",
+            "limit": 20,
+            "aggregate": true,
+            "refresh": null,
+        }))
+        .expect("list_threads");
+        assert!(request.options().aggregate && !request.options().refresh);
+
+        let error = parse_request::<ListRequest>(&json!({
+            "workspaceId": "ws-1",
+            "limit": 5_000_000_000u64,
+        }))
+        .unwrap_err()
+        .0;
+        assert!(error.starts_with("`limit`: invalid value"), "{error}");
+
+        let error = parse_request::<RespondToServerRequest>(&json!({
+            "workspaceId": "ws-1",
+            "requestId": null,
+            "result": {},
+        }))
+        .unwrap_err()
+        .0;
+        assert!(error.starts_with("`requestId`: invalid type"), "{error}");
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// How long a dropped connection's state waits for its client to come back.
+pub(crate) const RESUME_TICKET_TTL: Duration = Duration::from_secs(5 * 60);
+/// How long `redeem` waits for a connection that still looks open to let go of its ticket.
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum Entry<T> {
+    /// The connection holding the ticket is still open; notifying asks it to park and close.
+    Live(Arc<Notify>),
+    Parked {
+        state: T,
+        expires_at: Instant,
+    },
+}
+
+/// Single-use tickets that let a client reconnect into the state its last connection left
+/// behind. A ticket is live while its connection is open and parked once it closes.
+pub(crate) struct ResumeTickets<T> {
+    entries: Mutex<HashMap<String, Entry<T>>>,
+    ttl: Duration,
+}
+
+impl<T> ResumeTickets<T> {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry<T>>> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        entries.retain(|_, entry| match entry {
+            Entry::Live(_) => true,
+            Entry::Parked { expires_at, .. } => *expires_at > now,
+        });
+        entries
+    }
+
+    /// Issues a ticket for an open connection. The returned handle fires when another
+    /// connection redeems the ticket first; the holder should then park and close.
+    pub(crate) fn issue(&self) -> (String, Arc<Notify>) {
+        let ticket = Uuid::new_v4().to_string();
+        let takeover = Arc::new(Notify::new());
+        self.lock()
+            .insert(ticket.clone(), Entry::Live(Arc::clone(&takeover)));
+        (ticket, takeover)
+    }
+
+    /// Keeps `state` until the ticket is redeemed or expires. Revoked tickets stay gone.
+    pub(crate) fn park(&self, ticket: &str, state: T) {
+        if let Some(entry) = self.lock().get_mut(ticket) {
+            *entry = Entry::Parked {
+                state,
+                expires_at: Instant::now() + self.ttl,
+            };
+        }
+    }
+
+    pub(crate) fn revoke(&self, ticket: &str) {
+        self.lock().remove(ticket);
+    }
+
+    /// Takes the state parked under `ticket`. If its connection hasn't noticed the drop
+    /// yet, asks it to park and waits briefly for it to do so.
+    pub(crate) async fn redeem(&self, ticket: &str) -> Option<T> {
+        let deadline = Instant::now() + TAKEOVER_TIMEOUT;
+        let mut asked = false;
+        loop {
+            {
+                let mut entries = self.lock();
+                match entries.remove(ticket)? {
+                    Entry::Parked { state, .. } => return Some(state),
+                    Entry::Live(takeover) => {
+                        if !asked {
+                            takeover.notify_one();
+                            asked = true;
+                        }
+                        entries.insert(ticket.to_string(), Entry::Live(takeover));
+                    }
+                }
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime")
+            .block_on(future)
+    }
+
+    #[test]
+    fn parked_state_is_redeemed_once() {
+        let tickets = ResumeTickets::new(RESUME_TICKET_TTL);
+        let (ticket, _) = tickets.issue();
+        tickets.park(&ticket, "cursor");
+        assert_eq!(block_on(tickets.redeem(&ticket)), Some("cursor"));
+        assert_eq!(block_on(tickets.redeem(&ticket)), None);
+        assert_eq!(block_on(tickets.redeem("unknown")), None);
+
+        let (revoked, _) = tickets.issue();
+        tickets.revoke(&revoked);
+        tickets.park(&revoked, "kicked");
+        assert_eq!(block_on(tickets.redeem(&revoked)), None);
+
+        let expiring = ResumeTickets::new(Duration::ZERO);
+        let (ticket, _) = expiring.issue();
+        expiring.park(&ticket, "stale");
+        assert_eq!(block_on(expiring.redeem(&ticket)), None);
+    }
+
+    #[test]
+    fn redeeming_a_live_ticket_takes_over_its_connection() {
+        let tickets = Arc::new(ResumeTickets::new(RESUME_TICKET_TTL));
+        let (ticket, takeover) = tickets.issue();
+        let holder = {
+            let tickets = Arc::clone(&tickets);
+            let ticket = ticket.clone();
+            async move {
+                takeover.notified().await;
+                tickets.park(&ticket, "half-open");
+            }
+        };
+        let (redeemed, ()) = block_on(async { tokio::join!(tickets.redeem(&ticket), holder) });
+        assert_eq!(redeemed, Some("half-open"));
+    }
+}
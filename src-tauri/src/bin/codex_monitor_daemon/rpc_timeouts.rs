@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::jsonrpc;
+use crate::rpc_trace::redact;
+
+const DEFAULT_BUDGET: Duration = Duration::from_secs(120);
+const DEFAULT_SLOW_THRESHOLD: Duration = Duration::from_secs(2);
+
+/// Methods that legitimately run longer than the default budget. `None` means the
+/// call waits on the user (a browser login) and is never cut off.
+const BUILTIN_BUDGETS: &[(&str, Option<u64>)] = &[
+    ("codex_login", None),
+    ("mcp_server_oauth_login", None),
+    ("run_build", Some(30 * 60)),
+    ("audit_dependencies", Some(10 * 60)),
+    ("add_worktree", Some(10 * 60)),
+    ("remove_worktree", Some(10 * 60)),
+    ("reconcile_worktrees", Some(10 * 60)),
+    ("set_sparse_checkout", Some(10 * 60)),
+    ("fanout_run", Some(10 * 60)),
+    ("backup_data", Some(10 * 60)),
+    ("restore_data", Some(10 * 60)),
+    ("indexed_search", Some(5 * 60)),
+    ("search_all_workspaces", Some(5 * 60)),
+    ("workspace_symbols", Some(5 * 60)),
+    ("disk_usage", Some(5 * 60)),
+    ("analyze_workspace", Some(5 * 60)),
+    ("generate_commit_message", Some(5 * 60)),
+];
+
+/// Why an RPC failed. Timeouts are reported with their own error code so clients can
+/// tell them apart from the method's own errors.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RpcError {
+    Failed(String),
+    /// The params don't fit the method's request type.
+    InvalidParams(String),
+    /// No method has this name.
+    MethodNotFound(String),
+    /// The method failed because the workspace its params name doesn't exist.
+    NotFound(String),
+    /// The caller's token doesn't have the method's scope.
+    Forbidden(String),
+    TimedOut {
+        method: String,
+        budget: Duration,
+    },
+}
+
+impl RpcError {
+    pub(crate) fn message(&self) -> String {
+        match self {
+            Self::Failed(message)
+            | Self::InvalidParams(message)
+            | Self::NotFound(message)
+            | Self::Forbidden(message) => message.clone(),
+            Self::MethodNotFound(method) => format!("unknown method: {method}"),
+            Self::TimedOut { method, budget } => format!(
+                "`{method}` timed out after {}s; raise its budget with --rpc-timeout {method}=<secs>.",
+                budget.as_secs()
+            ),
+        }
+    }
+
+    /// The JSON-RPC `error` object of an RPC response.
+    pub(crate) fn to_json(&self) -> Value {
+        match self {
+            Self::Failed(message) | Self::NotFound(message) => jsonrpc::failure(message),
+            Self::InvalidParams(message) => {
+                jsonrpc::error_object(jsonrpc::INVALID_PARAMS, message, None)
+            }
+            Self::MethodNotFound(_) => {
+                jsonrpc::error_object(jsonrpc::METHOD_NOT_FOUND, &self.message(), None)
+            }
+            Self::Forbidden(message) => jsonrpc::error_object(jsonrpc::FORBIDDEN, message, None),
+            Self::TimedOut { method, budget } => jsonrpc::error_object(
+                jsonrpc::TIMEOUT,
+                &self.message(),
+                Some(json!({ "method": method, "timeoutMs": budget.as_millis() as u64 })),
+            ),
+        }
+    }
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Failed(error.to_string())
+    }
+}
+
+/// Per-method time budgets for RPC dispatch plus the slow-call logging threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RpcTimeouts {
+    default_budget: Option<Duration>,
+    budgets: HashMap<String, Option<Duration>>,
+    /// Calls taking at least this long are logged; `None` turns the log off.
+    pub(crate) slow_threshold: Option<Duration>,
+}
+
+impl Default for RpcTimeouts {
+    fn default() -> Self {
+        Self {
+            default_budget: Some(DEFAULT_BUDGET),
+            budgets: BUILTIN_BUDGETS
+                .iter()
+                .map(|(method, secs)| (method.to_string(), secs.map(Duration::from_secs)))
+                .collect(),
+            slow_threshold: Some(DEFAULT_SLOW_THRESHOLD),
+        }
+    }
+}
+
+impl RpcTimeouts {
+    /// Applies one `<method>=<secs>` value, e.g. `run_build=3600`. `*` sets the default
+    /// budget and `0` removes the limit.
+    pub(crate) fn apply(&mut self, value: &str) -> Result<(), String> {
+        let (method, secs) = value
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid RPC timeout `{value}`; expected <method>=<secs>."))?;
+        let secs = secs
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid RPC timeout `{value}`; <secs> must be a number."))?;
+        let budget = Some(Duration::from_secs(secs)).filter(|budget| !budget.is_zero());
+        match method.trim() {
+            "" => return Err(format!("Invalid RPC timeout `{value}`; <method> is empty.")),
+            "*" => self.default_budget = budget,
+            method => {
+                self.budgets.insert(method.to_string(), budget);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn budget(&self, method: &str) -> Option<Duration> {
+        self.budgets
+            .get(method)
+            .copied()
+            .unwrap_or(self.default_budget)
+    }
+
+    /// Runs one dispatch under the method's budget. A call that runs out is dropped;
+    /// work it already handed to git or the app-server may still finish.
+    pub(crate) async fn run<F>(&self, method: &str, call: F) -> Result<Value, RpcError>
+    where
+        F: Future<Output = Result<Value, RpcError>>,
+    {
+        let Some(budget) = self.budget(method) else {
+            return call.await;
+        };
+        match tokio::time::timeout(budget, call).await {
+            Ok(result) => result,
+            Err(_) => Err(RpcError::TimedOut {
+                method: method.to_string(),
+                budget,
+            }),
+        }
+    }
+
+    /// Logs a call that took at least the slow threshold, with secrets redacted from
+    /// its parameters.
+    pub(crate) fn log_if_slow(
+        &self,
+        client: &str,
+        method: &str,
+        params: &Value,
+        elapsed: Duration,
+        error: Option<&RpcError>,
+    ) {
+        if let Some(line) = self.slow_entry(client, method, params, elapsed, error) {
+            tracing::warn!("slow rpc: {line}");
+        }
+    }
+
+    fn slow_entry(
+        &self,
+        client: &str,
+        method: &str,
+        params: &Value,
+        elapsed: Duration,
+        error: Option<&RpcError>,
+    ) -> Option<Value> {
+        let threshold = self.slow_threshold?;
+        if elapsed < threshold {
+            return None;
+        }
+        let outcome = match error {
+            None => "ok",
+            Some(
+                RpcError::Failed(_)
+                | RpcError::NotFound(_)
+                | RpcError::InvalidParams(_)
+                | RpcError::MethodNotFound(_)
+                | RpcError::Forbidden(_),
+            ) => "error",
+            Some(RpcError::TimedOut { .. }) => "timeout",
+        };
+        Some(json!({
+            "client": client,
+            "method": method,
+            "elapsedMs": elapsed.as_millis() as u64,
+            "outcome": outcome,
+            "params": redact(params),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_method_and_default_budgets() {
+        let mut timeouts = RpcTimeouts::default();
+        assert_eq!(timeouts.budget("ping"), Some(DEFAULT_BUDGET));
+        assert_eq!(timeouts.budget("codex_login"), None);
+        timeouts.apply("*=30").expect("default");
+        timeouts.apply("run_build=0").expect("unlimited");
+        timeouts.apply(" list_threads = 5 ").expect("method");
+        assert_eq!(timeouts.budget("ping"), Some(Duration::from_secs(30)));
+        assert_eq!(timeouts.budget("run_build"), None);
+        assert_eq!(
+            timeouts.budget("list_threads"),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            timeouts.budget("indexed_search"),
+            Some(Duration::from_secs(300))
+        );
+        for invalid in ["ping", "ping=soon", "=5"] {
+            assert!(timeouts.apply(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn times_out_slow_calls_and_logs_them_redacted() {
+        let mut timeouts = RpcTimeouts::default();
+        timeouts.apply("ping=0").expect("unlimited");
+        timeouts
+            .budgets
+            .insert("list_threads".to_string(), Some(Duration::from_millis(10)));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        let error = runtime
+            .block_on(timeouts.run("list_threads", async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(json!({}))
+            }))
+            .expect_err("timed out");
+        assert_eq!(error.to_json()["code"], json!(jsonrpc::TIMEOUT));
+        assert_eq!(error.to_json()["data"]["timeoutMs"], json!(10));
+        let ok = runtime.block_on(timeouts.run("ping", async { Ok(json!({ "ok": true })) }));
+        assert_eq!(ok, Ok(json!({ "ok": true })));
+
+        let params = json!({ "workspaceId": "ws-1", "token": "s3cret" });
+        assert!(timeouts
+            .slow_entry(
+                "cli",
+                "list_threads",
+                &params,
+                Duration::from_millis(5),
+                None
+            )
+            .is_none());
+        let entry = timeouts
+            .slow_entry(
+                "cli",
+                "list_threads",
+                &params,
+                Duration::from_secs(3),
+                Some(&error),
+            )
+            .expect("slow");
+        assert_eq!(entry["outcome"], json!("timeout"));
+        assert_eq!(entry["elapsedMs"], json!(3000));
+        assert_eq!(entry["params"]["workspaceId"], json!("ws-1"));
+        assert_ne!(entry["params"]["token"], json!("s3cret"));
+    }
+}
@@ -0,0 +1,228 @@
+use serde_json::{json, Map, Value};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::audit::now_millis;
+use crate::jsonrpc::RequestId;
+
+const TRACE_LOG_FILE: &str = "rpc-trace.log";
+const MAX_TRACE_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_ROTATED_FILES: usize = 3;
+const REDACTED: &str = "[redacted]";
+/// Key fragments (compared case-insensitively) whose values never reach the trace file.
+const SECRET_KEY_FRAGMENTS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "passphrase",
+    "authorization",
+    "apikey",
+    "api_key",
+    "cookie",
+    "credential",
+    "ticket",
+];
+
+/// Rotating JSON-lines trace of full RPC requests and responses, with secrets redacted.
+pub(crate) struct RpcTrace {
+    path: PathBuf,
+    max_bytes: u64,
+    forced: bool,
+    enabled: AtomicBool,
+    lock: Mutex<()>,
+}
+
+impl RpcTrace {
+    /// `forced` comes from `--trace-rpc` and keeps tracing on regardless of the setting.
+    pub(crate) fn new(data_dir: &Path, forced: bool, setting_enabled: bool) -> Self {
+        Self {
+            path: data_dir.join(TRACE_LOG_FILE),
+            max_bytes: MAX_TRACE_FILE_BYTES,
+            forced,
+            enabled: AtomicBool::new(forced || setting_enabled),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Applies the `traceRpcEnabled` setting; a `--trace-rpc` daemon stays enabled.
+    pub(crate) fn apply_setting(&self, setting_enabled: bool) {
+        self.enabled
+            .store(self.forced || setting_enabled, Ordering::Relaxed);
+    }
+
+    /// Records one message; failures are reported on stderr but never block the caller.
+    pub(crate) fn record(
+        &self,
+        direction: &str,
+        client: &str,
+        id: Option<&RequestId>,
+        method: &str,
+        payload: &Value,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let line = json!({
+            "timestamp": now_millis(),
+            "direction": direction,
+            "client": client,
+            "id": id,
+            "method": method,
+            "payload": redact(payload),
+        })
+        .to_string();
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(err) = self.rotate_if_needed().and_then(|_| append_line(&self.path, &line)) {
+            tracing::error!("Failed to write rpc trace {}: {err}", self.path.display());
+        }
+    }
+
+    /// Returns the last `limit` entries, oldest first, reading into rotated files if needed.
+    pub(crate) fn tail(&self, limit: usize) -> Result<Vec<Value>, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut entries: Vec<Value> = Vec::new();
+        for index in 0..=MAX_ROTATED_FILES {
+            if entries.len() >= limit {
+                break;
+            }
+            let path = rotated_path(&self.path, index);
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(format!("Failed to read {}: {err}", path.display())),
+            };
+            let mut older = contents
+                .lines()
+                .rev()
+                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+                .take(limit - entries.len())
+                .collect::<Vec<_>>();
+            entries.append(&mut older);
+        }
+        entries.reverse();
+        Ok(entries)
+    }
+
+    fn rotate_if_needed(&self) -> Result<(), String> {
+        let size = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Ok(()),
+        };
+        if size < self.max_bytes {
+            return Ok(());
+        }
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, index + 1))
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        std::fs::rename(&self.path, rotated_path(&self.path, 1)).map_err(|err| err.to_string())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let lower = key.to_ascii_lowercase();
+    SECRET_KEY_FRAGMENTS
+        .iter()
+        .any(|fragment| lower.contains(fragment))
+}
+
+/// Copies `value`, replacing the string values of secret-looking keys at any depth.
+pub(crate) fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut redacted = Map::with_capacity(map.len());
+            for (key, entry) in map {
+                // Only string values are secrets; counters such as `tokenUsage` stay visible.
+                let next = if is_secret_key(key) && entry.is_string() {
+                    Value::String(REDACTED.to_string())
+                } else {
+                    redact(entry)
+                };
+                redacted.insert(key.clone(), next);
+            }
+            Value::Object(redacted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+fn append_line(path: &Path, line: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| err.to_string())?;
+    writeln!(file, "{line}").map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn redacts_secret_keys_at_any_depth() {
+        let payload = json!({
+            "token": "abc",
+            "settings": { "remoteBackendToken": "xyz", "theme": "dark", "apiKey": null },
+            "items": [{ "Authorization": "Bearer 1" }],
+            "tokenUsage": { "total": 12 }
+        });
+        let redacted = redact(&payload);
+        assert_eq!(redacted["token"], json!(REDACTED));
+        assert_eq!(redacted["settings"]["remoteBackendToken"], json!(REDACTED));
+        assert_eq!(redacted["settings"]["theme"], json!("dark"));
+        assert_eq!(redacted["settings"]["apiKey"], Value::Null);
+        assert_eq!(redacted["items"][0]["Authorization"], json!(REDACTED));
+        assert_eq!(redacted["tokenUsage"]["total"], json!(12));
+    }
+
+    #[test]
+    fn rotates_and_tails_across_files() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-trace-{}", Uuid::new_v4()));
+        let mut trace = RpcTrace::new(&dir, false, true);
+        trace.max_bytes = 200;
+        for index in 0..6 {
+            let id = RequestId::Number(index.into());
+            trace.record("request", "client-1", Some(&id), "ping", &json!({ "n": index }));
+        }
+        assert!(rotated_path(trace.path(), 1).exists());
+
+        let tail = trace.tail(4).expect("tail");
+        let ids = tail.iter().map(|entry| entry["id"].clone()).collect::<Vec<_>>();
+        assert_eq!(ids, vec![json!(2), json!(3), json!(4), json!(5)]);
+
+        trace.apply_setting(false);
+        let id = RequestId::Number(9.into());
+        trace.record("request", "client-1", Some(&id), "ping", &Value::Null);
+        assert_eq!(trace.tail(1).expect("tail")[0]["id"], json!(5));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,410 @@
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::jsonrpc;
+use crate::mock_app_server::MOCK_APP_SERVER_ENV;
+
+const SELF_TEST_TOKEN: &str = "self-test";
+/// A `--token-file` token with only the `read` scope.
+const SELF_TEST_READ_TOKEN: &str = "self-test-read";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const CALL_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Boots a second copy of this daemon against the mock app-server in a scratch data dir
+/// and drives the RPC surface end to end: auth, workspaces, threads, turns and files.
+/// Prints one line per step and fails on the first broken one.
+pub(crate) fn run() -> Result<(), String> {
+    let scratch = std::env::temp_dir().join(format!("codex-monitor-self-test-{}", Uuid::new_v4()));
+    let result = SelfTest::start(&scratch).and_then(|mut test| {
+        let outcome = test.run_steps();
+        test.stop();
+        outcome.map_err(
+            |err| match std::fs::read_to_string(scratch.join("daemon.log")) {
+                Ok(log) if !log.trim().is_empty() => format!("{err}\n\ndaemon stderr:\n{log}"),
+                _ => err,
+            },
+        )
+    });
+    let _ = std::fs::remove_dir_all(&scratch);
+    result
+}
+
+struct SelfTest {
+    daemon: Child,
+    client: RpcClient,
+    listen: SocketAddr,
+    workspace_dir: PathBuf,
+}
+
+impl SelfTest {
+    fn start(scratch: &Path) -> Result<Self, String> {
+        let data_dir = scratch.join("data");
+        let workspace_dir = scratch.join("workspace");
+        std::fs::create_dir_all(&data_dir).map_err(|err| err.to_string())?;
+        std::fs::create_dir_all(workspace_dir.join("src")).map_err(|err| err.to_string())?;
+        std::fs::write(workspace_dir.join("README.md"), "# Self test\n")
+            .map_err(|err| err.to_string())?;
+        std::fs::write(workspace_dir.join("src/main.rs"), "fn main() {}\n")
+            .map_err(|err| err.to_string())?;
+
+        let token_file = scratch.join("tokens.json");
+        std::fs::write(
+            &token_file,
+            json!([{ "name": "reader", "token": SELF_TEST_READ_TOKEN, "scopes": ["read"] }])
+                .to_string(),
+        )
+        .map_err(|err| err.to_string())?;
+
+        let listen = free_local_addr()?;
+        let log =
+            std::fs::File::create(scratch.join("daemon.log")).map_err(|err| err.to_string())?;
+        let daemon = Command::new(current_exe()?)
+            .arg("--listen")
+            .arg(listen.to_string())
+            .arg("--data-dir")
+            .arg(&data_dir)
+            .arg("--token")
+            .arg(SELF_TEST_TOKEN)
+            .arg("--token-file")
+            .arg(&token_file)
+            .env(MOCK_APP_SERVER_ENV, "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(log)
+            .spawn()
+            .map_err(|err| format!("Failed to start daemon: {err}"))?;
+        let mut test = Self {
+            client: match RpcClient::connect(listen) {
+                Ok(client) => client,
+                Err(err) => {
+                    let mut daemon = daemon;
+                    let _ = daemon.kill();
+                    let _ = daemon.wait();
+                    return Err(err);
+                }
+            },
+            daemon,
+            listen,
+            workspace_dir,
+        };
+        test.step("daemon started", |_| Ok(()))?;
+        Ok(test)
+    }
+
+    fn stop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+
+    fn step<T>(
+        &mut self,
+        name: &str,
+        body: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        match body(self) {
+            Ok(value) => {
+                println!("ok   {name}");
+                Ok(value)
+            }
+            Err(err) => {
+                println!("FAIL {name}: {err}");
+                Err(format!("{name}: {err}"))
+            }
+        }
+    }
+
+    fn run_steps(&mut self) -> Result<(), String> {
+        let ticket = self.step("auth", |test| {
+            let result = test
+                .client
+                .call("auth", json!({ "token": SELF_TEST_TOKEN }))?;
+            expect(
+                result["ok"] == json!(true),
+                "auth was not accepted",
+                &result,
+            )?;
+            string_at(&result, &["resumeTicket"])
+        })?;
+        self.step("resume", |test| {
+            // Drop the connection and come back on a new one with the ticket instead of
+            // the token.
+            test.client = RpcClient::connect(test.listen)?;
+            let result = test.client.call("resume", json!({ "ticket": ticket }))?;
+            expect(
+                result["resumed"] == json!(true),
+                "ticket was not accepted",
+                &result,
+            )
+        })?;
+        self.step("ping", |test| {
+            let result = test.client.call("ping", json!({}))?;
+            expect(
+                result["ok"] == json!(true),
+                "unexpected ping reply",
+                &result,
+            )
+        })?;
+        self.step("batch", |test| {
+            let result = test.client.batch(json!([
+                { "jsonrpc": "2.0", "id": "batch-ping", "method": "ping" },
+                { "jsonrpc": "2.0", "method": "ping" },
+                { "jsonrpc": "2.0", "id": 0, "method": "no_such_method" },
+            ]))?;
+            let answered = result.as_array().map(Vec::len) == Some(2)
+                && result[0]["id"] == json!("batch-ping")
+                && result[0]["result"]["ok"] == json!(true)
+                && result[1]["error"]["code"] == json!(jsonrpc::METHOD_NOT_FOUND);
+            expect(answered, "unexpected batch reply", &result)
+        })?;
+
+        let workspace_id = self.step("add_workspace", |test| {
+            let path = test.workspace_dir.to_string_lossy().to_string();
+            let codex_bin = current_exe()?.to_string_lossy().to_string();
+            let result = test.client.call(
+                "add_workspace",
+                json!({ "path": path, "codex_bin": codex_bin }),
+            )?;
+            expect(
+                result["connected"] == json!(true),
+                "workspace not connected",
+                &result,
+            )?;
+            string_at(&result, &["id"])
+        })?;
+        self.step("list_workspaces", |test| {
+            let result = test.client.call("list_workspaces", json!({}))?;
+            let listed = result
+                .as_array()
+                .is_some_and(|list| list.iter().any(|info| info["id"] == workspace_id));
+            expect(listed, "added workspace missing", &result)
+        })?;
+        self.step("connect_workspace", |test| {
+            let result = test
+                .client
+                .call("connect_workspace", json!({ "id": workspace_id }))?;
+            expect(result["ok"] == json!(true), "connect failed", &result)
+        })?;
+
+        let thread_id = self.step("start_thread", |test| {
+            let result = test
+                .client
+                .call("start_thread", json!({ "workspaceId": workspace_id }))?;
+            string_at(&result, &["result", "thread", "id"])
+        })?;
+        self.step("list_threads", |test| {
+            let result = test.client.call(
+                "list_threads",
+                json!({ "workspaceId": workspace_id, "refresh": true }),
+            )?;
+            let listed = result.get("result").unwrap_or(&result)["data"]
+                .as_array()
+                .is_some_and(|threads| threads.iter().any(|thread| thread["id"] == thread_id));
+            expect(listed, "started thread missing", &result)
+        })?;
+        self.step("send_user_message", |test| {
+            let result = test.client.call(
+                "send_user_message",
+                json!({ "workspaceId": workspace_id, "threadId": thread_id, "text": "hello" }),
+            )?;
+            let turn_id = string_at(&result, &["result", "turn", "id"])?;
+            test.client.wait_for_event(|event| {
+                event["method"] == "app-server-event"
+                    && event["params"]["message"]["method"] == "turn/completed"
+                    && event["params"]["message"]["params"]["turn"]["id"] == turn_id
+            })
+        })?;
+
+        self.step("list_workspace_files", |test| {
+            let result = test.client.call(
+                "list_workspace_files",
+                json!({ "workspaceId": workspace_id }),
+            )?;
+            let files = result
+                .as_array()
+                .map(|files| files.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+                .unwrap_or_default();
+            let complete = files.contains(&"README.md") && files.contains(&"src/main.rs");
+            expect(complete, "workspace files missing", &result)
+        })?;
+        self.step("read_workspace_file", |test| {
+            let result = test.client.call(
+                "read_workspace_file",
+                json!({ "workspaceId": workspace_id, "path": "README.md" }),
+            )?;
+            expect(
+                result["content"] == "# Self test\n",
+                "unexpected content",
+                &result,
+            )
+        })?;
+
+        self.step("remove_workspace", |test| {
+            let plan = test
+                .client
+                .call("remove_workspace", json!({ "id": workspace_id }))?;
+            expect(
+                plan["confirmToken"].is_string(),
+                "removal plan without a token",
+                &plan,
+            )?;
+            test.client.call(
+                "remove_workspace",
+                json!({ "id": workspace_id, "confirmToken": plan["confirmToken"] }),
+            )?;
+            let result = test.client.call("list_workspaces", json!({}))?;
+            let listed = result
+                .as_array()
+                .is_some_and(|list| list.iter().any(|info| info["id"] == workspace_id));
+            expect(!listed, "workspace still listed", &result)
+        })?;
+
+        self.step("read-scoped token", |test| {
+            let mut reader = RpcClient::connect(test.listen)?;
+            reader.call("auth", json!({ "token": SELF_TEST_READ_TOKEN }))?;
+            reader.call("subscribe_events", json!({}))?;
+            for (method, params) in [
+                ("identify_client", json!({ "deviceName": "reader" })),
+                ("open_files", json!({ "workspaceId": "ws", "files": [] })),
+                ("focus_file", json!({ "workspaceId": "ws", "path": "README.md" })),
+            ] {
+                match reader.call(method, params) {
+                    Err(message) if message.contains("`chat` scope") => {}
+                    other => return Err(format!("{method} was not refused: {other:?}")),
+                }
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+fn current_exe() -> Result<PathBuf, String> {
+    std::env::current_exe().map_err(|err| format!("Failed to locate daemon binary: {err}"))
+}
+
+fn free_local_addr() -> Result<SocketAddr, String> {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map_err(|err| format!("Failed to reserve a local port: {err}"))
+}
+
+fn expect(condition: bool, message: &str, value: &Value) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(format!("{message}: {value}"))
+    }
+}
+
+fn string_at(value: &Value, path: &[&str]) -> Result<String, String> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("missing {}: {value}", path.join(".")))
+}
+
+/// Blocking line-delimited JSON-RPC client that keeps notifications for `wait_for_event`.
+struct RpcClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+    events: Vec<Value>,
+}
+
+impl RpcClient {
+    fn connect(addr: SocketAddr) -> Result<Self, String> {
+        let started = Instant::now();
+        let stream = loop {
+            match TcpStream::connect(addr) {
+                Ok(stream) => break stream,
+                Err(err) if started.elapsed() > STARTUP_TIMEOUT => {
+                    return Err(format!("Daemon did not start listening on {addr}: {err}"));
+                }
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        stream
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .map_err(|err| err.to_string())?;
+        let writer = stream.try_clone().map_err(|err| err.to_string())?;
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+            next_id: 1,
+            events: Vec::new(),
+        })
+    }
+
+    fn read_message(&mut self, deadline: Instant) -> Result<Value, String> {
+        // Bytes read before a timeout stay in `line`, so a split message is resumed.
+        let mut line = Vec::new();
+        loop {
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => return Err("daemon closed the connection".to_string()),
+                Ok(_) if line.ends_with(b"\n") => {
+                    return serde_json::from_slice(&line).map_err(|err| err.to_string());
+                }
+                Ok(_) => {}
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if Instant::now() > deadline {
+                        return Err("timed out waiting for the daemon".to_string());
+                    }
+                }
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(self.writer, "{request}").map_err(|err| err.to_string())?;
+        let deadline = Instant::now() + CALL_TIMEOUT;
+        loop {
+            let message = self.read_message(deadline)?;
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                self.events.push(message);
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(error["message"].as_str().unwrap_or("error").to_string());
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    /// Sends a raw batch and returns the array that answers it.
+    fn batch(&mut self, requests: Value) -> Result<Value, String> {
+        writeln!(self.writer, "{requests}").map_err(|err| err.to_string())?;
+        let deadline = Instant::now() + CALL_TIMEOUT;
+        loop {
+            let message = self.read_message(deadline)?;
+            if message.is_array() {
+                return Ok(message);
+            }
+            self.events.push(message);
+        }
+    }
+
+    fn wait_for_event(&mut self, matches: impl Fn(&Value) -> bool) -> Result<(), String> {
+        if self.events.iter().any(&matches) {
+            return Ok(());
+        }
+        let deadline = Instant::now() + CALL_TIMEOUT;
+        loop {
+            let message = self.read_message(deadline)?;
+            if matches(&message) {
+                return Ok(());
+            }
+            self.events.push(message);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use serde::Serialize;
+
+/// Which events a connection receives, set with `subscribe_events`. Events tied to a
+/// workspace outside `workspace_ids` are skipped; the rest always go out.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EventSubscriptions {
+    /// `None` subscribes to every workspace.
+    pub(crate) workspace_ids: Option<Vec<String>>,
+}
+
+impl EventSubscriptions {
+    pub(crate) fn allows(&self, workspace_id: Option<&str>) -> bool {
+        match (&self.workspace_ids, workspace_id) {
+            (Some(ids), Some(workspace_id)) => ids.iter().any(|id| id == workspace_id),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_only_workspace_events() {
+        let all = EventSubscriptions::default();
+        assert!(all.allows(Some("ws-1")));
+
+        let one = EventSubscriptions {
+            workspace_ids: Some(vec!["ws-1".to_string()]),
+        };
+        assert!(one.allows(Some("ws-1")));
+        assert!(!one.allows(Some("ws-2")));
+        assert!(one.allows(None));
+    }
+}
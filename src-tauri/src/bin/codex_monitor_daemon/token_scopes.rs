@@ -0,0 +1,343 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// What a token may do. Every scope also allows the read-only methods; `admin` allows
+/// everything, including the methods no other scope covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Scope {
+    Read,
+    Files,
+    Git,
+    Chat,
+    Admin,
+}
+
+impl Scope {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Files => "files",
+            Self::Git => "git",
+            Self::Chat => "chat",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+const READ_METHODS: &[&str] = &[
+    "ping",
+    "subscribe_events",
+    "subscribe_since",
+    "health",
+    "get_capabilities",
+    "list_workspaces",
+    "is_workspace_path_dir",
+    "fanout_status",
+    "compare_fanout",
+    "worktree_setup_status",
+    "preview_codex_command",
+    "list_workspace_files",
+    "read_workspace_file",
+    "resolve_mentions",
+    "plan_context",
+    "indexed_search",
+    "workspace_symbols",
+    "find_definition",
+    "search_all_workspaces",
+    "ci_status",
+    "get_sparse_checkout",
+    "workspace_stats",
+    "stale_branches_report",
+    "disk_usage",
+    "analyze_workspace",
+    "list_packages",
+    "list_problems",
+    "job_status",
+    "file_read",
+    "effective_instructions",
+    "extract_document",
+    "render_markdown",
+    "render_notebook",
+    "file_outline",
+    "get_thumbnail",
+    "file_history",
+    "get_app_settings",
+    "export_safety_policy",
+    "evaluate_approval_policy",
+    "list_pending_approvals",
+    "list_clients",
+    "list_editor_presence",
+    "merge_revisions",
+    "get_session_log",
+    "get_codex_config_path",
+    "get_config_model",
+    "thread_graph",
+    "list_slash_commands",
+    "get_draft",
+    "time_tracking_summary",
+    "list_pinned_context",
+    "list_collaboration_presets",
+    "list_account_profiles",
+    "list_threads",
+    "list_mcp_server_status",
+    "mcp_server_health",
+    "model_list",
+    "collaboration_mode_list",
+    "account_rate_limits",
+    "account_read",
+    "skills_list",
+    "skills_config",
+    "apps_list",
+    "validate_rules",
+];
+
+const FILES_METHODS: &[&str] = &[
+    "begin_write",
+    "write_chunk",
+    "commit_write",
+    "abort_write",
+    "file_write",
+    "file_delete",
+    "revert_file_to",
+    "restore_snapshot",
+];
+
+const GIT_METHODS: &[&str] = &[
+    "add_worktree",
+    "remove_worktree",
+    "reconcile_worktrees",
+    "delete_stale_branches",
+    "rename_worktree",
+    "rename_worktree_upstream",
+    "worktree_setup_mark_ran",
+    "set_sparse_checkout",
+    "generate_commit_message",
+    "fanout_pick",
+];
+
+const CHAT_METHODS: &[&str] = &[
+    "connect_workspace",
+    "start_thread",
+    "resume_thread",
+    "fork_thread",
+    "archive_thread",
+    "compact_thread",
+    "set_thread_name",
+    "send_user_message",
+    "turn_interrupt",
+    "start_review",
+    "respond_to_server_request",
+    "identify_client",
+    "open_files",
+    "focus_file",
+    "save_draft",
+    "time_tracking_ping",
+    "pin_context",
+    "unpin_context",
+    "fanout_run",
+    "auto_fix_start",
+    "job_cancel",
+];
+
+/// The scope a method needs; methods not listed need `admin`.
+pub(crate) fn required_scope(method: &str) -> Scope {
+    [
+        (Scope::Read, READ_METHODS),
+        (Scope::Files, FILES_METHODS),
+        (Scope::Git, GIT_METHODS),
+        (Scope::Chat, CHAT_METHODS),
+    ]
+    .into_iter()
+    .find(|(_, methods)| methods.contains(&method))
+    .map(|(scope, _)| scope)
+    .unwrap_or(Scope::Admin)
+}
+
+/// What one authenticated connection or REST request may call.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Grant {
+    /// The token's name from `--token-file`; `None` for `--token` or no auth.
+    pub(crate) token_name: Option<String>,
+    scopes: Vec<Scope>,
+}
+
+impl Grant {
+    /// The shared `--token`, or a daemon running without auth.
+    pub(crate) fn full() -> Self {
+        Self {
+            token_name: None,
+            scopes: vec![Scope::Admin],
+        }
+    }
+
+    pub(crate) fn scopes(&self) -> Vec<&'static str> {
+        self.scopes.iter().map(|scope| scope.name()).collect()
+    }
+
+    /// `Err` names the scope the method needs.
+    pub(crate) fn check(&self, method: &str) -> Result<(), String> {
+        let required = required_scope(method);
+        let allowed = required == Scope::Read
+            || self
+                .scopes
+                .iter()
+                .any(|scope| *scope == required || *scope == Scope::Admin);
+        if allowed {
+            return Ok(());
+        }
+        Err(format!(
+            "`{method}` needs the `{}` scope, which this token doesn't have",
+            required.name()
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct ScopedToken {
+    #[serde(default)]
+    name: Option<String>,
+    token: String,
+    scopes: Vec<Scope>,
+}
+
+/// The tokens a daemon accepts: the shared `--token`, with full access, plus the scoped
+/// tokens of `--token-file`. Empty means the daemon runs without auth.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct TokenSet {
+    shared: Option<String>,
+    scoped: Vec<ScopedToken>,
+}
+
+impl TokenSet {
+    pub(crate) fn new(shared: Option<String>, scoped: Vec<ScopedToken>) -> Self {
+        Self { shared, scoped }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.shared.is_none() && self.scoped.is_empty()
+    }
+
+    /// The grant for a token the client presented, or `None` if no token matches.
+    pub(crate) fn authenticate(&self, provided: &str) -> Option<Grant> {
+        if provided.is_empty() {
+            return None;
+        }
+        if self.shared.as_deref() == Some(provided) {
+            return Some(Grant::full());
+        }
+        self.scoped
+            .iter()
+            .find(|token| token.token == provided)
+            .map(|token| {
+                let mut scopes = token.scopes.clone();
+                scopes.sort();
+                scopes.dedup();
+                Grant {
+                    token_name: token.name.clone(),
+                    scopes,
+                }
+            })
+    }
+}
+
+/// Reads a `--token-file`: a JSON array of `{ name?, token, scopes }`.
+pub(crate) fn load_token_file(path: &Path) -> Result<Vec<ScopedToken>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    parse_token_file(&contents).map_err(|err| format!("Invalid {}: {err}", path.display()))
+}
+
+fn parse_token_file(contents: &str) -> Result<Vec<ScopedToken>, String> {
+    let mut tokens: Vec<ScopedToken> =
+        serde_json::from_str(contents).map_err(|err| err.to_string())?;
+    let mut seen = HashSet::new();
+    for (index, token) in tokens.iter_mut().enumerate() {
+        token.token = token.token.trim().to_string();
+        let label = token
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("token #{}", index + 1));
+        if token.token.is_empty() {
+            return Err(format!("{label} has an empty `token`"));
+        }
+        if token.scopes.is_empty() {
+            return Err(format!("{label} has no `scopes`"));
+        }
+        if !seen.insert(token.token.clone()) {
+            return Err(format!("{label} repeats another entry's `token`"));
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_gate_methods_and_reads_are_always_allowed() {
+        let tokens = TokenSet::new(
+            Some("shared".to_string()),
+            parse_token_file(
+                r#"[
+                    { "name": "ci", "token": " ci-token ", "scopes": ["git", "read"] },
+                    { "token": "phone", "scopes": ["chat"] }
+                ]"#,
+            )
+            .expect("token file"),
+        );
+        assert_eq!(tokens.authenticate("shared"), Some(Grant::full()));
+        assert_eq!(tokens.authenticate("nope"), None);
+        assert_eq!(tokens.authenticate(""), None);
+
+        let ci = tokens.authenticate("ci-token").expect("ci");
+        assert_eq!(ci.token_name.as_deref(), Some("ci"));
+        assert_eq!(ci.scopes(), vec!["read", "git"]);
+        assert!(ci.check("list_workspaces").is_ok());
+        assert!(ci.check("add_worktree").is_ok());
+        let error = ci.check("send_user_message").expect_err("chat");
+        assert!(error.contains("`chat` scope"), "{error}");
+
+        let phone = tokens.authenticate("phone").expect("phone");
+        assert!(phone.check("send_user_message").is_ok());
+        assert!(phone.check("read_workspace_file").is_ok());
+        assert!(phone.check("file_write").is_err());
+        assert!(phone.check("update_app_settings").is_err());
+        assert!(phone.check("open_files").is_ok());
+
+        let reader = Grant {
+            token_name: None,
+            scopes: vec![Scope::Read],
+        };
+        assert!(reader.check("subscribe_events").is_ok());
+        assert!(reader.check("subscribe_since").is_ok());
+        for method in ["identify_client", "open_files", "focus_file"] {
+            let error = reader.check(method).expect_err(method);
+            assert!(error.contains("`chat` scope"), "{error}");
+        }
+        assert!(Grant::full().check("update_app_settings").is_ok());
+        assert_eq!(required_scope("no_such_method"), Scope::Admin);
+    }
+
+    #[test]
+    fn rejects_malformed_token_files() {
+        let error = parse_token_file(r#"[{ "token": "a", "scopes": ["root"] }]"#)
+            .expect_err("unknown scope");
+        assert!(error.contains("unknown variant"), "{error}");
+        let error = parse_token_file(r#"[{ "name": "x", "token": " ", "scopes": ["read"] }]"#)
+            .expect_err("empty token");
+        assert_eq!(error, "x has an empty `token`");
+        let error = parse_token_file(r#"[{ "token": "a", "scopes": [] }]"#).expect_err("no scopes");
+        assert_eq!(error, "token #1 has no `scopes`");
+        let error = parse_token_file(
+            r#"[{ "token": "a", "scopes": ["read"] }, { "token": "a", "scopes": ["git"] }]"#,
+        )
+        .expect_err("duplicate");
+        assert!(error.contains("repeats"), "{error}");
+        assert!(TokenSet::default().is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+
+use tokio::net::{UnixListener, UnixStream};
+
+/// Binds `--listen-unix`. A socket left behind by a daemon that is no longer running is
+/// replaced; a live one, or any other kind of file, is an error. The socket is made
+/// owner-only, so filesystem permissions decide who can connect.
+pub(crate) fn bind(path: &Path) -> Result<UnixListener, String> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(format!("{} is in use by another daemon", path.display()));
+            }
+            std::fs::remove_file(path)
+                .map_err(|err| format!("Failed to remove stale {}: {err}", path.display()))?;
+        }
+        Ok(_) => return Err(format!("{} exists and is not a socket", path.display())),
+        Err(_) => {}
+    }
+    let listener = UnixListener::bind(path)
+        .map_err(|err| format!("Failed to bind {}: {err}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|err| format!("Failed to restrict {}: {err}", path.display()))?;
+    Ok(listener)
+}
+
+/// Names a unix socket client by the peer's process id where the platform reports it.
+pub(crate) fn client_label(socket: &UnixStream) -> String {
+    match socket.peer_cred().ok().and_then(|cred| cred.pid()) {
+        Some(pid) => format!("unix:pid {pid}"),
+        None => "unix".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn replaces_stale_sockets_and_refuses_other_files() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-unix-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("daemon.sock");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let listener = bind(&path).expect("bind");
+            let mode = std::fs::metadata(&path)
+                .expect("metadata")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+            let error = bind(&path).expect_err("live socket");
+            assert!(error.contains("in use"), "{error}");
+            drop(listener);
+
+            let listener = bind(&path).expect("rebind over stale socket");
+            let _client = UnixStream::connect(&path).await.expect("connect");
+            let (server, _) = listener.accept().await.expect("accept");
+            assert!(client_label(&server).starts_with("unix"));
+        });
+
+        let file = dir.join("plain");
+        std::fs::write(&file, "not a socket").expect("write file");
+        let error = runtime
+            .block_on(async { bind(&file).map(|_| ()) })
+            .expect_err("regular file");
+        assert!(error.contains("not a socket"), "{error}");
+        assert!(file.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
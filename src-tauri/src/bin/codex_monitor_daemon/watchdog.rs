@@ -0,0 +1,279 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+/// Probes between two storage write checks.
+const STORAGE_PROBE_EVERY: u32 = 30;
+/// A probe that wakes up this late means the event loop is wedged.
+const MAX_EVENT_LOOP_LAG: Duration = Duration::from_secs(5);
+const STORAGE_PROBE_FILE: &str = ".health-probe";
+
+/// What the `health` RPC returns; the same checks gate systemd watchdog pings.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HealthReport {
+    pub(crate) healthy: bool,
+    /// How late the most recent probe woke up.
+    pub(crate) event_loop_lag_ms: u64,
+    pub(crate) max_event_loop_lag_ms: u64,
+    pub(crate) storage_writable: bool,
+    pub(crate) storage_error: Option<String>,
+    pub(crate) checked_at: Option<u64>,
+    pub(crate) uptime_ms: u64,
+    /// Set when systemd runs the daemon with `WatchdogSec=`.
+    pub(crate) watchdog_interval_ms: Option<u64>,
+}
+
+impl HealthReport {
+    fn evaluate(&mut self) {
+        self.healthy =
+            self.storage_writable && self.event_loop_lag_ms < MAX_EVENT_LOOP_LAG.as_millis() as u64;
+    }
+
+    fn status_line(&self) -> String {
+        match (&self.storage_error, self.healthy) {
+            (_, true) => "STATUS=healthy".to_string(),
+            (Some(error), false) => format!("STATUS=storage not writable: {error}"),
+            (None, false) => format!("STATUS=event loop lagging by {} ms", self.event_loop_lag_ms),
+        }
+    }
+}
+
+/// Self-health loop plus systemd `sd_notify` support (`READY=1`, `WATCHDOG=1`, `STATUS=`).
+/// Pings stop while the daemon is unhealthy, and a wedged event loop can't send them at
+/// all, so systemd restarts it once `WatchdogSec=` runs out.
+pub(crate) struct HealthMonitor {
+    started: Instant,
+    watchdog: Option<Duration>,
+    report: Mutex<HealthReport>,
+}
+
+impl HealthMonitor {
+    pub(crate) fn from_env() -> Self {
+        let watchdog = watchdog_interval(
+            std::env::var("WATCHDOG_USEC").ok().as_deref(),
+            std::env::var("WATCHDOG_PID").ok().as_deref(),
+            std::process::id(),
+        );
+        Self::new(watchdog)
+    }
+
+    fn new(watchdog: Option<Duration>) -> Self {
+        Self {
+            started: Instant::now(),
+            watchdog,
+            report: Mutex::new(HealthReport {
+                healthy: true,
+                event_loop_lag_ms: 0,
+                max_event_loop_lag_ms: 0,
+                storage_writable: true,
+                storage_error: None,
+                checked_at: None,
+                uptime_ms: 0,
+                watchdog_interval_ms: watchdog.map(|interval| interval.as_millis() as u64),
+            }),
+        }
+    }
+
+    pub(crate) fn report(&self) -> HealthReport {
+        let mut report = self
+            .report
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+        report.uptime_ms = self.started.elapsed().as_millis() as u64;
+        report
+    }
+
+    fn record(&self, lag: Duration, storage: Option<Result<(), String>>) -> HealthReport {
+        let mut report = self.report.lock().unwrap_or_else(|err| err.into_inner());
+        report.event_loop_lag_ms = lag.as_millis() as u64;
+        report.max_event_loop_lag_ms = report.max_event_loop_lag_ms.max(report.event_loop_lag_ms);
+        if let Some(storage) = storage {
+            report.storage_writable = storage.is_ok();
+            report.storage_error = storage.err();
+        }
+        report.checked_at = Some(now_millis());
+        report.evaluate();
+        report.clone()
+    }
+
+    /// Tells systemd the daemon is up, then probes until the process exits.
+    pub(crate) async fn run(self: Arc<Self>, data_dir: PathBuf) {
+        sd_notify("READY=1\nSTATUS=healthy");
+        let interval = self
+            .watchdog
+            .map(|watchdog| (watchdog / 2).min(PROBE_INTERVAL))
+            .unwrap_or(PROBE_INTERVAL);
+        let mut probes = 0u32;
+        let mut was_healthy = true;
+        loop {
+            let started = Instant::now();
+            tokio::time::sleep(interval).await;
+            let lag = started.elapsed().saturating_sub(interval);
+            let storage = if probes.is_multiple_of(STORAGE_PROBE_EVERY) {
+                Some(probe_storage(&data_dir).await)
+            } else {
+                None
+            };
+            probes = probes.wrapping_add(1);
+            let report = self.record(lag, storage);
+            if report.healthy != was_healthy {
+                was_healthy = report.healthy;
+                tracing::info!(
+                    "health: {}",
+                    report.status_line().trim_start_matches("STATUS=")
+                );
+                sd_notify(&report.status_line());
+            }
+            if report.healthy && self.watchdog.is_some() {
+                sd_notify("WATCHDOG=1");
+            }
+        }
+    }
+}
+
+/// The ping interval systemd asked for, if it is meant for this process.
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.trim().parse::<u32>().ok() != Some(own_pid) {
+            return None;
+        }
+    }
+    let usec = usec?.trim().parse::<u64>().ok()?;
+    Some(Duration::from_micros(usec)).filter(|interval| !interval.is_zero())
+}
+
+async fn probe_storage(data_dir: &Path) -> Result<(), String> {
+    let path = data_dir.join(STORAGE_PROBE_FILE);
+    tokio::fs::write(&path, now_millis().to_string())
+        .await
+        .map_err(|err| format!("{}: {err}", data_dir.display()))?;
+    let _ = tokio::fs::remove_file(&path).await;
+    Ok(())
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Sends one notification to `$NOTIFY_SOCKET`; a no-op outside systemd.
+pub(crate) fn sd_notify(message: &str) {
+    if let Some(socket) = std::env::var_os("NOTIFY_SOCKET") {
+        if let Err(err) = notify_socket(&socket.to_string_lossy(), message) {
+            tracing::warn!("sd_notify failed: {err}");
+        }
+    }
+}
+
+#[cfg(unix)]
+fn notify_socket(socket: &str, message: &str) -> Result<(), String> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound().map_err(|err| err.to_string())?;
+    if let Some(name) = socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())
+                .map_err(|err| err.to_string())?;
+            datagram
+                .send_to_addr(message.as_bytes(), &addr)
+                .map_err(|err| err.to_string())?;
+            return Ok(());
+        }
+        #[cfg(not(target_os = "linux"))]
+        return Err(format!(
+            "abstract socket @{name} is only supported on Linux"
+        ));
+    }
+    datagram
+        .send_to(message.as_bytes(), socket)
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn notify_socket(_socket: &str, _message: &str) -> Result<(), String> {
+    Err("NOTIFY_SOCKET is only supported on Unix".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn watchdog_interval_honours_the_target_pid() {
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 7),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("7"), 7),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("8"), 7), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 7), None);
+        assert_eq!(watchdog_interval(None, Some("7"), 7), None);
+    }
+
+    #[test]
+    fn lag_and_storage_failures_make_the_daemon_unhealthy() {
+        let monitor = HealthMonitor::new(Some(Duration::from_secs(10)));
+        assert_eq!(monitor.report().watchdog_interval_ms, Some(10_000));
+        let report = monitor.record(Duration::from_millis(40), Some(Ok(())));
+        assert!(report.healthy);
+        assert_eq!(report.status_line(), "STATUS=healthy");
+
+        let report = monitor.record(Duration::from_secs(6), None);
+        assert!(!report.healthy);
+        assert_eq!(report.max_event_loop_lag_ms, 6000);
+        assert!(report.status_line().contains("lagging by 6000 ms"));
+
+        let report = monitor.record(Duration::ZERO, Some(Err("read-only".to_string())));
+        assert!(!report.healthy);
+        assert_eq!(
+            report.status_line(),
+            "STATUS=storage not writable: read-only"
+        );
+        let report = monitor.record(Duration::ZERO, None);
+        assert!(
+            !report.storage_writable,
+            "storage state holds between checks"
+        );
+        let report = monitor.record(Duration::ZERO, Some(Ok(())));
+        assert!(report.healthy);
+        assert_eq!(report.max_event_loop_lag_ms, 6000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn notifies_a_datagram_socket_and_probes_storage() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-notify-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("notify.sock");
+        let receiver = std::os::unix::net::UnixDatagram::bind(&path).expect("bind");
+        notify_socket(&path.to_string_lossy(), "READY=1").expect("notify");
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).expect("recv");
+        assert_eq!(&buf[..len], b"READY=1");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        runtime.block_on(probe_storage(&dir)).expect("writable");
+        assert!(!dir.join(STORAGE_PROBE_FILE).exists());
+        assert!(runtime
+            .block_on(probe_storage(&dir.join("missing")))
+            .is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
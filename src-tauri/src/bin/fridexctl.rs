@@ -0,0 +1,923 @@
+use fridex_core::shared::file_history_core::content_hash;
+use fridex_core::shared::tls_core;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+
+use base64::Engine;
+use serde_json::{json, Value};
+use tokio_rustls::rustls::{ClientConnection, StreamOwned};
+
+const DEFAULT_HOST: &str = "127.0.0.1:4732";
+const DEFAULT_REMOTE_DAEMON: &str = "codex-monitor-daemon";
+/// App-server event schema requested from the daemon; schema 2 spells ids in camelCase.
+const EVENT_SCHEMA: u64 = 2;
+
+fn usage() -> String {
+    format!(
+        "\
+USAGE:
+  fridexctl [--host <addr> [--tls] [--tls-ca <pem>] | --unix <path> | --ssh <destination>]
+            [--token <token>] [--json] <command>
+
+OPTIONS:
+  --host <addr>          Daemon address (default: {DEFAULT_HOST})
+  --tls                  Connect over TLS to a daemon started with --tls-cert
+  --tls-ca <pem>         Also trust this certificate, e.g. a self-signed daemon's (implies --tls)
+  --unix <path>          Connect to a daemon started with --listen-unix <path> instead
+  --ssh <destination>    Run `codex-monitor-daemon --stdio` on that host over ssh instead
+  --remote-daemon <path> Daemon binary to run over --ssh (default: {DEFAULT_REMOTE_DAEMON})
+  --token <token>        Daemon token (default: $CODEX_MONITOR_DAEMON_TOKEN)
+  --json                 Print raw JSON results instead of tables and text
+  -h, --help             Show this help
+
+COMMANDS:
+  workspaces                         List workspaces
+  threads <workspace>                List a workspace's threads
+  send <workspace> [--thread <id>] [--model <id>] [--no-wait] <prompt>...
+                                     Send a prompt (`-` reads it from stdin), starting a new
+                                     thread unless --thread is given, and stream the reply
+                                     until the turn completes
+  events [<workspace>...]            Print daemon events as JSON lines until interrupted
+  read <workspace> <path>            Print a workspace file
+  write <workspace> <path>           Replace a workspace file with stdin, streamed in chunks
+  read-file <agents|config> [<workspace>]
+                                     Print AGENTS.md or config.toml (global without a workspace)
+  write-file <agents|config> [<workspace>]
+                                     Replace AGENTS.md or config.toml with stdin
+  worktrees <workspace>              List a workspace's worktrees
+  worktree add <workspace> <branch>  Create a worktree on a new branch
+  worktree rename <worktree> <branch>
+  worktree remove <worktree> [--yes]
+                                     Show what removing a worktree deletes; --yes deletes it
+  call <method> [<params-json>|-]    Call any daemon method and print its result
+
+Workspaces can be given by id or by name.
+"
+    )
+}
+
+struct Options {
+    host: String,
+    tls: bool,
+    tls_ca: Option<String>,
+    unix: Option<String>,
+    ssh: Option<String>,
+    remote_daemon: String,
+    token: Option<String>,
+    json: bool,
+    command: CtlCommand,
+}
+
+enum FileTarget {
+    Agents,
+    Config,
+}
+
+enum CtlCommand {
+    Workspaces,
+    Threads {
+        workspace: String,
+    },
+    Send {
+        workspace: String,
+        thread_id: Option<String>,
+        model: Option<String>,
+        wait: bool,
+        prompt: String,
+    },
+    Events {
+        workspaces: Vec<String>,
+    },
+    Read {
+        workspace: String,
+        path: String,
+    },
+    Write {
+        workspace: String,
+        path: String,
+    },
+    ReadFile {
+        target: FileTarget,
+        workspace: Option<String>,
+    },
+    WriteFile {
+        target: FileTarget,
+        workspace: Option<String>,
+    },
+    Worktrees {
+        workspace: String,
+    },
+    AddWorktree {
+        workspace: String,
+        branch: String,
+    },
+    RenameWorktree {
+        worktree: String,
+        branch: String,
+    },
+    RemoveWorktree {
+        worktree: String,
+        confirmed: bool,
+    },
+    Call {
+        method: String,
+        params: Option<String>,
+    },
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut host = DEFAULT_HOST.to_string();
+    let mut tls = false;
+    let mut tls_ca: Option<String> = None;
+    let mut unix: Option<String> = None;
+    let mut ssh: Option<String> = None;
+    let mut remote_daemon = DEFAULT_REMOTE_DAEMON.to_string();
+    let mut token = env::var("CODEX_MONITOR_DAEMON_TOKEN")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let mut json = false;
+
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{}", usage());
+                std::process::exit(0);
+            }
+            "--host" => host = args.next().ok_or("--host requires a value")?,
+            "--tls" => tls = true,
+            "--tls-ca" => {
+                tls_ca = Some(args.next().ok_or("--tls-ca requires a value")?);
+                tls = true;
+            }
+            "--unix" => unix = Some(args.next().ok_or("--unix requires a value")?),
+            "--ssh" => ssh = Some(args.next().ok_or("--ssh requires a value")?),
+            "--remote-daemon" => {
+                remote_daemon = args.next().ok_or("--remote-daemon requires a value")?;
+            }
+            "--token" => {
+                let value = args.next().ok_or("--token requires a value")?;
+                token = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+            }
+            "--json" => json = true,
+            _ => return Err(format!("Unknown option: {arg}")),
+        }
+    }
+
+    if unix.is_some() && ssh.is_some() {
+        return Err("--unix can't be combined with --ssh".to_string());
+    }
+    if tls && (unix.is_some() || ssh.is_some()) {
+        return Err("--tls only applies to --host connections".to_string());
+    }
+
+    let command = parse_command(args.collect())?;
+    Ok(Options {
+        host,
+        tls,
+        tls_ca,
+        unix,
+        ssh,
+        remote_daemon,
+        token,
+        json,
+        command,
+    })
+}
+
+fn parse_command(args: Vec<String>) -> Result<CtlCommand, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Err("Missing command".to_string());
+    };
+    let rest = rest.iter().map(String::as_str).collect::<Vec<_>>();
+    let command = match (name.as_str(), rest.as_slice()) {
+        ("workspaces", []) => CtlCommand::Workspaces,
+        ("threads", [workspace]) => CtlCommand::Threads {
+            workspace: workspace.to_string(),
+        },
+        ("send", [workspace, rest @ ..]) => {
+            let mut thread_id = None;
+            let mut model = None;
+            let mut wait = true;
+            let mut prompt = Vec::new();
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next() {
+                match *arg {
+                    "--thread" => thread_id = Some(rest.next().ok_or("--thread requires a value")?),
+                    "--model" => model = Some(rest.next().ok_or("--model requires a value")?),
+                    "--no-wait" => wait = false,
+                    _ => prompt.push(*arg),
+                }
+            }
+            let prompt = match prompt.as_slice() {
+                ["-"] => read_stdin()?,
+                _ => prompt.join(" "),
+            };
+            if prompt.trim().is_empty() {
+                return Err("send requires a prompt".to_string());
+            }
+            CtlCommand::Send {
+                workspace: workspace.to_string(),
+                thread_id: thread_id.map(|id| id.to_string()),
+                model: model.map(|id| id.to_string()),
+                wait,
+                prompt,
+            }
+        }
+        ("events", workspaces) => CtlCommand::Events {
+            workspaces: workspaces.iter().map(|id| id.to_string()).collect(),
+        },
+        ("read", [workspace, path]) => CtlCommand::Read {
+            workspace: workspace.to_string(),
+            path: path.to_string(),
+        },
+        ("write", [workspace, path]) => CtlCommand::Write {
+            workspace: workspace.to_string(),
+            path: path.to_string(),
+        },
+        ("read-file" | "write-file", [target, workspace @ ..]) if workspace.len() <= 1 => {
+            let target = match *target {
+                "agents" => FileTarget::Agents,
+                "config" => FileTarget::Config,
+                other => return Err(format!("Unknown file `{other}`; use agents or config")),
+            };
+            let workspace = workspace.first().map(|id| id.to_string());
+            if name == "read-file" {
+                CtlCommand::ReadFile { target, workspace }
+            } else {
+                CtlCommand::WriteFile { target, workspace }
+            }
+        }
+        ("worktrees", [workspace]) => CtlCommand::Worktrees {
+            workspace: workspace.to_string(),
+        },
+        ("worktree", ["add", workspace, branch]) => CtlCommand::AddWorktree {
+            workspace: workspace.to_string(),
+            branch: branch.to_string(),
+        },
+        ("worktree", ["rename", worktree, branch]) => CtlCommand::RenameWorktree {
+            worktree: worktree.to_string(),
+            branch: branch.to_string(),
+        },
+        ("worktree", ["remove", worktree]) => CtlCommand::RemoveWorktree {
+            worktree: worktree.to_string(),
+            confirmed: false,
+        },
+        ("worktree", ["remove", worktree, "--yes"] | ["remove", "--yes", worktree]) => {
+            CtlCommand::RemoveWorktree {
+                worktree: worktree.to_string(),
+                confirmed: true,
+            }
+        }
+        ("call", [method, params @ ..]) if params.len() <= 1 => CtlCommand::Call {
+            method: method.to_string(),
+            params: params.first().map(|params| params.to_string()),
+        },
+        _ => return Err(format!("Invalid arguments for `{name}`")),
+    };
+    Ok(command)
+}
+
+fn read_stdin() -> Result<String, String> {
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .map_err(|err| format!("Failed to read stdin: {err}"))?;
+    Ok(content)
+}
+
+fn read_stdin_bytes() -> Result<Vec<u8>, String> {
+    let mut content = Vec::new();
+    io::stdin()
+        .read_to_end(&mut content)
+        .map_err(|err| format!("Failed to read stdin: {err}"))?;
+    Ok(content)
+}
+
+/// A TLS session shared by the client's reader and writer, which take turns on it.
+#[derive(Clone)]
+struct TlsStream(Rc<RefCell<StreamOwned<ClientConnection, TcpStream>>>);
+
+impl TlsStream {
+    fn connect(stream: TcpStream, options: &Options) -> Result<Self, String> {
+        let config = tls_core::client_config(options.tls_ca.as_deref().map(Path::new))?;
+        let name = tls_core::server_name(&options.host)?;
+        let connection = ClientConnection::new(config, name).map_err(|err| err.to_string())?;
+        let mut stream = StreamOwned::new(connection, stream);
+        while stream.conn.is_handshaking() {
+            stream
+                .conn
+                .complete_io(&mut stream.sock)
+                .map_err(|err| format!("TLS handshake with {} failed: {err}", options.host))?;
+        }
+        Ok(Self(Rc::new(RefCell::new(stream))))
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+/// Blocking line-delimited JSON-RPC client. Notifications that arrive while waiting for
+/// a response are queued for `next_event`.
+struct DaemonClient {
+    reader: Box<dyn BufRead>,
+    writer: Box<dyn Write>,
+    next_id: u64,
+    events: VecDeque<Value>,
+    ssh: Option<Child>,
+}
+
+impl DaemonClient {
+    fn connect(options: &Options) -> Result<Self, String> {
+        let mut client = match (&options.ssh, &options.unix) {
+            (Some(destination), _) => {
+                let mut child = Command::new("ssh")
+                    .arg(destination)
+                    .arg(&options.remote_daemon)
+                    .arg("--stdio")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .map_err(|err| format!("Failed to run ssh: {err}"))?;
+                let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+                    return Err("Failed to open the ssh pipes".to_string());
+                };
+                Self {
+                    reader: Box::new(BufReader::new(stdout)),
+                    writer: Box::new(stdin),
+                    next_id: 1,
+                    events: VecDeque::new(),
+                    ssh: Some(child),
+                }
+            }
+            #[cfg(unix)]
+            (None, Some(path)) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)
+                    .map_err(|err| format!("Failed to connect to {path}: {err}"))?;
+                let writer = stream.try_clone().map_err(|err| err.to_string())?;
+                Self {
+                    reader: Box::new(BufReader::new(stream)),
+                    writer: Box::new(writer),
+                    next_id: 1,
+                    events: VecDeque::new(),
+                    ssh: None,
+                }
+            }
+            #[cfg(not(unix))]
+            (None, Some(_)) => return Err("--unix is only supported on Unix".to_string()),
+            (None, None) => {
+                let stream = TcpStream::connect(&options.host)
+                    .map_err(|err| format!("Failed to connect to {}: {err}", options.host))?;
+                if options.tls {
+                    let stream = TlsStream::connect(stream, options)?;
+                    Self {
+                        reader: Box::new(BufReader::new(stream.clone())),
+                        writer: Box::new(stream),
+                        next_id: 1,
+                        events: VecDeque::new(),
+                        ssh: None,
+                    }
+                } else {
+                    let writer = stream.try_clone().map_err(|err| err.to_string())?;
+                    Self {
+                        reader: Box::new(BufReader::new(stream)),
+                        writer: Box::new(writer),
+                        next_id: 1,
+                        events: VecDeque::new(),
+                        ssh: None,
+                    }
+                }
+            }
+        };
+
+        let identity = json!({
+            "deviceName": "fridexctl",
+            "clientVersion": format!("fridexctl-{}", env!("CARGO_PKG_VERSION")),
+            "eventSchema": EVENT_SCHEMA,
+        });
+        let handshake = match &options.token {
+            Some(token) => {
+                let mut params = identity;
+                params["token"] = json!(token);
+                client.call("auth", params)
+            }
+            None => client.call("identify_client", identity),
+        };
+        match handshake {
+            Err(err) if err == "unauthorized" => Err(
+                "The daemon requires a token; pass --token or set CODEX_MONITOR_DAEMON_TOKEN"
+                    .to_string(),
+            ),
+            Err(err) => Err(err),
+            Ok(_) => Ok(client),
+        }
+    }
+
+    fn read_message(&mut self) -> Result<Value, String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err("The daemon closed the connection".to_string()),
+            Ok(_) => serde_json::from_str(&line).map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(self.writer, "{request}")
+            .and_then(|()| self.writer.flush())
+            .map_err(|err| err.to_string())?;
+        loop {
+            let message = self.read_message()?;
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                self.events.push_back(message);
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(error["message"].as_str().unwrap_or("error").to_string());
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Value, String> {
+        match self.events.pop_front() {
+            Some(event) => Ok(event),
+            None => self.read_message(),
+        }
+    }
+}
+
+impl Drop for DaemonClient {
+    fn drop(&mut self) {
+        if let Some(child) = self.ssh.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Finds a workspace by id, then by unique name.
+fn resolve_workspace(client: &mut DaemonClient, reference: &str) -> Result<Value, String> {
+    let workspaces = client.call("list_workspaces", json!({}))?;
+    let workspaces = workspaces.as_array().cloned().unwrap_or_default();
+    if let Some(workspace) = workspaces.iter().find(|info| info["id"] == reference) {
+        return Ok(workspace.clone());
+    }
+    let named = workspaces
+        .iter()
+        .filter(|info| info["name"] == reference)
+        .collect::<Vec<_>>();
+    match named.as_slice() {
+        [workspace] => Ok((*workspace).clone()),
+        [] => Err(format!("No workspace with id or name `{reference}`")),
+        _ => Err(format!(
+            "Several workspaces are named `{reference}`; use an id"
+        )),
+    }
+}
+
+fn workspace_id(workspace: &Value) -> String {
+    workspace["id"].as_str().unwrap_or_default().to_string()
+}
+
+/// Results from the app-server are wrapped in its response envelope.
+fn unwrap_result(value: &Value) -> &Value {
+    value.get("result").unwrap_or(value)
+}
+
+fn print_json(value: &Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+    );
+}
+
+fn first_line(text: &str, max_chars: usize) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() > max_chars {
+        format!("{}…", line.chars().take(max_chars).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let mut client = DaemonClient::connect(&options)?;
+    let json_output = options.json;
+    match options.command {
+        CtlCommand::Workspaces => {
+            let workspaces = client.call("list_workspaces", json!({}))?;
+            if json_output {
+                print_json(&workspaces);
+                return Ok(());
+            }
+            for workspace in workspaces.as_array().into_iter().flatten() {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    workspace["id"].as_str().unwrap_or_default(),
+                    workspace["name"].as_str().unwrap_or_default(),
+                    if workspace["connected"] == true {
+                        "connected"
+                    } else {
+                        "disconnected"
+                    },
+                    workspace["path"].as_str().unwrap_or_default(),
+                );
+            }
+        }
+        CtlCommand::Threads { workspace } => {
+            let workspace = resolve_workspace(&mut client, &workspace)?;
+            let threads = client.call(
+                "list_threads",
+                json!({ "workspaceId": workspace_id(&workspace), "aggregate": true }),
+            )?;
+            if json_output {
+                print_json(&threads);
+                return Ok(());
+            }
+            for thread in unwrap_result(&threads)["data"]
+                .as_array()
+                .into_iter()
+                .flatten()
+            {
+                let updated_at = thread
+                    .get("updatedAt")
+                    .or_else(|| thread.get("createdAt"))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                println!(
+                    "{}\t{}\t{}",
+                    thread["id"].as_str().unwrap_or_default(),
+                    updated_at,
+                    first_line(thread["preview"].as_str().unwrap_or_default(), 80),
+                );
+            }
+        }
+        CtlCommand::Send {
+            workspace,
+            thread_id,
+            model,
+            wait,
+            prompt,
+        } => {
+            let workspace = resolve_workspace(&mut client, &workspace)?;
+            send(
+                &mut client,
+                &workspace,
+                thread_id,
+                model,
+                wait,
+                prompt,
+                json_output,
+            )?;
+        }
+        CtlCommand::Events { workspaces } => {
+            if !workspaces.is_empty() {
+                let ids = workspaces
+                    .iter()
+                    .map(|reference| {
+                        resolve_workspace(&mut client, reference).map(|ws| workspace_id(&ws))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                client.call("subscribe_events", json!({ "workspaceIds": ids }))?;
+            }
+            let mut stdout = io::stdout();
+            loop {
+                let event = client.next_event()?;
+                if writeln!(stdout, "{event}")
+                    .and_then(|()| stdout.flush())
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+        }
+        CtlCommand::Read { workspace, path } => {
+            let workspace = resolve_workspace(&mut client, &workspace)?;
+            let file = client.call(
+                "read_workspace_file",
+                json!({ "workspaceId": workspace_id(&workspace), "path": path }),
+            )?;
+            if json_output {
+                print_json(&file);
+                return Ok(());
+            }
+            print!("{}", file["content"].as_str().unwrap_or_default());
+            if file["truncated"] == true {
+                eprintln!("(truncated)");
+            }
+        }
+        CtlCommand::Write { workspace, path } => {
+            let workspace = resolve_workspace(&mut client, &workspace)?;
+            let content = read_stdin_bytes()?;
+            let committed =
+                write_streamed(&mut client, &workspace_id(&workspace), &path, &content)?;
+            if json_output {
+                print_json(&committed);
+            }
+        }
+        CtlCommand::ReadFile { target, workspace } => {
+            let params = file_params(&mut client, target, workspace)?;
+            let file = client.call("file_read", params)?;
+            if json_output {
+                print_json(&file);
+                return Ok(());
+            }
+            if file["exists"] == false {
+                return Err("The file does not exist".to_string());
+            }
+            print!("{}", file["content"].as_str().unwrap_or_default());
+            if file["truncated"] == true {
+                eprintln!("(truncated)");
+            }
+        }
+        CtlCommand::WriteFile { target, workspace } => {
+            let mut params = file_params(&mut client, target, workspace)?;
+            params["content"] = json!(read_stdin()?);
+            let result = client.call("file_write", params)?;
+            if json_output {
+                print_json(&result);
+            }
+        }
+        CtlCommand::Worktrees { workspace } => {
+            let workspace = resolve_workspace(&mut client, &workspace)?;
+            let workspaces = client.call("list_workspaces", json!({}))?;
+            let worktrees = workspaces
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|info| info["parentId"] == workspace["id"])
+                .cloned()
+                .collect::<Vec<_>>();
+            if json_output {
+                print_json(&Value::Array(worktrees));
+                return Ok(());
+            }
+            for worktree in worktrees {
+                println!(
+                    "{}\t{}\t{}",
+                    worktree["id"].as_str().unwrap_or_default(),
+                    worktree["worktree"]["branch"].as_str().unwrap_or_default(),
+                    worktree["path"].as_str().unwrap_or_default(),
+                );
+            }
+        }
+        CtlCommand::AddWorktree { workspace, branch } => {
+            let workspace = resolve_workspace(&mut client, &workspace)?;
+            let worktree = client.call(
+                "add_worktree",
+                json!({ "parentId": workspace_id(&workspace), "branch": branch }),
+            )?;
+            if json_output {
+                print_json(&worktree);
+            } else {
+                println!("{}", worktree["id"].as_str().unwrap_or_default());
+            }
+        }
+        CtlCommand::RenameWorktree { worktree, branch } => {
+            let worktree = resolve_workspace(&mut client, &worktree)?;
+            let renamed = client.call(
+                "rename_worktree",
+                json!({ "id": workspace_id(&worktree), "branch": branch }),
+            )?;
+            if json_output {
+                print_json(&renamed);
+            }
+        }
+        CtlCommand::RemoveWorktree {
+            worktree,
+            confirmed,
+        } => {
+            let worktree = resolve_workspace(&mut client, &worktree)?;
+            let id = workspace_id(&worktree);
+            let plan = client.call("remove_worktree", json!({ "id": id }))?;
+            if !confirmed {
+                if json_output {
+                    print_json(&plan);
+                    return Ok(());
+                }
+                for folder in plan["deletedFolders"].as_array().into_iter().flatten() {
+                    let changes = match folder["uncommittedFiles"].as_u64() {
+                        Some(0) => "clean".to_string(),
+                        Some(count) => format!("{count} uncommitted files"),
+                        None => "status unknown".to_string(),
+                    };
+                    println!(
+                        "would delete {} ({}, {changes})",
+                        folder["path"].as_str().unwrap_or_default(),
+                        folder["branch"].as_str().unwrap_or_default()
+                    );
+                }
+                println!("Re-run with --yes to delete it.");
+                return Ok(());
+            }
+            client.call(
+                "remove_worktree",
+                json!({ "id": id, "confirmToken": plan["confirmToken"] }),
+            )?;
+        }
+        CtlCommand::Call { method, params } => {
+            let params = match params.as_deref() {
+                None => json!({}),
+                Some("-") => serde_json::from_str(&read_stdin()?)
+                    .map_err(|err| format!("Invalid params JSON: {err}"))?,
+                Some(params) => serde_json::from_str(params)
+                    .map_err(|err| format!("Invalid params JSON: {err}"))?,
+            };
+            print_json(&client.call(&method, params)?);
+        }
+    }
+    Ok(())
+}
+
+/// Uploads `content` through `begin_write`, `write_chunk` and `commit_write`, so files
+/// larger than one message fit. A failed upload is aborted on the daemon.
+fn write_streamed(
+    client: &mut DaemonClient,
+    workspace_id: &str,
+    path: &str,
+    content: &[u8],
+) -> Result<Value, String> {
+    let started = client.call(
+        "begin_write",
+        json!({ "workspaceId": workspace_id, "path": path, "size": content.len() }),
+    )?;
+    let write_id = started["writeId"]
+        .as_str()
+        .ok_or("begin_write returned no writeId")?
+        .to_string();
+    let chunk_bytes = started["maxChunkBytes"]
+        .as_u64()
+        .filter(|bytes| *bytes > 0)
+        .ok_or("begin_write returned no maxChunkBytes")? as usize;
+    let mut upload = || {
+        for (index, chunk) in content.chunks(chunk_bytes).enumerate() {
+            client.call(
+                "write_chunk",
+                json!({
+                    "writeId": write_id,
+                    "offset": index * chunk_bytes,
+                    "data": base64::engine::general_purpose::STANDARD.encode(chunk),
+                }),
+            )?;
+        }
+        client.call(
+            "commit_write",
+            json!({ "writeId": write_id, "sha256": content_hash(content) }),
+        )
+    };
+    let committed = upload();
+    if committed.is_err() {
+        let _ = client.call("abort_write", json!({ "writeId": write_id }));
+    }
+    committed
+}
+
+fn file_params(
+    client: &mut DaemonClient,
+    target: FileTarget,
+    workspace: Option<String>,
+) -> Result<Value, String> {
+    let kind = match target {
+        FileTarget::Agents => "agents",
+        FileTarget::Config => "config",
+    };
+    Ok(match workspace {
+        Some(workspace) => {
+            let workspace = resolve_workspace(client, &workspace)?;
+            json!({ "scope": "workspace", "kind": kind, "workspaceId": workspace_id(&workspace) })
+        }
+        None => json!({ "scope": "global", "kind": kind }),
+    })
+}
+
+fn send(
+    client: &mut DaemonClient,
+    workspace: &Value,
+    thread_id: Option<String>,
+    model: Option<String>,
+    wait: bool,
+    prompt: String,
+    json_output: bool,
+) -> Result<(), String> {
+    let workspace_id = workspace_id(workspace);
+    if workspace["connected"] != true {
+        client.call("connect_workspace", json!({ "id": workspace_id }))?;
+    }
+    let thread_id = match thread_id {
+        Some(thread_id) => thread_id,
+        None => {
+            let started = client.call("start_thread", json!({ "workspaceId": workspace_id }))?;
+            let thread_id = unwrap_result(&started)["thread"]["id"]
+                .as_str()
+                .ok_or_else(|| format!("start_thread returned no thread id: {started}"))?
+                .to_string();
+            eprintln!("thread {thread_id}");
+            thread_id
+        }
+    };
+    let response = client.call(
+        "send_user_message",
+        json!({
+            "workspaceId": workspace_id,
+            "threadId": thread_id,
+            "text": prompt,
+            "model": model,
+        }),
+    )?;
+    let turn_id = unwrap_result(&response)["turn"]["id"].clone();
+    if !wait {
+        if json_output {
+            print_json(&json!({ "threadId": thread_id, "result": response }));
+        } else {
+            println!("{}", turn_id.as_str().unwrap_or_default());
+        }
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout();
+    loop {
+        let event = client.next_event()?;
+        if event["method"] != "app-server-event" || event["params"]["workspace_id"] != workspace_id
+        {
+            continue;
+        }
+        let message = &event["params"]["message"];
+        let params = &message["params"];
+        if params["threadId"] != thread_id.as_str() {
+            continue;
+        }
+        if json_output {
+            println!("{event}");
+        }
+        let method = message["method"].as_str().unwrap_or_default();
+        match method {
+            "item/agentMessage/delta" if !json_output => {
+                print!("{}", params["delta"].as_str().unwrap_or_default());
+                let _ = stdout.flush();
+            }
+            "error" if !json_output => {
+                eprintln!(
+                    "\nerror: {}",
+                    params["error"]["message"]
+                        .as_str()
+                        .unwrap_or("unknown error")
+                );
+            }
+            "turn/completed" if params["turn"]["id"] == turn_id => {
+                if !json_output {
+                    println!();
+                }
+                if params["turn"]["status"] == "failed" {
+                    let message = params["turn"]["error"]["message"]
+                        .as_str()
+                        .unwrap_or("turn failed");
+                    return Err(message.to_string());
+                }
+                return Ok(());
+            }
+            _ if message.get("id").is_some() && !json_output => {
+                eprintln!("\nwaiting on `{method}`; answer it from the app");
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n\n{}", usage());
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = run(options) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
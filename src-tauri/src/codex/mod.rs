@@ -1,16 +1,16 @@
 use serde_json::{json, Map, Value};
 use std::io::ErrorKind;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 
-pub(crate) mod args;
-pub(crate) mod config;
-pub(crate) mod home;
+pub(crate) use fridex_core::codex::{args, config, home};
+
+use args::CodexArgs;
 
 pub(crate) use crate::backend::app_server::WorkspaceSession;
 use crate::backend::events::AppServerEvent;
@@ -21,30 +21,71 @@ use crate::backend::app_server::{
 use crate::shared::process_core::tokio_command;
 use crate::event_sink::TauriEventSink;
 use crate::remote_backend;
-use crate::shared::codex_core;
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::session_log_core::{self, SessionLogTail};
+use crate::shared::account_profiles_core::{AccountProfile, AccountProfileInfo};
+use crate::shared::auth_watch_core;
+use crate::shared::collaboration_presets_core::CollaborationPreset;
+use crate::shared::drafts_core::ThreadDraft;
+use crate::shared::mcp_health_core::{self, McpServerHealth};
+use crate::shared::pinned_context_core::{self, ContextPin, PinInput, PinnedContextView};
+use crate::shared::rules_lint_core::{self, RulesFormatResult, RulesValidation};
+use crate::shared::slash_commands_core::{self, SlashCommandInfo};
+use crate::shared::thread_branches_core::ThreadGraph;
+use crate::shared::time_tracking_core::{TimePeriod, TimeSummary};
+use crate::shared::turn_snapshots_core::SnapshotRestore;
+use crate::shared::{codex_core, commit_message_core, list_cache_core, thread_branches_core};
 use crate::state::AppState;
 use crate::types::WorkspaceEntry;
 
 pub(crate) async fn spawn_workspace_session(
     entry: WorkspaceEntry,
     default_codex_bin: Option<String>,
-    codex_args: Option<String>,
+    codex_args: Option<CodexArgs>,
     app_handle: AppHandle,
     codex_home: Option<PathBuf>,
 ) -> Result<Arc<WorkspaceSession>, String> {
     let client_version = app_handle.package_info().version.to_string();
+    let session_logs_dir = app_handle.state::<AppState>().session_logs_dir.clone();
     let event_sink = TauriEventSink::new(app_handle);
     spawn_workspace_session_inner(
         entry,
         default_codex_bin,
         codex_args,
         codex_home,
+        Some(session_logs_dir),
         client_version,
         event_sink,
     )
     .await
 }
 
+#[tauri::command]
+pub(crate) async fn get_session_log(
+    workspace_id: String,
+    tail: Option<usize>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<SessionLogTail, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "get_session_log",
+            json!({ "workspaceId": workspace_id, "tail": tail }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let dir = state.session_logs_dir.clone();
+    tokio::task::spawn_blocking(move || {
+        session_log_core::session_log_tail_core(&dir, &workspace_id, tail)
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
 #[tauri::command]
 pub(crate) async fn codex_doctor(
     codex_bin: Option<String>,
@@ -147,6 +188,7 @@ pub(crate) async fn codex_doctor(
 #[tauri::command]
 pub(crate) async fn start_thread(
     workspace_id: String,
+    cwd: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -155,12 +197,37 @@ pub(crate) async fn start_thread(
             &*state,
             app,
             "start_thread",
-            json!({ "workspaceId": workspace_id }),
+            json!({ "workspaceId": workspace_id, "cwd": cwd }),
         )
         .await;
     }
 
-    codex_core::start_thread_core(&state.sessions, workspace_id).await
+    let response =
+        codex_core::start_thread_core(&state.sessions, workspace_id.clone(), cwd.clone()).await?;
+    record_thread_branch(&state, &workspace_id, &response, cwd, None).await;
+    Ok(response)
+}
+
+async fn record_thread_branch(
+    state: &AppState,
+    workspace_id: &str,
+    response: &Value,
+    cwd: Option<String>,
+    forked_from: Option<String>,
+) {
+    if let Err(error) = thread_branches_core::record_thread_branch_core(
+        &state.workspaces,
+        &state.thread_branches,
+        &state.thread_branches_path,
+        workspace_id,
+        response,
+        cwd,
+        forked_from,
+    )
+    .await
+    {
+        tracing::warn!(workspace_id, "failed to record thread branch: {error}");
+    }
 }
 
 #[tauri::command]
@@ -200,7 +267,367 @@ pub(crate) async fn fork_thread(
         .await;
     }
 
-    codex_core::fork_thread_core(&state.sessions, workspace_id, thread_id).await
+    let cwd = thread_branches_core::thread_cwd_core(&state.thread_branches, &thread_id).await;
+    let response =
+        codex_core::fork_thread_core(&state.sessions, workspace_id.clone(), thread_id.clone())
+            .await?;
+    record_thread_branch(&state, &workspace_id, &response, cwd, Some(thread_id)).await;
+    Ok(response)
+}
+
+#[tauri::command]
+pub(crate) async fn thread_graph(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ThreadGraph, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "thread_graph",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    Ok(thread_branches_core::thread_graph_core(&state.thread_branches, &workspace_id).await)
+}
+
+#[tauri::command]
+pub(crate) async fn list_slash_commands(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<SlashCommandInfo>, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "list_slash_commands", json!({})).await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let settings = state.app_settings.lock().await;
+    Ok(slash_commands_core::list_slash_commands(&settings.slash_commands))
+}
+
+#[tauri::command]
+pub(crate) async fn save_draft(
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    images: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Option<ThreadDraft>, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "save_draft",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "text": text,
+                "images": images,
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    state
+        .drafts
+        .save(&workspace_id, &thread_id, text, images.unwrap_or_default())
+}
+
+#[tauri::command]
+pub(crate) async fn get_draft(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Option<ThreadDraft>, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "get_draft",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    Ok(state.drafts.get(&workspace_id, &thread_id))
+}
+
+#[tauri::command]
+pub(crate) async fn time_tracking_ping(
+    workspace_id: String,
+    thread_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        remote_backend::call_remote(
+            &*state,
+            app,
+            "time_tracking_ping",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id }),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if !state.workspaces.lock().await.contains_key(&workspace_id) {
+        return Err("workspace not found".to_string());
+    }
+    state
+        .time_tracker
+        .record_focus(&workspace_id, thread_id.as_deref());
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn time_tracking_summary(
+    period: Option<TimePeriod>,
+    count: Option<u32>,
+    workspace_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<TimeSummary, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "time_tracking_summary",
+            json!({ "period": period, "count": count, "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    Ok(state
+        .time_tracker
+        .summary(period.unwrap_or_default(), count, workspace_id.as_deref()))
+}
+
+#[tauri::command]
+pub(crate) async fn list_pinned_context(
+    workspace_id: String,
+    thread_id: String,
+    model: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<PinnedContextView, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "list_pinned_context",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "model": model }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    pinned_context_core::pinned_context_view_core(
+        &state.workspaces,
+        &state.app_settings,
+        &state.pinned_context,
+        &workspace_id,
+        &thread_id,
+        model.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn pin_context(
+    workspace_id: String,
+    thread_id: String,
+    path: Option<String>,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    text: Option<String>,
+    label: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ContextPin, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "pin_context",
+            json!({
+                "workspaceId": workspace_id,
+                "threadId": thread_id,
+                "path": path,
+                "startLine": start_line,
+                "endLine": end_line,
+                "text": text,
+                "label": label,
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    state.pinned_context.pin(
+        &workspace_id,
+        &thread_id,
+        PinInput {
+            path,
+            start_line,
+            end_line,
+            text,
+            label,
+        },
+    )
+}
+
+#[tauri::command]
+pub(crate) async fn unpin_context(
+    workspace_id: String,
+    thread_id: String,
+    pin_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<usize, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "unpin_context",
+            json!({ "workspaceId": workspace_id, "threadId": thread_id, "pinId": pin_id }),
+        )
+        .await?;
+        return Ok(response["removed"].as_u64().unwrap_or(0) as usize);
+    }
+
+    state
+        .pinned_context
+        .unpin(&workspace_id, &thread_id, pin_id.as_deref())
+}
+
+#[tauri::command]
+pub(crate) async fn list_collaboration_presets(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<CollaborationPreset>, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "list_collaboration_presets", json!({}))
+                .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    Ok(state.collaboration_presets.list())
+}
+
+#[tauri::command]
+pub(crate) async fn save_collaboration_preset(
+    name: String,
+    description: Option<String>,
+    collaboration_mode: Value,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<CollaborationPreset, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "save_collaboration_preset",
+            json!({
+                "name": name,
+                "description": description,
+                "collaborationMode": collaboration_mode,
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    state
+        .collaboration_presets
+        .save(&name, description, collaboration_mode)
+}
+
+#[tauri::command]
+pub(crate) async fn delete_collaboration_preset(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "delete_collaboration_preset",
+            json!({ "name": name }),
+        )
+        .await;
+    }
+
+    let deleted = state.collaboration_presets.delete(&name)?;
+    Ok(json!({ "deleted": deleted }))
+}
+
+#[tauri::command]
+pub(crate) async fn list_account_profiles(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<AccountProfileInfo>, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "list_account_profiles", json!({})).await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let workspaces = state.workspaces.lock().await;
+    Ok(state.account_profiles.list(&workspaces))
+}
+
+#[tauri::command]
+pub(crate) async fn save_account_profile(
+    name: String,
+    codex_home: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<AccountProfile, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "save_account_profile",
+            json!({ "name": name, "codexHome": codex_home }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    state.account_profiles.save(&name, &codex_home)
+}
+
+#[tauri::command]
+pub(crate) async fn delete_account_profile(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "delete_account_profile",
+            json!({ "name": name }),
+        )
+        .await;
+    }
+
+    let workspaces = state.workspaces.lock().await;
+    let deleted = state.account_profiles.delete(&name, &workspaces)?;
+    Ok(json!({ "deleted": deleted }))
 }
 
 #[tauri::command]
@@ -209,6 +636,9 @@ pub(crate) async fn list_threads(
     cursor: Option<String>,
     limit: Option<u32>,
     sort_key: Option<String>,
+    branch: Option<String>,
+    aggregate: Option<bool>,
+    refresh: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -221,34 +651,148 @@ pub(crate) async fn list_threads(
                 "workspaceId": workspace_id,
                 "cursor": cursor,
                 "limit": limit,
-                "sortKey": sort_key
+                "sortKey": sort_key,
+                "branch": branch,
+                "aggregate": aggregate,
+                "refresh": refresh
+            }),
+        )
+        .await;
+    }
+
+    let response = list_cache_core::fetch_list(cursor, aggregate.unwrap_or(false), |cursor| {
+        codex_core::list_threads_core(
+            &state.sessions,
+            workspace_id.clone(),
+            cursor,
+            limit,
+            sort_key.clone(),
+        )
+    })
+    .await?;
+    let response = thread_branches_core::enrich_thread_list_core(
+        &state.thread_branches,
+        response,
+        branch.as_deref(),
+    )
+    .await;
+    Ok(state.thread_usage.enrich_thread_list(response))
+}
+
+#[tauri::command]
+pub(crate) async fn list_mcp_server_status(
+    workspace_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+    aggregate: Option<bool>,
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "list_mcp_server_status",
+            json!({
+                "workspaceId": workspace_id,
+                "cursor": cursor,
+                "limit": limit,
+                "aggregate": aggregate,
+                "refresh": refresh
             }),
         )
         .await;
     }
 
-    codex_core::list_threads_core(&state.sessions, workspace_id, cursor, limit, sort_key).await
+    list_cache_core::fetch_list(cursor, aggregate.unwrap_or(false), |cursor| {
+        codex_core::list_mcp_server_status_core(
+            &state.sessions,
+            workspace_id.clone(),
+            cursor,
+            limit,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn mcp_server_oauth_login(
+    workspace_id: String,
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "mcp_server_oauth_login",
+            json!({ "workspaceId": workspace_id, "name": name }),
+        )
+        .await;
+    }
+
+    codex_core::mcp_server_oauth_login_core(&state.sessions, workspace_id, name).await
 }
 
 #[tauri::command]
-pub(crate) async fn list_mcp_server_status(
-    workspace_id: String,
-    cursor: Option<String>,
-    limit: Option<u32>,
+pub(crate) async fn mcp_server_health(
+    workspace_id: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<Value, String> {
+) -> Result<Vec<McpServerHealth>, String> {
     if remote_backend::is_remote_mode(&*state).await {
-        return remote_backend::call_remote(
+        let response = remote_backend::call_remote(
             &*state,
             app,
-            "list_mcp_server_status",
-            json!({ "workspaceId": workspace_id, "cursor": cursor, "limit": limit }),
+            "mcp_server_health",
+            json!({ "workspaceId": workspace_id }),
         )
-        .await;
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
-    codex_core::list_mcp_server_status_core(&state.sessions, workspace_id, cursor, limit).await
+    Ok(state.mcp_health.snapshot(workspace_id.as_deref()))
+}
+
+/// Probes MCP servers of connected workspaces; in remote mode the daemon probes instead.
+pub(crate) fn spawn_mcp_health_prober(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let event_sink = TauriEventSink::new(app.clone());
+        let mut interval = tokio::time::interval(Duration::from_secs(
+            mcp_health_core::MCP_PROBE_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            let state = app.state::<AppState>();
+            if remote_backend::is_remote_mode(&state).await {
+                continue;
+            }
+            state.mcp_health.probe_once(&state.sessions, &event_sink).await;
+        }
+    });
+}
+
+/// Watches Codex sign-ins of connected workspaces; in remote mode the daemon watches.
+pub(crate) fn spawn_auth_watcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let event_sink = TauriEventSink::new(app.clone());
+        let mut interval = tokio::time::interval(Duration::from_secs(
+            auth_watch_core::AUTH_CHECK_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            let state = app.state::<AppState>();
+            if remote_backend::is_remote_mode(&state).await {
+                continue;
+            }
+            state
+                .auth_watcher
+                .check_once(&state.workspaces, &state.sessions, &event_sink)
+                .await;
+        }
+    });
 }
 
 #[tauri::command]
@@ -320,8 +864,12 @@ pub(crate) async fn send_user_message(
     model: Option<String>,
     effort: Option<String>,
     access_mode: Option<String>,
+    sandbox_preset: Option<String>,
+    cwd: Option<String>,
     images: Option<Vec<String>>,
+    documents: Option<Vec<String>>,
     collaboration_mode: Option<Value>,
+    collaboration_mode_preset: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -339,12 +887,18 @@ pub(crate) async fn send_user_message(
         payload.insert("model".to_string(), json!(model));
         payload.insert("effort".to_string(), json!(effort));
         payload.insert("accessMode".to_string(), json!(access_mode));
+        payload.insert("sandboxPreset".to_string(), json!(sandbox_preset));
+        payload.insert("cwd".to_string(), json!(cwd));
         payload.insert("images".to_string(), json!(images));
+        payload.insert("documents".to_string(), json!(documents));
         if let Some(mode) = collaboration_mode {
             if !mode.is_null() {
                 payload.insert("collaborationMode".to_string(), mode);
             }
         }
+        if let Some(preset) = collaboration_mode_preset {
+            payload.insert("collaborationModePreset".to_string(), json!(preset));
+        }
         return remote_backend::call_remote(
             &*state,
             app,
@@ -354,16 +908,36 @@ pub(crate) async fn send_user_message(
         .await;
     }
 
+    let cwd = match cwd {
+        Some(cwd) => Some(cwd),
+        None => thread_branches_core::thread_cwd_core(&state.thread_branches, &thread_id).await,
+    };
+    let entry = state.workspaces.lock().await.get(&workspace_id).cloned();
+    if let Some(entry) = entry {
+        state
+            .thread_usage
+            .record_branch(&thread_id, thread_branches_core::workspace_branch(&entry));
+    }
     codex_core::send_user_message_core(
         &state.sessions,
-        workspace_id,
-        thread_id,
-        text,
-        model,
-        effort,
-        access_mode,
-        images,
-        collaboration_mode,
+        &state.app_settings,
+        &state.turn_snapshots,
+        &state.collaboration_presets,
+        &state.pinned_context,
+        codex_core::SendUserMessageRequest {
+            workspace_id,
+            thread_id,
+            text,
+            model,
+            effort,
+            access_mode,
+            sandbox_preset,
+            cwd,
+            images,
+            documents,
+            collaboration_mode,
+            collaboration_mode_preset,
+        },
     )
     .await
 }
@@ -408,6 +982,30 @@ pub(crate) async fn turn_interrupt(
     codex_core::turn_interrupt_core(&state.sessions, workspace_id, thread_id, turn_id).await
 }
 
+#[tauri::command]
+pub(crate) async fn restore_snapshot(
+    turn_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<SnapshotRestore, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "restore_snapshot",
+            json!({ "turnId": turn_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let restore = state.turn_snapshots.restore(&turn_id).await?;
+    if let Some(entry) = state.workspaces.lock().await.get(&restore.workspace_id) {
+        state.file_lists.invalidate(Path::new(&entry.path));
+    }
+    Ok(restore)
+}
+
 #[tauri::command]
 pub(crate) async fn start_review(
     workspace_id: String,
@@ -451,7 +1049,7 @@ pub(crate) async fn model_list(
         .await;
     }
 
-    codex_core::model_list_core(&state.sessions, workspace_id).await
+    codex_core::model_list_core(&state.sessions, &state.workspaces, workspace_id).await
 }
 
 #[tauri::command]
@@ -555,11 +1153,68 @@ pub(crate) async fn skills_list(
     codex_core::skills_list_core(&state.sessions, workspace_id).await
 }
 
+#[tauri::command]
+pub(crate) async fn skills_config(
+    workspace_id: String,
+    scope: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "skills_config",
+            json!({ "workspaceId": workspace_id, "scope": scope }),
+        )
+        .await;
+    }
+
+    codex_core::skills_config_core(&state.workspaces, workspace_id, scope).await
+}
+
+#[tauri::command]
+pub(crate) async fn skills_set_enabled(
+    workspace_id: String,
+    path: String,
+    enabled: bool,
+    scope: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "skills_set_enabled",
+            json!({
+                "workspaceId": workspace_id,
+                "path": path,
+                "enabled": enabled,
+                "scope": scope,
+            }),
+        )
+        .await;
+    }
+
+    codex_core::skills_set_enabled_core(
+        &state.workspaces,
+        &state.sessions,
+        workspace_id,
+        path,
+        enabled,
+        scope,
+    )
+    .await
+}
+
 #[tauri::command]
 pub(crate) async fn apps_list(
     workspace_id: String,
     cursor: Option<String>,
     limit: Option<u32>,
+    aggregate: Option<bool>,
+    refresh: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<Value, String> {
@@ -568,12 +1223,21 @@ pub(crate) async fn apps_list(
             &*state,
             app,
             "apps_list",
-            json!({ "workspaceId": workspace_id, "cursor": cursor, "limit": limit }),
+            json!({
+                "workspaceId": workspace_id,
+                "cursor": cursor,
+                "limit": limit,
+                "aggregate": aggregate,
+                "refresh": refresh
+            }),
         )
         .await;
     }
 
-    codex_core::apps_list_core(&state.sessions, workspace_id, cursor, limit).await
+    list_cache_core::fetch_list(cursor, aggregate.unwrap_or(false), |cursor| {
+        codex_core::apps_list_core(&state.sessions, workspace_id.clone(), cursor, limit)
+    })
+    .await
 }
 
 #[tauri::command]
@@ -599,16 +1263,6 @@ pub(crate) async fn respond_to_server_request(
         .await
 }
 
-fn build_commit_message_prompt(diff: &str) -> String {
-    format!(
-        "Generate a concise git commit message for the following changes. \
-Follow conventional commit format (e.g., feat:, fix:, refactor:, docs:, etc.). \
-Keep the summary line under 72 characters. \
-Only output the commit message, nothing else.\n\n\
-Changes:\n{diff}"
-    )
-}
-
 /// Gets the diff content for commit message generation
 #[tauri::command]
 pub(crate) async fn get_commit_message_prompt(
@@ -622,7 +1276,7 @@ pub(crate) async fn get_commit_message_prompt(
         return Err("No changes to generate commit message for".to_string());
     }
 
-    let prompt = build_commit_message_prompt(&diff);
+    let prompt = commit_message_core::build_commit_message_prompt(&diff);
 
     Ok(prompt)
 }
@@ -636,6 +1290,48 @@ pub(crate) async fn remember_approval_rule(
     codex_core::remember_approval_rule_core(&state.workspaces, workspace_id, command).await
 }
 
+#[tauri::command]
+pub(crate) async fn validate_rules(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<RulesValidation, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "validate_rules",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    rules_lint_core::validate_rules_core(&state.workspaces, &workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn format_rules(
+    workspace_id: String,
+    write: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<RulesFormatResult, String> {
+    let write = write.unwrap_or(false);
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "format_rules",
+            json!({ "workspaceId": workspace_id, "write": write }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    rules_lint_core::format_rules_core(&state.workspaces, &workspace_id, write).await
+}
+
 #[tauri::command]
 pub(crate) async fn get_config_model(
     workspace_id: String,
@@ -659,176 +1355,29 @@ pub(crate) async fn get_config_model(
 #[tauri::command]
 pub(crate) async fn generate_commit_message(
     workspace_id: String,
+    staged: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<String, String> {
-    // Get the diff from git
-    let diff = crate::git::get_workspace_diff(&workspace_id, &state).await?;
-
-    if diff.trim().is_empty() {
-        return Err("No changes to generate commit message for".to_string());
-    }
-
-    let prompt = build_commit_message_prompt(&diff);
-
-    // Get the session
-    let session = {
-        let sessions = state.sessions.lock().await;
-        sessions
-            .get(&workspace_id)
-            .ok_or("workspace not connected")?
-            .clone()
-    };
-
-    // Create a background thread
-    let thread_params = json!({
-        "cwd": session.entry.path,
-        "approvalPolicy": "never"  // Never ask for approval in background
-    });
-    let thread_result = session.send_request("thread/start", thread_params).await?;
-
-    // Handle error response
-    if let Some(error) = thread_result.get("error") {
-        let error_msg = error
-            .get("message")
-            .and_then(|m| m.as_str())
-            .unwrap_or("Unknown error starting thread");
-        return Err(error_msg.to_string());
-    }
-
-    // Extract threadId - try multiple paths since response format may vary
-    let thread_id = thread_result
-        .get("result")
-        .and_then(|r| r.get("threadId"))
-        .or_else(|| thread_result.get("result").and_then(|r| r.get("thread")).and_then(|t| t.get("id")))
-        .or_else(|| thread_result.get("threadId"))
-        .or_else(|| thread_result.get("thread").and_then(|t| t.get("id")))
-        .and_then(|t| t.as_str())
-        .ok_or_else(|| format!("Failed to get threadId from thread/start response: {:?}", thread_result))?
-        .to_string();
-
-    // Hide background helper threads from the sidebar, even if a thread/started event leaked.
-    let _ = app.emit(
-        "app-server-event",
-        AppServerEvent {
-            workspace_id: workspace_id.clone(),
-            message: json!({
-                "method": "codex/backgroundThread",
-                "params": {
-                    "threadId": thread_id,
-                    "action": "hide"
-                }
-            }),
-        },
-    );
-
-    // Create channel for receiving events
-    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
-
-    // Register callback for this thread
-    {
-        let mut callbacks = session.background_thread_callbacks.lock().await;
-        callbacks.insert(thread_id.clone(), tx);
-    }
-
-    // Start a turn with the commit message prompt
-    let turn_params = json!({
-        "threadId": thread_id,
-        "input": [{ "type": "text", "text": prompt }],
-        "cwd": session.entry.path,
-        "approvalPolicy": "never",
-        "sandboxPolicy": { "type": "readOnly" },
-    });
-    let turn_result = session.send_request("turn/start", turn_params).await;
-    let turn_result = match turn_result {
-        Ok(result) => result,
-        Err(error) => {
-            // Clean up if turn fails to start
-            {
-                let mut callbacks = session.background_thread_callbacks.lock().await;
-                callbacks.remove(&thread_id);
-            }
-            let archive_params = json!({ "threadId": thread_id.as_str() });
-            let _ = session.send_request("thread/archive", archive_params).await;
-            return Err(error);
-        }
-    };
-
-    if let Some(error) = turn_result.get("error") {
-        let error_msg = error
-            .get("message")
-            .and_then(|m| m.as_str())
-            .unwrap_or("Unknown error starting turn");
-        {
-            let mut callbacks = session.background_thread_callbacks.lock().await;
-            callbacks.remove(&thread_id);
-        }
-        let archive_params = json!({ "threadId": thread_id.as_str() });
-        let _ = session.send_request("thread/archive", archive_params).await;
-        return Err(error_msg.to_string());
-    }
-
-    // Collect assistant text from events
-    let mut commit_message = String::new();
-    let timeout_duration = Duration::from_secs(60);
-    let collect_result = timeout(timeout_duration, async {
-        while let Some(event) = rx.recv().await {
-            let method = event.get("method").and_then(|m| m.as_str()).unwrap_or("");
-
-            match method {
-                "item/agentMessage/delta" => {
-                    // Extract text delta from agent messages
-                    if let Some(params) = event.get("params") {
-                        if let Some(delta) = params.get("delta").and_then(|d| d.as_str()) {
-                            commit_message.push_str(delta);
-                        }
-                    }
-                }
-                "turn/completed" => {
-                    // Turn completed, we can stop listening
-                    break;
-                }
-                "turn/error" => {
-                    // Error occurred
-                    let error_msg = event
-                        .get("params")
-                        .and_then(|p| p.get("error"))
-                        .and_then(|e| e.as_str())
-                        .unwrap_or("Unknown error during commit message generation");
-                    return Err(error_msg.to_string());
-                }
-                _ => {
-                    // Ignore other events (turn/started, item/started, item/completed, reasoning events, etc.)
-                }
-            }
-        }
-        Ok(())
-    })
-    .await;
-
-    // Unregister callback
-    {
-        let mut callbacks = session.background_thread_callbacks.lock().await;
-        callbacks.remove(&thread_id);
-    }
-
-    // Archive the thread to clean up
-    let archive_params = json!({ "threadId": thread_id });
-    let _ = session.send_request("thread/archive", archive_params).await;
-
-    // Handle timeout or collection error
-    match collect_result {
-        Ok(Ok(())) => {}
-        Ok(Err(e)) => return Err(e),
-        Err(_) => return Err("Timeout waiting for commit message generation".to_string()),
-    }
-
-    let trimmed = commit_message.trim().to_string();
-    if trimmed.is_empty() {
-        return Err("No commit message was generated".to_string());
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "generate_commit_message",
+            json!({ "workspaceId": workspace_id, "staged": staged }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
-    Ok(trimmed)
+    commit_message_core::generate_commit_message_core(
+        &state.workspaces,
+        &state.sessions,
+        &workspace_id,
+        staged,
+        &TauriEventSink::new(app),
+    )
+    .await
 }
 
 #[tauri::command]
@@ -857,7 +1406,7 @@ pub(crate) async fn generate_run_metadata(
         let sessions = state.sessions.lock().await;
         sessions
             .get(&workspace_id)
-            .ok_or("workspace not connected")?
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotConnected))?
             .clone()
     };
 
@@ -882,7 +1431,7 @@ Task:\n{cleaned_prompt}"
     );
 
     let thread_params = json!({
-        "cwd": session.entry.path,
+        "cwd": session.workspace_path(),
         "approvalPolicy": "never"
     });
     let thread_result = session.send_request("thread/start", thread_params).await?;
@@ -929,7 +1478,7 @@ Task:\n{cleaned_prompt}"
     let turn_params = json!({
         "threadId": thread_id,
         "input": [{ "type": "text", "text": title_prompt }],
-        "cwd": session.entry.path,
+        "cwd": session.workspace_path(),
         "approvalPolicy": "never",
         "sandboxPolicy": { "type": "readOnly" },
     });
@@ -1,6 +1,9 @@
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 
-use crate::backend::events::{AppServerEvent, EventSink, TerminalExit, TerminalOutput};
+use crate::backend::events::{
+    AppServerEvent, EventSink, TerminalExit, TerminalOutput, EVENT_SCHEMA_VERSION,
+};
+use crate::state::AppState;
 
 #[derive(Clone)]
 pub(crate) struct TauriEventSink {
@@ -15,7 +18,15 @@ impl TauriEventSink {
 
 impl EventSink for TauriEventSink {
     fn emit_app_server_event(&self, event: AppServerEvent) {
-        let _ = self.app.emit("app-server-event", event);
+        if let Some(state) = self.app.try_state::<AppState>() {
+            state.thread_usage.observe(&event.message);
+            state
+                .time_tracker
+                .observe(&event.workspace_id, &event.message);
+        }
+        let _ = self
+            .app
+            .emit("app-server-event", event.for_schema(EVENT_SCHEMA_VERSION));
     }
 
     fn emit_terminal_output(&self, event: TerminalOutput) {
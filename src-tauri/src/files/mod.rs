@@ -2,14 +2,22 @@ use serde_json::json;
 use tauri::{AppHandle, State};
 
 use crate::remote_backend;
-use crate::shared::files_core::{file_read_core, file_write_core};
+use crate::shared::documents_core::{extract_document_core, ExtractedDocument};
+use crate::shared::file_history_core::{FileHistoryResponse, FileVersion};
+use crate::shared::files_core::{
+    effective_instructions_core, file_delete_core, file_read_core, file_write_core,
+    EffectiveInstructions,
+};
+use crate::shared::outline_core::{file_outline_core, FileOutline};
+use crate::shared::preview_core::{
+    render_markdown_core, render_notebook_core, Notebook, RenderedMarkdown,
+};
+use crate::shared::thumbnails_core::{thumbnail_core, Thumbnail};
 use crate::state::AppState;
 use self::io::TextFileResponse;
 use self::policy::{FileKind, FileScope};
 
-pub(crate) mod io;
-pub(crate) mod ops;
-pub(crate) mod policy;
+pub(crate) use fridex_core::files::{io, ops, policy};
 
 async fn file_read_impl(
     scope: FileScope,
@@ -56,7 +64,16 @@ async fn file_write_impl(
         return Ok(());
     }
 
-    file_write_core(&state.workspaces, scope, kind, workspace_id, content).await
+    file_write_core(
+        &state.workspaces,
+        &state.app_settings,
+        &state.file_history,
+        scope,
+        kind,
+        workspace_id,
+        content,
+    )
+    .await
 }
 
 #[tauri::command]
@@ -81,3 +98,193 @@ pub(crate) async fn file_write(
 ) -> Result<(), String> {
     file_write_impl(scope, kind, workspace_id, content, &*state, &app).await
 }
+
+#[tauri::command]
+pub(crate) async fn file_delete(
+    scope: FileScope,
+    kind: FileKind,
+    workspace_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<bool, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "file_delete",
+            json!({ "scope": scope, "kind": kind, "workspaceId": workspace_id }),
+        )
+        .await?;
+        return Ok(response["deleted"].as_bool().unwrap_or(false));
+    }
+
+    file_delete_core(&state.workspaces, &state.app_settings, scope, kind, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn effective_instructions(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<EffectiveInstructions, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "effective_instructions",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    effective_instructions_core(&state.workspaces, &workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn extract_document(
+    workspace_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ExtractedDocument, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "extract_document",
+            json!({ "workspaceId": workspace_id, "path": path }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    extract_document_core(&state.workspaces, &workspace_id, path).await
+}
+
+#[tauri::command]
+pub(crate) async fn render_markdown(
+    workspace_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<RenderedMarkdown, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "render_markdown",
+            json!({ "workspaceId": workspace_id, "path": path }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    render_markdown_core(&state.workspaces, &workspace_id, path).await
+}
+
+#[tauri::command]
+pub(crate) async fn render_notebook(
+    workspace_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Notebook, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "render_notebook",
+            json!({ "workspaceId": workspace_id, "path": path }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    render_notebook_core(&state.workspaces, &workspace_id, path).await
+}
+
+#[tauri::command]
+pub(crate) async fn file_outline(
+    workspace_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<FileOutline, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "file_outline",
+            json!({ "workspaceId": workspace_id, "path": path }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    file_outline_core(&state.workspaces, &workspace_id, path).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_thumbnail(
+    workspace_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Thumbnail, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "get_thumbnail",
+            json!({ "workspaceId": workspace_id, "path": path }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    thumbnail_core(
+        &state.workspaces,
+        state.thumbnails_dir.clone(),
+        &workspace_id,
+        path,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn file_history(
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<FileHistoryResponse, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "file_history", json!({ "path": path }))
+                .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    Ok(state.file_history.history(&path))
+}
+
+#[tauri::command]
+pub(crate) async fn revert_file_to(
+    version: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<FileVersion, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "revert_file_to",
+            json!({ "version": version }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let protected = state.app_settings.lock().await.protected_path_globs.clone();
+    state.file_history.revert_to(&version, &protected)
+}
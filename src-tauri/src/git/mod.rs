@@ -4,13 +4,17 @@ use std::path::{Path, PathBuf};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use git2::{BranchType, DiffOptions, Repository, Sort, Status, StatusOptions};
 use serde_json::json;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 
 use crate::shared::process_core::tokio_command;
 use crate::git_utils::{
-    checkout_branch, commit_to_entry, diff_patch_to_string, diff_stats_for_path, image_mime_type,
-    list_git_roots as scan_git_roots, parse_github_repo, resolve_git_root,
+    checkout_branch, collect_workspace_diff, commit_to_entry, diff_patch_to_string, diff_stats_for_path, github_repo_from_path, image_mime_type,
+    list_git_roots as scan_git_roots, resolve_git_root,
 };
+use crate::remote_backend;
+use crate::event_sink::TauriEventSink;
+use crate::shared::ci_core::{self, CiStatus};
+use crate::shared::i18n_core::{self, MessageKey};
 use crate::state::AppState;
 use crate::types::{
     BranchInfo, GitCommitDiff, GitFileDiff, GitFileStatus, GitHubIssue, GitHubIssuesResponse,
@@ -317,100 +321,6 @@ fn status_for_delta(status: git2::Delta) -> &'static str {
     }
 }
 
-fn build_combined_diff(diff: &git2::Diff) -> String {
-    let mut combined_diff = String::new();
-    for (index, delta) in diff.deltas().enumerate() {
-        let path = delta
-            .new_file()
-            .path()
-            .or_else(|| delta.old_file().path());
-        let Some(path) = path else {
-            continue;
-        };
-        let patch = match git2::Patch::from_diff(diff, index) {
-            Ok(patch) => patch,
-            Err(_) => continue,
-        };
-        let Some(mut patch) = patch else {
-            continue;
-        };
-        let content = match diff_patch_to_string(&mut patch) {
-            Ok(content) => content,
-            Err(_) => continue,
-        };
-        if content.trim().is_empty() {
-            continue;
-        }
-        if !combined_diff.is_empty() {
-            combined_diff.push_str("\n\n");
-        }
-        combined_diff.push_str(&format!("=== {} ===\n", path.display()));
-        combined_diff.push_str(&content);
-    }
-    combined_diff
-}
-
-fn collect_workspace_diff(repo_root: &Path) -> Result<String, String> {
-    let repo = Repository::open(repo_root).map_err(|e| e.to_string())?;
-    let head_tree = repo
-        .head()
-        .ok()
-        .and_then(|head| head.peel_to_tree().ok());
-
-    let mut options = DiffOptions::new();
-    let index = repo.index().map_err(|e| e.to_string())?;
-    let diff = match head_tree.as_ref() {
-        Some(tree) => repo
-            .diff_tree_to_index(Some(tree), Some(&index), Some(&mut options))
-            .map_err(|e| e.to_string())?,
-        None => repo
-            .diff_tree_to_index(None, Some(&index), Some(&mut options))
-            .map_err(|e| e.to_string())?,
-    };
-    let combined_diff = build_combined_diff(&diff);
-    if !combined_diff.trim().is_empty() {
-        return Ok(combined_diff);
-    }
-
-    let mut options = DiffOptions::new();
-    options
-        .include_untracked(true)
-        .recurse_untracked_dirs(true)
-        .show_untracked_content(true);
-    let diff = match head_tree.as_ref() {
-        Some(tree) => repo
-            .diff_tree_to_workdir_with_index(Some(tree), Some(&mut options))
-            .map_err(|e| e.to_string())?,
-        None => repo
-            .diff_tree_to_workdir_with_index(None, Some(&mut options))
-            .map_err(|e| e.to_string())?,
-    };
-    Ok(build_combined_diff(&diff))
-}
-
-fn github_repo_from_path(path: &Path) -> Result<String, String> {
-    let repo = Repository::open(path).map_err(|e| e.to_string())?;
-    let remotes = repo.remotes().map_err(|e| e.to_string())?;
-    let name = if remotes.iter().any(|remote| remote == Some("origin")) {
-        "origin".to_string()
-    } else {
-        remotes
-            .iter()
-            .flatten()
-            .next()
-            .unwrap_or("")
-            .to_string()
-    };
-    if name.is_empty() {
-        return Err("No git remote configured.".to_string());
-    }
-    let remote = repo.find_remote(&name).map_err(|e| e.to_string())?;
-    let remote_url = remote
-        .url()
-        .ok_or("Remote has no URL configured.")?;
-    parse_github_repo(remote_url).ok_or("Remote is not a GitHub repository.".to_string())
-}
-
 fn parse_pr_diff(diff: &str) -> Vec<GitHubPullRequestDiff> {
     let mut entries = Vec::new();
     let mut current_lines: Vec<&str> = Vec::new();
@@ -510,7 +420,7 @@ pub(crate) async fn get_git_status(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     drop(workspaces);
 
@@ -644,7 +554,7 @@ pub(crate) async fn stage_git_file(
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
     };
 
     let repo_root = resolve_git_root(&entry)?;
@@ -666,7 +576,7 @@ pub(crate) async fn stage_git_all(
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
     };
 
     let repo_root = resolve_git_root(&entry)?;
@@ -684,7 +594,7 @@ pub(crate) async fn unstage_git_file(
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
     };
 
     let repo_root = resolve_git_root(&entry)?;
@@ -705,7 +615,7 @@ pub(crate) async fn revert_git_file(
         workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
     };
 
     let repo_root = resolve_git_root(&entry)?;
@@ -732,7 +642,7 @@ pub(crate) async fn revert_git_all(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?;
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
     let repo_root = resolve_git_root(entry)?;
     run_git_command(&repo_root, &["restore", "--staged", "--worktree", "--", "."]).await?;
     run_git_command(&repo_root, &["clean", "-f", "-d"]).await
@@ -747,7 +657,7 @@ pub(crate) async fn commit_git(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -762,7 +672,7 @@ pub(crate) async fn push_git(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -777,7 +687,7 @@ pub(crate) async fn pull_git(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -792,7 +702,7 @@ pub(crate) async fn fetch_git(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -807,7 +717,7 @@ pub(crate) async fn sync_git(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -825,7 +735,7 @@ pub(crate) async fn list_git_roots(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let root = PathBuf::from(&entry.path);
@@ -841,12 +751,12 @@ pub(crate) async fn get_workspace_diff(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     drop(workspaces);
 
     let repo_root = resolve_git_root(&entry)?;
-    collect_workspace_diff(&repo_root)
+    collect_workspace_diff(&repo_root, None)
 }
 
 #[tauri::command]
@@ -857,7 +767,7 @@ pub(crate) async fn get_git_diffs(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     drop(workspaces);
 
@@ -1010,7 +920,7 @@ pub(crate) async fn get_git_log(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     drop(workspaces);
 
@@ -1119,7 +1029,7 @@ pub(crate) async fn get_git_commit_diff(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     drop(workspaces);
 
@@ -1257,7 +1167,7 @@ pub(crate) async fn get_git_remote(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -1280,6 +1190,48 @@ pub(crate) async fn get_git_remote(
     Ok(remote.url().map(|url| url.to_string()))
 }
 
+#[tauri::command]
+pub(crate) async fn ci_status(
+    workspace_id: String,
+    branch: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<CiStatus, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "ci_status",
+            json!({ "workspaceId": workspace_id, "branch": branch }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    ci_core::ci_status_core(&state.workspaces, &workspace_id, &branch, |_| Ok(())).await
+}
+
+/// Polls CI for pushed worktree branches; in remote mode the daemon polls instead.
+pub(crate) fn spawn_ci_poller(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let event_sink = TauriEventSink::new(app.clone());
+        let mut poller = ci_core::CiPoller::default();
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            ci_core::CI_POLL_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            let state = app.state::<AppState>();
+            if remote_backend::is_remote_mode(&state).await {
+                continue;
+            }
+            poller
+                .poll_once(&state.workspaces, &event_sink, |_, _| Ok(()))
+                .await;
+        }
+    });
+}
+
 #[tauri::command]
 pub(crate) async fn get_github_issues(
     workspace_id: String,
@@ -1288,7 +1240,7 @@ pub(crate) async fn get_github_issues(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -1358,7 +1310,7 @@ pub(crate) async fn get_github_pull_requests(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -1434,7 +1386,7 @@ pub(crate) async fn get_github_pull_request_diff(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -1482,7 +1434,7 @@ pub(crate) async fn get_github_pull_request_comments(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
@@ -1527,7 +1479,7 @@ pub(crate) async fn list_git_branches(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     let repo_root = resolve_git_root(&entry)?;
     let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
@@ -1562,7 +1514,7 @@ pub(crate) async fn checkout_git_branch(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     let repo_root = resolve_git_root(&entry)?;
     let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
@@ -1578,7 +1530,7 @@ pub(crate) async fn create_git_branch(
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
         .get(&workspace_id)
-        .ok_or("workspace not found")?
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?
         .clone();
     let repo_root = resolve_git_root(&entry)?;
     let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
@@ -1614,7 +1566,7 @@ mod tests {
         index.add_path(Path::new("staged.txt")).expect("add path");
         index.write().expect("write index");
 
-        let diff = collect_workspace_diff(&root).expect("collect diff");
+        let diff = collect_workspace_diff(&root, None).expect("collect diff");
         assert!(diff.contains("staged.txt"));
         assert!(diff.contains("staged"));
     }
@@ -1625,7 +1577,7 @@ mod tests {
         let file_path = root.join("unstaged.txt");
         fs::write(&file_path, "unstaged\n").expect("write unstaged file");
 
-        let diff = collect_workspace_diff(&root).expect("collect diff");
+        let diff = collect_workspace_diff(&root, None).expect("collect diff");
         assert!(diff.contains("unstaged.txt"));
         assert!(diff.contains("unstaged"));
     }
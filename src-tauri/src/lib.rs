@@ -1,30 +1,28 @@
+use std::io::IsTerminal;
+
 use tauri::Manager;
 #[cfg(target_os = "macos")]
 use tauri::{RunEvent, WindowEvent};
 
-mod backend;
+mod backup;
 mod codex;
 mod files;
 mod dictation;
 mod event_sink;
 mod git;
-mod git_utils;
 mod local_usage;
 mod menu;
 mod notifications;
 mod prompts;
 mod remote_backend;
-mod rules;
 mod settings;
 mod state;
-mod storage;
-mod shared;
 mod terminal;
-mod types;
-mod utils;
 mod window;
 mod workspaces;
 
+use fridex_core::{backend, git_utils, shared, storage, types, utils};
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     #[cfg(target_os = "linux")]
@@ -35,6 +33,13 @@ pub fn run() {
         }
     }
 
+    // Core reports best-effort failures (file history, snapshots, ...) through `tracing`.
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .try_init();
+
     let builder = tauri::Builder::default()
         .enable_macos_default_menu(false)
         .manage(menu::MenuItemRegistry::<tauri::Wry>::default())
@@ -53,6 +58,9 @@ pub fn run() {
         .setup(|app| {
             let state = state::AppState::load(&app.handle());
             app.manage(state);
+            git::spawn_ci_poller(app.handle().clone());
+            codex::spawn_mcp_health_prober(app.handle().clone());
+            codex::spawn_auth_watcher(app.handle().clone());
             #[cfg(desktop)]
             {
                 app.handle()
@@ -73,12 +81,27 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             settings::get_app_settings,
             settings::update_app_settings,
+            settings::merge_revisions,
             settings::get_codex_config_path,
+            backup::backup_data,
+            backup::restore_data,
+            backup::export_safety_policy,
+            backup::import_safety_policy,
             files::file_read,
             files::file_write,
+            files::file_delete,
+            files::effective_instructions,
+            files::extract_document,
+            files::render_markdown,
+            files::render_notebook,
+            files::file_outline,
+            files::get_thumbnail,
+            files::file_history,
+            files::revert_file_to,
             codex::get_config_model,
             menu::menu_set_accelerators,
             codex::codex_doctor,
+            codex::get_session_log,
             workspaces::list_workspaces,
             workspaces::is_workspace_path_dir,
             workspaces::add_workspace,
@@ -88,29 +111,54 @@ pub fn run() {
             workspaces::worktree_setup_mark_ran,
             workspaces::remove_workspace,
             workspaces::remove_worktree,
+            workspaces::reconcile_worktrees,
+            workspaces::stale_branches_report,
+            workspaces::delete_stale_branches,
             workspaces::rename_worktree,
             workspaces::rename_worktree_upstream,
             workspaces::apply_worktree_changes,
             workspaces::update_workspace_settings,
             workspaces::update_workspace_codex_bin,
+            workspaces::relocate_workspace,
             codex::start_thread,
             codex::send_user_message,
             codex::turn_interrupt,
+            codex::restore_snapshot,
             codex::start_review,
             codex::respond_to_server_request,
             codex::remember_approval_rule,
+            codex::validate_rules,
+            codex::format_rules,
             codex::get_commit_message_prompt,
             codex::generate_commit_message,
             codex::generate_run_metadata,
             codex::resume_thread,
             codex::fork_thread,
+            codex::thread_graph,
+            codex::list_slash_commands,
+            codex::save_draft,
+            codex::get_draft,
+            codex::time_tracking_ping,
+            codex::time_tracking_summary,
+            codex::list_pinned_context,
+            codex::pin_context,
+            codex::unpin_context,
+            codex::list_collaboration_presets,
+            codex::save_collaboration_preset,
+            codex::delete_collaboration_preset,
+            codex::list_account_profiles,
+            codex::save_account_profile,
+            codex::delete_account_profile,
             codex::list_threads,
             codex::list_mcp_server_status,
+            codex::mcp_server_health,
+            codex::mcp_server_oauth_login,
             codex::archive_thread,
             codex::compact_thread,
             codex::set_thread_name,
             codex::collaboration_mode_list,
             workspaces::connect_workspace,
+            workspaces::preview_codex_command,
             git::get_git_status,
             git::list_git_roots,
             git::get_git_diffs,
@@ -127,12 +175,45 @@ pub fn run() {
             git::pull_git,
             git::fetch_git,
             git::sync_git,
+            git::ci_status,
             git::get_github_issues,
             git::get_github_pull_requests,
             git::get_github_pull_request_diff,
             git::get_github_pull_request_comments,
             workspaces::list_workspace_files,
             workspaces::read_workspace_file,
+            workspaces::get_capabilities,
+            workspaces::begin_write,
+            workspaces::write_chunk,
+            workspaces::commit_write,
+            workspaces::abort_write,
+            workspaces::resolve_mentions,
+            workspaces::plan_context,
+            workspaces::indexed_search,
+            workspaces::workspace_symbols,
+            workspaces::find_definition,
+            workspaces::get_sparse_checkout,
+            workspaces::set_sparse_checkout,
+            workspaces::workspace_stats,
+            workspaces::disk_usage,
+            workspaces::analyze_workspace,
+            workspaces::list_packages,
+            workspaces::audit_dependencies,
+            workspaces::run_build,
+            workspaces::list_problems,
+            workspaces::search_all_workspaces,
+            remote_backend::list_clients,
+            remote_backend::kick_client,
+            remote_backend::list_pending_approvals,
+            remote_backend::job_start,
+            remote_backend::auto_fix_start,
+            remote_backend::run_retention,
+            remote_backend::fanout_run,
+            remote_backend::fanout_status,
+            remote_backend::compare_fanout,
+            remote_backend::fanout_pick,
+            remote_backend::job_status,
+            remote_backend::job_cancel,
             workspaces::open_workspace_in,
             workspaces::get_open_app_icon,
             git::list_git_branches,
@@ -144,6 +225,8 @@ pub fn run() {
             codex::codex_login,
             codex::codex_login_cancel,
             codex::skills_list,
+            codex::skills_config,
+            codex::skills_set_enabled,
             codex::apps_list,
             prompts::prompts_list,
             prompts::prompts_create,
@@ -6,6 +6,7 @@ use tokio::task;
 use tauri::State;
 
 use crate::codex::home::{resolve_default_codex_home, resolve_workspace_codex_home};
+use crate::shared::i18n_core::{self, MessageKey};
 use crate::state::AppState;
 use crate::types::WorkspaceEntry;
 
@@ -46,7 +47,7 @@ fn require_workspace_entry(
     workspaces
         .get(workspace_id)
         .cloned()
-        .ok_or_else(|| "workspace not found".to_string())
+        .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))
 }
 
 fn app_data_dir(state: &State<'_, AppState>) -> Result<PathBuf, String> {
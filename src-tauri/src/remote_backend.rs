@@ -1,20 +1,30 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use flate2::read::GzDecoder;
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::io::Read;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
-use tauri::{AppHandle, Emitter};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
 use tokio::sync::{mpsc, oneshot, Mutex};
 
+use crate::backend::events::EVENT_SCHEMA_VERSION;
+use crate::shared::tls_core;
 use crate::state::AppState;
 use crate::types::BackendMode;
 
 const DEFAULT_REMOTE_HOST: &str = "127.0.0.1:4732";
 const DISCONNECTED_MESSAGE: &str = "remote backend disconnected";
+/// Encodings offered to the daemon for large responses and events.
+const ACCEPTED_COMPRESSION: &[&str] = &["gzip"];
 
 type PendingMap = HashMap<u64, oneshot::Sender<Result<Value, String>>>;
+type RemoteReader = Box<dyn AsyncRead + Send + Unpin>;
+type RemoteWriter = Box<dyn AsyncWrite + Send + Unpin>;
 
 pub(crate) fn normalize_path_for_remote(path: String) -> String {
     let trimmed = path.trim();
@@ -29,6 +39,29 @@ pub(crate) fn normalize_path_for_remote(path: String) -> String {
     path
 }
 
+/// Name reported to the daemon so other devices can tell connections apart.
+fn local_device_name() -> Option<String> {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // SAFETY: the buffer outlives the call and its length is passed alongside it.
+        let result =
+            unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+        if result == 0 {
+            let end = buffer.iter().position(|byte| *byte == 0).unwrap_or(buffer.len());
+            let name = String::from_utf8_lossy(&buffer[..end]).trim().to_string();
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+    }
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
 fn normalize_wsl_unc_path(path: &str) -> Option<String> {
     let lower = path.to_ascii_lowercase();
     let (prefix_len, raw) = if lower.starts_with("\\\\wsl$\\") {
@@ -73,6 +106,7 @@ impl RemoteBackend {
         self.inner.pending.lock().await.insert(id, tx);
 
         let request = json!({
+            "jsonrpc": "2.0",
             "id": id,
             "method": method,
             "params": params,
@@ -109,6 +143,204 @@ pub(crate) async fn call_remote(
     }
 }
 
+/// Devices attached to the remote daemon; empty when running locally.
+#[tauri::command]
+pub(crate) async fn list_clients(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Ok(json!([]));
+    }
+    call_remote(&*state, app, "list_clients", json!({})).await
+}
+
+#[tauri::command]
+pub(crate) async fn kick_client(
+    client_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Client presence is only available in remote mode".to_string());
+    }
+    call_remote(&*state, app, "kick_client", json!({ "clientId": client_id })).await
+}
+
+#[tauri::command]
+pub(crate) async fn list_pending_approvals(
+    workspace_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Ok(json!([]));
+    }
+    call_remote(
+        &*state,
+        app,
+        "list_pending_approvals",
+        json!({ "workspaceId": workspace_id }),
+    )
+    .await
+}
+
+/// Background jobs live in the daemon; there is no local job manager.
+#[tauri::command]
+pub(crate) async fn job_start(
+    kind: String,
+    params: Option<Value>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Background jobs are only available in remote mode".to_string());
+    }
+    call_remote(
+        &*state,
+        app,
+        "job_start",
+        json!({ "kind": kind, "params": params }),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn auto_fix_start(
+    workspace_id: String,
+    goal: Option<String>,
+    test_command: Option<Vec<String>>,
+    max_iterations: Option<u32>,
+    max_tokens: Option<u64>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Auto-fix is only available in remote mode".to_string());
+    }
+    call_remote(
+        &*state,
+        app,
+        "auto_fix_start",
+        json!({
+            "workspaceId": workspace_id,
+            "goal": goal,
+            "testCommand": test_command,
+            "maxIterations": max_iterations,
+            "maxTokens": max_tokens,
+        }),
+    )
+    .await
+}
+
+/// Retention runs on the daemon, which also applies it hourly.
+#[tauri::command]
+pub(crate) async fn run_retention(
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Thread retention is only available in remote mode".to_string());
+    }
+    call_remote(&*state, app, "run_retention", json!({ "dryRun": dry_run })).await
+}
+
+/// Fan-out runs are tracked by the daemon from its thread events.
+#[tauri::command]
+pub(crate) async fn fanout_run(
+    parent_id: String,
+    branches: Vec<String>,
+    prompt: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Fan-out runs are only available in remote mode".to_string());
+    }
+    call_remote(
+        &*state,
+        app,
+        "fanout_run",
+        json!({ "parentId": parent_id, "branches": branches, "prompt": prompt }),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn fanout_status(
+    run_id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Ok(if run_id.is_some() { Value::Null } else { json!([]) });
+    }
+    call_remote(&*state, app, "fanout_status", json!({ "runId": run_id })).await
+}
+
+#[tauri::command]
+pub(crate) async fn compare_fanout(
+    run_id: String,
+    run_checks: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Fan-out runs are only available in remote mode".to_string());
+    }
+    call_remote(
+        &*state,
+        app,
+        "compare_fanout",
+        json!({ "runId": run_id, "runChecks": run_checks }),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn fanout_pick(
+    run_id: String,
+    branch: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Fan-out runs are only available in remote mode".to_string());
+    }
+    call_remote(
+        &*state,
+        app,
+        "fanout_pick",
+        json!({ "runId": run_id, "branch": branch }),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn job_status(
+    id: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Ok(if id.is_some() { Value::Null } else { json!([]) });
+    }
+    call_remote(&*state, app, "job_status", json!({ "id": id })).await
+}
+
+#[tauri::command]
+pub(crate) async fn job_cancel(
+    id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if !is_remote_mode(&*state).await {
+        return Err("Background jobs are only available in remote mode".to_string());
+    }
+    call_remote(&*state, app, "job_cancel", json!({ "id": id })).await
+}
+
 async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<RemoteBackend, String> {
     {
         let guard = state.remote_backend.lock().await;
@@ -117,11 +349,16 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
         }
     }
 
-    let (host, token) = {
+    let (host, token, tls, tls_ca) = {
         let settings = state.app_settings.lock().await;
         (
             settings.remote_backend_host.clone(),
             settings.remote_backend_token.clone(),
+            settings.remote_backend_tls,
+            settings
+                .remote_backend_tls_ca
+                .clone()
+                .filter(|path| !path.trim().is_empty()),
         )
     };
 
@@ -134,7 +371,18 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
     let stream = TcpStream::connect(resolved_host.clone())
         .await
         .map_err(|err| format!("Failed to connect to remote backend at {resolved_host}: {err}"))?;
-    let (reader, mut writer) = stream.into_split();
+    let (reader, mut writer): (RemoteReader, RemoteWriter) = if tls {
+        let config = tls_core::client_config(tls_ca.as_deref().map(std::path::Path::new))?;
+        let stream = TlsConnector::from(config)
+            .connect(tls_core::server_name(&resolved_host)?, stream)
+            .await
+            .map_err(|err| format!("TLS handshake with {resolved_host} failed: {err}"))?;
+        let (reader, writer) = tokio::io::split(stream);
+        (Box::new(reader), Box::new(writer))
+    } else {
+        let (reader, writer) = stream.into_split();
+        (Box::new(reader), Box::new(writer))
+    };
 
     let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
     let pending = Arc::new(Mutex::new(PendingMap::new()));
@@ -149,6 +397,7 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
         while let Some(message) = out_rx.recv().await {
             if writer.write_all(message.as_bytes()).await.is_err()
                 || writer.write_all(b"\n").await.is_err()
+                || writer.flush().await.is_err()
             {
                 connected_for_writer.store(false, Ordering::SeqCst);
                 let mut pending = pending_for_writer.lock().await;
@@ -161,12 +410,14 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
     });
 
     let app_for_reader = app.clone();
+    let event_seq = Arc::clone(&state.remote_event_seq);
     let read_task = tokio::spawn(async move {
         read_loop(
             app_for_reader,
             reader,
             pending_for_reader,
             connected_for_reader,
+            event_seq,
         )
         .await;
     });
@@ -180,11 +431,66 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
         }),
     };
 
-    if let Some(token) = token {
-        client
-            .call("auth", json!({ "token": token }))
+    // A ticket from the previous connection restores its event stream and subscriptions;
+    // if the daemon rejects it (expired, restarted), fall back to a fresh handshake.
+    let ticket = state.remote_resume_ticket.lock().await.take();
+    let resumed = match ticket {
+        Some(ticket) => client
+            .call("resume", json!({ "ticket": ticket }))
             .await
-            .map(|_| ())?;
+            .ok(),
+        None => None,
+    };
+    let resumed_ok = resumed.is_some();
+    let handshake = match resumed {
+        Some(result) => Some(result),
+        None => {
+            let device_name = local_device_name();
+            let client_version = app.package_info().version.to_string();
+            if let Some(token) = token {
+                Some(
+                    client
+                        .call(
+                            "auth",
+                            json!({
+                                "token": token,
+                                "deviceName": device_name,
+                                "clientVersion": client_version,
+                                "compression": ACCEPTED_COMPRESSION,
+                                "eventSchema": EVENT_SCHEMA_VERSION,
+                            }),
+                        )
+                        .await?,
+                )
+            } else {
+                // Older daemons don't know this method; presence is best-effort.
+                client
+                    .call(
+                        "identify_client",
+                        json!({
+                            "deviceName": device_name,
+                            "clientVersion": client_version,
+                            "compression": ACCEPTED_COMPRESSION,
+                            "eventSchema": EVENT_SCHEMA_VERSION,
+                        }),
+                    )
+                    .await
+                    .ok()
+            }
+        }
+    };
+    *state.remote_resume_ticket.lock().await = handshake
+        .as_ref()
+        .and_then(|result| result.get("resumeTicket"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    // Without a resumed stream, ask for what was emitted since the last event we saw.
+    // Older daemons don't know the method.
+    let last_seq = state.remote_event_seq.load(Ordering::SeqCst);
+    if !resumed_ok && last_seq > 0 {
+        let _ = client
+            .call("subscribe_since", json!({ "seq": last_seq }))
+            .await;
     }
 
     {
@@ -197,11 +503,27 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
     Ok(client)
 }
 
+/// Unwraps a `{"compressed":"gzip","data":"<base64>"}` line; other messages pass through.
+fn decompress_message(message: Value) -> Option<Value> {
+    if message.get("compressed").and_then(Value::as_str) != Some("gzip") {
+        return Some(message);
+    }
+    let data = STANDARD
+        .decode(message.get("data").and_then(Value::as_str)?)
+        .ok()?;
+    let mut decoded = String::new();
+    GzDecoder::new(data.as_slice())
+        .read_to_string(&mut decoded)
+        .ok()?;
+    serde_json::from_str(&decoded).ok()
+}
+
 async fn read_loop(
     app: AppHandle,
-    reader: tokio::net::tcp::OwnedReadHalf,
+    reader: RemoteReader,
     pending: Arc<Mutex<PendingMap>>,
     connected: Arc<AtomicBool>,
+    event_seq: Arc<AtomicU64>,
 ) {
     let mut lines = BufReader::new(reader).lines();
 
@@ -215,6 +537,9 @@ async fn read_loop(
             Ok(value) => value,
             Err(_) => continue,
         };
+        let Some(message) = decompress_message(message) else {
+            continue;
+        };
 
         if let Some(id) = message.get("id").and_then(|value| value.as_u64()) {
             let sender = pending.lock().await.remove(&id);
@@ -244,6 +569,9 @@ async fn read_loop(
             continue;
         }
         let params = message.get("params").cloned().unwrap_or(Value::Null);
+        if let Some(seq) = params.get("eventSeq").and_then(Value::as_u64) {
+            event_seq.store(seq, Ordering::SeqCst);
+        }
         match method {
             "app-server-event" => {
                 let _ = app.emit("app-server-event", params);
@@ -254,6 +582,24 @@ async fn read_loop(
             "terminal-exit" => {
                 let _ = app.emit("terminal-exit", params);
             }
+            "client-presence" => {
+                let _ = app.emit("client-presence", params);
+            }
+            "client-kicked" => {
+                let _ = app.emit("client-kicked", params);
+            }
+            "job-update" => {
+                let _ = app.emit("job-update", params);
+            }
+            "workspace-changed" => {
+                let _ = app.emit("workspace-changed", params);
+            }
+            "file-conflict" => {
+                let _ = app.emit("file-conflict", params);
+            }
+            "editor-presence" => {
+                let _ = app.emit("editor-presence", params);
+            }
             _ => {}
         }
     }
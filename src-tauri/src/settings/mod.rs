@@ -1,6 +1,8 @@
+use serde_json::Value;
 use tauri::{State, Window};
 
 use crate::state::AppState;
+use crate::shared::revisions_core::{merge_values, MergeResult};
 use crate::shared::settings_core::{
     get_app_settings_core, get_codex_config_path_core, update_app_settings_core,
 };
@@ -20,15 +22,31 @@ pub(crate) async fn get_app_settings(
 #[tauri::command]
 pub(crate) async fn update_app_settings(
     settings: AppSettings,
+    expected_revision: Option<u64>,
     state: State<'_, AppState>,
     window: Window,
 ) -> Result<AppSettings, String> {
-    let updated =
-        update_app_settings_core(settings, &state.app_settings, &state.settings_path).await?;
+    let updated = update_app_settings_core(
+        settings,
+        expected_revision,
+        &state.app_settings,
+        &state.settings_path,
+    )
+    .await?;
     let _ = window::apply_window_appearance(&window, updated.theme.as_str());
     Ok(updated)
 }
 
+/// Pure three-way merge for resolving a revision conflict; runs locally even in remote mode.
+#[tauri::command]
+pub(crate) async fn merge_revisions(
+    base: Value,
+    current: Value,
+    proposed: Value,
+) -> Result<MergeResult, String> {
+    Ok(merge_values(&base, &current, &proposed))
+}
+
 #[tauri::command]
 pub(crate) async fn get_codex_config_path() -> Result<String, String> {
     get_codex_config_path_core()
@@ -1,498 +0,0 @@
-use serde_json::{json, Map, Value};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::pin::Pin;
-use std::sync::Arc;
-use std::time::Duration;
-
-use tokio::sync::{Mutex, oneshot};
-use tokio::sync::oneshot::error::TryRecvError;
-use tokio::time::timeout;
-use tokio::time::Instant;
-
-use crate::backend::app_server::WorkspaceSession;
-use crate::codex::config as codex_config;
-use crate::codex::home::{resolve_default_codex_home, resolve_workspace_codex_home};
-use crate::rules;
-use crate::shared::account::{build_account_response, read_auth_account};
-use crate::types::WorkspaceEntry;
-
-const LOGIN_START_TIMEOUT: Duration = Duration::from_secs(30);
-
-pub(crate) enum CodexLoginCancelState {
-    PendingStart(oneshot::Sender<()>),
-    LoginId(String),
-}
-
-async fn get_session_clone(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: &str,
-) -> Result<Arc<WorkspaceSession>, String> {
-    let sessions = sessions.lock().await;
-    sessions
-        .get(workspace_id)
-        .cloned()
-        .ok_or_else(|| "workspace not connected".to_string())
-}
-
-async fn resolve_workspace_and_parent(
-    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-    workspace_id: &str,
-) -> Result<(WorkspaceEntry, Option<WorkspaceEntry>), String> {
-    let workspaces = workspaces.lock().await;
-    let entry = workspaces
-        .get(workspace_id)
-        .cloned()
-        .ok_or_else(|| "workspace not found".to_string())?;
-    let parent_entry = entry
-        .parent_id
-        .as_ref()
-        .and_then(|parent_id| workspaces.get(parent_id))
-        .cloned();
-    Ok((entry, parent_entry))
-}
-
-async fn resolve_codex_home_for_workspace_core(
-    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-    workspace_id: &str,
-) -> Result<PathBuf, String> {
-    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, workspace_id).await?;
-    resolve_workspace_codex_home(&entry, parent_entry.as_ref())
-        .or_else(resolve_default_codex_home)
-        .ok_or_else(|| "Unable to resolve CODEX_HOME".to_string())
-}
-
-pub(crate) async fn start_thread_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({
-        "cwd": session.entry.path,
-        "approvalPolicy": "on-request"
-    });
-    session.send_request("thread/start", params).await
-}
-
-pub(crate) async fn resume_thread_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "threadId": thread_id });
-    session.send_request("thread/resume", params).await
-}
-
-pub(crate) async fn fork_thread_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "threadId": thread_id });
-    session.send_request("thread/fork", params).await
-}
-
-pub(crate) async fn list_threads_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    cursor: Option<String>,
-    limit: Option<u32>,
-    sort_key: Option<String>,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "cursor": cursor, "limit": limit, "sortKey": sort_key });
-    session.send_request("thread/list", params).await
-}
-
-pub(crate) async fn list_mcp_server_status_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    cursor: Option<String>,
-    limit: Option<u32>,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "cursor": cursor, "limit": limit });
-    session.send_request("mcpServerStatus/list", params).await
-}
-
-pub(crate) async fn archive_thread_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "threadId": thread_id });
-    session.send_request("thread/archive", params).await
-}
-
-pub(crate) async fn compact_thread_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "threadId": thread_id });
-    session.send_request("thread/compact/start", params).await
-}
-
-pub(crate) async fn set_thread_name_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-    name: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "threadId": thread_id, "name": name });
-    session.send_request("thread/name/set", params).await
-}
-
-pub(crate) async fn send_user_message_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-    text: String,
-    model: Option<String>,
-    effort: Option<String>,
-    access_mode: Option<String>,
-    images: Option<Vec<String>>,
-    collaboration_mode: Option<Value>,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
-    let sandbox_policy = match access_mode.as_str() {
-        "full-access" => json!({ "type": "dangerFullAccess" }),
-        "read-only" => json!({ "type": "readOnly" }),
-        _ => json!({
-            "type": "workspaceWrite",
-            "writableRoots": [session.entry.path],
-            "networkAccess": true
-        }),
-    };
-
-    let approval_policy = if access_mode == "full-access" {
-        "never"
-    } else {
-        "on-request"
-    };
-
-    let trimmed_text = text.trim();
-    let mut input: Vec<Value> = Vec::new();
-    if !trimmed_text.is_empty() {
-        input.push(json!({ "type": "text", "text": trimmed_text }));
-    }
-    if let Some(paths) = images {
-        for path in paths {
-            let trimmed = path.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-            if trimmed.starts_with("data:")
-                || trimmed.starts_with("http://")
-                || trimmed.starts_with("https://")
-            {
-                input.push(json!({ "type": "image", "url": trimmed }));
-            } else {
-                input.push(json!({ "type": "localImage", "path": trimmed }));
-            }
-        }
-    }
-    if input.is_empty() {
-        return Err("empty user message".to_string());
-    }
-
-    let mut params = Map::new();
-    params.insert("threadId".to_string(), json!(thread_id));
-    params.insert("input".to_string(), json!(input));
-    params.insert("cwd".to_string(), json!(session.entry.path));
-    params.insert("approvalPolicy".to_string(), json!(approval_policy));
-    params.insert("sandboxPolicy".to_string(), json!(sandbox_policy));
-    params.insert("model".to_string(), json!(model));
-    params.insert("effort".to_string(), json!(effort));
-    if let Some(mode) = collaboration_mode {
-        if !mode.is_null() {
-            params.insert("collaborationMode".to_string(), mode);
-        }
-    }
-    session
-        .send_request("turn/start", Value::Object(params))
-        .await
-}
-
-pub(crate) async fn collaboration_mode_list_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    session
-        .send_request("collaborationMode/list", json!({}))
-        .await
-}
-
-pub(crate) async fn turn_interrupt_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-    turn_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "threadId": thread_id, "turnId": turn_id });
-    session.send_request("turn/interrupt", params).await
-}
-
-pub(crate) async fn start_review_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    thread_id: String,
-    target: Value,
-    delivery: Option<String>,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let mut params = Map::new();
-    params.insert("threadId".to_string(), json!(thread_id));
-    params.insert("target".to_string(), target);
-    if let Some(delivery) = delivery {
-        params.insert("delivery".to_string(), json!(delivery));
-    }
-    session
-        .send_request("review/start", Value::Object(params))
-        .await
-}
-
-pub(crate) async fn model_list_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    session.send_request("model/list", json!({})).await
-}
-
-pub(crate) async fn account_rate_limits_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    session
-        .send_request("account/rateLimits/read", Value::Null)
-        .await
-}
-
-pub(crate) async fn account_read_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let session = {
-        let sessions = sessions.lock().await;
-        sessions.get(&workspace_id).cloned()
-    };
-    let response = if let Some(session) = session {
-        session.send_request("account/read", Value::Null).await.ok()
-    } else {
-        None
-    };
-
-    let (entry, parent_entry) = resolve_workspace_and_parent(workspaces, &workspace_id).await?;
-    let codex_home = resolve_workspace_codex_home(&entry, parent_entry.as_ref())
-        .or_else(resolve_default_codex_home);
-    let fallback = read_auth_account(codex_home);
-
-    Ok(build_account_response(response, fallback))
-}
-
-pub(crate) async fn codex_login_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    codex_login_cancels: &Mutex<HashMap<String, CodexLoginCancelState>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
-    {
-        let mut cancels = codex_login_cancels.lock().await;
-        if let Some(existing) = cancels.remove(&workspace_id) {
-            match existing {
-                CodexLoginCancelState::PendingStart(tx) => {
-                    let _ = tx.send(());
-                }
-                CodexLoginCancelState::LoginId(_) => {}
-            }
-        }
-        cancels.insert(workspace_id.clone(), CodexLoginCancelState::PendingStart(cancel_tx));
-    }
-
-    let start = Instant::now();
-    let mut cancel_rx = cancel_rx;
-    let mut login_request: Pin<Box<_>> = Box::pin(
-        session.send_request("account/login/start", json!({ "type": "chatgpt" })),
-    );
-
-    let response = loop {
-        match cancel_rx.try_recv() {
-            Ok(_) => {
-                let mut cancels = codex_login_cancels.lock().await;
-                cancels.remove(&workspace_id);
-                return Err("Codex login canceled.".to_string());
-            }
-            Err(TryRecvError::Closed) => {
-                let mut cancels = codex_login_cancels.lock().await;
-                cancels.remove(&workspace_id);
-                return Err("Codex login canceled.".to_string());
-            }
-            Err(TryRecvError::Empty) => {}
-        }
-
-        let elapsed = start.elapsed();
-        if elapsed >= LOGIN_START_TIMEOUT {
-            let mut cancels = codex_login_cancels.lock().await;
-            cancels.remove(&workspace_id);
-            return Err("Codex login start timed out.".to_string());
-        }
-
-        let tick = Duration::from_millis(150);
-        let remaining = LOGIN_START_TIMEOUT.saturating_sub(elapsed);
-        let wait_for = remaining.min(tick);
-
-        match timeout(wait_for, &mut login_request).await {
-            Ok(result) => break result?,
-            Err(_elapsed) => continue,
-        }
-    };
-
-    let payload = response.get("result").unwrap_or(&response);
-    let login_id = payload
-        .get("loginId")
-        .or_else(|| payload.get("login_id"))
-        .and_then(Value::as_str)
-        .map(str::to_string)
-        .ok_or_else(|| "missing login id in account/login/start response".to_string())?;
-    let auth_url = payload
-        .get("authUrl")
-        .or_else(|| payload.get("auth_url"))
-        .and_then(Value::as_str)
-        .map(str::to_string)
-        .ok_or_else(|| "missing auth url in account/login/start response".to_string())?;
-
-    {
-        let mut cancels = codex_login_cancels.lock().await;
-        cancels.insert(workspace_id, CodexLoginCancelState::LoginId(login_id.clone()));
-    }
-
-    Ok(json!({
-        "loginId": login_id,
-        "authUrl": auth_url,
-        "raw": response,
-    }))
-}
-
-pub(crate) async fn codex_login_cancel_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    codex_login_cancels: &Mutex<HashMap<String, CodexLoginCancelState>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let cancel_state = {
-        let mut cancels = codex_login_cancels.lock().await;
-        cancels.remove(&workspace_id)
-    };
-
-    let Some(cancel_state) = cancel_state else {
-        return Ok(json!({ "canceled": false }));
-    };
-
-    match cancel_state {
-        CodexLoginCancelState::PendingStart(cancel_tx) => {
-            let _ = cancel_tx.send(());
-            return Ok(json!({
-                "canceled": true,
-                "status": "canceled",
-            }));
-        }
-        CodexLoginCancelState::LoginId(login_id) => {
-            let session = get_session_clone(sessions, &workspace_id).await?;
-            let response = session
-                .send_request(
-                    "account/login/cancel",
-                    json!({
-                        "loginId": login_id,
-                    }),
-                )
-                .await?;
-
-            let payload = response.get("result").unwrap_or(&response);
-            let status = payload
-                .get("status")
-                .and_then(Value::as_str)
-                .unwrap_or_default();
-            let canceled = status.eq_ignore_ascii_case("canceled");
-
-            Ok(json!({
-                "canceled": canceled,
-                "status": status,
-                "raw": response,
-            }))
-        }
-    }
-}
-
-pub(crate) async fn skills_list_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "cwd": session.entry.path });
-    session.send_request("skills/list", params).await
-}
-
-pub(crate) async fn apps_list_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    cursor: Option<String>,
-    limit: Option<u32>,
-) -> Result<Value, String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    let params = json!({ "cursor": cursor, "limit": limit });
-    session.send_request("app/list", params).await
-}
-
-pub(crate) async fn respond_to_server_request_core(
-    sessions: &Mutex<HashMap<String, Arc<WorkspaceSession>>>,
-    workspace_id: String,
-    request_id: Value,
-    result: Value,
-) -> Result<(), String> {
-    let session = get_session_clone(sessions, &workspace_id).await?;
-    session.send_response(request_id, result).await
-}
-
-pub(crate) async fn remember_approval_rule_core(
-    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-    workspace_id: String,
-    command: Vec<String>,
-) -> Result<Value, String> {
-    let command = command
-        .into_iter()
-        .map(|item| item.trim().to_string())
-        .filter(|item| !item.is_empty())
-        .collect::<Vec<_>>();
-    if command.is_empty() {
-        return Err("empty command".to_string());
-    }
-
-    let codex_home = resolve_codex_home_for_workspace_core(workspaces, &workspace_id).await?;
-    let rules_path = rules::default_rules_path(&codex_home);
-    rules::append_prefix_rule(&rules_path, &command)?;
-
-    Ok(json!({
-        "ok": true,
-        "rulesPath": rules_path,
-    }))
-}
-
-pub(crate) async fn get_config_model_core(
-    workspaces: &Mutex<HashMap<String, WorkspaceEntry>>,
-    workspace_id: String,
-) -> Result<Value, String> {
-    let codex_home = resolve_codex_home_for_workspace_core(workspaces, &workspace_id).await?;
-    let model = codex_config::read_config_model(Some(codex_home))?;
-    Ok(json!({ "model": model }))
-}
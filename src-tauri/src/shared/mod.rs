@@ -1,8 +0,0 @@
-pub(crate) mod account;
-pub(crate) mod codex_core;
-pub(crate) mod files_core;
-pub(crate) mod git_core;
-pub(crate) mod process_core;
-pub(crate) mod settings_core;
-pub(crate) mod worktree_core;
-pub(crate) mod workspaces_core;
@@ -1,13 +1,37 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
 use tokio::sync::Mutex;
 
 use crate::dictation::DictationState;
+use crate::files::policy as file_policy;
+use crate::shared::account_profiles_core::AccountProfiles;
+use crate::shared::auth_watch_core::AuthWatcher;
 use crate::shared::codex_core::CodexLoginCancelState;
-use crate::storage::{read_settings, read_workspaces};
-use crate::types::{AppSettings, WorkspaceEntry};
+use crate::shared::collaboration_presets_core::CollaborationPresets;
+use crate::shared::data_files_core::{SETTINGS_FILE, WORKSPACES_FILE};
+use crate::shared::drafts_core::Drafts;
+use crate::shared::file_history_core::FileHistory;
+use crate::shared::file_list_cache_core::FileListCache;
+use crate::shared::file_transfer_core::PendingWrites;
+use crate::shared::mcp_health_core::McpHealthMonitor;
+use crate::shared::pinned_context_core::PinnedContext;
+use crate::shared::problems_core::Problems;
+use crate::shared::removal_core::RemovalConfirmations;
+use crate::shared::stale_branches_core::StaleBranchConfirmations;
+use crate::shared::i18n_core;
+use crate::shared::search_index_core::SearchIndexes;
+use crate::shared::symbols_core::SymbolIndex;
+use crate::shared::session_log_core::SESSION_LOGS_DIR;
+use crate::shared::thread_branches_core::THREAD_BRANCHES_FILE;
+use crate::shared::thread_usage_core::ThreadUsageStats;
+use crate::shared::time_tracking_core::TimeTracker;
+use crate::shared::thumbnails_core::THUMBNAILS_DIR;
+use crate::shared::turn_snapshots_core::TurnSnapshots;
+use crate::storage::{read_settings, read_thread_branches, read_workspaces};
+use crate::types::{AppSettings, ThreadBranchRecord, WorkspaceEntry};
 
 pub(crate) struct AppState {
     pub(crate) workspaces: Mutex<HashMap<String, WorkspaceEntry>>,
@@ -15,11 +39,37 @@ pub(crate) struct AppState {
     pub(crate) terminal_sessions:
         Mutex<HashMap<String, Arc<crate::terminal::TerminalSession>>>,
     pub(crate) remote_backend: Mutex<Option<crate::remote_backend::RemoteBackend>>,
+    /// Ticket from the daemon's last handshake, presented first when reconnecting.
+    pub(crate) remote_resume_ticket: Mutex<Option<String>>,
+    /// The last `eventSeq` the daemon sent, to catch up with `subscribe_since` when a
+    /// ticket can't be resumed.
+    pub(crate) remote_event_seq: Arc<AtomicU64>,
     pub(crate) storage_path: PathBuf,
     pub(crate) settings_path: PathBuf,
     pub(crate) app_settings: Mutex<AppSettings>,
     pub(crate) dictation: Mutex<DictationState>,
     pub(crate) codex_login_cancels: Mutex<HashMap<String, CodexLoginCancelState>>,
+    pub(crate) thread_branches: Mutex<HashMap<String, ThreadBranchRecord>>,
+    pub(crate) thread_branches_path: PathBuf,
+    pub(crate) thread_usage: ThreadUsageStats,
+    pub(crate) time_tracker: TimeTracker,
+    pub(crate) search_indexes: SearchIndexes,
+    pub(crate) symbol_index: Arc<SymbolIndex>,
+    pub(crate) session_logs_dir: PathBuf,
+    pub(crate) thumbnails_dir: PathBuf,
+    pub(crate) turn_snapshots: TurnSnapshots,
+    pub(crate) file_history: FileHistory,
+    pub(crate) drafts: Drafts,
+    pub(crate) collaboration_presets: CollaborationPresets,
+    pub(crate) pinned_context: PinnedContext,
+    pub(crate) account_profiles: AccountProfiles,
+    pub(crate) mcp_health: McpHealthMonitor,
+    pub(crate) auth_watcher: AuthWatcher,
+    pub(crate) file_lists: FileListCache,
+    pub(crate) problems: Problems,
+    pub(crate) removal_confirmations: RemovalConfirmations,
+    pub(crate) stale_branch_confirmations: StaleBranchConfirmations,
+    pub(crate) pending_writes: PendingWrites,
 }
 
 impl AppState {
@@ -28,20 +78,47 @@ impl AppState {
             .path()
             .app_data_dir()
             .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| ".".into()));
-        let storage_path = data_dir.join("workspaces.json");
-        let settings_path = data_dir.join("settings.json");
+        let storage_path = data_dir.join(WORKSPACES_FILE);
+        let settings_path = data_dir.join(SETTINGS_FILE);
         let workspaces = read_workspaces(&storage_path).unwrap_or_default();
         let app_settings = read_settings(&settings_path).unwrap_or_default();
+        i18n_core::set_locale(&app_settings.locale);
+        file_policy::set_skip_dirs(&app_settings.skip_dirs);
+        let thread_branches_path = data_dir.join(THREAD_BRANCHES_FILE);
+        let thread_branches = read_thread_branches(&thread_branches_path).unwrap_or_default();
         Self {
             workspaces: Mutex::new(workspaces),
             sessions: Mutex::new(HashMap::new()),
             terminal_sessions: Mutex::new(HashMap::new()),
             remote_backend: Mutex::new(None),
+            remote_resume_ticket: Mutex::new(None),
+            remote_event_seq: Arc::new(AtomicU64::new(0)),
             storage_path,
             settings_path,
             app_settings: Mutex::new(app_settings),
             dictation: Mutex::new(DictationState::default()),
             codex_login_cancels: Mutex::new(HashMap::new()),
+            thread_branches: Mutex::new(thread_branches),
+            thread_branches_path,
+            thread_usage: ThreadUsageStats::load(&data_dir),
+            time_tracker: TimeTracker::load(&data_dir),
+            search_indexes: SearchIndexes::default(),
+            symbol_index: Arc::default(),
+            session_logs_dir: data_dir.join(SESSION_LOGS_DIR),
+            thumbnails_dir: data_dir.join(THUMBNAILS_DIR),
+            turn_snapshots: TurnSnapshots::load(&data_dir),
+            file_history: FileHistory::load(&data_dir),
+            drafts: Drafts::load(&data_dir),
+            collaboration_presets: CollaborationPresets::load(&data_dir),
+            pinned_context: PinnedContext::load(&data_dir),
+            account_profiles: AccountProfiles::load(&data_dir),
+            mcp_health: McpHealthMonitor::default(),
+            auth_watcher: AuthWatcher::default(),
+            file_lists: FileListCache::default(),
+            problems: Problems::default(),
+            removal_confirmations: RemovalConfirmations::default(),
+            stale_branch_confirmations: StaleBranchConfirmations::default(),
+            pending_writes: PendingWrites::default(),
         }
     }
 }
@@ -28,14 +28,35 @@ use super::worktree::{
 
 use crate::backend::app_server::WorkspaceSession;
 use crate::codex::spawn_workspace_session;
-use crate::codex::args::resolve_workspace_codex_args;
+use crate::codex::args::{resolve_workspace_codex_args, CodexArgs, CodexCommand};
 use crate::codex::home::resolve_workspace_codex_home;
 use crate::git_utils::resolve_git_root;
 use crate::remote_backend;
+use crate::shared::build_core::{self, BuildReport};
+use crate::shared::context_budget_core::{self, ContextPlan};
+use crate::shared::dependency_audit_core::{self, DependencyAudit};
+use crate::shared::file_transfer_core::{
+    self, Capabilities, CommittedWrite, WriteProgress, WriteSession,
+};
+use crate::shared::disk_usage_core::{self, DiskUsageReport};
+use crate::shared::i18n_core::{self, MessageKey};
+use crate::shared::mentions_core::{self, MentionResolution};
+use crate::shared::packages_core::{self, WorkspacePackage};
+use crate::shared::problems_core::ProblemsView;
 use crate::shared::process_core::{kill_child_process_tree, tokio_command};
+use crate::shared::search_index_core::{
+    self, IndexedSearchResponse, MultiWorkspaceSearchResponse, SearchOptions,
+};
+use crate::shared::removal_core::{self, RemovalPlan};
+use crate::shared::sparse_checkout_core::{self, SparseCheckout};
+use crate::shared::stale_branches_core::{self, StaleBranchDeletion, StaleBranchesReport};
+use crate::shared::symbols_core::{self, DefinitionLookup, WorkspaceSymbolsResponse};
 #[cfg(target_os = "windows")]
 use crate::shared::process_core::{build_cmd_c_command, resolve_windows_executable};
+use crate::shared::workspace_analysis_core::{self, WorkspaceAnalysis};
+use crate::shared::workspace_stats_core::{self, WorkspaceStats};
 use crate::shared::workspaces_core;
+use crate::shared::worktree_reconcile_core::{self, WorktreeReconcileReport};
 use crate::state::AppState;
 use crate::storage::write_workspaces;
 use crate::types::{
@@ -47,12 +68,438 @@ fn spawn_with_app(
     app: &AppHandle,
     entry: WorkspaceEntry,
     default_bin: Option<String>,
-    codex_args: Option<String>,
+    codex_args: Option<CodexArgs>,
     codex_home: Option<PathBuf>,
 ) -> impl std::future::Future<Output = Result<Arc<WorkspaceSession>, String>> {
     spawn_workspace_session(entry, default_bin, codex_args, app.clone(), codex_home)
 }
 
+#[tauri::command]
+pub(crate) async fn resolve_mentions(
+    workspace_id: String,
+    text: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<MentionResolution, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "resolve_mentions",
+            json!({ "workspaceId": workspace_id, "text": text }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    mentions_core::resolve_mentions_core(&state.workspaces, &workspace_id, &text, |root, max| {
+        state.file_lists.list(root, max, list_workspace_files_inner)
+    })
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn plan_context(
+    workspace_id: String,
+    text: String,
+    model: Option<String>,
+    budget_tokens: Option<usize>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ContextPlan, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "plan_context",
+            json!({
+                "workspaceId": workspace_id,
+                "text": text,
+                "model": model,
+                "budgetTokens": budget_tokens,
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    context_budget_core::plan_context_core(
+        &state.workspaces,
+        &workspace_id,
+        &text,
+        model.as_deref(),
+        budget_tokens,
+        |root, max| state.file_lists.list(root, max, list_workspace_files_inner),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn indexed_search(
+    workspace_id: String,
+    query: String,
+    limit: Option<usize>,
+    path_prefix: Option<String>,
+    options: Option<SearchOptions>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<IndexedSearchResponse, String> {
+    let options = options.unwrap_or_default();
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "indexed_search",
+            json!({
+                "workspaceId": workspace_id,
+                "query": query,
+                "limit": limit,
+                "pathPrefix": path_prefix,
+                "matchCase": options.match_case,
+                "wholeWord": options.whole_word,
+                "regex": options.regex,
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    search_index_core::indexed_search_core(
+        &state.workspaces,
+        &state.search_indexes,
+        &workspace_id,
+        search_index_core::SearchQuery {
+            query: &query,
+            limit,
+            path_prefix: path_prefix.as_deref(),
+            options,
+        },
+        list_workspace_files_inner,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn workspace_symbols(
+    workspace_id: String,
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceSymbolsResponse, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "workspace_symbols",
+            json!({ "workspaceId": workspace_id, "query": query, "limit": limit }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    symbols_core::workspace_symbols_core(
+        &state.workspaces,
+        &state.symbol_index,
+        &workspace_id,
+        &query,
+        limit,
+        |root, max| state.file_lists.list(root, max, list_workspace_files_inner),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn find_definition(
+    workspace_id: String,
+    path: String,
+    line: usize,
+    column: usize,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DefinitionLookup, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "find_definition",
+            json!({
+                "workspaceId": workspace_id,
+                "path": path,
+                "line": line,
+                "column": column,
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    symbols_core::find_definition_core(
+        &state.workspaces,
+        &state.symbol_index,
+        &workspace_id,
+        &path,
+        line,
+        column,
+        |root, max| state.file_lists.list(root, max, list_workspace_files_inner),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn search_all_workspaces(
+    query: String,
+    workspace_ids: Option<Vec<String>>,
+    limit: Option<usize>,
+    options: Option<SearchOptions>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<MultiWorkspaceSearchResponse, String> {
+    let options = options.unwrap_or_default();
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "search_all_workspaces",
+            json!({
+                "query": query,
+                "workspaceIds": workspace_ids,
+                "limit": limit,
+                "matchCase": options.match_case,
+                "wholeWord": options.whole_word,
+                "regex": options.regex,
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    search_index_core::search_all_workspaces_core(
+        &state.workspaces,
+        &state.search_indexes,
+        &query,
+        workspace_ids,
+        limit,
+        options,
+        list_workspace_files_inner,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn get_sparse_checkout(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<SparseCheckout, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "get_sparse_checkout",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    sparse_checkout_core::get_sparse_checkout_core(&state.workspaces, &workspace_id).await
+}
+
+/// `patterns: None` turns sparse checkout off.
+#[tauri::command]
+pub(crate) async fn set_sparse_checkout(
+    workspace_id: String,
+    patterns: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<SparseCheckout, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "set_sparse_checkout",
+            json!({ "workspaceId": workspace_id, "patterns": patterns }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let (root, sparse) = match patterns {
+        Some(patterns) => {
+            sparse_checkout_core::set_sparse_checkout_core(
+                &state.workspaces,
+                &workspace_id,
+                &patterns,
+            )
+            .await?
+        }
+        None => {
+            sparse_checkout_core::disable_sparse_checkout_core(&state.workspaces, &workspace_id)
+                .await?
+        }
+    };
+    state.file_lists.invalidate(&root);
+    Ok(sparse)
+}
+
+#[tauri::command]
+pub(crate) async fn workspace_stats(
+    id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceStats, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "workspace_stats", json!({ "id": id }))
+                .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    workspace_stats_core::workspace_stats_core(
+        &state.workspaces,
+        &state.thread_branches,
+        &state.time_tracker,
+        &id,
+        list_workspace_files_inner,
+    )
+    .await
+}
+
+/// In remote mode the daemon also measures every 10 minutes and warns on `diskQuota`.
+#[tauri::command]
+pub(crate) async fn disk_usage(
+    workspace_id: Option<String>,
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DiskUsageReport, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "disk_usage",
+            json!({ "workspaceId": workspace_id, "refresh": refresh.unwrap_or(false) }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("Failed to resolve app data dir: {err}"))?;
+    let quota = state.app_settings.lock().await.disk_quota.clone();
+    let report = disk_usage_core::disk_usage_core(&state.workspaces, &data_dir, &quota).await?;
+    Ok(match workspace_id {
+        Some(workspace_id) => disk_usage_core::report_for_workspace(report, &workspace_id),
+        None => report,
+    })
+}
+
+#[tauri::command]
+pub(crate) async fn analyze_workspace(
+    id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceAnalysis, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "analyze_workspace", json!({ "id": id }))
+                .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    workspace_analysis_core::analyze_workspace_core(
+        &state.workspaces,
+        &id,
+        list_workspace_files_inner,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn list_packages(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<WorkspacePackage>, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "list_packages",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    packages_core::list_packages_core(&state.workspaces, &workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn audit_dependencies(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DependencyAudit, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "audit_dependencies",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    dependency_audit_core::audit_dependencies_core(&state.workspaces, &workspace_id, |_| Ok(()))
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn run_build(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<BuildReport, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "run_build",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    build_core::run_build_core(&state.workspaces, &state.problems, &workspace_id, |_| Ok(()))
+        .await
+}
+
+#[tauri::command]
+pub(crate) async fn list_problems(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ProblemsView, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "list_problems",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    Ok(state.problems.view(&workspace_id))
+}
+
 #[tauri::command]
 pub(crate) async fn read_workspace_file(
     workspace_id: String,
@@ -71,15 +518,125 @@ pub(crate) async fn read_workspace_file(
         return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
+    let max_bytes =
+        file_transfer_core::max_read_bytes(&state.app_settings.lock().await.file_limits);
     workspaces_core::read_workspace_file_core(
         &state.workspaces,
         &workspace_id,
         &path,
-        |root, rel_path| read_workspace_file_inner(root, rel_path),
+        |root, rel_path| read_workspace_file_inner(root, rel_path, max_bytes),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn get_capabilities(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Capabilities, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response =
+            remote_backend::call_remote(&*state, app, "get_capabilities", json!({})).await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    Ok(file_transfer_core::capabilities(&*state.app_settings.lock().await))
+}
+
+#[tauri::command]
+pub(crate) async fn begin_write(
+    workspace_id: String,
+    path: String,
+    size: Option<u64>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WriteSession, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "begin_write",
+            json!({ "workspaceId": workspace_id, "path": path, "size": size }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    file_transfer_core::begin_write_core(
+        &state.workspaces,
+        &state.app_settings,
+        &state.pending_writes,
+        &workspace_id,
+        &path,
+        size,
     )
     .await
 }
 
+#[tauri::command]
+pub(crate) async fn write_chunk(
+    write_id: String,
+    offset: u64,
+    data: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WriteProgress, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "write_chunk",
+            json!({ "writeId": write_id, "offset": offset, "data": data }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    file_transfer_core::write_chunk_core(&state.pending_writes, &write_id, offset, &data)
+}
+
+#[tauri::command]
+pub(crate) async fn commit_write(
+    write_id: String,
+    sha256: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<CommittedWrite, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "commit_write",
+            json!({ "writeId": write_id, "sha256": sha256 }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    file_transfer_core::commit_write_core(
+        &state.pending_writes,
+        &state.file_lists,
+        &state.file_history,
+        &write_id,
+        sha256.as_deref(),
+    )
+}
+
+#[tauri::command]
+pub(crate) async fn abort_write(
+    write_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        remote_backend::call_remote(&*state, app, "abort_write", json!({ "writeId": write_id }))
+            .await?;
+        return Ok(());
+    }
+
+    file_transfer_core::abort_write_core(&state.pending_writes, &write_id)
+}
+
 
 #[tauri::command]
 pub(crate) async fn list_workspaces(
@@ -293,10 +850,14 @@ pub(crate) async fn add_worktree(
     branch: String,
     name: Option<String>,
     copy_agents_md: Option<bool>,
+    allow_protected_branch: Option<bool>,
+    base_ref: Option<String>,
+    fetch_base: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<WorkspaceInfo, String> {
     let copy_agents_md = copy_agents_md.unwrap_or(true);
+    let allow_protected_branch = allow_protected_branch.unwrap_or(false);
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
@@ -306,7 +867,10 @@ pub(crate) async fn add_worktree(
                 "parentId": parent_id,
                 "branch": branch,
                 "name": name,
-                "copyAgentsMd": copy_agents_md
+                "copyAgentsMd": copy_agents_md,
+                "allowProtectedBranch": allow_protected_branch,
+                "baseRef": base_ref,
+                "fetchBase": fetch_base
             }),
         )
         .await?;
@@ -321,8 +885,15 @@ pub(crate) async fn add_worktree(
     workspaces_core::add_worktree_core(
         parent_id,
         branch,
-        name,
-        copy_agents_md,
+        workspaces_core::WorktreeOptions {
+            name,
+            copy_agents_md,
+            allow_protected_branch,
+            base: workspaces_core::WorktreeBase {
+                base_ref,
+                fetch: fetch_base,
+            },
+        },
         &data_dir,
         &state.workspaces,
         &state.sessions,
@@ -336,6 +907,11 @@ pub(crate) async fn add_worktree(
             async move { git_branch_exists(&root, &branch).await }
         },
         None::<fn(&PathBuf, &str) -> std::future::Ready<Result<Option<String>, String>>>,
+        |root, remote| {
+            let root = root.clone();
+            let remote = remote.to_string();
+            async move { git_remote_exists(&root, &remote).await }
+        },
         |root, args| {
             workspaces_core::run_git_command_unit(root, args, |repo, args_owned| {
                 run_git_command_owned(repo, args_owned)
@@ -401,14 +977,44 @@ pub(crate) async fn worktree_setup_mark_ran(
 #[tauri::command]
 pub(crate) async fn remove_workspace(
     id: String,
+    confirm_token: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<Option<RemovalPlan>, String> {
     if remote_backend::is_remote_mode(&*state).await {
-        remote_backend::call_remote(&*state, app, "remove_workspace", json!({ "id": id })).await?;
-        return Ok(());
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "remove_workspace",
+            json!({ "id": id, "confirmToken": confirm_token }),
+        )
+        .await?;
+        if confirm_token.is_some() {
+            return Ok(None);
+        }
+        return serde_json::from_value(response)
+            .map(Some)
+            .map_err(|err| err.to_string());
     }
 
+    let Some(token) = confirm_token else {
+        return removal_core::plan_removal_core(
+            &state.workspaces,
+            &state.removal_confirmations,
+            &id,
+            false,
+        )
+        .await
+        .map(Some);
+    };
+    removal_core::confirm_removal_core(
+        &state.workspaces,
+        &state.removal_confirmations,
+        &id,
+        false,
+        &token,
+    )
+    .await?;
     workspaces_core::remove_workspace_core(
         id,
         &state.workspaces,
@@ -427,21 +1033,52 @@ pub(crate) async fn remove_workspace(
         true,
         true,
     )
-    .await
+    .await?;
+    Ok(None)
 }
 
 
 #[tauri::command]
 pub(crate) async fn remove_worktree(
     id: String,
+    confirm_token: Option<String>,
     state: State<'_, AppState>,
     app: AppHandle,
-) -> Result<(), String> {
+) -> Result<Option<RemovalPlan>, String> {
     if remote_backend::is_remote_mode(&*state).await {
-        remote_backend::call_remote(&*state, app, "remove_worktree", json!({ "id": id })).await?;
-        return Ok(());
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "remove_worktree",
+            json!({ "id": id, "confirmToken": confirm_token }),
+        )
+        .await?;
+        if confirm_token.is_some() {
+            return Ok(None);
+        }
+        return serde_json::from_value(response)
+            .map(Some)
+            .map_err(|err| err.to_string());
     }
 
+    let Some(token) = confirm_token else {
+        return removal_core::plan_removal_core(
+            &state.workspaces,
+            &state.removal_confirmations,
+            &id,
+            true,
+        )
+        .await
+        .map(Some);
+    };
+    removal_core::confirm_removal_core(
+        &state.workspaces,
+        &state.removal_confirmations,
+        &id,
+        true,
+        &token,
+    )
+    .await?;
     workspaces_core::remove_worktree_core(
         id,
         &state.workspaces,
@@ -458,6 +1095,100 @@ pub(crate) async fn remove_worktree(
                 .map_err(|err| format!("Failed to remove worktree folder: {err}"))
         },
     )
+    .await?;
+    Ok(None)
+}
+
+
+#[tauri::command]
+pub(crate) async fn stale_branches_report(
+    workspace_id: String,
+    inactive_days: Option<u32>,
+    base: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<StaleBranchesReport, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "stale_branches_report",
+            json!({ "workspaceId": workspace_id, "inactiveDays": inactive_days, "base": base }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    stale_branches_core::stale_branches_report_core(
+        &state.workspaces,
+        &state.thread_branches,
+        &state.stale_branch_confirmations,
+        &workspace_id,
+        inactive_days,
+        base,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn delete_stale_branches(
+    workspace_id: String,
+    branches: Vec<String>,
+    confirm_token: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<StaleBranchDeletion, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "delete_stale_branches",
+            json!({
+                "workspaceId": workspace_id,
+                "branches": branches,
+                "confirmToken": confirm_token,
+                "force": force
+            }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    stale_branches_core::delete_stale_branches_core(
+        &state.workspaces,
+        &state.stale_branch_confirmations,
+        &workspace_id,
+        &branches,
+        &confirm_token,
+        force.unwrap_or(false),
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn reconcile_worktrees(
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorktreeReconcileReport, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "reconcile_worktrees",
+            json!({ "dryRun": dry_run }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    worktree_reconcile_core::reconcile_worktrees_core(
+        &state.workspaces,
+        &state.sessions,
+        &state.storage_path,
+        dry_run.unwrap_or(false),
+    )
     .await
 }
 
@@ -466,15 +1197,21 @@ pub(crate) async fn remove_worktree(
 pub(crate) async fn rename_worktree(
     id: String,
     branch: String,
+    allow_protected_branch: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<WorkspaceInfo, String> {
+    let allow_protected_branch = allow_protected_branch.unwrap_or(false);
     if remote_backend::is_remote_mode(&*state).await {
         let response = remote_backend::call_remote(
             &*state,
             app,
             "rename_worktree",
-            json!({ "id": id, "branch": branch }),
+            json!({
+                "id": id,
+                "branch": branch,
+                "allowProtectedBranch": allow_protected_branch
+            }),
         )
         .await?;
         return serde_json::from_value(response).map_err(|err| err.to_string());
@@ -488,6 +1225,7 @@ pub(crate) async fn rename_worktree(
     workspaces_core::rename_worktree_core(
         id,
         branch,
+        allow_protected_branch,
         &data_dir,
         &state.workspaces,
         &state.sessions,
@@ -523,15 +1261,22 @@ pub(crate) async fn rename_worktree_upstream(
     id: String,
     old_branch: String,
     new_branch: String,
+    allow_protected_branch: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
+    let allow_protected_branch = allow_protected_branch.unwrap_or(false);
     if remote_backend::is_remote_mode(&*state).await {
         remote_backend::call_remote(
             &*state,
             app,
             "rename_worktree_upstream",
-            json!({ "id": id, "oldBranch": old_branch, "newBranch": new_branch }),
+            json!({
+                "id": id,
+                "oldBranch": old_branch,
+                "newBranch": new_branch,
+                "allowProtectedBranch": allow_protected_branch
+            }),
         )
         .await?;
         return Ok(());
@@ -541,6 +1286,7 @@ pub(crate) async fn rename_worktree_upstream(
         id,
         old_branch,
         new_branch,
+        allow_protected_branch,
         &state.workspaces,
         |entry| resolve_git_root(entry),
         |root, branch| {
@@ -584,7 +1330,7 @@ pub(crate) async fn apply_worktree_changes(
         let entry = workspaces
             .get(&workspace_id)
             .cloned()
-            .ok_or("workspace not found")?;
+            .ok_or_else(|| i18n_core::message(MessageKey::WorkspaceNotFound))?;
         if !entry.kind.is_worktree() {
             return Err("Not a worktree workspace.".to_string());
         }
@@ -708,6 +1454,7 @@ pub(crate) async fn apply_worktree_changes(
 pub(crate) async fn update_workspace_settings(
     id: String,
     settings: WorkspaceSettings,
+    expected_revision: Option<u64>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<WorkspaceInfo, String> {
@@ -716,15 +1463,18 @@ pub(crate) async fn update_workspace_settings(
             &*state,
             app,
             "update_workspace_settings",
-            json!({ "id": id, "settings": settings }),
+            json!({ "id": id, "settings": settings, "expectedRevision": expected_revision }),
         )
         .await?;
         return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
     workspaces_core::update_workspace_settings_core(
-        id,
-        settings,
+        workspaces_core::WorkspaceSettingsUpdate {
+            id,
+            settings,
+            expected_revision,
+        },
         &state.workspaces,
         &state.sessions,
         &state.app_settings,
@@ -744,6 +1494,7 @@ pub(crate) async fn update_workspace_settings(
 pub(crate) async fn update_workspace_codex_bin(
     id: String,
     codex_bin: Option<String>,
+    expected_revision: Option<u64>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<WorkspaceInfo, String> {
@@ -753,7 +1504,7 @@ pub(crate) async fn update_workspace_codex_bin(
             &*state,
             app,
             "update_workspace_codex_bin",
-            json!({ "id": id, "codex_bin": codex_bin }),
+            json!({ "id": id, "codex_bin": codex_bin, "expectedRevision": expected_revision }),
         )
         .await?;
         return serde_json::from_value(response).map_err(|err| err.to_string());
@@ -762,6 +1513,7 @@ pub(crate) async fn update_workspace_codex_bin(
     workspaces_core::update_workspace_codex_bin_core(
         id,
         codex_bin,
+        expected_revision,
         &state.workspaces,
         &state.sessions,
         &state.storage_path,
@@ -770,6 +1522,68 @@ pub(crate) async fn update_workspace_codex_bin(
 }
 
 
+#[tauri::command]
+pub(crate) async fn relocate_workspace(
+    id: String,
+    new_path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceInfo, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let new_path = remote_backend::normalize_path_for_remote(new_path);
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "relocate_workspace",
+            json!({ "id": id, "newPath": new_path }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    workspaces_core::relocate_workspace_core(
+        workspaces_core::WorkspaceRelocation { id, new_path },
+        &state.workspaces,
+        &state.sessions,
+        &state.app_settings,
+        &state.storage_path,
+        |root, args| {
+            workspaces_core::run_git_command_unit(root, args, |repo, args_owned| {
+                run_git_command_owned(repo, args_owned)
+            })
+        },
+        |entry, default_bin, codex_args, codex_home| {
+            spawn_with_app(&app, entry, default_bin, codex_args, codex_home)
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn preview_codex_command(
+    workspace_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<CodexCommand, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "preview_codex_command",
+            json!({ "workspaceId": workspace_id }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    workspaces_core::preview_codex_command_core(
+        workspace_id,
+        &state.workspaces,
+        &state.app_settings,
+    )
+    .await
+}
+
 #[tauri::command]
 pub(crate) async fn connect_workspace(
     id: String,
@@ -813,7 +1627,7 @@ pub(crate) async fn list_workspace_files(
     }
 
     workspaces_core::list_workspace_files_core(&state.workspaces, &workspace_id, |root| {
-        list_workspace_files_inner(root, usize::MAX)
+        state.file_lists.list(root, usize::MAX, list_workspace_files_inner)
     })
     .await
 }
@@ -1,10 +1,10 @@
 mod commands;
-mod files;
 mod git;
 mod macos;
-mod settings;
 mod worktree;
 
+use fridex_core::workspaces::{files, settings};
+
 pub(crate) use commands::*;
 
 #[cfg(test)]
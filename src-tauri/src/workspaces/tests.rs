@@ -3,12 +3,12 @@ use std::future::Future;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use super::settings::{apply_workspace_settings_update, sort_workspaces};
+use super::settings::apply_workspace_settings_update;
 use super::worktree::{
     build_clone_destination_path, sanitize_clone_dir_name, sanitize_worktree_name,
 };
 use crate::backend::app_server::WorkspaceSession;
-use crate::shared::workspaces_core::rename_worktree_core;
+use crate::shared::workspaces_core::{rename_worktree_core, sort_workspaces};
 use crate::storage::{read_workspaces, write_workspaces};
 use crate::types::{
     AppSettings, WorktreeInfo, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings,
@@ -52,10 +52,21 @@ fn workspace_with_id_and_kind(
             group_id: None,
             git_root: None,
             codex_home: None,
+            account_profile: None,
             codex_args: None,
+            codex_launch: None,
             launch_script: None,
             launch_scripts: None,
             worktree_setup_script: None,
+            worktree_base_ref: None,
+            worktree_fetch_base: false,
+            default_model: None,
+            default_effort: None,
+            default_access_mode: None,
+            default_collaboration_preset: None,
+            protected: false,
+            protected_branches: Vec::new(),
+            revision: 0,
         },
     }
 }
@@ -286,6 +297,7 @@ fn rename_worktree_preserves_custom_name() {
     let updated = rename_worktree_core(
         worktree.id.clone(),
         "feature/new".to_string(),
+        false,
         &temp_dir,
         &workspaces,
         &sessions,
@@ -356,6 +368,7 @@ fn rename_worktree_updates_name_when_unmodified() {
     let updated = rename_worktree_core(
         worktree.id.clone(),
         "feature/new".to_string(),
+        false,
         &temp_dir,
         &workspaces,
         &sessions,
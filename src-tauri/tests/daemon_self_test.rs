@@ -0,0 +1,15 @@
+use std::process::Command;
+
+#[test]
+fn daemon_self_test_passes_against_mock_app_server() {
+    let output = Command::new(env!("CARGO_BIN_EXE_codex_monitor_daemon"))
+        .arg("--self-test")
+        .output()
+        .unwrap_or_else(|error| panic!("Failed to run daemon self-test: {error}"));
+    assert!(
+        output.status.success(),
+        "self-test failed\nstdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -47,6 +47,7 @@ describe("useAppServerEvents", () => {
     const handlers: Handlers = {
       onAppServerEvent: vi.fn(),
       onWorkspaceConnected: vi.fn(),
+      onSpawnProgress: vi.fn(),
       onThreadStarted: vi.fn(),
       onThreadNameUpdated: vi.fn(),
       onBackgroundThreadAction: vi.fn(),
@@ -69,6 +70,26 @@ describe("useAppServerEvents", () => {
     });
     expect(handlers.onWorkspaceConnected).toHaveBeenCalledWith("ws-1");
 
+    act(() => {
+      listener?.({
+        workspace_id: "ws-1",
+        message: {
+          method: "codex/spawnProgress",
+          params: {
+            workspaceId: "ws-1",
+            stage: "initializing",
+            elapsedMs: 120,
+            error: "timed out",
+          },
+        },
+      });
+    });
+    expect(handlers.onSpawnProgress).toHaveBeenCalledWith("ws-1", {
+      stage: "initializing",
+      elapsedMs: 120,
+      error: "timed out",
+    });
+
     act(() => {
       listener?.({
         workspace_id: "ws-1",
@@ -3,6 +3,7 @@ import type {
   AppServerEvent,
   ApprovalRequest,
   RequestUserInputRequest,
+  SpawnProgress,
 } from "../../../types";
 import { subscribeAppServerEvents } from "../../../services/events";
 import {
@@ -30,6 +31,7 @@ type AgentCompleted = {
 
 type AppServerEventHandlers = {
   onWorkspaceConnected?: (workspaceId: string) => void;
+  onSpawnProgress?: (workspaceId: string, progress: SpawnProgress) => void;
   onThreadStarted?: (workspaceId: string, thread: Record<string, unknown>) => void;
   onThreadNameUpdated?: (
     workspaceId: string,
@@ -96,6 +98,7 @@ export const METHODS_ROUTED_IN_USE_APP_SERVER_EVENTS = [
   "account/updated",
   "codex/backgroundThread",
   "codex/connected",
+  "codex/spawnProgress",
   "error",
   "item/agentMessage/delta",
   "item/commandExecution/outputDelta",
@@ -134,6 +137,15 @@ export function useAppServerEvents(handlers: AppServerEventHandlers) {
         return;
       }
 
+      if (method === "codex/spawnProgress") {
+        handlers.onSpawnProgress?.(workspace_id, {
+          stage: String(params.stage ?? "") as SpawnProgress["stage"],
+          elapsedMs: Number(params.elapsedMs ?? 0),
+          error: typeof params.error === "string" ? params.error : null,
+        });
+        return;
+      }
+
       const requestId = getAppServerRequestId(payload);
       const hasRequestId = requestId !== null;
 
@@ -214,6 +214,7 @@ export function useModels({
           item.defaultReasoningEffort ?? item.default_reasoning_effort,
         ),
         isDefault: Boolean(item.isDefault ?? item.is_default ?? false),
+        capabilities: item.capabilities ?? null,
       }));
       const data = (() => {
         if (!configModelFromConfig) {
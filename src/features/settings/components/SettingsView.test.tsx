@@ -24,6 +24,7 @@ const baseSettings: AppSettings = {
   backendMode: "local",
   remoteBackendHost: "127.0.0.1:4732",
   remoteBackendToken: null,
+  remoteBackendTls: false,
   defaultAccessMode: "current",
   reviewDeliveryMode: "inline",
   composerModelShortcut: null,
@@ -64,6 +65,9 @@ const baseSettings: AppSettings = {
   unifiedExecEnabled: true,
   experimentalAppsEnabled: false,
   personality: "friendly",
+  locale: "en",
+  traceRpcEnabled: false,
+  revision: 0,
   dictationEnabled: false,
   dictationModelId: "base",
   dictationPreferredLanguage: null,
@@ -89,6 +93,15 @@ const baseSettings: AppSettings = {
     },
   ],
   selectedOpenAppId: "vscode",
+  approvalPolicyRules: [],
+  sandboxPresets: [],
+  protectedPathGlobs: [],
+  snapshotBeforeTurns: false,
+  slashCommands: [],
+  retention: {},
+  diskQuota: {},
+  fileLimits: {},
+  skipDirs: {},
 };
 
 const createDoctorResult = () => ({
@@ -346,6 +346,7 @@ export function SettingsView({
   const [codexArgsDraft, setCodexArgsDraft] = useState(appSettings.codexArgs ?? "");
   const [remoteHostDraft, setRemoteHostDraft] = useState(appSettings.remoteBackendHost);
   const [remoteTokenDraft, setRemoteTokenDraft] = useState(appSettings.remoteBackendToken ?? "");
+  const [remoteTlsCaDraft, setRemoteTlsCaDraft] = useState(appSettings.remoteBackendTlsCa ?? "");
   const [scaleDraft, setScaleDraft] = useState(
     `${Math.round(clampUiScale(appSettings.uiScale) * 100)}%`,
   );
@@ -547,6 +548,10 @@ export function SettingsView({
     setRemoteTokenDraft(appSettings.remoteBackendToken ?? "");
   }, [appSettings.remoteBackendToken]);
 
+  useEffect(() => {
+    setRemoteTlsCaDraft(appSettings.remoteBackendTlsCa ?? "");
+  }, [appSettings.remoteBackendTlsCa]);
+
   useEffect(() => {
     setScaleDraft(`${Math.round(clampUiScale(appSettings.uiScale) * 100)}%`);
   }, [appSettings.uiScale]);
@@ -753,6 +758,18 @@ export function SettingsView({
     });
   };
 
+  const handleCommitRemoteTlsCa = async () => {
+    const nextCa = remoteTlsCaDraft.trim() ? remoteTlsCaDraft.trim() : null;
+    setRemoteTlsCaDraft(nextCa ?? "");
+    if (nextCa === (appSettings.remoteBackendTlsCa ?? null)) {
+      return;
+    }
+    await onUpdateAppSettings({
+      ...appSettings,
+      remoteBackendTlsCa: nextCa,
+    });
+  };
+
   const handleCommitScale = async () => {
     if (parsedScale === null) {
       setScaleDraft(`${Math.round(clampUiScale(appSettings.uiScale) * 100)}%`);
@@ -1590,6 +1607,25 @@ export function SettingsView({
                     <option value="dim">Dim</option>
                   </select>
                 </div>
+                <div className="settings-field">
+                  <label className="settings-field-label" htmlFor="locale-select">
+                    Backend message language
+                  </label>
+                  <select
+                    id="locale-select"
+                    className="settings-select"
+                    value={appSettings.locale}
+                    onChange={(event) =>
+                      void onUpdateAppSettings({
+                        ...appSettings,
+                        locale: event.target.value as AppSettings["locale"],
+                      })
+                    }
+                  >
+                    <option value="en">English</option>
+                    <option value="tr">Türkçe</option>
+                  </select>
+                </div>
                 <div className="settings-toggle-row">
                   <div>
                     <div className="settings-toggle-title">
@@ -3219,6 +3255,50 @@ export function SettingsView({
                     <div className="settings-help">
                       Start the daemon separately and point CodexMonitor to it (host:port + token).
                     </div>
+                    <div className="settings-toggle-row">
+                      <div>
+                        <div className="settings-toggle-title">Use TLS</div>
+                        <div className="settings-toggle-subtitle">
+                          For daemons started with <code>--tls-cert</code>.
+                        </div>
+                      </div>
+                      <button
+                        type="button"
+                        className={`settings-toggle ${appSettings.remoteBackendTls ? "on" : ""}`}
+                        onClick={() =>
+                          void onUpdateAppSettings({
+                            ...appSettings,
+                            remoteBackendTls: !appSettings.remoteBackendTls,
+                          })
+                        }
+                        aria-pressed={appSettings.remoteBackendTls}
+                      >
+                        <span className="settings-toggle-knob" />
+                      </button>
+                    </div>
+                    {appSettings.remoteBackendTls && (
+                      <>
+                        <input
+                          className="settings-input"
+                          value={remoteTlsCaDraft}
+                          placeholder="CA certificate path (optional)"
+                          onChange={(event) => setRemoteTlsCaDraft(event.target.value)}
+                          onBlur={() => {
+                            void handleCommitRemoteTlsCa();
+                          }}
+                          onKeyDown={(event) => {
+                            if (event.key === "Enter") {
+                              event.preventDefault();
+                              void handleCommitRemoteTlsCa();
+                            }
+                          }}
+                          aria-label="Remote backend CA certificate"
+                        />
+                        <div className="settings-help">
+                          PEM file to trust for a self-signed daemon certificate.
+                        </div>
+                      </>
+                    )}
                   </div>
                 )}
 
@@ -19,6 +19,7 @@ import { getDefaultInterruptShortcut, isMacPlatform } from "../../../utils/short
 
 const allowedThemes = new Set(["system", "light", "dark", "dim"]);
 const allowedPersonality = new Set(["friendly", "pragmatic"]);
+const allowedLocales = new Set(["en", "tr"]);
 
 function buildDefaultSettings(): AppSettings {
   const isMac = isMacPlatform();
@@ -28,6 +29,7 @@ function buildDefaultSettings(): AppSettings {
     backendMode: "local",
     remoteBackendHost: "127.0.0.1:4732",
     remoteBackendToken: null,
+    remoteBackendTls: false,
     defaultAccessMode: "current",
     reviewDeliveryMode: "inline",
     composerModelShortcut: isMac ? "cmd+shift+m" : "ctrl+shift+m",
@@ -66,6 +68,9 @@ function buildDefaultSettings(): AppSettings {
     unifiedExecEnabled: true,
     experimentalAppsEnabled: false,
     personality: "friendly",
+    locale: "en",
+    traceRpcEnabled: false,
+    revision: 0,
     dictationEnabled: false,
     dictationModelId: "base",
     dictationPreferredLanguage: null,
@@ -82,6 +87,29 @@ function buildDefaultSettings(): AppSettings {
     workspaceGroups: [],
     openAppTargets: DEFAULT_OPEN_APP_TARGETS,
     selectedOpenAppId: DEFAULT_OPEN_APP_ID,
+    approvalPolicyRules: [],
+    sandboxPresets: [],
+    protectedPathGlobs: [
+      "**/.git/**",
+      "~/.ssh/**",
+      "~/.aws/**",
+      "~/.gnupg/**",
+      "~/.kube/**",
+      "**/.env",
+      "**/.env.*",
+      "**/*.pem",
+      "**/*.key",
+      "**/id_rsa*",
+      "**/id_ed25519*",
+      "**/*.tfstate",
+      "**/*.tfstate.backup",
+    ],
+    snapshotBeforeTurns: false,
+    slashCommands: [],
+    retention: {},
+    diskQuota: {},
+    fileLimits: {},
+    skipDirs: {},
   };
 }
 
@@ -124,6 +152,7 @@ function normalizeAppSettings(settings: AppSettings): AppSettings {
     personality: allowedPersonality.has(settings.personality)
       ? settings.personality
       : "friendly",
+    locale: allowedLocales.has(settings.locale) ? settings.locale : "en",
     reviewDeliveryMode:
       settings.reviewDeliveryMode === "detached" ? "detached" : "inline",
     openAppTargets: normalizedTargets,
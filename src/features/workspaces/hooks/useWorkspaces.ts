@@ -23,6 +23,23 @@ import {
   updateWorkspaceCodexBin as updateWorkspaceCodexBinService,
   updateWorkspaceSettings as updateWorkspaceSettingsService,
 } from "../../../services/tauri";
+import type { RemovalFolder, RemovalPlan } from "../../../services/tauri";
+
+function describeRemovalFolders(folders: RemovalFolder[]) {
+  return folders
+    .map((folder) => {
+      const changes =
+        folder.uncommittedFiles === null
+          ? ""
+          : folder.uncommittedFiles === 0
+            ? " (no uncommitted changes)"
+            : ` (${folder.uncommittedFiles} uncommitted file${
+                folder.uncommittedFiles === 1 ? "" : "s"
+              })`;
+      return `\n• ${folder.path}${changes}`;
+    })
+    .join("");
+}
 
 const GROUP_ID_RANDOM_MODULUS = 1_000_000;
 const RESERVED_GROUP_NAME = "Ungrouped";
@@ -659,22 +676,37 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
     [updateWorkspaceSettings, workspaceGroupById, workspaces],
   );
 
+  async function requestRemovalPlan(
+    workspaceId: string,
+    worktree: boolean,
+  ): Promise<RemovalPlan | null> {
+    try {
+      return worktree
+        ? await removeWorktreeService(workspaceId)
+        : await removeWorkspaceService(workspaceId);
+    } catch (error) {
+      const errorMessage = error instanceof Error ? error.message : String(error);
+      void message(errorMessage, {
+        title: worktree ? "Delete worktree failed" : "Delete workspace failed",
+        kind: "error",
+      });
+      return null;
+    }
+  }
+
   async function removeWorkspace(workspaceId: string) {
-    const workspace = workspaces.find((entry) => entry.id === workspaceId);
-    const workspaceName = workspace?.name || "this workspace";
-    const worktreeCount = workspaces.filter(
-      (entry) => entry.parentId === workspaceId,
-    ).length;
-    const childIds = new Set(
-      workspaces
-        .filter((entry) => entry.parentId === workspaceId)
-        .map((entry) => entry.id),
-    );
+    const plan = await requestRemovalPlan(workspaceId, false);
+    if (!plan) {
+      return;
+    }
+    const workspaceName = plan.name || "this workspace";
+    const childIds = new Set(plan.unregistered);
+    const worktreeCount = plan.deletedFolders.length;
     const detail =
       worktreeCount > 0
         ? `\n\nThis will also delete ${worktreeCount} worktree${
             worktreeCount === 1 ? "" : "s"
-          } on disk.`
+          } on disk:${describeRemovalFolders(plan.deletedFolders)}`
         : "";
 
     const confirmed = await ask(
@@ -699,7 +731,7 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
       payload: { workspaceId },
     });
     try {
-      await removeWorkspaceService(workspaceId);
+      await removeWorkspaceService(workspaceId, plan.confirmToken);
       setWorkspaces((prev) =>
         prev.filter(
           (entry) =>
@@ -726,11 +758,14 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
   }
 
   async function removeWorktree(workspaceId: string) {
-    const workspace = workspaces.find((entry) => entry.id === workspaceId);
-    const workspaceName = workspace?.name || "this worktree";
+    const plan = await requestRemovalPlan(workspaceId, true);
+    if (!plan) {
+      return;
+    }
+    const workspaceName = plan.name || "this worktree";
 
     const confirmed = await ask(
-      `Are you sure you want to delete "${workspaceName}"?\n\nThis will close the agent, remove its worktree, and delete it from CodexMonitor.`,
+      `Are you sure you want to delete "${workspaceName}"?\n\nThis will close the agent, remove its worktree, and delete it from CodexMonitor:${describeRemovalFolders(plan.deletedFolders)}`,
       {
         title: "Delete Worktree",
         kind: "warning",
@@ -756,7 +791,7 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
       payload: { workspaceId },
     });
     try {
-      await removeWorktreeService(workspaceId);
+      await removeWorktreeService(workspaceId, plan.confirmToken);
       setWorkspaces((prev) => prev.filter((entry) => entry.id !== workspaceId));
       setActiveWorkspaceId((prev) => (prev === workspaceId ? null : prev));
     } catch (error) {
@@ -168,6 +168,8 @@ describe("tauri invoke wrappers", () => {
       workspaceId: "ws-10",
       cursor: "cursor-1",
       limit: 25,
+      aggregate: null,
+      refresh: null,
     });
   });
 
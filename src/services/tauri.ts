@@ -3,10 +3,13 @@ import { open } from "@tauri-apps/plugin-dialog";
 import type { Options as NotificationOptions } from "@tauri-apps/plugin-notification";
 import type {
   AppSettings,
+  ApprovalPolicyRule,
   CodexDoctorResult,
   DictationModelStatus,
   DictationSessionState,
   LocalUsageSnapshot,
+  RetentionPolicy,
+  SandboxPreset,
   WorkspaceInfo,
   WorkspaceSettings,
 } from "../types";
@@ -83,7 +86,7 @@ export type GlobalCodexConfigResponse = TextFileResponse;
 export type AgentMdResponse = TextFileResponse;
 
 type FileScope = "workspace" | "global";
-type FileKind = "agents" | "config";
+type FileKind = "agents" | "agents_override" | "config";
 
 async function fileRead(
   scope: FileScope,
@@ -102,6 +105,29 @@ async function fileWrite(
   return invoke("file_write", { scope, kind, workspaceId, content });
 }
 
+export type FileVersion = {
+  version: string;
+  path: string;
+  hash: string;
+  previousHash: string | null;
+  size: number;
+  source: "original" | "external" | "file_write" | "revert";
+  createdAt: number;
+};
+
+export type FileHistoryResponse = {
+  path: string;
+  versions: FileVersion[];
+};
+
+export async function fileHistory(path: string): Promise<FileHistoryResponse> {
+  return invoke<FileHistoryResponse>("file_history", { path });
+}
+
+export async function revertFileTo(version: string): Promise<FileVersion> {
+  return invoke<FileVersion>("revert_file_to", { version });
+}
+
 export async function readGlobalAgentsMd(): Promise<GlobalAgentsResponse> {
   return fileRead("global", "agents");
 }
@@ -110,6 +136,20 @@ export async function writeGlobalAgentsMd(content: string): Promise<void> {
   return fileWrite("global", "agents", content);
 }
 
+export async function readGlobalAgentsOverrideMd(): Promise<TextFileResponse> {
+  return fileRead("global", "agents_override");
+}
+
+export async function writeGlobalAgentsOverrideMd(content: string): Promise<void> {
+  return fileWrite("global", "agents_override", content);
+}
+
+export async function deleteGlobalAgentsFile(
+  kind: "agents" | "agents_override",
+): Promise<boolean> {
+  return invoke<boolean>("file_delete", { scope: "global", kind });
+}
+
 export async function readGlobalCodexConfigToml(): Promise<GlobalCodexConfigResponse> {
   return fileRead("global", "config");
 }
@@ -158,8 +198,18 @@ export async function addWorktree(
   branch: string,
   name: string | null,
   copyAgentsMd = true,
+  allowProtectedBranch = false,
+  base: { baseRef?: string | null; fetchBase?: boolean | null } = {},
 ): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("add_worktree", { parentId, branch, name, copyAgentsMd });
+  return invoke<WorkspaceInfo>("add_worktree", {
+    parentId,
+    branch,
+    name,
+    copyAgentsMd,
+    allowProtectedBranch,
+    baseRef: base.baseRef ?? null,
+    fetchBase: base.fetchBase ?? null,
+  });
 }
 
 export type WorktreeSetupStatus = {
@@ -180,38 +230,183 @@ export async function markWorktreeSetupRan(workspaceId: string): Promise<void> {
 export async function updateWorkspaceSettings(
   id: string,
   settings: WorkspaceSettings,
+  expectedRevision?: number,
 ): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("update_workspace_settings", { id, settings });
+  return invoke<WorkspaceInfo>("update_workspace_settings", {
+    id,
+    settings,
+    expectedRevision: expectedRevision ?? null,
+  });
 }
 
 export async function updateWorkspaceCodexBin(
   id: string,
   codex_bin: string | null,
+  expectedRevision?: number,
+): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("update_workspace_codex_bin", {
+    id,
+    codex_bin,
+    expectedRevision: expectedRevision ?? null,
+  });
+}
+
+export async function relocateWorkspace(
+  id: string,
+  newPath: string,
 ): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("update_workspace_codex_bin", { id, codex_bin });
+  return invoke<WorkspaceInfo>("relocate_workspace", { id, newPath });
+}
+
+export type RemovalFolder = {
+  workspaceId: string;
+  name: string;
+  path: string;
+  branch: string | null;
+  uncommittedFiles: number | null;
+};
+
+export type RemovalPlan = {
+  confirmToken: string;
+  workspaceId: string;
+  name: string;
+  path: string;
+  unregistered: string[];
+  deletedFolders: RemovalFolder[];
+  expiresAt: number;
+};
+
+/**
+ * Without `confirmToken` nothing is removed: the backend returns a plan whose token
+ * confirms the removal in a second call.
+ */
+export async function removeWorkspace(
+  id: string,
+  confirmToken?: string,
+): Promise<RemovalPlan | null> {
+  return invoke<RemovalPlan | null>("remove_workspace", {
+    id,
+    confirmToken: confirmToken ?? null,
+  });
+}
+
+export async function removeWorktree(
+  id: string,
+  confirmToken?: string,
+): Promise<RemovalPlan | null> {
+  return invoke<RemovalPlan | null>("remove_worktree", {
+    id,
+    confirmToken: confirmToken ?? null,
+  });
 }
 
-export async function removeWorkspace(id: string): Promise<void> {
-  return invoke("remove_workspace", { id });
+export type WorktreeIssue =
+  | "missing"
+  | "unregistered"
+  | "branchMismatch"
+  | "orphaned"
+  | "parentUnavailable";
+
+export type WorktreeMismatch = {
+  workspaceId: string;
+  name: string;
+  path: string;
+  parentId: string | null;
+  issue: WorktreeIssue;
+  storedBranch: string | null;
+  actualBranch: string | null;
+  detail: string | null;
+  pruned: boolean;
+};
+
+export type WorktreeReconcileReport = {
+  checked: number;
+  dryRun: boolean;
+  mismatches: WorktreeMismatch[];
+};
+
+export async function reconcileWorktrees(
+  dryRun?: boolean,
+): Promise<WorktreeReconcileReport> {
+  return invoke<WorktreeReconcileReport>("reconcile_worktrees", { dryRun });
+}
+
+export type StaleReason = "merged" | "inactive";
+
+export type StaleBranch = {
+  name: string;
+  tip: string;
+  lastCommitAt: number;
+  inactiveDays: number;
+  reasons: StaleReason[];
+  worktreeIds: string[];
+  checkedOut: boolean;
+  threadIds: string[];
+  protected: boolean;
+  deletable: boolean;
+};
+
+export type StaleBranchesReport = {
+  workspaceId: string;
+  base: string;
+  inactiveDays: number;
+  branches: StaleBranch[];
+  confirmToken: string;
+  expiresAt: number;
+};
+
+export type StaleBranchDeletion = {
+  deleted: string[];
+  skipped: { name: string; reason: string }[];
+};
+
+export async function staleBranchesReport(
+  workspaceId: string,
+  inactiveDays?: number,
+  base?: string,
+): Promise<StaleBranchesReport> {
+  return invoke<StaleBranchesReport>("stale_branches_report", {
+    workspaceId,
+    inactiveDays,
+    base,
+  });
 }
 
-export async function removeWorktree(id: string): Promise<void> {
-  return invoke("remove_worktree", { id });
+// `confirmToken` comes from `staleBranchesReport`.
+export async function deleteStaleBranches(
+  workspaceId: string,
+  branches: string[],
+  confirmToken: string,
+  force = false,
+): Promise<StaleBranchDeletion> {
+  return invoke<StaleBranchDeletion>("delete_stale_branches", {
+    workspaceId,
+    branches,
+    confirmToken,
+    force,
+  });
 }
 
 export async function renameWorktree(
   id: string,
   branch: string,
+  allowProtectedBranch = false,
 ): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("rename_worktree", { id, branch });
+  return invoke<WorkspaceInfo>("rename_worktree", { id, branch, allowProtectedBranch });
 }
 
 export async function renameWorktreeUpstream(
   id: string,
   oldBranch: string,
   newBranch: string,
+  allowProtectedBranch = false,
 ): Promise<void> {
-  return invoke("rename_worktree_upstream", { id, oldBranch, newBranch });
+  return invoke("rename_worktree_upstream", {
+    id,
+    oldBranch,
+    newBranch,
+    allowProtectedBranch,
+  });
 }
 
 export async function applyWorktreeChanges(workspaceId: string): Promise<void> {
@@ -238,18 +433,224 @@ export async function getOpenAppIcon(appName: string): Promise<string | null> {
   return invoke<string | null>("get_open_app_icon", { appName });
 }
 
+export type CodexCommand = {
+  program: string;
+  args: string[];
+  env: Record<string, string>;
+  cwd: string;
+  commandLine: string;
+};
+
+export async function previewCodexCommand(
+  workspaceId: string,
+): Promise<CodexCommand> {
+  return invoke<CodexCommand>("preview_codex_command", { workspaceId });
+}
+
 export async function connectWorkspace(id: string): Promise<void> {
   return invoke("connect_workspace", { id });
 }
 
-export async function startThread(workspaceId: string) {
-  return invoke<any>("start_thread", { workspaceId });
+export async function startThread(workspaceId: string, cwd?: string | null) {
+  return invoke<any>("start_thread", { workspaceId, cwd: cwd ?? null });
 }
 
 export async function forkThread(workspaceId: string, threadId: string) {
   return invoke<any>("fork_thread", { workspaceId, threadId });
 }
 
+export type ThreadGraphNode = {
+  threadId: string;
+  parentThreadId: string | null;
+  forkTurnId: string | null;
+  branch: string | null;
+  startedAt: number;
+  children: ThreadGraphNode[];
+};
+
+export type ThreadGraph = {
+  workspaceId: string;
+  roots: ThreadGraphNode[];
+};
+
+export async function threadGraph(workspaceId: string): Promise<ThreadGraph> {
+  return invoke<ThreadGraph>("thread_graph", { workspaceId });
+}
+
+export type SlashCommandInfo = {
+  name: string;
+  description: string;
+  argumentHint: string | null;
+  kind: "prompt" | "rpc";
+  rpc: string | null;
+  source: "builtin" | "custom";
+};
+
+export async function listSlashCommands(): Promise<SlashCommandInfo[]> {
+  return invoke<SlashCommandInfo[]>("list_slash_commands");
+}
+
+export type ThreadDraft = {
+  workspaceId: string;
+  threadId: string;
+  text: string;
+  images: string[];
+  updatedAt: number;
+};
+
+export async function saveDraft(
+  workspaceId: string,
+  threadId: string,
+  text: string,
+  images?: string[] | null,
+): Promise<ThreadDraft | null> {
+  return invoke<ThreadDraft | null>("save_draft", {
+    workspaceId,
+    threadId,
+    text,
+    images: images ?? null,
+  });
+}
+
+export async function getDraft(
+  workspaceId: string,
+  threadId: string,
+): Promise<ThreadDraft | null> {
+  return invoke<ThreadDraft | null>("get_draft", { workspaceId, threadId });
+}
+
+// Call while a workspace (and optionally one of its threads) is in focus; pings at
+// most 5 minutes apart count as continuous time.
+export async function timeTrackingPing(
+  workspaceId: string,
+  threadId?: string | null,
+): Promise<void> {
+  await invoke("time_tracking_ping", { workspaceId, threadId: threadId ?? null });
+}
+
+export type TimePeriod = "day" | "week";
+
+export type WorkspaceTime = {
+  workspaceId: string;
+  totalMs: number;
+  threads: { threadId: string; totalMs: number }[];
+};
+
+export type TimeSummary = {
+  period: TimePeriod;
+  buckets: { start: string; totalMs: number; workspaces: WorkspaceTime[] }[];
+  totalMs: number;
+};
+
+export async function timeTrackingSummary(
+  period: TimePeriod = "day",
+  count?: number | null,
+  workspaceId?: string | null,
+): Promise<TimeSummary> {
+  return invoke<TimeSummary>("time_tracking_summary", {
+    period,
+    count: count ?? null,
+    workspaceId: workspaceId ?? null,
+  });
+}
+
+export type ContextPin = {
+  id: string;
+  kind: "file" | "snippet";
+  path: string | null;
+  startLine: number | null;
+  endLine: number | null;
+  text: string | null;
+  label: string | null;
+  createdAt: number;
+};
+
+export async function listPinnedContext(
+  workspaceId: string,
+  threadId: string,
+  model?: string | null,
+): Promise<{ pins: ContextPin[]; plan: ContextPlan }> {
+  return invoke("list_pinned_context", { workspaceId, threadId, model: model ?? null });
+}
+
+export async function pinContext(
+  workspaceId: string,
+  threadId: string,
+  pin:
+    | { path: string; startLine?: number; endLine?: number; label?: string }
+    | { text: string; label?: string },
+): Promise<ContextPin> {
+  return invoke<ContextPin>("pin_context", { workspaceId, threadId, ...pin });
+}
+
+export async function unpinContext(
+  workspaceId: string,
+  threadId: string,
+  pinId?: string | null,
+): Promise<number> {
+  return invoke<number>("unpin_context", { workspaceId, threadId, pinId: pinId ?? null });
+}
+
+export type CollaborationPreset = {
+  name: string;
+  description: string | null;
+  collaborationMode: Record<string, unknown>;
+  updatedAt: number;
+};
+
+export async function listCollaborationPresets(): Promise<CollaborationPreset[]> {
+  return invoke<CollaborationPreset[]>("list_collaboration_presets");
+}
+
+export async function saveCollaborationPreset(
+  name: string,
+  collaborationMode: Record<string, unknown>,
+  description?: string | null,
+): Promise<CollaborationPreset> {
+  return invoke<CollaborationPreset>("save_collaboration_preset", {
+    name,
+    description: description ?? null,
+    collaborationMode,
+  });
+}
+
+export type AccountProfile = {
+  name: string;
+  codexHome: string;
+  updatedAt: number;
+};
+
+export type AccountProfileInfo = AccountProfile & {
+  resolvedHome: string | null;
+  signedIn: boolean;
+  email: string | null;
+  planType: string | null;
+  workspaceIds: string[];
+};
+
+export async function listAccountProfiles(): Promise<AccountProfileInfo[]> {
+  return invoke<AccountProfileInfo[]>("list_account_profiles");
+}
+
+export async function saveAccountProfile(
+  name: string,
+  codexHome: string,
+): Promise<AccountProfile> {
+  return invoke<AccountProfile>("save_account_profile", { name, codexHome });
+}
+
+export async function deleteAccountProfile(
+  name: string,
+): Promise<{ deleted: boolean }> {
+  return invoke<{ deleted: boolean }>("delete_account_profile", { name });
+}
+
+export async function deleteCollaborationPreset(
+  name: string,
+): Promise<{ deleted: boolean }> {
+  return invoke<{ deleted: boolean }>("delete_collaboration_preset", { name });
+}
+
 export async function compactThread(workspaceId: string, threadId: string) {
   return invoke<any>("compact_thread", { workspaceId, threadId });
 }
@@ -262,8 +663,12 @@ export async function sendUserMessage(
     model?: string | null;
     effort?: string | null;
     accessMode?: "read-only" | "current" | "full-access";
+    sandboxPreset?: string | null;
+    cwd?: string | null;
     images?: string[];
+    documents?: string[];
     collaborationMode?: Record<string, unknown> | null;
+    collaborationModePreset?: string | null;
   },
 ) {
   const payload: Record<string, unknown> = {
@@ -274,10 +679,20 @@ export async function sendUserMessage(
     effort: options?.effort ?? null,
     accessMode: options?.accessMode ?? null,
     images: options?.images ?? null,
+    documents: options?.documents ?? null,
   };
   if (options?.collaborationMode) {
     payload.collaborationMode = options.collaborationMode;
   }
+  if (options?.collaborationModePreset) {
+    payload.collaborationModePreset = options.collaborationModePreset;
+  }
+  if (options?.sandboxPreset) {
+    payload.sandboxPreset = options.sandboxPreset;
+  }
+  if (options?.cwd) {
+    payload.cwd = options.cwd;
+  }
   return invoke("send_user_message", payload);
 }
 
@@ -289,6 +704,21 @@ export async function interruptTurn(
   return invoke("turn_interrupt", { workspaceId, threadId, turnId });
 }
 
+export type SnapshotRestore = {
+  turnId: string;
+  workspaceId: string;
+  restoredFiles: string[];
+  removedFiles: string[];
+  headChanged: boolean;
+  replacedCommit: string;
+};
+
+export async function restoreSnapshot(
+  turnId: string,
+): Promise<SnapshotRestore> {
+  return invoke<SnapshotRestore>("restore_snapshot", { turnId });
+}
+
 export async function startReview(
   workspaceId: string,
   threadId: string,
@@ -333,6 +763,30 @@ export async function rememberApprovalRule(
   return invoke("remember_approval_rule", { workspaceId, command });
 }
 
+export type RulesDiagnostic = {
+  line: number;
+  column: number;
+  severity: "error" | "warning";
+  message: string;
+};
+
+export async function validateRules(workspaceId: string): Promise<{
+  path: string;
+  exists: boolean;
+  valid: boolean;
+  ruleCount: number;
+  diagnostics: RulesDiagnostic[];
+}> {
+  return invoke("validate_rules", { workspaceId });
+}
+
+export async function formatRules(
+  workspaceId: string,
+  write = false,
+): Promise<{ path: string; changed: boolean; written: boolean; contents: string }> {
+  return invoke("format_rules", { workspaceId, write });
+}
+
 export async function getGitStatus(workspace_id: string): Promise<{
   branchName: string;
   files: GitFileStatus[];
@@ -418,6 +872,24 @@ export async function syncGit(workspaceId: string): Promise<void> {
   return invoke("sync_git", { workspaceId });
 }
 
+export type CiState = "pending" | "success" | "failure" | "none";
+
+export type CiStatus = {
+  workspaceId: string;
+  branch: string;
+  repo: string;
+  sha: string | null;
+  state: CiState;
+  checks: { name: string; state: CiState; url: string | null }[];
+};
+
+export async function ciStatus(
+  workspaceId: string,
+  branch: string,
+): Promise<CiStatus> {
+  return invoke<CiStatus>("ci_status", { workspaceId, branch });
+}
+
 export async function getGitHubIssues(
   workspace_id: string,
 ): Promise<GitHubIssuesResponse> {
@@ -501,12 +973,57 @@ export async function getSkillsList(workspaceId: string) {
   return invoke<any>("skills_list", { workspaceId });
 }
 
+export type SkillConfigScope = "global" | "workspace";
+
+export type SkillConfigEntry = {
+  path: string;
+  enabled: boolean;
+};
+
+export type SkillsConfigResponse = {
+  scope: SkillConfigScope;
+  configPath: string;
+  entries: SkillConfigEntry[];
+  skills?: any;
+};
+
+export async function getSkillsConfig(
+  workspaceId: string,
+  scope?: SkillConfigScope | null,
+) {
+  return invoke<SkillsConfigResponse>("skills_config", {
+    workspaceId,
+    scope: scope ?? null,
+  });
+}
+
+export async function setSkillEnabled(
+  workspaceId: string,
+  path: string,
+  enabled: boolean,
+  scope?: SkillConfigScope | null,
+) {
+  return invoke<SkillsConfigResponse>("skills_set_enabled", {
+    workspaceId,
+    path,
+    enabled,
+    scope: scope ?? null,
+  });
+}
+
 export async function getAppsList(
   workspaceId: string,
   cursor?: string | null,
   limit?: number | null,
+  options?: ListPageOptions | null,
 ) {
-  return invoke<any>("apps_list", { workspaceId, cursor, limit });
+  return invoke<any>("apps_list", {
+    workspaceId,
+    cursor,
+    limit,
+    aggregate: options?.aggregate ?? null,
+    refresh: options?.refresh ?? null,
+  });
 }
 
 export async function getPromptsList(workspaceId: string) {
@@ -580,8 +1097,79 @@ export async function getAppSettings(): Promise<AppSettings> {
   return invoke<AppSettings>("get_app_settings");
 }
 
-export async function updateAppSettings(settings: AppSettings): Promise<AppSettings> {
-  return invoke<AppSettings>("update_app_settings", { settings });
+export async function updateAppSettings(
+  settings: AppSettings,
+  expectedRevision?: number,
+): Promise<AppSettings> {
+  return invoke<AppSettings>("update_app_settings", {
+    settings,
+    expectedRevision: expectedRevision ?? null,
+  });
+}
+
+export type MergeResult<T> = {
+  merged: T;
+  conflicts: string[];
+};
+
+export async function mergeRevisions<T>(
+  base: T,
+  current: T,
+  proposed: T,
+): Promise<MergeResult<T>> {
+  return invoke<MergeResult<T>>("merge_revisions", { base, current, proposed });
+}
+
+export type BackupResult = {
+  path: string;
+  createdAt: string;
+  entries: string[];
+};
+
+export type RestoreResult = {
+  restored: string[];
+  safetyBackup: string | null;
+};
+
+export async function backupData(destination: string): Promise<BackupResult> {
+  return invoke<BackupResult>("backup_data", { destination });
+}
+
+export async function restoreData(
+  source: string,
+  confirm: boolean,
+): Promise<RestoreResult> {
+  return invoke<RestoreResult>("restore_data", { source, confirm });
+}
+
+export type SafetyPolicyBundle = {
+  version: number;
+  exportedAt?: string | null;
+  approvalPolicyRules?: ApprovalPolicyRule[] | null;
+  sandboxPresets?: SandboxPreset[] | null;
+  protectedPathGlobs?: string[] | null;
+  rules?: string | null;
+};
+
+export type SafetyPolicyImportMode = "merge" | "replace";
+
+export type SafetyPolicyImportResult = {
+  approvalPolicyRules: number;
+  sandboxPresets: number;
+  protectedPathGlobs: number;
+  rulesFileUpdated: boolean;
+  revision: number;
+};
+
+export async function exportSafetyPolicy(): Promise<SafetyPolicyBundle> {
+  return invoke<SafetyPolicyBundle>("export_safety_policy");
+}
+
+export async function importSafetyPolicy(
+  bundle: SafetyPolicyBundle,
+  mode: SafetyPolicyImportMode = "merge",
+): Promise<SafetyPolicyImportResult> {
+  return invoke<SafetyPolicyImportResult>("import_safety_policy", { bundle, mode });
 }
 
 type MenuAcceleratorUpdate = {
@@ -602,6 +1190,22 @@ export async function runCodexDoctor(
   return invoke<CodexDoctorResult>("codex_doctor", { codexBin, codexArgs });
 }
 
+export type SessionLogTail = {
+  workspaceId: string;
+  path: string;
+  lines: string[];
+};
+
+export async function getSessionLog(
+  workspaceId: string,
+  tail?: number,
+): Promise<SessionLogTail> {
+  return invoke<SessionLogTail>("get_session_log", {
+    workspaceId,
+    tail: tail ?? null,
+  });
+}
+
 export async function getWorkspaceFiles(workspaceId: string) {
   return invoke<string[]>("list_workspace_files", { workspaceId });
 }
@@ -616,6 +1220,697 @@ export async function readWorkspaceFile(
   });
 }
 
+export type Capabilities = {
+  files: {
+    maxReadBytes: number;
+    maxWriteBytes: number;
+    maxChunkBytes: number;
+    streamingWrites: boolean;
+  };
+};
+
+export async function getCapabilities(): Promise<Capabilities> {
+  return invoke<Capabilities>("get_capabilities");
+}
+
+export type WriteSession = {
+  writeId: string;
+  path: string;
+  maxChunkBytes: number;
+  maxWriteBytes: number;
+};
+
+export type CommittedWrite = {
+  workspaceId: string;
+  path: string;
+  bytes: number;
+  sha256: string;
+};
+
+export async function beginWrite(
+  workspaceId: string,
+  path: string,
+  size?: number | null,
+): Promise<WriteSession> {
+  return invoke<WriteSession>("begin_write", { workspaceId, path, size: size ?? null });
+}
+
+export async function writeChunk(
+  writeId: string,
+  offset: number,
+  data: string,
+): Promise<{ writeId: string; written: number }> {
+  return invoke<{ writeId: string; written: number }>("write_chunk", {
+    writeId,
+    offset,
+    data,
+  });
+}
+
+export async function commitWrite(
+  writeId: string,
+  sha256?: string | null,
+): Promise<CommittedWrite> {
+  return invoke<CommittedWrite>("commit_write", { writeId, sha256: sha256 ?? null });
+}
+
+export async function abortWrite(writeId: string): Promise<void> {
+  return invoke("abort_write", { writeId });
+}
+
+function bytesToBase64(bytes: Uint8Array): string {
+  let binary = "";
+  for (let index = 0; index < bytes.length; index += 0x8000) {
+    binary += String.fromCharCode(...bytes.subarray(index, index + 0x8000));
+  }
+  return btoa(binary);
+}
+
+async function sha256Hex(bytes: Uint8Array): Promise<string> {
+  const digest = await crypto.subtle.digest("SHA-256", bytes);
+  return Array.from(new Uint8Array(digest), (byte) =>
+    byte.toString(16).padStart(2, "0"),
+  ).join("");
+}
+
+/** Writes a workspace file of any size through begin_write/write_chunk/commit_write. */
+export async function writeWorkspaceFile(
+  workspaceId: string,
+  path: string,
+  content: Uint8Array,
+  onProgress?: (written: number, total: number) => void,
+): Promise<CommittedWrite> {
+  const session = await beginWrite(workspaceId, path, content.length);
+  try {
+    for (let offset = 0; offset < content.length; offset += session.maxChunkBytes) {
+      const chunk = content.subarray(offset, offset + session.maxChunkBytes);
+      const { written } = await writeChunk(session.writeId, offset, bytesToBase64(chunk));
+      onProgress?.(written, content.length);
+    }
+    return await commitWrite(session.writeId, await sha256Hex(content));
+  } catch (error) {
+    await abortWrite(session.writeId).catch(() => {});
+    throw error;
+  }
+}
+
+export type ResolvedMention = {
+  raw: string;
+  kind: "file" | "symbol" | "snippet" | "unresolved";
+  path: string | null;
+  startLine: number | null;
+  endLine: number | null;
+  snippet: string | null;
+  truncated: boolean;
+};
+
+export type MentionResolution = {
+  mentions: ResolvedMention[];
+  contextBlock: string;
+};
+
+export async function resolveMentions(
+  workspaceId: string,
+  text: string,
+): Promise<MentionResolution> {
+  return invoke<MentionResolution>("resolve_mentions", { workspaceId, text });
+}
+
+export type PlannedAttachment = {
+  raw: string;
+  kind: "file" | "symbol" | "snippet" | "unresolved";
+  path: string | null;
+  startLine: number | null;
+  endLine: number | null;
+  estimatedTokens: number;
+  includedTokens: number;
+  status: "included" | "trimmed" | "excluded" | "unresolved";
+};
+
+export type ContextPlan = {
+  model: string | null;
+  contextWindow: number;
+  budgetTokens: number;
+  messageTokens: number;
+  usedTokens: number;
+  attachments: PlannedAttachment[];
+  contextBlock: string;
+};
+
+export async function planContext(
+  workspaceId: string,
+  text: string,
+  model?: string | null,
+  budgetTokens?: number | null,
+): Promise<ContextPlan> {
+  return invoke<ContextPlan>("plan_context", {
+    workspaceId,
+    text,
+    model: model ?? null,
+    budgetTokens: budgetTokens ?? null,
+  });
+}
+
+export type IndexedSearchMatch = {
+  path: string;
+  line: number;
+  preview: string;
+  ranges: [number, number][];
+};
+
+export type IndexedSearchResponse = {
+  status: "building" | "ready";
+  refreshing: boolean;
+  indexedFiles: number;
+  builtAt: number | null;
+  elapsedMs: number;
+  results: IndexedSearchMatch[];
+};
+
+/** Without options every word must occur in a file, in any case. */
+export type SearchOptions = {
+  matchCase?: boolean;
+  wholeWord?: boolean;
+  regex?: boolean;
+};
+
+export async function indexedSearch(
+  workspaceId: string,
+  query: string,
+  limit?: number,
+  pathPrefix?: string,
+  options?: SearchOptions,
+): Promise<IndexedSearchResponse> {
+  return invoke<IndexedSearchResponse>("indexed_search", {
+    workspaceId,
+    query,
+    limit: limit ?? null,
+    pathPrefix: pathPrefix ?? null,
+    options: options ?? null,
+  });
+}
+
+export type WorkspaceSymbol = {
+  name: string;
+  kind: SymbolKind;
+  language: string;
+  path: string;
+  line: number;
+  column: number;
+  container: string | null;
+};
+
+export type WorkspaceSymbolsResponse = {
+  symbols: WorkspaceSymbol[];
+  indexedFiles: number;
+};
+
+export async function workspaceSymbols(
+  workspaceId: string,
+  query: string,
+  limit?: number,
+): Promise<WorkspaceSymbolsResponse> {
+  return invoke<WorkspaceSymbolsResponse>("workspace_symbols", {
+    workspaceId,
+    query,
+    limit: limit ?? null,
+  });
+}
+
+export type DefinitionLookup = {
+  symbol: string | null;
+  definitions: WorkspaceSymbol[];
+};
+
+export async function findDefinition(
+  workspaceId: string,
+  path: string,
+  line: number,
+  column: number,
+): Promise<DefinitionLookup> {
+  return invoke<DefinitionLookup>("find_definition", {
+    workspaceId,
+    path,
+    line,
+    column,
+  });
+}
+
+export type WorkspaceSearchMatch = IndexedSearchMatch & {
+  workspaceId: string;
+  workspaceName: string;
+};
+
+export type MultiWorkspaceSearchResponse = {
+  results: WorkspaceSearchMatch[];
+  building: string[];
+  errors: { workspaceId: string; error: string }[];
+  elapsedMs: number;
+};
+
+export async function searchAllWorkspaces(
+  query: string,
+  workspaceIds?: string[],
+  limit?: number,
+  options?: SearchOptions,
+): Promise<MultiWorkspaceSearchResponse> {
+  return invoke<MultiWorkspaceSearchResponse>("search_all_workspaces", {
+    query,
+    workspaceIds: workspaceIds ?? null,
+    limit: limit ?? null,
+    options: options ?? null,
+  });
+}
+
+export type WorkspaceStats = {
+  workspaceId: string;
+  totalFiles: number;
+  totalBytes: number;
+  truncated: boolean;
+  languages: { language: string; files: number; bytes: number }[];
+  commits: {
+    last7Days: number;
+    last30Days: number;
+    weekly: number[];
+    lastCommitAt: number | null;
+  };
+  activeThreads: number;
+  threadsStarted: number;
+  threadsStartedLast7Days: number;
+  tokensLast7Days: number;
+  tokensLast30Days: number;
+};
+
+export type SparseCheckout = {
+  enabled: boolean;
+  cone: boolean;
+  patterns: string[];
+};
+
+export async function getSparseCheckout(
+  workspaceId: string,
+): Promise<SparseCheckout> {
+  return invoke<SparseCheckout>("get_sparse_checkout", { workspaceId });
+}
+
+/** `patterns: null` turns sparse checkout off. */
+export async function setSparseCheckout(
+  workspaceId: string,
+  patterns: string[] | null,
+): Promise<SparseCheckout> {
+  return invoke<SparseCheckout>("set_sparse_checkout", { workspaceId, patterns });
+}
+
+export async function workspaceStats(id: string): Promise<WorkspaceStats> {
+  return invoke<WorkspaceStats>("workspace_stats", { id });
+}
+
+export type DiskUsageWarning = {
+  scope: "workspace" | "worktree" | "dataDir";
+  workspaceId: string | null;
+  path: string;
+  bytes: number;
+  thresholdBytes: number;
+};
+
+export type DiskUsageReport = {
+  dataDir: string;
+  dataDirBytes: number;
+  workspaces: {
+    workspaceId: string;
+    parentId: string | null;
+    path: string;
+    bytes: number;
+    worktreeBytes: number;
+  }[];
+  warnings: DiskUsageWarning[];
+  measuredAt: number;
+};
+
+export async function diskUsage(
+  workspaceId?: string,
+  refresh = false,
+): Promise<DiskUsageReport> {
+  return invoke<DiskUsageReport>("disk_usage", {
+    workspaceId: workspaceId ?? null,
+    refresh,
+  });
+}
+
+export type WorkspaceTaskSuggestion = {
+  kind: "build" | "test" | "lint" | "run";
+  command: string;
+  cwd: string;
+  source: string;
+};
+
+export type WorkspaceAnalysis = {
+  workspaceId: string;
+  name: string;
+  languages: { language: string; files: number; bytes: number }[];
+  buildSystems: { name: string; manifest: string }[];
+  packages: WorkspacePackage[];
+  entryPoints: { path: string; kind: "binary" | "library" | "script" | "web" }[];
+  tasks: WorkspaceTaskSuggestion[];
+  ci: string[];
+  readme: { path: string; title: string | null; summary: string } | null;
+  prompt: string;
+};
+
+export async function analyzeWorkspace(id: string): Promise<WorkspaceAnalysis> {
+  return invoke<WorkspaceAnalysis>("analyze_workspace", { id });
+}
+
+export type WorkspacePackage = {
+  name: string;
+  ecosystem: "cargo" | "npm" | "pnpm" | "go";
+  path: string;
+  manifest: string;
+  workspaceRoot: boolean;
+};
+
+export async function listPackages(
+  workspaceId: string,
+): Promise<WorkspacePackage[]> {
+  return invoke<WorkspacePackage[]>("list_packages", { workspaceId });
+}
+
+export type AuditSeverity = "unknown" | "low" | "moderate" | "high" | "critical";
+
+export type AuditFinding = {
+  ecosystem: "cargo" | "npm" | "pnpm" | "python";
+  path: string;
+  package: string;
+  version: string | null;
+  advisoryId: string;
+  title: string | null;
+  severity: AuditSeverity;
+  url: string | null;
+  fixedIn: string[];
+};
+
+export type DependencyAudit = {
+  workspaceId: string;
+  runs: {
+    ecosystem: AuditFinding["ecosystem"];
+    path: string;
+    tool: string;
+    error: string | null;
+  }[];
+  findings: AuditFinding[];
+};
+
+export async function auditDependencies(
+  workspaceId: string,
+): Promise<DependencyAudit> {
+  return invoke<DependencyAudit>("audit_dependencies", { workspaceId });
+}
+
+export type ProblemSeverity = "error" | "warning" | "info";
+
+export type Problem = {
+  source: string;
+  path: string;
+  line: number;
+  column: number | null;
+  severity: ProblemSeverity;
+  code: string | null;
+  message: string;
+};
+
+export type ProblemsView = {
+  workspaceId: string;
+  errorCount: number;
+  warningCount: number;
+  problems: Problem[];
+};
+
+export type BuildRun = {
+  tool: "cargo" | "tsc" | "go";
+  command: string;
+  cwd: string;
+  exitCode: number | null;
+  success: boolean;
+  durationMs: number;
+  errorCount: number;
+  warningCount: number;
+  error: string | null;
+  outputTail: string | null;
+};
+
+export type BuildReport = {
+  workspaceId: string;
+  success: boolean;
+  runs: BuildRun[];
+  problems: Problem[];
+};
+
+export async function runBuild(workspaceId: string): Promise<BuildReport> {
+  return invoke<BuildReport>("run_build", { workspaceId });
+}
+
+export async function listProblems(workspaceId: string): Promise<ProblemsView> {
+  return invoke<ProblemsView>("list_problems", { workspaceId });
+}
+
+export type RemoteClient = {
+  id: string;
+  deviceName: string | null;
+  clientVersion: string | null;
+  remoteAddr: string;
+  authenticated: boolean;
+  connectedAt: number;
+  lastActivityAt: number;
+};
+
+export async function listClients(): Promise<RemoteClient[]> {
+  return invoke<RemoteClient[]>("list_clients");
+}
+
+export async function kickClient(clientId: string): Promise<RemoteClient> {
+  return invoke<RemoteClient>("kick_client", { clientId });
+}
+
+export type JobKind =
+  | "index_build"
+  | "backup_data"
+  | "audit_dependencies"
+  | "run_build"
+  | "generate_commit_message"
+  | "auto_fix";
+
+export type Job = {
+  id: string;
+  kind: JobKind;
+  workspaceId: string | null;
+  state: "running" | "succeeded" | "failed" | "cancelled";
+  progress: number | null;
+  message: string | null;
+  result: unknown;
+  error: string | null;
+  startedAt: number;
+  finishedAt: number | null;
+};
+
+export async function jobStart(
+  kind: JobKind,
+  params: Record<string, unknown>,
+): Promise<Job> {
+  return invoke<Job>("job_start", { kind, params });
+}
+
+export type AutoFixOptions = {
+  goal?: string | null;
+  testCommand?: string[] | null;
+  maxIterations?: number | null;
+  maxTokens?: number | null;
+};
+
+export type AutoFixReport = {
+  workspaceId: string;
+  threadId: string | null;
+  outcome: "fixed" | "maxIterations" | "budgetExhausted";
+  iterations: {
+    iteration: number;
+    errorCount: number;
+    testsFailed: boolean;
+    turnId: string | null;
+    tokensUsed: number;
+  }[];
+  tokensUsed: number;
+  build: BuildReport;
+  tests: {
+    command: string;
+    exitCode: number | null;
+    success: boolean;
+    durationMs: number;
+    error: string | null;
+    outputTail: string | null;
+  } | null;
+};
+
+// Runs as an `auto_fix` job; its `result` is an `AutoFixReport`. Cancel with `jobCancel`.
+export async function autoFixStart(
+  workspaceId: string,
+  options: AutoFixOptions = {},
+): Promise<Job> {
+  return invoke<Job>("auto_fix_start", {
+    workspaceId,
+    goal: options.goal ?? null,
+    testCommand: options.testCommand ?? null,
+    maxIterations: options.maxIterations ?? null,
+    maxTokens: options.maxTokens ?? null,
+  });
+}
+
+export async function jobStatus(id: string): Promise<Job> {
+  return invoke<Job>("job_status", { id });
+}
+
+export async function listJobs(): Promise<Job[]> {
+  return invoke<Job[]>("job_status", { id: null });
+}
+
+export async function jobCancel(id: string): Promise<Job> {
+  return invoke<Job>("job_cancel", { id });
+}
+
+export type FanoutBranch = {
+  branch: string;
+  workspaceId: string | null;
+  threadId: string | null;
+  turnId: string | null;
+  status: "starting" | "running" | "completed" | "failed";
+  error: string | null;
+  tokensUsed: number;
+  summary: string | null;
+  finishedAt: number | null;
+};
+
+export type FanoutRun = {
+  id: string;
+  parentId: string;
+  prompt: string;
+  baseCommit: string | null;
+  createdAt: number;
+  finishedAt: number | null;
+  picked: string | null;
+  branches: FanoutBranch[];
+};
+
+export async function fanoutRun(
+  parentId: string,
+  branches: string[],
+  prompt: string,
+): Promise<FanoutRun> {
+  return invoke<FanoutRun>("fanout_run", { parentId, branches, prompt });
+}
+
+export type RetentionReport = {
+  dryRun: boolean;
+  policy: RetentionPolicy;
+  archived: {
+    workspaceId: string;
+    threadId: string;
+    idleDays: number;
+    error: string | null;
+  }[];
+  purged: {
+    path: string;
+    bytes: number;
+    ageDays: number;
+    error: string | null;
+  }[];
+  bytesFreed: number;
+  errors: string[];
+};
+
+// Without `dryRun: false` this only reports what the `retention` setting would do.
+export async function runRetention(dryRun = true): Promise<RetentionReport> {
+  return invoke<RetentionReport>("run_retention", { dryRun });
+}
+
+export async function fanoutStatus(runId: string): Promise<FanoutRun> {
+  return invoke<FanoutRun>("fanout_status", { runId });
+}
+
+export async function listFanoutRuns(): Promise<FanoutRun[]> {
+  return invoke<FanoutRun[]>("fanout_status", { runId: null });
+}
+
+export type WorktreeDiff = {
+  base: string;
+  files: {
+    path: string;
+    additions: number | null;
+    deletions: number | null;
+    untracked: boolean;
+  }[];
+  additions: number;
+  deletions: number;
+  patch: string;
+  truncated: boolean;
+};
+
+export type FanoutComparison = {
+  runId: string;
+  parentId: string;
+  baseCommit: string | null;
+  picked: string | null;
+  branches: {
+    branch: string;
+    workspaceId: string | null;
+    status: FanoutBranch["status"];
+    tokensUsed: number;
+    summary: string | null;
+    diff: WorktreeDiff | null;
+    diffError: string | null;
+    build: BuildReport | null;
+    buildError: string | null;
+    errorCount: number;
+    warningCount: number;
+  }[];
+};
+
+export async function compareFanout(
+  runId: string,
+  runChecks = false,
+): Promise<FanoutComparison> {
+  return invoke<FanoutComparison>("compare_fanout", { runId, runChecks });
+}
+
+export type FanoutPickResult = {
+  runId: string;
+  branch: string;
+  appliedFiles: number;
+  removedWorktrees: string[];
+  deletedBranches: string[];
+  errors: string[];
+};
+
+export async function fanoutPick(
+  runId: string,
+  branch: string,
+): Promise<FanoutPickResult> {
+  return invoke<FanoutPickResult>("fanout_pick", { runId, branch });
+}
+
+export type PendingApproval = {
+  workspaceId: string;
+  requestId: number | string;
+  method: string;
+  message: Record<string, unknown>;
+  receivedAt: number;
+};
+
+export async function listPendingApprovals(
+  workspaceId?: string | null,
+): Promise<PendingApproval[]> {
+  return invoke<PendingApproval[]>("list_pending_approvals", {
+    workspaceId: workspaceId ?? null,
+  });
+}
+
 export async function readAgentMd(workspaceId: string): Promise<AgentMdResponse> {
   return fileRead("workspace", "agents", workspaceId);
 }
@@ -624,6 +1919,163 @@ export async function writeAgentMd(workspaceId: string, content: string): Promis
   return fileWrite("workspace", "agents", content, workspaceId);
 }
 
+export async function readAgentOverrideMd(workspaceId: string): Promise<TextFileResponse> {
+  return fileRead("workspace", "agents_override", workspaceId);
+}
+
+export async function writeAgentOverrideMd(
+  workspaceId: string,
+  content: string,
+): Promise<void> {
+  return fileWrite("workspace", "agents_override", content, workspaceId);
+}
+
+export async function deleteAgentFile(
+  workspaceId: string,
+  kind: "agents" | "agents_override",
+): Promise<boolean> {
+  return invoke<boolean>("file_delete", { scope: "workspace", kind, workspaceId });
+}
+
+export type InstructionSource = {
+  scope: FileScope;
+  path: string;
+  bytes: number;
+  truncated: boolean;
+  shadows: string[];
+};
+
+export type EffectiveInstructions = {
+  codexHome: string | null;
+  projectRoot: string;
+  maxBytes: number;
+  sources: InstructionSource[];
+  content: string;
+};
+
+export async function getEffectiveInstructions(
+  workspaceId: string,
+): Promise<EffectiveInstructions> {
+  return invoke<EffectiveInstructions>("effective_instructions", { workspaceId });
+}
+
+export type DocumentChunk = {
+  page: number;
+  text: string;
+};
+
+export type ExtractedDocument = {
+  path: string;
+  kind: "pdf" | "docx";
+  pages: number;
+  chars: number;
+  truncated: boolean;
+  chunks: DocumentChunk[];
+};
+
+export async function extractDocument(
+  workspaceId: string,
+  path: string,
+): Promise<ExtractedDocument> {
+  return invoke<ExtractedDocument>("extract_document", { workspaceId, path });
+}
+
+export type RenderedMarkdown = {
+  path: string;
+  html: string;
+  title: string | null;
+  links: string[];
+};
+
+export async function renderMarkdown(
+  workspaceId: string,
+  path: string,
+): Promise<RenderedMarkdown> {
+  return invoke<RenderedMarkdown>("render_markdown", { workspaceId, path });
+}
+
+export type NotebookOutput = {
+  outputType: "stream" | "execute_result" | "display_data" | "error";
+  name: string | null;
+  text: string | null;
+  image: string | null;
+};
+
+export type NotebookCell = {
+  cellType: "markdown" | "code" | "raw";
+  source: string;
+  executionCount: number | null;
+  html: string | null;
+  outputs: NotebookOutput[];
+};
+
+export type Notebook = {
+  path: string;
+  language: string | null;
+  cells: NotebookCell[];
+};
+
+export async function renderNotebook(
+  workspaceId: string,
+  path: string,
+): Promise<Notebook> {
+  return invoke<Notebook>("render_notebook", { workspaceId, path });
+}
+
+export type SymbolKind =
+  | "module"
+  | "class"
+  | "struct"
+  | "enum"
+  | "interface"
+  | "trait"
+  | "impl"
+  | "function"
+  | "method"
+  | "constant"
+  | "type";
+
+export type OutlineSymbol = {
+  name: string;
+  kind: SymbolKind;
+  startLine: number;
+  endLine: number;
+  nameLine: number;
+  nameColumn: number;
+  children: OutlineSymbol[];
+};
+
+export type FileOutline = {
+  path: string;
+  language: string | null;
+  symbols: OutlineSymbol[];
+};
+
+export async function fileOutline(
+  workspaceId: string,
+  path: string,
+): Promise<FileOutline> {
+  return invoke<FileOutline>("file_outline", { workspaceId, path });
+}
+
+export type Thumbnail = {
+  path: string;
+  mime: string;
+  width: number;
+  height: number;
+  originalWidth: number;
+  originalHeight: number;
+  originalBytes: number;
+  dataUrl: string;
+};
+
+export async function getThumbnail(
+  workspaceId: string,
+  path: string,
+): Promise<Thumbnail> {
+  return invoke<Thumbnail>("get_thumbnail", { workspaceId, path });
+}
+
 export async function listGitBranches(workspaceId: string) {
   return invoke<any>("list_git_branches", { workspaceId });
 }
@@ -727,21 +2179,85 @@ export async function closeTerminalSession(
   return invoke("terminal_close", { workspaceId, terminalId });
 }
 
+// `aggregate` walks every page and returns them as one list; `refresh` bypasses the
+// remote daemon's short-lived list cache.
+export type ListPageOptions = {
+  aggregate?: boolean;
+  refresh?: boolean;
+};
+
 export async function listThreads(
   workspaceId: string,
   cursor?: string | null,
   limit?: number | null,
   sortKey?: "created_at" | "updated_at" | null,
+  branch?: string | null,
+  options?: ListPageOptions | null,
 ) {
-  return invoke<any>("list_threads", { workspaceId, cursor, limit, sortKey });
+  return invoke<any>("list_threads", {
+    workspaceId,
+    cursor,
+    limit,
+    sortKey,
+    branch,
+    aggregate: options?.aggregate ?? null,
+    refresh: options?.refresh ?? null,
+  });
 }
 
 export async function listMcpServerStatus(
   workspaceId: string,
   cursor?: string | null,
   limit?: number | null,
+  options?: ListPageOptions | null,
 ) {
-  return invoke<any>("list_mcp_server_status", { workspaceId, cursor, limit });
+  return invoke<any>("list_mcp_server_status", {
+    workspaceId,
+    cursor,
+    limit,
+    aggregate: options?.aggregate ?? null,
+    refresh: options?.refresh ?? null,
+  });
+}
+
+export type McpProbe = {
+  at: number;
+  ok: boolean;
+  latencyMs: number | null;
+  toolCount: number | null;
+  error: string | null;
+};
+
+export type McpServerHealth = {
+  workspaceId: string;
+  server: string;
+  healthy: boolean;
+  consecutiveFailures: number;
+  lastLatencyMs: number | null;
+  avgLatencyMs: number | null;
+  history: McpProbe[];
+  authStatus: string | null;
+  authExpiresAt: number | null;
+};
+
+export type McpAuthExpiring = {
+  workspaceId: string;
+  server: string;
+  authStatus: string | null;
+  expiresAt: number | null;
+  expired: boolean;
+};
+
+export async function mcpServerOauthLogin(workspaceId: string, name: string) {
+  return invoke<any>("mcp_server_oauth_login", { workspaceId, name });
+}
+
+export async function mcpServerHealth(
+  workspaceId?: string | null,
+): Promise<McpServerHealth[]> {
+  return invoke<McpServerHealth[]>("mcp_server_health", {
+    workspaceId: workspaceId ?? null,
+  });
 }
 
 export async function resumeThread(workspaceId: string, threadId: string) {
@@ -768,8 +2284,12 @@ export async function getCommitMessagePrompt(
 
 export async function generateCommitMessage(
   workspaceId: string,
+  staged?: boolean,
 ): Promise<string> {
-  return invoke("generate_commit_message", { workspaceId });
+  return invoke("generate_commit_message", {
+    workspaceId,
+    staged: staged ?? null,
+  });
 }
 
 export async function sendNotification(
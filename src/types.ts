@@ -4,10 +4,29 @@ export type WorkspaceSettings = {
   groupId?: string | null;
   gitRoot?: string | null;
   codexHome?: string | null;
+  accountProfile?: string | null;
   codexArgs?: string | null;
+  codexLaunch?: CodexLaunch | null;
   launchScript?: string | null;
   launchScripts?: LaunchScriptEntry[] | null;
   worktreeSetupScript?: string | null;
+  /** Start point for new worktree branches, e.g. `origin/main`. */
+  worktreeBaseRef?: string | null;
+  worktreeFetchBase?: boolean;
+  defaultModel?: string | null;
+  defaultEffort?: string | null;
+  defaultAccessMode?: AccessMode | null;
+  defaultCollaborationPreset?: string | null;
+  protected?: boolean;
+  /** Branch globs (`main`, `release/*`) worktree operations refuse without an override. */
+  protectedBranches?: string[];
+  revision?: number;
+};
+
+/** Structured Codex args; values may use `{workspacePath}` and `{branch}`. */
+export type CodexLaunch = {
+  args: string[];
+  env: Record<string, string>;
 };
 
 export type LaunchScriptIconId =
@@ -61,6 +80,8 @@ export type WorkspaceInfo = {
 
 export type AppServerEvent = {
   workspace_id: string;
+  /** Event schema the backend normalized `message` to; absent on legacy events. */
+  schemaVersion?: number;
   message: Record<string, unknown>;
 };
 
@@ -99,10 +120,18 @@ export type ConversationItem =
       changes?: { path: string; kind?: string; diff?: string }[];
     };
 
+export type ThreadUsage = {
+  messageCount: number;
+  lastActivityAt: number | null;
+  totalTokens: number;
+  branches: string[];
+};
+
 export type ThreadSummary = {
   id: string;
   name: string;
   updatedAt: number;
+  usage?: ThreadUsage;
 };
 
 export type ThreadListSortKey = "created_at" | "updated_at";
@@ -117,6 +146,7 @@ export type AccessMode = "read-only" | "current" | "full-access";
 export type BackendMode = "local" | "remote";
 export type ThemePreference = "system" | "light" | "dark" | "dim";
 export type PersonalityPreference = "friendly" | "pragmatic";
+export type BackendLocale = "en" | "tr";
 
 
 export type ComposerEditorPreset = "default" | "helpful" | "smart";
@@ -144,9 +174,12 @@ export type OpenAppTarget = {
 export type AppSettings = {
   codexBin: string | null;
   codexArgs: string | null;
+  codexLaunch?: CodexLaunch | null;
   backendMode: BackendMode;
   remoteBackendHost: string;
   remoteBackendToken: string | null;
+  remoteBackendTls: boolean;
+  remoteBackendTlsCa?: string | null;
   defaultAccessMode: AccessMode;
   reviewDeliveryMode: "inline" | "detached";
   composerModelShortcut: string | null;
@@ -185,6 +218,9 @@ export type AppSettings = {
   unifiedExecEnabled: boolean;
   experimentalAppsEnabled: boolean;
   personality: PersonalityPreference;
+  locale: BackendLocale;
+  traceRpcEnabled: boolean;
+  revision: number;
   dictationEnabled: boolean;
   dictationModelId: string;
   dictationPreferredLanguage: string | null;
@@ -201,6 +237,63 @@ export type AppSettings = {
   workspaceGroups: WorkspaceGroup[];
   openAppTargets: OpenAppTarget[];
   selectedOpenAppId: string;
+  approvalPolicyRules: ApprovalPolicyRule[];
+  sandboxPresets: SandboxPreset[];
+  protectedPathGlobs: string[];
+  snapshotBeforeTurns: boolean;
+  slashCommands: CustomSlashCommand[];
+  pinnedContextBudgetTokens?: number | null;
+  retention: RetentionPolicy;
+  diskQuota: DiskQuota;
+  fileLimits: FileLimits;
+  skipDirs: SkipDirSettings;
+};
+
+export type DiskQuota = {
+  workspaceWarningBytes?: number | null;
+  worktreeWarningBytes?: number | null;
+  dataDirWarningBytes?: number | null;
+};
+
+export type FileLimits = {
+  maxReadBytes?: number | null;
+  maxWriteBytes?: number | null;
+};
+
+/** Unset categories default to skipped. */
+export type SkipDirSettings = {
+  vcs?: boolean;
+  buildArtifacts?: boolean;
+  packageDirs?: boolean;
+  custom?: string[];
+};
+
+export type RetentionPolicy = {
+  archiveIdleDays?: number | null;
+  purgeArchivedDays?: number | null;
+};
+
+export type CustomSlashCommand = {
+  name: string;
+  description?: string | null;
+  prompt: string;
+};
+
+export type SandboxPreset = {
+  id: string;
+  name: string;
+  mode?: "readOnly" | "workspaceWrite" | "dangerFullAccess";
+  writableRoots?: string[];
+  networkAccess?: boolean;
+};
+
+export type ApprovalPolicyRule = {
+  id: string;
+  decision: "allow" | "deny";
+  kind?: "any" | "exec" | "patch";
+  commandPrefix?: string[];
+  pathGlobs?: string[];
+  access?: "any" | "readOnly" | "write";
 };
 
 export type CodexDoctorResult = {
@@ -242,6 +335,13 @@ export type RequestUserInputParams = {
   questions: RequestUserInputQuestion[];
 };
 
+/** One `codex/spawnProgress` event; `error` is set when startup failed in `stage`. */
+export type SpawnProgress = {
+  stage: "resolvingBinary" | "startingProcess" | "initializing" | "ready";
+  elapsedMs: number;
+  error: string | null;
+};
+
 export type RequestUserInputRequest = {
   workspace_id: string;
   request_id: number | string;
@@ -455,6 +555,15 @@ export type ModelOption = {
   supportedReasoningEfforts: { reasoningEffort: string; description: string }[];
   defaultReasoningEffort: string | null;
   isDefault: boolean;
+  capabilities?: ModelCapabilities | null;
+};
+
+export type ModelCapabilities = {
+  vision: boolean;
+  maxContextTokens: number | null;
+  collaborationModes: boolean;
+  pricingTier: "low" | "standard" | "high" | null;
+  source: "cache" | "bundled" | "unknown";
 };
 
 export type CollaborationModeOption = {